@@ -1,6 +1,8 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::outcome::ExitCode;
+
 /// A utility to rename files based on their metadata
 #[derive(Parser, Debug)]
 #[command(name = "nameback")]
@@ -13,30 +15,116 @@ use std::path::PathBuf;
 ))]
 #[command(about = "Renames files based on metadata from exiftool", long_about = None)]
 pub struct Args {
-    /// Directory to scan for files
-    #[arg(value_name = "DIRECTORY")]
-    pub directory: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Directories to scan, or files to analyze (all are renamed as one batch)
+    #[arg(value_name = "PATH")]
+    pub directories: Vec<PathBuf>,
+
+    /// Also analyze the files and directories listed in this file, one per line ("-" reads stdin)
+    #[arg(long = "files-from", value_name = "LIST", conflicts_with = "watch")]
+    pub files_from: Option<PathBuf>,
 
     /// Run in dry-run mode (preview changes without renaming)
     #[arg(short = 'n', long = "dry-run")]
     pub dry_run: bool,
 
+    /// Choose which proposed renames to apply from a searchable list before renaming
+    #[arg(long = "pick")]
+    pub pick: bool,
+
+    /// Review each proposed rename before anything is renamed: accept, skip, edit, or accept all
+    #[arg(short = 'i', long = "interactive", conflicts_with = "pick")]
+    pub interactive: bool,
+
+    /// Keep running and rename new files as they arrive in the directory (one only; Ctrl-C stops)
+    #[arg(short = 'w', long = "watch", conflicts_with_all = ["pick", "interactive"])]
+    pub watch: bool,
+
+    /// With --watch: seconds a new file must go unchanged before it is renamed
+    #[arg(long = "settle", value_name = "SECONDS", default_value_t = 3.0, requires = "watch")]
+    pub settle: f64,
+
     /// Skip hidden files and directories
     #[arg(short = 's', long = "skip-hidden")]
     pub skip_hidden: bool,
 
+    /// How many folder levels to descend (1 = only files directly in the directory)
+    #[arg(long = "max-depth", value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Only analyze files matching this glob, e.g. "**/*.pdf" (repeatable; bare names match at any depth)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files and folders matching this glob, e.g. "node_modules/" (repeatable; trailing / = folders only)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only analyze these file categories, e.g. "images,documents" (image, document, audio, video, email, web, archive, installer, ebook, code, other)
+    #[arg(long = "only", value_name = "CATEGORIES", value_delimiter = ',')]
+    pub only: Vec<nameback_core::FileCategory>,
+
+    /// Leave out these file categories, e.g. "video"
+    #[arg(long = "skip", value_name = "CATEGORIES", value_delimiter = ',', conflicts_with = "only")]
+    pub skip: Vec<nameback_core::FileCategory>,
+
     /// Verbose logging
     #[arg(short = 'v', long = "verbose")]
     pub verbose: bool,
 
+    /// How results are shown: log lines, or a table of old → new names grouped by directory
+    /// (default: table for dry runs, text otherwise)
+    #[arg(long = "format", value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Log output format (json emits one object per event with file/stage spans)
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Also write logs to this file (appended, no colors)
+    #[arg(long = "log-file", value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Write every decision of the run to this JSON file: names proposed, candidates the
+    /// scorer rejected and why, renames and errors
+    #[arg(long = "report", value_name = "PATH", conflicts_with = "watch")]
+    pub report: Option<PathBuf>,
+
+    /// Restore the original names of a previous run (the most recent one if no batch id is given)
+    #[arg(long = "undo", value_name = "BATCH_ID", num_args = 0..=1, default_missing_value = "")]
+    pub undo: Option<String>,
+
     /// Check and install missing dependencies
     #[arg(long = "install-deps")]
     pub install_deps: bool,
 
+    /// Never switch system DNS to public servers while installing dependencies; retry
+    /// failed downloads with backoff instead (HTTP(S)_PROXY is honored either way)
+    #[arg(long = "never-touch-dns")]
+    pub never_touch_dns: bool,
+
     /// Check dependency status without installing
     #[arg(long = "check-deps")]
     pub check_deps: bool,
 
+    /// Write a commented config file with every option (to --config or the default location)
+    #[arg(long = "init-config")]
+    pub init_config: bool,
+
+    /// Don't read or write the analysis cache this run
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// Analyze every file afresh, replacing what the cache holds for them
+    #[arg(long = "refresh-cache", conflicts_with = "no_cache")]
+    pub refresh_cache: bool,
+
+    /// Empty the analysis cache (the one --config or NAMEBACK_CACHE_PATH points to, if set)
+    #[arg(long = "clear-cache")]
+    pub clear_cache: bool,
+
     /// Disable GPS location in filenames (location is included by default)
     #[arg(long = "no-location")]
     pub no_location: bool,
@@ -45,16 +133,272 @@ pub struct Args {
     #[arg(long = "no-timestamp")]
     pub no_timestamp: bool,
 
+    /// strftime pattern for the timestamp in names, e.g. "%Y%m%d" or "%d.%m.%Y" (default: %Y-%m-%d)
+    #[arg(long = "timestamp-format", value_name = "FORMAT")]
+    pub timestamp_format: Option<String>,
+
+    /// Timestamp detail: date (default) or datetime (adds the time of day, e.g. 2024-03-15_143022)
+    #[arg(long = "timestamp-precision", value_name = "PRECISION")]
+    pub timestamp_precision: Option<nameback_core::TimestampPrecision>,
+
+    /// Where the timestamp goes: suffix (default) or prefix (names sort by date)
+    #[arg(long = "timestamp-position", value_name = "POSITION")]
+    pub timestamp_position: Option<nameback_core::TimestampPosition>,
+
+    /// Add the number of attachments to email names, e.g. "_3attachments"
+    #[arg(long = "attachment-count")]
+    pub attachment_count: bool,
+
+    /// Name photos without better names by their rough subject: Receipt, Whiteboard, Screenshot,
+    /// Document Scan, Portrait or Landscape (reads every photo's pixels)
+    #[arg(long = "subject-hints")]
+    pub subject_hints: bool,
+
+    /// Leave archives unopened instead of naming them after their contents
+    /// (README or manifest title, top folder, main file type and newest year)
+    #[arg(long = "no-archive-peek")]
+    pub no_archive_peek: bool,
+
+    /// Name receipts and invoices (PDFs and photos) from their date, vendor and total,
+    /// e.g. 2023-10-15_AcmeCorp_invoice_142.50.pdf
+    #[arg(long = "receipts")]
+    pub receipts: bool,
+
+    /// Tesseract languages OCR reads text in, used together, e.g. "eng+deu+jpn"
+    /// (each needs its language pack installed; default tries Chinese and English)
+    #[arg(long = "ocr-languages", value_name = "LANGS", value_delimiter = '+')]
+    pub ocr_languages: Vec<String>,
+
+    /// Analyze at most N files at the same time (default: one per CPU core); OCR and video
+    /// tools then run at most N at a time too
+    #[arg(short = 'j', long = "jobs", value_name = "N")]
+    pub jobs: Option<usize>,
+
     /// Use single-frame video analysis for faster processing (default is multi-frame)
     #[arg(long = "fast-video")]
     pub fast_video: bool,
 
+    /// How multi-frame video OCR combines frames: vote (text persisting across frames) or best (single best frame)
+    #[arg(long = "frame-aggregation", value_name = "STRATEGY")]
+    pub frame_aggregation: Option<nameback_core::FrameAggregation>,
+
+    /// Most frames multi-frame video OCR reads per video, picked at scene changes (default 6)
+    #[arg(long = "video-frames", value_name = "N")]
+    pub video_frames: Option<usize>,
+
+    /// Name files from this many key phrases of long text (PDF and text content, OCR) (default 1)
+    #[arg(long = "key-phrases", value_name = "N")]
+    pub key_phrase_count: Option<usize>,
+
+    /// Most words in one key phrase (default 3)
+    #[arg(long = "key-phrase-words", value_name = "N")]
+    pub key_phrase_max_words: Option<usize>,
+
+    /// Don't name files from OCR text tesseract read with a mean confidence (0-100) under this (default 40)
+    #[arg(long = "min-ocr-confidence", value_name = "N")]
+    pub min_ocr_confidence: Option<u8>,
+
+    /// Stop an external tool (exiftool, ffmpeg, ...) that runs longer than this on one file and report the file as timed out (default 300)
+    #[arg(long = "tool-timeout", value_name = "SECONDS")]
+    pub tool_timeout: Option<u64>,
+
+    /// Leave files modified within the last this many seconds alone as still being written (temporary files and incomplete downloads are always skipped)
+    #[arg(long = "stable-for", value_name = "SECONDS")]
+    pub stable_for: Option<u64>,
+
+    /// Skip files larger than this many megabytes without analyzing them
+    #[arg(long = "max-file-size", value_name = "MB")]
+    pub max_file_size_mb: Option<u64>,
+
+    /// Name PDFs with more pages from their metadata, without reading or OCR'ing their text
+    #[arg(long = "max-pdf-pages", value_name = "PAGES")]
+    pub max_pdf_pages: Option<u32>,
+
+    /// Name videos longer than this from their metadata, without OCR'ing frames
+    #[arg(long = "max-video-duration", value_name = "MINUTES")]
+    pub max_video_duration_min: Option<u64>,
+
+    /// Metadata reader: auto (exiftool if installed), exiftool, or native (built-in, no exiftool needed)
+    #[arg(long = "metadata-backend", value_name = "BACKEND")]
+    pub metadata_backend: Option<nameback_core::MetadataBackend>,
+
     /// Disable geocoding of GPS coordinates (shows raw coordinates instead of city names)
     #[arg(long = "no-geocode")]
     pub no_geocode: bool,
+
+    /// Where place names come from: offline (GeoNames city database, downloaded once), online (OpenStreetMap), or auto (offline once downloaded)
+    #[arg(long = "geocoder", value_name = "SOURCE")]
+    pub geocoder: Option<nameback_core::GeocodeSource>,
+
+    /// GeoNames city database for --geocoder offline (e.g. cities1000.txt or cities15000.zip)
+    #[arg(long = "geocode-database", value_name = "FILE")]
+    pub geocode_database: Option<PathBuf>,
+
+    /// How precisely places are named: country, region, city (default) or coordinates
+    #[arg(long = "location-precision", value_name = "PRECISION")]
+    pub location_precision: Option<nameback_core::LocationPrecision>,
+
+    /// Never name locations within RADIUS (default 1km) of this point, e.g. home (can be repeated)
+    #[arg(long = "location-blocklist", value_name = "LAT,LON[,RADIUS]")]
+    pub location_blocklist: Vec<nameback_core::BlockedArea>,
+
+    /// Identify music without a title by its audio fingerprint, using this AcoustID API key
+    /// (needs fpcalc from Chromaprint; sends fingerprints to acoustid.org)
+    #[arg(long = "acoustid-key", value_name = "KEY")]
+    pub acoustid_key: Option<String>,
+
+    /// Name PDFs of academic papers FirstAuthor_Year_Title from the DOI or arXiv identifier on
+    /// their first page (sends identifiers to Crossref and arXiv; results are cached)
+    #[arg(long = "paper-lookup")]
+    pub paper_lookup: bool,
+
+    /// Filename template, e.g. "{camera}_{name}" or "{category}/{yyyy}/{title}" (/ sorts into subfolders)
+    #[arg(long = "template", value_name = "TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Move files into folders built from this template, e.g. "{yyyy}/{mm}/{location}" or "{category}"
+    /// (created in the processed directory unless --organize-into is given, which several
+    /// inputs need; undo with --undo)
+    #[arg(long = "organize", value_name = "TEMPLATE")]
+    pub organize: Option<String>,
+
+    /// Create the --organize folders in this directory instead
+    #[arg(long = "organize-into", value_name = "DIR")]
+    pub organize_into: Option<PathBuf>,
+
+    /// Exact copies of another file: flag (rename as usual), skip, or suffix (first file's name + _dup1, _dup2, ...)
+    #[arg(long = "duplicates", value_name = "MODE")]
+    pub duplicates: Option<nameback_core::DuplicateHandling>,
+
+    /// Symbolic links: skip, follow (rename the files they point to), or rename-link (name links after what they point to)
+    #[arg(long = "symlinks", value_name = "POLICY")]
+    pub symlinks: Option<nameback_core::SymlinkPolicy>,
+
+    /// Proposed name already taken by another file: content (tell apart by sender, amount or hash), number, timestamp, hash, keep-original, or fail (rename nothing)
+    #[arg(long = "on-collision", value_name = "STRATEGY")]
+    pub on_collision: Option<nameback_core::CollisionStrategy>,
+
+    /// New name already taken by a file on disk: skip (default), suffix (_1, _2, ...), trash (move that file to the system trash), or overwrite
+    #[arg(long = "on-conflict", value_name = "ACTION")]
+    pub on_conflict: Option<nameback_core::OnConflict>,
+
+    /// Case of proposed names: keep (default), snake, kebab, title or camel
+    #[arg(long = "name-case", value_name = "CASE")]
+    pub name_case: Option<nameback_core::NameCase>,
+
+    /// What joins the words of proposed names: underscore, hyphen or space (default: hyphen for
+    /// --name-case kebab, underscore otherwise)
+    #[arg(long = "word-separator", value_name = "SEP")]
+    pub word_separator: Option<nameback_core::WordSeparator>,
+
+    /// Fewest numbered files (IMG_001, ...) or burst photos that make a series (default 3)
+    #[arg(long = "series-min-files", value_name = "N")]
+    pub series_min_files: Option<usize>,
+
+    /// Pad series numbers to at least this many digits (default 3, or 2 for bursts)
+    #[arg(long = "series-padding", value_name = "DIGITS")]
+    pub series_padding: Option<usize>,
+
+    /// What goes between a series' name and its number: underscore, hyphen or space (default:
+    /// the series' own, e.g. name(001))
+    #[arg(long = "series-separator", value_name = "SEP")]
+    pub series_separator: Option<nameback_core::WordSeparator>,
+
+    /// Number series from N in order (default: numbered series keep their numbers, bursts start at 1)
+    #[arg(long = "series-start", value_name = "N")]
+    pub series_start: Option<usize>,
+
+    /// Give files whose content is another format than their extension says (a PNG named .jpg)
+    /// that format's extension in their new name
+    #[arg(long = "fix-extensions")]
+    pub fix_extensions: bool,
+
+    /// Lower-case the extensions of new names (IMG_0042.JPG becomes Sunset.jpg)
+    #[arg(long = "lowercase-extensions")]
+    pub lowercase_extensions: bool,
+
+    /// List sets of files with identical content after processing
+    #[arg(long = "report-duplicates")]
+    pub report_duplicates: bool,
+
+    /// Write the derived title into file metadata (XMP/EXIF, PDF, ID3): "also" while renaming, or "only" instead of renaming
+    #[arg(long = "write-metadata", value_name = "MODE")]
+    pub write_metadata: Option<nameback_core::MetadataWriteMode>,
+
+    /// Rename files on their own, leaving sidecars (.xmp, .aae, .srt, ...) and RAW+JPEG twins behind
+    #[arg(long = "no-sidecars")]
+    pub no_sidecars: bool,
+
+    /// Also rename folders still called "New Folder (3)" or the like, after the files in them
+    #[arg(long = "rename-dirs", conflicts_with = "watch")]
+    pub rename_dirs: bool,
+
+    /// Only propose names scoring at least this much (names score about 2-10; 5 or more is a good name)
+    #[arg(long = "min-confidence", value_name = "SCORE")]
+    pub min_confidence: Option<f32>,
+
+    /// Apply a named configuration profile (e.g. photos, scans, forensics)
+    #[arg(long = "config-profile", value_name = "NAME", env = "NAMEBACK_PROFILE")]
+    pub config_profile: Option<String>,
+
+    /// Config file with settings and profiles (default: config.toml in the user config directory)
+    #[arg(long = "config", value_name = "PATH", env = "NAMEBACK_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Print a per-stage timing report after processing
+    #[arg(long = "profile")]
+    pub profile: bool,
+}
+
+impl Args {
+    /// `--format`, defaulting to the table preview for dry runs (except in watch mode)
+    pub fn output_format(&self) -> OutputFormat {
+        self.format.unwrap_or(if self.dry_run && !self.watch {
+            OutputFormat::Table
+        } else {
+            OutputFormat::Text
+        })
+    }
+}
+
+/// Commands other than renaming (options go before the command)
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show every name considered for a file, how each was scored, and why the winner was
+    /// picked or why no name was proposed (nothing is renamed)
+    Explain {
+        /// The file to explain
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+/// How analysis and rename results are reported
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Log lines as files are processed
+    Text,
+    /// Aligned, colored table with a totals footer (logs are reduced to warnings)
+    Table,
+}
+
+/// Output format for log events
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// Newline-delimited JSON
+    Json,
 }
 
 /// Parses command-line arguments
+/// Invalid arguments exit with `ExitCode::BadArguments`; --help and --version with success
 pub fn parse_args() -> Args {
-    Args::parse()
+    Args::try_parse().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit();
+        }
+        let _ = e.print();
+        ExitCode::BadArguments.exit()
+    })
 }
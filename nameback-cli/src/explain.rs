@@ -0,0 +1,84 @@
+use anstyle::{AnsiColor, Style};
+use nameback_core::{ExplainedCandidate, Explanation};
+use std::io::IsTerminal;
+
+/// Prints every candidate name considered for a file with its score worked out, then the
+/// proposed name and why it won (or why there is none)
+/// Colors are used only when stdout is a terminal and NO_COLOR is unset
+pub fn print_explanation(explanation: &Explanation) {
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let paint = |style: Style, text: &str| {
+        if color {
+            format!("{style}{text}{style:#}")
+        } else {
+            text.to_string()
+        }
+    };
+
+    let analysis = &explanation.analysis;
+    println!(
+        "{} ({})",
+        paint(Style::new().bold(), &analysis.original_path.display().to_string()),
+        analysis.file_category
+    );
+
+    if explanation.candidates.is_empty() {
+        println!("\nNo candidate names");
+    } else {
+        println!("\nCandidates:");
+    }
+    let width = explanation
+        .candidates
+        .iter()
+        .map(|c| c.name.chars().count() + 2)
+        .max()
+        .unwrap_or(0);
+    for candidate in &explanation.candidates {
+        let (mark, style) = match &candidate.rejected {
+            None => ("✓", AnsiColor::Green.on_default().bold()),
+            Some(_) => ("✗", Style::new().dimmed()),
+        };
+        let quoted = format!("\"{}\"", candidate.name);
+        let reason = candidate.rejected.as_ref().map(|r| format!("  {}", r)).unwrap_or_default();
+        println!(
+            "  {} {:<width$}  {:<15}  {:>5.2}{}",
+            paint(style, mark),
+            quoted,
+            candidate.source.label(),
+            candidate.score,
+            reason,
+        );
+        println!("      {}", paint(Style::new().dimmed(), &breakdown(candidate)));
+    }
+
+    for issue in &analysis.errors {
+        println!("\n{} {}", paint(AnsiColor::Yellow.on_default(), "Issue:"), issue);
+    }
+    match &analysis.proposed_name {
+        Some(name) => println!("\nProposed name: {}", paint(AnsiColor::Cyan.on_default(), name)),
+        None => println!("\nNo name proposed"),
+    }
+    println!("Why: {}", explanation.verdict());
+}
+
+/// "length 2.00 + source 3.00 + words 1.50 + diversity 0.93 = 7.43 × 0.3 (only a date) = 2.23"
+fn breakdown(candidate: &ExplainedCandidate) -> String {
+    let Some(breakdown) = &candidate.breakdown else {
+        return "score set by a name provider".to_string();
+    };
+    let mut text = format!(
+        "length {:.2} + source {:.2} + words {:.2} + diversity {:.2} = {:.2}",
+        breakdown.length,
+        breakdown.source,
+        breakdown.words,
+        breakdown.diversity,
+        breakdown.points()
+    );
+    if !breakdown.penalties.is_empty() {
+        for penalty in &breakdown.penalties {
+            text.push_str(&format!(" × {} ({})", penalty.factor(), penalty));
+        }
+        text.push_str(&format!(" = {:.2}", candidate.score));
+    }
+    text
+}
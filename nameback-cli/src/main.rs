@@ -1,9 +1,167 @@
-use anyhow::Result;
-use nameback_core::{RenameConfig, RenameEngine};
+use anyhow::{Context, Result};
+use nameback_core::{
+    ConfigFile, DeferReason, FileCategory, InstallOptions, Profile, RenameConfig, RenameEngine,
+    RunReport,
+};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use outcome::{ExitCode, Summary};
 
 mod cli;
+mod explain;
+mod outcome;
+mod picker;
+mod progress;
+mod review;
+mod table;
+mod watch;
+
+/// Installs the global tracing subscriber (stderr, plus --log-file if given)
+/// RUST_LOG takes precedence over the --verbose flag when set
+/// The table format keeps only warnings by default so the table stays readable
+fn init_logging(args: &cli::Args) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(match (args.verbose, args.output_format()) {
+            (true, _) => "debug",
+            (false, cli::OutputFormat::Table) => "warn",
+            (false, cli::OutputFormat::Text) => "info",
+        })
+    });
+
+    let file_layer = match &args.log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            Some(format_layer(args.log_format, Mutex::new(file), false))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(format_layer(args.log_format, std::io::stderr, true))
+        .with(file_layer)
+        .init();
+
+    Ok(())
+}
+
+/// Number of slowest files listed in the --profile report
+const PROFILE_SLOWEST_FILES: usize = 5;
+
+/// Prints the per-stage timing table for --profile
+fn print_profile_report(report: &nameback_core::ProfileReport) {
+    if report.is_empty() {
+        println!("\nNo timings recorded (all files served from cache?)");
+        return;
+    }
+
+    let total = report.total().as_secs_f64();
+
+    println!("\nStage timings (wall time summed over files):");
+    println!(
+        "  {:<10} {:>7} {:>11} {:>11} {:>11} {:>7}",
+        "stage", "files", "total", "mean", "max", "share"
+    );
+    for stats in report.stage_stats() {
+        let share = if total > 0.0 {
+            stats.total.as_secs_f64() / total * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "  {:<10} {:>7} {:>10.3}s {:>9.1}ms {:>9.1}ms {:>6.1}%",
+            stats.stage.name(),
+            stats.files,
+            stats.total.as_secs_f64(),
+            stats.mean().as_secs_f64() * 1000.0,
+            stats.max.as_secs_f64() * 1000.0,
+            share
+        );
+    }
+
+    println!("\nSlowest files:");
+    for (path, elapsed) in report.slowest_files(PROFILE_SLOWEST_FILES) {
+        println!("  {:>9.3}s  {}", elapsed.as_secs_f64(), path.display());
+    }
+}
+
+/// Lists each file that has exact copies, followed by the copies
+fn print_duplicates(analyses: &[nameback_core::FileAnalysis]) {
+    let mut sets: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+    for analysis in analyses {
+        if let Some(original) = &analysis.duplicate_of {
+            sets.entry(original).or_default().push(&analysis.original_path);
+        }
+    }
+
+    if sets.is_empty() {
+        println!("\nNo duplicate files found");
+        return;
+    }
+
+    let copies: usize = sets.values().map(Vec::len).sum();
+    println!("\nDuplicates ({} copies of {} files):", copies, sets.len());
+    for (original, copies) in sets {
+        println!("  {}", original.display());
+        for copy in copies {
+            println!("    = {}", copy.display());
+        }
+    }
+}
+
+/// Reports an invalid argument, config file or environment variable and exits with
+/// `ExitCode::BadArguments` (generic so it fits `unwrap_or_else`)
+fn bad_arguments<T>(error: anyhow::Error) -> T {
+    eprintln!("Error: {:?}", error);
+    ExitCode::BadArguments.exit()
+}
+
+/// Builds a fmt layer for the requested log format
+fn format_layer<S, W>(
+    format: cli::LogFormat,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
 
-fn main() -> Result<()> {
+    match format {
+        cli::LogFormat::Text => layer.boxed(),
+        // Include the active file/stage spans so each event can be traced back to its file
+        cli::LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Runs nameback; an error that stops the run exits with `ExitCode::Error`, so scripts can
+/// tell it from files that failed to rename
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {:?}", e);
+        ExitCode::Error.exit();
+    }
+}
+
+fn run() -> Result<()> {
 
     // Refuse to run as root for security
     #[cfg(unix)]
@@ -12,7 +170,7 @@ fn main() -> Result<()> {
             eprintln!("ERROR: nameback refuses to run as root for security reasons.");
             eprintln!("Running as root could accidentally modify system directories.");
             eprintln!("Please run as a regular user.");
-            std::process::exit(1);
+            ExitCode::Error.exit();
         }
     }
 
@@ -25,67 +183,352 @@ fn main() -> Result<()> {
     }
 
     if args.install_deps {
-        match nameback_core::install_dependencies() {
+        let mut options = InstallOptions::from_config(&settings_config(&args));
+        options.never_touch_dns |= args.never_touch_dns;
+        match nameback_core::install_dependencies_with_options(None, &options) {
             Ok(_) => {
                 println!("\nRun 'nameback --check-deps' to verify installation.");
                 return Ok(());
             }
             Err(e) => {
                 eprintln!("Error: {}", e);
-                std::process::exit(1);
+                ExitCode::DependencyMissing.exit();
             }
         }
     }
 
-    // Require directory argument for normal operation
-    let directory = args.directory.as_ref().ok_or_else(|| {
-        anyhow::anyhow!("Directory argument is required. Use --help for usage information.")
-    })?;
+    if args.init_config {
+        let path = match &args.config {
+            Some(path) => path.clone(),
+            None => ConfigFile::default_path().context("No user config directory")?,
+        };
+        ConfigFile::write_default(&path)?;
+        println!("Wrote {}", path.display());
+        return Ok(());
+    }
 
-    // Initialize logger with appropriate level based on verbose flag
-    if std::env::var("RUST_LOG").is_err() {
-        if args.verbose {
-            std::env::set_var("RUST_LOG", "debug");
-        } else {
-            std::env::set_var("RUST_LOG", "info");
+    if args.clear_cache {
+        let cleared = RenameEngine::new(settings_config(&args)).clear_cache()?;
+        println!(
+            "Cleared {} cached entries ({} bytes)",
+            cleared.total_entries, cleared.cache_size_bytes
+        );
+        return Ok(());
+    }
+
+    if let Some(batch_id) = &args.undo {
+        init_logging(&args)?;
+        let engine = RenameEngine::with_defaults();
+        let batch_id = Some(batch_id.as_str()).filter(|id| !id.is_empty());
+        let report = engine.undo_batch(batch_id)?;
+
+        tracing::info!(
+            "Restored {} files from batch {}",
+            report.restored.len(),
+            report.batch_id
+        );
+        for conflict in &report.conflicts {
+            tracing::warn!(
+                "Left {} as is: {} (was {})",
+                conflict.new_path.display(),
+                conflict.reason,
+                conflict.original_path.display()
+            );
+        }
+        if !report.conflicts.is_empty() {
+            ExitCode::PartialFailure.exit();
         }
+        return Ok(());
+    }
+
+    // Require a directory (or files) for normal operation
+    for path in &args.directories {
+        if !path.exists() {
+            bad_arguments(anyhow::anyhow!("No such file or directory: {}", path.display()))
+        }
+    }
+    let mut inputs = args.directories.clone();
+    if let Some(cli::Command::Explain { file }) = &args.command {
+        if !file.is_file() {
+            bad_arguments(anyhow::anyhow!("No such file: {}", file.display()))
+        }
+        inputs.push(file.clone());
+    }
+    if let Some(list) = &args.files_from {
+        inputs.extend(read_file_list(list).unwrap_or_else(bad_arguments));
+    }
+    if inputs.is_empty() {
+        bad_arguments(anyhow::anyhow!(
+            "Directory argument is required. Use --help for usage information."
+        ))
+    }
+    // Tables, reports and --organize are relative to this
+    let directory = &common_root(&inputs);
+    if args.watch && !(inputs.len() == 1 && inputs[0].is_dir()) {
+        bad_arguments(anyhow::anyhow!("--watch takes exactly one directory"))
     }
-    env_logger::init();
+
+    // Initialize logger with appropriate level based on verbose flag
+    init_logging(&args)?;
 
     if args.dry_run {
-        log::info!("Running in DRY-RUN mode - no files will be renamed");
+        tracing::info!("Running in DRY-RUN mode - no files will be renamed");
     }
 
     // Create rename engine with configuration from CLI args
-    let config = RenameConfig {
-        skip_hidden: args.skip_hidden,
-        include_location: !args.no_location, // Inverted: location is default, no_location opts out
-        include_timestamp: !args.no_timestamp, // Inverted: timestamp is default, no_timestamp opts out
-        multiframe_video: !args.fast_video, // Inverted: multiframe is default, fast_video opts out
-        geocode: !args.no_geocode, // Inverted: geocoding is default, no_geocode opts out
-        enable_cache: true, // Metadata caching enabled by default
-        cache_path: None, // Use default cache location
-    };
+    let config_file = load_config_file(&args).unwrap_or_else(bad_arguments);
+    let mut builder = config_file.apply(RenameConfig::builder());
+    if let Some(name) = &args.config_profile {
+        let profile = config_file.profile(name).unwrap_or_else(|| {
+            let available: Vec<&str> = config_file.profile_names().collect();
+            bad_arguments(anyhow::anyhow!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                available.join(", ")
+            ))
+        });
+        tracing::info!("Using profile: {}", name);
+        builder = profile.apply(builder);
+    }
+
+    // Precedence: command line > environment > config file profile > config file settings >
+    // defaults; `[category.*]` overrides win for their category
+    builder = Profile::from_env().unwrap_or_else(bad_arguments).apply(builder);
+
+    // Flags override the profile only when given
+    if args.skip_hidden {
+        builder = builder.skip_hidden(true);
+    }
+    if let Some(depth) = args.max_depth {
+        builder = builder.max_depth(depth);
+    }
+    if !args.include.is_empty() {
+        builder = builder.include_globs(args.include.clone());
+    }
+    if !args.exclude.is_empty() {
+        builder = builder.exclude_globs(args.exclude.clone());
+    }
+    if !args.only.is_empty() {
+        builder = builder.categories(args.only.clone());
+    } else if !args.skip.is_empty() {
+        let kept = FileCategory::ALL.into_iter().filter(|c| !args.skip.contains(c));
+        builder = builder.categories(kept);
+    }
+    if args.no_location {
+        builder = builder.include_location(false);
+    }
+    if args.no_timestamp {
+        builder = builder.include_timestamp(false);
+    }
+    if let Some(format) = &args.timestamp_format {
+        builder = builder.timestamp_format(format.clone());
+    }
+    if let Some(precision) = args.timestamp_precision {
+        builder = builder.timestamp_precision(precision);
+    }
+    if let Some(position) = args.timestamp_position {
+        builder = builder.timestamp_position(position);
+    }
+    if args.attachment_count {
+        builder = builder.email_attachment_count(true);
+    }
+    if args.subject_hints {
+        builder = builder.subject_hints(true);
+    }
+    if args.no_archive_peek {
+        builder = builder.peek_archives(false);
+    }
+    if args.receipts {
+        builder = builder.receipt_mode(true);
+    }
+    if !args.ocr_languages.is_empty() {
+        builder = builder.ocr_languages(args.ocr_languages.clone());
+    }
+    if let Some(jobs) = args.jobs {
+        builder = builder.max_parallel_files(jobs);
+    }
+    if args.fast_video {
+        builder = builder.multiframe_video(false);
+    }
+    if let Some(aggregation) = args.frame_aggregation {
+        builder = builder.frame_aggregation(aggregation);
+    }
+    if let Some(frames) = args.video_frames {
+        builder = builder.video_frames(frames);
+    }
+    if let Some(count) = args.key_phrase_count {
+        builder = builder.key_phrase_count(count);
+    }
+    if let Some(words) = args.key_phrase_max_words {
+        builder = builder.key_phrase_max_words(words);
+    }
+    if let Some(confidence) = args.min_ocr_confidence {
+        builder = builder.min_ocr_confidence(confidence);
+    }
+    if let Some(secs) = args.tool_timeout {
+        builder = builder.tool_timeout(std::time::Duration::from_secs(secs));
+    }
+    if let Some(secs) = args.stable_for {
+        builder = builder.stable_for(std::time::Duration::from_secs(secs));
+    }
+    if let Some(megabytes) = args.max_file_size_mb {
+        builder = builder.max_file_size_mb(megabytes);
+    }
+    if let Some(pages) = args.max_pdf_pages {
+        builder = builder.max_pdf_pages(pages);
+    }
+    if let Some(minutes) = args.max_video_duration_min {
+        builder = builder.max_video_duration_min(minutes);
+    }
+    if let Some(backend) = args.metadata_backend {
+        builder = builder.metadata_backend(backend);
+    }
+    if args.no_geocode {
+        builder = builder.geocode(false);
+    }
+    if let Some(source) = args.geocoder {
+        builder = builder.geocode_source(source);
+    }
+    if let Some(path) = &args.geocode_database {
+        builder = builder.geocode_database(path.clone());
+    }
+    if let Some(precision) = args.location_precision {
+        builder = builder.location_precision(precision);
+    }
+    if !args.location_blocklist.is_empty() {
+        builder = builder.location_blocklist(args.location_blocklist.clone());
+    }
+    if let Some(key) = &args.acoustid_key {
+        builder = builder.acoustid_key(key.clone());
+    }
+    if args.paper_lookup {
+        builder = builder.paper_lookup(true);
+    }
+    if let Some(template) = &args.template {
+        builder = builder.template(template.clone());
+    }
+    if let Some(template) = &args.organize {
+        let root = match (&args.organize_into, inputs.as_slice()) {
+            (Some(root), _) => root.clone(),
+            (None, [single]) if single.is_dir() => single.clone(),
+            _ => bad_arguments(anyhow::anyhow!("--organize with several inputs needs --organize-into")),
+        };
+        builder = builder.organize(template.clone()).organize_root(root);
+    } else if let Some(root) = &args.organize_into {
+        builder = builder.organize_root(root.clone());
+    }
+    if let Some(handling) = args.duplicates {
+        builder = builder.duplicates(handling);
+    }
+    if let Some(policy) = args.symlinks {
+        builder = builder.symlinks(policy);
+    }
+    if let Some(strategy) = args.on_collision {
+        builder = builder.collision_strategy(strategy);
+    }
+    if let Some(action) = args.on_conflict {
+        builder = builder.on_conflict(action);
+    }
+    if let Some(case) = args.name_case {
+        builder = builder.name_case(case);
+    }
+    if let Some(separator) = args.word_separator {
+        builder = builder.word_separator(separator);
+    }
+    if let Some(files) = args.series_min_files {
+        builder = builder.series_min_files(files);
+    }
+    if let Some(digits) = args.series_padding {
+        builder = builder.series_padding(digits);
+    }
+    if let Some(separator) = args.series_separator {
+        builder = builder.series_separator(separator);
+    }
+    if let Some(start) = args.series_start {
+        builder = builder.series_start(start);
+    }
+    if args.fix_extensions {
+        builder = builder.fix_extensions(true);
+    }
+    if args.lowercase_extensions {
+        builder = builder.lowercase_extensions(true);
+    }
+    if let Some(mode) = args.write_metadata {
+        builder = builder.write_metadata(mode);
+    }
+    if args.no_sidecars {
+        builder = builder.rename_sidecars(false);
+    }
+    if args.rename_dirs {
+        builder = builder.rename_directories(true);
+    }
+    if let Some(score) = args.min_confidence {
+        builder = builder.min_confidence(score);
+    }
+    if args.refresh_cache {
+        builder = builder.refresh_cache(true);
+    }
+    if args.never_touch_dns {
+        builder = builder.never_touch_dns(true);
+    }
+    let mut config = builder
+        .build()
+        .context("Invalid configuration")
+        .unwrap_or_else(bad_arguments);
+    // --no-cache wins over a cache_path from the config file or environment
+    if args.no_cache {
+        config.enable_cache = false;
+    }
 
+    let metadata_only = config.write_metadata == nameback_core::MetadataWriteMode::Only;
+    let install_options = InstallOptions::from_config(&config);
     let engine = RenameEngine::new(config);
 
-    // Smart dependency detection - check if missing deps are needed for this directory
-    log::info!("Checking dependencies for: {}", directory.display());
-    match nameback_core::detect_needed_dependencies(directory) {
+    if let Some(cli::Command::Explain { file }) = &args.command {
+        let explanation = engine.explain(file)?;
+        explain::print_explanation(&explanation);
+        // Nothing is renamed; the exit code only says whether explaining worked
+        println!("{}", Summary::new(std::slice::from_ref(&explanation.analysis), &[]));
+        return Ok(());
+    }
+
+    // Smart dependency detection - check if missing deps are needed for these files
+    tracing::info!("Checking dependencies for: {}", directory.display());
+    match nameback_core::detect_needed_dependencies_for(&inputs) {
         Ok(needs) => {
             if needs.has_required_missing() {
                 eprintln!("\n⚠️  ERROR: Required dependencies are missing!\n");
                 for dep in &needs.missing_required {
                     eprintln!("  ✗ {} - {}", dep.name(), dep.description());
+                    if let Some(reason) = needs.reason(*dep) {
+                        eprintln!("    {}", reason);
+                    }
                 }
                 eprintln!("\nRun 'nameback --install-deps' to install them.\n");
-                std::process::exit(1);
+                ExitCode::DependencyMissing.exit();
             }
 
-            if !needs.missing_optional.is_empty() {
+            // Old tools are still used; say what they cost
+            for outdated in &needs.outdated {
+                tracing::warn!("{} (update it with your package manager)", outdated);
+            }
+
+            // Without a terminal to answer (or with the file list on stdin) there's no prompt
+            if !needs.missing_optional.is_empty() && !std::io::stdin().is_terminal() {
+                for dep in &needs.missing_optional {
+                    match needs.reason(*dep) {
+                        Some(reason) => tracing::warn!("Optional dependency missing: {}, {}", dep.name(), reason),
+                        None => tracing::warn!("Optional dependency missing: {}", dep.name()),
+                    }
+                }
+                tracing::warn!("Run 'nameback --install-deps' to install them");
+            } else if !needs.missing_optional.is_empty() {
                 println!("\n⚠️  Optional dependencies missing:");
                 for dep in &needs.missing_optional {
                     println!("  • {} - {}", dep.name(), dep.description());
+                    if let Some(reason) = needs.reason(*dep) {
+                        println!("    {}", reason);
+                    }
                 }
 
                 print!("\nWould you like to install them now? [Y/n]: ");
@@ -99,7 +542,7 @@ fn main() -> Result<()> {
                 if response.is_empty() || response == "y" || response == "yes" {
                     println!();
                     // Install with simple progress reporting
-                    match nameback_core::install_dependencies_with_progress(Some(Box::new(
+                    match nameback_core::install_dependencies_with_options(Some(Box::new(
                         |msg: &str, pct: u8| {
                             if pct == 0 {
                                 print!("⏳ ");
@@ -112,7 +555,7 @@ fn main() -> Result<()> {
                                 std::io::stdout().flush().ok();
                             }
                         },
-                    ))) {
+                    )), &install_options) {
                         Ok(_) => println!("\n✅ Dependencies installed successfully!\n"),
                         Err(e) => {
                             eprintln!("\n❌ Failed to install dependencies: {}", e);
@@ -125,37 +568,234 @@ fn main() -> Result<()> {
             }
         }
         Err(e) => {
-            log::warn!("Failed to check dependencies: {}. Continuing anyway...", e);
+            tracing::warn!("Failed to check dependencies: {}. Continuing anyway...", e);
         }
     }
 
-    // Process directory
-    log::info!("Analyzing directory: {}", directory.display());
-    let analyses = engine.analyze_directory(directory)?;
+    if args.watch {
+        let summary = watch::watch(&engine, directory, &args)?;
+        println!("{}", summary);
+        summary.exit_code().exit()
+    }
+
+    // Process the directories and files
+    for input in &inputs {
+        tracing::info!("Analyzing: {}", input.display());
+    }
+    let analyses = match progress::ProgressBar::for_stderr(&args) {
+        Some(mut bar) => {
+            let analyses = engine.analyze_paths_with_progress(&inputs, |p| bar.update(&p));
+            bar.finish();
+            analyses?
+        }
+        None => engine.analyze_paths(&inputs)?,
+    };
 
-    log::info!("Found {} files to process", analyses.len());
+    tracing::info!("Found {} files to process", analyses.len());
 
     // Count files with proposed names
     let renameable = analyses.iter().filter(|a| a.proposed_name.is_some()).count();
-    log::info!("{} files have suitable metadata for renaming", renameable);
+    tracing::info!("{} files have suitable metadata for renaming", renameable);
+
+    // Let the user choose which proposals to apply
+    let picked;
+    let to_rename = if args.pick || args.interactive {
+        if !std::io::stdin().is_terminal() {
+            bad_arguments(anyhow::anyhow!("--pick and --interactive need an interactive terminal"))
+        }
+        picked = if args.pick {
+            picker::pick(&analyses)?
+        } else {
+            review::review(&analyses)?
+        };
+        tracing::info!("{} files picked for renaming", picked.len());
+        &picked
+    } else {
+        &analyses
+    };
+
+    // Check the batch as a whole; conflicting renames are refused by rename_files, which
+    // reports folders that can't be written to once each rather than for every file
+    let plan = engine.plan(to_rename);
+    let format = args.output_format();
+    if format == cli::OutputFormat::Text {
+        for planned in plan.renames.iter().filter(|r| !r.conflicts.is_empty()) {
+            for conflict in planned.conflicts.iter().filter(|c| c.unwritable_folder().is_none()) {
+                tracing::warn!(
+                    "{} → {}: {}",
+                    planned.original_path.display(),
+                    planned.new_path.display(),
+                    conflict
+                );
+            }
+        }
+    }
 
     // Perform renames
-    let results = engine.rename_files(&analyses, args.dry_run);
+    let results = engine.rename_files(to_rename, args.dry_run);
 
     // Report results
-    let successful = results.iter().filter(|r| r.success).count();
-    let failed = results.iter().filter(|r| !r.success).count();
+    let summary = Summary::new(&analyses, &results);
+    let deferred: Vec<(&Path, DeferReason)> = analyses
+        .iter()
+        .filter_map(|a| Some((a.original_path.as_path(), a.deferred?)))
+        .chain(
+            results
+                .iter()
+                .filter_map(|r| Some((r.original_path.as_path(), r.deferred?))),
+        )
+        .collect();
 
-    if args.dry_run {
-        log::info!("[DRY RUN] Would rename {} files", successful);
+    let written = results.iter().filter(|r| r.metadata_written).count();
+    if written > 0 {
+        let verb = if args.dry_run { "[DRY RUN] Would write" } else { "Wrote" };
+        tracing::info!("{} titles into the metadata of {} files", verb, written);
+    }
+
+    let companions: usize = results.iter().map(|r| r.companions.len()).sum();
+    if companions > 0 {
+        let verb = if args.dry_run { "[DRY RUN] Would move" } else { "Moved" };
+        tracing::info!("{} {} sidecar and paired files along with their files", verb, companions);
+    }
+
+    if metadata_only {
+        if summary.failed > 0 {
+            tracing::warn!("Failed to write metadata to {} files", summary.failed);
+        }
+    } else if args.dry_run {
+        tracing::info!("[DRY RUN] Would rename {} files", summary.renamed);
     } else {
-        log::info!("Successfully renamed {} files", successful);
-        if failed > 0 {
-            log::warn!("Failed to rename {} files", failed);
+        tracing::info!("Successfully renamed {} files", summary.renamed);
+        if summary.failed > 0 {
+            tracing::warn!("Failed to rename {} files", summary.failed);
         }
     }
 
-    log::info!("Processing complete!");
+    if format == cli::OutputFormat::Table {
+        table::print_table(directory, &analyses, &results, &plan, args.dry_run);
+    } else if !deferred.is_empty() {
+        tracing::warn!(
+            "Deferred {} files that were in use or still being written (run again later):",
+            deferred.len()
+        );
+        for (path, reason) in &deferred {
+            tracing::warn!("  {} ({})", path.display(), reason);
+        }
+    }
+    if format == cli::OutputFormat::Text {
+        let over_limit: Vec<_> = analyses.iter().filter(|a| a.over_limit.is_some()).collect();
+        if !over_limit.is_empty() {
+            tracing::info!("{} files were over the size limits:", over_limit.len());
+        }
+        for analysis in over_limit {
+            let limit = analysis.over_limit.expect("filtered on over_limit");
+            let handling = if limit.skipped() { "skipped" } else { "content not read" };
+            tracing::info!("  {} ({}; {})", analysis.original_path.display(), limit, handling);
+        }
 
-    Ok(())
+        let mismatched: Vec<_> = analyses.iter().filter(|a| a.extension_mismatch.is_some()).collect();
+        if !mismatched.is_empty() {
+            tracing::warn!("{} files have an extension that doesn't match their content:", mismatched.len());
+        }
+        for analysis in mismatched {
+            let mismatch = analysis.extension_mismatch.as_ref().expect("filtered on extension_mismatch");
+            let handling = if mismatch.fixed { "fixed" } else { "use --fix-extensions to fix" };
+            tracing::warn!("  {} ({}; {})", analysis.original_path.display(), mismatch, handling);
+        }
+    }
+
+    if let Some(path) = &args.report {
+        let mut report = RunReport::new(directory, args.dry_run);
+        report.record_analyses(&analyses);
+        report.record_results(&results);
+        report.save(path)?;
+        tracing::info!("Wrote run report to {}", path.display());
+    }
+
+    if let Some(batch_id) = results.iter().find_map(|r| r.batch_id.as_deref()) {
+        tracing::info!("To revert these renames, run: nameback --undo {}", batch_id);
+    }
+
+    if let Some(stats) = engine.cache_stats() {
+        tracing::info!(
+            "Cache: {} hits, {} misses ({} invalidated), {} entries, {:.1} MB",
+            stats.hits,
+            stats.misses,
+            stats.invalidations,
+            stats.total_entries,
+            stats.cache_size_bytes as f64 / 1_048_576.0
+        );
+    }
+
+    tracing::info!("Processing complete!");
+
+    if args.report_duplicates {
+        print_duplicates(&analyses);
+    }
+
+    if args.profile {
+        print_profile_report(&engine.profile_report());
+    }
+
+    // Always the last line on stdout, for scripts
+    println!("{}", summary);
+    summary.exit_code().exit()
+}
+
+/// The paths listed in `list` (or stdin for "-"), one per line; blank lines are ignored
+fn read_file_list(list: &Path) -> Result<Vec<PathBuf>> {
+    let text = if list == Path::new("-") {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read the file list from stdin")?
+    } else {
+        std::fs::read_to_string(list)
+            .with_context(|| format!("Failed to read file list {}", list.display()))?
+    };
+    Ok(text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// The deepest folder holding every input ("." when relative inputs share none)
+fn common_root(inputs: &[PathBuf]) -> PathBuf {
+    let folder = |input: &PathBuf| match input.is_dir() {
+        true => input.clone(),
+        false => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let mut root = folder(&inputs[0]);
+    for input in &inputs[1..] {
+        let folder = folder(input);
+        while !folder.starts_with(&root) {
+            if !root.pop() {
+                break;
+            }
+        }
+    }
+    if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    }
+}
+
+/// The configuration from the config file and environment alone, for commands that take no
+/// other options (exits on invalid settings)
+fn settings_config(args: &cli::Args) -> RenameConfig {
+    let config_file = load_config_file(args).unwrap_or_else(bad_arguments);
+    let profile = Profile::from_env().unwrap_or_else(bad_arguments);
+    profile
+        .apply(config_file.apply(RenameConfig::builder()))
+        .build()
+        .map_err(anyhow::Error::from)
+        .unwrap_or_else(bad_arguments)
+}
+
+/// The --config file, or the one in the default location
+fn load_config_file(args: &cli::Args) -> Result<ConfigFile> {
+    match &args.config {
+        Some(path) => ConfigFile::load(path),
+        None => ConfigFile::load_default(),
+    }
 }
@@ -0,0 +1,170 @@
+use nameback_core::{FileAnalysis, RenameResult};
+use std::fmt;
+
+/// What the process exit status tells a script (see "Exit Codes" in the user guide)
+/// These values are a contract: never renumber them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Every file with a proposed name was renamed
+    Success = 0,
+    /// Some files failed to rename (or an undo left files as they were)
+    PartialFailure = 1,
+    /// No file had a proposed name, so nothing was renamed
+    NothingRenameable = 2,
+    /// A required dependency is missing (or couldn't be installed)
+    DependencyMissing = 3,
+    /// Invalid arguments, config file or environment variables
+    BadArguments = 4,
+    /// The run stopped on an error before it finished (e.g. a folder couldn't be read)
+    Error = 5,
+}
+
+impl ExitCode {
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// Counts of a run's outcome, printed as its last line, e.g.
+/// `renamed=12 skipped=3 failed=1 deferred=0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    /// Files renamed (in a dry run, that would be; with `--write-metadata only`, written to)
+    pub renamed: usize,
+    /// Files left alone: no proposed name, not picked, or a skipped duplicate
+    pub skipped: usize,
+    /// Files whose rename failed or was refused
+    pub failed: usize,
+    /// Files in use or still being written, to be picked up by a later run
+    pub deferred: usize,
+}
+
+impl Summary {
+    pub fn new(analyses: &[FileAnalysis], results: &[RenameResult]) -> Self {
+        let renamed = results.iter().filter(|r| r.success).count();
+        let deferred_renames = results.iter().filter(|r| r.deferred.is_some()).count();
+        let failed = results.len() - renamed - deferred_renames;
+        let deferred = deferred_renames + analyses.iter().filter(|a| a.deferred.is_some()).count();
+        Summary {
+            renamed,
+            skipped: analyses.len().saturating_sub(renamed + failed + deferred),
+            failed,
+            deferred,
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        if self.failed > 0 {
+            ExitCode::PartialFailure
+        } else if self.renamed == 0 {
+            ExitCode::NothingRenameable
+        } else {
+            ExitCode::Success
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "renamed={} skipped={} failed={} deferred={}",
+            self.renamed, self.skipped, self.failed, self.deferred
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nameback_core::{DeferReason, FileCategory, NameSource};
+    use std::path::PathBuf;
+
+    fn analysis(name: &str, proposed: Option<&str>, deferred: Option<DeferReason>) -> FileAnalysis {
+        FileAnalysis {
+            original_path: PathBuf::from("/photos").join(name),
+            original_name: name.to_string(),
+            proposed_name: proposed.map(str::to_string),
+            title: None,
+            collision_suffix: None,
+            collision_strategy: None,
+            destination: None,
+            duplicate_of: None,
+            companions: Vec::new(),
+            file_category: FileCategory::Image,
+            confidence: 0.0,
+            name_source: NameSource::Fallback,
+            errors: Vec::new(),
+            rejected: Vec::new(),
+            alternatives: Vec::new(),
+            deferred,
+            over_limit: None,
+            series: None,
+            extension_mismatch: None,
+        }
+    }
+
+    fn result(name: &str, success: bool, deferred: Option<DeferReason>) -> RenameResult {
+        RenameResult {
+            original_path: PathBuf::from("/photos").join(name),
+            new_name: "Harbor.jpg".to_string(),
+            new_path: PathBuf::from("/photos/Harbor.jpg"),
+            collision_suffix: None,
+            collision_strategy: None,
+            rollback_token: None,
+            success,
+            error: (!success).then(|| "Not renamed".to_string()),
+            error_kind: None,
+            deferred,
+            batch_id: None,
+            metadata_written: false,
+            companions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_exit_code_values() {
+        // Scripts depend on these numbers
+        assert_eq!(ExitCode::Success as i32, 0);
+        assert_eq!(ExitCode::PartialFailure as i32, 1);
+        assert_eq!(ExitCode::NothingRenameable as i32, 2);
+        assert_eq!(ExitCode::DependencyMissing as i32, 3);
+        assert_eq!(ExitCode::BadArguments as i32, 4);
+        assert_eq!(ExitCode::Error as i32, 5);
+    }
+
+    #[test]
+    fn test_summary_counts_and_exit_code() {
+        let analyses = vec![
+            analysis("a.jpg", Some("Harbor.jpg"), None),
+            analysis("b.jpg", Some("Sunset.jpg"), None),
+            analysis("c.jpg", Some("Beach.jpg"), None),
+            analysis("d.jpg", None, None),
+            analysis("e.jpg.part", None, Some(DeferReason::PartialDownload)),
+        ];
+
+        let results = vec![
+            result("a.jpg", true, None),
+            result("b.jpg", false, None),
+            result("c.jpg", false, Some(DeferReason::Locked)),
+        ];
+        let summary = Summary::new(&analyses, &results);
+        assert_eq!(summary, Summary { renamed: 1, skipped: 1, failed: 1, deferred: 2 });
+        assert_eq!(summary.to_string(), "renamed=1 skipped=1 failed=1 deferred=2");
+        assert_eq!(summary.exit_code(), ExitCode::PartialFailure);
+
+        let results = vec![result("a.jpg", true, None), result("b.jpg", true, None)];
+        let summary = Summary::new(&analyses, &results);
+        assert_eq!(summary.to_string(), "renamed=2 skipped=2 failed=0 deferred=1");
+        assert_eq!(summary.exit_code(), ExitCode::Success);
+
+        // Nothing renamed and nothing failed: only deferred or unnamed files
+        let summary = Summary::new(&analyses, &[result("c.jpg", false, Some(DeferReason::Locked))]);
+        assert_eq!(summary.to_string(), "renamed=0 skipped=3 failed=0 deferred=2");
+        assert_eq!(summary.exit_code(), ExitCode::NothingRenameable);
+
+        let summary = Summary::new(&[], &[]);
+        assert_eq!(summary.to_string(), "renamed=0 skipped=0 failed=0 deferred=0");
+        assert_eq!(summary.exit_code(), ExitCode::NothingRenameable);
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use nameback_core::FileAnalysis;
+use std::io::{BufRead, Write};
+
+/// Rows listed at once; narrow the list with a search to reach the rest
+const PAGE_SIZE: usize = 30;
+
+const HELP: &str = "\
+  /text     fuzzy-search old and new names (/ alone clears the search)
+  3 5-8     toggle the listed files with these numbers
+  a n i     select all, none, or invert (applies to the listed files)
+  Enter     rename the selected files
+  q         quit without renaming anything";
+
+/// A proposal and whether it will be applied
+struct Entry<'a> {
+    analysis: &'a FileAnalysis,
+    /// Old and new name, lowercased for matching
+    haystack: String,
+    selected: bool,
+}
+
+/// Lets the user search and toggle which proposed renames to apply
+/// Everything starts selected, as in the GUI; returns the chosen analyses
+pub fn pick(analyses: &[FileAnalysis]) -> Result<Vec<FileAnalysis>> {
+    let mut entries: Vec<Entry> = analyses
+        .iter()
+        .filter(|a| a.deferred.is_none())
+        .filter_map(|analysis| {
+            let proposed = analysis.proposed_name.as_ref()?;
+            Some(Entry {
+                analysis,
+                haystack: format!("{} {}", analysis.original_name, proposed).to_lowercase(),
+                selected: true,
+            })
+        })
+        .collect();
+
+    if entries.is_empty() {
+        println!("No proposed renames to pick from.");
+        return Ok(Vec::new());
+    }
+
+    let stdin = std::io::stdin();
+    let mut query = String::new();
+    println!("\n{}", HELP);
+
+    loop {
+        let view = filter(&entries, &query);
+        print_view(&entries, &view, &query);
+
+        print!("pick> ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            // End of input: treat like quitting
+            return Ok(Vec::new());
+        }
+
+        match line.trim() {
+            "" => break,
+            "q" => return Ok(Vec::new()),
+            "?" | "h" => println!("{}", HELP),
+            "a" => view.iter().for_each(|&i| entries[i].selected = true),
+            "n" => view.iter().for_each(|&i| entries[i].selected = false),
+            "i" => view.iter().for_each(|&i| entries[i].selected = !entries[i].selected),
+            command if command.starts_with('/') => query = command[1..].trim().to_lowercase(),
+            command => match parse_numbers(command, view.len()) {
+                Some(numbers) => {
+                    for number in numbers {
+                        let index = view[number - 1];
+                        entries[index].selected = !entries[index].selected;
+                    }
+                }
+                None => println!("Unrecognized input (? for help)"),
+            },
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.selected)
+        .map(|e| e.analysis.clone())
+        .collect())
+}
+
+/// Indices of entries matching `query`, best matches first (all entries, in order, when empty)
+fn filter(entries: &[Entry], query: &str) -> Vec<usize> {
+    let mut scored: Vec<(usize, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| Some((i, fuzzy_score(query, &e.haystack)?)))
+        .collect();
+    scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+fn print_view(entries: &[Entry], view: &[usize], query: &str) {
+    let selected = entries.iter().filter(|e| e.selected).count();
+    println!();
+    if query.is_empty() {
+        println!("{} of {} selected", selected, entries.len());
+    } else {
+        println!(
+            "{} of {} selected, {} matching \"{}\"",
+            selected,
+            entries.len(),
+            view.len(),
+            query
+        );
+    }
+
+    for (number, &index) in view.iter().enumerate().take(PAGE_SIZE) {
+        let entry = &entries[index];
+        println!(
+            "  [{}] {:>3}  {} → {}",
+            if entry.selected { "x" } else { " " },
+            number + 1,
+            entry.analysis.original_name,
+            entry.analysis.proposed_name.as_deref().unwrap_or_default()
+        );
+    }
+    if view.len() > PAGE_SIZE {
+        println!("  … and {} more (narrow the list with /text)", view.len() - PAGE_SIZE);
+    }
+}
+
+/// Subsequence match of `query` in `text` (both lowercase)
+/// Consecutive characters and matches at word starts score higher
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut wanted = query.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in text.chars() {
+        let Some(&next) = wanted.peek() else { break };
+        if c == next {
+            wanted.next();
+            score += 1;
+            if previous_matched {
+                score += 5;
+            }
+            if previous.is_none_or(|p| matches!(p, ' ' | '_' | '-' | '.' | '/')) {
+                score += 3;
+            }
+            previous_matched = true;
+        } else {
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+
+    wanted.peek().is_none().then_some(score)
+}
+
+/// Numbers and ranges like "3 5-8,10", each within 1..=max
+fn parse_numbers(input: &str, max: usize) -> Option<Vec<usize>> {
+    let mut numbers = Vec::new();
+    for part in input.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let (start, end): (usize, usize) = match part.split_once('-') {
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None => {
+                let n = part.parse().ok()?;
+                (n, n)
+            }
+        };
+        if start == 0 || start > end || end > max {
+            return None;
+        }
+        numbers.extend(start..=end);
+    }
+    (!numbers.is_empty()).then_some(numbers)
+}
@@ -0,0 +1,102 @@
+use nameback_core::Progress;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Characters in the bar itself
+const BAR_WIDTH: usize = 24;
+
+/// File names longer than this are shortened so the line fits an 80-column terminal
+const MAX_NAME_WIDTH: usize = 18;
+
+/// Redraws closer together than this are skipped, so fast runs don't flood the terminal
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One-line progress bar redrawn in place on stderr while a directory is analyzed
+pub struct ProgressBar {
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    /// A bar if stderr is a terminal that only gets occasional log lines
+    /// (not with --verbose or JSON logs, whose output would break it up)
+    pub fn for_stderr(args: &crate::cli::Args) -> Option<Self> {
+        let shown = std::io::stderr().is_terminal()
+            && !args.verbose
+            && args.log_format == crate::cli::LogFormat::Text;
+        shown.then_some(Self { last_draw: None })
+    }
+
+    pub fn update(&mut self, progress: &Progress) {
+        let finished = progress.done == progress.total;
+        if !finished && self.last_draw.is_some_and(|last| last.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{}", render(progress));
+        let _ = stderr.flush();
+    }
+
+    /// Clears the bar so the output that follows starts on a clean line
+    pub fn finish(self) {
+        if self.last_draw.is_some() {
+            let mut stderr = std::io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+/// `[#########---------------]  12/40  ocr  IMG_0042.jpg  0:12, ~0:28 left`
+fn render(progress: &Progress) -> String {
+    let filled = ((progress.fraction() * BAR_WIDTH as f32) as usize).min(BAR_WIDTH);
+    let mut line = format!(
+        "[{}{}] {:>width$}/{}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        progress.done,
+        progress.total,
+        width = progress.total.to_string().len()
+    );
+
+    if progress.done < progress.total {
+        if let Some(stage) = progress.stage {
+            line.push_str(&format!("  {}", stage.name()));
+        }
+        let name = progress
+            .current_file
+            .as_deref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy());
+        if let Some(name) = name {
+            line.push_str(&format!("  {}", fit(&name)));
+        }
+    }
+
+    line.push_str(&format!("  {}", format_duration(progress.elapsed)));
+    if let Some(eta) = progress.eta.filter(|_| progress.done < progress.total) {
+        line.push_str(&format!(", ~{} left", format_duration(eta)));
+    }
+    line
+}
+
+/// Shortens a name to `MAX_NAME_WIDTH` characters, keeping its end (and extension) visible
+fn fit(name: &str) -> String {
+    let len = name.chars().count();
+    if len <= MAX_NAME_WIDTH {
+        return name.to_string();
+    }
+    let tail: String = name.chars().skip(len - (MAX_NAME_WIDTH - 1)).collect();
+    format!("…{}", tail)
+}
+
+/// `0:05`, `12:34` or `1:02:03`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use nameback_core::FileAnalysis;
+use std::io::{BufRead, Write};
+
+/// Answer to the per-file prompt
+enum Choice {
+    Accept,
+    Skip,
+    Edit,
+    AcceptAll,
+    Quit,
+}
+
+/// Walks through each proposed rename, asking to accept, skip or edit it
+/// Returns the accepted analyses, with edited names in `proposed_name`
+pub fn review(analyses: &[FileAnalysis]) -> Result<Vec<FileAnalysis>> {
+    let proposals: Vec<&FileAnalysis> = analyses
+        .iter()
+        .filter(|a| a.proposed_name.is_some() && a.deferred.is_none())
+        .collect();
+
+    if proposals.is_empty() {
+        println!("No proposed renames to review.");
+        return Ok(Vec::new());
+    }
+
+    let mut accepted = Vec::new();
+    for (position, &analysis) in proposals.iter().enumerate() {
+        let proposed = analysis.proposed_name.as_deref().unwrap_or_default();
+        println!(
+            "\n[{}/{}] {}\n   → {}",
+            position + 1,
+            proposals.len(),
+            analysis.original_path.display(),
+            proposed
+        );
+
+        let choice = loop {
+            let answer = prompt("Rename? [Y]es / [n]o / [e]dit / [a]ll remaining / [q]uit: ")?;
+            match answer.as_deref().map(str::to_lowercase).as_deref() {
+                None | Some("q") => break Choice::Quit,
+                Some("" | "y") => break Choice::Accept,
+                Some("n") => break Choice::Skip,
+                Some("e") => break Choice::Edit,
+                Some("a") => break Choice::AcceptAll,
+                Some(_) => println!("Please answer y, n, e, a or q"),
+            }
+        };
+
+        match choice {
+            Choice::Accept => accepted.push(analysis.clone()),
+            Choice::Skip => {}
+            Choice::Edit => {
+                if let Some(name) = edit_name(analysis)? {
+                    accepted.push(FileAnalysis {
+                        proposed_name: Some(name),
+                        collision_suffix: None,
+                        collision_strategy: None,
+                        ..analysis.clone()
+                    });
+                }
+            }
+            Choice::AcceptAll => {
+                accepted.extend(proposals[position..].iter().map(|&a| a.clone()));
+                break;
+            }
+            Choice::Quit => {
+                println!("Quitting without renaming anything.");
+                return Ok(Vec::new());
+            }
+        }
+    }
+
+    Ok(accepted)
+}
+
+/// Asks for a replacement name until it is usable; None skips the file
+fn edit_name(analysis: &FileAnalysis) -> Result<Option<String>> {
+    let proposed = analysis.proposed_name.as_deref().unwrap_or_default();
+    loop {
+        let Some(input) = prompt(&format!("New name (empty keeps {}, - skips): ", proposed))? else {
+            return Ok(None);
+        };
+        if input.is_empty() {
+            return Ok(Some(proposed.to_string()));
+        }
+        if input == "-" {
+            return Ok(None);
+        }
+        // A dot in the typed name ("Invoice 2023.10") isn't taken for an extension, so the
+        // file's own is added; say so rather than renaming to something the user didn't type
+        match nameback_core::check_filename(&input, &analysis.original_path) {
+            Ok(name) => {
+                if name != input {
+                    println!("Using {}", name);
+                }
+                return Ok(Some(name));
+            }
+            Err(problem) => println!("{}", problem),
+        }
+    }
+}
+
+/// Prints `question` and reads a trimmed answer (None at end of input)
+fn prompt(question: &str) -> Result<Option<String>> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
@@ -0,0 +1,269 @@
+use anstyle::{AnsiColor, Style};
+use nameback_core::{FileAnalysis, PlannedRename, RenamePlan, RenameResult};
+use std::collections::{BTreeMap, HashMap};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+/// Names longer than this are shortened so the columns stay readable
+const MAX_NAME_WIDTH: usize = 48;
+
+/// What happened (or in dry-run mode, would happen) to a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Status {
+    Proposed,
+    Renamed,
+    Failed,
+    Conflict,
+    Skipped,
+    Deferred,
+}
+
+impl Status {
+    /// Footer order
+    const ALL: [Status; 6] = [
+        Status::Proposed,
+        Status::Renamed,
+        Status::Failed,
+        Status::Conflict,
+        Status::Skipped,
+        Status::Deferred,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Status::Proposed => "proposed",
+            Status::Renamed => "renamed",
+            Status::Failed => "failed",
+            Status::Conflict => "conflict",
+            Status::Skipped => "skipped",
+            Status::Deferred => "deferred",
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Status::Proposed => AnsiColor::Cyan.on_default(),
+            Status::Renamed => AnsiColor::Green.on_default(),
+            Status::Failed => AnsiColor::Red.on_default().bold(),
+            Status::Conflict => AnsiColor::Magenta.on_default().bold(),
+            Status::Skipped => Style::new().dimmed(),
+            Status::Deferred => AnsiColor::Yellow.on_default(),
+        }
+    }
+}
+
+/// One line of the table
+struct Row {
+    old: String,
+    new: String,
+    status: Status,
+    /// Error or deferral reason shown after the status
+    note: Option<String>,
+    /// Problem that doesn't stop the rename, e.g. a name differing only in case from another
+    warning: Option<String>,
+}
+
+/// Prints results as old → new columns grouped by subdirectory, with a totals footer
+/// Renames refused by the `plan` show as conflicts; case-only clashes are flagged
+/// Colors are used only when stdout is a terminal and NO_COLOR is unset
+pub fn print_table(
+    directory: &Path,
+    analyses: &[FileAnalysis],
+    results: &[RenameResult],
+    plan: &RenamePlan,
+    dry_run: bool,
+) {
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let paint = |style: Style, text: &str| {
+        if color {
+            format!("{style}{text}{style:#}")
+        } else {
+            text.to_string()
+        }
+    };
+
+    let results: HashMap<&Path, &RenameResult> = results
+        .iter()
+        .map(|r| (r.original_path.as_path(), r))
+        .collect();
+
+    let mut groups: BTreeMap<PathBuf, Vec<Row>> = BTreeMap::new();
+    let mut totals: HashMap<Status, usize> = HashMap::new();
+    for analysis in analyses {
+        let result = results.get(analysis.original_path.as_path()).copied();
+        let planned = plan.get(&analysis.original_path);
+        let row = row(directory, analysis, result, planned, dry_run);
+        *totals.entry(row.status).or_default() += 1;
+        let subdirectory = analysis
+            .original_path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(directory).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        groups.entry(subdirectory).or_default().push(row);
+    }
+
+    let old_width = column_width(groups.values().flatten().map(|r| r.old.as_str()));
+    let new_width = column_width(groups.values().flatten().map(|r| r.new.as_str()));
+
+    for (subdirectory, mut rows) in groups {
+        rows.sort_by(|a, b| a.old.cmp(&b.old));
+
+        let heading = if subdirectory.as_os_str().is_empty() {
+            directory.display().to_string()
+        } else {
+            format!("{}/", subdirectory.display())
+        };
+        println!("\n{}", paint(Style::new().bold(), &heading));
+
+        for row in rows {
+            let arrow = if row.new.is_empty() { " " } else { "→" };
+            let mut line = format!(
+                "  {:<old_width$}  {}  {:<new_width$}  {}",
+                fit(&row.old),
+                arrow,
+                fit(&row.new),
+                paint(row.status.style(), row.status.label()),
+            );
+            if let Some(note) = &row.note {
+                line.push_str(&paint(Style::new().dimmed(), &format!("  {}", note)));
+            }
+            if let Some(warning) = &row.warning {
+                line.push_str(&paint(AnsiColor::Yellow.on_default(), &format!("  ⚠ {}", warning)));
+            }
+            println!("{}", line.trim_end());
+        }
+    }
+
+    let counts: Vec<String> = Status::ALL
+        .iter()
+        .filter_map(|status| {
+            let count = *totals.get(status)?;
+            Some(paint(status.style(), &format!("{} {}", count, status.label())))
+        })
+        .collect();
+    println!(
+        "\n{}: {}",
+        paint(Style::new().bold(), &format!("{} files", analyses.len())),
+        counts.join(", ")
+    );
+}
+
+/// The table row for one analyzed file, its rename result if it was attempted, and its
+/// entry in the batch plan
+fn row(
+    directory: &Path,
+    analysis: &FileAnalysis,
+    result: Option<&RenameResult>,
+    planned: Option<&PlannedRename>,
+    dry_run: bool,
+) -> Row {
+    let old = analysis.original_name.clone();
+    // Organized files show where they go, relative to the directory when inside it
+    let new = |result: &RenameResult| match analysis.destination {
+        Some(_) => result
+            .new_path
+            .strip_prefix(directory)
+            .unwrap_or(&result.new_path)
+            .display()
+            .to_string(),
+        None => result.new_name.clone(),
+    };
+
+    if let Some(reason) = analysis.deferred.or(result.and_then(|r| r.deferred)) {
+        return Row {
+            old,
+            new: String::new(),
+            status: Status::Deferred,
+            note: Some(reason.to_string()),
+            warning: None,
+        };
+    }
+
+    let blocking = planned.and_then(PlannedRename::blocking_conflict);
+    let warning = planned
+        .filter(|_| blocking.is_none())
+        .and_then(|p| p.conflicts.first())
+        .map(|conflict| conflict.to_string());
+
+    match result {
+        None => Row {
+            old,
+            new: String::new(),
+            status: Status::Skipped,
+            note: analysis
+                .duplicate_of
+                .as_ref()
+                .map(|original| {
+                    let name = original.file_name().unwrap_or(original.as_os_str());
+                    format!("duplicate of {}", name.to_string_lossy())
+                })
+                .or_else(|| analysis.over_limit.map(|limit| limit.to_string())),
+            warning: None,
+        },
+        Some(result) if !result.success => match blocking {
+            Some(conflict) => Row {
+                old,
+                new: new(result),
+                status: Status::Conflict,
+                note: Some(conflict.to_string()),
+                warning: None,
+            },
+            None => Row {
+                old,
+                new: new(result),
+                status: Status::Failed,
+                note: result.error.clone(),
+                warning,
+            },
+        },
+        Some(result) => Row {
+            old,
+            new: new(result),
+            status: if dry_run { Status::Proposed } else { Status::Renamed },
+            note: success_note(result, analysis),
+            warning,
+        },
+    }
+}
+
+/// Extras done along with a rename: sidecars moved with the file, title written, and the
+/// extension when it didn't match the content
+fn success_note(result: &RenameResult, analysis: &FileAnalysis) -> Option<String> {
+    let mut notes = Vec::new();
+    if let Some(mismatch) = &analysis.extension_mismatch {
+        let handling = if mismatch.fixed { "extension fixed" } else { "extension kept" };
+        notes.push(format!("{}, {}", mismatch, handling));
+    }
+    if !result.companions.is_empty() {
+        let names: Vec<String> = result
+            .companions
+            .iter()
+            .map(|(_, new_path)| new_path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .collect();
+        notes.push(format!("with {}", names.join(", ")));
+    }
+    if result.metadata_written {
+        notes.push("title written to metadata".to_string());
+    }
+    (!notes.is_empty()).then(|| notes.join("; "))
+}
+
+/// Width of a name column: the longest name, capped at `MAX_NAME_WIDTH`
+fn column_width<'a>(names: impl Iterator<Item = &'a str>) -> usize {
+    names
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_NAME_WIDTH)
+}
+
+/// Shortens a name to `MAX_NAME_WIDTH` characters, keeping its end (and extension) visible
+fn fit(name: &str) -> String {
+    let len = name.chars().count();
+    if len <= MAX_NAME_WIDTH {
+        return name.to_string();
+    }
+    let tail: String = name.chars().skip(len - (MAX_NAME_WIDTH - 1)).collect();
+    format!("…{}", tail)
+}
@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use nameback_core::{RenameEngine, StopSignal, WatchEvent, WatchOptions};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cli::Args;
+use crate::outcome::Summary;
+
+/// Renames new files in `directory` as they arrive, until Ctrl-C
+/// Returns what happened to the files that arrived; files left as is after retrying count as
+/// deferred
+pub fn watch(engine: &RenameEngine, directory: &Path, args: &Args) -> Result<Summary> {
+    let options = WatchOptions {
+        settle: Duration::try_from_secs_f64(args.settle)
+            .with_context(|| format!("Invalid --settle value: {}", args.settle))?,
+        dry_run: args.dry_run,
+        ..WatchOptions::default()
+    };
+
+    let stop = StopSignal::new();
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || handler_stop.stop()).context("Failed to set up Ctrl-C handling")?;
+
+    tracing::info!(
+        "Watching {} for new files (Ctrl-C to stop)",
+        directory.display()
+    );
+    let mut summary = Summary {
+        renamed: 0,
+        skipped: 0,
+        failed: 0,
+        deferred: 0,
+    };
+    engine.watch(directory, &options, &stop, |event| match event {
+        WatchEvent::Renamed(result) if result.success => {
+            summary.renamed += 1;
+            let verb = if args.dry_run { "Would rename" } else { "Renamed" };
+            tracing::info!(
+                "{} {} → {}",
+                verb,
+                result.original_path.display(),
+                result.new_name
+            );
+        }
+        WatchEvent::Renamed(result) => {
+            summary.failed += 1;
+            tracing::warn!(
+                "Failed to rename {}: {}",
+                result.original_path.display(),
+                result.error.as_deref().unwrap_or("unknown error")
+            )
+        }
+        WatchEvent::Unnamed(analysis) => {
+            summary.skipped += 1;
+            tracing::info!("No suitable name found for {}", analysis.original_path.display())
+        }
+        WatchEvent::Retrying {
+            path,
+            attempt,
+            reason,
+        } => tracing::info!(
+            "{} is {}, trying again later (attempt {})",
+            path.display(),
+            reason,
+            attempt
+        ),
+        WatchEvent::GaveUp { path, reason } => {
+            summary.deferred += 1;
+            tracing::warn!("Left {} as is ({})", path.display(), reason)
+        }
+    })?;
+
+    tracing::info!("Stopped watching; renamed {} files", summary.renamed);
+    Ok(summary)
+}
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::tool_command::ToolCommand;
+
+/// AcoustID lookup endpoint
+const LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+
+/// AcoustID allows 3 requests per second per client
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+
+/// Matches scoring lower than this are more often wrong than right
+const MIN_MATCH_SCORE: f64 = 0.7;
+
+// Time of the last lookup, shared by the analysis threads
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Chromaprint fingerprint of the first two minutes of a track, as printed by `fpcalc -json`
+#[derive(Debug, Deserialize)]
+struct Fingerprint {
+    duration: f64,
+    fingerprint: String,
+}
+
+/// AcoustID lookup response structure
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    status: String,
+    #[serde(default)]
+    results: Vec<LookupResult>,
+    error: Option<LookupError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupResult {
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Recording {
+    title: Option<String>,
+    #[serde(default)]
+    artists: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artist {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupError {
+    message: String,
+}
+
+/// Identifies an audio file by its Chromaprint fingerprint (fpcalc) and the AcoustID web
+/// service, using the given API key
+/// Returns a name like "Artist - Title", or None when fpcalc isn't installed or nothing matched
+pub fn identify_track(path: &Path, api_key: &str) -> Result<Option<String>> {
+    let fpcalc = crate::deps_check::Dependency::Chromaprint;
+    let Some(version) = fpcalc.version() else {
+        debug!("fpcalc not available, skipping audio fingerprinting");
+        return Ok(None);
+    };
+    if let Some(outdated) = fpcalc.check_version(&version) {
+        debug!("{}, skipping audio fingerprinting", outdated);
+        return Ok(None);
+    }
+
+    let fingerprint = fingerprint(path)?;
+    wait_for_rate_limit();
+    let response = lookup(&fingerprint, api_key)?;
+    best_match(response)
+}
+
+/// Runs fpcalc on the file
+fn fingerprint(path: &Path) -> Result<Fingerprint> {
+    let output = crate::deps_check::create_command("fpcalc")
+        .arg("-json")
+        .arg(path)
+        .tool_output()
+        .context("Failed to run fpcalc")?;
+
+    if !output.status.success() {
+        anyhow::bail!("fpcalc failed with status: {}", output.status);
+    }
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse fpcalc JSON output")
+}
+
+/// Waits until another request is allowed
+fn wait_for_rate_limit() {
+    let mut last_request = LAST_REQUEST.lock().unwrap();
+    if let Some(elapsed) = last_request.map(|last| last.elapsed()) {
+        if elapsed < MIN_REQUEST_INTERVAL {
+            std::thread::sleep(MIN_REQUEST_INTERVAL - elapsed);
+        }
+    }
+    *last_request = Some(Instant::now());
+}
+
+/// Make the actual API request to AcoustID
+fn lookup(fingerprint: &Fingerprint, api_key: &str) -> Result<LookupResponse> {
+    let user_agent = format!(
+        "Nameback/{} (https://github.com/h4x0r/nameback)",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    debug!("Looking up fingerprint ({:.0}s) via AcoustID", fingerprint.duration);
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    // Fingerprints run to a few kilobytes, so they go in a POST body rather than the URL
+    let duration = (fingerprint.duration.round() as u64).to_string();
+    let response = client
+        .post(LOOKUP_URL)
+        .form(&[
+            ("client", api_key),
+            ("meta", "recordings"),
+            ("duration", duration.as_str()),
+            ("fingerprint", fingerprint.fingerprint.as_str()),
+        ])
+        .send()
+        .context("Failed to send AcoustID request")?;
+
+    // Errors such as an invalid API key come back as JSON with a 400 status
+    response.json().context("Failed to parse AcoustID response")
+}
+
+/// Picks the best-scoring recording that has both a title and an artist
+fn best_match(response: LookupResponse) -> Result<Option<String>> {
+    if response.status != "ok" {
+        let message = response.error.map(|e| e.message).unwrap_or(response.status);
+        anyhow::bail!("AcoustID lookup failed: {}", message);
+    }
+
+    let mut results = response.results;
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok(results
+        .into_iter()
+        .filter(|result| result.score >= MIN_MATCH_SCORE)
+        .flat_map(|result| result.recordings)
+        .find_map(|recording| {
+            let title = recording.title.filter(|t| !t.trim().is_empty())?;
+            let artist = recording.artists.first()?.name.trim().to_string();
+            if artist.is_empty() {
+                return None;
+            }
+            Some(format!("{} - {}", artist, title.trim()))
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> LookupResponse {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_best_match() {
+        let response = parse(
+            r#"{"status": "ok", "results": [
+                {"id": "a", "score": 0.42, "recordings": [{"title": "Wrong", "artists": [{"name": "Nobody"}]}]},
+                {"id": "b", "score": 0.95, "recordings": [
+                    {"id": "r1"},
+                    {"title": "Bohemian Rhapsody", "artists": [{"name": "Queen"}, {"name": "Other"}]}
+                ]}
+            ]}"#,
+        );
+        assert_eq!(best_match(response).unwrap().as_deref(), Some("Queen - Bohemian Rhapsody"));
+
+        // Only low-scoring matches
+        let response = parse(
+            r#"{"status": "ok", "results": [{"score": 0.5, "recordings": [{"title": "T", "artists": [{"name": "A"}]}]}]}"#,
+        );
+        assert_eq!(best_match(response).unwrap(), None);
+
+        assert_eq!(best_match(parse(r#"{"status": "ok", "results": []}"#)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_best_match_reports_api_errors() {
+        let response = parse(r#"{"status": "error", "error": {"code": 4, "message": "invalid API key"}}"#);
+        let error = best_match(response).unwrap_err();
+        assert!(error.to_string().contains("invalid API key"));
+    }
+
+    #[test]
+    fn test_parse_fpcalc_output() {
+        let fingerprint: Fingerprint =
+            serde_json::from_str(r#"{"duration": 354.07, "fingerprint": "AQADtNIyRUkkHYnE"}"#).unwrap();
+        assert_eq!(fingerprint.duration.round() as u64, 354);
+        assert_eq!(fingerprint.fingerprint, "AQADtNIyRUkkHYnE");
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Per-user data directory for nameback (logs, history, caches)
+/// - Windows: %LOCALAPPDATA%\nameback
+/// - macOS: ~/Library/Application Support/nameback
+/// - Linux: $XDG_DATA_HOME/nameback or ~/.local/share/nameback
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| home_dir().map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|dir| dir.join("nameback"))
+}
+
+/// Per-user configuration directory for nameback
+/// - Windows: %APPDATA%\nameback
+/// - macOS: ~/Library/Application Support/nameback
+/// - Linux: $XDG_CONFIG_HOME/nameback or ~/.config/nameback
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| home_dir().map(|home| home.join(".config")))
+    };
+
+    base.map(|dir| dir.join("nameback"))
+}
+
+/// Per-user cache directory for nameback (data that can be rebuilt at any time)
+/// - Windows: %LOCALAPPDATA%\nameback\cache
+/// - macOS: ~/Library/Caches/nameback
+/// - Linux: $XDG_CACHE_HOME/nameback or ~/.cache/nameback
+pub fn cache_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return data_dir().map(|dir| dir.join("cache"));
+    }
+    let base = if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| home_dir().map(|home| home.join(".cache")))
+    };
+
+    base.map(|dir| dir.join("nameback"))
+}
+
+/// Directory for application log files
+pub fn log_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Current user's home directory from the environment
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var).map(PathBuf::from).filter(|p| !p.as_os_str().is_empty())
+}
@@ -0,0 +1,148 @@
+/// Manufacturer names as written in EXIF Make, mapped to a short brand name
+/// Compared case-insensitively; unlisted makes keep their first word
+const MAKERS: &[(&str, &str)] = &[
+    ("NIKON CORPORATION", "Nikon"),
+    ("NIKON", "Nikon"),
+    ("Canon", "Canon"),
+    ("SONY", "Sony"),
+    ("FUJIFILM", "Fujifilm"),
+    ("OLYMPUS IMAGING CORP.", "Olympus"),
+    ("OLYMPUS CORPORATION", "Olympus"),
+    ("OM Digital Solutions", "OM"),
+    ("Panasonic", "Panasonic"),
+    ("LEICA CAMERA AG", "Leica"),
+    ("RICOH IMAGING COMPANY, LTD.", "Ricoh"),
+    ("PENTAX Corporation", "Pentax"),
+    ("PENTAX", "Pentax"),
+    ("Hasselblad", "Hasselblad"),
+    ("SIGMA", "Sigma"),
+    ("Apple", "Apple"),
+    ("samsung", "Samsung"),
+    ("Google", "Google"),
+    ("DJI", "DJI"),
+    ("GoPro", "GoPro"),
+];
+
+/// Short brand name for an EXIF Make value
+fn brand(make: &str) -> Option<String> {
+    let make = make.trim();
+    if let Some((_, brand)) = MAKERS.iter().find(|(name, _)| name.eq_ignore_ascii_case(make)) {
+        return Some(brand.to_string());
+    }
+    make.split_whitespace()
+        .next()
+        .map(|word| word.trim_end_matches([',', '.']).to_string())
+        .filter(|word| !word.is_empty())
+}
+
+/// Keep only characters that are safe and readable in a filename
+fn clean_token(token: &str) -> String {
+    token
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '.'))
+        .collect()
+}
+
+/// Camera name for filenames from EXIF Make and Model
+/// e.g. "NIKON CORPORATION" + "NIKON Z 6" becomes "Nikon_Z6"
+pub fn normalize_camera(make: Option<&str>, model: Option<&str>) -> Option<String> {
+    let brand = make.and_then(brand);
+
+    let mut words: Vec<&str> = model.unwrap_or_default().split_whitespace().collect();
+    // Models usually repeat the make ("NIKON Z 6", "Canon EOS R5")
+    let make_word = make.and_then(|m| m.split_whitespace().next());
+    if let Some(first) = words.first() {
+        let repeats_make = make_word.is_some_and(|w| w.eq_ignore_ascii_case(first))
+            || brand.as_deref().is_some_and(|b| b.eq_ignore_ascii_case(first));
+        if repeats_make {
+            words.remove(0);
+        }
+    }
+
+    // Rejoin series letters split from their number ("Z 6" -> "Z6")
+    let mut tokens: Vec<String> = Vec::new();
+    for word in words {
+        let token = clean_token(word);
+        if token.is_empty() {
+            continue;
+        }
+        match tokens.last_mut() {
+            Some(last)
+                if last.chars().count() == 1
+                    && last.chars().all(char::is_alphabetic)
+                    && token.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                last.push_str(&token)
+            }
+            _ => tokens.push(token),
+        }
+    }
+
+    let model = (!tokens.is_empty()).then(|| tokens.join("_"));
+    match (brand, model) {
+        (Some(brand), Some(model)) => Some(format!("{}_{}", brand, model)),
+        (brand, model) => brand.or(model),
+    }
+}
+
+/// Lens name for filenames from EXIF LensModel
+/// e.g. "NIKKOR Z 24-70mm f/4 S" becomes "NIKKOR_Z_24-70mm_f4_S"
+pub fn normalize_lens(lens: &str) -> Option<String> {
+    let tokens: Vec<String> = lens
+        .split_whitespace()
+        .map(clean_token)
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    // Placeholders like "----" mean the camera didn't know the lens
+    if !tokens.iter().any(|t| t.chars().any(char::is_alphanumeric)) {
+        return None;
+    }
+    Some(tokens.join("_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_camera() {
+        let camera = |make, model| normalize_camera(make, model);
+        assert_eq!(
+            camera(Some("NIKON CORPORATION"), Some("NIKON Z 6")),
+            Some("Nikon_Z6".to_string())
+        );
+        assert_eq!(
+            camera(Some("Canon"), Some("Canon EOS R5")),
+            Some("Canon_EOS_R5".to_string())
+        );
+        assert_eq!(
+            camera(Some("SONY"), Some("ILCE-7M3")),
+            Some("Sony_ILCE-7M3".to_string())
+        );
+        assert_eq!(
+            camera(Some("Apple"), Some("iPhone 13 Pro")),
+            Some("Apple_iPhone_13_Pro".to_string())
+        );
+        assert_eq!(
+            camera(Some("Xiaomi Inc."), None),
+            Some("Xiaomi".to_string())
+        );
+        assert_eq!(camera(None, Some("X-T4")), Some("X-T4".to_string()));
+        assert_eq!(camera(None, None), None);
+    }
+
+    #[test]
+    fn test_normalize_lens() {
+        assert_eq!(
+            normalize_lens("NIKKOR Z 24-70mm f/4 S"),
+            Some("NIKKOR_Z_24-70mm_f4_S".to_string())
+        );
+        assert_eq!(
+            normalize_lens("EF24-105mm f/4L IS USM"),
+            Some("EF24-105mm_f4L_IS_USM".to_string())
+        );
+        assert_eq!(normalize_lens("----"), None);
+        assert_eq!(normalize_lens(""), None);
+    }
+}
@@ -1,4 +1,3 @@
-#![allow(dead_code)]
 #![allow(unused_assignments)]
 
 use anyhow::Result;
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::profiling::Stage;
+use crate::{disambiguate, AnalysisIssue, FileAnalysis, NameStyle};
+
+/// Length of the content-hash suffix
+const HASH_SUFFIX_LEN: usize = 6;
+
+/// What happens when a file's proposed name is already taken by another file's
+/// The file that claimed the name first keeps it either way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionStrategy {
+    /// Add a detail from the file's content that tells it apart (sender, amount, or a short
+    /// content hash), or a `_1`, `_2`, … counter when nothing does
+    #[default]
+    Content,
+    /// Add a `_1`, `_2`, … counter
+    Number,
+    /// Add the file's modification time, e.g. `_20231015-143022`
+    Timestamp,
+    /// Add a short hash of the file's content, e.g. `_3fa9c1`
+    Hash,
+    /// Propose no name, so the file keeps its original one
+    KeepOriginal,
+    /// Refuse to rename any file of the batch (see `PlanConflict::NameCollisions`)
+    Fail,
+}
+
+impl fmt::Display for CollisionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollisionStrategy::Content => write!(f, "content"),
+            CollisionStrategy::Number => write!(f, "number"),
+            CollisionStrategy::Timestamp => write!(f, "timestamp"),
+            CollisionStrategy::Hash => write!(f, "hash"),
+            CollisionStrategy::KeepOriginal => write!(f, "keep-original"),
+            CollisionStrategy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+impl FromStr for CollisionStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "content" => Ok(CollisionStrategy::Content),
+            "number" => Ok(CollisionStrategy::Number),
+            "timestamp" => Ok(CollisionStrategy::Timestamp),
+            "hash" => Ok(CollisionStrategy::Hash),
+            "keep-original" => Ok(CollisionStrategy::KeepOriginal),
+            "fail" => Ok(CollisionStrategy::Fail),
+            _ => anyhow::bail!(
+                "unknown collision strategy '{}' (expected content, number, timestamp, hash, keep-original or fail)",
+                s
+            ),
+        }
+    }
+}
+
+/// Apply `strategy` to the analyses whose proposed name got a collision counter, and record
+/// the strategy that settled each one in `FileAnalysis::collision_strategy`
+/// Returns the indices of analyses that changed
+pub(crate) fn resolve(
+    analyses: &mut [FileAnalysis],
+    strategy: CollisionStrategy,
+    style: NameStyle,
+    existing_names: &mut HashSet<String>,
+) -> Vec<usize> {
+    let collided: Vec<usize> = (0..analyses.len())
+        .filter(|&index| analyses[index].collision_suffix.is_some())
+        .collect();
+
+    match strategy {
+        CollisionStrategy::Content => {
+            let renamed: HashSet<usize> =
+                disambiguate::disambiguate(analyses, style, existing_names).into_iter().collect();
+            for &index in &collided {
+                analyses[index].collision_strategy = Some(if renamed.contains(&index) {
+                    CollisionStrategy::Content
+                } else {
+                    CollisionStrategy::Number
+                });
+            }
+        }
+        CollisionStrategy::Number | CollisionStrategy::Fail => {
+            for &index in &collided {
+                analyses[index].collision_strategy = Some(strategy);
+            }
+        }
+        CollisionStrategy::Timestamp | CollisionStrategy::Hash => {
+            for &index in &collided {
+                let analysis = &mut analyses[index];
+                let token = match strategy {
+                    CollisionStrategy::Timestamp => modified_token(&analysis.original_path),
+                    _ => hash_token(&analysis.original_path),
+                };
+                if let (Some(token), Some((base, extension))) =
+                    (token, disambiguate::collided_name(analysis, style.separator()))
+                {
+                    let separator = style.separator();
+                    let (name, counter) =
+                        free_name(&format!("{}{}{}", base, separator, token), &extension, separator, existing_names);
+                    if let Some(old) = analysis.proposed_name.replace(name) {
+                        existing_names.remove(&old);
+                    }
+                    analysis.collision_suffix = counter;
+                }
+                analysis.collision_strategy = Some(strategy);
+            }
+        }
+        CollisionStrategy::KeepOriginal => {
+            for &index in &collided {
+                let analysis = &mut analyses[index];
+                if let Some(old) = analysis.proposed_name.take() {
+                    existing_names.remove(&old);
+                }
+                analysis.title = None;
+                analysis.collision_suffix = None;
+                analysis.collision_strategy = Some(strategy);
+                analysis.errors.push(AnalysisIssue::fatal(
+                    Stage::Score,
+                    "Proposed name is taken by another file; keeping the original name",
+                ));
+            }
+        }
+    }
+
+    for &index in &collided {
+        tracing::debug!(
+            "Name collision for {} settled by {}",
+            analyses[index].original_path.display(),
+            strategy
+        );
+    }
+    collided
+}
+
+/// `base` + `extension`, with a counter added if that is taken too; claims the name
+fn free_name(
+    base: &str,
+    extension: &str,
+    separator: &str,
+    existing_names: &mut HashSet<String>,
+) -> (String, Option<u32>) {
+    let mut name = format!("{}{}", base, extension);
+    let mut counter = None;
+    for n in 1.. {
+        if !existing_names.contains(&name) {
+            break;
+        }
+        name = format!("{}{}{}{}", base, separator, n, extension);
+        counter = Some(n);
+    }
+    existing_names.insert(name.clone());
+    (name, counter)
+}
+
+/// The file's modification time, e.g. `20231015-143022` (local time)
+fn modified_token(path: &Path) -> Option<String> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let local: chrono::DateTime<chrono::Local> = modified.into();
+    Some(local.format("%Y%m%d-%H%M%S").to_string())
+}
+
+/// Start of the file's content hash
+fn hash_token(path: &Path) -> Option<String> {
+    crate::metadata_cache::MetadataCache::compute_file_hash(path)
+        .ok()
+        .map(|hash| hash.chars().take(HASH_SUFFIX_LEN).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use tempfile::TempDir;
+
+    /// Three files proposed as `Report.txt`; the first claimed it, the others got counters
+    fn collided(dir: &Path) -> (Vec<FileAnalysis>, HashSet<String>) {
+        let analyses = (0..3)
+            .map(|i| {
+                let path = dir.join(format!("scan_{}.txt", i));
+                std::fs::write(&path, format!("content {}", i)).unwrap();
+                FileAnalysis {
+                    proposed_name: Some(match i {
+                        0 => "Report.txt".to_string(),
+                        _ => format!("Report_{}.txt", i),
+                    }),
+                    collision_suffix: (i > 0).then_some(i),
+                    ..crate::pipeline::unanalyzed(&path, FileCategory::Document)
+                }
+            })
+            .collect();
+        let names = ["Report.txt", "Report_1.txt", "Report_2.txt"].map(String::from).into();
+        (analyses, names)
+    }
+
+    #[test]
+    fn test_number_and_fail_keep_counters() {
+        let temp_dir = TempDir::new().unwrap();
+        for strategy in [CollisionStrategy::Number, CollisionStrategy::Fail] {
+            let (mut analyses, mut names) = collided(temp_dir.path());
+            assert_eq!(resolve(&mut analyses, strategy, NameStyle::default(), &mut names), vec![1, 2]);
+            assert_eq!(analyses[1].proposed_name.as_deref(), Some("Report_1.txt"));
+            assert_eq!(analyses[0].collision_strategy, None);
+            assert_eq!(analyses[2].collision_strategy, Some(strategy));
+        }
+    }
+
+    #[test]
+    fn test_hash_and_timestamp_suffixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut analyses, mut names) = collided(temp_dir.path());
+        resolve(&mut analyses, CollisionStrategy::Hash, NameStyle::default(), &mut names);
+        let hashed = analyses[1].proposed_name.clone().unwrap();
+        assert!(hashed.starts_with("Report_") && hashed.ends_with(".txt"));
+        assert_eq!(hashed.len(), "Report_.txt".len() + HASH_SUFFIX_LEN);
+        assert_ne!(analyses[1].proposed_name, analyses[2].proposed_name);
+        assert_eq!(analyses[1].collision_suffix, None);
+        assert!(names.contains(&hashed) && !names.contains("Report_1.txt"));
+
+        // Files written within the same second share a timestamp, so the second one is numbered
+        let (mut analyses, mut names) = collided(temp_dir.path());
+        resolve(&mut analyses, CollisionStrategy::Timestamp, NameStyle::default(), &mut names);
+        let stamp = modified_token(&analyses[1].original_path).unwrap();
+        assert_eq!(analyses[1].proposed_name, Some(format!("Report_{}.txt", stamp)));
+        assert_eq!(analyses[1].collision_strategy, Some(CollisionStrategy::Timestamp));
+        if modified_token(&analyses[2].original_path).unwrap() == stamp {
+            assert_eq!(analyses[2].proposed_name, Some(format!("Report_{}_1.txt", stamp)));
+            assert_eq!(analyses[2].collision_suffix, Some(1));
+        }
+    }
+
+    #[test]
+    fn test_keep_original_drops_the_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut analyses, mut names) = collided(temp_dir.path());
+        resolve(&mut analyses, CollisionStrategy::KeepOriginal, NameStyle::default(), &mut names);
+        assert_eq!(analyses[0].proposed_name.as_deref(), Some("Report.txt"));
+        assert_eq!(analyses[1].proposed_name, None);
+        assert_eq!(analyses[1].errors.len(), 1);
+        assert!(!names.contains("Report_1.txt"));
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        assert_eq!("keep_original".parse::<CollisionStrategy>().unwrap(), CollisionStrategy::KeepOriginal);
+        assert_eq!(" Hash ".parse::<CollisionStrategy>().unwrap(), CollisionStrategy::Hash);
+        assert_eq!(CollisionStrategy::KeepOriginal.to_string(), "keep-original");
+        assert!("random".parse::<CollisionStrategy>().is_err());
+    }
+}
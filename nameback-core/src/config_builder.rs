@@ -0,0 +1,994 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{
+    BlockedArea, CategoryOverrides, CollisionStrategy, DuplicateHandling, FileCategory, GeocodeSource, FrameAggregation,
+    LocationPrecision, MetadataBackend, MetadataWriteMode, NameCase, OnConflict, RenameConfig, SymlinkPolicy,
+    TimestampPosition, TimestampPrecision, WordSeparator,
+};
+
+/// Most digits series numbers are padded to; wider padding is more likely a typo
+const MAX_SERIES_PADDING: usize = 9;
+
+/// Invalid combination of `RenameConfig` options
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// Geocoding was requested but location is not included in filenames
+    GeocodeWithoutLocation,
+    /// A cache path was given while caching is disabled
+    CachePathWithoutCache,
+    /// The cache path is empty
+    EmptyCachePath,
+    /// The undo journal path is empty
+    EmptyJournalPath,
+    /// The AcoustID API key is empty
+    EmptyAcoustidKey,
+    /// An OCR language isn't a tesseract language name
+    InvalidOcrLanguage(String),
+    /// The filename template uses a variable that doesn't exist
+    UnknownTemplateVariable(String),
+    /// The filename template could place files outside their folder
+    UnsafeTemplate(&'static str),
+    /// The minimum confidence is negative or not a number
+    InvalidMinConfidence,
+    /// The maximum depth is 0, which would find no files
+    InvalidMaxDepth,
+    /// The number of files analyzed at once is 0
+    InvalidMaxParallelFiles,
+    /// The number of OCR and media tools run at once is 0
+    InvalidMaxOcrProcesses,
+    /// The video frame budget is 0
+    InvalidVideoFrames,
+    /// The minimum OCR confidence is over 100
+    InvalidMinOcrConfidence,
+    /// Fewer than 2 files would make a series
+    InvalidSeriesMinFiles,
+    /// Series numbers would be padded to more than `MAX_SERIES_PADDING` digits
+    InvalidSeriesPadding,
+    /// The external tool timeout is 0
+    InvalidToolTimeout,
+    /// A file size, page or duration limit is 0 (named by the `&str`)
+    InvalidLimit(&'static str),
+    /// An include or exclude pattern isn't a valid glob
+    InvalidGlob(String),
+    /// The category list is empty, which would analyze no files
+    NoCategories,
+    /// An organize root was given without an organize folder template
+    OrganizeRootWithoutTemplate,
+    /// Files can't be organized into folders while only their metadata is written
+    OrganizeWithMetadataOnly,
+    /// Folders are named after the files in them, which organizing moves elsewhere
+    RenameDirectoriesWithOrganize,
+    /// The timestamp format isn't a strftime pattern that makes a safe file name
+    InvalidTimestampFormat(String, &'static str),
+    /// Locations were asked for by country or region, which needs geocoding, with it off
+    LocationPrecisionWithoutGeocode,
+    /// A location blocklist area has an impossible position or radius
+    InvalidBlockedArea(String, &'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::GeocodeWithoutLocation => {
+                write!(f, "geocoding requires location to be included in filenames")
+            }
+            ConfigError::CachePathWithoutCache => {
+                write!(f, "a cache path was set but caching is disabled")
+            }
+            ConfigError::EmptyCachePath => write!(f, "cache path is empty"),
+            ConfigError::EmptyJournalPath => write!(f, "undo journal path is empty"),
+            ConfigError::EmptyAcoustidKey => write!(f, "AcoustID API key is empty"),
+            ConfigError::InvalidOcrLanguage(name) => {
+                write!(f, "invalid OCR language '{}' (expected a tesseract name like eng or chi_sim)", name)
+            }
+            ConfigError::UnknownTemplateVariable(name) => write!(
+                f,
+                "unknown template variable {{{}}} (available: {})",
+                name,
+                crate::TEMPLATE_VARIABLES.join(", ")
+            ),
+            ConfigError::UnsafeTemplate(reason) => write!(f, "filename template {}", reason),
+            ConfigError::InvalidMinConfidence => {
+                write!(f, "minimum confidence must be a number of 0 or more")
+            }
+            ConfigError::InvalidMaxDepth => write!(f, "maximum depth must be at least 1"),
+            ConfigError::InvalidMaxParallelFiles => {
+                write!(f, "files analyzed in parallel must be at least 1")
+            }
+            ConfigError::InvalidMaxOcrProcesses => {
+                write!(f, "OCR processes run in parallel must be at least 1")
+            }
+            ConfigError::InvalidVideoFrames => write!(f, "video frames read must be at least 1"),
+            ConfigError::InvalidMinOcrConfidence => {
+                write!(f, "minimum OCR confidence must be between 0 and 100")
+            }
+            ConfigError::InvalidSeriesMinFiles => write!(f, "a series must be at least 2 files"),
+            ConfigError::InvalidSeriesPadding => {
+                write!(f, "series numbers can be padded to at most {} digits", MAX_SERIES_PADDING)
+            }
+            ConfigError::InvalidToolTimeout => write!(f, "tool timeout must be longer than 0 seconds"),
+            ConfigError::InvalidLimit(limit) => write!(f, "{} must be at least 1", limit),
+            ConfigError::InvalidGlob(error) => write!(f, "invalid pattern: {}", error),
+            ConfigError::NoCategories => write!(f, "at least one file category must be enabled"),
+            ConfigError::OrganizeRootWithoutTemplate => {
+                write!(f, "an organize folder was set without an organize template")
+            }
+            ConfigError::OrganizeWithMetadataOnly => {
+                write!(f, "files can't be organized when only their metadata is written")
+            }
+            ConfigError::RenameDirectoriesWithOrganize => {
+                write!(f, "folders can't be renamed while files are organized into other folders")
+            }
+            ConfigError::InvalidTimestampFormat(format, reason) => {
+                write!(f, "timestamp format '{}' {}", format, reason)
+            }
+            ConfigError::LocationPrecisionWithoutGeocode => {
+                write!(f, "naming locations by country or region requires geocoding")
+            }
+            ConfigError::InvalidBlockedArea(area, reason) => {
+                write!(f, "location blocklist area '{}': {}", area, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Builder for `RenameConfig` that rejects contradictory options
+///
+/// Unset options take their `RenameConfig::default()` value. Only options that were
+/// set explicitly are checked against each other, so e.g. turning location off
+/// doesn't conflict with geocoding's default.
+#[derive(Debug, Clone, Default)]
+pub struct RenameConfigBuilder {
+    skip_hidden: Option<bool>,
+    max_depth: Option<usize>,
+    include_globs: Option<Vec<String>>,
+    exclude_globs: Option<Vec<String>>,
+    categories: Option<HashSet<FileCategory>>,
+    include_location: Option<bool>,
+    location_precision: Option<LocationPrecision>,
+    location_blocklist: Option<Vec<BlockedArea>>,
+    include_timestamp: Option<bool>,
+    timestamp_format: Option<String>,
+    timestamp_precision: Option<TimestampPrecision>,
+    timestamp_position: Option<TimestampPosition>,
+    email_attachment_count: Option<bool>,
+    subject_hints: Option<bool>,
+    peek_archives: Option<bool>,
+    receipt_mode: Option<bool>,
+    ocr_languages: Option<Vec<String>>,
+    multiframe_video: Option<bool>,
+    max_parallel_files: Option<usize>,
+    max_ocr_processes: Option<usize>,
+    frame_aggregation: Option<FrameAggregation>,
+    video_frames: Option<usize>,
+    key_phrase_count: Option<usize>,
+    key_phrase_max_words: Option<usize>,
+    min_ocr_confidence: Option<u8>,
+    metadata_backend: Option<MetadataBackend>,
+    geocode: Option<bool>,
+    geocode_source: Option<GeocodeSource>,
+    geocode_database: Option<PathBuf>,
+    acoustid_key: Option<String>,
+    paper_lookup: Option<bool>,
+    enable_cache: Option<bool>,
+    refresh_cache: Option<bool>,
+    cache_path: Option<PathBuf>,
+    journal_path: Option<PathBuf>,
+    template: Option<String>,
+    name_case: Option<NameCase>,
+    word_separator: Option<WordSeparator>,
+    series_min_files: Option<usize>,
+    series_padding: Option<usize>,
+    series_separator: Option<WordSeparator>,
+    series_start: Option<usize>,
+    fix_extensions: Option<bool>,
+    lowercase_extensions: Option<bool>,
+    organize: Option<String>,
+    organize_root: Option<PathBuf>,
+    duplicates: Option<DuplicateHandling>,
+    collision_strategy: Option<CollisionStrategy>,
+    on_conflict: Option<OnConflict>,
+    write_metadata: Option<MetadataWriteMode>,
+    rename_sidecars: Option<bool>,
+    rename_directories: Option<bool>,
+    category_overrides: HashMap<FileCategory, CategoryOverrides>,
+    min_confidence: Option<f32>,
+    never_touch_dns: Option<bool>,
+    tool_timeout: Option<Duration>,
+    stable_for: Option<Duration>,
+    max_file_size_mb: Option<u64>,
+    max_pdf_pages: Option<u32>,
+    max_video_duration_min: Option<u64>,
+    symlinks: Option<SymlinkPolicy>,
+}
+
+impl RenameConfigBuilder {
+    /// Create a builder with every option unset
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip hidden files and directories (starting with .)
+    pub fn skip_hidden(mut self, skip: bool) -> Self {
+        self.skip_hidden = Some(skip);
+        self
+    }
+
+    /// Folder levels to descend (1 = only files directly in the directory)
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Only analyze files matching one of these globs, e.g. `**/*.pdf`
+    pub fn include_globs(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.include_globs = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Skip files and folders matching these globs, e.g. `node_modules/`
+    pub fn exclude_globs(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exclude_globs = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only analyze files of these categories
+    pub fn categories(mut self, categories: impl IntoIterator<Item = FileCategory>) -> Self {
+        self.categories = Some(categories.into_iter().collect());
+        self
+    }
+
+    /// Include GPS location in filenames
+    pub fn include_location(mut self, include: bool) -> Self {
+        self.include_location = Some(include);
+        self.category_overrides.values_mut().for_each(|overrides| overrides.include_location = None);
+        self
+    }
+
+    /// Include formatted timestamp in filenames
+    pub fn include_timestamp(mut self, include: bool) -> Self {
+        self.include_timestamp = Some(include);
+        self.category_overrides.values_mut().for_each(|overrides| overrides.include_timestamp = None);
+        self
+    }
+
+    /// Append the number of attachments to email names
+    pub fn email_attachment_count(mut self, include: bool) -> Self {
+        self.email_attachment_count = Some(include);
+        self
+    }
+
+    /// Offer a rough photo subject (receipt, whiteboard, screenshot, ...) as a name
+    pub fn subject_hints(mut self, enable: bool) -> Self {
+        self.subject_hints = Some(enable);
+        self
+    }
+
+    /// Name archives after their contents instead of leaving them to their filename
+    pub fn peek_archives(mut self, enable: bool) -> Self {
+        self.peek_archives = Some(enable);
+        self
+    }
+
+    /// Name receipts and invoices from their date, vendor and total
+    pub fn receipt_mode(mut self, enable: bool) -> Self {
+        self.receipt_mode = Some(enable);
+        self
+    }
+
+    /// Tesseract languages OCR reads text in, e.g. `["eng", "deu", "jpn"]`
+    pub fn ocr_languages(mut self, languages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.ocr_languages = Some(languages.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Use multi-frame video analysis
+    pub fn multiframe_video(mut self, multiframe: bool) -> Self {
+        self.multiframe_video = Some(multiframe);
+        self
+    }
+
+    /// Analyze at most this many files at the same time (defaults to one per CPU core)
+    pub fn max_parallel_files(mut self, max: usize) -> Self {
+        self.max_parallel_files = Some(max);
+        self
+    }
+
+    /// Run at most this many OCR and media tools at the same time
+    pub fn max_ocr_processes(mut self, max: usize) -> Self {
+        self.max_ocr_processes = Some(max);
+        self
+    }
+
+    /// How multi-frame video OCR combines text across frames
+    pub fn frame_aggregation(mut self, aggregation: FrameAggregation) -> Self {
+        self.frame_aggregation = Some(aggregation);
+        self
+    }
+
+    /// Read at most this many frames per video in multi-frame video OCR
+    pub fn video_frames(mut self, frames: usize) -> Self {
+        self.video_frames = Some(frames);
+        self
+    }
+
+    /// Name files from this many key phrases of long text (default 1)
+    pub fn key_phrase_count(mut self, count: usize) -> Self {
+        self.key_phrase_count = Some(count);
+        self
+    }
+
+    /// Allow this many words in one key phrase (default 3)
+    pub fn key_phrase_max_words(mut self, words: usize) -> Self {
+        self.key_phrase_max_words = Some(words);
+        self
+    }
+
+    /// Don't name files from OCR text read with a mean confidence under this (0-100, default 40)
+    pub fn min_ocr_confidence(mut self, confidence: u8) -> Self {
+        self.min_ocr_confidence = Some(confidence);
+        self
+    }
+
+    /// Metadata reader: exiftool, the built-in reader, or exiftool when installed (default)
+    pub fn metadata_backend(mut self, backend: MetadataBackend) -> Self {
+        self.metadata_backend = Some(backend);
+        self
+    }
+
+    /// Convert GPS coordinates to city names
+    pub fn geocode(mut self, geocode: bool) -> Self {
+        self.geocode = Some(geocode);
+        self
+    }
+
+    /// Look up place names in the offline city database, online, or offline once the
+    /// database has been downloaded (default)
+    pub fn geocode_source(mut self, source: GeocodeSource) -> Self {
+        self.geocode_source = Some(source);
+        self
+    }
+
+    /// GeoNames city database for offline geocoding (defaults to one in the data directory)
+    pub fn geocode_database(mut self, path: impl Into<PathBuf>) -> Self {
+        self.geocode_database = Some(path.into());
+        self
+    }
+
+    /// Identify untitled audio files through AcoustID with this API key (makes network calls)
+    pub fn acoustid_key(mut self, key: impl Into<String>) -> Self {
+        self.acoustid_key = Some(key.into());
+        self
+    }
+
+    /// Name academic papers from the Crossref or arXiv record of their DOI or arXiv identifier
+    /// (makes network calls)
+    pub fn paper_lookup(mut self, enable: bool) -> Self {
+        self.paper_lookup = Some(enable);
+        self
+    }
+
+    /// Enable metadata caching
+    pub fn enable_cache(mut self, enable: bool) -> Self {
+        self.enable_cache = Some(enable);
+        self
+    }
+
+    /// Analyze every file afresh, ignoring cached results but still caching the new ones
+    pub fn refresh_cache(mut self, refresh: bool) -> Self {
+        self.refresh_cache = Some(refresh);
+        self
+    }
+
+    /// Cache file path (implies nothing about `enable_cache`, which defaults to on)
+    pub fn cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_path = Some(path.into());
+        self
+    }
+
+    /// Undo journal file (defaults to journal.jsonl in the user data directory)
+    pub fn journal_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal_path = Some(path.into());
+        self
+    }
+
+    /// Filename template, e.g. `{camera}_{name}`
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self.category_overrides.values_mut().for_each(|overrides| overrides.template = None);
+        self
+    }
+
+    /// Name locations by country, region, city (default) or coordinates
+    pub fn location_precision(mut self, precision: LocationPrecision) -> Self {
+        self.location_precision = Some(precision);
+        self
+    }
+
+    /// Areas around private places whose files get no location in their names
+    pub fn location_blocklist<I>(mut self, areas: I) -> Self
+    where
+        I: IntoIterator<Item = BlockedArea>,
+    {
+        self.location_blocklist = Some(areas.into_iter().collect());
+        self
+    }
+
+    /// strftime pattern for the timestamp in names, e.g. `%Y%m%d` or `%d.%m.%Y`
+    pub fn timestamp_format(mut self, format: impl Into<String>) -> Self {
+        self.timestamp_format = Some(format.into());
+        self
+    }
+
+    /// Date alone (default) or date and time of day in names
+    pub fn timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = Some(precision);
+        self
+    }
+
+    /// Timestamp at the end (default) or start of names
+    pub fn timestamp_position(mut self, position: TimestampPosition) -> Self {
+        self.timestamp_position = Some(position);
+        self
+    }
+
+    /// Case of proposed names: as found (default), snake_case, kebab-case, Title_Case or camelCase
+    pub fn name_case(mut self, case: NameCase) -> Self {
+        self.name_case = Some(case);
+        self
+    }
+
+    /// Separator between the words of proposed names (defaults to the case's own)
+    pub fn word_separator(mut self, separator: WordSeparator) -> Self {
+        self.word_separator = Some(separator);
+        self
+    }
+
+    /// Fewest files that make a numbered series or a photo burst (defaults to 3)
+    pub fn series_min_files(mut self, files: usize) -> Self {
+        self.series_min_files = Some(files);
+        self
+    }
+
+    /// Pad series numbers to at least this many digits (defaults to 3, or 2 for bursts)
+    pub fn series_padding(mut self, digits: usize) -> Self {
+        self.series_padding = Some(digits);
+        self
+    }
+
+    /// Separator between a series' name and its number (defaults to the series' own)
+    pub fn series_separator(mut self, separator: WordSeparator) -> Self {
+        self.series_separator = Some(separator);
+        self
+    }
+
+    /// Number series from this number on (by default numbered series keep their numbers)
+    pub fn series_start(mut self, start: usize) -> Self {
+        self.series_start = Some(start);
+        self
+    }
+
+    /// Give files whose content is in another format than their extension says that
+    /// format's extension in their new name
+    pub fn fix_extensions(mut self, enable: bool) -> Self {
+        self.fix_extensions = Some(enable);
+        self
+    }
+
+    /// Lower-case the extensions of new names (by default they keep their case)
+    pub fn lowercase_extensions(mut self, enable: bool) -> Self {
+        self.lowercase_extensions = Some(enable);
+        self
+    }
+
+    /// Move files into folders from this template, e.g. `{yyyy}/{mm}/{location}`
+    pub fn organize(mut self, template: impl Into<String>) -> Self {
+        self.organize = Some(template.into());
+        self
+    }
+
+    /// Folder the `organize` folders are created in (defaults to each file's own folder)
+    pub fn organize_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.organize_root = Some(path.into());
+        self
+    }
+
+    /// What to do with exact copies of other files: flag them (default), skip them, or suffix them
+    pub fn duplicates(mut self, handling: DuplicateHandling) -> Self {
+        self.duplicates = Some(handling);
+        self
+    }
+
+    /// What to do when a proposed name is taken by another file: tell them apart by content
+    /// (default), number, timestamp or hash suffix, keep the original name, or fail the batch
+    pub fn collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = Some(strategy);
+        self
+    }
+
+    /// What to do when a new name is taken by a file on disk outside the batch: skip the
+    /// rename (default), add a counter, move that file to the trash, or overwrite it
+    pub fn on_conflict(mut self, action: OnConflict) -> Self {
+        self.on_conflict = Some(action);
+        self
+    }
+
+    /// Write the derived title into file metadata as well as renaming (`Also`) or instead (`Only`)
+    pub fn write_metadata(mut self, mode: MetadataWriteMode) -> Self {
+        self.write_metadata = Some(mode);
+        self
+    }
+
+    /// Rename sidecars and RAW+JPEG pairs along with the file they belong to (default on)
+    pub fn rename_sidecars(mut self, rename: bool) -> Self {
+        self.rename_sidecars = Some(rename);
+        self
+    }
+
+    /// Also name placeholder folders (`New Folder (3)`) after the files in them (default off)
+    pub fn rename_directories(mut self, rename: bool) -> Self {
+        self.rename_directories = Some(rename);
+        self
+    }
+
+    /// Never change system DNS while installing dependencies (default off)
+    pub fn never_touch_dns(mut self, never: bool) -> Self {
+        self.never_touch_dns = Some(never);
+        self
+    }
+
+    /// Kill external tools that run longer than this over one file (default 5 minutes)
+    pub fn tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = Some(timeout);
+        self
+    }
+
+    /// Leave files modified less than this long ago alone as still being written (default
+    /// only files seen changing during analysis are)
+    pub fn stable_for(mut self, period: Duration) -> Self {
+        self.stable_for = Some(period);
+        self
+    }
+
+    /// Leave files larger than this many megabytes out of analysis (default no limit)
+    pub fn max_file_size_mb(mut self, megabytes: u64) -> Self {
+        self.max_file_size_mb = Some(megabytes);
+        self
+    }
+
+    /// Name PDFs with more pages from their metadata alone (default no limit)
+    pub fn max_pdf_pages(mut self, pages: u32) -> Self {
+        self.max_pdf_pages = Some(pages);
+        self
+    }
+
+    /// Name videos longer than this many minutes from their metadata alone (default no limit)
+    pub fn max_video_duration_min(mut self, minutes: u64) -> Self {
+        self.max_video_duration_min = Some(minutes);
+        self
+    }
+
+    /// What scanning does with symbolic links (default skip)
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = Some(policy);
+        self
+    }
+
+    /// Options for files of `category` only, over the ones set for all files so far
+    /// Setting an option for all files afterwards (e.g. from a command-line flag) replaces them
+    pub fn category_overrides(mut self, category: FileCategory, overrides: CategoryOverrides) -> Self {
+        self.category_overrides.insert(category, overrides);
+        self
+    }
+
+    /// Minimum confidence score a name needs to be proposed, e.g. 5.0
+    pub fn min_confidence(mut self, score: f32) -> Self {
+        self.min_confidence = Some(score);
+        self.category_overrides.values_mut().for_each(|overrides| overrides.min_confidence = None);
+        self
+    }
+
+    /// Validate the options and build the configuration
+    pub fn build(self) -> Result<RenameConfig, ConfigError> {
+        let defaults = RenameConfig::default();
+        let config = RenameConfig {
+            skip_hidden: self.skip_hidden.unwrap_or(defaults.skip_hidden),
+            max_depth: self.max_depth.or(defaults.max_depth),
+            include_globs: self.include_globs.unwrap_or(defaults.include_globs),
+            exclude_globs: self.exclude_globs.unwrap_or(defaults.exclude_globs),
+            categories: self.categories.or(defaults.categories),
+            include_location: self.include_location.unwrap_or(defaults.include_location),
+            location_precision: self.location_precision.unwrap_or(defaults.location_precision),
+            location_blocklist: self.location_blocklist.unwrap_or(defaults.location_blocklist),
+            include_timestamp: self.include_timestamp.unwrap_or(defaults.include_timestamp),
+            timestamp_format: self.timestamp_format.or(defaults.timestamp_format),
+            timestamp_precision: self.timestamp_precision.unwrap_or(defaults.timestamp_precision),
+            timestamp_position: self.timestamp_position.unwrap_or(defaults.timestamp_position),
+            email_attachment_count: self.email_attachment_count.unwrap_or(defaults.email_attachment_count),
+            subject_hints: self.subject_hints.unwrap_or(defaults.subject_hints),
+            peek_archives: self.peek_archives.unwrap_or(defaults.peek_archives),
+            receipt_mode: self.receipt_mode.unwrap_or(defaults.receipt_mode),
+            ocr_languages: self.ocr_languages.unwrap_or(defaults.ocr_languages),
+            multiframe_video: self.multiframe_video.unwrap_or(defaults.multiframe_video),
+            max_parallel_files: self.max_parallel_files.or(defaults.max_parallel_files),
+            max_ocr_processes: self.max_ocr_processes.or(defaults.max_ocr_processes),
+            frame_aggregation: self.frame_aggregation.unwrap_or(defaults.frame_aggregation),
+            video_frames: self.video_frames.unwrap_or(defaults.video_frames),
+            key_phrase_count: self.key_phrase_count.unwrap_or(defaults.key_phrase_count),
+            key_phrase_max_words: self.key_phrase_max_words.unwrap_or(defaults.key_phrase_max_words),
+            min_ocr_confidence: self.min_ocr_confidence.unwrap_or(defaults.min_ocr_confidence),
+            metadata_backend: self.metadata_backend.unwrap_or(defaults.metadata_backend),
+            geocode: self.geocode.unwrap_or(defaults.geocode),
+            geocode_source: self.geocode_source.unwrap_or(defaults.geocode_source),
+            geocode_database: self.geocode_database.or(defaults.geocode_database),
+            acoustid_key: self.acoustid_key.or(defaults.acoustid_key),
+            paper_lookup: self.paper_lookup.unwrap_or(defaults.paper_lookup),
+            enable_cache: self.enable_cache.unwrap_or(defaults.enable_cache),
+            refresh_cache: self.refresh_cache.unwrap_or(defaults.refresh_cache),
+            cache_path: self.cache_path.or(defaults.cache_path),
+            journal_path: self.journal_path.or(defaults.journal_path),
+            template: self.template.or(defaults.template),
+            name_case: self.name_case.unwrap_or(defaults.name_case),
+            word_separator: self.word_separator.or(defaults.word_separator),
+            series_min_files: self.series_min_files.unwrap_or(defaults.series_min_files),
+            series_padding: self.series_padding.or(defaults.series_padding),
+            series_separator: self.series_separator.or(defaults.series_separator),
+            series_start: self.series_start.or(defaults.series_start),
+            fix_extensions: self.fix_extensions.unwrap_or(defaults.fix_extensions),
+            lowercase_extensions: self.lowercase_extensions.unwrap_or(defaults.lowercase_extensions),
+            organize: self.organize.or(defaults.organize),
+            organize_root: self.organize_root.or(defaults.organize_root),
+            duplicates: self.duplicates.unwrap_or(defaults.duplicates),
+            collision_strategy: self.collision_strategy.unwrap_or(defaults.collision_strategy),
+            on_conflict: self.on_conflict.unwrap_or(defaults.on_conflict),
+            write_metadata: self.write_metadata.unwrap_or(defaults.write_metadata),
+            rename_sidecars: self.rename_sidecars.unwrap_or(defaults.rename_sidecars),
+            rename_directories: self.rename_directories.unwrap_or(defaults.rename_directories),
+            category_overrides: self.category_overrides,
+            min_confidence: self.min_confidence.unwrap_or(defaults.min_confidence),
+            never_touch_dns: self.never_touch_dns.unwrap_or(defaults.never_touch_dns),
+            tool_timeout: self.tool_timeout.unwrap_or(defaults.tool_timeout),
+            stable_for: self.stable_for.or(defaults.stable_for),
+            max_file_size_mb: self.max_file_size_mb.or(defaults.max_file_size_mb),
+            max_pdf_pages: self.max_pdf_pages.or(defaults.max_pdf_pages),
+            max_video_duration_min: self.max_video_duration_min.or(defaults.max_video_duration_min),
+            symlinks: self.symlinks.unwrap_or(defaults.symlinks),
+        };
+
+        if self.geocode == Some(true) && !config.include_location {
+            return Err(ConfigError::GeocodeWithoutLocation);
+        }
+        if self.geocode == Some(false)
+            && matches!(self.location_precision, Some(LocationPrecision::Country | LocationPrecision::Region))
+        {
+            return Err(ConfigError::LocationPrecisionWithoutGeocode);
+        }
+        for area in &config.location_blocklist {
+            if let Some(reason) = area.problem() {
+                return Err(ConfigError::InvalidBlockedArea(area.to_string(), reason));
+            }
+        }
+        if let Some(path) = &config.cache_path {
+            if path.as_os_str().is_empty() {
+                return Err(ConfigError::EmptyCachePath);
+            }
+            if !config.enable_cache {
+                return Err(ConfigError::CachePathWithoutCache);
+            }
+        }
+
+        if config.journal_path.as_ref().is_some_and(|p| p.as_os_str().is_empty()) {
+            return Err(ConfigError::EmptyJournalPath);
+        }
+        if config.acoustid_key.as_ref().is_some_and(|key| key.trim().is_empty()) {
+            return Err(ConfigError::EmptyAcoustidKey);
+        }
+        // Language names also end up in tesseract's data file paths
+        let is_language = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with('/')
+                && !name.contains("..")
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        };
+        if let Some(name) = config.ocr_languages.iter().find(|name| !is_language(name)) {
+            return Err(ConfigError::InvalidOcrLanguage(name.clone()));
+        }
+
+        let templates = config
+            .category_overrides
+            .values()
+            .filter_map(|overrides| overrides.template.as_deref())
+            .chain(config.template.as_deref());
+        for template in templates {
+            if let Some(name) = crate::template::unknown_variable(template) {
+                return Err(ConfigError::UnknownTemplateVariable(name));
+            }
+            if let Some(reason) = crate::template::unsafe_path(template) {
+                return Err(ConfigError::UnsafeTemplate(reason));
+            }
+        }
+
+        if let Some(format) = config.timestamp_format.as_deref() {
+            if let Some(reason) = crate::location_timestamp::check_format(format) {
+                return Err(ConfigError::InvalidTimestampFormat(format.to_string(), reason));
+            }
+        }
+
+        if let Some(organize) = config.organize.as_deref() {
+            if let Some(name) = crate::template::unknown_variable(organize) {
+                return Err(ConfigError::UnknownTemplateVariable(name));
+            }
+            // Only folders, so a trailing `/` is fine
+            if let Some(reason) = crate::template::unsafe_path(organize.trim_end_matches(['/', '\\'])) {
+                return Err(ConfigError::UnsafeTemplate(reason));
+            }
+        } else if config.organize_root.is_some() {
+            return Err(ConfigError::OrganizeRootWithoutTemplate);
+        }
+        if config.organize.is_some() && config.write_metadata == MetadataWriteMode::Only {
+            return Err(ConfigError::OrganizeWithMetadataOnly);
+        }
+        if config.organize.is_some() && config.rename_directories {
+            return Err(ConfigError::RenameDirectoriesWithOrganize);
+        }
+
+        if config.max_depth == Some(0) {
+            return Err(ConfigError::InvalidMaxDepth);
+        }
+        if config.max_parallel_files == Some(0) {
+            return Err(ConfigError::InvalidMaxParallelFiles);
+        }
+        if config.max_ocr_processes == Some(0) {
+            return Err(ConfigError::InvalidMaxOcrProcesses);
+        }
+        if config.video_frames == 0 {
+            return Err(ConfigError::InvalidVideoFrames);
+        }
+        if config.key_phrase_count == 0 {
+            return Err(ConfigError::InvalidLimit("key phrase count"));
+        }
+        if config.key_phrase_max_words == 0 {
+            return Err(ConfigError::InvalidLimit("key phrase length"));
+        }
+        if config.min_ocr_confidence > 100 {
+            return Err(ConfigError::InvalidMinOcrConfidence);
+        }
+        if config.series_min_files < 2 {
+            return Err(ConfigError::InvalidSeriesMinFiles);
+        }
+        if config.series_padding.is_some_and(|digits| digits > MAX_SERIES_PADDING) {
+            return Err(ConfigError::InvalidSeriesPadding);
+        }
+        if config.tool_timeout.is_zero() {
+            return Err(ConfigError::InvalidToolTimeout);
+        }
+        if config.stable_for.is_some_and(|period| period.is_zero()) {
+            return Err(ConfigError::InvalidLimit("stability period"));
+        }
+        if config.max_file_size_mb == Some(0) {
+            return Err(ConfigError::InvalidLimit("maximum file size"));
+        }
+        if config.max_pdf_pages == Some(0) {
+            return Err(ConfigError::InvalidLimit("maximum PDF pages"));
+        }
+        if config.max_video_duration_min == Some(0) {
+            return Err(ConfigError::InvalidLimit("maximum video duration"));
+        }
+        for pattern in config.include_globs.iter().chain(&config.exclude_globs) {
+            crate::scan_filter::check(pattern).map_err(|e| ConfigError::InvalidGlob(e.to_string()))?;
+        }
+
+        if config.categories.as_ref().is_some_and(HashSet::is_empty) {
+            return Err(ConfigError::NoCategories);
+        }
+
+        let thresholds = config
+            .category_overrides
+            .values()
+            .filter_map(|overrides| overrides.min_confidence)
+            .chain([config.min_confidence]);
+        for min_confidence in thresholds {
+            if !(min_confidence >= 0.0 && min_confidence.is_finite()) {
+                return Err(ConfigError::InvalidMinConfidence);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unset_options_use_defaults() {
+        let config = RenameConfigBuilder::new().build().unwrap();
+        let defaults = RenameConfig::default();
+        assert_eq!(config.include_location, defaults.include_location);
+        assert_eq!(config.geocode, defaults.geocode);
+        assert_eq!(config.enable_cache, defaults.enable_cache);
+        assert_eq!(config.never_touch_dns, defaults.never_touch_dns);
+        assert!(RenameConfigBuilder::new().never_touch_dns(true).build().unwrap().never_touch_dns);
+
+        // Turning location off doesn't conflict with the geocode default
+        assert!(RenameConfigBuilder::new().include_location(false).build().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_contradictions() {
+        let err = RenameConfigBuilder::new()
+            .geocode(true)
+            .include_location(false)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::GeocodeWithoutLocation);
+
+        let err = RenameConfigBuilder::new()
+            .enable_cache(false)
+            .cache_path("/tmp/cache.json")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::CachePathWithoutCache);
+
+        let err = RenameConfigBuilder::new().cache_path("").build().unwrap_err();
+        assert_eq!(err, ConfigError::EmptyCachePath);
+
+        let err = RenameConfigBuilder::new().journal_path("").build().unwrap_err();
+        assert_eq!(err, ConfigError::EmptyJournalPath);
+
+        let err = RenameConfigBuilder::new().acoustid_key(" ").build().unwrap_err();
+        assert_eq!(err, ConfigError::EmptyAcoustidKey);
+
+        let err = RenameConfigBuilder::new().ocr_languages(["eng", "deu+jpn"]).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidOcrLanguage("deu+jpn".to_string()));
+        assert!(RenameConfigBuilder::new().ocr_languages(["../eng"]).build().is_err());
+        assert!(RenameConfigBuilder::new().ocr_languages(["eng", "script/Latin"]).build().is_ok());
+
+        let err = RenameConfigBuilder::new().template("{camra}_{name}").build().unwrap_err();
+        assert_eq!(err, ConfigError::UnknownTemplateVariable("camra".to_string()));
+
+        let err = RenameConfigBuilder::new().template("../{name}").build().unwrap_err();
+        assert!(matches!(err, ConfigError::UnsafeTemplate(_)));
+        assert!(RenameConfigBuilder::new().template("{camera}_{name}").build().is_ok());
+
+        let err = RenameConfigBuilder::new().timestamp_format("%Y/%m").build().unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidTimestampFormat(format, _) if format == "%Y/%m"));
+        assert!(RenameConfigBuilder::new().timestamp_format("%Y%m%d").build().is_ok());
+
+        let err = RenameConfigBuilder::new()
+            .geocode(false)
+            .location_precision(LocationPrecision::Country)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::LocationPrecisionWithoutGeocode);
+        assert!(RenameConfigBuilder::new()
+            .geocode(false)
+            .location_precision(LocationPrecision::Coordinates)
+            .build()
+            .is_ok());
+        let far_north = BlockedArea {
+            latitude: 91.0,
+            longitude: 0.0,
+            radius_km: 1.0,
+        };
+        let err = RenameConfigBuilder::new().location_blocklist([far_north]).build().unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidBlockedArea(..)));
+
+        let err = RenameConfigBuilder::new().min_confidence(-1.0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMinConfidence);
+        assert!(RenameConfigBuilder::new().min_confidence(f32::NAN).build().is_err());
+        assert!(RenameConfigBuilder::new().min_confidence(5.0).build().is_ok());
+
+        let err = RenameConfigBuilder::new().max_depth(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMaxDepth);
+        let err = RenameConfigBuilder::new().max_parallel_files(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMaxParallelFiles);
+        let err = RenameConfigBuilder::new().max_ocr_processes(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMaxOcrProcesses);
+        let err = RenameConfigBuilder::new().video_frames(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidVideoFrames);
+        let err = RenameConfigBuilder::new().key_phrase_max_words(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidLimit("key phrase length"));
+        let config = RenameConfigBuilder::new().key_phrase_count(2).build().unwrap();
+        assert_eq!((config.key_phrase_count, config.key_phrase_max_words), (2, 3));
+        let err = RenameConfigBuilder::new().min_ocr_confidence(101).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMinOcrConfidence);
+        let config = RenameConfigBuilder::new().min_ocr_confidence(0).build().unwrap();
+        assert_eq!(config.min_ocr_confidence, 0);
+        let err = RenameConfigBuilder::new().series_min_files(1).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidSeriesMinFiles);
+        let err = RenameConfigBuilder::new().series_padding(12).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidSeriesPadding);
+        let config = RenameConfigBuilder::new().series_min_files(2).series_start(0).build().unwrap();
+        assert_eq!((config.series_min_files, config.series_padding, config.series_start), (2, None, Some(0)));
+        let err = RenameConfigBuilder::new().tool_timeout(Duration::ZERO).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidToolTimeout);
+        let config = RenameConfigBuilder::new().tool_timeout(Duration::from_secs(30)).build().unwrap();
+        assert_eq!(config.tool_timeout, Duration::from_secs(30));
+        let err = RenameConfigBuilder::new().stable_for(Duration::ZERO).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidLimit("stability period"));
+        let config = RenameConfigBuilder::new().stable_for(Duration::from_secs(10)).build().unwrap();
+        assert_eq!(config.stable_for, Some(Duration::from_secs(10)));
+        let err = RenameConfigBuilder::new().max_pdf_pages(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::InvalidLimit("maximum PDF pages"));
+        assert_eq!(err.to_string(), "maximum PDF pages must be at least 1");
+        let config = RenameConfigBuilder::new()
+            .max_file_size_mb(2048)
+            .max_video_duration_min(90)
+            .build()
+            .unwrap();
+        assert_eq!((config.max_file_size_mb, config.max_video_duration_min), (Some(2048), Some(90)));
+        let err = RenameConfigBuilder::new().exclude_globs(["[oops"]).build().unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidGlob(_)));
+        assert!(RenameConfigBuilder::new().include_globs(["**/*.pdf"]).build().is_ok());
+
+        let err = RenameConfigBuilder::new().organize("{yyyy}/{mnth}").build().unwrap_err();
+        assert_eq!(err, ConfigError::UnknownTemplateVariable("mnth".to_string()));
+        let err = RenameConfigBuilder::new().organize("../{yyyy}").build().unwrap_err();
+        assert!(matches!(err, ConfigError::UnsafeTemplate(_)));
+        let err = RenameConfigBuilder::new().organize_root("/tmp/sorted").build().unwrap_err();
+        assert_eq!(err, ConfigError::OrganizeRootWithoutTemplate);
+        assert!(RenameConfigBuilder::new().organize("{category}/{yyyy}/").build().is_ok());
+        let err = RenameConfigBuilder::new()
+            .organize("{yyyy}")
+            .write_metadata(MetadataWriteMode::Only)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::OrganizeWithMetadataOnly);
+        let err = RenameConfigBuilder::new()
+            .organize("{yyyy}")
+            .rename_directories(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::RenameDirectoriesWithOrganize);
+
+        let err = RenameConfigBuilder::new().categories([]).build().unwrap_err();
+        assert_eq!(err, ConfigError::NoCategories);
+        let config = RenameConfigBuilder::new().categories([FileCategory::Image]).build().unwrap();
+        assert!(config.includes_category(&FileCategory::Image));
+        assert!(!config.includes_category(&FileCategory::Video));
+    }
+
+    #[test]
+    fn test_category_overrides() {
+        let photos = CategoryOverrides {
+            template: Some("{camera}_{name}".to_string()),
+            min_confidence: Some(6.0),
+            ..CategoryOverrides::default()
+        };
+        let config = RenameConfigBuilder::new()
+            .min_confidence(3.0)
+            .category_overrides(FileCategory::Image, photos.clone())
+            .build()
+            .unwrap();
+        assert_eq!(config.min_confidence_for(&FileCategory::Image), 6.0);
+        assert_eq!(config.min_confidence_for(&FileCategory::Document), 3.0);
+
+        // An option set for all files later (a flag) replaces the category's value
+        let config = RenameConfigBuilder::new()
+            .category_overrides(FileCategory::Image, photos)
+            .template("{name}")
+            .build()
+            .unwrap();
+        let overrides = &config.category_overrides[&FileCategory::Image];
+        assert_eq!(overrides.template, None);
+        assert_eq!(overrides.min_confidence, Some(6.0));
+
+        let bad = CategoryOverrides {
+            template: Some("{nmae}".to_string()),
+            ..CategoryOverrides::default()
+        };
+        let err = RenameConfigBuilder::new()
+            .category_overrides(FileCategory::Video, bad)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::UnknownTemplateVariable("nmae".to_string()));
+    }
+}
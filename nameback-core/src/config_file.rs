@@ -0,0 +1,1117 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::{
+    BlockedArea, CollisionStrategy, DuplicateHandling, GeocodeSource, FileCategory, FrameAggregation, LocationPrecision,
+    MetadataBackend, MetadataWriteMode, NameCase, OnConflict, RenameConfigBuilder, SymlinkPolicy, TimestampPosition,
+    TimestampPrecision, WordSeparator,
+};
+
+/// Settings stored in `config.toml`
+///
+/// ```toml
+/// # Applied on every run
+/// include_timestamp = false
+/// exclude_globs = ["node_modules/"]
+///
+/// # Only for files of one category
+/// [category.image]
+/// template = "{camera}_{name}"
+///
+/// [profile.scans]
+/// include_location = false
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigFile {
+    /// Options applied on every run, below any profile, environment variable or flag
+    pub settings: Profile,
+    /// Options for files of one category (`[category.<name>]`), over every other setting
+    pub categories: HashMap<FileCategory, CategoryOverrides>,
+    /// Named profiles defined in the file (`[profile.<name>]`), selectable with `--config-profile <name>`
+    /// or the GUI dropdown
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// Options that can differ per file category, e.g. a camera prefix for photos only
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryOverrides {
+    pub include_location: Option<bool>,
+    pub include_timestamp: Option<bool>,
+    pub template: Option<String>,
+    pub min_confidence: Option<f32>,
+}
+
+/// Options set by a named profile (unset options keep their defaults)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub skip_hidden: Option<bool>,
+    pub max_depth: Option<usize>,
+    pub include_globs: Option<Vec<String>>,
+    pub exclude_globs: Option<Vec<String>>,
+    pub categories: Option<Vec<FileCategory>>,
+    pub include_location: Option<bool>,
+    pub include_timestamp: Option<bool>,
+    pub timestamp_format: Option<String>,
+    pub timestamp_precision: Option<TimestampPrecision>,
+    pub timestamp_position: Option<TimestampPosition>,
+    pub email_attachment_count: Option<bool>,
+    pub subject_hints: Option<bool>,
+    pub peek_archives: Option<bool>,
+    pub receipt_mode: Option<bool>,
+    pub ocr_languages: Option<Vec<String>>,
+    pub multiframe_video: Option<bool>,
+    pub max_parallel_files: Option<usize>,
+    pub max_ocr_processes: Option<usize>,
+    pub frame_aggregation: Option<FrameAggregation>,
+    pub video_frames: Option<usize>,
+    pub key_phrase_count: Option<usize>,
+    pub key_phrase_max_words: Option<usize>,
+    pub min_ocr_confidence: Option<u8>,
+    pub metadata_backend: Option<MetadataBackend>,
+    pub geocode: Option<bool>,
+    pub geocode_source: Option<GeocodeSource>,
+    pub geocode_database: Option<PathBuf>,
+    pub location_precision: Option<LocationPrecision>,
+    pub location_blocklist: Option<Vec<BlockedArea>>,
+    pub acoustid_key: Option<String>,
+    pub paper_lookup: Option<bool>,
+    pub enable_cache: Option<bool>,
+    pub cache_path: Option<PathBuf>,
+    pub template: Option<String>,
+    pub name_case: Option<NameCase>,
+    pub word_separator: Option<WordSeparator>,
+    pub series_min_files: Option<usize>,
+    pub series_padding: Option<usize>,
+    pub series_separator: Option<WordSeparator>,
+    pub series_start: Option<usize>,
+    pub fix_extensions: Option<bool>,
+    pub lowercase_extensions: Option<bool>,
+    pub organize: Option<String>,
+    pub organize_root: Option<PathBuf>,
+    pub duplicates: Option<DuplicateHandling>,
+    pub collision_strategy: Option<CollisionStrategy>,
+    pub on_conflict: Option<OnConflict>,
+    pub write_metadata: Option<MetadataWriteMode>,
+    pub rename_sidecars: Option<bool>,
+    pub rename_directories: Option<bool>,
+    pub min_confidence: Option<f32>,
+    pub never_touch_dns: Option<bool>,
+    /// Seconds an external tool may run over one file
+    pub tool_timeout: Option<u64>,
+    /// Seconds a file must go unmodified before it is analyzed or renamed
+    pub stable_for: Option<u64>,
+    pub max_file_size_mb: Option<u64>,
+    pub max_pdf_pages: Option<u32>,
+    pub max_video_duration_min: Option<u64>,
+    pub symlinks: Option<SymlinkPolicy>,
+}
+
+impl Profile {
+    /// Apply the options this profile sets on top of `builder`
+    pub fn apply(&self, mut builder: RenameConfigBuilder) -> RenameConfigBuilder {
+        if let Some(skip) = self.skip_hidden {
+            builder = builder.skip_hidden(skip);
+        }
+        if let Some(depth) = self.max_depth {
+            builder = builder.max_depth(depth);
+        }
+        if let Some(patterns) = &self.include_globs {
+            builder = builder.include_globs(patterns.clone());
+        }
+        if let Some(patterns) = &self.exclude_globs {
+            builder = builder.exclude_globs(patterns.clone());
+        }
+        if let Some(categories) = &self.categories {
+            builder = builder.categories(categories.clone());
+        }
+        if let Some(include) = self.include_location {
+            builder = builder.include_location(include);
+        }
+        if let Some(include) = self.include_timestamp {
+            builder = builder.include_timestamp(include);
+        }
+        if let Some(format) = &self.timestamp_format {
+            builder = builder.timestamp_format(format.clone());
+        }
+        if let Some(precision) = self.timestamp_precision {
+            builder = builder.timestamp_precision(precision);
+        }
+        if let Some(position) = self.timestamp_position {
+            builder = builder.timestamp_position(position);
+        }
+        if let Some(include) = self.email_attachment_count {
+            builder = builder.email_attachment_count(include);
+        }
+        if let Some(enable) = self.subject_hints {
+            builder = builder.subject_hints(enable);
+        }
+        if let Some(enable) = self.peek_archives {
+            builder = builder.peek_archives(enable);
+        }
+        if let Some(enable) = self.receipt_mode {
+            builder = builder.receipt_mode(enable);
+        }
+        if let Some(languages) = &self.ocr_languages {
+            builder = builder.ocr_languages(languages.clone());
+        }
+        if let Some(multiframe) = self.multiframe_video {
+            builder = builder.multiframe_video(multiframe);
+        }
+        if let Some(max) = self.max_parallel_files {
+            builder = builder.max_parallel_files(max);
+        }
+        if let Some(max) = self.max_ocr_processes {
+            builder = builder.max_ocr_processes(max);
+        }
+        if let Some(aggregation) = self.frame_aggregation {
+            builder = builder.frame_aggregation(aggregation);
+        }
+        if let Some(frames) = self.video_frames {
+            builder = builder.video_frames(frames);
+        }
+        if let Some(count) = self.key_phrase_count {
+            builder = builder.key_phrase_count(count);
+        }
+        if let Some(words) = self.key_phrase_max_words {
+            builder = builder.key_phrase_max_words(words);
+        }
+        if let Some(confidence) = self.min_ocr_confidence {
+            builder = builder.min_ocr_confidence(confidence);
+        }
+        if let Some(backend) = self.metadata_backend {
+            builder = builder.metadata_backend(backend);
+        }
+        if let Some(geocode) = self.geocode {
+            builder = builder.geocode(geocode);
+        }
+        if let Some(source) = self.geocode_source {
+            builder = builder.geocode_source(source);
+        }
+        if let Some(path) = &self.geocode_database {
+            builder = builder.geocode_database(path.clone());
+        }
+        if let Some(precision) = self.location_precision {
+            builder = builder.location_precision(precision);
+        }
+        if let Some(areas) = &self.location_blocklist {
+            builder = builder.location_blocklist(areas.clone());
+        }
+        if let Some(key) = &self.acoustid_key {
+            builder = builder.acoustid_key(key.clone());
+        }
+        if let Some(enable) = self.paper_lookup {
+            builder = builder.paper_lookup(enable);
+        }
+        if let Some(enable) = self.enable_cache {
+            builder = builder.enable_cache(enable);
+        }
+        if let Some(path) = &self.cache_path {
+            builder = builder.cache_path(path.clone());
+        }
+        if let Some(template) = &self.template {
+            builder = builder.template(template.clone());
+        }
+        if let Some(case) = self.name_case {
+            builder = builder.name_case(case);
+        }
+        if let Some(separator) = self.word_separator {
+            builder = builder.word_separator(separator);
+        }
+        if let Some(files) = self.series_min_files {
+            builder = builder.series_min_files(files);
+        }
+        if let Some(digits) = self.series_padding {
+            builder = builder.series_padding(digits);
+        }
+        if let Some(separator) = self.series_separator {
+            builder = builder.series_separator(separator);
+        }
+        if let Some(start) = self.series_start {
+            builder = builder.series_start(start);
+        }
+        if let Some(enable) = self.fix_extensions {
+            builder = builder.fix_extensions(enable);
+        }
+        if let Some(enable) = self.lowercase_extensions {
+            builder = builder.lowercase_extensions(enable);
+        }
+        if let Some(template) = &self.organize {
+            builder = builder.organize(template.clone());
+        }
+        if let Some(path) = &self.organize_root {
+            builder = builder.organize_root(path.clone());
+        }
+        if let Some(handling) = self.duplicates {
+            builder = builder.duplicates(handling);
+        }
+        if let Some(strategy) = self.collision_strategy {
+            builder = builder.collision_strategy(strategy);
+        }
+        if let Some(action) = self.on_conflict {
+            builder = builder.on_conflict(action);
+        }
+        if let Some(mode) = self.write_metadata {
+            builder = builder.write_metadata(mode);
+        }
+        if let Some(rename) = self.rename_sidecars {
+            builder = builder.rename_sidecars(rename);
+        }
+        if let Some(rename) = self.rename_directories {
+            builder = builder.rename_directories(rename);
+        }
+        if let Some(never) = self.never_touch_dns {
+            builder = builder.never_touch_dns(never);
+        }
+        if let Some(secs) = self.tool_timeout {
+            builder = builder.tool_timeout(std::time::Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.stable_for {
+            builder = builder.stable_for(std::time::Duration::from_secs(secs));
+        }
+        if let Some(megabytes) = self.max_file_size_mb {
+            builder = builder.max_file_size_mb(megabytes);
+        }
+        if let Some(pages) = self.max_pdf_pages {
+            builder = builder.max_pdf_pages(pages);
+        }
+        if let Some(minutes) = self.max_video_duration_min {
+            builder = builder.max_video_duration_min(minutes);
+        }
+        if let Some(policy) = self.symlinks {
+            builder = builder.symlinks(policy);
+        }
+        if let Some(score) = self.min_confidence {
+            builder = builder.min_confidence(score);
+        }
+        builder
+    }
+}
+
+/// Prefix of the environment variables read by `Profile::from_env`
+const ENV_PREFIX: &str = "NAMEBACK_";
+
+impl Profile {
+    /// Options set through `NAMEBACK_*` environment variables
+    ///
+    /// Each option maps to its upper-cased name (`NAMEBACK_INCLUDE_LOCATION=false`,
+    /// `NAMEBACK_CACHE_PATH=/tmp/metadata.sqlite`). Booleans accept 1/0, true/false, yes/no, on/off.
+    /// Glob and category lists are comma-separated (`NAMEBACK_EXCLUDE_GLOBS=node_modules/,*.tmp`),
+    /// OCR languages also `+`-separated as tesseract writes them (`NAMEBACK_OCR_LANGUAGES=eng+deu`).
+    /// Apply after the config file profile and before command-line flags.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let flag = |option: &str| -> Result<Option<bool>> {
+            let name = format!("{}{}", ENV_PREFIX, option.to_uppercase());
+            var(&name)
+                .map(|value| match value.trim().to_lowercase().as_str() {
+                    "1" | "true" | "yes" | "on" => Ok(true),
+                    "0" | "false" | "no" | "off" => Ok(false),
+                    _ => anyhow::bail!("{} must be true or false, got '{}'", name, value),
+                })
+                .transpose()
+        };
+
+        let globs = |option: &str| var(&format!("{}{}", ENV_PREFIX, option)).map(|value| split_globs(&value));
+
+        Ok(Self {
+            skip_hidden: flag("skip_hidden")?,
+            max_depth: var(&format!("{}MAX_DEPTH", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_DEPTH", ENV_PREFIX))?,
+            include_globs: globs("INCLUDE_GLOBS"),
+            exclude_globs: globs("EXCLUDE_GLOBS"),
+            categories: var(&format!("{}CATEGORIES", ENV_PREFIX))
+                .map(|value| {
+                    value
+                        .split(',')
+                        .filter(|name| !name.trim().is_empty())
+                        .map(str::parse)
+                        .collect::<Result<Vec<FileCategory>>>()
+                })
+                .transpose()
+                .with_context(|| format!("Invalid {}CATEGORIES", ENV_PREFIX))?,
+            include_location: flag("include_location")?,
+            include_timestamp: flag("include_timestamp")?,
+            timestamp_format: var(&format!("{}TIMESTAMP_FORMAT", ENV_PREFIX)),
+            timestamp_precision: var(&format!("{}TIMESTAMP_PRECISION", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}TIMESTAMP_PRECISION", ENV_PREFIX))?,
+            timestamp_position: var(&format!("{}TIMESTAMP_POSITION", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}TIMESTAMP_POSITION", ENV_PREFIX))?,
+            email_attachment_count: flag("email_attachment_count")?,
+            subject_hints: flag("subject_hints")?,
+            peek_archives: flag("peek_archives")?,
+            receipt_mode: flag("receipt_mode")?,
+            ocr_languages: var(&format!("{}OCR_LANGUAGES", ENV_PREFIX)).map(|value| {
+                value
+                    .split([',', '+'])
+                    .map(str::trim)
+                    .filter(|lang| !lang.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }),
+            multiframe_video: flag("multiframe_video")?,
+            max_parallel_files: var(&format!("{}MAX_PARALLEL_FILES", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_PARALLEL_FILES", ENV_PREFIX))?,
+            max_ocr_processes: var(&format!("{}MAX_OCR_PROCESSES", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_OCR_PROCESSES", ENV_PREFIX))?,
+            frame_aggregation: var(&format!("{}FRAME_AGGREGATION", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}FRAME_AGGREGATION", ENV_PREFIX))?,
+            video_frames: var(&format!("{}VIDEO_FRAMES", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}VIDEO_FRAMES", ENV_PREFIX))?,
+            key_phrase_count: var(&format!("{}KEY_PHRASE_COUNT", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}KEY_PHRASE_COUNT", ENV_PREFIX))?,
+            key_phrase_max_words: var(&format!("{}KEY_PHRASE_MAX_WORDS", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}KEY_PHRASE_MAX_WORDS", ENV_PREFIX))?,
+            min_ocr_confidence: var(&format!("{}MIN_OCR_CONFIDENCE", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MIN_OCR_CONFIDENCE", ENV_PREFIX))?,
+            metadata_backend: var(&format!("{}METADATA_BACKEND", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}METADATA_BACKEND", ENV_PREFIX))?,
+            geocode: flag("geocode")?,
+            geocode_source: var(&format!("{}GEOCODE_SOURCE", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}GEOCODE_SOURCE", ENV_PREFIX))?,
+            geocode_database: var(&format!("{}GEOCODE_DATABASE", ENV_PREFIX)).map(PathBuf::from),
+            location_precision: var(&format!("{}LOCATION_PRECISION", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}LOCATION_PRECISION", ENV_PREFIX))?,
+            location_blocklist: var(&format!("{}LOCATION_BLOCKLIST", ENV_PREFIX))
+                .map(|value| {
+                    value
+                        .split(';')
+                        .filter(|area| !area.trim().is_empty())
+                        .map(str::parse)
+                        .collect::<Result<Vec<BlockedArea>>>()
+                })
+                .transpose()
+                .with_context(|| format!("Invalid {}LOCATION_BLOCKLIST", ENV_PREFIX))?,
+            acoustid_key: var(&format!("{}ACOUSTID_KEY", ENV_PREFIX)),
+            paper_lookup: flag("paper_lookup")?,
+            enable_cache: flag("enable_cache")?,
+            cache_path: var(&format!("{}CACHE_PATH", ENV_PREFIX)).map(PathBuf::from),
+            template: var(&format!("{}TEMPLATE", ENV_PREFIX)),
+            name_case: var(&format!("{}NAME_CASE", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}NAME_CASE", ENV_PREFIX))?,
+            word_separator: var(&format!("{}WORD_SEPARATOR", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}WORD_SEPARATOR", ENV_PREFIX))?,
+            series_min_files: var(&format!("{}SERIES_MIN_FILES", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}SERIES_MIN_FILES", ENV_PREFIX))?,
+            series_padding: var(&format!("{}SERIES_PADDING", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}SERIES_PADDING", ENV_PREFIX))?,
+            series_separator: var(&format!("{}SERIES_SEPARATOR", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}SERIES_SEPARATOR", ENV_PREFIX))?,
+            series_start: var(&format!("{}SERIES_START", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}SERIES_START", ENV_PREFIX))?,
+            fix_extensions: flag("fix_extensions")?,
+            lowercase_extensions: flag("lowercase_extensions")?,
+            organize: var(&format!("{}ORGANIZE", ENV_PREFIX)),
+            organize_root: var(&format!("{}ORGANIZE_ROOT", ENV_PREFIX)).map(PathBuf::from),
+            duplicates: var(&format!("{}DUPLICATES", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}DUPLICATES", ENV_PREFIX))?,
+            collision_strategy: var(&format!("{}COLLISION_STRATEGY", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}COLLISION_STRATEGY", ENV_PREFIX))?,
+            on_conflict: var(&format!("{}ON_CONFLICT", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}ON_CONFLICT", ENV_PREFIX))?,
+            write_metadata: var(&format!("{}WRITE_METADATA", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}WRITE_METADATA", ENV_PREFIX))?,
+            rename_sidecars: flag("rename_sidecars")?,
+            rename_directories: flag("rename_directories")?,
+            min_confidence: var(&format!("{}MIN_CONFIDENCE", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MIN_CONFIDENCE", ENV_PREFIX))?,
+            never_touch_dns: flag("never_touch_dns")?,
+            tool_timeout: var(&format!("{}TOOL_TIMEOUT", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}TOOL_TIMEOUT", ENV_PREFIX))?,
+            stable_for: var(&format!("{}STABLE_FOR", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}STABLE_FOR", ENV_PREFIX))?,
+            max_file_size_mb: var(&format!("{}MAX_FILE_SIZE_MB", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_FILE_SIZE_MB", ENV_PREFIX))?,
+            max_pdf_pages: var(&format!("{}MAX_PDF_PAGES", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_PDF_PAGES", ENV_PREFIX))?,
+            max_video_duration_min: var(&format!("{}MAX_VIDEO_DURATION_MIN", ENV_PREFIX))
+                .map(|value| value.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}MAX_VIDEO_DURATION_MIN", ENV_PREFIX))?,
+            symlinks: var(&format!("{}SYMLINKS", ENV_PREFIX))
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("Invalid {}SYMLINKS", ENV_PREFIX))?,
+        })
+    }
+}
+
+/// Splits a comma-separated glob list, keeping commas inside `{a,b}` alternatives
+fn split_globs(value: &str) -> Vec<String> {
+    let mut patterns = vec![String::new()];
+    let mut braces = 0usize;
+    for c in value.chars() {
+        match c {
+            ',' if braces == 0 => patterns.push(String::new()),
+            _ => {
+                match c {
+                    '{' => braces += 1,
+                    '}' => braces = braces.saturating_sub(1),
+                    _ => {}
+                }
+                patterns.last_mut().unwrap().push(c);
+            }
+        }
+    }
+    patterns
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// The table whose `[header]` comes first in the file (`[profile.x]` rather than its
+/// implicit `profile` parent)
+fn first_header(table: &mut toml_edit::Table) -> Option<&mut toml_edit::Table> {
+    fn headers<'a>(table: &'a mut toml_edit::Table, found: &mut Vec<&'a mut toml_edit::Table>) {
+        for (_, item) in table.iter_mut() {
+            if let Some(child) = item.as_table_mut() {
+                if child.is_implicit() {
+                    headers(child, found);
+                } else {
+                    found.push(child);
+                }
+            }
+        }
+    }
+    let mut found = Vec::new();
+    headers(table, &mut found);
+    found.into_iter().min_by_key(|t| t.position().unwrap_or(usize::MAX))
+}
+
+/// Profiles available without a config file (a config file entry with the same name replaces them)
+static BUILTIN_PROFILES: LazyLock<BTreeMap<String, Profile>> = LazyLock::new(|| {
+    BTreeMap::from([
+        (
+            "photos".to_string(),
+            // Geocoding is on by default; setting it here would refuse `--no-location`
+            Profile {
+                include_location: Some(true),
+                include_timestamp: Some(true),
+                ..Profile::default()
+            },
+        ),
+        (
+            "scans".to_string(),
+            // Scanned documents are named from their content; location and time add noise
+            Profile {
+                include_location: Some(false),
+                include_timestamp: Some(false),
+                ..Profile::default()
+            },
+        ),
+        (
+            "forensics".to_string(),
+            // No network lookups and nothing about the evidence written to the cache
+            Profile {
+                skip_hidden: Some(false),
+                geocode: Some(false),
+                enable_cache: Some(false),
+                ..Profile::default()
+            },
+        ),
+    ])
+});
+
+/// Written by `ConfigFile::write_default` (`nameback --init-config`): every option, commented out
+const DEFAULT_CONFIG: &str = r#"# nameback configuration
+#
+# Options here apply to every run. Profiles (selected with --config-profile) and
+# NAMEBACK_* environment variables go on top, and command-line flags win over all.
+# Uncomment a line to change it from the default shown.
+
+# Skip hidden files and folders (names starting with a dot)
+# skip_hidden = false
+
+# Folder levels to descend (1 = only files directly in the directory)
+# max_depth = 3
+
+# Only rename files matching these globs / never touch files matching these
+# include_globs = ["*.pdf", "*.jpg"]
+# exclude_globs = ["node_modules/", "*.tmp"]
+
+# Only analyze these categories: image, document, audio, video, email, web, archive, installer, ebook, code
+# categories = ["image", "video"]
+
+# Add the GPS location / the date to names
+# include_location = true
+# include_timestamp = true
+
+# How the date is written: a strftime pattern (default "%Y-%m-%d"), "date" or
+# "datetime" (adds the time of day), and whether it ends or starts names
+# timestamp_format = "%Y%m%d"
+# timestamp_precision = "date"
+# timestamp_position = "suffix"
+
+# Add the number of attachments to email names (e.g. _3attachments)
+# email_attachment_count = false
+
+# Name photos without better names by their rough subject (Receipt, Whiteboard,
+# Screenshot, Document Scan, Portrait, Landscape)
+# subject_hints = false
+
+# Name archives after what's inside them (README/manifest title or top folder,
+# main file type, newest year), e.g. ProjectX_source_2021.zip
+# peek_archives = true
+
+# Name receipts and invoices (PDFs and photos) from their date, vendor and
+# total, e.g. 2023-10-15_AcmeCorp_invoice_142.50.pdf
+# receipt_mode = false
+
+# Tesseract languages OCR reads text in, used together. Each needs its language
+# pack installed (`tesseract --list-langs` shows them). Unset = Chinese and English
+# ocr_languages = ["eng", "deu", "jpn"]
+
+# Look up place names for GPS coordinates
+# geocode = true
+
+# Where place names come from: "offline" (a GeoNames city database, downloaded once,
+# about 10 MB), "online" (OpenStreetMap, one request per second) or "auto" (offline
+# once the database is there, otherwise online)
+# geocode_source = "auto"
+# geocode_database = "/path/to/cities1000.txt"
+
+# How precisely places are named: "country", "region", "city" or "coordinates".
+# Country and region never fall back to coordinates
+# location_precision = "city"
+
+# Never name places within a radius of these points ("latitude,longitude,radius",
+# radius in km or m, default 1km), e.g. around home
+# location_blocklist = ["47.61,-122.33,2km"]
+
+# Identify music without a title from its audio fingerprint (needs fpcalc from
+# Chromaprint, network access and a free API key from https://acoustid.org)
+# acoustid_key = "your-api-key"
+
+# Name PDFs of academic papers FirstAuthor_Year_Title from the Crossref or arXiv
+# record of the DOI or arXiv identifier on their first page (network access;
+# results are cached)
+# paper_lookup = false
+
+# Read several video frames ("vote" or "best") instead of one, at most
+# video_frames of them, picked at scene changes (title cards, slides)
+# multiframe_video = true
+# frame_aggregation = "vote"
+# video_frames = 6
+
+# Long text (PDF and text content, OCR) is named from its best key phrases:
+# how many of them, and how many words each may have
+# key_phrase_count = 1
+# key_phrase_max_words = 3
+
+# OCR drops the words tesseract is least sure of; text read with a lower mean
+# confidence (0-100) than this doesn't name files at all
+# min_ocr_confidence = 40
+
+# Files analyzed at once (default: one per CPU core) and OCR/media tools
+# (tesseract, ffmpeg, ...) run at once across them; lower these on laptops
+# max_parallel_files = 4
+# max_ocr_processes = 2
+
+# Seconds an external tool (exiftool, ffmpeg, ...) may spend on one file before
+# it is stopped and the file is reported as timed out
+# tool_timeout = 300
+
+# Seconds a file must go unmodified before it is analyzed or renamed, for folders
+# that slow copies or scanners are still writing to (temporary files, lock files
+# and incomplete downloads are always skipped)
+# stable_for = 10
+
+# Limits for pathological files: larger files are skipped, and longer PDFs and
+# videos are named from their metadata without reading or OCR'ing their content
+# max_file_size_mb = 4096
+# max_pdf_pages = 500
+# max_video_duration_min = 180
+
+# Metadata reader: "auto" (exiftool if installed), "exiftool" or "native"
+# metadata_backend = "auto"
+
+# Cache analysis results between runs (one database for every folder)
+# enable_cache = true
+# cache_path = "/path/to/metadata.sqlite"
+
+# Filename template, e.g. "{camera}_{name}" or "{category}/{yyyy}/{title}"
+# template = "{name}"
+
+# Case of proposed names: "keep" (as found), "snake", "kebab", "title" or "camel",
+# and what joins their words: "underscore", "hyphen" or "space" (default: the case's own)
+# name_case = "keep"
+# word_separator = "underscore"
+
+# Numbered files (IMG_001, ...) and photo bursts: the fewest files that make a
+# series, the digits numbers are padded to (default 3, 2 for bursts), what goes
+# before the number (default: the series' own) and the first number (default:
+# numbered series keep theirs, bursts count from 1)
+# series_min_files = 3
+# series_padding = 3
+# series_separator = "underscore"
+# series_start = 1
+
+# Give files whose content is another format than their extension says (a PNG
+# named .jpg) that format's extension when renaming; mismatches are reported either way
+# fix_extensions = false
+
+# Lower-case the extensions of new names (IMG_0042.JPG → Sunset.jpg); by default
+# they keep their case. Compound extensions such as .tar.gz are always kept whole
+# lowercase_extensions = false
+
+# Move files into folders built from this template, under organize_root
+# organize = "{yyyy}/{mm}/{location}"
+# organize_root = "/path/to/Sorted"
+
+# Exact copies of another file: "flag", "skip" or "suffix"
+# duplicates = "flag"
+
+# Symbolic links: "skip", "follow" (rename the files they point to) or
+# "rename-link" (name links after what they point to); hard links are scanned once
+# symlinks = "skip"
+
+# Proposed name taken by another file: "content", "number", "timestamp", "hash",
+# "keep-original" or "fail" (refuse the whole batch)
+# collision_strategy = "content"
+
+# New name taken by a file already on disk: "skip", "suffix", "trash" (move that file
+# to the system trash) or "overwrite"
+# on_conflict = "skip"
+
+# Write the derived title into file metadata: "off", "also" or "only"
+# write_metadata = "off"
+
+# Rename .xmp/.aae sidecars and RAW+JPEG twins along with their file
+# rename_sidecars = true
+
+# Also name folders still called "New Folder (3)" or the like after the files in them
+# rename_directories = false
+
+# Only propose names scoring at least this much (about 2-10; 5 or more is a good name)
+# min_confidence = 0.0
+
+# Never switch system DNS to public servers when installing dependencies runs into
+# network errors; retry with backoff instead (HTTP(S)_PROXY is honored either way)
+# never_touch_dns = false
+
+# Options for one category only: include_location, include_timestamp,
+# template and min_confidence
+#
+# [category.image]
+# template = "{camera}_{name}"
+#
+# [category.document]
+# include_location = false
+
+# Named profiles, selected with --config-profile <name>; they take every option above.
+# photos, scans and forensics are built in and can be redefined here.
+#
+# [profile.archive]
+# skip_hidden = true
+# categories = ["document", "code"]
+"#;
+
+impl ConfigFile {
+    /// Default config file location (`config.toml` in the per-user config directory)
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Load a config file; a missing file yields only the built-in profiles
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::parse(&data).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Load from `default_path`, or only the built-in profiles if there is none
+    pub fn load_default() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Top-level options, then the `category` and `profile` tables
+    fn parse(data: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(data)?;
+        let categories = match table.remove("category") {
+            Some(value) => value.try_into().context("Invalid [category] table")?,
+            None => HashMap::new(),
+        };
+        let profiles = match table.remove("profile") {
+            Some(value) => value.try_into().context("Invalid [profile] table")?,
+            None => BTreeMap::new(),
+        };
+        let settings = toml::Value::Table(table).try_into()?;
+        Ok(Self {
+            settings,
+            categories,
+            profiles,
+        })
+    }
+
+    /// Write the commented default file to `path` (refusing to replace an existing one)
+    pub fn write_default(path: &Path) -> Result<()> {
+        if path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)
+                .with_context(|| format!("Failed to create {}", folder.display()))?;
+        }
+        fs::write(path, DEFAULT_CONFIG)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Replace the top-level options in the file at `path` with `settings`, keeping its
+    /// comments, categories and profiles (a missing file starts from the commented default)
+    pub fn save_settings(&mut self, path: &Path, settings: Profile) -> Result<()> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DEFAULT_CONFIG.to_string(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read config file {}", path.display()))
+            }
+        };
+        let mut document: toml_edit::DocumentMut = data
+            .parse()
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        let values = toml::Table::try_from(&settings).context("Failed to serialize settings")?;
+        let stale: Vec<String> = document
+            .iter()
+            .filter(|(key, item)| item.is_value() && !values.contains_key(*key))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for key in stale {
+            document.remove(&key);
+        }
+        let has_values = !values.is_empty();
+        for (key, value) in values {
+            let value: toml_edit::Value = value.to_string().parse()?;
+            document[key.as_str()] = toml_edit::value(value);
+        }
+        // Options come first; keep a blank line before the comments and tables below them
+        if let Some(table) = first_header(document.as_table_mut()).filter(|_| has_values) {
+            let prefix = table.decor().prefix().and_then(|p| p.as_str()).unwrap_or_default().to_string();
+            if !prefix.starts_with('\n') {
+                table.decor_mut().set_prefix(format!("\n{}", prefix));
+            }
+        }
+
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)
+                .with_context(|| format!("Failed to create {}", folder.display()))?;
+        }
+        fs::write(path, document.to_string())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Apply the file's top-level options and category overrides on top of `builder`
+    pub fn apply(&self, builder: RenameConfigBuilder) -> RenameConfigBuilder {
+        self.categories
+            .iter()
+            .fold(self.settings.apply(builder), |builder, (category, overrides)| {
+                builder.category_overrides(category.clone(), overrides.clone())
+            })
+    }
+
+    /// Look up a profile by name (the file's, or else a built-in one)
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name).or_else(|| BUILTIN_PROFILES.get(name))
+    }
+
+    /// Names of all available profiles, sorted
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        let mut names: Vec<&str> = self
+            .profiles
+            .keys()
+            .chain(BUILTIN_PROFILES.keys())
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_profiles_and_builtins() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("config.toml");
+        fs::write(
+            &path,
+            r#"
+include_location = false
+exclude_globs = ["node_modules/"]
+
+[category.image]
+include_location = true
+template = "{camera}_{name}"
+
+[profile.scans]
+include_timestamp = true
+
+[profile.archive]
+skip_hidden = true
+categories = ["document", "code"]
+"#,
+        )?;
+
+        let config = ConfigFile::load(&path)?;
+        let names: Vec<&str> = config.profile_names().collect();
+        assert_eq!(names, vec!["archive", "forensics", "photos", "scans"]);
+
+        // File entry replaces the built-in profile of the same name
+        let scans = config.profile("scans").unwrap();
+        assert_eq!(scans.include_timestamp, Some(true));
+        assert_eq!(scans.include_location, None);
+
+        let built = config
+            .profile("archive")
+            .unwrap()
+            .apply(RenameConfigBuilder::new())
+            .build()?;
+        assert!(built.skip_hidden);
+        assert!(built.includes_category(&FileCategory::SourceCode));
+        assert!(!built.includes_category(&FileCategory::Image));
+
+        // Flags still override a built-in profile, `--no-location` included
+        let photos = config.profile("photos").unwrap().apply(RenameConfigBuilder::new());
+        assert!(!photos.include_location(false).build()?.include_location);
+
+        // Top-level options and category overrides apply without a profile
+        let built = config.apply(RenameConfigBuilder::new()).build()?;
+        assert!(!built.include_location);
+        assert_eq!(built.exclude_globs, ["node_modules/"]);
+        let images = &built.category_overrides[&FileCategory::Image];
+        assert_eq!(images.include_location, Some(true));
+        assert_eq!(images.template.as_deref(), Some("{camera}_{name}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_and_save_settings() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("nameback").join("config.toml");
+        ConfigFile::write_default(&path)?;
+        assert!(ConfigFile::write_default(&path).is_err());
+        // Everything is commented out, so the defaults are unchanged
+        let mut config = ConfigFile::load(&path)?;
+        assert_eq!(config, ConfigFile::default());
+
+        fs::write(&path, fs::read_to_string(&path)? + "\n[profile.archive]\nskip_hidden = true\n")?;
+        let settings = Profile {
+            geocode: Some(false),
+            exclude_globs: Some(vec!["*.tmp".to_string()]),
+            ..Profile::default()
+        };
+        config.save_settings(&path, settings.clone())?;
+        config.save_settings(&path, Profile { geocode: Some(true), ..settings })?;
+
+        let data = fs::read_to_string(&path)?;
+        assert!(data.starts_with("exclude_globs = [\"*.tmp\"]\ngeocode = true\n\n# nameback configuration"));
+        let saved = ConfigFile::load(&path)?;
+        assert_eq!(saved.settings.geocode, Some(true));
+        assert_eq!(saved.settings.exclude_globs, Some(vec!["*.tmp".to_string()]));
+        assert_eq!(saved.profile("archive").unwrap().skip_hidden, Some(true));
+        assert_eq!(saved.settings, config.settings);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_file_and_unknown_keys() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config = ConfigFile::load(&temp_dir.path().join("missing.toml"))?;
+        assert!(config.profile("forensics").is_some());
+
+        let path = temp_dir.path().join("config.toml");
+        fs::write(&path, "[profile.photos]\ngeocod = true\n")?;
+        assert!(ConfigFile::load(&path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_from_env_vars() {
+        let vars = |name: &str| match name {
+            "NAMEBACK_GEOCODE" => Some("off".to_string()),
+            "NAMEBACK_SKIP_HIDDEN" => Some("Yes".to_string()),
+            "NAMEBACK_CACHE_PATH" => Some("/tmp/metadata.sqlite".to_string()),
+            "NAMEBACK_TEMPLATE" => Some("{camera}_{name}".to_string()),
+            "NAMEBACK_NAME_CASE" => Some("kebab-case".to_string()),
+            "NAMEBACK_WORD_SEPARATOR" => Some("space".to_string()),
+            "NAMEBACK_TIMESTAMP_FORMAT" => Some("%d.%m.%Y".to_string()),
+            "NAMEBACK_TIMESTAMP_PRECISION" => Some("DateTime".to_string()),
+            "NAMEBACK_TIMESTAMP_POSITION" => Some("prefix".to_string()),
+            "NAMEBACK_FRAME_AGGREGATION" => Some("best".to_string()),
+            "NAMEBACK_VIDEO_FRAMES" => Some("12".to_string()),
+            "NAMEBACK_KEY_PHRASE_COUNT" => Some("2".to_string()),
+            "NAMEBACK_MIN_OCR_CONFIDENCE" => Some(" 60".to_string()),
+            "NAMEBACK_TOOL_TIMEOUT" => Some("60".to_string()),
+            "NAMEBACK_STABLE_FOR" => Some("15".to_string()),
+            "NAMEBACK_MAX_PDF_PAGES" => Some("200".to_string()),
+            "NAMEBACK_MIN_CONFIDENCE" => Some("5.5".to_string()),
+            "NAMEBACK_METADATA_BACKEND" => Some("native".to_string()),
+            "NAMEBACK_GEOCODE_SOURCE" => Some("Offline".to_string()),
+            "NAMEBACK_LOCATION_PRECISION" => Some("Region".to_string()),
+            "NAMEBACK_LOCATION_BLOCKLIST" => Some("47.61,-122.33,2km; 48.85,2.35".to_string()),
+            "NAMEBACK_MAX_DEPTH" => Some("2".to_string()),
+            "NAMEBACK_MAX_OCR_PROCESSES" => Some(" 2".to_string()),
+            "NAMEBACK_EXCLUDE_GLOBS" => Some("node_modules/, *.{tmp,bak}".to_string()),
+            "NAMEBACK_CATEGORIES" => Some("images, video".to_string()),
+            "NAMEBACK_ORGANIZE" => Some("{yyyy}/{mm}".to_string()),
+            "NAMEBACK_DUPLICATES" => Some("Suffix".to_string()),
+            "NAMEBACK_SYMLINKS" => Some("rename_link".to_string()),
+            "NAMEBACK_COLLISION_STRATEGY" => Some("keep_original".to_string()),
+            "NAMEBACK_ON_CONFLICT" => Some("Trash".to_string()),
+            "NAMEBACK_WRITE_METADATA" => Some("also".to_string()),
+            "NAMEBACK_RENAME_SIDECARS" => Some("off".to_string()),
+            "NAMEBACK_RENAME_DIRECTORIES" => Some("1".to_string()),
+            "NAMEBACK_NEVER_TOUCH_DNS" => Some("yes".to_string()),
+            "NAMEBACK_PEEK_ARCHIVES" => Some("no".to_string()),
+            "NAMEBACK_RECEIPT_MODE" => Some("1".to_string()),
+            "NAMEBACK_PAPER_LOOKUP" => Some("on".to_string()),
+            "NAMEBACK_SERIES_MIN_FILES" => Some("4".to_string()),
+            "NAMEBACK_SERIES_SEPARATOR" => Some("hyphen".to_string()),
+            "NAMEBACK_FIX_EXTENSIONS" => Some("true".to_string()),
+            "NAMEBACK_LOWERCASE_EXTENSIONS" => Some("yes".to_string()),
+            "NAMEBACK_OCR_LANGUAGES" => Some("eng+deu, jpn".to_string()),
+            _ => None,
+        };
+        let profile = Profile::from_vars(vars).unwrap();
+        assert_eq!(profile.geocode, Some(false));
+        assert_eq!(profile.skip_hidden, Some(true));
+        assert_eq!(profile.include_location, None);
+        assert_eq!(profile.peek_archives, Some(false));
+        assert_eq!(profile.receipt_mode, Some(true));
+        assert_eq!(profile.paper_lookup, Some(true));
+        assert_eq!((profile.series_min_files, profile.series_padding), (Some(4), None));
+        assert_eq!(profile.series_separator, Some(WordSeparator::Hyphen));
+        assert_eq!((profile.fix_extensions, profile.lowercase_extensions), (Some(true), Some(true)));
+        assert_eq!(profile.cache_path, Some(PathBuf::from("/tmp/metadata.sqlite")));
+        assert_eq!(profile.template.as_deref(), Some("{camera}_{name}"));
+        assert_eq!(profile.name_case, Some(NameCase::Kebab));
+        assert_eq!(profile.word_separator, Some(WordSeparator::Space));
+        assert_eq!(profile.timestamp_format.as_deref(), Some("%d.%m.%Y"));
+        assert_eq!(profile.timestamp_precision, Some(TimestampPrecision::DateTime));
+        assert_eq!(profile.timestamp_position, Some(TimestampPosition::Prefix));
+        assert_eq!(profile.frame_aggregation, Some(FrameAggregation::Best));
+        assert_eq!(profile.video_frames, Some(12));
+        assert_eq!((profile.key_phrase_count, profile.key_phrase_max_words), (Some(2), None));
+        assert_eq!(profile.min_ocr_confidence, Some(60));
+        assert_eq!(profile.tool_timeout, Some(60));
+        assert_eq!(profile.stable_for, Some(15));
+        assert_eq!(profile.max_pdf_pages, Some(200));
+        assert_eq!(profile.max_file_size_mb, None);
+        assert_eq!(profile.min_confidence, Some(5.5));
+        assert_eq!(profile.metadata_backend, Some(MetadataBackend::Native));
+        assert_eq!(profile.geocode_source, Some(GeocodeSource::Offline));
+        assert_eq!(profile.geocode_database, None);
+        assert_eq!(profile.location_precision, Some(LocationPrecision::Region));
+        assert_eq!(
+            profile.location_blocklist,
+            Some(vec!["47.61,-122.33,2km".parse().unwrap(), "48.85,2.35,1km".parse().unwrap()])
+        );
+        assert_eq!(profile.max_depth, Some(2));
+        assert_eq!(profile.max_ocr_processes, Some(2));
+        assert_eq!(profile.max_parallel_files, None);
+        assert_eq!(
+            profile.exclude_globs,
+            Some(vec!["node_modules/".to_string(), "*.{tmp,bak}".to_string()])
+        );
+        assert_eq!(profile.include_globs, None);
+        assert_eq!(profile.organize.as_deref(), Some("{yyyy}/{mm}"));
+        assert_eq!(profile.organize_root, None);
+        assert_eq!(profile.duplicates, Some(DuplicateHandling::Suffix));
+        assert_eq!(profile.symlinks, Some(SymlinkPolicy::RenameLink));
+        assert_eq!(profile.collision_strategy, Some(CollisionStrategy::KeepOriginal));
+        assert_eq!(profile.on_conflict, Some(OnConflict::Trash));
+        assert_eq!(profile.write_metadata, Some(MetadataWriteMode::Also));
+        assert_eq!(profile.rename_sidecars, Some(false));
+        assert_eq!(profile.rename_directories, Some(true));
+        assert_eq!(profile.never_touch_dns, Some(true));
+        assert_eq!(profile.categories, Some(vec![FileCategory::Image, FileCategory::Video]));
+        assert_eq!(
+            profile.ocr_languages,
+            Some(vec!["eng".to_string(), "deu".to_string(), "jpn".to_string()])
+        );
+
+        let err = Profile::from_vars(|name| (name == "NAMEBACK_ENABLE_CACHE").then(|| "maybe".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("NAMEBACK_ENABLE_CACHE"));
+    }
+}
@@ -1,5 +1,7 @@
+use std::fmt;
 use std::process::Command;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
 
 // Platform-specific dependency installation modules
 #[cfg(target_os = "windows")]
@@ -14,29 +16,12 @@ mod logger;
 // Constants for external URLs and installation
 mod constants {
     /// GitHub Release URLs
+    #[cfg_attr(not(windows), allow(dead_code))] // Only used by the Windows bundled installer
     pub const GITHUB_RELEASES_BASE: &str = "https://github.com/h4x0r/nameback/releases/download";
 
-    /// Package manager installation URLs
-    #[allow(dead_code)] // Used in error messages and future refactoring
+    /// Scoop's installation script
+    #[cfg_attr(not(windows), allow(dead_code))] // Only used by the Windows Scoop installer
     pub const SCOOP_INSTALL: &str = "https://get.scoop.sh";
-    #[allow(dead_code)]
-    pub const SCOOP_WEBSITE: &str = "https://scoop.sh";
-    #[allow(dead_code)]
-    pub const CHOCOLATEY_INSTALL: &str = "https://community.chocolatey.org/install.ps1";
-    #[allow(dead_code)]
-    pub const HOMEBREW_WEBSITE: &str = "https://brew.sh";
-    #[allow(dead_code)]
-    pub const MACPORTS_WEBSITE: &str = "https://www.macports.org";
-
-    /// Dependency download URLs
-    #[allow(dead_code)]
-    pub const EXIFTOOL_WEBSITE: &str = "https://exiftool.org/";
-    #[allow(dead_code)]
-    pub const TESSERACT_WEBSITE: &str = "https://github.com/UB-Mannheim/tesseract/wiki";
-    #[allow(dead_code)]
-    pub const FFMPEG_WEBSITE: &str = "https://ffmpeg.org/download.html";
-    #[allow(dead_code)]
-    pub const IMAGEMAGICK_WEBSITE: &str = "https://imagemagick.org/script/download.php";
 }
 
 // Windows MSI progress reporting
@@ -101,35 +86,6 @@ mod msi_progress {
     pub fn report_action_data(_message: &str) {}
 }
 
-/// Centralized progress reporting for dependency installation
-struct ProgressReporter<'a> {
-    callback: &'a Option<ProgressCallback>,
-}
-
-impl<'a> ProgressReporter<'a> {
-    fn new(callback: &'a Option<ProgressCallback>) -> Self {
-        Self { callback }
-    }
-
-    /// Report installation progress with message and percentage
-    fn report(&self, message: &str, percentage: u8) {
-        // Always report to MSI progress (noop on non-Windows)
-        msi_progress::report_action_data(message);
-
-        // Report via callback or println
-        if let Some(ref cb) = self.callback {
-            cb(message, percentage);
-        } else {
-            println!("[{}%] {}", percentage, message);
-        }
-    }
-
-    /// Report action start (primarily for MSI)
-    fn report_action(&self, action_name: &str) {
-        msi_progress::report_action_start(action_name);
-    }
-}
-
 /// Represents a dependency and its installation status
 #[derive(Debug, Clone, Copy)]
 pub struct Dependency {
@@ -163,14 +119,20 @@ pub const DEPENDENCIES: &[Dependency] = &[
         name: "ImageMagick",
         command: "magick",
         required: false,
-        description: "Optional - enables HEIC image support on Windows/Linux",
+        description: "Optional - enables HEIC and camera RAW image support on Windows/Linux",
+    },
+    Dependency {
+        name: "Chromaprint",
+        command: "fpcalc",
+        required: false,
+        description: "Optional - identifies untitled music via AcoustID (needs acoustid_key)",
     },
 ];
 
 /// Checks if a command is available in the system PATH
 pub fn is_command_available(command: &str) -> bool {
-    // FFmpeg uses single dash (-version) instead of double dash (--version)
-    let version_flag = if command == "ffmpeg" { "-version" } else { "--version" };
+    // FFmpeg and fpcalc use single dash (-version) instead of double dash (--version)
+    let version_flag = if matches!(command, "ffmpeg" | "fpcalc") { "-version" } else { "--version" };
 
     Command::new(command)
         .arg(version_flag)
@@ -195,6 +157,7 @@ pub fn print_dependency_status() {
 
     let statuses = check_dependencies();
     let mut all_required_installed = true;
+    let mut all_supported = true;
 
     for (dep, installed) in &statuses {
         let status = if *installed { "✓" } else { "✗" };
@@ -207,6 +170,16 @@ pub fn print_dependency_status() {
         println!("{} {} {}", status, dep.name, required_label);
         println!("   {}", dep.description);
 
+        // Version, and what an older one than supported costs
+        let tool = crate::deps_check::Dependency::from_command(dep.command).filter(|_| *installed);
+        if let Some((tool, version)) = tool.and_then(|tool| tool.version().map(|version| (tool, version))) {
+            println!("   Version: {}", version);
+            if let Some(outdated) = tool.check_version(&version) {
+                println!("   ⚠ {}", outdated);
+                all_supported = false;
+            }
+        }
+
         if dep.required && !installed {
             all_required_installed = false;
         }
@@ -220,19 +193,172 @@ pub fn print_dependency_status() {
         println!("⚠ WARNING: Some required dependencies are missing!");
         println!("Run 'nameback --install-deps' to install them.\n");
     }
+
+    if !all_supported {
+        println!("⚠ WARNING: Some dependencies are older than supported; update them with your package manager.\n");
+    }
 }
 
 /// Progress callback for dependency installation
 pub type ProgressCallback = Box<dyn Fn(&str, u8) + Send + Sync>;
 
-/// Runs the appropriate installer script based on the platform
-pub fn run_installer() -> Result<(), String> {
-    run_installer_with_progress(None)
+/// How dependency installation may use the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstallOptions {
+    /// Never switch system DNS to public servers after a network error (e.g. on managed
+    /// machines); failed downloads are retried with backoff instead
+    pub never_touch_dns: bool,
+}
+
+impl InstallOptions {
+    /// The install options set in a rename configuration
+    pub fn from_config(config: &crate::RenameConfig) -> Self {
+        Self {
+            never_touch_dns: config.never_touch_dns,
+        }
+    }
+}
+
+/// Why a dependency couldn't be installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallError {
+    /// None of the supported package managers (listed) is installed
+    NoPackageManager(String),
+    /// The package manager couldn't be set up (Scoop on Windows)
+    PackageManagerSetup(String),
+    /// Every package manager tried failed to install the tool
+    InstallFailed {
+        dependency: crate::deps_check::Dependency,
+        details: String,
+    },
+    /// The install finished but the tool still isn't found (e.g. not yet on PATH)
+    NotFoundAfterInstall(crate::deps_check::Dependency),
+    /// Dependencies can't be installed automatically on this platform
+    UnsupportedPlatform,
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallError::NoPackageManager(supported) => {
+                write!(f, "no supported package manager found (install {})", supported)
+            }
+            InstallError::PackageManagerSetup(details) => {
+                write!(f, "failed to set up the package manager: {}", details)
+            }
+            InstallError::InstallFailed { dependency, details } => {
+                write!(f, "failed to install {}: {}", dependency.name(), details)
+            }
+            InstallError::NotFoundAfterInstall(dependency) => write!(
+                f,
+                "{} was installed but can't be found; restart nameback or add it to PATH",
+                dependency.name()
+            ),
+            InstallError::UnsupportedPlatform => {
+                write!(f, "unsupported platform, please install dependencies manually")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstallError {}
+
+/// Times a failed download or package install is retried when DNS is left alone
+pub(crate) const NETWORK_RETRIES: u32 = 3;
+
+/// Give up on a single download attempt after this long
+#[cfg_attr(not(windows), allow(dead_code))] // Only the Windows installers download directly
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Proxy variables passed on to package managers run through sudo, which drops them
+#[cfg_attr(windows, allow(dead_code))] // No sudo on Windows
+pub(crate) const PROXY_VARS: [&str; 8] = [
+    "HTTP_PROXY", "http_proxy", "HTTPS_PROXY", "https_proxy",
+    "ALL_PROXY", "all_proxy", "NO_PROXY", "no_proxy",
+];
+
+/// Pause before retry number `retry` (from 0): 2s, 4s, 8s, ...
+pub(crate) fn backoff(retry: u32) -> Duration {
+    Duration::from_secs(2u64 << retry.min(5))
+}
+
+/// Tries `attempt` again up to NETWORK_RETRIES times, pausing longer before each, until it works
+#[cfg_attr(windows, allow(dead_code))] // Windows retries through `download`
+pub(crate) fn retry_with_backoff(what: &str, mut attempt: impl FnMut() -> bool) -> bool {
+    for retry in 0..NETWORK_RETRIES {
+        let delay = backoff(retry);
+        println!("Retrying {} in {}s ({}/{})...", what, delay.as_secs(), retry + 1, NETWORK_RETRIES);
+        std::thread::sleep(delay);
+        if attempt() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Names of the proxy variables that are set, as `var` reports them
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn proxy_vars_in(var: impl Fn(&str) -> Option<String>) -> Vec<&'static str> {
+    PROXY_VARS
+        .into_iter()
+        .filter(|name| var(name).is_some_and(|value| !value.trim().is_empty()))
+        .collect()
+}
+
+/// Names of the proxy variables set in this process's environment
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn proxy_vars() -> Vec<&'static str> {
+    proxy_vars_in(|name| std::env::var(name).ok())
+}
+
+/// The proxy for HTTPS downloads from HTTPS_PROXY or ALL_PROXY (either case), if any
+#[cfg_attr(not(windows), allow(dead_code))] // Only PowerShell downloads need it passed explicitly
+pub(crate) fn https_proxy() -> Option<String> {
+    ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
 }
 
-/// Runs the installer with optional progress callback
+/// Downloads `url` through any proxy set in HTTP(S)_PROXY (reqwest honors them), retrying
+/// connection errors, timeouts and server errors with backoff
+#[cfg_attr(not(windows), allow(dead_code))] // Only the Windows installers download directly
+pub(crate) fn download(url: &str) -> Result<Vec<u8>, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut retry = 0;
+    loop {
+        let error = match client.get(url).send() {
+            Ok(response) if response.status().is_success() => {
+                return response
+                    .bytes()
+                    .map(|bytes| bytes.to_vec())
+                    .map_err(|e| format!("Failed to read response: {}", e));
+            }
+            Ok(response) if response.status().is_server_error() => format!("HTTP {}", response.status()),
+            Ok(response) => return Err(format!("Failed to download {} (HTTP {})", url, response.status())),
+            Err(e) if e.is_connect() || e.is_timeout() => e.to_string(),
+            Err(e) => return Err(format!("Failed to download {}: {}", url, e)),
+        };
+        if retry == NETWORK_RETRIES {
+            return Err(format!("Failed to download {} after {} retries: {}", url, NETWORK_RETRIES, error));
+        }
+        let delay = backoff(retry);
+        println!("Download of {} failed ({}), retrying in {}s...", url, error, delay.as_secs());
+        std::thread::sleep(delay);
+        retry += 1;
+    }
+}
+
+/// Runs the appropriate installer for the platform, with optional progress callback
 /// Callback receives: (status_message, percentage)
-pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result<(), String> {
+pub fn run_installer_with_progress(
+    progress: Option<ProgressCallback>,
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
     let is_interactive = progress.is_none();
 
     // Initialize logger
@@ -288,7 +414,9 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
     {
         // Try switching to public DNS first to avoid SSL/network issues during Scoop installation
         report_progress("Checking network connectivity...", 5);
-        if let Err(e) = windows::try_with_public_dns() {
+        if options.never_touch_dns {
+            // Leave DNS alone; downloads are retried with backoff instead
+        } else if let Err(e) = windows::try_with_public_dns() {
             if is_interactive {
                 println!("Warning: Failed to switch to public DNS: {}", e);
                 println!("Continuing with current DNS settings...");
@@ -308,7 +436,8 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
             } else {
                 println!("[{}%] {}", pct, msg);
             }
-        })?;
+        }, options)
+        .map_err(InstallError::PackageManagerSetup)?;
 
         // Install all Windows dependencies via Scoop (with Chocolatey/bundled fallbacks)
         windows::install_dependencies_via_scoop(&scoop_cmd, move |msg: &str, pct: u8| {
@@ -319,6 +448,10 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
             } else {
                 println!("[{}%] {}", pct, msg);
             }
+        })
+        .map_err(|details| InstallError::InstallFailed {
+            dependency: crate::deps_check::Dependency::ExifTool,
+            details,
         })?;
 
         // Ensure DNS is restored even if we didn't explicitly restore it earlier
@@ -328,7 +461,7 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
     #[cfg(target_os = "macos")]
     {
         // Install all macOS dependencies via Homebrew/MacPorts
-        macos::install_dependencies(&report_progress)?;
+        macos::install_dependencies(&report_progress, options)?;
 
         // Ensure DNS is restored
         macos::restore_dns();
@@ -336,7 +469,7 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
 
     #[cfg(target_os = "linux")]
     {
-        linux::install_dependencies(&report_progress)?;
+        linux::install_dependencies(&report_progress, options)?;
 
         // Ensure DNS is restored
         linux::restore_dns();
@@ -344,7 +477,7 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
 
     #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
-        return Err("Unsupported platform. Please install dependencies manually.".to_string());
+        return Err(InstallError::UnsupportedPlatform);
     }
 
     if is_interactive {
@@ -358,16 +491,112 @@ pub fn run_installer_with_progress(progress: Option<ProgressCallback>) -> Result
 
     // Finalize logger and report log location
     logger_arc.finalize();
-    logger_arc.info(&format!("Installation completed successfully"));
+    logger_arc.info("Installation completed successfully");
     logger_arc.info(&format!("Log saved to: {:?}", logger_arc.log_path()));
 
     Ok(())
 }
 
+/// Installs a single dependency with the platform's package manager, then checks it can be found
+/// Callback receives: (status_message, percentage)
+pub fn run_single_installer(
+    dependency: crate::deps_check::Dependency,
+    progress: Option<ProgressCallback>,
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
+    let progress = Arc::new(progress);
+    let report_progress = |msg: &str, pct: u8| report_to(&progress, msg, pct);
+
+    msi_progress::report_action_start(&format!("Installing {}", dependency.name()));
+    report_progress(&format!("Installing {}...", dependency.name()), 0);
+
+    #[cfg(target_os = "windows")]
+    {
+        let progress_for_scoop = Arc::clone(&progress);
+        let scoop_cmd = windows::ensure_scoop_installed(move |msg: &str, pct: u8| {
+            report_to(&progress_for_scoop, msg, pct)
+        }, options)
+        .map_err(InstallError::PackageManagerSetup)?;
+        windows::install_dependency_via_scoop(&scoop_cmd, dependency, &report_progress);
+        windows::restore_dns();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let result = macos::install_dependency(dependency, &report_progress, options);
+        macos::restore_dns();
+        result?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let result = linux::install_dependency(dependency, &report_progress, options);
+        linux::restore_dns();
+        result?;
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        return Err(InstallError::UnsupportedPlatform);
+    }
+
+    if !dependency.is_available() {
+        return Err(InstallError::NotFoundAfterInstall(dependency));
+    }
+    report_progress(&format!("{} installed", dependency.name()), 100);
+    Ok(())
+}
+
+/// Reports installation progress to the callback (or stdout) and the MSI log
+fn report_to(progress: &Option<ProgressCallback>, msg: &str, pct: u8) {
+    tracing::info!("[{}%] {}", pct, msg);
+    msi_progress::report_action_data(msg);
+    match progress {
+        Some(cb) => cb(msg, pct),
+        None => println!("[{}%] {}", pct, msg),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_install_error_messages() {
+        use crate::deps_check::Dependency;
+
+        let failed = InstallError::InstallFailed {
+            dependency: Dependency::Tesseract,
+            details: "failed with all available package managers".to_string(),
+        };
+        assert_eq!(failed.to_string(), "failed to install tesseract: failed with all available package managers");
+        assert_eq!(
+            InstallError::NotFoundAfterInstall(Dependency::FFmpeg).to_string(),
+            "ffmpeg was installed but can't be found; restart nameback or add it to PATH"
+        );
+        // Usable with anyhow and downcast back by callers that want the kind
+        let error = anyhow::Error::from(InstallError::UnsupportedPlatform);
+        assert_eq!(error.downcast_ref::<InstallError>(), Some(&InstallError::UnsupportedPlatform));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let delays: Vec<u64> = (0..NETWORK_RETRIES).map(|retry| backoff(retry).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8]);
+    }
+
+    #[test]
+    fn test_proxy_vars_in() {
+        let vars = |name: &str| match name {
+            "https_proxy" => Some("http://proxy.corp:3128".to_string()),
+            "NO_PROXY" => Some("localhost,.corp".to_string()),
+            "HTTP_PROXY" => Some(" ".to_string()),
+            _ => None,
+        };
+        assert_eq!(proxy_vars_in(vars), ["https_proxy", "NO_PROXY"]);
+        assert!(proxy_vars_in(|_| None).is_empty());
+    }
+
     #[test]
     fn test_is_command_available_for_existing_command() {
         // Test with commands that support --version on all platforms
@@ -407,7 +636,7 @@ mod tests {
     #[test]
     fn test_dependencies_have_valid_names() {
         // Test that all dependencies in DEPENDENCIES have proper names
-        assert_eq!(DEPENDENCIES.len(), 4);
+        assert_eq!(DEPENDENCIES.len(), 5);
 
         let exiftool = DEPENDENCIES.iter().find(|d| d.name == "ExifTool");
         assert!(exiftool.is_some());
@@ -440,8 +669,8 @@ mod tests {
 
     #[test]
     fn test_optional_dependencies() {
-        // Tesseract, FFmpeg, and ImageMagick should be optional
+        // Tesseract, FFmpeg, ImageMagick and Chromaprint should be optional
         let optional_count = DEPENDENCIES.iter().filter(|d| !d.required).count();
-        assert_eq!(optional_count, 3);
+        assert_eq!(optional_count, 4);
     }
 }
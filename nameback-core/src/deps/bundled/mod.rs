@@ -3,6 +3,7 @@
 //! This module provides bundled installers as a final fallback when
 //! package managers fail. Downloads pre-packaged binaries from GitHub Releases.
 
+mod verify;
 #[cfg(target_os = "windows")]
 mod windows;
 
@@ -0,0 +1,162 @@
+//! Checksum and signature checks of bundled dependency downloads, made before anything
+//! is extracted or run
+
+#![cfg_attr(not(windows), allow(dead_code))] // Only the Windows bundled installer downloads assets
+
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of every bundled asset of this release (see checksums.txt)
+const CHECKSUMS: &str = include_str!("checksums.txt");
+
+// Windows release builds ship the fallback that installs these assets; without a manifest it
+// would refuse every one of them (see RELEASING.md)
+#[cfg(all(windows, not(debug_assertions)))]
+const _: () = assert!(
+    lists_checksums(CHECKSUMS),
+    "checksums.txt lists no assets; run scripts/bundle-release-deps-windows.sh --write-manifest"
+);
+
+/// minisign public key (base64) that `<asset>.minisig` signatures are checked against;
+/// signatures are required only once a key is set
+const SIGNING_KEY: Option<&str> = None;
+
+/// Checks a downloaded asset against the embedded checksum manifest and, when a signing key
+/// is set, against the signature `fetch_signature` downloads
+pub fn verify_asset(
+    asset: &str,
+    data: &[u8],
+    fetch_signature: impl FnOnce() -> Result<Vec<u8>, String>,
+) -> Result<(), String> {
+    verify_checksum(CHECKSUMS, asset, data)?;
+    if let Some(key) = SIGNING_KEY {
+        let signature = fetch_signature()
+            .map_err(|e| format!("No signature for {}, refusing to install it: {}", asset, e))?;
+        let signature = String::from_utf8(signature)
+            .map_err(|_| format!("Signature of {} is not text", asset))?;
+        verify_signature(key, data, &signature)
+            .map_err(|e| format!("{}: {}, refusing to install it", asset, e))?;
+    }
+    Ok(())
+}
+
+/// The checksum `manifest` lists for `asset` (lines of `<sha256>  <name>`, `#` comments)
+fn expected_checksum<'a>(manifest: &'a str, asset: &str) -> Option<&'a str> {
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        // sha256sum marks binary mode with '*'
+        .find(|(_, name)| name.trim_start().trim_start_matches('*') == asset)
+        .map(|(checksum, _)| checksum)
+}
+
+/// Whether `manifest` has any line besides blank lines and `#` comments
+const fn lists_checksums(manifest: &str) -> bool {
+    let bytes = manifest.as_bytes();
+    // Only whitespace seen since the start of the line
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'\n' {
+            line_start = true;
+        } else if line_start && byte == b'#' {
+            line_start = false;
+        } else if line_start && !byte.is_ascii_whitespace() {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Lowercase hex SHA-256 of `data`
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Errs unless `manifest` lists `asset` with the SHA-256 of `data`
+fn verify_checksum(manifest: &str, asset: &str, data: &[u8]) -> Result<(), String> {
+    if !lists_checksums(manifest) {
+        return Err(format!(
+            "This build has no checksums for bundled dependencies, refusing to install {}",
+            asset
+        ));
+    }
+    let expected = expected_checksum(manifest, asset).ok_or_else(|| {
+        format!("No checksum for {} in this release's manifest, refusing to install it", asset)
+    })?;
+    let actual = sha256_hex(data);
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "Checksum mismatch for {} (expected {}, got {}), refusing to install it",
+            asset, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Errs unless `signature` (a .minisig file) is a valid signature of `data` by `key`
+fn verify_signature(key: &str, data: &[u8], signature: &str) -> Result<(), String> {
+    let key = PublicKey::from_base64(key).map_err(|e| format!("Invalid signing key: {}", e))?;
+    let signature = Signature::decode(signature).map_err(|e| format!("Invalid signature: {}", e))?;
+    // Legacy (non-prehashed) signatures are still Ed25519 over the whole file
+    key.verify(data, &signature, true)
+        .map_err(|e| format!("Signature check failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_checksum() {
+        let data = b"abc";
+        let checksum = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256_hex(data), checksum);
+
+        let manifest = format!(
+            "# comment\n\n{}  deps-exiftool-windows.zip\n{} *deps-ffmpeg-windows.zip\n",
+            checksum,
+            checksum.to_uppercase()
+        );
+        assert_eq!(verify_checksum(&manifest, "deps-exiftool-windows.zip", data), Ok(()));
+        assert_eq!(verify_checksum(&manifest, "deps-ffmpeg-windows.zip", data), Ok(()));
+
+        let mismatch = verify_checksum(&manifest, "deps-exiftool-windows.zip", b"abd").unwrap_err();
+        assert!(mismatch.starts_with("Checksum mismatch"), "{}", mismatch);
+        let unlisted = verify_checksum(&manifest, "deps-tesseract-windows.zip", data).unwrap_err();
+        assert!(unlisted.starts_with("No checksum"), "{}", unlisted);
+
+        // A manifest of comments only is reported as such
+        assert!(lists_checksums(&manifest));
+        assert!(!lists_checksums("# comment\n  \n#  deps-exiftool-windows.zip\n"));
+        let empty = verify_checksum("# comment\n", "deps-exiftool-windows.zip", data).unwrap_err();
+        assert!(empty.contains("no checksums"), "{}", empty);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        // Test vector from minisign
+        let key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+        let signature = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+        assert_eq!(verify_signature(key, b"test", signature), Ok(()));
+        assert!(verify_signature(key, b"Test", signature).is_err());
+        assert!(verify_signature(key, b"test", "not a signature").is_err());
+    }
+
+    #[test]
+    fn test_embedded_manifest_parses() {
+        // Every entry is a SHA-256 of a deps- asset
+        for line in CHECKSUMS.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+            let (checksum, name) = line.split_once(char::is_whitespace).expect("checksum and name");
+            assert_eq!(checksum.len(), 64, "{}", line);
+            assert!(name.trim_start_matches([' ', '*']).starts_with("deps-"), "{}", line);
+        }
+    }
+}
@@ -26,19 +26,19 @@ pub fn install_from_bundled(dep_name: &str, platform: &str) -> Result<(), String
     println!("Downloading {} from GitHub Release...", dep_name);
     println!("URL: {}", download_url);
 
-    // Use reqwest to download (already in dependencies)
-    let response = reqwest::blocking::get(&download_url)
-        .map_err(|e| format!("Failed to download bundled installer: {}", e))?;
+    // Honors HTTP(S)_PROXY and retries network errors with backoff
+    let content = crate::deps::download(&download_url)
+        .map_err(|e| format!("Failed to download bundled installer {}: {}", asset_name, e))?;
 
-    if !response.status().is_success() {
-        return Err(format!("GitHub Release asset not found: {} (HTTP {})", asset_name, response.status()));
-    }
+    // Nothing downloaded is extracted or run unless it matches this release's manifest
+    super::verify::verify_asset(&asset_name, &content, || {
+        crate::deps::download(&format!("{}.minisig", download_url))
+    })?;
+    println!("Verified SHA-256 of {}", asset_name);
 
     // Save to temp directory
     let temp_dir = std::env::temp_dir();
     let installer_path = temp_dir.join(&asset_name);
-    let content = response.bytes()
-        .map_err(|e| format!("Failed to read response: {}", e))?;
 
     std::fs::write(&installer_path, &content)
         .map_err(|e| format!("Failed to write installer: {}", e))?;
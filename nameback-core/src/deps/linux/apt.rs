@@ -3,7 +3,10 @@
 //! Handles dependency installation on Linux using multiple package managers:
 //! apt-get (Debian/Ubuntu), dnf/yum (Fedora/RHEL), pacman (Arch), snap
 
-use std::process::Command;
+use std::process::{Command, Output};
+
+use crate::deps::{InstallError, InstallOptions};
+use crate::deps_check::Dependency;
 
 /// Installs all dependencies on Linux via available package managers
 ///
@@ -22,214 +25,241 @@ use std::process::Command;
 ///
 /// # Arguments
 /// * `report_progress` - Progress callback for UI updates
+/// * `options` - Whether DNS may be switched after a network error
 ///
 /// # Returns
 /// * `Ok(())` if required dependencies installed successfully
-/// * `Err(String)` if required dependencies failed
+/// * `Err(InstallError)` if required dependencies failed
 pub fn install_dependencies(
-    report_progress: &impl Fn(&str, u8)
-) -> Result<(), String> {
+    report_progress: &impl Fn(&str, u8),
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
     report_progress("Detecting package managers...", 10);
+    let managers = detect_package_managers()?;
+
+    for (dependency, pct) in [
+        (Dependency::ExifTool, 30),
+        (Dependency::Tesseract, 50),
+        (Dependency::FFmpeg, 70),
+        (Dependency::ImageMagick, 90),
+    ] {
+        report_progress(&format!("Installing {}...", dependency.name()), pct);
+        if let Err(e) = install_with(&managers, dependency, options) {
+            if dependency == Dependency::ExifTool {
+                super::restore_dns();
+                return Err(e);
+            }
+            println!("WARNING: {} (optional)", e);
+            println!("  Install manually with your package manager");
+        }
+    }
+
+    report_progress("Linux dependencies installed", 100);
+    Ok(())
+}
+
+/// Installs one dependency via the first package manager that succeeds
+///
+/// # Returns
+/// * `Ok(())` if a package manager installed it
+/// * `Err(InstallError)` if there is no package manager or none could
+pub fn install_dependency(
+    dependency: Dependency,
+    report_progress: &impl Fn(&str, u8),
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
+    report_progress("Detecting package managers...", 10);
+    let managers = detect_package_managers()?;
+
+    report_progress(&format!("Installing {}...", dependency.name()), 30);
+    install_with(&managers, dependency, options)
+}
+
+/// Installs one dependency with the first of `managers` that succeeds
+fn install_with(
+    managers: &[PackageManager],
+    dependency: Dependency,
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
+    if install_package(managers, dependency.name(), packages(&dependency), options) {
+        Ok(())
+    } else {
+        Err(InstallError::InstallFailed {
+            dependency,
+            details: "failed with all available package managers".to_string(),
+        })
+    }
+}
+
+/// Package name of a dependency for each package manager that has it
+fn packages(dependency: &Dependency) -> &'static [(&'static str, &'static str)] {
+    match dependency {
+        Dependency::ExifTool => &[
+            ("apt-get", "libimage-exiftool-perl"),
+            ("dnf", "perl-Image-ExifTool"),
+            ("yum", "perl-Image-ExifTool"),
+            ("pacman", "perl-image-exiftool"),
+            ("snap", "exiftool"),
+        ],
+        Dependency::Tesseract => &[
+            ("apt-get", "tesseract-ocr"),
+            ("dnf", "tesseract"),
+            ("yum", "tesseract"),
+            ("pacman", "tesseract"),
+            ("snap", "tesseract"),
+        ],
+        Dependency::FFmpeg => &[
+            ("apt-get", "ffmpeg"),
+            ("dnf", "ffmpeg"),
+            ("yum", "ffmpeg"),
+            ("pacman", "ffmpeg"),
+            ("snap", "ffmpeg"),
+        ],
+        Dependency::ImageMagick => &[
+            ("apt-get", "imagemagick"),
+            ("dnf", "ImageMagick"),
+            ("yum", "ImageMagick"),
+            ("pacman", "imagemagick"),
+            ("snap", "imagemagick"),
+        ],
+        Dependency::Chromaprint => &[
+            ("apt-get", "libchromaprint-tools"),
+            ("dnf", "chromaprint-tools"),
+            ("yum", "chromaprint-tools"),
+            ("pacman", "chromaprint"),
+        ],
+    }
+}
 
-    // Detect ALL available package managers for fallback
+/// A package manager found on this system, with its install arguments
+type PackageManager = (&'static str, Vec<&'static str>);
+
+/// Detects ALL available package managers, so a failed install can fall back to the next
+fn detect_package_managers() -> Result<Vec<PackageManager>, InstallError> {
     let mut available_managers = Vec::new();
 
     if Command::new("apt-get").arg("--version").output().is_ok() {
-        available_managers.push(("apt-get", vec!["install", "-y"], "libimage-exiftool-perl"));
+        available_managers.push(("apt-get", vec!["install", "-y"]));
     }
     if Command::new("dnf").arg("--version").output().is_ok() {
-        available_managers.push(("dnf", vec!["install", "-y"], "perl-Image-ExifTool"));
+        available_managers.push(("dnf", vec!["install", "-y"]));
     }
     if Command::new("yum").arg("--version").output().is_ok() {
-        available_managers.push(("yum", vec!["install", "-y"], "perl-Image-ExifTool"));
+        available_managers.push(("yum", vec!["install", "-y"]));
     }
     if Command::new("pacman").arg("--version").output().is_ok() {
-        available_managers.push(("pacman", vec!["-S", "--noconfirm"], "perl-image-exiftool"));
+        available_managers.push(("pacman", vec!["-S", "--noconfirm"]));
     }
     if Command::new("snap").arg("version").output().is_ok() {
-        available_managers.push(("snap", vec!["install"], "exiftool"));
+        available_managers.push(("snap", vec!["install"]));
     }
 
     if available_managers.is_empty() {
-        return Err("No supported package manager found (apt-get, dnf, yum, pacman, or snap required)".to_string());
+        return Err(InstallError::NoPackageManager("apt-get, dnf, yum, pacman or snap".to_string()));
     }
 
     println!("Found {} package manager(s): {}",
              available_managers.len(),
-             available_managers.iter().map(|(name, _, _)| *name).collect::<Vec<_>>().join(", "));
+             available_managers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "));
+
+    Ok(available_managers)
+}
 
+/// Runs one package manager's install command, through sudo unless running as root
+fn run_install(pkg_manager: &str, install_cmd: &[&str], package: &str) -> std::io::Result<Output> {
     // Check if running with sudo/root
     let needs_sudo = std::env::var("USER").unwrap_or_default() != "root";
 
-    // Helper function to try installing with all available package managers
-    let try_install_package = |display_name: &str, packages: &[(&str, &str)]| -> bool {
-        println!("Installing {}...", display_name);
-
-        // Try each available package manager
-        for (pkg_manager, install_cmd, _) in &available_managers {
-            // Find package name for this manager
-            let package = packages.iter()
-                .find(|(manager, _)| manager == pkg_manager)
-                .map(|(_, pkg)| *pkg);
-
-            if let Some(package) = package {
-                println!("Trying {} with {}...", display_name, pkg_manager);
-
-                let result = if *pkg_manager == "snap" {
-                    // snap doesn't need sudo prefix in command
-                    let mut cmd = Command::new("sudo");
-                    cmd.arg("snap");
-                    for arg in install_cmd {
-                        cmd.arg(arg);
-                    }
-                    cmd.arg(package).output()
-                } else if needs_sudo {
-                    let mut cmd = Command::new("sudo");
-                    cmd.arg(pkg_manager);
-                    for arg in install_cmd {
-                        cmd.arg(arg);
-                    }
-                    cmd.arg(package).output()
-                } else {
-                    let mut cmd = Command::new(pkg_manager);
-                    for arg in install_cmd {
-                        cmd.arg(arg);
-                    }
-                    cmd.arg(package).output()
-                };
+    // snap is always run through sudo
+    let mut cmd = if pkg_manager == "snap" || needs_sudo {
+        let mut cmd = Command::new("sudo");
+        // sudo resets the environment, which would hide HTTP(S)_PROXY from the package manager
+        let proxy_vars = crate::deps::proxy_vars();
+        if !proxy_vars.is_empty() {
+            cmd.arg(format!("--preserve-env={}", proxy_vars.join(",")));
+        }
+        cmd.arg(pkg_manager);
+        cmd
+    } else {
+        Command::new(pkg_manager)
+    };
+    cmd.args(install_cmd).arg(package).output()
+}
 
-                match result {
-                    Ok(output) if output.status.success() => {
-                        println!("{} installed successfully with {}", display_name, pkg_manager);
-                        return true;
-                    }
-                    Ok(output) => {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        println!("{} failed with {}: {}", display_name, pkg_manager, stderr);
-
-                        // Check for DNS/network errors
-                        if stderr.contains("Could not resolve") ||
-                           stderr.contains("Temporary failure in name resolution") ||
-                           stderr.contains("Name or service not known") {
-                            println!("Detected DNS error, trying DNS fallback...");
-
-                            if super::try_with_public_dns().is_ok() {
-                                println!("Retrying {} with public DNS...", display_name);
-
-                                let retry = if *pkg_manager == "snap" {
-                                    let mut cmd = Command::new("sudo");
-                                    cmd.arg("snap");
-                                    for arg in install_cmd {
-                                        cmd.arg(arg);
-                                    }
-                                    cmd.arg(package).output()
-                                } else if needs_sudo {
-                                    let mut cmd = Command::new("sudo");
-                                    cmd.arg(pkg_manager);
-                                    for arg in install_cmd {
-                                        cmd.arg(arg);
-                                    }
-                                    cmd.arg(package).output()
-                                } else {
-                                    let mut cmd = Command::new(pkg_manager);
-                                    for arg in install_cmd {
-                                        cmd.arg(arg);
-                                    }
-                                    cmd.arg(package).output()
-                                };
-
-                                super::restore_dns();
-
-                                if let Ok(retry_output) = retry {
-                                    if retry_output.status.success() {
-                                        println!("{} installed successfully with DNS fallback", display_name);
-                                        return true;
-                                    }
-                                }
-                            }
+/// Tries installing a package with each available package manager in turn
+fn install_package(
+    available_managers: &[PackageManager],
+    display_name: &str,
+    packages: &[(&str, &str)],
+    options: &InstallOptions,
+) -> bool {
+    println!("Installing {}...", display_name);
+
+    // Try each available package manager
+    for (pkg_manager, install_cmd) in available_managers {
+        // Find package name for this manager
+        let Some(package) = packages.iter()
+            .find(|(manager, _)| manager == pkg_manager)
+            .map(|(_, pkg)| *pkg)
+        else {
+            continue;
+        };
+
+        println!("Trying {} with {}...", display_name, pkg_manager);
+
+        match run_install(pkg_manager, install_cmd, package) {
+            Ok(output) if output.status.success() => {
+                println!("{} installed successfully with {}", display_name, pkg_manager);
+                return true;
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                println!("{} failed with {}: {}", display_name, pkg_manager, stderr);
+
+                // Check for DNS/network errors
+                if stderr.contains("Could not resolve") ||
+                   stderr.contains("Temporary failure in name resolution") ||
+                   stderr.contains("Name or service not known") {
+                    if options.never_touch_dns {
+                        println!("Detected DNS error, retrying without changing DNS (never_touch_dns)...");
+                        let retried = crate::deps::retry_with_backoff(display_name, || {
+                            run_install(pkg_manager, install_cmd, package)
+                                .is_ok_and(|output| output.status.success())
+                        });
+                        if retried {
+                            println!("{} installed successfully after retrying", display_name);
+                            return true;
                         }
-
-                        // Try next package manager
                         continue;
                     }
-                    Err(e) => {
-                        println!("Failed to execute {} command: {}", pkg_manager, e);
-                        continue;
-                    }
-                }
-            }
-        }
-
-        false
-    };
 
-    // Install exiftool (required)
-    report_progress("Installing exiftool (required)...", 30);
+                    println!("Detected DNS error, trying DNS fallback...");
 
-    let exiftool_packages = vec![
-        ("apt-get", "libimage-exiftool-perl"),
-        ("dnf", "perl-Image-ExifTool"),
-        ("yum", "perl-Image-ExifTool"),
-        ("pacman", "perl-image-exiftool"),
-        ("snap", "exiftool"),
-    ];
+                    if super::try_with_public_dns().is_ok() {
+                        println!("Retrying {} with public DNS...", display_name);
 
-    if !try_install_package("exiftool", &exiftool_packages) {
-        super::restore_dns();
-        return Err("Failed to install exiftool with all available package managers".to_string());
-    }
+                        let retry = run_install(pkg_manager, install_cmd, package);
 
-    // Install tesseract (optional)
-    report_progress("Installing tesseract (optional OCR support)...", 50);
-
-    let tesseract_packages = vec![
-        ("apt-get", "tesseract-ocr"),
-        ("dnf", "tesseract"),
-        ("yum", "tesseract"),
-        ("pacman", "tesseract"),
-        ("snap", "tesseract"),
-    ];
-
-    if !try_install_package("tesseract", &tesseract_packages) {
-        println!("WARNING: Tesseract (OCR) installation failed (optional)");
-        println!("  OCR support will be disabled");
-        println!("  Install manually with your package manager");
-    }
+                        super::restore_dns();
 
-    // Install ffmpeg (optional)
-    report_progress("Installing ffmpeg (optional video support)...", 70);
-
-    let ffmpeg_packages = vec![
-        ("apt-get", "ffmpeg"),
-        ("dnf", "ffmpeg"),
-        ("yum", "ffmpeg"),
-        ("pacman", "ffmpeg"),
-        ("snap", "ffmpeg"),
-    ];
-
-    if !try_install_package("ffmpeg", &ffmpeg_packages) {
-        println!("WARNING: FFmpeg installation failed (optional)");
-        println!("  Video frame extraction will be disabled");
-        println!("  Install manually with your package manager");
-    }
+                        if let Ok(retry_output) = retry {
+                            if retry_output.status.success() {
+                                println!("{} installed successfully with DNS fallback", display_name);
+                                return true;
+                            }
+                        }
+                    }
+                }
 
-    // Install imagemagick (optional)
-    report_progress("Installing imagemagick (optional HEIC support)...", 90);
-
-    let imagemagick_packages = vec![
-        ("apt-get", "imagemagick"),
-        ("dnf", "ImageMagick"),
-        ("yum", "ImageMagick"),
-        ("pacman", "imagemagick"),
-        ("snap", "imagemagick"),
-    ];
-
-    if !try_install_package("imagemagick", &imagemagick_packages) {
-        println!("WARNING: ImageMagick installation failed (optional)");
-        println!("  HEIC/HEIF image support will be disabled");
-        println!("  Install manually with your package manager");
+                // Try next package manager
+                continue;
+            }
+            Err(e) => {
+                println!("Failed to execute {} command: {}", pkg_manager, e);
+                continue;
+            }
+        }
     }
 
-    report_progress("Linux dependencies installed", 100);
-    Ok(())
+    false
 }
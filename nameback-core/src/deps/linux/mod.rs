@@ -11,4 +11,4 @@ mod dns_fallback;
 mod apt;
 
 pub use dns_fallback::{try_with_public_dns, restore_dns};
-pub use apt::install_dependencies;
+pub use apt::{install_dependencies, install_dependency};
@@ -32,7 +32,7 @@ impl InstallLogger {
         };
 
         // Write header
-        logger.info(&format!("=== Nameback Dependency Installation Log ==="));
+        logger.info("=== Nameback Dependency Installation Log ===");
         logger.info(&format!("Version: {}", env!("CARGO_PKG_VERSION")));
         logger.info(&format!("Log file: {:?}", log_path));
         logger.info(&format!("Started: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S")));
@@ -64,30 +64,10 @@ impl InstallLogger {
         self.log_message("INFO", message);
     }
 
-    /// Log a warning message
-    pub fn warn(&self, message: &str) {
-        self.log_message("WARN", message);
-    }
-
-    /// Log an error message
-    pub fn error(&self, message: &str) {
-        self.log_message("ERROR", message);
-    }
-
-    /// Log a debug message
-    pub fn debug(&self, message: &str) {
-        self.log_message("DEBUG", message);
-    }
-
     /// Internal method to write log messages
     fn log_message(&self, level: &str, message: &str) {
         let timestamp = chrono::Local::now().format("%H:%M:%S");
-        let formatted = if level.is_empty() {
-            // No level prefix (for raw output like stdout/stderr)
-            format!("[{}] {}", timestamp, message)
-        } else {
-            format!("[{}] [{}] {}", timestamp, level, message)
-        };
+        let formatted = format!("[{}] [{}] {}", timestamp, level, message);
 
         // Write to log file only (console output handled by report_progress)
         if let Ok(mut file_opt) = self.log_file.lock() {
@@ -98,20 +78,6 @@ impl InstallLogger {
         }
     }
 
-    /// Log command output (stdout)
-    pub fn log_stdout(&self, output: &str) {
-        for line in output.lines() {
-            self.log_message("", &format!("  stdout: {}", line));
-        }
-    }
-
-    /// Log command output (stderr)
-    pub fn log_stderr(&self, output: &str) {
-        for line in output.lines() {
-            self.log_message("", &format!("  stderr: {}", line));
-        }
-    }
-
     /// Clean up old log files (keep only the last N files)
     pub fn cleanup_old_logs(keep_count: usize) -> Result<(), String> {
         let temp_dir = std::env::temp_dir();
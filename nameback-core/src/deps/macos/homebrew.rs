@@ -5,25 +5,107 @@
 
 use std::process::Command;
 
+use crate::deps::{InstallError, InstallOptions};
+use crate::deps_check::Dependency;
+
 /// Installs all dependencies on macOS via Homebrew/MacPorts
 ///
 /// Installs dependencies in order:
 /// 1. exiftool (required - core metadata extraction)
 /// 2. tesseract (optional - OCR support)
 /// 3. ffmpeg (optional - video frame extraction)
-/// 4. imagemagick (optional - HEIC/HEIF support)
+/// 4. imagemagick (optional - HEIC/HEIF support, only if the built-in `sips` is missing)
 ///
 /// # Arguments
 /// * `report_progress` - Progress callback for UI updates
+/// * `options` - Whether DNS may be switched after a network error
 ///
 /// # Returns
 /// * `Ok(())` if required dependencies installed successfully
-/// * `Err(String)` if required dependencies failed
+/// * `Err(InstallError)` if required dependencies failed
 pub fn install_dependencies(
-    report_progress: &impl Fn(&str, u8)
-) -> Result<(), String> {
+    report_progress: &impl Fn(&str, u8),
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
+    report_progress("Checking Homebrew installation...", 10);
+    let brew_installed = detect_package_manager()?;
+
+    for (dependency, pct) in [
+        (Dependency::ExifTool, 30),
+        (Dependency::Tesseract, 50),
+        (Dependency::FFmpeg, 70),
+        (Dependency::ImageMagick, 90),
+    ] {
+        // HEIC is converted with the built-in sips, so ImageMagick is only a fallback
+        if dependency == Dependency::ImageMagick
+            && (which::which("sips").is_ok() || std::path::Path::new("/usr/bin/sips").exists())
+        {
+            println!("sips found, skipping ImageMagick (HEIC/HEIF handled natively)");
+            continue;
+        }
+
+        report_progress(&format!("Installing {}...", dependency.name()), pct);
+        if let Err(e) = install_with(brew_installed, dependency, options) {
+            if dependency == Dependency::ExifTool {
+                super::restore_dns();
+                return Err(e);
+            }
+            println!("WARNING: {} (optional)", e);
+        }
+    }
+
+    report_progress("macOS dependencies installed", 100);
+    Ok(())
+}
+
+/// Installs one dependency with Homebrew, or MacPorts if Homebrew is missing or fails
+///
+/// # Returns
+/// * `Ok(())` if it was installed
+/// * `Err(InstallError)` if there is no package manager or none could install it
+pub fn install_dependency(
+    dependency: Dependency,
+    report_progress: &impl Fn(&str, u8),
+    options: &InstallOptions,
+) -> Result<(), InstallError> {
     report_progress("Checking Homebrew installation...", 10);
+    let brew_installed = detect_package_manager()?;
+
+    report_progress(&format!("Installing {}...", dependency.name()), 30);
+    install_with(brew_installed, dependency, options)
+}
 
+/// Installs one dependency with Homebrew (if installed), falling back to MacPorts
+fn install_with(brew_installed: bool, dependency: Dependency, options: &InstallOptions) -> Result<(), InstallError> {
+    let (brew_packages, port_package): (&[&str], &str) = match dependency {
+        Dependency::ExifTool => (&["exiftool"], "exiftool"),
+        Dependency::Tesseract => (&["tesseract", "tesseract-lang"], "tesseract"),
+        Dependency::FFmpeg => (&["ffmpeg"], "ffmpeg"),
+        Dependency::ImageMagick => (&["imagemagick"], "ImageMagick"),
+        Dependency::Chromaprint => (&["chromaprint"], "chromaprint"),
+    };
+    let installed = if brew_installed {
+        brew_packages.iter().all(|package| install_with_brew(package, options)) || install_with_port(port_package)
+    } else {
+        install_with_port(port_package)
+    };
+
+    if installed {
+        Ok(())
+    } else {
+        Err(InstallError::InstallFailed {
+            dependency,
+            details: format!("install it manually: brew install {}", brew_packages.join(" ")),
+        })
+    }
+}
+
+/// Checks for Homebrew, falling back to MacPorts
+///
+/// # Returns
+/// * `Ok(true)` if Homebrew is installed, `Ok(false)` if only MacPorts is
+/// * `Err(InstallError)` if neither is
+fn detect_package_manager() -> Result<bool, InstallError> {
     // Check if Homebrew is installed
     let brew_check = Command::new("brew")
         .arg("--version")
@@ -46,136 +128,93 @@ pub fn install_dependencies(
             .unwrap_or(false);
 
         if !port_installed {
-            return Err(
-                "No package manager found. Please install Homebrew (https://brew.sh) or MacPorts (https://www.macports.org)".to_string()
-            );
+            return Err(InstallError::NoPackageManager(
+                "Homebrew (https://brew.sh) or MacPorts (https://www.macports.org)".to_string()
+            ));
         }
 
         println!("MacPorts found, using as fallback package manager");
     }
 
-    // Helper to install with Homebrew with DNS fallback
-    let install_with_brew = |package: &str| -> bool {
-        println!("Installing {} with Homebrew...", package);
-        let result = Command::new("brew")
-            .args(["install", package])
-            .output();
+    Ok(brew_installed)
+}
 
-        match result {
-            Ok(output) if output.status.success() => {
-                println!("{} installed successfully", package);
-                true
-            }
-            Ok(output) => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                println!("Homebrew installation failed for {}: {}", package, stderr);
-
-                // Check for DNS/network errors
-                if stderr.contains("Could not resolve") ||
-                   stderr.contains("Failed to connect") ||
-                   stderr.contains("curl") && stderr.contains("error") {
-                    println!("Detected network error, trying DNS fallback...");
-
-                    if super::try_with_public_dns().is_ok() {
-                        println!("Retrying {} installation with public DNS...", package);
-                        let retry = Command::new("brew")
+/// Installs a package with Homebrew, retrying with public DNS after a network error
+fn install_with_brew(package: &str, options: &InstallOptions) -> bool {
+    println!("Installing {} with Homebrew...", package);
+    let result = Command::new("brew")
+        .args(["install", package])
+        .output();
+
+    match result {
+        Ok(output) if output.status.success() => {
+            println!("{} installed successfully", package);
+            true
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            println!("Homebrew installation failed for {}: {}", package, stderr);
+
+            // Check for DNS/network errors
+            if stderr.contains("Could not resolve") ||
+               stderr.contains("Failed to connect") ||
+               stderr.contains("curl") && stderr.contains("error") {
+                if options.never_touch_dns {
+                    println!("Detected network error, retrying without changing DNS (never_touch_dns)...");
+                    return crate::deps::retry_with_backoff(package, || {
+                        Command::new("brew")
                             .args(["install", package])
-                            .output();
+                            .output()
+                            .is_ok_and(|output| output.status.success())
+                    });
+                }
+
+                println!("Detected network error, trying DNS fallback...");
 
-                        super::restore_dns();
+                if super::try_with_public_dns().is_ok() {
+                    println!("Retrying {} installation with public DNS...", package);
+                    let retry = Command::new("brew")
+                        .args(["install", package])
+                        .output();
 
-                        if let Ok(retry_output) = retry {
-                            if retry_output.status.success() {
-                                println!("{} installed successfully with DNS fallback", package);
-                                return true;
-                            }
+                    super::restore_dns();
+
+                    if let Ok(retry_output) = retry {
+                        if retry_output.status.success() {
+                            println!("{} installed successfully with DNS fallback", package);
+                            return true;
                         }
                     }
                 }
-                false
-            }
-            Err(e) => {
-                println!("Failed to execute brew command: {}", e);
-                false
             }
+            false
         }
-    };
-
-    // Helper to install with MacPorts as fallback
-    let install_with_port = |package: &str| -> bool {
-        println!("Trying MacPorts as fallback for {}...", package);
-        let result = Command::new("sudo")
-            .args(["port", "install", package])
-            .status();
-
-        match result {
-            Ok(status) if status.success() => {
-                println!("{} installed successfully via MacPorts", package);
-                true
-            }
-            _ => {
-                println!("MacPorts installation also failed for {}", package);
-                false
-            }
+        Err(e) => {
+            println!("Failed to execute brew command: {}", e);
+            false
         }
-    };
-
-    // Install exiftool (required)
-    report_progress("Installing exiftool (required)...", 30);
-
-    let exiftool_installed = if brew_installed {
-        install_with_brew("exiftool") || install_with_port("exiftool")
-    } else {
-        install_with_port("exiftool")
-    };
-
-    if !exiftool_installed {
-        super::restore_dns();
-        return Err("Failed to install exiftool. Please install manually: brew install exiftool".to_string());
     }
+}
 
-    // Install tesseract (optional)
-    report_progress("Installing tesseract (optional OCR support)...", 50);
-    let tesseract_installed = if brew_installed {
-        install_with_brew("tesseract") || install_with_brew("tesseract-lang")
-    } else {
-        install_with_port("tesseract")
-    };
-
-    if !tesseract_installed {
-        println!("WARNING: Tesseract (OCR) installation failed (optional)");
-        println!("  OCR support will be disabled");
-        println!("  Install manually: brew install tesseract tesseract-lang");
-    }
-
-    // Install ffmpeg (optional)
-    report_progress("Installing ffmpeg (optional video support)...", 70);
-    let ffmpeg_installed = if brew_installed {
-        install_with_brew("ffmpeg")
-    } else {
-        install_with_port("ffmpeg")
-    };
-
-    if !ffmpeg_installed {
-        println!("WARNING: FFmpeg installation failed (optional)");
-        println!("  Video frame extraction will be disabled");
-        println!("  Install manually: brew install ffmpeg");
+/// Installs a package with MacPorts (the fallback package manager)
+fn install_with_port(package: &str) -> bool {
+    println!("Trying MacPorts as fallback for {}...", package);
+    let mut cmd = Command::new("sudo");
+    // sudo resets the environment, which would hide HTTP(S)_PROXY from MacPorts
+    let proxy_vars = crate::deps::proxy_vars();
+    if !proxy_vars.is_empty() {
+        cmd.arg(format!("--preserve-env={}", proxy_vars.join(",")));
     }
+    let result = cmd.args(["port", "install", package]).status();
 
-    // Install imagemagick (optional)
-    report_progress("Installing imagemagick (optional HEIC support)...", 90);
-    let imagemagick_installed = if brew_installed {
-        install_with_brew("imagemagick")
-    } else {
-        install_with_port("ImageMagick")
-    };
-
-    if !imagemagick_installed {
-        println!("WARNING: ImageMagick installation failed (optional)");
-        println!("  HEIC/HEIF image support will be disabled");
-        println!("  Install manually: brew install imagemagick");
+    match result {
+        Ok(status) if status.success() => {
+            println!("{} installed successfully via MacPorts", package);
+            true
+        }
+        _ => {
+            println!("MacPorts installation also failed for {}", package);
+            false
+        }
     }
-
-    report_progress("macOS dependencies installed", 100);
-    Ok(())
 }
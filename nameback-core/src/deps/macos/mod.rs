@@ -9,4 +9,4 @@ mod dns_fallback;
 mod homebrew;
 
 pub use dns_fallback::{try_with_public_dns, restore_dns};
-pub use homebrew::install_dependencies;
+pub use homebrew::{install_dependencies, install_dependency};
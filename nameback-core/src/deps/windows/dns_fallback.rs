@@ -13,6 +13,8 @@ use std::process::Command;
 /// # Returns
 /// * `Ok(())` if DNS was switched successfully
 /// * `Err(String)` if the DNS switch failed
+///
+/// Callers check `InstallOptions::never_touch_dns` first
 pub fn try_with_public_dns() -> Result<(), String> {
     println!("\n=== DNS FALLBACK: Attempting to use public DNS servers ===");
 
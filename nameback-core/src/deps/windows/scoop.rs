@@ -4,6 +4,7 @@
 
 use std::process::Command;
 use crate::deps::msi_progress;
+use crate::deps_check::Dependency;
 
 /// Progress reporting callback type
 type ProgressCallback = Box<dyn Fn(&str, u8) + Send + Sync>;
@@ -209,6 +210,19 @@ pub fn install_dependencies_via_scoop(
     Ok(())
 }
 
+/// Installs one dependency via Scoop with Chocolatey fallback (used to install a single tool)
+///
+/// Does not fail if installation is unsuccessful; callers check that the tool is found afterwards.
+pub fn install_dependency_via_scoop(
+    scoop_cmd: &str,
+    dependency: Dependency,
+    report_progress: &impl Fn(&str, u8)
+) {
+    let cmd_exe = std::env::var("COMSPEC").unwrap_or_else(|_| "C:\\Windows\\System32\\cmd.exe".to_string());
+    // Names are the Scoop (and Chocolatey) package names
+    install_optional_dependency(&cmd_exe, scoop_cmd, dependency.name(), dependency.description(), report_progress, 50);
+}
+
 /// Installs an optional dependency with Chocolatey fallback
 ///
 /// Does not fail if installation is unsuccessful - only prints warnings.
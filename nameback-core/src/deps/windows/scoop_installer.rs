@@ -4,7 +4,7 @@
 //! the path to the scoop.cmd executable for package installation.
 
 use std::process::Command;
-use crate::deps::{constants, msi_progress};
+use crate::deps::{constants, msi_progress, InstallOptions};
 
 /// Progress reporting callback type
 type ProgressCallback = Box<dyn Fn(&str, u8) + Send + Sync>;
@@ -16,12 +16,14 @@ type ProgressCallback = Box<dyn Fn(&str, u8) + Send + Sync>;
 ///
 /// # Arguments
 /// * `report_progress` - Callback for progress reporting
+/// * `options` - Whether DNS may be switched after a network error
 ///
 /// # Returns
 /// * `Ok(String)` - Path to scoop.cmd executable
 /// * `Err(String)` - Error message if installation fails
 pub fn ensure_scoop_installed(
-    report_progress: impl Fn(&str, u8) + Send + Sync + 'static
+    report_progress: impl Fn(&str, u8) + Send + Sync + 'static,
+    options: &InstallOptions,
 ) -> Result<String, String> {
     report_progress("Checking Scoop installation...", 10);
 
@@ -72,11 +74,15 @@ pub fn ensure_scoop_installed(
         println!("=== DEBUG: Installing Scoop ===");
         println!("Temp installer path: {}", installer_path);
 
-        // Download the Scoop installer to a temp file
+        // Download the Scoop installer to a temp file (Invoke-WebRequest ignores HTTPS_PROXY)
+        let proxy = crate::deps::https_proxy()
+            .map(|proxy| format!(" -Proxy '{}'", proxy.trim()))
+            .unwrap_or_default();
         let download_cmd = format!(
-            "Invoke-WebRequest -Uri '{}' -OutFile '{}' -UseBasicParsing",
+            "Invoke-WebRequest -Uri '{}' -OutFile '{}' -UseBasicParsing{}",
             constants::SCOOP_INSTALL,
-            installer_path
+            installer_path,
+            proxy
         );
 
         println!("Download command: powershell -NoProfile -Command \"{}\"", download_cmd);
@@ -121,7 +127,14 @@ pub fn ensure_scoop_installed(
                                    stderr.contains("connection") ||
                                    stderr.contains("network");
 
-            if is_dns_error || is_network_error {
+            if (is_dns_error || is_network_error) && options.never_touch_dns {
+                println!("Detected DNS/network error, downloading again with retries instead of changing DNS (never_touch_dns)...");
+                msi_progress::report_action_data("Network error detected, retrying download...");
+
+                let installer = crate::deps::download(constants::SCOOP_INSTALL)?;
+                std::fs::write(&installer_path, installer)
+                    .map_err(|e| format!("Failed to write Scoop installer: {}", e))?;
+            } else if is_dns_error || is_network_error {
                 println!("Detected DNS/network error, attempting DNS fallback to public DNS servers...");
                 msi_progress::report_action_data("DNS error detected, trying public DNS servers...");
 
@@ -198,9 +211,9 @@ pub fn ensure_scoop_installed(
                     );
                     return Err(error_msg);
                 }
+            } else {
+                return Err(format!("Failed to download Scoop installer: {}", stderr));
             }
-
-            return Err(format!("Failed to download Scoop installer: {}", stderr));
         }
 
         // Execute the installer with -RunAsAdmin flag (we already have UAC permission)
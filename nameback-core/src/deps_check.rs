@@ -1,34 +1,49 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
 
 /// Represents a dependency that might be needed
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Dependency {
     ExifTool,
     Tesseract,
     FFmpeg,
     ImageMagick,
+    Chromaprint,
 }
 
 impl Dependency {
+    /// Every external tool nameback can use
+    pub const ALL: [Dependency; 5] = [
+        Dependency::ExifTool,
+        Dependency::Tesseract,
+        Dependency::FFmpeg,
+        Dependency::ImageMagick,
+        Dependency::Chromaprint,
+    ];
+
     pub fn name(&self) -> &str {
         match self {
             Dependency::ExifTool => "exiftool",
             Dependency::Tesseract => "tesseract",
             Dependency::FFmpeg => "ffmpeg",
             Dependency::ImageMagick => "imagemagick",
+            Dependency::Chromaprint => "chromaprint",
         }
     }
 
     /// Get the bundled directory name (for MSI installer location)
+    #[cfg_attr(not(windows), allow(dead_code))]
     fn bundled_dir_name(&self) -> &str {
         match self {
             Dependency::ExifTool => "exiftool",
             Dependency::Tesseract => "tesseract",
             Dependency::FFmpeg => "ffmpeg",
             Dependency::ImageMagick => "imagemagick",
+            Dependency::Chromaprint => "chromaprint",
         }
     }
 
@@ -39,15 +54,17 @@ impl Dependency {
             Dependency::Tesseract => "tesseract",
             Dependency::FFmpeg => "ffmpeg",
             Dependency::ImageMagick => "magick",  // ImageMagick v7+ uses "magick.exe"
+            Dependency::Chromaprint => "fpcalc",
         }
     }
 
     pub fn description(&self) -> &str {
         match self {
-            Dependency::ExifTool => "Core metadata extraction (required)",
+            Dependency::ExifTool => "Full metadata extraction (a built-in reader covers common photo, video and audio tags)",
             Dependency::Tesseract => "OCR for images and videos",
             Dependency::FFmpeg => "Video frame extraction",
-            Dependency::ImageMagick => "HEIC/HEIF support",
+            Dependency::ImageMagick => "HEIC/HEIF and camera RAW support",
+            Dependency::Chromaprint => "Audio fingerprints for AcoustID lookups of untitled music",
         }
     }
 
@@ -58,38 +75,38 @@ impl Dependency {
         #[cfg(windows)]
         {
             if let Ok(programfiles) = std::env::var("PROGRAMFILES") {
-                log::debug!("Checking bundled location for {}: PROGRAMFILES={}", self.name(), programfiles);
+                tracing::debug!("Checking bundled location for {}: PROGRAMFILES={}", self.name(), programfiles);
 
                 let bundled_dir = PathBuf::from(&programfiles)
                     .join("nameback")
                     .join("deps")
                     .join(self.bundled_dir_name());
 
-                log::debug!("Constructed bundled directory path: {:?}", bundled_dir);
-                log::debug!("Does bundled directory exist? {}", bundled_dir.exists());
+                tracing::debug!("Constructed bundled directory path: {:?}", bundled_dir);
+                tracing::debug!("Does bundled directory exist? {}", bundled_dir.exists());
 
                 // Check primary executable name
                 let primary_path = bundled_dir.join(format!("{}.exe", self.exe_name()));
-                log::debug!("Checking primary path: {:?}, exists: {}", primary_path, primary_path.exists());
+                tracing::debug!("Checking primary path: {:?}, exists: {}", primary_path, primary_path.exists());
 
                 if primary_path.exists() {
-                    log::info!("Found {} in bundled installer location: {:?}", self.name(), primary_path);
+                    tracing::info!("Found {} in bundled installer location: {:?}", self.name(), primary_path);
                     return Some(primary_path);
                 }
 
                 // Check fallback names
                 for name in self.fallback_names() {
                     let fallback_path = bundled_dir.join(format!("{}.exe", name));
-                    log::debug!("Checking fallback path: {:?}, exists: {}", fallback_path, fallback_path.exists());
+                    tracing::debug!("Checking fallback path: {:?}, exists: {}", fallback_path, fallback_path.exists());
 
                     if fallback_path.exists() {
-                        log::info!("Found {} in bundled installer location (fallback for {}): {:?}",
+                        tracing::info!("Found {} in bundled installer location (fallback for {}): {:?}",
                                    name, self.name(), fallback_path);
                         return Some(fallback_path);
                     }
                 }
             } else {
-                log::warn!("PROGRAMFILES environment variable not found!");
+                tracing::warn!("PROGRAMFILES environment variable not found!");
             }
         }
 
@@ -103,27 +120,108 @@ impl Dependency {
         self.find_executable().map(Command::new)
     }
 
+    /// Flag that makes the tool print its version
+    fn version_flag(&self) -> &str {
+        match self {
+            Dependency::ExifTool => "-ver",
+            Dependency::Tesseract => "--version",
+            Dependency::FFmpeg | Dependency::ImageMagick | Dependency::Chromaprint => "-version",
+        }
+    }
+
     /// Check if this dependency is available
     pub fn is_available(&self) -> bool {
         if let Some(mut cmd) = self.create_command() {
             // Try to run version check
-            let result = match self {
-                Dependency::ExifTool => cmd.arg("-ver").output(),
-                Dependency::Tesseract => cmd.arg("--version").output(),
-                Dependency::FFmpeg => cmd.arg("-version").output(),
-                Dependency::ImageMagick => cmd.arg("-version").output(),
-            };
+            let result = cmd.arg(self.version_flag()).output();
 
             let available = result.map(|o| o.status.success()).unwrap_or(false);
-            log::debug!("Dependency check - {}: {}", self.name(),
+            tracing::debug!("Dependency check - {}: {}", self.name(),
                        if available { "available" } else { "missing" });
             available
         } else {
-            log::debug!("Dependency check - {}: missing", self.name());
+            tracing::debug!("Dependency check - {}: missing", self.name());
             false
         }
     }
 
+    /// Installed version, e.g. "12.76" for exiftool, or None if the tool is missing or fails
+    /// to run (the first line of its version output when no version number is in it)
+    pub fn version(&self) -> Option<String> {
+        let output = self.create_command()?.arg(self.version_flag()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Tesseract 4 printed its version to stderr
+        let text = match String::from_utf8_lossy(&output.stdout).trim() {
+            "" => String::from_utf8_lossy(&output.stderr).into_owned(),
+            stdout => stdout.to_string(),
+        };
+        parse_version(&text)
+    }
+
+    /// Oldest version nameback supports, and what works less well or not at all below it
+    pub fn minimum_version(&self) -> (&'static str, &'static str) {
+        match self {
+            Dependency::ExifTool => ("10.80", "tags of newer cameras and formats may not be read"),
+            Dependency::Tesseract => ("4.0", "OCR is much less accurate without the LSTM engine"),
+            Dependency::FFmpeg => ("4.0", "video frames may not be extracted reliably"),
+            Dependency::ImageMagick => ("6.9", "HEIC/HEIF images can't be converted"),
+            Dependency::Chromaprint => ("1.4", "fpcalc can't print JSON, so music isn't looked up on AcoustID"),
+        }
+    }
+
+    /// The installed tool if it is older than `minimum_version` (None when it is missing,
+    /// recent enough, or its version can't be read)
+    pub fn outdated(&self) -> Option<OutdatedDependency> {
+        self.check_version(&self.version()?)
+    }
+
+    /// `version` of this tool, if it is older than `minimum_version`
+    pub fn check_version(&self, version: &str) -> Option<OutdatedDependency> {
+        let (minimum, impact) = self.minimum_version();
+        match version_at_least(version, minimum) {
+            Some(false) => Some(OutdatedDependency {
+                dependency: *self,
+                version: version.to_string(),
+                minimum,
+                impact,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The dependency run as `command` (e.g. "magick"), if any
+    pub(crate) fn from_command(command: &str) -> Option<Dependency> {
+        Self::ALL.into_iter().find(|dependency| dependency.exe_name() == command)
+    }
+
+    /// Full path of the executable that would be run, if it is found
+    pub fn path(&self) -> Option<PathBuf> {
+        let executable = self.find_executable()?;
+        // Found in PATH by name; resolve where
+        Some(which::which(&executable).unwrap_or(executable))
+    }
+
+    /// Whether the tool is installed, and if so its version, path and whether it is outdated
+    /// (runs the tool, so it takes a moment)
+    pub fn status(&self) -> DependencyStatus {
+        match self.version() {
+            Some(version) => DependencyStatus::Installed {
+                outdated: self.check_version(&version),
+                version: Some(version),
+                path: self.path(),
+            },
+            // Found but doesn't report a version the usual way
+            None if self.is_available() => DependencyStatus::Installed {
+                version: None,
+                path: self.path(),
+                outdated: None,
+            },
+            None => DependencyStatus::Missing,
+        }
+    }
+
     /// Get fallback executable names (for ImageMagick which can be "convert" on Linux/macOS)
     fn fallback_names(&self) -> &[&str] {
         match self {
@@ -133,14 +231,130 @@ impl Dependency {
     }
 }
 
+/// What was found of one tool (see `Dependency::status`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyStatus {
+    Installed {
+        /// None when the tool doesn't report a version the usual way
+        version: Option<String>,
+        path: Option<PathBuf>,
+        /// Set when the version is older than supported
+        outdated: Option<OutdatedDependency>,
+    },
+    Missing,
+}
+
+impl DependencyStatus {
+    pub fn is_installed(&self) -> bool {
+        matches!(self, DependencyStatus::Installed { .. })
+    }
+}
+
+/// An installed tool older than nameback supports
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutdatedDependency {
+    pub dependency: Dependency,
+    /// Version found
+    pub version: String,
+    /// Oldest supported version
+    pub minimum: &'static str,
+    /// What works less well or not at all with the older version
+    pub impact: &'static str,
+}
+
+impl fmt::Display for OutdatedDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} is older than {}: {}",
+            self.dependency.name(),
+            self.version,
+            self.minimum,
+            self.impact
+        )
+    }
+}
+
+/// Files kept as examples of what needs a tool
+const SAMPLE_FILES: usize = 3;
+
+/// Smart detection stops after this many files; counts are then a lower bound
+const MAX_SCANNED_FILES: usize = 1000;
+
+/// Extensions named in a summary before the rest are counted as "other"
+const SUMMARY_EXTENSIONS: usize = 3;
+
+/// The sampled files a tool would be used for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilesNeeding {
+    pub count: usize,
+    /// Files per lowercase extension ("" for none)
+    pub extensions: BTreeMap<String, usize>,
+    /// The first few of the files
+    pub samples: Vec<PathBuf>,
+}
+
+impl FilesNeeding {
+    fn add(&mut self, path: &Path, extension: &str) {
+        self.count += 1;
+        *self.extensions.entry(extension.to_string()).or_default() += 1;
+        if self.samples.len() < SAMPLE_FILES {
+            self.samples.push(path.to_path_buf());
+        }
+    }
+
+    /// "42 .mp4 and 3 .mov files", most common extensions first
+    pub fn summary(&self) -> String {
+        let mut extensions: Vec<(&String, &usize)> =
+            self.extensions.iter().filter(|(extension, _)| !extension.is_empty()).collect();
+        extensions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        extensions.truncate(SUMMARY_EXTENSIONS);
+
+        let mut parts: Vec<String> =
+            extensions.iter().map(|(extension, count)| format!("{} .{}", count, extension)).collect();
+        let named: usize = extensions.iter().map(|(_, count)| **count).sum();
+        if named < self.count {
+            parts.push(format!("{} other", self.count - named));
+        }
+
+        let noun = if self.count == 1 { "file" } else { "files" };
+        match parts.split_last() {
+            Some((last, [])) => format!("{} {}", last, noun),
+            Some((last, rest)) => format!("{} and {} {}", rest.join(", "), last, noun),
+            None => format!("0 {}", noun),
+        }
+    }
+}
+
 /// Result of smart dependency detection
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct DependencyNeeds {
     pub missing_required: Vec<Dependency>,
     pub missing_optional: Vec<Dependency>,
+    /// Installed tools older than supported; they are still used
+    pub outdated: Vec<OutdatedDependency>,
+    /// The sampled files each tool would be used for, installed or not
+    pub needed_by: HashMap<Dependency, FilesNeeding>,
+    /// Whether there were more files than were scanned, so the counts in `needed_by` are
+    /// only those of the first ones
+    pub sampled: bool,
 }
 
 impl DependencyNeeds {
+    /// Why a tool is wanted, e.g. "needed for 42 .mp4 files (e.g. clip.mp4)", or "needed for at
+    /// least 42 .mp4 files" when the scan stopped early (see `sampled`)
+    pub fn reason(&self, dependency: Dependency) -> Option<String> {
+        let files = self.needed_by.get(&dependency).filter(|files| files.count > 0)?;
+        let examples: Vec<String> = files
+            .samples
+            .iter()
+            .map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned())
+            .collect();
+        let at_least = if self.sampled { "at least " } else { "" };
+        Some(format!("needed for {}{} (e.g. {})", at_least, files.summary(), examples.join(", ")))
+    }
+
+    /// Whether no tool is missing; outdated tools are still used, so they don't count
     pub fn is_empty(&self) -> bool {
         self.missing_required.is_empty() && self.missing_optional.is_empty()
     }
@@ -152,15 +366,20 @@ impl DependencyNeeds {
 
 /// Smart detection: scan directory and determine which dependencies are actually needed
 pub fn detect_needed_dependencies(directory: &Path) -> Result<DependencyNeeds> {
-    let mut needs_tesseract = false;
-    let mut needs_ffmpeg = false;
-    let mut needs_imagemagick = false; // Reserved for future HEIC detection
+    detect_needed_dependencies_for(&[directory.to_path_buf()])
+}
+
+/// Smart detection for several directories and files analyzed as one batch
+/// (see `RenameEngine::analyze_paths`); the file sample is shared between them
+pub fn detect_needed_dependencies_for(paths: &[PathBuf]) -> Result<DependencyNeeds> {
+    let mut needed_by: HashMap<Dependency, FilesNeeding> = HashMap::new();
 
     // Quick scan of file types (just check extensions)
     let mut file_count = 0;
-    for entry in WalkDir::new(directory)
-        .max_depth(3) // Don't scan too deep
-        .into_iter()
+    let mut sampled = false;
+    for entry in paths
+        .iter()
+        .flat_map(|path| WalkDir::new(path).max_depth(3)) // Don't scan too deep
         .filter_map(|e| e.ok())
     {
         if !entry.file_type().is_file() {
@@ -168,63 +387,73 @@ pub fn detect_needed_dependencies(directory: &Path) -> Result<DependencyNeeds> {
         }
 
         file_count += 1;
-        if file_count > 1000 {
+        if file_count > MAX_SCANNED_FILES {
             // Sampled enough files
+            sampled = true;
             break;
         }
 
         let path = entry.path();
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            let ext_lower = ext.to_lowercase();
-
-            match ext_lower.as_str() {
-                // Images that might need OCR
-                "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tiff" | "tif" | "webp" => {
-                    needs_tesseract = true;
-                }
-                // HEIC files need ImageMagick on Windows/Linux
-                "heic" | "heif" => {
-                    needs_tesseract = true;
-                    #[cfg(not(target_os = "macos"))]
-                    {
-                        needs_imagemagick = true;
-                    }
-                }
-                // Videos need FFmpeg for frame extraction
-                "mp4" | "mov" | "avi" | "mkv" | "webm" | "flv" | "wmv" | "m4v" => {
-                    needs_ffmpeg = true;
-                    needs_tesseract = true; // For OCR on extracted frames
+        let ext_lower = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+
+        let tools: &[Dependency] = match ext_lower.as_str() {
+            // Images that might need OCR
+            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tiff" | "tif" | "webp" => &[Dependency::Tesseract],
+            // HEIC and RAW files need ImageMagick on Windows/Linux
+            ext if ext == "heic" || ext == "heif" || crate::detector::RAW_EXTENSIONS.contains(&ext) => {
+                if cfg!(target_os = "macos") {
+                    &[Dependency::Tesseract]
+                } else {
+                    &[Dependency::Tesseract, Dependency::ImageMagick]
                 }
-                _ => {}
             }
+            // Videos need FFmpeg for frame extraction, and OCR on the extracted frames
+            "mp4" | "mov" | "avi" | "mkv" | "webm" | "flv" | "wmv" | "m4v" => {
+                &[Dependency::FFmpeg, Dependency::Tesseract]
+            }
+            _ => &[],
+        };
+        // ExifTool reads the metadata of every file
+        for tool in std::iter::once(&Dependency::ExifTool).chain(tools) {
+            needed_by.entry(*tool).or_default().add(path, &ext_lower);
         }
     }
+    let needs = |dependency: Dependency| needed_by.contains_key(&dependency);
 
     // Check which dependencies are actually missing
-    let mut missing_required = Vec::new();
     let mut missing_optional = Vec::new();
 
-    // ExifTool is always required
+    // ExifTool reads far more formats and tags than the built-in reader, but isn't required
     if !Dependency::ExifTool.is_available() {
-        missing_required.push(Dependency::ExifTool);
+        missing_optional.push(Dependency::ExifTool);
     }
 
     // Optional dependencies - only if needed
-    if needs_tesseract && !Dependency::Tesseract.is_available() {
-        missing_optional.push(Dependency::Tesseract);
-    }
-
-    if needs_ffmpeg && !Dependency::FFmpeg.is_available() {
-        missing_optional.push(Dependency::FFmpeg);
+    for dependency in [Dependency::Tesseract, Dependency::FFmpeg, Dependency::ImageMagick] {
+        if needs(dependency) && !dependency.is_available() {
+            missing_optional.push(dependency);
+        }
     }
 
-    if needs_imagemagick && !Dependency::ImageMagick.is_available() {
-        missing_optional.push(Dependency::ImageMagick);
-    }
+    // Installed tools too old to do everything nameback asks of them, of those this batch uses
+    // (each check runs the tool)
+    let outdated = Dependency::ALL
+        .iter()
+        .filter(|dependency| needs(**dependency) && !missing_optional.contains(dependency))
+        .filter_map(Dependency::outdated)
+        .collect();
 
     Ok(DependencyNeeds {
-        missing_required,
+        // Nothing is strictly required since metadata can be read without exiftool
+        missing_required: Vec::new(),
         missing_optional,
+        outdated,
+        needed_by,
+        sampled,
     })
 }
 
@@ -233,18 +462,19 @@ pub fn detect_needed_dependencies(directory: &Path) -> Result<DependencyNeeds> {
 /// 1. Bundled installer location ([INSTALLFOLDER]\deps\{tool})
 /// 2. PATH environment variable
 /// 3. Windows-specific: Scoop shims, Chocolatey bin (fallback)
+///
 /// Supports fallback names for platform-specific tool variants
 fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf> {
     // Try primary name in PATH
     if which::which(primary_name).is_ok() {
-        log::debug!("Found {} in PATH", primary_name);
+        tracing::debug!("Found {} in PATH", primary_name);
         return Some(PathBuf::from(primary_name));
     }
 
     // Try fallback names in PATH (e.g., "convert" for ImageMagick)
     for name in fallback_names {
         if which::which(name).is_ok() {
-            log::debug!("Found {} in PATH (fallback for {})", name, primary_name);
+            tracing::debug!("Found {} in PATH (fallback for {})", name, primary_name);
             return Some(PathBuf::from(name));
         }
     }
@@ -260,7 +490,7 @@ fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf
             // Check primary name
             let primary_path = scoop_shims.join(format!("{}.exe", primary_name));
             if primary_path.exists() {
-                log::debug!("Found {} in Scoop shims: {:?}", primary_name, primary_path);
+                tracing::debug!("Found {} in Scoop shims: {:?}", primary_name, primary_path);
                 return Some(primary_path);
             }
 
@@ -268,7 +498,7 @@ fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf
             for name in fallback_names {
                 let fallback_path = scoop_shims.join(format!("{}.exe", name));
                 if fallback_path.exists() {
-                    log::debug!("Found {} in Scoop shims (fallback for {}): {:?}",
+                    tracing::debug!("Found {} in Scoop shims (fallback for {}): {:?}",
                                name, primary_name, fallback_path);
                     return Some(fallback_path);
                 }
@@ -282,7 +512,7 @@ fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf
             // Check primary name
             let primary_path = choco_bin.join(format!("{}.exe", primary_name));
             if primary_path.exists() {
-                log::debug!("Found {} in Chocolatey bin: {:?}", primary_name, primary_path);
+                tracing::debug!("Found {} in Chocolatey bin: {:?}", primary_name, primary_path);
                 return Some(primary_path);
             }
 
@@ -290,7 +520,7 @@ fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf
             for name in fallback_names {
                 let fallback_path = choco_bin.join(format!("{}.exe", name));
                 if fallback_path.exists() {
-                    log::debug!("Found {} in Chocolatey bin (fallback for {}): {:?}",
+                    tracing::debug!("Found {} in Chocolatey bin (fallback for {}): {:?}",
                                name, primary_name, fallback_path);
                     return Some(fallback_path);
                 }
@@ -299,10 +529,44 @@ fn find_tool_path(primary_name: &str, fallback_names: &[&str]) -> Option<PathBuf
 
     }
 
-    log::debug!("Tool not found: {}", primary_name);
+    tracing::debug!("Tool not found: {}", primary_name);
     None
 }
 
+/// Whether a version (e.g. "7.1.1-15") is at least `minimum` (e.g. "6.9"), comparing the
+/// dotted numbers it starts with; None if it doesn't start with a number
+pub(crate) fn version_at_least(version: &str, minimum: &str) -> Option<bool> {
+    let numbers = |text: &str| -> Vec<u32> {
+        text.split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    let (version, minimum) = (numbers(version), numbers(minimum));
+    if version.is_empty() {
+        return None;
+    }
+    let length = version.len().max(minimum.len());
+    let padded = |mut numbers: Vec<u32>| {
+        numbers.resize(length, 0);
+        numbers
+    };
+    Some(padded(version) >= padded(minimum))
+}
+
+/// The version number in a tool's version output (the first word starting with a digit on
+/// its first line), or that line itself
+fn parse_version(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let version = line
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(line);
+    Some(version.trim_end_matches(',').to_string())
+}
+
 /// Legacy helper for backward compatibility
 /// Prefer using Dependency::create_command() instead
 pub fn create_command(tool_name: &str) -> Command {
@@ -321,6 +585,7 @@ mod tests {
         assert_eq!(Dependency::Tesseract.name(), "tesseract");
         assert_eq!(Dependency::FFmpeg.name(), "ffmpeg");
         assert_eq!(Dependency::ImageMagick.name(), "imagemagick");
+        assert_eq!(Dependency::Chromaprint.name(), "chromaprint");
     }
 
     #[test]
@@ -328,16 +593,42 @@ mod tests {
         let needs = DependencyNeeds {
             missing_required: vec![],
             missing_optional: vec![],
+            outdated: vec![],
+            needed_by: HashMap::new(),
+            sampled: false,
         };
         assert!(needs.is_empty());
         assert!(!needs.has_required_missing());
     }
 
+    #[test]
+    fn test_outdated_tools_alone_need_nothing() {
+        let needs = DependencyNeeds {
+            outdated: vec![OutdatedDependency {
+                dependency: Dependency::FFmpeg,
+                version: "3.4".to_string(),
+                minimum: "4.0",
+                impact: "slower frame extraction",
+            }],
+            ..DependencyNeeds::default()
+        };
+        assert!(needs.is_empty());
+
+        // Only the tools the files need are version-checked
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), b"x").unwrap();
+        let needs = detect_needed_dependencies_for(&[temp_dir.path().to_path_buf()]).unwrap();
+        assert!(needs.outdated.iter().all(|outdated| outdated.dependency == Dependency::ExifTool));
+    }
+
     #[test]
     fn test_dependency_needs_with_required() {
         let needs = DependencyNeeds {
             missing_required: vec![Dependency::ExifTool],
             missing_optional: vec![],
+            outdated: vec![],
+            needed_by: HashMap::new(),
+            sampled: false,
         };
         assert!(!needs.is_empty());
         assert!(needs.has_required_missing());
@@ -348,8 +639,85 @@ mod tests {
         let needs = DependencyNeeds {
             missing_required: vec![],
             missing_optional: vec![Dependency::Tesseract],
+            outdated: vec![],
+            needed_by: HashMap::new(),
+            sampled: false,
         };
         assert!(!needs.is_empty());
         assert!(!needs.has_required_missing());
     }
+
+    #[test]
+    fn test_files_needing_each_tool() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for name in ["a.mp4", "b.MP4", "c.mov", "photo.jpg", "notes.txt", "README"] {
+            std::fs::write(temp_dir.path().join(name), b"x").unwrap();
+        }
+        let needs = detect_needed_dependencies_for(&[temp_dir.path().to_path_buf()]).unwrap();
+
+        let ffmpeg = &needs.needed_by[&Dependency::FFmpeg];
+        assert_eq!(ffmpeg.count, 3);
+        assert_eq!(ffmpeg.summary(), "2 .mp4 and 1 .mov files");
+        assert_eq!(needs.needed_by[&Dependency::Tesseract].count, 4);
+        assert_eq!(needs.needed_by[&Dependency::ExifTool].count, 6);
+        assert!(!needs.needed_by.contains_key(&Dependency::Chromaprint));
+
+        let reason = needs.reason(Dependency::FFmpeg).unwrap();
+        assert!(reason.starts_with("needed for 2 .mp4 and 1 .mov files (e.g. "), "{}", reason);
+        assert_eq!(needs.reason(Dependency::Chromaprint), None);
+        assert!(!needs.sampled);
+
+        // Counts from a scan cut short are lower bounds
+        let sampled = DependencyNeeds { sampled: true, ..needs };
+        let reason = sampled.reason(Dependency::FFmpeg).unwrap();
+        assert!(reason.starts_with("needed for at least 2 .mp4 and 1 .mov files (e.g. "), "{}", reason);
+    }
+
+    #[test]
+    fn test_files_needing_summary() {
+        let mut files = FilesNeeding::default();
+        for (name, extension) in [("a.mp4", "mp4"), ("b.mkv", "mkv"), ("c.mov", "mov"), ("d.avi", "avi"), ("e", "")] {
+            files.add(Path::new(name), extension);
+        }
+        files.add(Path::new("f.mp4"), "mp4");
+        assert_eq!(files.summary(), "2 .mp4, 1 .avi, 1 .mkv and 2 other files");
+        assert_eq!(files.samples.len(), SAMPLE_FILES);
+
+        let mut one = FilesNeeding::default();
+        one.add(Path::new("clip.mp4"), "mp4");
+        assert_eq!(one.summary(), "1 .mp4 file");
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("12.76\n").as_deref(), Some("12.76"));
+        assert_eq!(parse_version("tesseract 5.3.0\n leptonica-1.82.0").as_deref(), Some("5.3.0"));
+        assert_eq!(
+            parse_version("Version: ImageMagick 7.1.1-15 Q16-HDRI x86_64 https://imagemagick.org").as_deref(),
+            Some("7.1.1-15")
+        );
+        assert_eq!(parse_version("ffmpeg version n6.1 Copyright").as_deref(), Some("ffmpeg version n6.1 Copyright"));
+        assert_eq!(parse_version("  \n"), None);
+    }
+
+    #[test]
+    fn test_version_at_least() {
+        assert_eq!(version_at_least("12.76", "10.80"), Some(true));
+        assert_eq!(version_at_least("10.8", "10.80"), Some(false));
+        assert_eq!(version_at_least("7.1.1-15", "6.9"), Some(true));
+        assert_eq!(version_at_least("6.1.1-3ubuntu5", "4.0"), Some(true));
+        assert_eq!(version_at_least("4", "4.0"), Some(true));
+        assert_eq!(version_at_least("3.05.02", "4.0"), Some(false));
+        assert_eq!(version_at_least("n6.1", "4.0"), None);
+    }
+
+    #[test]
+    fn test_check_version() {
+        let outdated = Dependency::Tesseract.check_version("3.05.02").expect("3.05 is too old");
+        assert_eq!(outdated.minimum, "4.0");
+        assert!(outdated.to_string().starts_with("tesseract 3.05.02 is older than 4.0: "));
+        assert_eq!(Dependency::Tesseract.check_version("5.3.0"), None);
+        assert_eq!(Dependency::FFmpeg.check_version("ffmpeg version n6.1"), None);
+        assert_eq!(Dependency::from_command("magick"), Some(Dependency::ImageMagick));
+    }
 }
@@ -1,10 +1,14 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::str::FromStr;
 
 /// Represents the category of a file based on its type
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FileCategory {
     Image,
     Document,
@@ -13,26 +17,170 @@ pub enum FileCategory {
     Email,
     Web,
     Archive,
+    /// Installers and packages: .exe, .msi, .dmg, .deb, .rpm, .apk
+    Installer,
+    Ebook,
+    #[serde(rename = "code")]
     SourceCode,
+    #[serde(rename = "other")]
     Unknown,
 }
 
+impl FileCategory {
+    /// Every category, including `Unknown` for unsupported files
+    pub const ALL: [FileCategory; 11] = [
+        FileCategory::Image,
+        FileCategory::Document,
+        FileCategory::Audio,
+        FileCategory::Video,
+        FileCategory::Email,
+        FileCategory::Web,
+        FileCategory::Archive,
+        FileCategory::Installer,
+        FileCategory::Ebook,
+        FileCategory::SourceCode,
+        FileCategory::Unknown,
+    ];
+
+    /// Lowercase name, e.g. `image` (`code` for source code, `other` for unknown files)
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileCategory::Image => "image",
+            FileCategory::Document => "document",
+            FileCategory::Audio => "audio",
+            FileCategory::Video => "video",
+            FileCategory::Email => "email",
+            FileCategory::Web => "web",
+            FileCategory::Archive => "archive",
+            FileCategory::Installer => "installer",
+            FileCategory::Ebook => "ebook",
+            FileCategory::SourceCode => "code",
+            FileCategory::Unknown => "other",
+        }
+    }
+}
+
+impl fmt::Display for FileCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FileCategory {
+    type Err = anyhow::Error;
+
+    /// Accepts the name, its plural (`images`) and a few aliases (`docs`, `source`, `unknown`)
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase();
+        let singular = name.strip_suffix('s').filter(|n| !n.is_empty()).unwrap_or(&name);
+        let category = FileCategory::ALL
+            .into_iter()
+            .find(|c| c.name() == name || c.name() == singular)
+            .or(match singular {
+                "doc" => Some(FileCategory::Document),
+                "book" => Some(FileCategory::Ebook),
+                "package" => Some(FileCategory::Installer),
+                "source" | "sourcecode" => Some(FileCategory::SourceCode),
+                "unknown" => Some(FileCategory::Unknown),
+                _ => None,
+            });
+        category.ok_or_else(|| {
+            let names: Vec<&str> = FileCategory::ALL.iter().map(FileCategory::name).collect();
+            anyhow::anyhow!("unknown file category '{}' (expected {})", s, names.join(", "))
+        })
+    }
+}
+
+/// A file whose content is in another format than its extension says
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionMismatch {
+    /// The file's own extension (`None` when its name has none)
+    pub extension: Option<String>,
+    /// The usual extension of the format its content is in, e.g. `png`
+    pub detected: String,
+    /// Whether the proposed name was given the detected extension
+    /// (`RenameConfig::fix_extensions`)
+    pub fixed: bool,
+}
+
+impl ExtensionMismatch {
+    /// The mismatch as reported for a file that was named or not: fixed when
+    /// `RenameConfig::fix_extensions` is on and there is a name to give the extension to
+    pub(crate) fn settled(self, config: &crate::RenameConfig, named: bool) -> Self {
+        ExtensionMismatch {
+            fixed: config.fix_extensions && named,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for ExtensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.extension {
+            Some(extension) => write!(f, "a .{} file named .{}", self.detected, extension),
+            None => write!(f, "a .{} file without an extension", self.detected),
+        }
+    }
+}
+
+/// Extensions of one format; a file with one of them whose content infer reports under
+/// another isn't misnamed (an MPEG-4 `.mov` or `.m4a`, a `.jpeg`, a `.docm` macro document)
+const SAME_FORMAT: &[&[&str]] = &[
+    &["jpg", "jpeg", "jpe", "jfif"],
+    &["tif", "tiff"],
+    &["heif", "heic", "hif"],
+    &["ico", "cur"],
+    &["psd", "psb"],
+    &["djvu", "djv"],
+    &["jp2", "j2k", "jpf", "jpx"],
+    &["mp4", "m4v", "m4a", "m4b", "m4p", "mov", "qt", "3gp", "3g2"],
+    &["mkv", "mka", "mk3d", "webm"],
+    &["mpg", "mpeg", "mpe", "m2v", "vob"],
+    &["wmv", "wma", "asf"],
+    &["flv", "f4v"],
+    &["mp3", "mp2", "aac", "mpga"],
+    &["ogg", "oga", "ogv", "opus", "spx"],
+    &["wav", "wave"],
+    &["aiff", "aif", "aifc"],
+    &["midi", "mid"],
+    &["pdf", "ai"],
+    &["mobi", "azw", "azw3", "prc"],
+    &["doc", "dot"],
+    &["xls", "xlt"],
+    &["ppt", "pps", "pot"],
+    &["docx", "docm", "dotx", "dotm"],
+    &["xlsx", "xlsm", "xltx", "xltm"],
+    &["pptx", "pptm", "potx", "ppsx"],
+];
+
+/// What a file's first bytes say about it
+pub(crate) struct Detection {
+    pub category: FileCategory,
+    /// Set when the content is in another format than the extension says
+    pub mismatch: Option<ExtensionMismatch>,
+}
+
 /// Detects the file type using the infer library (pure Rust, cross-platform)
-pub fn detect_file_type(path: &Path) -> Result<FileCategory> {
-    // Read the first 8192 bytes for file type detection
-    let mut file = File::open(path)?;
-    let mut buffer = vec![0u8; 8192];
-    let bytes_read = file.read(&mut buffer)?;
-    buffer.truncate(bytes_read);
+#[cfg(test)]
+fn detect_file_type(path: &Path) -> Result<FileCategory> {
+    detect(path).map(|detection| detection.category)
+}
+
+/// Detects the file type and checks the extension against it
+pub(crate) fn detect(path: &Path) -> Result<Detection> {
+    let kind = sniff(path)?;
 
     // Use infer to detect file type from magic bytes
-    let category = if let Some(kind) = infer::get(&buffer) {
+    let category = if let Some(kind) = &kind {
         let mime_type = kind.mime_type();
 
         match mime_type {
             // Image types
             s if s.starts_with("image/") => FileCategory::Image,
 
+            // Ebooks (EPUB is a ZIP container, so this comes before other document types)
+            "application/epub+zip" | "application/x-mobipocket-ebook" => FileCategory::Ebook,
+
             // Document types
             "application/pdf" => FileCategory::Document,
             s if s.starts_with("application/vnd.openxmlformats-officedocument") => {
@@ -44,6 +192,23 @@ pub fn detect_file_type(path: &Path) -> Result<FileCategory> {
             "application/msword" => FileCategory::Document,
             s if s.starts_with("text/") => FileCategory::Document,
 
+            // Plain compound files (Outlook .msg, .msi installers) are told apart by extension
+            "application/x-ole-storage" => detect_by_extension(path),
+
+            // Containers hold many kinds of file (.apk and .jar are zips, .dll is an executable),
+            // so the extension says which category this one belongs to
+            "application/zip"
+            | "application/gzip"
+            | "application/x-tar"
+            | "application/x-7z-compressed"
+            | "application/x-bzip2"
+            | "application/x-xz"
+            | "application/vnd.rar"
+            | "application/vnd.microsoft.portable-executable" => detect_by_extension(path),
+
+            // Linux packages
+            "application/vnd.debian.binary-package" | "application/x-rpm" => FileCategory::Installer,
+
             // Audio types
             s if s.starts_with("audio/") => FileCategory::Audio,
 
@@ -57,11 +222,93 @@ pub fn detect_file_type(path: &Path) -> Result<FileCategory> {
         detect_by_extension(path)
     };
 
-    Ok(category)
+    Ok(Detection {
+        category,
+        mismatch: kind.and_then(|kind| extension_mismatch(path, &kind)),
+    })
+}
+
+/// Reads the first 8192 bytes and matches them against infer's magic numbers
+fn sniff(path: &Path) -> Result<Option<infer::Type>> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; 8192];
+    let bytes_read = file.read(&mut buffer)?;
+    buffer.truncate(bytes_read);
+    Ok(infer::get(&buffer))
+}
+
+/// Checks a file's extension against its content (`None` when they agree, or when the
+/// file can't be read)
+pub(crate) fn check_extension(path: &Path) -> Option<ExtensionMismatch> {
+    let kind = sniff(path).ok().flatten()?;
+    extension_mismatch(path, &kind)
+}
+
+/// The extension that says what a file is: the one its content calls for when its own is
+/// missing or belongs to another format, otherwise its own (lowercased)
+/// Reads the file's first bytes; a file already detected takes `content_extension`
+pub(crate) fn effective_extension(path: &Path) -> Option<String> {
+    content_extension(path, check_extension(path).as_ref())
+}
+
+/// `effective_extension` of a file whose detection found `mismatch`
+pub(crate) fn content_extension(path: &Path, mismatch: Option<&ExtensionMismatch>) -> Option<String> {
+    match mismatch {
+        Some(mismatch) => Some(mismatch.detected.clone()),
+        None => path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase),
+    }
+}
+
+/// Compares a file's extension with the format infer found in it
+/// Only formats that content settles are checked: containers (ZIP, compound files, gzip)
+/// hold many formats told apart by extension, and RAW photos are TIFFs or carry JPEG previews
+fn extension_mismatch(path: &Path, kind: &infer::Type) -> Option<ExtensionMismatch> {
+    let settles_format = matches!(
+        kind.matcher_type(),
+        infer::MatcherType::Image
+            | infer::MatcherType::Video
+            | infer::MatcherType::Audio
+            | infer::MatcherType::Book
+            | infer::MatcherType::Doc
+    ) || matches!(kind.mime_type(), "application/pdf" | "application/rtf");
+    if !settles_format || is_raw(path) {
+        return None;
+    }
+
+    let detected = kind.extension();
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    if extension.is_some_and(|extension| same_format(extension, detected)) {
+        return None;
+    }
+    Some(ExtensionMismatch {
+        extension: extension.map(str::to_string),
+        detected: detected.to_string(),
+        fixed: false,
+    })
+}
+
+/// Whether two extensions name the same format: equal ignoring case, or aliases like
+/// `jpg`/`jpeg` (see `SAME_FORMAT`)
+pub(crate) fn same_format(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    a == b
+        || SAME_FORMAT
+            .iter()
+            .any(|group| group.contains(&a.as_str()) && group.contains(&b.as_str()))
+}
+
+/// Camera RAW formats; most start like a TIFF, but some (CR3, RAF, ORF) have no magic infer knows
+pub(crate) const RAW_EXTENSIONS: &[&str] = &["cr2", "cr3", "nef", "arw", "dng", "raf", "orf", "rw2", "pef", "srw"];
+
+/// Checks if a file is a camera RAW image based on extension
+pub(crate) fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
 }
 
 /// Fallback file type detection based on extension
-fn detect_by_extension(path: &Path) -> FileCategory {
+pub(crate) fn detect_by_extension(path: &Path) -> FileCategory {
     path.extension()
         .and_then(|ext| ext.to_str())
         .map(|ext| {
@@ -70,15 +317,20 @@ fn detect_by_extension(path: &Path) -> FileCategory {
                 // Images
                 "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tiff" | "tif" | "webp" | "heic"
                 | "heif" | "ico" | "svg" => FileCategory::Image,
+                ext if RAW_EXTENSIONS.contains(&ext) => FileCategory::Image,
                 // Documents
                 "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods"
                 | "odp" | "rtf" | "txt" | "md" | "markdown" | "csv" => FileCategory::Document,
+                // Ebooks
+                "epub" | "mobi" | "azw" | "azw3" => FileCategory::Ebook,
                 // Email
                 "eml" | "msg" => FileCategory::Email,
                 // Web
                 "html" | "htm" | "mhtml" => FileCategory::Web,
                 // Archive
                 "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" => FileCategory::Archive,
+                // Installer
+                "exe" | "msi" | "dmg" | "deb" | "rpm" | "apk" => FileCategory::Installer,
                 // Source Code (non-text mime types)
                 "py" | "js" | "ts" | "rs" | "java" | "c" | "cpp" | "cc" | "cxx" | "h" | "hpp" | "hxx" => FileCategory::SourceCode,
                 // Config files as documents
@@ -122,6 +374,16 @@ mod tests {
             detect_by_extension(&PathBuf::from("photo.HEIC")),
             FileCategory::Image
         );
+        assert_eq!(
+            detect_by_extension(&PathBuf::from("IMG_1234.CR3")),
+            FileCategory::Image
+        );
+        assert_eq!(
+            detect_by_extension(&PathBuf::from("DSCF0042.raf")),
+            FileCategory::Image
+        );
+        assert!(is_raw(&PathBuf::from("DSC_0001.NEF")));
+        assert!(!is_raw(&PathBuf::from("DSC_0001.jpg")));
     }
 
     #[test]
@@ -148,6 +410,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_by_extension_ebooks() {
+        assert_eq!(
+            detect_by_extension(&PathBuf::from("novel.epub")),
+            FileCategory::Ebook
+        );
+        assert_eq!(
+            detect_by_extension(&PathBuf::from("novel.MOBI")),
+            FileCategory::Ebook
+        );
+        assert_eq!(
+            detect_by_extension(&PathBuf::from("novel.azw3")),
+            FileCategory::Ebook
+        );
+    }
+
     #[test]
     fn test_detect_by_extension_audio() {
         assert_eq!(
@@ -224,6 +502,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_by_extension_installer() {
+        for name in ["Firefox Setup.exe", "tool.msi", "App.dmg", "pkg.deb", "pkg.rpm", "app.apk"] {
+            assert_eq!(detect_by_extension(&PathBuf::from(name)), FileCategory::Installer, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_detect_file_type_containers_by_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_magic = b"PK\x03\x04\x14\x00\x00\x00\x08\x00";
+        for (name, expected) in [
+            ("files.zip", FileCategory::Archive),
+            ("app.apk", FileCategory::Installer),
+            ("library.jar", FileCategory::Unknown),
+        ] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, zip_magic).unwrap();
+            assert_eq!(detect_file_type(&path).unwrap(), expected, "{}", name);
+        }
+    }
+
     #[test]
     fn test_detect_by_extension_source_code() {
         assert_eq!(
@@ -264,6 +564,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_category_names() {
+        for category in FileCategory::ALL {
+            assert_eq!(category.name().parse::<FileCategory>().unwrap(), category);
+        }
+        assert_eq!("Images".parse::<FileCategory>().unwrap(), FileCategory::Image);
+        assert_eq!(" docs ".parse::<FileCategory>().unwrap(), FileCategory::Document);
+        assert_eq!("audio".parse::<FileCategory>().unwrap(), FileCategory::Audio);
+        assert_eq!("source".parse::<FileCategory>().unwrap(), FileCategory::SourceCode);
+        assert_eq!("books".parse::<FileCategory>().unwrap(), FileCategory::Ebook);
+        assert_eq!("packages".parse::<FileCategory>().unwrap(), FileCategory::Installer);
+        assert!("spreadsheets".parse::<FileCategory>().is_err());
+    }
+
     #[test]
     fn test_detect_file_type_with_temp_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -314,4 +628,53 @@ mod tests {
         let result = detect_file_type(&txt_path).unwrap();
         assert_eq!(result, FileCategory::Document);
     }
+
+    #[test]
+    fn test_extension_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let png_magic = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let jpeg_magic = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46];
+        let zip_magic = b"PK\x03\x04\x14\x00\x00\x00\x08\x00";
+        let write = |name: &str, bytes: &[u8]| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            path
+        };
+
+        let misnamed = write("photo.JPG", &png_magic);
+        let detection = detect(&misnamed).unwrap();
+        assert_eq!(detection.category, FileCategory::Image);
+        let mismatch = detection.mismatch.unwrap();
+        assert_eq!(mismatch.extension.as_deref(), Some("JPG"));
+        assert_eq!(mismatch.detected, "png");
+        assert_eq!(mismatch.to_string(), "a .png file named .JPG");
+        assert_eq!(effective_extension(&misnamed).as_deref(), Some("png"));
+
+        let unnamed = write("download", b"%PDF-1.4\n");
+        let detection = detect(&unnamed).unwrap();
+        assert_eq!(detection.category, FileCategory::Document);
+        assert_eq!(detection.mismatch.unwrap().to_string(), "a .pdf file without an extension");
+
+        // Other names of the same format, containers and RAW files aren't mismatches
+        assert_eq!(check_extension(&write("photo.jpeg", &jpeg_magic)), None);
+        assert_eq!(check_extension(&write("photo.png", &png_magic)), None);
+        assert_eq!(check_extension(&write("library.jar", zip_magic)), None);
+        assert_eq!(check_extension(&write("DSC_0001.NEF", &jpeg_magic)), None);
+        assert_eq!(effective_extension(&write("notes.TXT", b"plain text")).as_deref(), Some("txt"));
+    }
+
+    #[test]
+    fn test_detect_file_type_outlook_message() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Outlook messages are plain compound files, like .msi installers
+        let msg_path = temp_dir.path().join("message.msg");
+        let mut msg = cfb::create(&msg_path).unwrap();
+        msg.create_storage("/__attach_version1.0_#00000000").unwrap();
+        msg.flush().unwrap();
+        drop(msg);
+
+        let result = detect_file_type(&msg_path).unwrap();
+        assert_eq!(result, FileCategory::Email);
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::detector::FileCategory;
+use crate::scorer::{NameCandidate, NameSource};
+use crate::{FileAnalysis, RenameConfig};
+
+/// Most words of the files' common subject used in a folder name
+const MAX_SUBJECT_WORDS: usize = 3;
+
+/// Widest range of years a folder name spans, e.g. `2021-2023`
+const MAX_YEAR_SPAN: i32 = 5;
+
+/// Names file managers give new folders, lowercase, without the counter they add
+const PLACEHOLDER_NAMES: &[&str] = &[
+    "new folder", "newfolder", "untitled folder", "untitled", "folder", "new", "directory",
+    "neuer ordner", "nouveau dossier", "nueva carpeta", "nuova cartella", "nieuwe map",
+    "nova pasta", "新建文件夹", "新しいフォルダー", "새 폴더",
+];
+
+/// A year on its own or starting a date (`2022`, `2022-03-15`, `20220315`)
+static YEAR: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"(?:^|\D)((?:19|20)\d{2})(?:[01]\d[0-3]\d)?(?:\D|$)").expect("valid year regex")
+});
+
+/// Proposed names for folders under the `roots` directories that still have a placeholder
+/// name (`New Folder (3)`), worked out from the analyses of the files directly in them
+/// Returned deepest first, so renaming in order never moves a folder out from under another
+pub(crate) fn propose(roots: &[PathBuf], analyses: &[FileAnalysis], config: &RenameConfig) -> Vec<FileAnalysis> {
+    let mut by_folder: BTreeMap<&Path, Vec<&FileAnalysis>> = BTreeMap::new();
+    for analysis in analyses.iter().filter(|a| a.deferred.is_none()) {
+        let Some(folder) = analysis.original_path.parent() else {
+            continue;
+        };
+        // The folders given to scan keep their names
+        if roots.iter().any(|root| root.is_dir() && folder.starts_with(root) && folder != root) {
+            by_folder.entry(folder).or_default().push(analysis);
+        }
+    }
+
+    let style = config.name_style();
+    let mut taken: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    let mut proposals: Vec<FileAnalysis> = Vec::new();
+    for (folder, files) in by_folder {
+        let (Some(parent), Some(name)) = (folder.parent(), folder.file_name().and_then(|n| n.to_str())) else {
+            continue;
+        };
+        if !is_placeholder(name) {
+            continue;
+        }
+        let Some(words) = folder_name(&files) else {
+            continue;
+        };
+        let base = style.apply(&crate::generator::sanitize_component(&words));
+        if base.is_empty() {
+            continue;
+        }
+
+        // Unique among the folder's siblings, including names proposed to other placeholders
+        let siblings = taken.entry(parent.to_path_buf()).or_insert_with(|| names_in(parent));
+        let mut proposed = base.clone();
+        let mut counter = 2;
+        while siblings.contains(&proposed.to_lowercase()) {
+            proposed = format!("{}{}{}", base, style.separator(), counter);
+            counter += 1;
+        }
+        siblings.insert(proposed.to_lowercase());
+
+        tracing::debug!("Proposing {} for folder {}", proposed, folder.display());
+        let candidate = NameCandidate::new(words, NameSource::FolderContents);
+        proposals.push(FileAnalysis {
+            proposed_name: Some(proposed),
+            confidence: candidate.score,
+            name_source: NameSource::FolderContents,
+            ..crate::pipeline::unanalyzed(folder, FileCategory::Unknown)
+        });
+    }
+
+    proposals.sort_by_key(|p| std::cmp::Reverse(p.original_path.components().count()));
+    proposals
+}
+
+/// Put folder renames after every file rename, deepest folder first
+/// Files keep their order; the paths of files in a folder are only valid until it is renamed
+pub(crate) fn rename_order(analyses: &[FileAnalysis]) -> Vec<&FileAnalysis> {
+    let mut ordered: Vec<&FileAnalysis> = analyses.iter().collect();
+    ordered.sort_by_cached_key(|a| match a.original_path.is_dir() {
+        true => std::cmp::Reverse(a.original_path.components().count()),
+        false => std::cmp::Reverse(usize::MAX),
+    });
+    ordered
+}
+
+/// Whether `name` is one a file manager gives new folders, with or without a counter
+/// (`New Folder (3)`, `Untitled Folder 2`, `Nouveau dossier`)
+fn is_placeholder(name: &str) -> bool {
+    let lower = name.trim().to_lowercase();
+    let without_counter = lower
+        .trim_end_matches(|c: char| c.is_ascii_digit() || matches!(c, '(' | ')' | ' ' | '_' | '-' | '.'));
+    PLACEHOLDER_NAMES.contains(&without_counter)
+}
+
+/// Words for a folder from its files: what their titles have in common (topped up with the
+/// kind of file most of them are), then the year or years most of them are from
+/// e.g. "Tax Documents 2022"; None when the files share nothing worth naming the folder after
+fn folder_name(files: &[&FileAnalysis]) -> Option<String> {
+    let mut words = common_words(files);
+    if words.len() < 2 {
+        if let Some(label) = dominant_category(files).and_then(category_label) {
+            if !words.iter().any(|w| w.eq_ignore_ascii_case(label)) {
+                words.push(label.to_string());
+            }
+        }
+    }
+    words.extend(dominant_years(files));
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Words found in the titles of most files, in the order they first appear
+fn common_words(files: &[&FileAnalysis]) -> Vec<String> {
+    let titles: Vec<&str> = files.iter().filter_map(|a| a.title.as_deref()).collect();
+    if titles.is_empty() {
+        return Vec::new();
+    }
+
+    let mut order: Vec<String> = Vec::new();
+    let mut spelling: HashMap<String, &str> = HashMap::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for title in &titles {
+        let mut seen = HashSet::new();
+        for word in title.split(|c: char| !c.is_alphanumeric()).filter(|w| is_subject_word(w)) {
+            let key = word.to_lowercase();
+            if seen.insert(key.clone()) {
+                *counts.entry(key.clone()).or_default() += 1;
+            }
+            if !spelling.contains_key(&key) {
+                spelling.insert(key.clone(), word);
+                order.push(key);
+            }
+        }
+    }
+
+    let needed = titles.len() / 2 + 1;
+    order
+        .into_iter()
+        .filter(|key| counts[key] >= needed)
+        .take(MAX_SUBJECT_WORDS)
+        .map(|key| spelling[&key].to_string())
+        .collect()
+}
+
+/// Words that say what a file is about: not stop words, numbers or single letters
+fn is_subject_word(word: &str) -> bool {
+    word.chars().count() > 1
+        && !word.chars().all(|c| c.is_numeric())
+        && !crate::key_phrases::is_stop_word(word)
+}
+
+/// The category of at least half the files
+fn dominant_category(files: &[&FileAnalysis]) -> Option<FileCategory> {
+    let mut counts: HashMap<FileCategory, usize> = HashMap::new();
+    for analysis in files {
+        *counts.entry(analysis.file_category.clone()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| count * 2 >= files.len())
+        .max_by_key(|(category, count)| (*count, std::cmp::Reverse(category.name())))
+        .map(|(category, _)| category)
+}
+
+/// What a folder of mostly one kind of file holds
+fn category_label(category: FileCategory) -> Option<&'static str> {
+    Some(match category {
+        FileCategory::Image => "Photos",
+        FileCategory::Document => "Documents",
+        FileCategory::Audio => "Music",
+        FileCategory::Video => "Videos",
+        FileCategory::Email => "Emails",
+        FileCategory::Web => "Web Pages",
+        FileCategory::Archive => "Archives",
+        FileCategory::Installer => "Installers",
+        FileCategory::Ebook => "Ebooks",
+        FileCategory::SourceCode => "Code",
+        FileCategory::Unknown => return None,
+    })
+}
+
+/// The year most dated files are from, or else the range they span if it is short (`2021-2023`)
+/// Years come from the proposed and original names, which carry the files' dates
+fn dominant_years(files: &[&FileAnalysis]) -> Option<String> {
+    let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+    let mut dated = 0;
+    for analysis in files {
+        let names = [analysis.proposed_name.as_deref(), Some(analysis.original_name.as_str())];
+        let years: HashSet<i32> = names
+            .into_iter()
+            .flatten()
+            .flat_map(|name| YEAR.captures_iter(name).filter_map(|c| c[1].parse().ok()).collect::<Vec<i32>>())
+            .collect();
+        if !years.is_empty() {
+            dated += 1;
+        }
+        for year in years {
+            *counts.entry(year).or_default() += 1;
+        }
+    }
+
+    let (&top, &count) = counts.iter().max_by_key(|&(&year, &count)| (count, year))?;
+    if count * 2 > dated {
+        return Some(top.to_string());
+    }
+    let (&first, &last) = (counts.keys().next()?, counts.keys().next_back()?);
+    (last - first <= MAX_YEAR_SPAN).then(|| format!("{}-{}", first, last))
+}
+
+/// Lowercase names of the entries in `folder`
+fn names_in(folder: &Path) -> HashSet<String> {
+    fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .map(|name| name.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file(folder: &Path, name: &str, title: &str, proposed: &str, category: FileCategory) -> FileAnalysis {
+        fs::create_dir_all(folder).unwrap();
+        let path = folder.join(name);
+        fs::write(&path, name).unwrap();
+        FileAnalysis {
+            proposed_name: Some(proposed.to_string()),
+            title: Some(title.to_string()),
+            ..crate::pipeline::unanalyzed(&path, category)
+        }
+    }
+
+    #[test]
+    fn test_placeholder_names() {
+        assert!(is_placeholder("New Folder (3)"));
+        assert!(is_placeholder("untitled folder 2"));
+        assert!(is_placeholder("Nouveau dossier"));
+        assert!(is_placeholder("新建文件夹 (2)"));
+        assert!(!is_placeholder("Taxes"));
+        assert!(!is_placeholder("2023"));
+        assert!(!is_placeholder("Folder of Receipts"));
+    }
+
+    #[test]
+    fn test_folder_names_from_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let taxes = root.join("New Folder (3)");
+        let trip = root.join("Untitled Folder").join("New Folder");
+        fs::create_dir_all(root.join("Tax_Documents_2022")).unwrap();
+        let analyses = vec![
+            file(&taxes, "scan1.pdf", "2022 Tax Return", "2022_Tax_Return.pdf", FileCategory::Document),
+            file(&taxes, "scan2.pdf", "Property Tax Bill", "Property_Tax_Bill_2022-11-02.pdf", FileCategory::Document),
+            file(&taxes, "scan3.pdf", "Charity Receipt", "Charity_Receipt.pdf", FileCategory::Document),
+            file(&trip, "IMG_20230812_1.jpg", "Beach Sunset", "Beach_Sunset.jpg", FileCategory::Image),
+            file(&trip, "IMG_20240102_2.jpg", "Beach Picnic", "Beach_Picnic.jpg", FileCategory::Image),
+            file(&root.join("Receipts"), "a.pdf", "Lunch", "Lunch.pdf", FileCategory::Document),
+            file(root, "top.pdf", "Top", "Top.pdf", FileCategory::Document),
+        ];
+        let proposals = propose(&[root.to_path_buf()], &analyses, &RenameConfig::default());
+        let names: Vec<(&Path, &str)> = proposals
+            .iter()
+            .map(|p| (p.original_path.as_path(), p.proposed_name.as_deref().unwrap()))
+            .collect();
+
+        // Deepest first; the sibling already called Tax_Documents_2022 gets a counter;
+        // "Untitled Folder" has no files of its own
+        assert_eq!(
+            names,
+            [(trip.as_path(), "Beach_Photos_2023-2024"), (taxes.as_path(), "Tax_Documents_2022_2")]
+        );
+        assert!(proposals.iter().all(|p| p.name_source == NameSource::FolderContents));
+    }
+
+    #[test]
+    fn test_folders_are_renamed_after_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let analyses = vec![
+            crate::pipeline::unanalyzed(root, FileCategory::Unknown),
+            file(&root.join("a"), "x.pdf", "X", "X.pdf", FileCategory::Document),
+            crate::pipeline::unanalyzed(&root.join("a"), FileCategory::Unknown),
+            file(root, "y.pdf", "Y", "Y.pdf", FileCategory::Document),
+        ];
+        let ordered: Vec<&Path> = rename_order(&analyses).iter().map(|a| a.original_path.as_path()).collect();
+        assert_eq!(ordered, [root.join("a/x.pdf"), root.join("y.pdf"), root.join("a"), root.to_path_buf()]);
+    }
+}
@@ -0,0 +1,262 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::{FileAnalysis, NameStyle};
+
+/// Extensions whose raw bytes are readable text
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "csv", "json", "yaml", "yml", "xml", "html", "htm", "eml", "rtf",
+];
+
+/// Only the start of a text file is read; letterheads and totals are near the top
+const TEXT_READ_LIMIT: usize = 64 * 1024;
+
+/// Heading words that say what a document is, not who it's from
+const GENERIC_WORDS: &[&str] = &[
+    "invoice", "receipt", "statement", "bill", "quote", "quotation", "order", "page", "date",
+    "total", "tax", "vat", "no", "number", "ref", "reference", "to", "from", "of", "the",
+];
+
+/// Length of the content-hash token used as a last resort
+const HASH_TOKEN_LEN: usize = 6;
+
+/// Details that can tell apart files that ended up with the same proposed name
+#[derive(Debug, Default)]
+struct Traits {
+    /// Sender or letterhead: the first meaningful line of the document
+    vendor: Option<String>,
+    /// Largest currency amount, e.g. `USD1234.50`
+    amount: Option<String>,
+    /// Short content hash
+    hash: Option<String>,
+}
+
+impl Traits {
+    fn read(path: &Path) -> Self {
+        let text = document_text(path);
+        Self {
+            vendor: text.as_deref().and_then(vendor),
+            amount: text.as_deref().and_then(amount),
+            hash: crate::metadata_cache::MetadataCache::compute_file_hash(path)
+                .ok()
+                .map(|hash| hash.chars().take(HASH_TOKEN_LEN).collect()),
+        }
+    }
+}
+
+/// Replace blind `_1`/`_2` collision counters with tokens taken from each file's content
+/// Returns the indices of analyses whose proposed name changed
+pub(crate) fn disambiguate(
+    analyses: &mut [FileAnalysis],
+    style: NameStyle,
+    existing_names: &mut HashSet<String>,
+) -> Vec<usize> {
+    // Group counter-suffixed names by the name they collided on
+    let mut groups: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (index, analysis) in analyses.iter().enumerate() {
+        if let Some(key) = collided_name(analysis, style.separator()) {
+            groups.entry(key).or_default().push(index);
+        }
+    }
+
+    let mut changed = Vec::new();
+    for ((base, extension), members) in groups {
+        let paths: Vec<&Path> = members
+            .iter()
+            .map(|&index| analyses[index].original_path.as_path())
+            .collect();
+        let tokens = distinguishing_tokens(&paths);
+
+        for (index, token) in members.into_iter().zip(tokens) {
+            let Some(token) = token else { continue };
+            let name = format!("{}{}{}{}", base, style.separator(), style.apply(&token), extension);
+            if existing_names.contains(&name) {
+                continue;
+            }
+
+            let analysis = &mut analyses[index];
+            tracing::debug!(
+                "Disambiguating {}: {} instead of a counter",
+                analysis.original_path.display(),
+                name
+            );
+            if let Some(old) = analysis.proposed_name.replace(name.clone()) {
+                existing_names.remove(&old);
+            }
+            existing_names.insert(name);
+            analysis.collision_suffix = None;
+            changed.push(index);
+        }
+    }
+    changed
+}
+
+/// The (base name, extension) a counter-suffixed proposed name collided on, given the
+/// `separator` its counter was added with
+pub(crate) fn collided_name(analysis: &FileAnalysis, separator: &str) -> Option<(String, String)> {
+    let suffix = analysis.collision_suffix?;
+    let proposed = analysis.proposed_name.as_deref()?;
+    let extension = analysis.new_extension().map(|ext| format!(".{}", ext)).unwrap_or_default();
+    let base = proposed
+        .strip_suffix(extension.as_str())?
+        .strip_suffix(&format!("{}{}", separator, suffix))?;
+    Some((base.to_string(), extension))
+}
+
+/// One token per file, drawn from the first kind of detail that differs across all of them:
+/// vendor, amount, vendor and amount together, then a short content hash
+/// Files with identical content get `None` and keep their counter
+fn distinguishing_tokens(paths: &[&Path]) -> Vec<Option<String>> {
+    let traits: Vec<Traits> = paths.iter().map(|path| Traits::read(path)).collect();
+
+    let kinds: [fn(&Traits) -> Option<String>; 3] = [
+        |t| t.vendor.clone(),
+        |t| t.amount.clone(),
+        |t| Some(format!("{}_{}", t.vendor.as_ref()?, t.amount.as_ref()?)),
+    ];
+    for kind in kinds {
+        let tokens: Vec<Option<String>> = traits.iter().map(kind).collect();
+        if all_distinct(&tokens) {
+            return tokens;
+        }
+    }
+
+    let hashes: Vec<Option<String>> = traits.into_iter().map(|t| t.hash).collect();
+    hashes
+        .iter()
+        .map(|hash| {
+            hash.clone()
+                .filter(|h| hashes.iter().filter(|other| other.as_ref() == Some(h)).count() == 1)
+        })
+        .collect()
+}
+
+/// Every token present and no two equal (ignoring case, as filesystems often do)
+fn all_distinct(tokens: &[Option<String>]) -> bool {
+    let mut seen = HashSet::new();
+    tokens
+        .iter()
+        .all(|token| token.as_ref().is_some_and(|t| seen.insert(t.to_lowercase())))
+}
+
+/// Text of a PDF or plain-text document, if it has any
+fn document_text(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if extension == "pdf" {
+        return pdf_extract::extract_text(path).ok();
+    }
+    if !TEXT_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+
+    use std::io::Read;
+    let mut buffer = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(TEXT_READ_LIMIT as u64)
+        .read_to_end(&mut buffer)
+        .ok()?;
+    Some(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// First line that names someone rather than the kind of document, as a short token
+/// e.g. "ACME Widgets Ltd." becomes "ACME_Widgets_Ltd"
+fn vendor(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let words: Vec<String> = line
+            .split_whitespace()
+            .map(|word| {
+                word.chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '-')
+                    .collect::<String>()
+            })
+            .filter(|word| word.chars().any(char::is_alphabetic))
+            .collect();
+        let meaningful = words
+            .iter()
+            .any(|word| !GENERIC_WORDS.contains(&word.to_lowercase().as_str()));
+        if !meaningful {
+            return None;
+        }
+
+        let token = words.into_iter().take(3).collect::<Vec<_>>().join("_");
+        Some(token.chars().take(30).collect::<String>().trim_matches('_').to_string())
+            .filter(|token| token.chars().filter(|c| c.is_alphabetic()).count() >= 3)
+    })
+}
+
+/// Largest currency amount in the text (usually the total), e.g. `EUR1234.50`
+fn amount(text: &str) -> Option<String> {
+    let re = Regex::new(
+        r"(?i)(\$|€|£|¥|\b(?:USD|EUR|GBP|CHF|JPY|CAD|AUD)\b)\s?(\d{1,3}(?:,\d{3})+(?:\.\d{2})?|\d+(?:\.\d{2})?)",
+    )
+    .unwrap();
+
+    re.captures_iter(text)
+        .filter_map(|caps| {
+            let currency = match &caps[1] {
+                "$" => "USD".to_string(),
+                "€" => "EUR".to_string(),
+                "£" => "GBP".to_string(),
+                "¥" => "JPY".to_string(),
+                code => code.to_uppercase(),
+            };
+            let digits = caps[2].replace(',', "");
+            let value: f64 = digits.parse().ok()?;
+            Some((value, format!("{}{}", currency, digits)))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, token)| token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vendor_and_amount() {
+        let text = "INVOICE\nACME Widgets Ltd.\n12 High Street\nSubtotal $1,000.00\nTotal $1,200.50\n";
+        assert_eq!(vendor(text), Some("ACME_Widgets_Ltd".to_string()));
+        assert_eq!(amount(text), Some("USD1200.50".to_string()));
+
+        assert_eq!(amount("Betrag: EUR 89.90"), Some("EUR89.90".to_string()));
+        assert_eq!(amount("no money here, just 42 items"), None);
+        assert_eq!(vendor("Invoice No. 7\nPage 1 of 2"), None);
+    }
+
+    #[test]
+    fn test_distinguishing_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+
+        // Different senders: the vendor tells them apart
+        let a = write("a.txt", "Invoice\nAcme Corp\nTotal $10.00");
+        let b = write("b.txt", "Invoice\nGlobex Inc\nTotal $10.00");
+        assert_eq!(
+            distinguishing_tokens(&[&a, &b]),
+            vec![Some("Acme_Corp".to_string()), Some("Globex_Inc".to_string())]
+        );
+
+        // Same sender, different totals: the amount does
+        let c = write("c.txt", "Invoice\nAcme Corp\nTotal $25.00");
+        assert_eq!(
+            distinguishing_tokens(&[&a, &c]),
+            vec![Some("USD10.00".to_string()), Some("USD25.00".to_string())]
+        );
+
+        // Nothing readable differs: fall back to a content hash; exact copies get nothing
+        let d = write("d.txt", "Invoice\nAcme Corp\nTotal $10.00\n");
+        let copy = write("copy.txt", "Invoice\nAcme Corp\nTotal $10.00\n");
+        let tokens = distinguishing_tokens(&[&a, &d, &copy]);
+        assert!(tokens[0].as_ref().is_some_and(|t| t.len() <= HASH_TOKEN_LEN));
+        assert_eq!(tokens[1], None);
+        assert_eq!(tokens[2], None);
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::profiling::Stage;
+use crate::{AnalysisIssue, FileAnalysis};
+
+/// What happens to files whose content exactly matches another file
+/// The file with the first path in each set is named as usual either way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateHandling {
+    /// Only mark them (`FileAnalysis::duplicate_of`); names are proposed as usual
+    #[default]
+    Flag,
+    /// Propose no name for them
+    Skip,
+    /// Give them the first file's name with a `_dup1`, `_dup2`, … suffix
+    Suffix,
+}
+
+impl fmt::Display for DuplicateHandling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuplicateHandling::Flag => write!(f, "flag"),
+            DuplicateHandling::Skip => write!(f, "skip"),
+            DuplicateHandling::Suffix => write!(f, "suffix"),
+        }
+    }
+}
+
+impl FromStr for DuplicateHandling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "flag" => Ok(DuplicateHandling::Flag),
+            "skip" => Ok(DuplicateHandling::Skip),
+            "suffix" => Ok(DuplicateHandling::Suffix),
+            _ => anyhow::bail!("unknown duplicate handling '{}' (expected flag, skip or suffix)", s),
+        }
+    }
+}
+
+/// Sets of files with identical content, as indices into `paths`, each in input order
+/// Only files sharing a size are hashed, so unique files cost a `stat` each
+pub(crate) fn find(paths: &[&Path]) -> Vec<Vec<usize>> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        // Empty files all match each other without being copies of anything
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() > 0 => {
+                by_size.entry(metadata.len()).or_default().push(index);
+            }
+            _ => {}
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for candidates in by_size.into_values().filter(|c| c.len() > 1) {
+        let mut by_hash: HashMap<blake3::Hash, Vec<usize>> = HashMap::new();
+        for index in candidates {
+            match hash(paths[index]) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(index),
+                Err(e) => tracing::debug!("Could not hash {}: {}", paths[index].display(), e),
+            }
+        }
+        groups.extend(by_hash.into_values().filter(|g| g.len() > 1));
+    }
+
+    for group in &mut groups {
+        group.sort_unstable();
+    }
+    groups.sort_unstable();
+    groups
+}
+
+pub(crate) fn hash(path: &Path) -> Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+/// Mark duplicates among `analyses` (copies of the file with the first path) and apply
+/// `handling` to them
+/// Deferred files and files too large to analyze are left out. Returns the indices of
+/// analyses that changed
+pub(crate) fn apply(
+    analyses: &mut [FileAnalysis],
+    handling: DuplicateHandling,
+    separator: &str,
+    existing_names: &mut HashSet<String>,
+) -> Vec<usize> {
+    let candidates: Vec<usize> = (0..analyses.len())
+        .filter(|&index| {
+            let analysis = &analyses[index];
+            analysis.deferred.is_none() && !analysis.over_limit.is_some_and(|limit| limit.skipped())
+        })
+        .collect();
+    let paths: Vec<&Path> = candidates
+        .iter()
+        .map(|&index| analyses[index].original_path.as_path())
+        .collect();
+    let groups: Vec<Vec<usize>> = find(&paths)
+        .into_iter()
+        .map(|group| {
+            // The copy with the first path (not whichever the scan met first) keeps its name
+            let mut group: Vec<usize> = group.into_iter().map(|i| candidates[i]).collect();
+            group.sort_by(|&a, &b| analyses[a].original_path.cmp(&analyses[b].original_path));
+            group
+        })
+        .collect();
+
+    let mut changed = Vec::new();
+    for group in groups {
+        let (first, copies) = group.split_first().expect("groups have at least two files");
+        let original: PathBuf = analyses[*first].original_path.clone();
+        let shared_name = analyses[*first].proposed_name.clone();
+        let destination = analyses[*first].destination.clone();
+        tracing::info!("{} duplicates of {}", copies.len(), original.display());
+
+        for (number, &index) in (1..).zip(copies) {
+            let analysis = &mut analyses[index];
+            analysis.duplicate_of = Some(original.clone());
+            match handling {
+                DuplicateHandling::Flag => {}
+                DuplicateHandling::Skip => {
+                    analysis.proposed_name = None;
+                    analysis.collision_suffix = None;
+                    analysis.errors.push(AnalysisIssue::fatal(
+                        Stage::Score,
+                        format!("Duplicate of {}", original.display()),
+                    ));
+                }
+                DuplicateHandling::Suffix => {
+                    if let Some(name) = &shared_name {
+                        analysis.proposed_name = Some(dup_name(name, number, separator, existing_names));
+                        analysis.collision_suffix = None;
+                        analysis.destination = destination.clone();
+                    }
+                }
+            }
+            changed.push(index);
+        }
+    }
+    changed
+}
+
+/// `name` with a free `_dupN` suffix (joined by `separator`) before the extension, starting
+/// at `number`
+fn dup_name(name: &str, number: u32, separator: &str, existing_names: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rfind('.').filter(|&dot| dot > 0 && !name[dot..].contains('/')) {
+        Some(dot) => name.split_at(dot),
+        None => (name, ""),
+    };
+    let candidate = (number..)
+        .map(|n| format!("{}{}dup{}{}", stem, separator, n, extension))
+        .find(|candidate| !existing_names.contains(candidate))
+        .expect("some suffix is free");
+    existing_names.insert(candidate.clone());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_groups_identical_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = [
+            ("a.jpg", "photo"),
+            ("b.txt", "other"), // same size, different content
+            ("c.jpg", "photo"),
+            ("d.jpg", "photo"),
+            ("e.txt", ""),
+            ("f.txt", ""),
+        ];
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .map(|(name, content)| {
+                let path = temp_dir.path().join(name);
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+
+        assert_eq!(find(&paths), vec![vec![0, 2, 3]]);
+    }
+
+    #[test]
+    fn test_apply_handling() {
+        let temp_dir = TempDir::new().unwrap();
+        let analyses: Vec<FileAnalysis> = ["IMG_1.jpg", "IMG_2.jpg", "IMG_3.jpg"]
+            .iter()
+            .map(|name| {
+                let path = temp_dir.path().join(name);
+                fs::write(&path, "same photo").unwrap();
+                FileAnalysis {
+                    proposed_name: Some(format!("Sunset_{}", name)),
+                    ..crate::pipeline::unanalyzed(&path, FileCategory::Image)
+                }
+            })
+            .collect();
+
+        let mut flagged = analyses.clone();
+        assert_eq!(apply(&mut flagged, DuplicateHandling::Flag, "_", &mut HashSet::new()), [1, 2]);
+        assert_eq!(flagged[0].duplicate_of, None);
+        assert_eq!(flagged[2].duplicate_of.as_ref(), Some(&analyses[0].original_path));
+        assert_eq!(flagged[2].proposed_name, analyses[2].proposed_name);
+
+        let mut skipped = analyses.clone();
+        apply(&mut skipped, DuplicateHandling::Skip, "_", &mut HashSet::new());
+        assert!(skipped[1].proposed_name.is_none());
+        assert!(skipped[0].proposed_name.is_some());
+
+        let mut suffixed = analyses.clone();
+        let mut taken = HashSet::from(["Sunset_IMG_1_dup1.jpg".to_string()]);
+        apply(&mut suffixed, DuplicateHandling::Suffix, "_", &mut taken);
+        assert_eq!(suffixed[1].proposed_name.as_deref(), Some("Sunset_IMG_1_dup2.jpg"));
+        assert_eq!(suffixed[2].proposed_name.as_deref(), Some("Sunset_IMG_1_dup3.jpg"));
+    }
+}
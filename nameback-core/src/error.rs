@@ -0,0 +1,160 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config_builder::ConfigError;
+use crate::deps::InstallError;
+use crate::deps_check::Dependency;
+use crate::renamer::RenameError;
+
+/// Why a `RenameEngine` call failed, for callers that handle some causes themselves
+/// instead of matching on messages
+/// Problems with single files during analysis (unreadable metadata, failed OCR) don't fail
+/// the run; they are reported in `FileAnalysis::errors`
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NamebackError {
+    /// An external tool the call needs isn't installed
+    DependencyMissing { tool: Dependency },
+    /// A file's embedded metadata couldn't be read
+    MetadataExtraction { path: PathBuf, source: anyhow::Error },
+    /// The path to explain isn't a file
+    NotAFile(PathBuf),
+    /// The analysis to rename has no proposed name
+    NoProposedName(PathBuf),
+    /// There is nowhere to keep the undo journal (set `journal_path`)
+    NoJournal,
+    /// There is nowhere to keep the metadata cache (set `cache_path`)
+    NoCacheDirectory,
+    /// A rename was refused or failed (see `RenameError`)
+    Rename(RenameError),
+    /// The configuration is invalid
+    Config(ConfigError),
+    /// A dependency couldn't be installed
+    Install(InstallError),
+    /// Reading or writing the metadata cache failed
+    Cache(anyhow::Error),
+    /// Reading or writing a file or folder failed
+    Io { kind: io::ErrorKind, source: anyhow::Error },
+    /// Anything else
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for NamebackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamebackError::DependencyMissing { tool } => write!(f, "{} is not installed", tool.name()),
+            NamebackError::MetadataExtraction { path, .. } => {
+                write!(f, "Failed to read metadata of {}", path.display())
+            }
+            NamebackError::NotAFile(path) => write!(f, "Not a file: {}", path.display()),
+            NamebackError::NoProposedName(path) => write!(f, "No proposed name for {}", path.display()),
+            NamebackError::NoJournal => write!(f, "No undo journal location (set journal_path)"),
+            NamebackError::NoCacheDirectory => write!(f, "No cache directory"),
+            NamebackError::Rename(e) => write!(f, "{}", e),
+            NamebackError::Config(e) => write!(f, "{}", e),
+            NamebackError::Install(e) => write!(f, "{}", e),
+            NamebackError::Cache(e) => write!(f, "Metadata cache: {}", e),
+            // Only the outermost message, like anyhow's; the rest is the source chain
+            NamebackError::Io { source, .. } | NamebackError::Other(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl std::error::Error for NamebackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NamebackError::MetadataExtraction { source, .. } | NamebackError::Cache(source) => {
+                Some(&**source)
+            }
+            NamebackError::Io { source, .. } | NamebackError::Other(source) => source.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for NamebackError {
+    /// Picks the variant from the causes in the error's chain
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(rename) = RenameError::find(&error) {
+            return NamebackError::Rename(rename.clone());
+        }
+        if let Some(config) = error.chain().find_map(|cause| cause.downcast_ref::<ConfigError>()) {
+            return NamebackError::Config(config.clone());
+        }
+        if let Some(io) = error.chain().find_map(|cause| cause.downcast_ref::<io::Error>()) {
+            return NamebackError::Io { kind: io.kind(), source: error };
+        }
+        NamebackError::Other(error)
+    }
+}
+
+impl From<io::Error> for NamebackError {
+    fn from(error: io::Error) -> Self {
+        NamebackError::Io {
+            kind: error.kind(),
+            source: error.into(),
+        }
+    }
+}
+
+impl From<RenameError> for NamebackError {
+    fn from(error: RenameError) -> Self {
+        NamebackError::Rename(error)
+    }
+}
+
+impl From<ConfigError> for NamebackError {
+    fn from(error: ConfigError) -> Self {
+        NamebackError::Config(error)
+    }
+}
+
+impl From<InstallError> for NamebackError {
+    fn from(error: InstallError) -> Self {
+        NamebackError::Install(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use std::path::Path;
+
+    #[test]
+    fn test_classified_from_anyhow() {
+        let taken = anyhow::Error::new(RenameError::TargetExists { path: PathBuf::from("/a/b.jpg") })
+            .context("Failed to rename");
+        assert!(matches!(
+            NamebackError::from(taken),
+            NamebackError::Rename(RenameError::TargetExists { .. })
+        ));
+
+        let missing = std::fs::read(Path::new("/no/such/file")).context("Failed to read file").unwrap_err();
+        let error = NamebackError::from(missing);
+        assert!(matches!(error, NamebackError::Io { kind: io::ErrorKind::NotFound, .. }));
+        // Displayed like anyhow, with the io error as the source
+        assert_eq!(error.to_string(), "Failed to read file");
+        assert!(std::error::Error::source(&error).is_some());
+
+        let other = NamebackError::from(anyhow::anyhow!("something else"));
+        assert!(matches!(other, NamebackError::Other(_)));
+        assert_eq!(format!("{:#}", anyhow::Error::from(other)), "something else");
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let error = NamebackError::DependencyMissing { tool: Dependency::ExifTool };
+        assert_eq!(error.to_string(), "exiftool is not installed");
+
+        let error = NamebackError::MetadataExtraction {
+            path: PathBuf::from("photo.jpg"),
+            source: anyhow::anyhow!("bad EXIF"),
+        };
+        assert_eq!(
+            format!("{:#}", anyhow::Error::from(error)),
+            "Failed to read metadata of photo.jpg: bad EXIF"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::scorer::{RejectReason, ScoreBreakdown, ACCEPTABLE_SCORE};
+use crate::{FileAnalysis, NameSource};
+
+/// Every name considered for one file, how each was scored and which one won
+/// (see `RenameEngine::explain`)
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The file's analysis, as a run over the file alone would make it
+    pub analysis: FileAnalysis,
+    /// The winning candidate first (if any), then the others from best to worst score
+    pub candidates: Vec<ExplainedCandidate>,
+}
+
+/// A candidate name with its score worked out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainedCandidate {
+    pub name: String,
+    pub source: NameSource,
+    pub score: f32,
+    /// None when a `NameProvider` set the score itself
+    pub breakdown: Option<ScoreBreakdown>,
+    /// Why the candidate wasn't used (None for the winner)
+    pub rejected: Option<RejectReason>,
+}
+
+impl ExplainedCandidate {
+    fn new(name: String, source: NameSource, score: f32, rejected: Option<RejectReason>) -> Self {
+        Self {
+            breakdown: ScoreBreakdown::of_candidate(&name, source, score),
+            name,
+            source,
+            score,
+            rejected,
+        }
+    }
+}
+
+impl Explanation {
+    pub(crate) fn new(analysis: FileAnalysis) -> Self {
+        let winner = analysis
+            .title
+            .clone()
+            .filter(|_| analysis.proposed_name.is_some())
+            .map(|title| ExplainedCandidate::new(title, analysis.name_source, analysis.confidence, None));
+        let mut candidates: Vec<ExplainedCandidate> = winner
+            .into_iter()
+            .chain(analysis.rejected.iter().map(|rejected| {
+                ExplainedCandidate::new(
+                    rejected.name.clone(),
+                    rejected.source,
+                    rejected.score,
+                    Some(rejected.reason.clone()),
+                )
+            }))
+            .collect();
+        // Stable, so the winner stays ahead of candidates scoring the same
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Self { analysis, candidates }
+    }
+
+    /// The winning candidate, if a name was proposed
+    pub fn winner(&self) -> Option<&ExplainedCandidate> {
+        self.candidates.iter().find(|candidate| candidate.rejected.is_none())
+    }
+
+    /// Why the winner was picked, or why no name was proposed
+    pub fn verdict(&self) -> String {
+        let analysis = &self.analysis;
+        if let Some(reason) = &analysis.deferred {
+            return format!("left alone for now: {}", reason);
+        }
+        if let Some(winner) = self.winner() {
+            let why = match self.candidates.len() {
+                1 => format!("the only candidate, and scored at least {:.1}", ACCEPTABLE_SCORE),
+                n => format!("highest score of {} candidates", n),
+            };
+            return format!("{} ({:.2} from {})", why, winner.score, winner.source.label());
+        }
+        if let Some(best) = self.candidates.first() {
+            return match &best.rejected {
+                Some(reason @ RejectReason::BelowMinConfidence { .. }) => {
+                    format!("the best candidate, \"{}\", scored {:.2}, {}", best.name, best.score, reason)
+                }
+                _ => format!("every candidate scored below {:.1}", ACCEPTABLE_SCORE),
+            };
+        }
+        match analysis.errors.iter().find(|issue| !issue.recoverable) {
+            Some(issue) => format!("no candidate names ({})", issue.message),
+            None => "no candidate names".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scorer::{self, NameCandidate};
+    use crate::{pipeline, FileCategory};
+    use std::path::Path;
+
+    /// The analysis the scorer would make of `candidates`, winner first, like the pipeline's
+    fn analysis(candidates: Vec<NameCandidate>) -> FileAnalysis {
+        let (best, rejected) = scorer::rank_candidates(candidates);
+        FileAnalysis {
+            proposed_name: best.as_ref().map(|best| format!("{}.pdf", best.name)),
+            title: best.as_ref().map(|best| best.name.clone()),
+            confidence: best.as_ref().map_or(0.0, |best| best.score),
+            name_source: best.as_ref().map_or(NameSource::Fallback, |best| best.source),
+            rejected,
+            ..pipeline::unanalyzed(Path::new("/docs/scan.pdf"), FileCategory::Document)
+        }
+    }
+
+    #[test]
+    fn test_candidates_with_breakdowns() {
+        let explanation = Explanation::new(analysis(vec![
+            NameCandidate::new("2023-10-15".to_string(), NameSource::Metadata),
+            NameCandidate::new("Quarterly Sales Report".to_string(), NameSource::Metadata),
+            NameCandidate::new("Sales".to_string(), NameSource::FilenameAnalysis),
+        ]));
+
+        let names: Vec<&str> = explanation.candidates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Quarterly Sales Report", "Sales", "2023-10-15"]);
+        assert_eq!(explanation.winner().map(|c| c.name.as_str()), Some("Quarterly Sales Report"));
+        assert!(explanation.candidates.iter().all(|c| c.breakdown.as_ref().map(|b| b.total()) == Some(c.score)));
+        assert_eq!(
+            explanation.candidates[2].rejected,
+            Some(RejectReason::LowQuality)
+        );
+        assert!(explanation.verdict().starts_with("highest score of 3 candidates"));
+    }
+
+    #[test]
+    fn test_verdict_without_a_name() {
+        let low = Explanation::new(analysis(vec![NameCandidate::new("20231015".to_string(), NameSource::TextExtract)]));
+        assert_eq!(low.winner(), None);
+        assert_eq!(low.verdict(), "every candidate scored below 2.0");
+
+        let mut weak = analysis(vec![NameCandidate::new("Sales".to_string(), NameSource::FilenameAnalysis)]);
+        let best = NameCandidate::new("Sales".to_string(), NameSource::FilenameAnalysis);
+        weak.rejected = vec![best.rejected(RejectReason::BelowMinConfidence { min_confidence: 6.0 })];
+        weak.proposed_name = None;
+        let weak = Explanation::new(weak);
+        assert!(weak.verdict().ends_with("below the minimum confidence of 6.0"), "{}", weak.verdict());
+
+        let none = Explanation::new(FileAnalysis {
+            errors: vec![crate::AnalysisIssue::fatal(crate::Stage::Detect, "Unsupported file type")],
+            ..analysis(Vec::new())
+        });
+        assert_eq!(none.verdict(), "no candidate names (Unsupported file type)");
+    }
+}
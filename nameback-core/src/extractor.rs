@@ -1,14 +1,28 @@
 use anyhow::{Context, Result};
-use log::debug;
-use serde::Deserialize;
-use std::path::Path;
-use std::process::Command;
+use tracing::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
 
 use crate::detector::FileCategory;
+use crate::geocoding::GeocodeBackend;
 use crate::image_ocr;
 use crate::pdf_content;
+use crate::process_limit::{limited, ProcessLimit};
+use crate::profiling::{self, Stage, StageTimer};
+use crate::scorer::{NameCandidate, NameSource};
+use crate::stream_tags;
 use crate::text_content;
+use crate::tool_command::{self, ToolCommand};
+use crate::video_metadata;
 use crate::video_ocr;
+use crate::AnalysisIssue;
 
 /// Represents metadata extracted from a file
 #[derive(Debug, Clone)]
@@ -16,23 +30,85 @@ pub struct FileMetadata {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    /// TV show name, from video stream tags
+    pub show: Option<String>,
     pub date_time_original: Option<String>,
     pub description: Option<String>,
     pub subject: Option<String>,
     pub author: Option<String>,
     pub creation_date: Option<String>,
     pub gps_location: Option<crate::location_timestamp::LocationData>,
-    pub geocode_enabled: Option<bool>,
+    /// Turns `gps_location` into a place name (None = use coordinates)
+    pub geocoder: Option<Arc<dyn GeocodeBackend>>,
     pub include_location: bool,
+    /// How precisely `gps_location` is named
+    pub location_precision: crate::LocationPrecision,
+    /// Areas whose locations are never named
+    pub location_blocklist: Vec<crate::BlockedArea>,
     pub include_timestamp: bool,
+    /// Add the attachment count to email names
+    pub include_attachment_count: bool,
+    /// Name archives after what's inside them
+    pub peek_archives: bool,
+    /// Rough subject guessed from a photo's pixels, e.g. `Receipt`
+    pub subject_hint: Option<String>,
+    /// Normalized camera make and model, e.g. `Nikon_Z6`
+    pub camera: Option<String>,
+    /// Normalized lens model
+    pub lens: Option<String>,
+    pub template: Option<String>,
+    /// Case and word separator applied to generated names
+    pub name_style: crate::NameStyle,
+    /// How and where the timestamp goes into generated names
+    pub timestamp_style: crate::TimestampStyle,
+    /// Detected file type, filled in before name generation for the `{category}` variable
+    pub category: Option<FileCategory>,
+    /// Folder the file ends up in, filled in before name generation so names are shortened
+    /// to keep the whole path within Windows' `MAX_PATH`
+    pub folder: Option<PathBuf>,
+    /// Pages of a PDF, for `RenameConfig::max_pdf_pages`
+    pub page_count: Option<u32>,
+    /// Length of a video or audio file in seconds, for `RenameConfig::max_video_duration_min`
+    pub duration: Option<f64>,
+    /// Capture time, resolution and camera of a photo, for grouping bursts
+    pub shot: Option<crate::series_detector::Shot>,
 }
 
 impl FileMetadata {
+    /// The fields that are set, labeled for display, e.g. ("Title", "Quarterly Report")
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let text = [
+            ("Title", &self.title),
+            ("Description", &self.description),
+            ("Subject", &self.subject),
+            ("Author", &self.author),
+            ("Artist", &self.artist),
+            ("Album", &self.album),
+            ("Show", &self.show),
+            ("Taken", &self.date_time_original),
+            ("Created", &self.creation_date),
+            ("Camera", &self.camera),
+            ("Lens", &self.lens),
+        ];
+        let mut fields: Vec<(&'static str, String)> = text
+            .into_iter()
+            .filter_map(|(label, value)| Some((label, value.clone()?)))
+            .collect();
+        if let Some(location) = &self.gps_location {
+            fields.push(("GPS", format!("{:.5}, {:.5}", location.latitude, location.longitude)));
+        }
+        fields
+    }
+
     /// Extracts the best candidate name from metadata based on file category
-    /// Now uses intelligent scoring to select from multiple sources
-    pub fn extract_name(&self, category: &FileCategory, path: &Path) -> Option<String> {
-        use crate::scorer::{NameCandidate, NameSource};
+    /// Now uses intelligent scoring to select from multiple sources; the candidate keeps its score
+    pub fn extract_name(&self, category: &FileCategory, path: &Path) -> Option<NameCandidate> {
+        crate::scorer::select_best_candidate(self.name_candidates(category, path))
+    }
 
+    /// Every scored candidate name for the file: metadata fields for its category, content,
+    /// filename stem and directory
+    pub(crate) fn name_candidates(&self, category: &FileCategory, path: &Path) -> Vec<NameCandidate> {
         let mut candidates = Vec::new();
 
         // Collect candidates from metadata fields based on category
@@ -47,10 +123,29 @@ impl FileMetadata {
                 if let Some(date) = &self.date_time_original {
                     candidates.push(NameCandidate::new(date.clone(), NameSource::Metadata));
                 }
+                if let Some(subject) = &self.subject_hint {
+                    candidates.push(NameCandidate::new(subject.clone(), NameSource::ImageAnalysis));
+                }
             }
             FileCategory::Document => {
+                // Office files handled by format handler: core properties, then headings,
+                // slide titles or sheet names from the content
+                let office = crate::format_handlers::office::extract_office_content(path);
+                if let Some(office) = &office {
+                    for text in office.title.iter().chain(&office.subject) {
+                        candidates.push(NameCandidate::new(text.clone(), NameSource::Metadata));
+                    }
+                    for heading in &office.headings {
+                        candidates.push(NameCandidate::new(heading.clone(), NameSource::TextExtract));
+                    }
+                }
+
                 if let Some(title) = &self.title {
-                    candidates.push(NameCandidate::new(title.clone(), NameSource::Metadata));
+                    // "PowerPoint Presentation" and the like would outscore the real content
+                    let placeholder = office.is_some() && crate::format_handlers::office::is_placeholder_title(title);
+                    if !placeholder {
+                        candidates.push(NameCandidate::new(title.clone(), NameSource::Metadata));
+                    }
                 }
                 if let Some(subject) = &self.subject {
                     candidates.push(NameCandidate::new(subject.clone(), NameSource::Metadata));
@@ -74,6 +169,12 @@ impl FileMetadata {
                 if let Some(title) = &self.title {
                     candidates.push(NameCandidate::new(title.clone(), NameSource::Metadata));
                 }
+                if let Some(show) = &self.show {
+                    candidates.push(NameCandidate::new(show.clone(), NameSource::Metadata));
+                }
+                if let Some(artist) = &self.artist {
+                    candidates.push(NameCandidate::new(artist.clone(), NameSource::Metadata));
+                }
                 if let Some(date) = &self.creation_date {
                     candidates.push(NameCandidate::new(date.clone(), NameSource::Metadata));
                 }
@@ -81,7 +182,7 @@ impl FileMetadata {
             FileCategory::Email => {
                 // Email files handled by format handler
                 if let Ok(email_meta) = crate::format_handlers::email::extract_email_metadata(path) {
-                    if let Some(name) = crate::format_handlers::email::format_email_filename(&email_meta) {
+                    if let Some(name) = crate::format_handlers::email::format_email_filename(&email_meta, self.include_attachment_count) {
                         candidates.push(NameCandidate::new(name, NameSource::Metadata));
                     }
                 }
@@ -93,9 +194,32 @@ impl FileMetadata {
                 }
             }
             FileCategory::Archive => {
-                // Archive files handled by format handler
-                if let Ok(Some(name)) = crate::format_handlers::archive::extract_archive_info(path) {
-                    candidates.push(NameCandidate::new(name, NameSource::Metadata));
+                // Archive files handled by format handler: `ProjectX_source_2021` from the contents
+                if self.peek_archives {
+                    if let Ok(Some(archive)) = crate::format_handlers::archive::extract_archive_info(path) {
+                        // Just the kind and year (`documents_2023`) loses to a meaningful filename
+                        let source = if archive.generic { NameSource::Fallback } else { NameSource::Metadata };
+                        candidates.push(NameCandidate::new(archive.name, source));
+                    }
+                }
+            }
+            FileCategory::Installer => {
+                // Installers handled by format handler: `Mozilla_Firefox_120.0.1_win64` from version info
+                if let Ok(Some(installer)) = crate::format_handlers::installer::extract_installer_metadata(path) {
+                    if let Some(name) = crate::format_handlers::installer::format_installer_filename(&installer) {
+                        candidates.push(NameCandidate::new(name, NameSource::Metadata));
+                    }
+                }
+            }
+            FileCategory::Ebook => {
+                // Ebooks handled by format handler: `Author - Title 2019` from the OPF or MOBI header
+                if let Ok(Some(book)) = crate::format_handlers::ebook::extract_ebook_metadata(path) {
+                    if let Some(name) = crate::format_handlers::ebook::format_ebook_filename(&book) {
+                        candidates.push(NameCandidate::new(name, NameSource::Metadata));
+                    }
+                }
+                if let Some(title) = &self.title {
+                    candidates.push(NameCandidate::new(title.clone(), NameSource::Metadata));
                 }
             }
             FileCategory::SourceCode => {
@@ -117,65 +241,305 @@ impl FileMetadata {
             candidates.push(NameCandidate::new(context, NameSource::DirectoryContext));
         }
 
-        // Use scorer to select best candidate
-        crate::scorer::select_best_candidate(candidates).map(|c| c.name)
+        candidates
+    }
+}
+
+
+/// Where embedded metadata is read from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataBackend {
+    /// exiftool when it is installed, otherwise the built-in reader
+    #[default]
+    Auto,
+    /// Always exiftool (fails if it isn't installed)
+    Exiftool,
+    /// Always the built-in reader: EXIF in JPEG/HEIC/PNG/TIFF/WebP, MP4/MOV atoms and ID3 tags
+    Native,
+}
+
+impl fmt::Display for MetadataBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataBackend::Auto => write!(f, "auto"),
+            MetadataBackend::Exiftool => write!(f, "exiftool"),
+            MetadataBackend::Native => write!(f, "native"),
+        }
+    }
+}
+
+impl FromStr for MetadataBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(MetadataBackend::Auto),
+            "exiftool" => Ok(MetadataBackend::Exiftool),
+            "native" => Ok(MetadataBackend::Native),
+            _ => anyhow::bail!("unknown metadata backend '{}' (expected auto, exiftool or native)", s),
+        }
     }
 }
 
-/// Extracts metadata from a file using exiftool
-pub fn extract_metadata(path: &Path, config: &crate::RenameConfig) -> Result<FileMetadata> {
-    let output = crate::deps_check::create_command("exiftool")
+/// Whether exiftool can be found, checked once per process
+pub(crate) fn exiftool_installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| {
+        let installed = crate::deps_check::Dependency::ExifTool.find_executable().is_some();
+        if !installed {
+            tracing::info!("exiftool not found; reading metadata with the built-in reader");
+        }
+        installed
+    })
+}
+
+/// Embedded metadata fields, named as exiftool reports them
+/// The built-in reader fills in the same fields, formatted the way exiftool would
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct RawMetadata {
+    #[serde(rename = "Title")]
+    pub title: Option<String>,
+    #[serde(rename = "Artist")]
+    pub artist: Option<String>,
+    #[serde(rename = "Album")]
+    pub album: Option<String>,
+    #[serde(rename = "DateTimeOriginal")]
+    pub date_time_original: Option<String>,
+    /// Fraction of a second `date_time_original` was taken at, e.g. `05`
+    #[serde(rename = "SubSecTimeOriginal", default, deserialize_with = "string_or_number")]
+    pub sub_sec_time_original: Option<String>,
+    #[serde(rename = "ImageWidth", default, deserialize_with = "string_or_number")]
+    pub image_width: Option<String>,
+    #[serde(rename = "ImageHeight", default, deserialize_with = "string_or_number")]
+    pub image_height: Option<String>,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Subject")]
+    pub subject: Option<String>,
+    #[serde(rename = "Author")]
+    pub author: Option<String>,
+    #[serde(rename = "LastModifiedBy")]
+    pub last_modified_by: Option<String>,
+    #[serde(rename = "CreationDate")]
+    pub creation_date: Option<String>,
+    #[serde(rename = "CreateDate")]
+    pub create_date: Option<String>,
+    #[serde(rename = "GPSLatitude")]
+    pub gps_latitude: Option<String>,
+    #[serde(rename = "GPSLatitudeRef")]
+    pub gps_latitude_ref: Option<String>,
+    #[serde(rename = "GPSLongitude")]
+    pub gps_longitude: Option<String>,
+    #[serde(rename = "GPSLongitudeRef")]
+    pub gps_longitude_ref: Option<String>,
+    #[serde(rename = "Make")]
+    pub make: Option<String>,
+    // Some models are plain numbers, which exiftool emits unquoted
+    #[serde(rename = "Model", default, deserialize_with = "string_or_number")]
+    pub model: Option<String>,
+    #[serde(rename = "LensModel")]
+    pub lens_model: Option<String>,
+    #[serde(rename = "Lens")]
+    pub lens: Option<String>,
+    #[serde(rename = "PageCount", default, deserialize_with = "string_or_number")]
+    pub page_count: Option<String>,
+    /// `0:05:23` or, under 30 seconds, `12.34 s`
+    #[serde(rename = "Duration", default, deserialize_with = "string_or_number")]
+    pub duration: Option<String>,
+}
+
+/// Reads embedded metadata (no content extraction or OCR) with the configured backend
+/// exiftool runs through `exiftool` when given, otherwise as a one-off process
+pub fn read_metadata(
+    path: &Path,
+    config: &crate::RenameConfig,
+    exiftool: Option<&ExiftoolPool>,
+) -> Result<FileMetadata> {
+    let _exif_timer = StageTimer::start(Stage::Exif);
+
+    let use_native = match config.metadata_backend {
+        MetadataBackend::Auto => !exiftool_installed(),
+        MetadataBackend::Exiftool => false,
+        MetadataBackend::Native => true,
+    };
+    let exif_data = if use_native {
+        crate::native_metadata::read(path)?
+    } else if let Some(pool) = exiftool {
+        pool.read(path)?
+    } else {
+        read_with_exiftool(&mut crate::deps_check::create_command("exiftool"), path)?
+    };
+
+    Ok(build_metadata(exif_data, config))
+}
+
+/// Runs exiftool once for one file
+fn read_with_exiftool(command: &mut Command, path: &Path) -> Result<RawMetadata> {
+    let output = command
         .arg("-json")
         .arg(path)
-        .output()
+        .tool_output()
         .context("Failed to execute `exiftool` command. Is exiftool installed?")?;
 
     if !output.status.success() {
         anyhow::bail!("exiftool command failed with status: {}", output.status);
     }
 
-    let json_str = String::from_utf8_lossy(&output.stdout);
-
-    // exiftool returns an array with one object
-    #[derive(Deserialize)]
-    struct ExiftoolOutput {
-        #[serde(rename = "Title")]
-        title: Option<String>,
-        #[serde(rename = "Artist")]
-        artist: Option<String>,
-        #[serde(rename = "Album")]
-        album: Option<String>,
-        #[serde(rename = "DateTimeOriginal")]
-        date_time_original: Option<String>,
-        #[serde(rename = "Description")]
-        description: Option<String>,
-        #[serde(rename = "Subject")]
-        subject: Option<String>,
-        #[serde(rename = "Author")]
-        author: Option<String>,
-        #[serde(rename = "LastModifiedBy")]
-        last_modified_by: Option<String>,
-        #[serde(rename = "CreationDate")]
-        creation_date: Option<String>,
-        #[serde(rename = "CreateDate")]
-        create_date: Option<String>,
-        #[serde(rename = "GPSLatitude")]
-        gps_latitude: Option<String>,
-        #[serde(rename = "GPSLatitudeRef")]
-        gps_latitude_ref: Option<String>,
-        #[serde(rename = "GPSLongitude")]
-        gps_longitude: Option<String>,
-        #[serde(rename = "GPSLongitudeRef")]
-        gps_longitude_ref: Option<String>,
-    }
-
-    let parsed: Vec<ExiftoolOutput> =
-        serde_json::from_str(&json_str).context("Failed to parse exiftool JSON output")?;
-
-    let exif_data = parsed
+    parse_exiftool_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The single object in exiftool's `-json` output
+fn parse_exiftool_json(json: &str) -> Result<RawMetadata> {
+    let parsed: Vec<RawMetadata> =
+        serde_json::from_str(json).context("Failed to parse exiftool JSON output")?;
+
+    parsed
         .into_iter()
         .next()
-        .context("No metadata found in exiftool output")?;
+        .context("No metadata found in exiftool output")
+}
+
+/// Persistent `exiftool -stay_open True -@ -` processes shared by the extract workers
+///
+/// Starting exiftool (a Perl program) costs far more than reading one file, so each
+/// worker borrows an idle process instead of spawning one per file. Processes start
+/// on first use and shut down when the pool is dropped. If the protocol ever fails
+/// (e.g. an exiftool too old for `-stay_open`), the pool falls back to one process per file.
+/// A process taking longer than `timeout` over one file is killed and the file fails.
+pub(crate) struct ExiftoolPool {
+    program: PathBuf,
+    idle: Mutex<Vec<StayOpen>>,
+    broken: AtomicBool,
+    timeout: Duration,
+}
+
+impl ExiftoolPool {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        let program = crate::deps_check::Dependency::ExifTool
+            .find_executable()
+            .unwrap_or_else(|| PathBuf::from("exiftool"));
+        Self::with_program(program, timeout)
+    }
+
+    fn with_program(program: PathBuf, timeout: Duration) -> Self {
+        Self {
+            program,
+            idle: Mutex::new(Vec::new()),
+            broken: AtomicBool::new(false),
+            timeout,
+        }
+    }
+
+    /// Metadata for one file, from an idle process (started if none is free)
+    pub(crate) fn read(&self, path: &Path) -> Result<RawMetadata> {
+        if !self.broken.load(Ordering::Relaxed) {
+            // One argument per line, trimmed by exiftool, so unusual paths go the slow way
+            let line = path
+                .to_str()
+                .filter(|p| !p.contains(['\n', '\r']) && p.trim() == *p);
+            if let Some(line) = line {
+                let process = self.idle.lock().unwrap().pop();
+                let process = match process {
+                    Some(process) => Ok(process),
+                    None => StayOpen::spawn(&self.program),
+                };
+                match process.and_then(|mut process| Ok((process.request(line, self.timeout)?, process))) {
+                    Ok((output, process)) => {
+                        self.idle.lock().unwrap().push(process);
+                        if output.trim().is_empty() {
+                            anyhow::bail!("exiftool could not read the file");
+                        }
+                        return parse_exiftool_json(&output);
+                    }
+                    // Running it again one-shot would most likely hang the same way
+                    Err(e) if tool_command::is_timeout(&e) => return Err(e),
+                    Err(e) => {
+                        tracing::warn!("Persistent exiftool failed ({:#}); starting one per file", e);
+                        self.broken.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        read_with_exiftool(&mut Command::new(&self.program), path)
+    }
+}
+
+/// One exiftool process reading commands from stdin
+struct StayOpen {
+    child: Child,
+    stdin: ChildStdin,
+    /// What exiftool printed before each `{ready}`, read on a thread of its own so waiting
+    /// for it can time out
+    responses: mpsc::Receiver<io::Result<String>>,
+}
+
+impl StayOpen {
+    fn spawn(program: &Path) -> Result<Self> {
+        let mut child = Command::new(program)
+            .args(["-stay_open", "True", "-@", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start exiftool")?;
+        let stdin = child.stdin.take().context("exiftool stdin unavailable")?;
+        let stdout = BufReader::new(child.stdout.take().context("exiftool stdout unavailable")?);
+        let (sender, responses) = mpsc::channel();
+        std::thread::spawn(move || Self::read_responses(stdout, sender));
+        Ok(Self { child, stdin, responses })
+    }
+
+    /// Sends what exiftool prints before each `{ready}` until it exits
+    fn read_responses(mut stdout: BufReader<ChildStdout>, sender: mpsc::Sender<io::Result<String>>) {
+        let mut output = String::new();
+        loop {
+            let mut line = String::new();
+            match stdout.read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) if line.trim_end() == "{ready}" => {
+                    if sender.send(Ok(std::mem::take(&mut output))).is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => output.push_str(&line),
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Runs `exiftool -json <path>` and returns what it printed before `{ready}`, killing
+    /// exiftool if that takes longer than `timeout`
+    fn request(&mut self, path: &str, timeout: Duration) -> Result<String> {
+        write!(self.stdin, "-json\n-charset\nfilename=utf8\n{}\n-execute\n", path)?;
+        self.stdin.flush()?;
+
+        match self.responses.recv_timeout(timeout) {
+            Ok(output) => Ok(output?),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // Dropping the process then reaps it
+                let _ = self.child.kill();
+                Err(tool_command::timeout_error("exiftool", timeout).into())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => anyhow::bail!("exiftool exited unexpectedly"),
+        }
+    }
+}
+
+impl Drop for StayOpen {
+    fn drop(&mut self) {
+        let _ = self.stdin.write_all(b"-stay_open\nFalse\n");
+        let _ = self.stdin.flush();
+        let _ = self.child.wait();
+    }
+}
+
+/// Turns raw metadata fields into `FileMetadata`, dropping unhelpful values
+pub(crate) fn build_metadata(exif_data: RawMetadata, config: &crate::RenameConfig) -> FileMetadata {
+    let shot = crate::series_detector::shot_of(&exif_data);
 
     // Don't use Creator field - it contains software name, not author name
     // (e.g., "Adobe InDesign CS6 (Windows)" instead of actual author)
@@ -198,110 +562,326 @@ pub fn extract_metadata(path: &Path, config: &crate::RenameConfig) -> Result<Fil
         exif_data.gps_longitude_ref.as_deref(),
     );
 
-    let mut metadata = FileMetadata {
+    FileMetadata {
         title: exif_data.title,
         artist: exif_data.artist,
         album: exif_data.album,
+        show: None,
         date_time_original: exif_data.date_time_original,
         description: exif_data.description,
         subject: exif_data.subject,
         author: filtered_author,
         creation_date: exif_data.creation_date.or(exif_data.create_date),
         gps_location,
-        geocode_enabled: Some(config.geocode),
+        geocoder: crate::geocoding::backend(config),
         include_location: config.include_location,
+        location_precision: config.location_precision,
+        location_blocklist: config.location_blocklist.clone(),
         include_timestamp: config.include_timestamp,
-    };
+        include_attachment_count: config.email_attachment_count,
+        peek_archives: config.peek_archives,
+        subject_hint: None,
+        camera: crate::camera::normalize_camera(
+            exif_data.make.as_deref(),
+            exif_data.model.as_deref(),
+        ),
+        lens: exif_data
+            .lens_model
+            .or(exif_data.lens)
+            .as_deref()
+            .and_then(crate::camera::normalize_lens),
+        template: config.template.clone(),
+        name_style: config.name_style(),
+        timestamp_style: config.timestamp_style(),
+        category: None,
+        folder: None,
+        page_count: exif_data.page_count.and_then(|pages| pages.trim().parse().ok()),
+        duration: exif_data.duration.as_deref().and_then(parse_duration),
+        shot,
+    }
+}
+
+/// Seconds in an exiftool duration: `1:02:03`, `12.34 s`, or either with ` (approx)`
+fn parse_duration(text: &str) -> Option<f64> {
+    let text = text.trim_end_matches("(approx)").trim();
+    if let Some(seconds) = text.strip_suffix('s') {
+        return seconds.trim().parse().ok();
+    }
+    text.split(':').try_fold(0.0, |total, part| Some(total * 60.0 + part.trim().parse::<f64>().ok()?))
+}
+
+/// Accepts a JSON string or number as a string
+fn string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// Fills in a title from file content (PDF/text extraction, image and video OCR,
+/// AcoustID fingerprint and paper lookups) when the embedded metadata has nothing useful,
+/// and the photo subject hint when turned on
+/// Failures are returned as recoverable issues; the metadata is still usable
+/// External tools run under `processes` when given, so a run only starts that many at once
+/// A tool that runs longer than `RenameConfig::tool_timeout` is stopped and reported as a
+/// timeout issue; the file's later tools are then skipped
+/// `extension` is the one the file's content calls for (see `detector::content_extension`)
+pub fn extract_content(
+    path: &Path,
+    extension: Option<&str>,
+    config: &crate::RenameConfig,
+    metadata: &mut FileMetadata,
+    processes: Option<&ProcessLimit>,
+) -> Vec<AnalysisIssue> {
+    let mut issues = Vec::new();
+    let ocr_languages = || crate::ocr_languages::language_attempts(&config.ocr_languages);
+    let phrases = crate::key_phrases::PhraseLimits::new(config);
+    let min_confidence = config.min_ocr_confidence;
+    let timestamp_style = config.timestamp_style();
+    let receipts = config.receipt_mode.then_some(&timestamp_style);
+    // By content where it says, so a PNG named .jpg is still read as an image
+    let (pdf, image, video) = (is_pdf(extension), is_image(extension), is_video(extension));
+
+    // Papers are named from the record of the DOI or arXiv identifier on their first page,
+    // when the user opted in; the title embedded in a paper's PDF is often a file name
+    if config.paper_lookup && pdf {
+        let paper = profiling::time(Stage::Ocr, || crate::paper_lookup::identify_paper(path, config.paper_cache_path()));
+        match paper {
+            Ok(Some(name)) => {
+                debug!("Identified paper: {}", name);
+                metadata.title = Some(name);
+                // The name carries the year; the PDF's dates are when it was downloaded
+                metadata.date_time_original = None;
+                metadata.creation_date = None;
+            }
+            Ok(None) => {}
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("Paper lookup failed: {}", e),
+            )),
+        }
+    }
 
     // For PDFs without useful metadata, try extracting text content
     // pdf_content module now uses key_phrases internally for longer text
-    if is_pdf(path)
+    if pdf
         && !is_useful_metadata(&metadata.title)
         && !is_useful_metadata(&metadata.subject)
     {
         debug!("PDF has no useful metadata, attempting content extraction");
-        if let Ok(Some(content)) = pdf_content::extract_pdf_content(path) {
-            debug!("Extracted PDF content: {}", content);
-            metadata.title = Some(content);
+        let content = limited(processes, || {
+            pdf_content::extract_pdf_content(path, &ocr_languages(), phrases, min_confidence, receipts)
+        });
+        match content {
+            Ok(Some(content)) => {
+                debug!("Extracted PDF content: {}", content.name);
+                name_from_content(metadata, content);
+            }
+            Ok(None) => {}
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("PDF content extraction failed: {}", e),
+            )),
         }
     }
 
     // For plain text files without useful metadata, try extracting content
     // text_content module now uses key_phrases internally for longer text
-    if is_text_file(path) && !has_any_useful_metadata(&metadata) {
+    if is_text_file(path) && !has_any_useful_metadata(metadata) {
         debug!("Text file has no useful metadata, attempting content extraction");
-        if let Ok(Some(content)) = text_content::extract_text_content(path) {
-            debug!("Extracted text content: {}", content);
-            metadata.title = Some(content);
+        match text_content::extract_text_content(path, phrases) {
+            Ok(Some(content)) => {
+                debug!("Extracted text content: {}", content);
+                metadata.title = Some(content);
+            }
+            Ok(None) => {}
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("Text content extraction failed: {}", e),
+            )),
         }
     }
 
     // For images without useful metadata, try OCR
     // image_ocr module now uses key_phrases internally for longer text
-    if is_image(path) && !has_any_useful_metadata(&metadata) {
-        debug!("Image has no useful metadata, attempting OCR");
-        if let Ok(Some(text)) = image_ocr::extract_image_text(path) {
-            debug!("Extracted image text: {}", text);
-            metadata.title = Some(text);
+    // In receipt mode every image is read: a photographed receipt has a camera date and
+    // nothing else to be named by, and only a receipt name replaces the metadata
+    let useful = has_any_useful_metadata(metadata);
+    if image && (!useful || receipts.is_some()) {
+        debug!("Attempting OCR on image (useful metadata: {})", useful);
+        let image_text = limited(processes, || {
+            profiling::time(Stage::Ocr, || {
+                image_ocr::extract_image_text(path, &ocr_languages(), phrases, min_confidence, receipts)
+            })
+        });
+        match image_text {
+            Ok(Some(content)) if content.dated || !useful => {
+                debug!("Extracted image text: {}", content.name);
+                name_from_content(metadata, content);
+            }
+            Ok(_) => {}
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("Image OCR failed: {}", e),
+            )),
+        }
+    }
+
+    // A rough subject is a last-resort name for photos (scored below OCR text)
+    if config.subject_hints && image {
+        let subject = profiling::time(Stage::Ocr, || {
+            crate::subject_hints::detect_subject(path, metadata.camera.is_some())
+        });
+        match subject {
+            Ok(subject) => metadata.subject_hint = subject.map(|s| s.label().to_string()),
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("Subject detection failed: {}", e),
+            )),
+        }
+    }
+
+    // Untitled music can be identified from its audio fingerprint, when the user opted in
+    if let Some(api_key) = &config.acoustid_key {
+        if is_audio(extension) && !is_useful_metadata(&metadata.title) {
+            debug!("Audio has no useful title, looking up its fingerprint on AcoustID");
+            let track = limited(processes, || {
+                profiling::time(Stage::Ocr, || crate::acoustid::identify_track(path, api_key))
+            });
+            match track {
+                Ok(Some(name)) => {
+                    debug!("Identified track: {}", name);
+                    metadata.title = Some(name);
+                }
+                Ok(None) => {}
+                Err(e) => issues.push(AnalysisIssue::recoverable(
+                    Stage::Ocr,
+                    format!("AcoustID lookup failed: {}", e),
+                )),
+            }
+        }
+    }
+
+    // Audio-stream tags name music videos and TV captures better than OCR can
+    if video {
+        let tags = limited(processes, || {
+            let _tags_timer = StageTimer::start(Stage::Exif);
+            stream_tags::read_stream_tags(path)
+        });
+        match tags {
+            Ok(tags) => {
+                if !is_useful_metadata(&metadata.title) && is_useful_metadata(&tags.title) {
+                    debug!("Using title from stream tags: {:?}", tags.title);
+                    metadata.title = tags.title;
+                }
+                if metadata.artist.is_none() {
+                    metadata.artist = tags.artist;
+                }
+                metadata.show = tags.show;
+            }
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Exif,
+                format!("Reading stream tags failed: {}", e),
+            )),
+        }
+    }
+
+    // Chapter titles and subtitle script headers name lectures and episodes before OCR
+    if video && !is_useful_metadata(&metadata.title) {
+        let video_metadata = limited(processes, || {
+            let _tags_timer = StageTimer::start(Stage::Exif);
+            video_metadata::read_video_metadata(path)
+        });
+        match video_metadata {
+            Ok(video_metadata) => {
+                let name = video_metadata.candidate_name().map(str::to_string);
+                if is_useful_metadata(&name) {
+                    debug!("Using title from chapters or subtitles: {:?}", name);
+                    metadata.title = name;
+                }
+            }
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Exif,
+                format!("Reading chapters and subtitles failed: {}", e),
+            )),
         }
     }
 
     // For videos without useful metadata, try extracting and OCR'ing a frame
     // video_ocr module now uses key_phrases internally for longer text
-    if is_video(path)
+    if video
         && !is_useful_metadata(&metadata.title)
         && !is_useful_metadata(&metadata.creation_date)
     {
         debug!("Video has no useful metadata, attempting frame extraction and OCR");
-        let video_text = if config.multiframe_video {
-            debug!("Using multi-frame video analysis (default)");
-            video_ocr::extract_video_text_multiframe(path)
-        } else {
-            debug!("Using single-frame video analysis (--fast-video)");
-            video_ocr::extract_video_text(path)
-        };
+        let video_text = limited(processes, || {
+            let _ocr_timer = StageTimer::start(Stage::Ocr);
+            if config.multiframe_video {
+                debug!("Using multi-frame video analysis (default)");
+                video_ocr::extract_video_text_multiframe(
+                    path,
+                    config.frame_aggregation,
+                    config.video_frames,
+                    &ocr_languages(),
+                    phrases,
+                    min_confidence,
+                )
+            } else {
+                debug!("Using single-frame video analysis (--fast-video)");
+                video_ocr::extract_video_text(path, &ocr_languages(), phrases, min_confidence)
+            }
+        });
 
-        if let Ok(Some(text)) = video_text {
-            debug!("Extracted video text: {}", text);
-            metadata.title = Some(text);
+        match video_text {
+            Ok(Some(text)) => {
+                debug!("Extracted video text: {}", text);
+                metadata.title = Some(text);
+            }
+            Ok(None) => {}
+            Err(e) => issues.push(AnalysisIssue::recoverable(
+                Stage::Ocr,
+                format!("Video frame OCR failed: {}", e),
+            )),
         }
     }
 
-    Ok(metadata)
+    // Also the tools stopped whose failure the steps above only logged
+    issues.extend(
+        tool_command::take_timeouts()
+            .into_iter()
+            .map(|(stage, message)| AnalysisIssue::timeout(stage, message)),
+    );
+    issues
 }
 
-/// Checks if a file is a PDF based on extension
-fn is_pdf(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
-        .unwrap_or(false)
+/// Checks if a file is a PDF by the extension its content calls for, or its own when the
+/// content doesn't say (see `detector::effective_extension`)
+pub(crate) fn is_pdf(extension: Option<&str>) -> bool {
+    extension == Some("pdf")
 }
 
-/// Checks if a file is an image based on extension
-fn is_image(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| {
-            matches!(
-                ext.to_lowercase().as_str(),
-                "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tiff" | "tif" | "webp" | "heic" | "heif"
-            )
-        })
-        .unwrap_or(false)
+/// Checks if a file is an image (camera RAW included) by its content or extension
+pub(crate) fn is_image(extension: Option<&str>) -> bool {
+    extension.is_some_and(|extension| {
+        matches!(
+            extension,
+            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tiff" | "tif" | "webp" | "heic" | "heif"
+        ) || crate::detector::RAW_EXTENSIONS.contains(&extension)
+    })
 }
 
-/// Checks if a file is a video based on extension
-fn is_video(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| {
-            matches!(
-                ext.to_lowercase().as_str(),
-                "mp4" | "mov" | "avi" | "mkv" | "webm" | "flv" | "wmv" | "m4v"
-            )
-        })
-        .unwrap_or(false)
+/// Checks if a file is an audio file by its content or extension
+fn is_audio(extension: Option<&str>) -> bool {
+    matches!(extension, Some("mp3" | "wav" | "flac" | "aac" | "ogg" | "m4a" | "wma" | "opus"))
+}
+
+/// Checks if a file is a video by its content or extension
+pub(crate) fn is_video(extension: Option<&str>) -> bool {
+    matches!(extension, Some("mp4" | "mov" | "avi" | "mkv" | "webm" | "flv" | "wmv" | "m4v"))
 }
 
 /// Checks if a file is a plain text file based on extension
@@ -411,10 +991,77 @@ fn has_any_useful_metadata(metadata: &FileMetadata) -> bool {
         || is_useful_metadata(&metadata.date_time_original)
 }
 
+/// Names the file from text read out of it; a name that carries its own date (a statement's
+/// month, a receipt's date) replaces the file's dates, which are only when it was saved
+fn name_from_content(metadata: &mut FileMetadata, content: pdf_content::PdfContent) {
+    metadata.title = Some(content.name);
+    if content.dated {
+        metadata.date_time_original = None;
+        metadata.creation_date = None;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_metadata_fields() {
+        let raw = RawMetadata {
+            title: Some("Harbour at Dusk".to_string()),
+            artist: Some("J. Doe".to_string()),
+            gps_latitude: Some("22.2855".to_string()),
+            gps_latitude_ref: Some("N".to_string()),
+            gps_longitude: Some("114.1577".to_string()),
+            gps_longitude_ref: Some("E".to_string()),
+            ..RawMetadata::default()
+        };
+        let fields = build_metadata(raw, &crate::RenameConfig::default()).fields();
+        assert_eq!(
+            fields,
+            [
+                ("Title", "Harbour at Dusk".to_string()),
+                ("Artist", "J. Doe".to_string()),
+                ("GPS", "22.28550, 114.15770".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_receipt_names_carry_the_only_date() {
+        let config = crate::RenameConfig { receipt_mode: true, ..crate::RenameConfig::default() };
+        let raw = RawMetadata {
+            date_time_original: Some("2024:01:03 09:12:00".to_string()),
+            ..RawMetadata::default()
+        };
+        let mut metadata = build_metadata(raw, &config);
+        let text = "ACME CORP, Inc.\n\
+                    INVOICE\n\
+                    Invoice Date: October 15, 2023\n\
+                    Amount Due             $142.50\n";
+        let content = pdf_content::name_bill(text, Some(&config.timestamp_style())).unwrap();
+        name_from_content(&mut metadata, content);
+
+        let candidate = metadata.title.clone().unwrap();
+        let (name, _) = crate::generator::generate_filename_with_metadata(
+            &candidate,
+            Some(std::ffi::OsStr::new("pdf")),
+            &mut std::collections::HashSet::new(),
+            Some(&metadata),
+        );
+        assert_eq!(name, "2023-10-15_AcmeCorp_invoice_142.50.pdf");
+    }
+
+    #[test]
+    fn test_page_count_and_duration() {
+        let raw = parse_exiftool_json(r#"[{"PageCount": 412, "Duration": "1:02:03 (approx)"}]"#).unwrap();
+        let metadata = build_metadata(raw, &crate::RenameConfig::default());
+        assert_eq!(metadata.page_count, Some(412));
+        assert_eq!(metadata.duration, Some(3723.0));
+        assert_eq!(parse_duration("12.34 s"), Some(12.34));
+        assert_eq!(parse_duration("unknown"), None);
+    }
+
     #[test]
     fn test_is_useful_metadata_rejects_errors() {
         assert!(!is_useful_metadata(&Some("ERROR: Cannot read file".to_string())));
@@ -487,4 +1134,83 @@ mod tests {
         assert!(!has_excessive_repetition("bookkeeper"));
         assert!(!has_excessive_repetition("aaa")); // exactly 3 is ok
     }
+
+    /// An executable shell script standing in for exiftool; each start is logged to `starts`
+    #[cfg(unix)]
+    fn fake_exiftool(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("exiftool");
+        let script = format!("#!/bin/sh\necho start >> '{}'\n{}", dir.join("starts").display(), body);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_exiftool_pool_reuses_processes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Answers each -execute with the last argument (the file) as its title
+        let program = fake_exiftool(
+            temp_dir.path(),
+            r#"while read -r line; do
+  case "$line" in
+    -execute) printf '[{"Title":"%s"}]\n{ready}\n' "$file" ;;
+    False) exit 0 ;;
+    *) file="$line" ;;
+  esac
+done"#,
+        );
+
+        let pool = ExiftoolPool::with_program(program, Duration::from_secs(10));
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            let path = temp_dir.path().join(name);
+            let raw = pool.read(&path).unwrap();
+            assert_eq!(raw.title.as_deref(), path.to_str());
+        }
+        drop(pool);
+        let starts = std::fs::read_to_string(temp_dir.path().join("starts")).unwrap();
+        assert_eq!(starts.lines().count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_exiftool_pool_times_out() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Never answers
+        let program = fake_exiftool(
+            temp_dir.path(),
+            r#"while read -r line; do
+  case "$line" in
+    -execute) sleep 5 ;;
+  esac
+done"#,
+        );
+
+        let pool = ExiftoolPool::with_program(program, Duration::from_millis(200));
+        let started = std::time::Instant::now();
+        let err = pool.read(&temp_dir.path().join("a.jpg")).unwrap_err();
+        assert!(tool_command::is_timeout(&err), "{:#}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // Not retried one-shot, which would hang as well
+        let starts = std::fs::read_to_string(temp_dir.path().join("starts")).unwrap();
+        assert_eq!(starts.lines().count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_exiftool_pool_falls_back_without_stay_open() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Ignores -stay_open and exits after one answer, like a one-shot run
+        let program = fake_exiftool(temp_dir.path(), r#"echo '[{"Title":"Report"}]'"#);
+
+        let pool = ExiftoolPool::with_program(program, Duration::from_secs(10));
+        for name in ["a.pdf", "b.pdf"] {
+            let raw = pool.read(&temp_dir.path().join(name)).unwrap();
+            assert_eq!(raw.title.as_deref(), Some("Report"));
+        }
+        let starts = std::fs::read_to_string(temp_dir.path().join("starts")).unwrap();
+        // The failed persistent start, then one run per file
+        assert_eq!(starts.lines().count(), 3);
+    }
 }
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Why a file was left alone for now (it can be processed on a later run)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferReason {
+    /// Another program has the file open (Windows sharing violation)
+    Locked,
+    /// The file changed size or modification time while it was being analyzed
+    StillWriting,
+    /// A browser or sync client's incomplete download (e.g. `.crdownload`, `.part`)
+    PartialDownload,
+    /// An editor's temporary or lock file (e.g. `.tmp`, `~$report.docx`)
+    Temporary,
+    /// The file changed more recently than `RenameConfig::stable_for` allows
+    RecentlyChanged,
+}
+
+impl fmt::Display for DeferReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeferReason::Locked => write!(f, "in use by another program"),
+            DeferReason::StillWriting => write!(f, "still being written"),
+            DeferReason::PartialDownload => write!(f, "incomplete download"),
+            DeferReason::Temporary => write!(f, "temporary or lock file"),
+            DeferReason::RecentlyChanged => write!(f, "changed too recently"),
+        }
+    }
+}
+
+/// Extensions browsers and download/sync clients use while a file is arriving
+const PARTIAL_DOWNLOAD_EXTENSIONS: &[&str] = &[
+    "crdownload", "part", "partial", "download", "opdownload", "!ut", "!qb", "bc!", "aria2", "filepart",
+];
+
+/// Extensions of editors' and installers' scratch files
+const TEMPORARY_EXTENSIONS: &[&str] = &["tmp", "temp", "swp"];
+
+/// Prefixes of the lock files Office (`~$report.docx`) and LibreOffice (`.~lock.report.odt#`)
+/// keep next to open documents
+const LOCK_FILE_PREFIXES: &[&str] = &["~$", ".~lock."];
+
+/// Files modified this recently get a second look before their analysis is trusted
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Pause before that second look
+const SETTLE_CHECK: Duration = Duration::from_millis(500);
+
+/// Size and modification time of a file, to notice it changing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Snapshot {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Snapshot {
+    pub(crate) fn take(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn is_recent(&self) -> bool {
+        self.changed_within(SETTLE_TIME)
+    }
+
+    /// Whether the file was modified less than `period` ago
+    fn changed_within(&self, period: Duration) -> bool {
+        self.modified
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < period)
+    }
+}
+
+/// Why `path` shouldn't be touched right now: an incomplete download, a temporary or lock
+/// file, a locked file, or one changed within the last `stable_for`
+pub(crate) fn check(path: &Path, stable_for: Option<Duration>) -> Option<DeferReason> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    if extension.as_ref().is_some_and(|ext| PARTIAL_DOWNLOAD_EXTENSIONS.contains(&ext.as_str())) {
+        return Some(DeferReason::PartialDownload);
+    }
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    if extension.is_some_and(|ext| TEMPORARY_EXTENSIONS.contains(&ext.as_str()))
+        || LOCK_FILE_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+    {
+        return Some(DeferReason::Temporary);
+    }
+    let unsettled = stable_for.is_some_and(|period| {
+        Snapshot::take(path).is_some_and(|snapshot| snapshot.changed_within(period))
+    });
+    if unsettled {
+        return Some(DeferReason::RecentlyChanged);
+    }
+    is_locked(path).then_some(DeferReason::Locked)
+}
+
+/// Files whose size or modification time differ from their snapshot
+/// If any file was modified within the last couple of seconds, waits briefly first
+/// so that a download or sync still in progress has a chance to show it
+pub(crate) fn still_writing(snapshots: &[(&Path, Snapshot)]) -> HashSet<PathBuf> {
+    if snapshots.iter().any(|(_, snapshot)| snapshot.is_recent()) {
+        std::thread::sleep(SETTLE_CHECK);
+    }
+
+    snapshots
+        .iter()
+        .filter(|(path, before)| Snapshot::take(path).is_some_and(|now| now != *before))
+        .map(|(path, _)| path.to_path_buf())
+        .collect()
+}
+
+/// Whether another process holds the file open
+/// Opening without sharing fails with a sharing violation while anyone else has it open
+#[cfg(windows)]
+fn is_locked(path: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    match std::fs::OpenOptions::new().read(true).share_mode(0).open(path) {
+        Ok(_) => false,
+        Err(e) => matches!(
+            e.raw_os_error(),
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+        ),
+    }
+}
+
+/// Unix has no mandatory locks; open files can be renamed safely
+#[cfg(not(windows))]
+fn is_locked(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_partial_download() {
+        assert_eq!(
+            check(Path::new("/tmp/report.pdf.crdownload"), None),
+            Some(DeferReason::PartialDownload)
+        );
+        assert_eq!(check(Path::new("/tmp/video.mp4.PART"), None), Some(DeferReason::PartialDownload));
+        assert_eq!(check(Path::new("/tmp/movie.mkv.aria2"), None), Some(DeferReason::PartialDownload));
+        assert_eq!(check(Path::new("/tmp/report.pdf"), None), None);
+    }
+
+    #[test]
+    fn test_check_temporary_and_lock_files() {
+        for name in ["setup.tmp", ".notes.txt.swp", "~$Budget.xlsx", ".~lock.Minutes.odt#"] {
+            assert_eq!(check(&Path::new("/tmp").join(name), None), Some(DeferReason::Temporary), "{}", name);
+        }
+        assert_eq!(check(Path::new("/tmp/Budget.xlsx"), None), None);
+    }
+
+    #[test]
+    fn test_check_stable_for() {
+        let temp_dir = TempDir::new().unwrap();
+        let fresh = temp_dir.path().join("fresh.pdf");
+        fs::write(&fresh, "just written").unwrap();
+
+        assert_eq!(check(&fresh, Some(Duration::from_secs(60))), Some(DeferReason::RecentlyChanged));
+        assert_eq!(check(&fresh, None), None);
+        // Files that can't be read are left to the later stages to report
+        assert_eq!(check(&temp_dir.path().join("missing.pdf"), Some(Duration::from_secs(60))), None);
+    }
+
+    #[test]
+    fn test_still_writing_detects_growth() {
+        let temp_dir = TempDir::new().unwrap();
+        let growing = temp_dir.path().join("growing.bin");
+        let done = temp_dir.path().join("done.bin");
+        fs::write(&growing, "first chunk").unwrap();
+        fs::write(&done, "complete").unwrap();
+
+        let snapshots = vec![
+            (growing.as_path(), Snapshot::take(&growing).unwrap()),
+            (done.as_path(), Snapshot::take(&done).unwrap()),
+        ];
+        fs::write(&growing, "first chunk, second chunk").unwrap();
+
+        let changed = still_writing(&snapshots);
+        assert!(changed.contains(&growing));
+        assert!(!changed.contains(&done));
+    }
+}
@@ -1,80 +1,571 @@
-use anyhow::Result;
-use log::debug;
+use anyhow::{Context, Result};
+use tracing::debug;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::detector::FileCategory;
+use crate::tool_command::ToolCommand;
+
+/// Entries listed before the rest of a huge archive is ignored
+const MAX_ENTRIES: usize = 10_000;
+
+/// Time spent listing one archive; compressed tarballs have to be read from the start
+const MAX_LISTING_TIME: Duration = Duration::from_secs(3);
+
+/// Largest central directory read from a ZIP file
+const MAX_CENTRAL_DIRECTORY: u64 = 16 * 1024 * 1024;
+
+/// Bytes of a README or manifest read for its title
+const MAX_TITLE_FILE: u64 = 64 * 1024;
+
+/// README and manifest files whose title names the archive, best first
+const TITLE_FILES: &[&str] = &[
+    "readme.md", "readme.markdown", "readme.rst", "readme.txt", "readme",
+    "package.json", "cargo.toml", "pyproject.toml",
+];
+
+/// One file or folder inside an archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, `/`-separated
+    pub path: String,
+    pub is_dir: bool,
+    /// Year the entry was last modified, when the archive records it
+    pub year: Option<i32>,
+}
+
+/// What peeking into an archive found
+#[derive(Debug, Default)]
+pub struct ArchiveContents {
+    pub entries: Vec<ArchiveEntry>,
+    /// Title from an embedded README or manifest (package.json, Cargo.toml, pyproject.toml)
+    pub title: Option<String>,
+    /// Whether the size or time limits cut the listing short
+    pub truncated: bool,
+}
+
+/// A name for an archive, from its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveName {
+    pub name: String,
+    /// Only the kind of files and their year (`documents_2023`), nothing that tells this
+    /// archive from others like it
+    pub generic: bool,
+}
 
 /// Inspects archive contents and suggests a name
-/// Looks at the first significant file inside the archive
-pub fn extract_archive_info(path: &Path) -> Result<Option<String>> {
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase());
-
-    match extension.as_deref() {
-        Some("zip") => inspect_zip(path),
-        Some("tar") | Some("tgz") | Some("tar.gz") => inspect_tar(path),
-        Some("7z") => inspect_7z(path),
-        Some("rar") => inspect_rar(path),
-        _ => Ok(None),
+/// Lists ZIP and TAR archives itself (7z and RAR with their tools) and names them after an
+/// embedded README/manifest title or their top folder, the dominant file type and the year
+/// of the newest file, e.g. `ProjectX_source_2021`
+pub fn extract_archive_info(path: &Path) -> Result<Option<ArchiveName>> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+
+    let contents = if name.ends_with(".zip") {
+        read_zip(path).or_else(|e| {
+            debug!("Reading ZIP directly failed ({}), listing with unzip", e);
+            list_with_tool(path, "unzip", &["-Z1"])
+        })?
+    } else if name.ends_with(".tar") {
+        read_tar(File::open(path)?, path)?
+    } else if name.ends_with(".tgz") || name.ends_with(".tar.gz") {
+        read_tar(flate2::read::GzDecoder::new(File::open(path)?), path)?
+    } else if name.ends_with(".tar.bz2") || name.ends_with(".tar.xz") {
+        list_with_tool(path, "tar", &["-tf"])?
+    } else if name.ends_with(".7z") {
+        read_7z(path)?
+    } else if name.ends_with(".rar") {
+        return Ok(inspect_rar(path)?.map(|name| ArchiveName { name, generic: false }));
+    } else {
+        return Ok(None);
+    };
+
+    if contents.truncated {
+        debug!("Listing of {} stopped after {} entries", path.display(), contents.entries.len());
     }
+    Ok(propose_name(&contents))
+}
+
+/// Builds `Title_kind_year` from what's in the archive, leaving out the parts it can't tell
+fn propose_name(contents: &ArchiveContents) -> Option<ArchiveName> {
+    let files: Vec<&str> = contents
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir && !is_junk_file(&entry.path))
+        .map(|entry| entry.path.as_str())
+        .collect();
+    if files.is_empty() {
+        return None;
+    }
+
+    let title = contents
+        .title
+        .as_deref()
+        .map(title_to_name)
+        .or_else(|| match files.as_slice() {
+            [only] => extract_stem(only),
+            _ => top_folder(&files).map(title_to_name).or_else(|| {
+                find_common_prefix(&files.iter().map(|f| f.to_string()).collect::<Vec<_>>())
+                    .filter(|common| common.len() > 3)
+            }),
+        })
+        .filter(|title| !title.is_empty());
+    // A single file's stem already says what it is
+    let kind = (files.len() > 1).then(|| dominant_kind(&files)).flatten();
+    let year = contents
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .filter_map(|entry| entry.year)
+        .max();
+
+    if title.is_none() && kind.is_none() {
+        return None;
+    }
+    let generic = title.is_none();
+    let parts: Vec<String> = [title, kind.map(str::to_string), year.map(|y| y.to_string())]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(ArchiveName {
+        name: parts.join("_"),
+        generic,
+    })
+}
+
+/// The one folder every file is in, e.g. `projectx-main` for GitHub downloads
+fn top_folder<'a>(files: &[&'a str]) -> Option<&'a str> {
+    let first = files.first()?.split_once('/')?.0;
+    files
+        .iter()
+        .all(|file| file.split_once('/').is_some_and(|(top, _)| top == first))
+        .then_some(first)
+        .filter(|top| !top.is_empty() && *top != ".")
+}
+
+/// What most of the files are, if one kind makes up more than half of them
+fn dominant_kind(files: &[&str]) -> Option<&'static str> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for file in files {
+        let kind = match crate::detector::detect_by_extension(Path::new(file)) {
+            FileCategory::Image => "images",
+            FileCategory::Document => "documents",
+            FileCategory::Audio => "audio",
+            FileCategory::Video => "videos",
+            FileCategory::Email => "emails",
+            FileCategory::Web => "web",
+            FileCategory::Archive => "archives",
+            FileCategory::Installer => "installers",
+            FileCategory::SourceCode => "source",
+            FileCategory::Ebook => "ebooks",
+            FileCategory::Unknown => continue,
+        };
+        *counts.entry(kind).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| count * 2 > files.len())
+        .max_by_key(|(kind, count)| (*count, *kind))
+        .map(|(kind, _)| kind)
+}
+
+/// The README or manifest to read a title from: the best-ranked one nearest the top
+fn title_file(entries: &[ArchiveEntry]) -> Option<&ArchiveEntry> {
+    entries
+        .iter()
+        .filter(|entry| !entry.is_dir && entry.path.matches('/').count() <= 1)
+        .filter_map(|entry| {
+            let name = entry.path.rsplit('/').next()?.to_lowercase();
+            let rank = TITLE_FILES.iter().position(|t| *t == name)?;
+            Some(((entry.path.matches('/').count(), rank), entry))
+        })
+        .min_by_key(|(key, _)| *key)
+        .map(|(_, entry)| entry)
+}
+
+/// Title of a README (its first heading or line) or a manifest's package name
+fn parse_title(file_name: &str, text: &str) -> Option<String> {
+    let lower = file_name.rsplit('/').next().unwrap_or(file_name).to_lowercase();
+    let title = match lower.as_str() {
+        "package.json" => serde_json::from_str::<serde_json::Value>(text)
+            .ok()?
+            .get("name")?
+            .as_str()
+            .map(str::to_string),
+        "cargo.toml" | "pyproject.toml" => {
+            let manifest: toml::Table = toml::from_str(text).ok()?;
+            ["package", "project"]
+                .iter()
+                .find_map(|section| manifest.get(*section)?.get("name")?.as_str())
+                .or_else(|| manifest.get("tool")?.get("poetry")?.get("name")?.as_str())
+                .map(str::to_string)
+        }
+        _ => {
+            let first = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+            // Markdown `# Title`, or an rST/Setext title underlined on the next line
+            let heading = first.trim_start_matches('#').trim();
+            (first.starts_with('#') || first.len() <= 60).then(|| heading.to_string())
+        }
+    }?;
+    // Scoped npm packages: `@acme/widgets` → `widgets`
+    let title = title.rsplit('/').next().unwrap_or(&title).trim().to_string();
+    (title.len() > 2).then_some(title)
+}
+
+/// A title as one name part, words joined by `_`
+fn title_to_name(title: &str) -> String {
+    title
+        .split_whitespace()
+        .map(clean_name)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
 }
 
-/// Inspects ZIP archive
-fn inspect_zip(path: &Path) -> Result<Option<String>> {
+/// Where a ZIP entry's data is: compression method, compressed size and local header offset
+type ZipLocation = (usize, u64, u64);
+
+/// Lists a ZIP archive from its central directory and reads the README/manifest title
+fn read_zip(path: &Path) -> Result<ArchiveContents> {
     debug!("Inspecting ZIP archive: {}", path.display());
+    let mut file = File::open(path)?;
+    let (mut contents, locations) = zip_directory(&mut file)?;
 
-    let output = Command::new("unzip")
-        .arg("-l") // List contents
-        .arg(path)
-        .output();
+    if let Some(entry_path) = title_file(&contents.entries).map(|entry| entry.path.clone()) {
+        match read_zip_entry(&mut file, locations[&entry_path], MAX_TITLE_FILE) {
+            Ok(data) => contents.title = parse_title(&entry_path, &String::from_utf8_lossy(&data)),
+            Err(e) => debug!("Could not read {} from ZIP: {}", entry_path, e),
+        }
+    }
 
-    if let Ok(output) = output {
-        if output.status.success() {
-            let listing = String::from_utf8_lossy(&output.stdout);
-            return extract_from_listing(&listing, "");
+    Ok(contents)
+}
+
+/// The start of one file in a ZIP archive (up to `max_bytes`), or None if it isn't there
+pub(crate) fn zip_file(path: &Path, name: &str, max_bytes: u64) -> Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let (_, locations) = zip_directory(&mut file)?;
+    locations
+        .get(name)
+        .map(|&location| read_zip_entry(&mut file, location, max_bytes))
+        .transpose()
+}
+
+/// Entries of a ZIP archive and where their data is, from its central directory
+fn zip_directory(file: &mut File) -> Result<(ArchiveContents, HashMap<String, ZipLocation>)> {
+    let len = file.metadata()?.len();
+
+    // The end-of-central-directory record, followed by a comment of up to 64 KiB
+    let tail_len = len.min(22 + 65_535);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)?;
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| tail[i..].starts_with(b"PK\x05\x06"))
+        .context("Not a ZIP archive")?;
+    let eocd = &tail[eocd..];
+    let (directory_size, directory_offset) = (u32_at(eocd, 12), u32_at(eocd, 16));
+    if directory_offset == u32::MAX as u64 || directory_size > MAX_CENTRAL_DIRECTORY {
+        anyhow::bail!("ZIP64 or oversized central directory");
+    }
+
+    let mut directory = vec![0u8; directory_size as usize];
+    file.seek(SeekFrom::Start(directory_offset))?;
+    file.read_exact(&mut directory).context("Truncated ZIP central directory")?;
+
+    let mut contents = ArchiveContents::default();
+    let mut locations = HashMap::new();
+    let mut pos = 0;
+    while directory
+        .get(pos..)
+        .is_some_and(|rest| rest.len() >= 46 && rest.starts_with(b"PK\x01\x02"))
+    {
+        if contents.entries.len() >= MAX_ENTRIES {
+            contents.truncated = true;
+            break;
         }
+        let header = &directory[pos..];
+        let name_len = u16_at(header, 28);
+        let name = header.get(46..46 + name_len).context("Truncated ZIP central directory")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let dos_date = u16_at(header, 14);
+        locations.insert(
+            name.clone(),
+            (u16_at(header, 10), u32_at(header, 20), u32_at(header, 42)),
+        );
+        contents.entries.push(ArchiveEntry {
+            is_dir: name.ends_with('/'),
+            path: name,
+            // DOS dates count years from 1980; a zero date means none was stored
+            year: (dos_date != 0).then(|| 1980 + (dos_date >> 9) as i32),
+        });
+        pos += 46 + name_len + u16_at(header, 30) + u16_at(header, 32);
     }
 
-    Ok(None)
+    Ok((contents, locations))
+}
+
+/// Start of a ZIP entry's data, up to `max_bytes`
+fn read_zip_entry(file: &mut File, (method, size, offset): ZipLocation, max_bytes: u64) -> Result<Vec<u8>> {
+    let mut header = [0u8; 30];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut header)?;
+    if !header.starts_with(b"PK\x03\x04") {
+        anyhow::bail!("Bad local header");
+    }
+    let data_start = offset + 30 + (u16_at(&header, 26) + u16_at(&header, 28)) as u64;
+    file.seek(SeekFrom::Start(data_start))?;
+    let data = Read::by_ref(file).take(size);
+
+    let mut bytes = Vec::new();
+    match method {
+        0 => data.take(max_bytes).read_to_end(&mut bytes)?,
+        8 => flate2::read::DeflateDecoder::new(data)
+            .take(max_bytes)
+            .read_to_end(&mut bytes)?,
+        _ => anyhow::bail!("Unsupported ZIP compression method {}", method),
+    };
+    Ok(bytes)
+}
+
+fn u16_at(data: &[u8], i: usize) -> usize {
+    u16::from_le_bytes([data[i], data[i + 1]]) as usize
+}
+
+fn u32_at(data: &[u8], i: usize) -> u64 {
+    u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as u64
 }
 
-/// Inspects TAR archive
-fn inspect_tar(path: &Path) -> Result<Option<String>> {
+/// Lists a (possibly gzipped) TAR stream, reading README/manifest titles on the way
+fn read_tar(reader: impl Read, path: &Path) -> Result<ArchiveContents> {
     debug!("Inspecting TAR archive: {}", path.display());
+    let mut contents = ArchiveContents::default();
+    // Title files found so far, with the entry they came from
+    let mut titles: Vec<(ArchiveEntry, Vec<u8>)> = Vec::new();
+
+    contents.truncated = walk_tar(
+        reader,
+        |entry| title_file(std::slice::from_ref(entry)).is_some(),
+        |entry, data| {
+            if let Some(data) = data {
+                titles.push((entry.clone(), data));
+            }
+            contents.entries.push(entry);
+            true
+        },
+    )?;
+
+    let candidates: Vec<ArchiveEntry> = titles.iter().map(|(entry, _)| entry.clone()).collect();
+    if let Some(best) = title_file(&candidates) {
+        let data = &titles.iter().find(|(entry, _)| entry == best).expect("listed above").1;
+        contents.title = parse_title(&best.path, &String::from_utf8_lossy(data));
+    }
+    Ok(contents)
+}
 
-    let output = Command::new("tar")
-        .arg("-tf") // List files
-        .arg(path)
-        .output();
+/// The start of one file in a TAR stream (up to `MAX_TITLE_FILE` bytes), or None if it isn't there
+/// `name` is matched without a leading `./`
+pub(crate) fn tar_file(reader: impl Read, name: &str) -> Result<Option<Vec<u8>>> {
+    let name = name.trim_start_matches("./");
+    let mut found = None;
+    walk_tar(
+        reader,
+        |entry| entry.path == name,
+        |_, data| {
+            found = data;
+            found.is_none()
+        },
+    )?;
+    Ok(found)
+}
 
-    if let Ok(output) = output {
-        if output.status.success() {
-            let listing = String::from_utf8_lossy(&output.stdout);
-            return extract_from_listing(&listing, "");
+/// Calls `visit` with each entry of a TAR stream, and the first `MAX_TITLE_FILE` bytes of
+/// those regular files `wants_data` picks, until `visit` returns false
+/// The stream has to be read up to the last entry, so this stops after `MAX_ENTRIES` or
+/// `MAX_LISTING_TIME`, returning whether it did
+fn walk_tar(
+    mut reader: impl Read,
+    wants_data: impl Fn(&ArchiveEntry) -> bool,
+    mut visit: impl FnMut(ArchiveEntry, Option<Vec<u8>>) -> bool,
+) -> Result<bool> {
+    let started = Instant::now();
+    let mut count = 0;
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; 512];
+
+    loop {
+        if count >= MAX_ENTRIES || started.elapsed() > MAX_LISTING_TIME {
+            return Ok(true);
+        }
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            // Archives cut short still list what came before
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        if header.iter().all(|&b| b == 0) {
+            return Ok(false); // End-of-archive marker
+        }
+
+        let size = octal(&header[124..136]);
+        let padded = size.div_ceil(512) * 512;
+        let kind = header[156];
+
+        // GNU long names and PAX headers carry the next entry's path as data
+        if kind == b'L' || kind == b'x' {
+            let mut data = Vec::new();
+            Read::by_ref(&mut reader).take(padded).read_to_end(&mut data)?;
+            data.truncate(size as usize);
+            let text = String::from_utf8_lossy(&data);
+            long_name = if kind == b'L' {
+                Some(text.trim_end_matches('\0').to_string())
+            } else {
+                text.lines()
+                    .find_map(|record| record.split_once(" path=").map(|(_, p)| p.to_string()))
+                    .or(long_name)
+            };
+            continue;
+        }
+
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = field(&header[0..100]);
+            // ustar splits long paths into a prefix and a name
+            match field(&header[345..500]) {
+                prefix if header[257..262] == *b"ustar" && !prefix.is_empty() => {
+                    format!("{}/{}", prefix, name)
+                }
+                _ => name,
+            }
+        });
+        let name = name.trim_start_matches("./").to_string();
+        let mtime = octal(&header[136..148]);
+        let entry = ArchiveEntry {
+            is_dir: kind == b'5' || name.ends_with('/'),
+            year: (mtime > 0)
+                .then(|| chrono::DateTime::from_timestamp(mtime as i64, 0))
+                .flatten()
+                .map(|time| chrono::Datelike::year(&time)),
+            path: name,
+        };
+
+        let mut body = Read::by_ref(&mut reader).take(padded);
+        let data = if (kind == b'0' || kind == 0) && wants_data(&entry) {
+            let mut data = Vec::new();
+            Read::by_ref(&mut body).take(MAX_TITLE_FILE).read_to_end(&mut data)?;
+            data.truncate(size.min(MAX_TITLE_FILE) as usize);
+            Some(data)
+        } else {
+            None
+        };
+        io::copy(&mut body, &mut io::sink())?;
+
+        if entry.path.is_empty() {
+            continue;
+        }
+        count += 1;
+        if !visit(entry, data) {
+            return Ok(false);
         }
     }
+}
 
-    Ok(None)
+/// A NUL-terminated TAR header string
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
 }
 
-/// Inspects 7z archive
-fn inspect_7z(path: &Path) -> Result<Option<String>> {
+/// A TAR header number, stored as octal text
+fn octal(bytes: &[u8]) -> u64 {
+    let text = field(bytes);
+    u64::from_str_radix(text.trim(), 8).unwrap_or(0)
+}
+
+/// Lists a 7z archive with `7z l -slt` and reads the README/manifest title with `7z e -so`
+fn read_7z(path: &Path) -> Result<ArchiveContents> {
     debug!("Inspecting 7z archive: {}", path.display());
 
-    let output = Command::new("7z")
-        .arg("l") // List contents
-        .arg(path)
-        .output();
+    let output = Command::new("7z").arg("l").arg("-slt").arg(path).tool_output();
+    let listing = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => return Ok(ArchiveContents::default()),
+    };
 
+    let mut contents = parse_7z_listing(&listing);
+    if let Some(entry_path) = title_file(&contents.entries).map(|entry| entry.path.clone()) {
+        let output = Command::new("7z").arg("e").arg("-so").arg(path).arg(&entry_path).tool_output();
+        if let Ok(output) = output {
+            if output.status.success() {
+                let text = &output.stdout[..output.stdout.len().min(MAX_TITLE_FILE as usize)];
+                contents.title = parse_title(&entry_path, &String::from_utf8_lossy(text));
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Entries from `7z l -slt` output: `Key = value` blocks after the `----------` line
+fn parse_7z_listing(listing: &str) -> ArchiveContents {
+    let mut contents = ArchiveContents::default();
+    let listing = listing.replace("\r\n", "\n");
+    let Some((_, entries)) = listing.split_once("\n----------") else {
+        return contents;
+    };
+
+    for block in entries.split("\n\n") {
+        let value = |key: &str| {
+            block.lines().find_map(|line| {
+                line.split_once(" = ")
+                    .filter(|(k, _)| k.trim() == key)
+                    .map(|(_, v)| v.trim())
+            })
+        };
+        let Some(entry_path) = value("Path") else {
+            continue;
+        };
+        if contents.entries.len() >= MAX_ENTRIES {
+            contents.truncated = true;
+            break;
+        }
+        contents.entries.push(ArchiveEntry {
+            path: entry_path.replace('\\', "/"),
+            is_dir: value("Folder") == Some("+") || value("Attributes").is_some_and(|a| a.starts_with('D')),
+            year: value("Modified").and_then(|m| m.get(..4)).and_then(|y| y.parse().ok()),
+        });
+    }
+    contents
+}
+
+/// Lists an archive with a tool printing one path per line (`unzip -Z1`, `tar -tf`)
+fn list_with_tool(path: &Path, program: &str, args: &[&str]) -> Result<ArchiveContents> {
+    debug!("Listing {} with {}", path.display(), program);
+    let output = Command::new(program).args(args).arg(path).tool_output();
+
+    let mut contents = ArchiveContents::default();
     if let Ok(output) = output {
         if output.status.success() {
-            let listing = String::from_utf8_lossy(&output.stdout);
-            return extract_from_listing(&listing, "");
+            for line in String::from_utf8_lossy(&output.stdout).lines().filter(|l| !l.is_empty()) {
+                if contents.entries.len() >= MAX_ENTRIES {
+                    contents.truncated = true;
+                    break;
+                }
+                contents.entries.push(ArchiveEntry {
+                    path: line.trim_start_matches("./").to_string(),
+                    is_dir: line.ends_with('/'),
+                    year: None,
+                });
+            }
         }
     }
-
-    Ok(None)
+    Ok(contents)
 }
 
 /// Inspects RAR archive
@@ -85,7 +576,7 @@ fn inspect_rar(path: &Path) -> Result<Option<String>> {
     let output = Command::new("unrar")
         .arg("l") // List contents
         .arg(path)
-        .output();
+        .tool_output();
 
     if let Ok(output) = output {
         if output.status.success() {
@@ -147,18 +638,19 @@ fn extract_from_listing(listing: &str, _prefix: &str) -> Result<Option<String>>
 /// Checks if a filename is a junk file to skip
 fn is_junk_file(filename: &str) -> bool {
     let lower = filename.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
 
     // System files
-    if lower.starts_with(".ds_store")
-        || lower.starts_with("thumbs.db")
-        || lower.starts_with("desktop.ini")
+    if name.starts_with(".ds_store")
+        || name.starts_with("thumbs.db")
+        || name.starts_with("desktop.ini")
         || lower.starts_with("__macosx")
     {
         return true;
     }
 
     // Common metadata files
-    if lower.ends_with(".txt") && (lower.contains("readme") || lower.contains("license")) {
+    if name.ends_with(".txt") && (name.contains("readme") || name.contains("license")) {
         return true;
     }
 
@@ -226,8 +718,190 @@ fn clean_name(name: &str) -> String {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use std::io::Write;
+
+    fn entry(path: &str, year: Option<i32>) -> ArchiveEntry {
+        ArchiveEntry {
+            path: path.to_string(),
+            is_dir: path.ends_with('/'),
+            year,
+        }
+    }
+
+    /// A ZIP archive of deflated files, each dated January 1st of its year
+    pub(crate) fn build_zip(files: &[(&str, &str, i32)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+        for (name, text, year) in files {
+            let mut deflated = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            deflated.write_all(text.as_bytes()).unwrap();
+            let deflated = deflated.finish().unwrap();
+            let date = (((year - 1980) as u16) << 9) | (1 << 5) | 1;
+            let offset = archive.len() as u32;
+
+            let mut common = vec![0x14, 0, 0, 0, 8, 0, 0, 0];
+            common.extend(date.to_le_bytes());
+            common.extend([0; 4]); // CRC isn't checked
+            common.extend((deflated.len() as u32).to_le_bytes());
+            common.extend((text.len() as u32).to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            common.extend([0, 0]);
+
+            archive.extend(b"PK\x03\x04");
+            archive.extend(&common);
+            archive.extend(name.as_bytes());
+            archive.extend(&deflated);
+
+            directory.extend(b"PK\x01\x02\x14\x00");
+            directory.extend(&common);
+            directory.extend([0; 10]); // Comment length, disk, attributes
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let directory_offset = archive.len() as u32;
+        archive.extend(&directory);
+        archive.extend(b"PK\x05\x06\x00\x00\x00\x00");
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend([0, 0]);
+        archive
+    }
+
+    /// A TAR archive of regular files, each modified at `mtime`
+    fn build_tar(files: &[(&str, &str, u64)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (name, text, mtime) in files {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", text.len()).as_bytes());
+            header[136..147].copy_from_slice(format!("{:011o}", mtime).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            archive.extend(header);
+            archive.extend(text.as_bytes());
+            archive.resize(archive.len().div_ceil(512) * 512, 0);
+        }
+        archive.extend([0u8; 1024]);
+        archive
+    }
+
+    #[test]
+    fn test_read_zip_names_after_readme() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("download (3).zip");
+        std::fs::write(
+            &path,
+            build_zip(&[
+                ("projectx-main/README.md", "\n# ProjectX\n\nA tool.\n", 2020),
+                ("projectx-main/src/main.rs", "fn main() {}", 2021),
+                ("projectx-main/src/lib.rs", "", 2019),
+            ]),
+        )?;
+
+        let contents = read_zip(&path)?;
+        assert_eq!(contents.entries.len(), 3);
+        assert_eq!(contents.entries[1], entry("projectx-main/src/main.rs", Some(2021)));
+        assert_eq!(contents.title.as_deref(), Some("ProjectX"));
+        assert_eq!(extract_archive_info(&path)?.map(|archive| archive.name).as_deref(), Some("ProjectX_source_2021"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tar_gz_names_after_manifest() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("archive.tar.gz");
+        let tar = build_tar(&[
+            ("./package.json", r#"{"name": "@acme/widgets", "version": "1.0.0"}"#, 1_625_000_000),
+            ("./index.js", "module.exports = {};", 1_625_000_000),
+            ("./lib/util.js", "", 1_600_000_000),
+        ]);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar)?;
+        std::fs::write(&path, gz.finish()?)?;
+
+        assert_eq!(extract_archive_info(&path)?.map(|archive| archive.name).as_deref(), Some("widgets_source_2021"));
+
+        let contents = read_tar(&tar[..1024], &path)?; // Cut short after the first file
+        assert_eq!(contents.entries, [entry("package.json", Some(2021))]);
+        Ok(())
+    }
+
+    fn proposed(contents: &ArchiveContents) -> Option<String> {
+        propose_name(contents).map(|proposed| proposed.name)
+    }
+
+    #[test]
+    fn test_propose_name() {
+        let contents = |entries: Vec<ArchiveEntry>| ArchiveContents {
+            entries,
+            ..Default::default()
+        };
+
+        // No title file: the top folder names it
+        let photos = contents(vec![
+            entry("Holiday Pics/", None),
+            entry("Holiday Pics/IMG_0001.jpg", Some(2018)),
+            entry("Holiday Pics/IMG_0002.jpg", Some(2018)),
+            entry("Holiday Pics/notes.txt", Some(2017)),
+        ]);
+        assert_eq!(proposed(&photos).as_deref(), Some("Holiday_Pics_images_2018"));
+
+        // Mixed files at the top level: just the kind and year, when one kind dominates
+        let mixed = contents(vec![
+            entry("a.pdf", Some(2022)),
+            entry("b.docx", Some(2023)),
+            entry("c.mp3", None),
+        ]);
+        assert_eq!(
+            propose_name(&mixed),
+            Some(ArchiveName { name: "documents_2023".to_string(), generic: true })
+        );
+        assert!(!propose_name(&photos).unwrap().generic);
+        let unclear = contents(vec![entry("a.pdf", None), entry("b.mp3", None)]);
+        assert_eq!(proposed(&unclear), None);
+        // A single file is named after its stem
+        assert_eq!(proposed(&contents(vec![entry("report.pdf", Some(2020))])).as_deref(), Some("report_2020"));
+        assert_eq!(proposed(&contents(vec![entry("x.bin", None), entry("y.dat", None)])), None);
+        assert_eq!(proposed(&contents(vec![entry(".DS_Store", None)])), None);
+    }
+
+    #[test]
+    fn test_parse_title() {
+        assert_eq!(parse_title("README.md", "# Project X\nBody").as_deref(), Some("Project X"));
+        assert_eq!(parse_title("README.rst", "Widgets\n=======\n").as_deref(), Some("Widgets"));
+        assert_eq!(
+            parse_title("Cargo.toml", "[package]\nname = \"nameback\"\nversion = \"0.8.0\"").as_deref(),
+            Some("nameback")
+        );
+        assert_eq!(
+            parse_title("pyproject.toml", "[tool.poetry]\nname = \"spamalot\"").as_deref(),
+            Some("spamalot")
+        );
+        assert_eq!(parse_title("README.txt", &"x".repeat(100)), None);
+        assert_eq!(title_to_name("Project X: the sequel"), "Project_X_the_sequel");
+    }
+
+    #[test]
+    fn test_parse_7z_listing() {
+        let listing = "7-Zip 23.01\n\n--\nPath = backup.7z\nType = 7z\n\n----------\n\
+            Path = Thesis\nFolder = +\nModified = 2020-05-01 10:00:00\n\n\
+            Path = Thesis\\chapter1.docx\nFolder = -\nModified = 2020-05-03 09:12:44\n\n\
+            Path = Thesis\\chapter2.docx\nFolder = -\nModified = 2020-06-11 17:40:02\n";
+        let contents = parse_7z_listing(listing);
+        assert_eq!(
+            contents.entries,
+            [
+                ArchiveEntry { is_dir: true, ..entry("Thesis", Some(2020)) },
+                entry("Thesis/chapter1.docx", Some(2020)),
+                entry("Thesis/chapter2.docx", Some(2020)),
+            ]
+        );
+        assert_eq!(proposed(&contents).as_deref(), Some("Thesis_documents_2020"));
+    }
 
     #[test]
     fn test_is_junk_file() {
@@ -0,0 +1,73 @@
+//! Reading ZIP-based containers with XML parts (Office Open XML, EPUB)
+
+use std::path::Path;
+use tracing::debug;
+
+/// Parts are read this far; the titles and headings looked for are near their start
+const MAX_PART_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Reads one part of a ZIP container as text, or None if it isn't there or the container
+/// can't be read
+pub fn zip_part(path: &Path, part: &str) -> Option<String> {
+    match super::archive::zip_file(path, part, MAX_PART_BYTES) {
+        Ok(Some(bytes)) if !bytes.is_empty() => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        Ok(_) => {
+            debug!("No {} in {}", part, path.display());
+            None
+        }
+        Err(e) => {
+            debug!("Failed to read {} from {}: {}", part, path.display(), e);
+            None
+        }
+    }
+}
+
+/// Text of the first `<tag>` element, entities decoded and whitespace collapsed
+pub fn element_text(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}", tag))?;
+    let rest = &xml[start..];
+    let open_end = rest.find('>')?;
+    if rest[..open_end].ends_with('/') {
+        return None; // Empty element
+    }
+    let close = rest.find(&format!("</{}>", tag))?;
+    clean_text(&rest[open_end + 1..close])
+}
+
+/// Decodes XML entities and collapses whitespace; None if nothing is left
+pub fn clean_text(raw: &str) -> Option<String> {
+    let decoded = raw
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    let text = decoded.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zip_part() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("report.docx");
+        let core = "<cp:coreProperties><dc:title>Q3 Budget</dc:title></cp:coreProperties>";
+        std::fs::write(&path, crate::format_handlers::archive::tests::build_zip(&[("docProps/core.xml", core, 2023)])).unwrap();
+        assert_eq!(zip_part(&path, "docProps/core.xml").as_deref(), Some(core));
+        assert_eq!(zip_part(&path, "word/document.xml"), None);
+
+        std::fs::write(&path, "not a zip").unwrap();
+        assert_eq!(zip_part(&path, "docProps/core.xml"), None);
+    }
+
+    #[test]
+    fn test_element_text() {
+        let core = r#"<cp:coreProperties><dc:title>Q3 Budget &amp; Forecast</dc:title><dc:subject/></cp:coreProperties>"#;
+        assert_eq!(element_text(core, "dc:title"), Some("Q3 Budget & Forecast".to_string()));
+        assert_eq!(element_text(core, "dc:subject"), None);
+        assert_eq!(element_text(core, "dc:creator"), None);
+    }
+}
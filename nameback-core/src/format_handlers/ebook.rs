@@ -0,0 +1,272 @@
+use anyhow::Result;
+use regex::Regex;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use super::container::{element_text, zip_part};
+
+/// Bytes of a MOBI file read for its header; record 0 with the EXTH block comes first
+const MOBI_BYTES_READ: u64 = 256 * 1024;
+
+/// EXTH record types used for naming
+const EXTH_AUTHOR: u32 = 100;
+const EXTH_PUBLISHING_DATE: u32 = 106;
+const EXTH_UPDATED_TITLE: u32 = 503;
+
+/// Metadata extracted from an ebook
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EbookMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Publication year, e.g. `2019`
+    pub year: Option<String>,
+}
+
+/// Reads title, author and publication year from an EPUB's OPF package or a MOBI header
+/// (MOBI, AZW and AZW3 share the format)
+pub fn extract_ebook_metadata(path: &Path) -> Result<Option<EbookMetadata>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase());
+
+    let metadata = match extension.as_deref() {
+        Some("epub") => read_epub(path),
+        Some("mobi") | Some("azw") | Some("azw3") => {
+            let mut data = Vec::new();
+            File::open(path)?.take(MOBI_BYTES_READ).read_to_end(&mut data)?;
+            parse_mobi(&data)
+        }
+        _ => None,
+    };
+    Ok(metadata.filter(|m| m.title.is_some()))
+}
+
+/// Formats ebook metadata as `Author - Title 2019` (sanitized to `Author_-_Title_2019`)
+pub fn format_ebook_filename(metadata: &EbookMetadata) -> Option<String> {
+    let title = metadata.title.as_deref()?;
+    let mut name = match &metadata.author {
+        Some(author) => format!("{} - {}", author, title),
+        None => title.to_string(),
+    };
+    if let Some(year) = &metadata.year {
+        name.push(' ');
+        name.push_str(year);
+    }
+    Some(name)
+}
+
+/// Reads the OPF package document the container points to
+fn read_epub(path: &Path) -> Option<EbookMetadata> {
+    debug!("Reading EPUB metadata: {}", path.display());
+
+    static ROOTFILE: OnceLock<Regex> = OnceLock::new();
+    let rootfile = ROOTFILE.get_or_init(|| Regex::new(r#"full-path="([^"]+)""#).unwrap());
+
+    let container = zip_part(path, "META-INF/container.xml")?;
+    let opf_path = rootfile.captures(&container)?[1].to_string();
+    let opf = zip_part(path, &opf_path)?;
+    Some(parse_opf(&opf))
+}
+
+/// Title, first creator and year of `dc:date` from an OPF package document
+fn parse_opf(opf: &str) -> EbookMetadata {
+    EbookMetadata {
+        title: element_text(opf, "dc:title"),
+        author: element_text(opf, "dc:creator"),
+        year: element_text(opf, "dc:date").and_then(|date| year_of(&date)),
+    }
+}
+
+/// Reads the full name and the EXTH author, title and publishing date of a MOBI file
+fn parse_mobi(data: &[u8]) -> Option<EbookMetadata> {
+    if !has_magic(data, 60, b"BOOKMOBI") {
+        return None;
+    }
+    debug!("Reading MOBI header");
+
+    // The first entry of the PDB record list points at record 0; its MOBI header follows
+    // a 16-byte PalmDOC header
+    let record0 = be_u32(data, 78)? as usize;
+    let mobi = record0 + 16;
+    if !has_magic(data, mobi, b"MOBI") {
+        return None;
+    }
+    let header_length = be_u32(data, mobi + 4)? as usize;
+
+    let name_offset = record0 + be_u32(data, record0 + 84)? as usize;
+    let name_length = be_u32(data, record0 + 88)? as usize;
+    let mut metadata = EbookMetadata {
+        title: data.get(name_offset..name_offset + name_length).and_then(text),
+        ..EbookMetadata::default()
+    };
+
+    let has_exth = be_u32(data, record0 + 128).is_some_and(|flags| flags & 0x40 != 0);
+    let exth = mobi + header_length;
+    if has_exth && has_magic(data, exth, b"EXTH") {
+        let count = be_u32(data, exth + 8)?;
+        let mut at = exth + 12;
+        for _ in 0..count {
+            let (Some(kind), Some(length)) = (be_u32(data, at), be_u32(data, at + 4)) else {
+                break;
+            };
+            let length = length as usize;
+            if length < 8 {
+                break;
+            }
+            let value = data.get(at + 8..at + length).and_then(text);
+            match kind {
+                EXTH_AUTHOR if metadata.author.is_none() => metadata.author = value,
+                EXTH_UPDATED_TITLE if value.is_some() => metadata.title = value,
+                EXTH_PUBLISHING_DATE if metadata.year.is_none() => {
+                    metadata.year = value.as_deref().and_then(year_of)
+                }
+                _ => {}
+            }
+            at += length;
+        }
+    }
+
+    Some(metadata)
+}
+
+fn has_magic(data: &[u8], at: usize, magic: &[u8]) -> bool {
+    data.get(at..at + magic.len()) == Some(magic)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Header text as a trimmed string (UTF-8, or Latin-1 as a fallback), None if empty
+fn text(bytes: &[u8]) -> Option<String> {
+    let value = match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    };
+    let value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Four-digit year at the start of a date like `2019-05-01` or `2019`
+fn year_of(date: &str) -> Option<String> {
+    let year: String = date.trim().chars().take(4).collect();
+    (year.len() == 4 && year.chars().all(|c| c.is_ascii_digit())).then_some(year)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_opf() {
+        let opf = r#"<package><metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+            <dc:title id="title">The Left Hand of Darkness</dc:title>
+            <dc:creator opf:role="aut">Ursula K. Le Guin</dc:creator>
+            <dc:date>1969-03-01T00:00:00Z</dc:date>
+        </metadata></package>"#;
+        let metadata = parse_opf(opf);
+        assert_eq!(metadata.title.as_deref(), Some("The Left Hand of Darkness"));
+        assert_eq!(metadata.author.as_deref(), Some("Ursula K. Le Guin"));
+        assert_eq!(metadata.year.as_deref(), Some("1969"));
+    }
+
+    #[test]
+    fn test_read_epub() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let container = r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+        let opf = "<package><metadata><dc:title>Dune</dc:title><dc:creator>Frank Herbert</dc:creator><dc:date>1965</dc:date></metadata></package>";
+        let archive = crate::format_handlers::archive::tests::build_zip(&[
+            ("mimetype", "application/epub+zip", 2020),
+            ("META-INF/container.xml", container, 2020),
+            ("OEBPS/content.opf", opf, 2020),
+        ]);
+        std::fs::write(&path, archive).unwrap();
+
+        let metadata = read_epub(&path).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Dune"));
+        assert_eq!(metadata.author.as_deref(), Some("Frank Herbert"));
+    }
+
+    #[test]
+    fn test_format_ebook_filename() {
+        let metadata = EbookMetadata {
+            title: Some("Project Hail Mary".to_string()),
+            author: Some("Andy Weir".to_string()),
+            year: Some("2021".to_string()),
+        };
+        assert_eq!(
+            format_ebook_filename(&metadata),
+            Some("Andy Weir - Project Hail Mary 2021".to_string())
+        );
+
+        let untitled = EbookMetadata {
+            author: Some("Andy Weir".to_string()),
+            ..EbookMetadata::default()
+        };
+        assert_eq!(format_ebook_filename(&untitled), None);
+    }
+
+    /// A minimal MOBI file: PDB header, one record with PalmDOC and MOBI headers, EXTH and name
+    fn mobi_file(name: &str, exth: &[(u32, &str)]) -> Vec<u8> {
+        let record0 = 86;
+        let mut data = vec![0u8; record0];
+        data[60..68].copy_from_slice(b"BOOKMOBI");
+        data[78..82].copy_from_slice(&(record0 as u32).to_be_bytes());
+
+        let header_length = 232u32;
+        let mut record = vec![0u8; 16 + header_length as usize];
+        record[16..20].copy_from_slice(b"MOBI");
+        record[20..24].copy_from_slice(&header_length.to_be_bytes());
+        record[128..132].copy_from_slice(&0x40u32.to_be_bytes());
+
+        let mut block = Vec::new();
+        for (kind, value) in exth {
+            block.extend_from_slice(&kind.to_be_bytes());
+            block.extend_from_slice(&(value.len() as u32 + 8).to_be_bytes());
+            block.extend_from_slice(value.as_bytes());
+        }
+        record.extend_from_slice(b"EXTH");
+        record.extend_from_slice(&(block.len() as u32 + 12).to_be_bytes());
+        record.extend_from_slice(&(exth.len() as u32).to_be_bytes());
+        record.extend_from_slice(&block);
+
+        let name_offset = record.len() as u32;
+        record[84..88].copy_from_slice(&name_offset.to_be_bytes());
+        record[88..92].copy_from_slice(&(name.len() as u32).to_be_bytes());
+        record.extend_from_slice(name.as_bytes());
+
+        data.extend_from_slice(&record);
+        data
+    }
+
+    #[test]
+    fn test_parse_mobi() {
+        let data = mobi_file(
+            "Dune",
+            &[(EXTH_AUTHOR, "Frank Herbert"), (EXTH_PUBLISHING_DATE, "1965-08-01")],
+        );
+        let metadata = parse_mobi(&data).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Dune"));
+        assert_eq!(metadata.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(metadata.year.as_deref(), Some("1965"));
+
+        // The updated title wins over the full name
+        let data = mobi_file("DUNE_EBOOK", &[(EXTH_UPDATED_TITLE, "Dune Messiah")]);
+        assert_eq!(parse_mobi(&data).unwrap().title.as_deref(), Some("Dune Messiah"));
+
+        assert_eq!(parse_mobi(b"not a mobi file"), None);
+    }
+
+    #[test]
+    fn test_year_of() {
+        assert_eq!(year_of("2019-05-01"), Some("2019".to_string()));
+        assert_eq!(year_of("2019"), Some("2019".to_string()));
+        assert_eq!(year_of("May 2019"), None);
+    }
+}
@@ -2,49 +2,67 @@
 
 use anyhow::{Context, Result};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek};
 use std::path::Path;
 
-/// Email metadata extracted from .eml files
+/// Email metadata extracted from .eml and .msg files
 #[derive(Debug, Clone)]
 pub struct EmailMetadata {
     pub subject: Option<String>,
     pub from: Option<String>,
     pub date: Option<String>,
+    /// Number of attached files
+    pub attachments: usize,
 }
 
-/// Extracts metadata from .eml (RFC 822) email files
+/// Extracts metadata from an email file, picking the reader by extension
 pub fn extract_email_metadata(path: &Path) -> Result<EmailMetadata> {
+    let is_msg = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("msg"));
+    if is_msg {
+        extract_msg_metadata(path)
+    } else {
+        extract_eml_metadata(path)
+    }
+}
+
+/// Extracts metadata from .eml (RFC 822) email files
+fn extract_eml_metadata(path: &Path) -> Result<EmailMetadata> {
     let file = File::open(path).context("Failed to open email file")?;
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(file);
 
     let mut subject = None;
     let mut from = None;
     let mut date = None;
+    let mut attachments = 0;
     let mut in_headers = true;
+    let mut header_lines = 0;
 
-    for line in reader.lines().take(200) {
-        let line = line?;
-
-        // Empty line marks end of headers
-        if line.trim().is_empty() && in_headers {
-            in_headers = false;
-            break;
-        }
-
-        if !in_headers {
-            break;
-        }
+    // Attachments are MIME parts anywhere in the body; bodies may hold 8-bit data
+    let mut raw = Vec::new();
+    while reader.read_until(b'\n', &mut raw)? > 0 {
+        let line = String::from_utf8_lossy(&raw).trim_end().to_string();
+        raw.clear();
 
         // Parse headers (case-insensitive)
         let lower_line = line.to_lowercase();
 
-        if lower_line.starts_with("subject:") {
-            subject = Some(extract_header_value(&line, "subject:"));
-        } else if lower_line.starts_with("from:") {
-            from = Some(extract_header_value(&line, "from:"));
-        } else if lower_line.starts_with("date:") {
-            date = Some(extract_header_value(&line, "date:"));
+        if in_headers {
+            header_lines += 1;
+            // Empty line marks end of headers
+            if line.trim().is_empty() || header_lines > 200 {
+                in_headers = false;
+            } else if lower_line.starts_with("subject:") {
+                subject = Some(extract_header_value(&line, "subject:"));
+            } else if lower_line.starts_with("from:") {
+                from = Some(extract_header_value(&line, "from:"));
+            } else if lower_line.starts_with("date:") {
+                date = Some(extract_header_value(&line, "date:"));
+            }
+        } else if is_attachment_disposition(&lower_line) {
+            attachments += 1;
         }
     }
 
@@ -52,6 +70,118 @@ pub fn extract_email_metadata(path: &Path) -> Result<EmailMetadata> {
         subject,
         from,
         date,
+        attachments,
+    })
+}
+
+/// Whether a (lower-cased) body line starts a MIME part sent as an attachment
+fn is_attachment_disposition(lower_line: &str) -> bool {
+    lower_line
+        .strip_prefix("content-disposition:")
+        .is_some_and(|value| value.trim_start().starts_with("attachment"))
+}
+
+/// Extracts metadata from Outlook .msg files, which keep MAPI properties in a compound file
+fn extract_msg_metadata(path: &Path) -> Result<EmailMetadata> {
+    let mut msg = cfb::open(path).context("Failed to open Outlook message")?;
+
+    let subject = read_msg_string(&mut msg, PR_SUBJECT);
+    let sender_name = read_msg_string(&mut msg, PR_SENDER_NAME);
+    let sender_address = read_msg_string(&mut msg, PR_SENDER_SMTP_ADDRESS)
+        .or_else(|| read_msg_string(&mut msg, PR_SENDER_EMAIL_ADDRESS))
+        .filter(|address| address.contains('@'));
+    let from = match (sender_name, sender_address) {
+        (Some(name), Some(address)) => Some(format!("{} <{}>", name, address)),
+        (name, address) => name.or(address),
+    };
+
+    // Sent time, else received time, else the Date: line of the original headers
+    let date = read_msg_properties(&mut msg)
+        .and_then(|properties| {
+            [PR_CLIENT_SUBMIT_TIME, PR_MESSAGE_DELIVERY_TIME]
+                .iter()
+                .find_map(|&id| msg_property_time(&properties, id))
+        })
+        .or_else(|| {
+            read_msg_string(&mut msg, PR_TRANSPORT_MESSAGE_HEADERS).and_then(|headers| {
+                headers.lines().find_map(|line| {
+                    line.to_lowercase()
+                        .starts_with("date:")
+                        .then(|| extract_header_value(line, "date:"))
+                })
+            })
+        });
+
+    let attachments = msg
+        .read_root_storage()
+        .filter(|entry| entry.is_storage() && entry.name().starts_with("__attach_version1.0_"))
+        .count();
+
+    Ok(EmailMetadata {
+        subject,
+        from,
+        date,
+        attachments,
+    })
+}
+
+// MAPI property IDs read from .msg files
+const PR_SUBJECT: u16 = 0x0037;
+const PR_CLIENT_SUBMIT_TIME: u16 = 0x0039;
+const PR_TRANSPORT_MESSAGE_HEADERS: u16 = 0x007D;
+const PR_SENDER_NAME: u16 = 0x0C1A;
+const PR_SENDER_EMAIL_ADDRESS: u16 = 0x0C1F;
+const PR_MESSAGE_DELIVERY_TIME: u16 = 0x0E06;
+const PR_SENDER_SMTP_ADDRESS: u16 = 0x5D01;
+
+/// MAPI property types
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_SYSTIME: u16 = 0x0040;
+
+/// Reads a string property stream (`__substg1.0_<id><type>`), UTF-16 or 8-bit
+fn read_msg_string<F: Read + Seek>(msg: &mut cfb::CompoundFile<F>, id: u16) -> Option<String> {
+    let read = |msg: &mut cfb::CompoundFile<F>, kind: u16| -> Option<Vec<u8>> {
+        let mut stream = msg.open_stream(format!("/__substg1.0_{:04X}{:04X}", id, kind)).ok()?;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).ok()?;
+        Some(data)
+    };
+
+    let text = if let Some(data) = read(msg, PT_UNICODE) {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(&read(msg, PT_STRING8)?).into_owned()
+    };
+
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Reads the fixed-size property stream of the message
+fn read_msg_properties<F: Read + Seek>(msg: &mut cfb::CompoundFile<F>) -> Option<Vec<u8>> {
+    let mut stream = msg.open_stream("/__properties_version1.0").ok()?;
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+/// Finds a time property in the message property stream as an RFC 2822 date
+/// The stream has a 32-byte header, then 16-byte entries: tag, flags, 8-byte value
+fn msg_property_time(properties: &[u8], id: u16) -> Option<String> {
+    let tag = ((id as u32) << 16) | PT_SYSTIME as u32;
+    properties.get(32..)?.chunks_exact(16).find_map(|entry| {
+        if u32::from_le_bytes(entry[0..4].try_into().ok()?) != tag {
+            return None;
+        }
+        // FILETIME: 100-nanosecond intervals since 1601-01-01
+        let filetime = u64::from_le_bytes(entry[8..16].try_into().ok()?);
+        let seconds = (filetime / 10_000_000) as i64 - 11_644_473_600;
+        chrono::DateTime::from_timestamp(seconds, 0).map(|time| time.to_rfc2822())
     })
 }
 
@@ -63,8 +193,8 @@ fn extract_header_value(line: &str, prefix: &str) -> String {
 }
 
 /// Formats email metadata into a filename
-/// Format: "Subject_from_Sender_YYYY-MM-DD"
-pub fn format_email_filename(metadata: &EmailMetadata) -> Option<String> {
+/// Format: "Subject_from_Sender_YYYY-MM-DD", plus "_3attachments" when `include_attachments` is set
+pub fn format_email_filename(metadata: &EmailMetadata, include_attachments: bool) -> Option<String> {
     let mut parts = Vec::new();
 
     // Add subject
@@ -91,10 +221,15 @@ pub fn format_email_filename(metadata: &EmailMetadata) -> Option<String> {
     }
 
     if parts.is_empty() {
-        None
-    } else {
-        Some(parts.join("_"))
+        return None;
     }
+
+    if include_attachments && metadata.attachments > 0 {
+        let noun = if metadata.attachments == 1 { "attachment" } else { "attachments" };
+        parts.push(format!("{}{}", metadata.attachments, noun));
+    }
+
+    Some(parts.join("_"))
 }
 
 /// Cleans email field for use in filename
@@ -205,14 +340,22 @@ mod tests {
             subject: Some("Weekly Status Report".to_string()),
             from: Some("Jane Smith <jane@example.com>".to_string()),
             date: Some("Mon, 15 Oct 2023 14:30:22 +0000".to_string()),
+            attachments: 3,
         };
 
-        let result = format_email_filename(&metadata);
+        let result = format_email_filename(&metadata, false);
         assert!(result.is_some());
         let filename = result.unwrap();
         assert!(filename.contains("Weekly_Status_Report"));
         assert!(filename.contains("from_Jane_Smith"));
         assert!(filename.contains("2023-10-15"));
+        assert!(!filename.contains("attachments"));
+
+        let result = format_email_filename(&metadata, true);
+        assert_eq!(
+            result.as_deref(),
+            Some("Weekly_Status_Report_from_Jane_Smith_2023-10-15_3attachments")
+        );
     }
 
     #[test]
@@ -221,9 +364,75 @@ mod tests {
             subject: Some("Test".to_string()),
             from: None,
             date: None,
+            attachments: 1,
         };
 
-        let result = format_email_filename(&metadata);
-        assert_eq!(result, Some("Test".to_string()));
+        let result = format_email_filename(&metadata, true);
+        assert_eq!(result, Some("Test_1attachment".to_string()));
+
+        // An attachment count alone isn't a name
+        let metadata = EmailMetadata { subject: None, ..metadata };
+        assert_eq!(format_email_filename(&metadata, true), None);
+    }
+
+    #[test]
+    fn test_extract_eml_metadata_counts_attachments() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("message.eml");
+        std::fs::write(
+            &path,
+            "From: Jane Smith <jane@example.com>\r\n\
+             Subject: Invoices\r\n\
+             Date: Mon, 15 Oct 2023 14:30:22 +0000\r\n\
+             Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+             \r\n\
+             --b\r\nContent-Type: text/plain\r\nContent-Disposition: inline\r\n\r\nSee attached.\r\n\
+             --b\r\nContent-Type: application/pdf\r\nContent-Disposition: attachment; filename=\"a.pdf\"\r\n\r\nJVBERi0=\r\n\
+             --b\r\nContent-Type: application/pdf\r\nContent-Disposition: ATTACHMENT;\r\n filename=\"b.pdf\"\r\n\r\nJVBERi0=\r\n\
+             --b--\r\n",
+        )?;
+
+        let metadata = extract_email_metadata(&path)?;
+        assert_eq!(metadata.subject.as_deref(), Some("Invoices"));
+        assert_eq!(metadata.from.as_deref(), Some("Jane Smith <jane@example.com>"));
+        assert_eq!(metadata.attachments, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_msg_metadata() -> Result<()> {
+        use std::io::Write;
+
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("message.msg");
+        let mut msg = cfb::create(&path)?;
+        let utf16 = |text: &str| text.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        msg.create_stream("/__substg1.0_0037001F")?.write_all(&utf16("Quarterly Review"))?;
+        msg.create_stream("/__substg1.0_0C1A001F")?.write_all(&utf16("Jane Smith"))?;
+        msg.create_stream("/__substg1.0_0C1F001E")?.write_all(b"/O=EXCHANGE/CN=JSMITH\0")?;
+
+        // Header, then PR_CLIENT_SUBMIT_TIME = 2023-10-15 14:30:22 UTC
+        let mut properties = vec![0u8; 32];
+        properties.extend_from_slice(&0x0039_0040u32.to_le_bytes());
+        properties.extend_from_slice(&6u32.to_le_bytes());
+        properties.extend_from_slice(&133_418_538_220_000_000u64.to_le_bytes());
+        msg.create_stream("/__properties_version1.0")?.write_all(&properties)?;
+
+        msg.create_storage("/__attach_version1.0_#00000000")?;
+        msg.create_storage("/__attach_version1.0_#00000001")?;
+        msg.create_storage("/__recip_version1.0_#00000000")?;
+        msg.flush()?;
+        drop(msg);
+
+        let metadata = extract_email_metadata(&path)?;
+        assert_eq!(metadata.subject.as_deref(), Some("Quarterly Review"));
+        // Exchange addresses aren't email addresses, so only the name is kept
+        assert_eq!(metadata.from.as_deref(), Some("Jane Smith"));
+        assert_eq!(metadata.attachments, 2);
+        assert_eq!(
+            format_email_filename(&metadata, true).as_deref(),
+            Some("Quarterly_Review_from_Jane_Smith_2023-10-15_2attachments")
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,881 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tracing::debug;
+
+use super::archive::{tar_file, zip_file};
+use crate::tool_command::ToolCommand;
+
+/// Bytes of a PE file read for its headers and section table
+const PE_HEADER_BYTES: u64 = 4096;
+
+/// Largest resource section searched for version information
+const MAX_PE_RESOURCES: u64 = 8 * 1024 * 1024;
+
+/// Bytes of an RPM read for its headers; name and version come early in the data store
+const RPM_HEADER_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Largest AndroidManifest.xml read from an APK
+const MAX_APK_MANIFEST: u64 = 1024 * 1024;
+
+/// Distinguishes temp files written by concurrent .deb reads
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// What an installer or package says it installs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstallerMetadata {
+    /// Publisher, e.g. `Mozilla` (company suffixes like `Corporation` dropped)
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    /// Target platform, e.g. `win64`, `arm64` or `amd64`
+    pub platform: Option<String>,
+}
+
+/// Reads product, vendor, version and platform from an installer or package: PE version
+/// resources (.exe), MSI properties, deb control files, RPM headers and APK manifests
+/// DMG disk images carry nothing to read, so they keep filename-based names
+pub fn extract_installer_metadata(path: &Path) -> Result<Option<InstallerMetadata>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase());
+
+    let metadata = match extension.as_deref() {
+        Some("exe") => read_pe(path)?,
+        Some("msi") => read_msi(path)?,
+        Some("deb") => read_deb(path)?,
+        Some("rpm") => {
+            let mut data = Vec::new();
+            File::open(path)?.take(RPM_HEADER_BYTES).read_to_end(&mut data)?;
+            parse_rpm(&data)
+        }
+        Some("apk") => zip_file(path, "AndroidManifest.xml", MAX_APK_MANIFEST)?
+            .and_then(|manifest| parse_apk_manifest(&manifest)),
+        _ => None,
+    };
+    Ok(metadata.filter(|m| m.product.is_some()))
+}
+
+/// Formats installer metadata as `Vendor Product 1.2.3 win64` (sanitized to
+/// `Vendor_Product_1.2.3_win64`), leaving out the vendor when the product starts with it
+pub fn format_installer_filename(metadata: &InstallerMetadata) -> Option<String> {
+    static PARENTHESES: OnceLock<Regex> = OnceLock::new();
+    let parentheses = PARENTHESES.get_or_init(|| Regex::new(r"\s*\([^)]*\)").unwrap());
+
+    // "Mozilla Firefox (x64 en-US)": the platform and language are added separately
+    let product = parentheses.replace_all(metadata.product.as_deref()?, "").trim().to_string();
+    if product.is_empty() {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    if let Some(vendor) = &metadata.vendor {
+        let first_word = vendor.split_whitespace().next().unwrap_or(vendor).to_lowercase();
+        if !product.to_lowercase().starts_with(&first_word) {
+            parts.push(vendor.as_str());
+        }
+    }
+    parts.push(&product);
+    if let Some(version) = metadata.version.as_deref().filter(|v| !product.contains(v)) {
+        parts.push(version);
+    }
+    if let Some(platform) = &metadata.platform {
+        parts.push(platform);
+    }
+    Some(parts.join(" "))
+}
+
+/// Drops company suffixes: `Mozilla Corporation` → `Mozilla`, `Acme, Inc.` → `Acme`
+fn clean_vendor(vendor: &str) -> Option<String> {
+    static SUFFIX: OnceLock<Regex> = OnceLock::new();
+    let suffix = SUFFIX.get_or_init(|| {
+        Regex::new(r"(?i)[,.]?\s+(inc\.?|incorporated|corp\.?|corporation|llc|ltd\.?|limited|gmbh|ag|s\.?a\.?|co\.?,?\s*ltd\.?|b\.?v\.?)$").unwrap()
+    });
+    let mut vendor = vendor.trim().to_string();
+    while let Some(found) = suffix.find(&vendor) {
+        vendor.truncate(found.start());
+    }
+    let vendor = vendor.trim_end_matches([',', '.', ' ']).to_string();
+    (!vendor.is_empty()).then_some(vendor)
+}
+
+/// A version as one filename part: `1, 2, 3, 0` → `1.2.3`, `5.2.3 (build 42)` → `5.2.3`
+fn clean_version(version: &str) -> Option<String> {
+    let version = version.trim().replace(", ", ".").replace(',', ".");
+    let mut version = version.split_whitespace().next()?.to_string();
+    // Windows versions always have four parts; a zero fourth part says nothing
+    while version.matches('.').count() > 2 && version.ends_with(".0") {
+        version.truncate(version.len() - 2);
+    }
+    version.starts_with(|c: char| c.is_ascii_digit()).then_some(version)
+}
+
+/// Trimmed text, None if empty
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<usize> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize)
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u64> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?) as u64)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<usize> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?) as usize)
+}
+
+/// Reads the version resource of a Windows executable
+fn read_pe(path: &Path) -> Result<Option<InstallerMetadata>> {
+    debug!("Reading PE version resource: {}", path.display());
+    let mut file = File::open(path)?;
+    let mut headers = Vec::new();
+    Read::by_ref(&mut file).take(PE_HEADER_BYTES).read_to_end(&mut headers)?;
+
+    let Some((machine, offset, size)) = pe_resource_section(&headers) else {
+        return Ok(None);
+    };
+    let mut resources = Vec::new();
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(size.min(MAX_PE_RESOURCES)).read_to_end(&mut resources)?;
+
+    let mut metadata = parse_version_resource(&resources);
+    // 32-bit installer stubs often install 64-bit programs, so only 64-bit ones say anything
+    metadata.platform = match machine {
+        0x8664 => Some("win64".to_string()),
+        0xaa64 => Some("arm64".to_string()),
+        _ => None,
+    };
+    Ok(Some(metadata))
+}
+
+/// Machine type and where the resource section is in the file: (machine, offset, size)
+fn pe_resource_section(headers: &[u8]) -> Option<(usize, u64, u64)> {
+    if !headers.starts_with(b"MZ") {
+        return None;
+    }
+    let pe = le_u32(headers, 0x3c)? as usize;
+    if headers.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = le_u16(headers, pe + 4)?;
+    let sections = le_u16(headers, pe + 6)?;
+    let optional = pe + 24;
+    let directories = match le_u16(headers, optional)? {
+        0x10b => optional + 96,  // PE32
+        0x20b => optional + 112, // PE32+
+        _ => return None,
+    };
+    // The resource table is the third data directory
+    let rva = le_u32(headers, directories + 16)?;
+    let size = le_u32(headers, directories + 20)?;
+    if rva == 0 || size == 0 {
+        return None;
+    }
+
+    let table = optional + le_u16(headers, pe + 20)?;
+    (0..sections).find_map(|i| {
+        let section = table + 40 * i;
+        let virtual_size = le_u32(headers, section + 8)?;
+        let virtual_address = le_u32(headers, section + 12)?;
+        let raw_size = le_u32(headers, section + 16)?;
+        let raw_pointer = le_u32(headers, section + 20)?;
+        (rva >= virtual_address && rva < virtual_address + virtual_size.max(raw_size))
+            .then(|| (machine, raw_pointer + (rva - virtual_address), size))
+    })
+}
+
+/// Product, company and version from the `VS_VERSIONINFO` in a resource section
+fn parse_version_resource(resources: &[u8]) -> InstallerMetadata {
+    // The fixed file info's product version, for files without a ProductVersion string
+    let fixed_version = find(resources, &0xFEEF04BDu32.to_le_bytes()).and_then(|at| {
+        let (high, low) = (le_u32(resources, at + 16)?, le_u32(resources, at + 20)?);
+        (high != 0 || low != 0).then(|| format!("{}.{}.{}.{}", high >> 16, high & 0xffff, low >> 16, low & 0xffff))
+    });
+
+    InstallerMetadata {
+        vendor: version_string(resources, "CompanyName").and_then(|v| clean_vendor(&v)),
+        product: version_string(resources, "ProductName").or_else(|| version_string(resources, "FileDescription")),
+        version: version_string(resources, "ProductVersion")
+            .or(fixed_version)
+            .and_then(|v| clean_version(&v)),
+        platform: None,
+    }
+}
+
+/// Value of a `String` entry in a version resource: a header of three words (length, value
+/// length in words, type), the UTF-16 key, then the value aligned to four bytes
+fn version_string(resources: &[u8], key: &str) -> Option<String> {
+    let needle: Vec<u8> = key.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+    let mut from = 0;
+    while let Some(found) = find(&resources[from..], &needle) {
+        let key_at = from + found;
+        from = key_at + 2;
+        if key_at < 6 || key_at % 4 != 2 {
+            continue;
+        }
+        let value_words = le_u16(resources, key_at - 4)?;
+        let value_at = (key_at + needle.len()).div_ceil(4) * 4;
+        let Some(value) = resources.get(value_at..value_at + value_words * 2) else {
+            continue;
+        };
+        let units: Vec<u16> = value
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        if let Some(text) = non_empty(&String::from_utf16_lossy(&units)) {
+            return Some(text);
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Reads ProductName, ProductVersion and Manufacturer from an MSI's Property table,
+/// and the platform from its summary information
+fn read_msi(path: &Path) -> Result<Option<InstallerMetadata>> {
+    debug!("Reading MSI properties: {}", path.display());
+    let mut msi = cfb::open(path).context("Failed to open MSI database")?;
+    let mut stream = |name: &str| -> Option<Vec<u8>> {
+        let mut data = Vec::new();
+        msi.open_stream(format!("/{}", name)).ok()?.read_to_end(&mut data).ok()?;
+        Some(data)
+    };
+
+    let pool = stream(&msi_table_stream("_StringPool"));
+    let string_data = stream(&msi_table_stream("_StringData"));
+    let table = stream(&msi_table_stream("Property"));
+    let summary = stream("\u{5}SummaryInformation");
+    let (Some(pool), Some(string_data), Some(table)) = (pool, string_data, table) else {
+        return Ok(None);
+    };
+
+    let Some(properties) = msi_properties(&pool, &string_data, &table) else {
+        return Ok(None);
+    };
+    Ok(Some(InstallerMetadata {
+        vendor: properties.get("Manufacturer").and_then(|v| clean_vendor(v)),
+        product: properties.get("ProductName").cloned(),
+        version: properties.get("ProductVersion").and_then(|v| clean_version(v)),
+        platform: summary.as_deref().and_then(msi_platform),
+    }))
+}
+
+/// Name of the stream holding an MSI table: pairs of name characters are packed into one
+/// character from U+3800, a lone one from U+4800, and tables are marked with U+4840
+fn msi_table_stream(table: &str) -> String {
+    const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz._";
+    let index = |c: u8| ALPHABET.iter().position(|&a| a == c).map(|i| i as u32);
+
+    let mut name = String::from('\u{4840}');
+    let bytes = table.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (encoded, used) = match (index(bytes[i]), bytes.get(i + 1).and_then(|&c| index(c))) {
+            (Some(first), Some(second)) => (0x3800 + first + (second << 6), 2),
+            (Some(first), None) => (0x4800 + first, 1),
+            (None, _) => (bytes[i] as u32, 1),
+        };
+        name.extend(char::from_u32(encoded));
+        i += used;
+    }
+    name
+}
+
+/// The Property table as a map, decoded through the string pool
+/// Tables are stored by column; each cell here is a string id of 2 bytes (3 in databases
+/// with long string references)
+fn msi_properties(pool: &[u8], string_data: &[u8], table: &[u8]) -> Option<HashMap<String, String>> {
+    let header = le_u32(pool, 0)?;
+    let id_size = if header & 0x8000_0000 != 0 { 3 } else { 2 };
+
+    // String ids count from 1; each pool entry is the string's length and reference count
+    let mut strings = vec![String::new()];
+    let (mut entry, mut offset) = (4, 0);
+    while entry + 4 <= pool.len() {
+        let mut len = le_u16(pool, entry)?;
+        let refs = le_u16(pool, entry + 2)?;
+        entry += 4;
+        // Strings over 64 KiB have a null entry followed by the length's high and low words
+        if len == 0 && refs != 0 {
+            len = (le_u16(pool, entry + 2)? << 16) + le_u16(pool, entry)?;
+            entry += 4;
+        }
+        let bytes = string_data.get(offset..offset + len)?;
+        offset += len;
+        strings.push(match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            // Most databases use a Windows code page; Latin-1 is close enough for names
+            Err(_) => bytes.iter().map(|&b| b as char).collect(),
+        });
+    }
+
+    let id = |at: usize| -> Option<&String> {
+        let low = le_u16(table, at)?;
+        let high = if id_size == 3 { *table.get(at + 2)? as usize } else { 0 };
+        strings.get(low + (high << 16))
+    };
+    let rows = table.len() / (2 * id_size);
+    (0..rows)
+        .map(|row| {
+            let key = id(row * id_size)?;
+            let value = id((rows + row) * id_size)?;
+            Some((key.clone(), value.clone()))
+        })
+        .collect()
+}
+
+/// Platform from the summary information's Template property (`x64;1033`, `Arm64;1033`)
+fn msi_platform(summary: &[u8]) -> Option<String> {
+    const PID_TEMPLATE: u64 = 7;
+    const VT_LPSTR: u64 = 30;
+
+    let section = le_u32(summary, 44)? as usize;
+    let count = le_u32(summary, section + 4)? as usize;
+    let template = (0..count).find_map(|i| {
+        let at = section + 8 + 8 * i;
+        if le_u32(summary, at)? != PID_TEMPLATE {
+            return None;
+        }
+        let value = section + le_u32(summary, at + 4)? as usize;
+        if le_u32(summary, value)? != VT_LPSTR {
+            return None;
+        }
+        let len = le_u32(summary, value + 4)? as usize;
+        non_empty(&String::from_utf8_lossy(summary.get(value + 8..value + 8 + len)?))
+    })?;
+
+    match template.split(';').next()?.to_lowercase().as_str() {
+        "x64" | "amd64" => Some("win64".to_string()),
+        "arm64" => Some("arm64".to_string()),
+        _ => None,
+    }
+}
+
+/// Reads the control file of a Debian package (an `ar` archive holding `control.tar.*`)
+fn read_deb(path: &Path) -> Result<Option<InstallerMetadata>> {
+    debug!("Reading Debian control file: {}", path.display());
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != b"!<arch>\n" {
+        return Ok(None);
+    }
+
+    let mut header = [0u8; 60];
+    while file.read_exact(&mut header).is_ok() {
+        let name = String::from_utf8_lossy(&header[0..16]).trim().trim_end_matches('/').to_string();
+        let size: u64 = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .context("Corrupt Debian package")?;
+        if !name.starts_with("control.tar") {
+            // Members are padded to an even length
+            file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+            continue;
+        }
+
+        let member = Read::by_ref(&mut file).take(size);
+        let control = match name.as_str() {
+            "control.tar" => tar_file(member, "control")?,
+            "control.tar.gz" => tar_file(flate2::read::GzDecoder::new(member), "control")?,
+            _ => control_with_tar(member)?,
+        };
+        return Ok(control.map(|control| parse_deb_control(&String::from_utf8_lossy(&control))));
+    }
+    Ok(None)
+}
+
+/// Extracts `control` from an xz- or zstd-compressed control tarball with the tar tool
+fn control_with_tar(mut member: impl Read) -> Result<Option<Vec<u8>>> {
+    let temp = std::env::temp_dir().join(format!(
+        "nameback_deb_control_{}_{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut temp_file = File::create(&temp)?;
+    std::io::copy(&mut member, &mut temp_file)?;
+    drop(temp_file);
+
+    let control = ["./control", "control"].iter().find_map(|name| {
+        let output = crate::deps_check::create_command("tar").arg("-xOf").arg(&temp).arg(name).tool_output().ok()?;
+        (output.status.success() && !output.stdout.is_empty()).then_some(output.stdout)
+    });
+    let _ = std::fs::remove_file(&temp);
+    Ok(control)
+}
+
+/// Package, Version and Architecture from a Debian control file
+fn parse_deb_control(control: &str) -> InstallerMetadata {
+    let field = |name: &str| {
+        control.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+    };
+    InstallerMetadata {
+        vendor: None,
+        product: field("Package"),
+        // The epoch (`1:`) only orders versions
+        version: field("Version").map(|v| v.split_once(':').map_or(v.clone(), |(_, v)| v.to_string())),
+        platform: field("Architecture").filter(|arch| arch != "all"),
+    }
+}
+
+/// Name, version, release, architecture and vendor from an RPM's main header
+/// (after the 96-byte lead and the signature header, which is padded to 8 bytes)
+fn parse_rpm(data: &[u8]) -> Option<InstallerMetadata> {
+    const NAME: usize = 1000;
+    const VERSION: usize = 1001;
+    const RELEASE: usize = 1002;
+    const VENDOR: usize = 1011;
+    const ARCH: usize = 1022;
+
+    if !data.starts_with(&[0xed, 0xab, 0xee, 0xdb]) {
+        return None;
+    }
+    let header_len = |at: usize| -> Option<usize> {
+        if data.get(at..at + 4)? != [0x8e, 0xad, 0xe8, 0x01] {
+            return None;
+        }
+        Some(16 + be_u32(data, at + 8)? * 16 + be_u32(data, at + 12)?)
+    };
+    let main = 96 + header_len(96)?.div_ceil(8) * 8;
+    header_len(main)?;
+
+    let entries = be_u32(data, main + 8)?;
+    let store = main + 16 + entries * 16;
+    let mut tags = HashMap::new();
+    for i in 0..entries {
+        let entry = main + 16 + i * 16;
+        let (tag, offset) = (be_u32(data, entry)?, be_u32(data, entry + 8)?);
+        if ![NAME, VERSION, RELEASE, VENDOR, ARCH].contains(&tag) {
+            continue;
+        }
+        // Strings, string arrays and i18n strings all start with a NUL-terminated string
+        let text = data.get(store + offset..)?;
+        let end = text.iter().position(|&b| b == 0)?;
+        if let Some(value) = non_empty(&String::from_utf8_lossy(&text[..end])) {
+            tags.insert(tag, value);
+        }
+    }
+
+    let version = match (tags.get(&VERSION), tags.get(&RELEASE)) {
+        (Some(version), Some(release)) => Some(format!("{}-{}", version, release)),
+        (version, _) => version.cloned(),
+    };
+    Some(InstallerMetadata {
+        vendor: tags.get(&VENDOR).and_then(|v| clean_vendor(v)),
+        product: tags.get(&NAME).cloned(),
+        version,
+        platform: tags.get(&ARCH).filter(|arch| *arch != "noarch").cloned(),
+    })
+}
+
+/// Package, version name and (literal) label from an APK's binary AndroidManifest.xml
+/// The vendor is taken from the package name: `org.mozilla.firefox` → `mozilla`
+fn parse_apk_manifest(data: &[u8]) -> Option<InstallerMetadata> {
+    const RES_STRING_POOL: usize = 0x0001;
+    const RES_XML: usize = 0x0003;
+    const RES_XML_START_ELEMENT: usize = 0x0102;
+    const RES_XML_RESOURCE_MAP: usize = 0x0180;
+    const ATTR_LABEL: u64 = 0x0101_0001;
+    const ATTR_VERSION_NAME: u64 = 0x0101_021c;
+    const TYPE_STRING: u8 = 0x03;
+
+    if le_u16(data, 0)? != RES_XML {
+        return None;
+    }
+    let mut strings: Vec<String> = Vec::new();
+    let mut resource_ids: Vec<u64> = Vec::new();
+    let (mut package, mut version, mut label) = (None, None, None);
+
+    let mut pos = le_u16(data, 2)?;
+    while pos + 8 <= data.len() {
+        let kind = le_u16(data, pos)?;
+        let size = le_u32(data, pos + 4)? as usize;
+        let chunk = data.get(pos..pos + size)?;
+        match kind {
+            RES_STRING_POOL => strings = parse_string_pool(chunk)?,
+            RES_XML_RESOURCE_MAP => {
+                resource_ids = (8..size).step_by(4).filter_map(|at| le_u32(chunk, at)).collect();
+            }
+            RES_XML_START_ELEMENT => {
+                let element = strings.get(le_u32(chunk, 20)? as usize).map(String::as_str);
+                let first = 16 + le_u16(chunk, 24)?;
+                let (stride, count) = (le_u16(chunk, 26)?, le_u16(chunk, 28)?);
+                for attribute in (0..count).map(|i| first + i * stride) {
+                    let name_index = le_u32(chunk, attribute + 4)? as usize;
+                    let name = strings.get(name_index).map(String::as_str).unwrap_or("");
+                    let id = resource_ids.get(name_index).copied();
+                    let raw = le_u32(chunk, attribute + 8)?;
+                    let value = if raw != u32::MAX as u64 {
+                        strings.get(raw as usize).cloned()
+                    } else if *chunk.get(attribute + 15)? == TYPE_STRING {
+                        strings.get(le_u32(chunk, attribute + 16)? as usize).cloned()
+                    } else {
+                        None // A resource reference, e.g. @string/app_name
+                    };
+                    match (element, name, id) {
+                        (Some("manifest"), "package", _) => package = value,
+                        (Some("manifest"), "versionName", _) | (Some("manifest"), _, Some(ATTR_VERSION_NAME)) => {
+                            version = value
+                        }
+                        (Some("application"), "label", _) | (Some("application"), _, Some(ATTR_LABEL)) => label = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        if size == 0 {
+            break;
+        }
+        pos += size;
+    }
+
+    let package = package?;
+    let segments: Vec<&str> = package.split('.').collect();
+    let (vendor, product) = match segments.as_slice() {
+        [_, vendor, rest @ ..] if !rest.is_empty() => (Some(vendor.to_string()), rest.join(".")),
+        _ => (None, package.clone()),
+    };
+    Some(InstallerMetadata {
+        vendor,
+        product: Some(label.and_then(|l| non_empty(&l)).unwrap_or(product)),
+        version: version.and_then(|v| non_empty(&v)),
+        platform: None,
+    })
+}
+
+/// Strings of a binary XML string pool, UTF-16 or (with flag 0x100) UTF-8
+fn parse_string_pool(chunk: &[u8]) -> Option<Vec<String>> {
+    let count = le_u32(chunk, 8)? as usize;
+    let utf8 = le_u32(chunk, 16)? & 0x100 != 0;
+    let strings_start = le_u32(chunk, 20)? as usize;
+    let offsets = le_u16(chunk, 2)?;
+
+    (0..count)
+        .map(|i| {
+            let at = strings_start + le_u32(chunk, offsets + 4 * i)? as usize;
+            if utf8 {
+                // Character count, then byte count; each one or two bytes long
+                let skip_len = |at: usize| -> Option<(usize, usize)> {
+                    let first = *chunk.get(at)? as usize;
+                    if first & 0x80 != 0 {
+                        Some((((first & 0x7f) << 8) | *chunk.get(at + 1)? as usize, at + 2))
+                    } else {
+                        Some((first, at + 1))
+                    }
+                };
+                let (_, at) = skip_len(at)?;
+                let (bytes, at) = skip_len(at)?;
+                Some(String::from_utf8_lossy(chunk.get(at..at + bytes)?).into_owned())
+            } else {
+                let mut len = le_u16(chunk, at)?;
+                let mut at = at + 2;
+                if len & 0x8000 != 0 {
+                    len = ((len & 0x7fff) << 16) | le_u16(chunk, at)?;
+                    at += 2;
+                }
+                let units: Vec<u16> = chunk
+                    .get(at..at + len * 2)?
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn pad4(data: &mut Vec<u8>) {
+        data.resize(data.len().div_ceil(4) * 4, 0);
+    }
+
+    /// A version resource `String` entry, starting four-byte aligned
+    fn version_entry(resources: &mut Vec<u8>, key: &str, value: &str) {
+        pad4(resources);
+        let start = resources.len();
+        resources.extend([0, 0]);
+        resources.extend(((value.encode_utf16().count() + 1) as u16).to_le_bytes());
+        resources.extend(1u16.to_le_bytes());
+        resources.extend(utf16(key));
+        resources.extend([0, 0]);
+        pad4(resources);
+        resources.extend(utf16(value));
+        resources.extend([0, 0]);
+        let len = (resources.len() - start) as u16;
+        resources[start..start + 2].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// A 64-bit PE whose only section holds the given resources
+    fn build_pe(resources: &[u8]) -> Vec<u8> {
+        let mut pe = vec![0u8; 0x200];
+        pe[0..2].copy_from_slice(b"MZ");
+        pe[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        pe[0x44..0x46].copy_from_slice(&0x8664u16.to_le_bytes());
+        pe[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        pe[0x54..0x56].copy_from_slice(&240u16.to_le_bytes());
+        pe[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+        let directory = 0x58 + 112 + 16;
+        pe[directory..directory + 4].copy_from_slice(&0x1000u32.to_le_bytes());
+        pe[directory + 4..directory + 8].copy_from_slice(&(resources.len() as u32).to_le_bytes());
+        let section = 0x58 + 240;
+        pe[section..section + 5].copy_from_slice(b".rsrc");
+        for (at, value) in [(8, resources.len() as u32), (12, 0x1000), (16, resources.len() as u32), (20, 0x200)] {
+            pe[section + at..section + at + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        pe.extend(resources);
+        pe
+    }
+
+    /// A TAR archive of regular files
+    fn build_tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (name, text) in files {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", text.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            archive.extend(header);
+            archive.extend(text.as_bytes());
+            archive.resize(archive.len().div_ceil(512) * 512, 0);
+        }
+        archive.extend([0u8; 1024]);
+        archive
+    }
+
+    #[test]
+    fn test_format_installer_filename() {
+        let firefox = InstallerMetadata {
+            vendor: Some("Mozilla".to_string()),
+            product: Some("Mozilla Firefox (x64 en-US)".to_string()),
+            version: Some("120.0.1".to_string()),
+            platform: Some("win64".to_string()),
+        };
+        assert_eq!(format_installer_filename(&firefox).as_deref(), Some("Mozilla Firefox 120.0.1 win64"));
+
+        let tool = InstallerMetadata {
+            vendor: Some("Acme".to_string()),
+            product: Some("Widget Studio 3.1".to_string()),
+            version: Some("3.1".to_string()),
+            platform: None,
+        };
+        assert_eq!(format_installer_filename(&tool).as_deref(), Some("Acme Widget Studio 3.1"));
+        assert_eq!(format_installer_filename(&InstallerMetadata::default()), None);
+    }
+
+    #[test]
+    fn test_clean_vendor_and_version() {
+        assert_eq!(clean_vendor("Mozilla Corporation").as_deref(), Some("Mozilla"));
+        assert_eq!(clean_vendor("Acme, Inc.").as_deref(), Some("Acme"));
+        assert_eq!(clean_vendor("Shenzhen Example Co., Ltd.").as_deref(), Some("Shenzhen Example"));
+        assert_eq!(clean_vendor("VideoLAN").as_deref(), Some("VideoLAN"));
+
+        assert_eq!(clean_version("1, 2, 3, 0").as_deref(), Some("1.2.3"));
+        assert_eq!(clean_version("7.2.0.0").as_deref(), Some("7.2.0"));
+        assert_eq!(clean_version("5.2.3 (build 42)").as_deref(), Some("5.2.3"));
+        assert_eq!(clean_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_read_pe_version_resource() -> Result<()> {
+        let mut resources = vec![0u8; 16];
+        resources.extend(0xFEEF04BDu32.to_le_bytes());
+        resources.extend([0; 12]);
+        resources.extend(((3u32 << 16) | 2).to_le_bytes());
+        resources.extend((1u32 << 16).to_le_bytes());
+        resources.extend([0; 24]);
+        version_entry(&mut resources, "CompanyName", "VideoLAN");
+        version_entry(&mut resources, "ProductName", "VLC media player");
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("vlc-setup.exe");
+        std::fs::write(&path, build_pe(&resources))?;
+
+        let metadata = extract_installer_metadata(&path)?.unwrap();
+        assert_eq!(
+            metadata,
+            InstallerMetadata {
+                vendor: Some("VideoLAN".to_string()),
+                product: Some("VLC media player".to_string()),
+                version: Some("3.2.1".to_string()),
+                platform: Some("win64".to_string()),
+            }
+        );
+        assert_eq!(
+            format_installer_filename(&metadata).as_deref(),
+            Some("VideoLAN VLC media player 3.2.1 win64")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_msi_properties_and_platform() {
+        // Strings 1-6, then the Property table's key and value columns
+        let strings = ["ProductName", "ProductVersion", "Manufacturer", "Widget Studio", "3.1.0", "Acme Corp."];
+        let mut pool = vec![0xe4, 0x04, 0, 0];
+        let mut string_data = Vec::new();
+        for text in strings {
+            pool.extend((text.len() as u16).to_le_bytes());
+            pool.extend(1u16.to_le_bytes());
+            string_data.extend(text.as_bytes());
+        }
+        let table: Vec<u8> = [1u16, 2, 3, 4, 5, 6].iter().flat_map(|id| id.to_le_bytes()).collect();
+
+        let properties = msi_properties(&pool, &string_data, &table).unwrap();
+        assert_eq!(properties["ProductName"], "Widget Studio");
+        assert_eq!(properties["Manufacturer"], "Acme Corp.");
+        assert_eq!(clean_vendor(&properties["Manufacturer"]).as_deref(), Some("Acme"));
+
+        let mut summary = vec![0u8; 48];
+        summary[44..48].copy_from_slice(&48u32.to_le_bytes());
+        let template = b"x64;1033\0";
+        for value in [0u32, 1, 7, 16, 30, template.len() as u32] {
+            summary.extend(value.to_le_bytes());
+        }
+        summary.extend(template);
+        assert_eq!(msi_platform(&summary).as_deref(), Some("win64"));
+
+        let name: Vec<char> = msi_table_stream("Property").chars().collect();
+        assert_eq!(name.len(), 5);
+        assert_eq!(name[0], '\u{4840}');
+        assert_eq!(name[1], '\u{4559}');
+    }
+
+    #[test]
+    fn test_read_deb_control() -> Result<()> {
+        let control = "Package: ripgrep\nVersion: 1:14.1.0-1\nArchitecture: amd64\nMaintainer: Someone\n";
+        let mut compressed = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        compressed.write_all(&build_tar(&[("./control", control)]))?;
+        let compressed = compressed.finish()?;
+
+        let mut deb = b"!<arch>\n".to_vec();
+        for (name, data) in [("debian-binary", b"2.0\n".to_vec()), ("control.tar.gz", compressed)] {
+            deb.extend(format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, 644, data.len()).as_bytes());
+            deb.extend(&data);
+            if data.len() % 2 == 1 {
+                deb.push(b'\n');
+            }
+        }
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("ripgrep_amd64.deb");
+        std::fs::write(&path, deb)?;
+
+        let metadata = extract_installer_metadata(&path)?.unwrap();
+        assert_eq!(format_installer_filename(&metadata).as_deref(), Some("ripgrep 14.1.0-1 amd64"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rpm() {
+        let mut rpm = vec![0xed, 0xab, 0xee, 0xdb];
+        rpm.resize(96, 0);
+        // An empty signature header
+        rpm.extend([0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0]);
+        rpm.extend([0u8; 8]);
+
+        let tags = [(1000u32, "htop"), (1001, "3.3.0"), (1002, "1.fc40"), (1022, "x86_64")];
+        let mut store = Vec::new();
+        let mut index = Vec::new();
+        for (tag, value) in tags {
+            for field in [tag, 6, store.len() as u32, 1] {
+                index.extend(field.to_be_bytes());
+            }
+            store.extend(value.as_bytes());
+            store.push(0);
+        }
+        rpm.extend([0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0]);
+        rpm.extend((tags.len() as u32).to_be_bytes());
+        rpm.extend((store.len() as u32).to_be_bytes());
+        rpm.extend(index);
+        rpm.extend(store);
+
+        let metadata = parse_rpm(&rpm).unwrap();
+        assert_eq!(format_installer_filename(&metadata).as_deref(), Some("htop 3.3.0-1.fc40 x86_64"));
+    }
+
+    #[test]
+    fn test_parse_apk_manifest() {
+        let strings = ["manifest", "package", "versionName", "application", "label", "org.mozilla.firefox", "120.0.1", "Firefox"];
+        let mut pool = Vec::new();
+        let mut offsets = Vec::new();
+        for text in strings {
+            offsets.extend((pool.len() as u32).to_le_bytes());
+            pool.extend((text.len() as u16).to_le_bytes());
+            pool.extend(utf16(text));
+            pool.extend([0, 0]);
+        }
+        let mut chunk = vec![0x01, 0x00, 28, 0];
+        chunk.extend(((28 + offsets.len() + pool.len()) as u32).to_le_bytes());
+        for value in [strings.len() as u32, 0, 0, (28 + offsets.len()) as u32, 0] {
+            chunk.extend(value.to_le_bytes());
+        }
+        chunk.extend(offsets);
+        chunk.extend(pool);
+
+        // A start element with (name, string value) attributes
+        let element = |name: u32, attributes: &[(u32, u32)]| {
+            let mut chunk = vec![0x02, 0x01, 16, 0];
+            chunk.extend(((36 + 20 * attributes.len()) as u32).to_le_bytes());
+            for value in [0u32, u32::MAX, u32::MAX, name] {
+                chunk.extend(value.to_le_bytes());
+            }
+            for value in [20u16, 20, attributes.len() as u16, 0, 0, 0] {
+                chunk.extend(value.to_le_bytes());
+            }
+            for (name, value) in attributes {
+                for field in [u32::MAX, *name, *value] {
+                    chunk.extend(field.to_le_bytes());
+                }
+                chunk.extend([8, 0, 0, 3]);
+                chunk.extend(value.to_le_bytes());
+            }
+            chunk
+        };
+        let mut body = chunk;
+        body.extend(element(0, &[(1, 5), (2, 6)]));
+        body.extend(element(3, &[(4, 7)]));
+
+        let mut manifest = vec![0x03, 0x00, 8, 0];
+        manifest.extend(((8 + body.len()) as u32).to_le_bytes());
+        manifest.extend(body);
+
+        let metadata = parse_apk_manifest(&manifest).unwrap();
+        assert_eq!(
+            metadata,
+            InstallerMetadata {
+                vendor: Some("mozilla".to_string()),
+                product: Some("Firefox".to_string()),
+                version: Some("120.0.1".to_string()),
+                platform: None,
+            }
+        );
+    }
+}
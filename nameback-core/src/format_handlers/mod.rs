@@ -1,3 +1,9 @@
 pub mod archive;
+mod container;
+pub mod ebook;
 pub mod email;
+pub mod installer;
+pub mod office;
+pub mod receipt;
+pub mod statement;
 pub mod web;
@@ -0,0 +1,233 @@
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use super::container::{clean_text, element_text, zip_part};
+
+/// Slides read for titles (the first ones carry the deck's topic)
+const SLIDES_READ: usize = 3;
+
+/// Paragraphs of a document looked at for headings
+const PARAGRAPHS_READ: usize = 200;
+
+/// What an Office Open XML file (DOCX/XLSX/PPTX) says about itself
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OfficeContent {
+    /// `dc:title` from docProps/core.xml, unless it is an application placeholder
+    pub title: Option<String>,
+    /// `dc:subject` from docProps/core.xml
+    pub subject: Option<String>,
+    /// Document title and headings, slide titles, or sheet names, in document order
+    pub headings: Vec<String>,
+}
+
+/// Reads the core properties and the first content parts of a DOCX, XLSX or PPTX file
+/// Returns None when the container can't be read or says nothing useful
+pub fn extract_office_content(path: &Path) -> Option<OfficeContent> {
+    let kind = office_kind(path)?;
+    debug!("Reading Office content: {}", path.display());
+
+    let core = zip_part(path, "docProps/core.xml");
+    let mut content = OfficeContent {
+        title: core
+            .as_deref()
+            .and_then(|xml| element_text(xml, "dc:title"))
+            .filter(|title| !is_placeholder_title(title)),
+        subject: core.as_deref().and_then(|xml| element_text(xml, "dc:subject")),
+        headings: Vec::new(),
+    };
+
+    content.headings = match kind {
+        OfficeKind::Document => zip_part(path, "word/document.xml")
+            .map(|xml| document_headings(&xml))
+            .unwrap_or_default(),
+        OfficeKind::Presentation => (1..=SLIDES_READ)
+            .filter_map(|n| zip_part(path, &format!("ppt/slides/slide{}.xml", n)))
+            .filter_map(|xml| slide_title(&xml))
+            .collect(),
+        OfficeKind::Spreadsheet => zip_part(path, "xl/workbook.xml")
+            .map(|xml| sheet_names(&xml))
+            .unwrap_or_default(),
+    };
+
+    if content == OfficeContent::default() {
+        return None;
+    }
+    Some(content)
+}
+
+/// Titles Word, Excel and PowerPoint fill in by themselves, which say nothing about the file
+pub fn is_placeholder_title(title: &str) -> bool {
+    let lower = title.trim().to_lowercase();
+    lower.is_empty()
+        || lower.starts_with("microsoft word - ")
+        || lower.starts_with("microsoft powerpoint - ")
+        || matches!(
+            lower.as_str(),
+            "powerpoint presentation" | "presentation" | "document" | "title" | "slide 1" | "book1"
+        )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OfficeKind {
+    Document,
+    Spreadsheet,
+    Presentation,
+}
+
+fn office_kind(path: &Path) -> Option<OfficeKind> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "docx" | "docm" | "dotx" => Some(OfficeKind::Document),
+        "xlsx" | "xlsm" | "xltx" => Some(OfficeKind::Spreadsheet),
+        "pptx" | "pptm" | "potx" => Some(OfficeKind::Presentation),
+        _ => None,
+    }
+}
+
+/// Title-styled paragraphs and headings of a document, or its first real paragraph
+/// when it has no headings
+fn document_headings(xml: &str) -> Vec<String> {
+    static PARAGRAPH: OnceLock<Regex> = OnceLock::new();
+    static STYLE: OnceLock<Regex> = OnceLock::new();
+    let paragraph = PARAGRAPH.get_or_init(|| Regex::new(r"(?s)<w:p[ >].*?</w:p>").unwrap());
+    let style = STYLE.get_or_init(|| Regex::new(r#"<w:pStyle w:val="([^"]+)""#).unwrap());
+
+    let mut headings = Vec::new();
+    let mut first_paragraph = None;
+    for found in paragraph.find_iter(xml).take(PARAGRAPHS_READ) {
+        let Some(text) = run_text(found.as_str(), "w:t") else {
+            continue;
+        };
+        let style_id = style
+            .captures(found.as_str())
+            .map(|c| c[1].to_lowercase())
+            .unwrap_or_default();
+        if style_id == "title" || style_id.starts_with("heading") {
+            headings.push(text);
+        } else if first_paragraph.is_none() && text.split_whitespace().count() >= 3 {
+            first_paragraph = Some(text);
+        }
+    }
+
+    if headings.is_empty() {
+        headings.extend(first_paragraph);
+    }
+    headings
+}
+
+/// Text of the title placeholder on a slide
+fn slide_title(xml: &str) -> Option<String> {
+    static SHAPE: OnceLock<Regex> = OnceLock::new();
+    let shape = SHAPE.get_or_init(|| Regex::new(r"(?s)<p:sp>.*?</p:sp>").unwrap());
+
+    shape
+        .find_iter(xml)
+        .map(|found| found.as_str())
+        .find(|sp| sp.contains(r#"<p:ph type="title""#) || sp.contains(r#"<p:ph type="ctrTitle""#))
+        .and_then(|sp| run_text(sp, "a:t"))
+}
+
+/// Sheet names of a workbook, leaving out defaults like "Sheet1"
+fn sheet_names(xml: &str) -> Vec<String> {
+    static SHEET: OnceLock<Regex> = OnceLock::new();
+    static DEFAULT_NAME: OnceLock<Regex> = OnceLock::new();
+    let sheet = SHEET.get_or_init(|| Regex::new(r#"<sheet [^>]*name="([^"]+)""#).unwrap());
+    let default_name = DEFAULT_NAME.get_or_init(|| {
+        Regex::new(r"(?i)^(sheet|feuil|tabelle|hoja|foglio|planilha|blad|лист)\s*\d+$").unwrap()
+    });
+
+    sheet
+        .captures_iter(xml)
+        .filter_map(|c| clean_text(&c[1]))
+        .filter(|name| !default_name.is_match(name))
+        .collect()
+}
+
+/// Joined text of the `<tag>` runs in an XML fragment (`w:t` in Word, `a:t` in slides)
+/// Runs can split a word, so only breaks, tabs and paragraph ends become spaces
+fn run_text(fragment: &str, tag: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut rest = fragment;
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    while let Some(start) = find_open_tag(rest, &open) {
+        let between = &rest[..start];
+        if ["</a:p>", "<a:br", "<w:br", "<w:tab", "<w:cr"].iter().any(|gap| between.contains(gap)) {
+            text.push(' ');
+        }
+        rest = &rest[start + open.len()..];
+        let (Some(open_end), Some(end)) = (rest.find('>'), rest.find(&close)) else {
+            break;
+        };
+        if open_end < end {
+            text.push_str(&rest[open_end + 1..end]);
+        }
+        rest = &rest[end..];
+    }
+    clean_text(&text)
+}
+
+/// Position of the next `<tag>` or `<tag attr=...>`, skipping tags that only share the
+/// prefix (`<w:tab/>` when looking for `<w:t`)
+fn find_open_tag(xml: &str, open: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(found) = xml[from..].find(open) {
+        let at = from + found;
+        if xml[at + open.len()..].starts_with(['>', ' ']) {
+            return Some(at);
+        }
+        from = at + open.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_headings() {
+        let xml = r#"<w:body>
+            <w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr><w:r><w:t>Annual</w:t></w:r><w:r><w:t xml:space="preserve"> Report</w:t></w:r></w:p>
+            <w:p><w:r><w:t>Some body text that is not a heading</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:tab/><w:t>Results</w:t></w:r></w:p>
+        </w:body>"#;
+        assert_eq!(document_headings(xml), vec!["Annual Report", "Results"]);
+
+        let plain = r#"<w:p><w:r><w:t>Hi</w:t></w:r></w:p><w:p><w:r><w:t>Minutes of the board meeting</w:t></w:r></w:p>"#;
+        assert_eq!(document_headings(plain), vec!["Minutes of the board meeting"]);
+    }
+
+    #[test]
+    fn test_slide_title() {
+        let xml = r#"<p:spTree>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="body"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>Agenda</a:t></a:r></a:p></p:txBody></p:sp>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="ctrTitle"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>Product Road</a:t></a:r><a:r><a:t>map</a:t></a:r></a:p><a:p><a:r><a:t>2025</a:t></a:r></a:p></p:txBody></p:sp>
+        </p:spTree>"#;
+        assert_eq!(slide_title(xml), Some("Product Roadmap 2025".to_string()));
+        assert_eq!(slide_title("<p:spTree></p:spTree>"), None);
+    }
+
+    #[test]
+    fn test_sheet_names() {
+        let xml = r#"<sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/><sheet name="Travel Expenses" sheetId="2" r:id="rId2"/><sheet name="Feuil3" sheetId="3" r:id="rId3"/></sheets>"#;
+        assert_eq!(sheet_names(xml), vec!["Travel Expenses"]);
+    }
+
+    #[test]
+    fn test_is_placeholder_title() {
+        assert!(is_placeholder_title("PowerPoint Presentation"));
+        assert!(is_placeholder_title("Microsoft Word - notes.doc"));
+        assert!(!is_placeholder_title("Quarterly Sales Review"));
+    }
+
+    #[test]
+    fn test_office_kind() {
+        assert_eq!(office_kind(Path::new("a.DOCX")), Some(OfficeKind::Document));
+        assert_eq!(office_kind(Path::new("a.xlsm")), Some(OfficeKind::Spreadsheet));
+        assert_eq!(office_kind(Path::new("a.pptx")), Some(OfficeKind::Presentation));
+        assert_eq!(office_kind(Path::new("a.doc")), None);
+    }
+}
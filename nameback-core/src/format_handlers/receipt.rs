@@ -0,0 +1,429 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use crate::location_timestamp::TimestampStyle;
+
+/// Lines from the top searched for the vendor's name
+const VENDOR_LINES: usize = 6;
+
+/// Lines that end in an amount, like a column of prices, for text to read as an itemized bill
+const PRICE_COLUMN_LINES: usize = 3;
+
+/// Whether a document bills someone or confirms a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Invoice,
+    Receipt,
+}
+
+impl ReceiptKind {
+    fn label(&self) -> &'static str {
+        match self {
+            ReceiptKind::Invoice => "invoice",
+            ReceiptKind::Receipt => "receipt",
+        }
+    }
+}
+
+/// What names a receipt or invoice
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiptFields {
+    pub kind: ReceiptKind,
+    /// Shop or company, e.g. `AcmeCorp` (legal forms like `Inc.` dropped, words joined)
+    pub vendor: Option<String>,
+    /// Invoice or purchase date; a due date only if there is no other
+    pub date: Option<NaiveDate>,
+    /// Amount paid or due with two decimals and no currency, e.g. `1234.50`
+    pub total: String,
+}
+
+/// Reads the vendor, date and total from the text of a receipt or invoice (PDF text or OCR)
+/// Text is taken for one by its keywords (`Invoice`, `Total`, `Amount due`, `Rechnung`, ...)
+/// and its layout (a column of prices); anything else, or a bill without a total, gives None
+pub fn parse_receipt(text: &str) -> Option<ReceiptFields> {
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let kind = detect_kind(&lines)?;
+    let total = find_total(&lines)?;
+    Some(ReceiptFields {
+        kind,
+        vendor: find_vendor(&lines),
+        date: find_date(&lines),
+        total,
+    })
+}
+
+/// Formats receipt fields as `2023-10-15 AcmeCorp invoice 142.50` (sanitized to
+/// `2023-10-15_AcmeCorp_invoice_142.50`), the date first so receipts sort by it, in `style`
+pub fn format_receipt_filename(fields: &ReceiptFields, style: &TimestampStyle) -> String {
+    let date = fields
+        .date
+        .and_then(|date| style.render(&date.format("%Y-%m-%d").to_string()));
+    date.into_iter()
+        .chain(fields.vendor.clone())
+        .chain([fields.kind.label().to_string(), fields.total.clone()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The name of `text` if it reads as a receipt or invoice (see `parse_receipt`)
+pub(crate) fn receipt_name(text: &str, style: &TimestampStyle) -> Option<String> {
+    let fields = parse_receipt(text)?;
+    debug!("Text reads as a receipt: {:?}", fields);
+    Some(format_receipt_filename(&fields, style))
+}
+
+/// Titles that say what the document is, in English, German, French, Spanish, Italian,
+/// Portuguese and Dutch
+fn kind_keyword(line: &str) -> Option<ReceiptKind> {
+    static INVOICE: OnceLock<Regex> = OnceLock::new();
+    static RECEIPT: OnceLock<Regex> = OnceLock::new();
+    let invoice = INVOICE.get_or_init(|| {
+        Regex::new(r"(?i)\b(invoice|tax invoice|rechnung|facture|factura|fattura|fatura|factuur)\b").unwrap()
+    });
+    let receipt = RECEIPT.get_or_init(|| {
+        Regex::new(r"(?i)\b(receipt|sales receipt|quittung|kassenbon|kassenbeleg|reçu|ticket de caisse|recibo|ricevuta|scontrino|kassabon)\b").unwrap()
+    });
+    if invoice.is_match(line) {
+        Some(ReceiptKind::Invoice)
+    } else if receipt.is_match(line) {
+        Some(ReceiptKind::Receipt)
+    } else {
+        None
+    }
+}
+
+/// Labels found on bills: subtotals, taxes, payment and billing details
+fn is_bill_label(line: &str) -> bool {
+    static LABEL: OnceLock<Regex> = OnceLock::new();
+    let label = LABEL.get_or_init(|| {
+        Regex::new(r"(?i)\b(sub-?\s?total|tax|vat|gst|mwst|ust|tva|iva|bill to|ship to|invoice (no|number|#)|due date|payment|paid|cash|change|card|visa|mastercard|qty|quantity|unit price|cashier|thank you)\b").unwrap()
+    });
+    label.is_match(line)
+}
+
+/// Whether the lines are a receipt or an invoice, and which: a title saying so next to a
+/// total, or with no title, bill labels over a column of prices
+fn detect_kind(lines: &[&str]) -> Option<ReceiptKind> {
+    let labels = lines.iter().filter(|line| is_bill_label(line)).count();
+    let prices = lines.iter().filter(|line| ends_in_amount(line)).count();
+    let has_total = lines.iter().any(|line| total_rank(line).is_some());
+
+    if let Some(kind) = lines.iter().find_map(|line| kind_keyword(line)) {
+        return (has_total || prices >= PRICE_COLUMN_LINES).then_some(kind);
+    }
+    if !has_total || labels < 2 || prices < PRICE_COLUMN_LINES {
+        return None;
+    }
+    // Shops print what was paid with; invoices say who to bill
+    let paid = lines.iter().any(|line| {
+        let line = line.to_lowercase();
+        ["cash", "change", "cashier", "card", "thank you"].iter().any(|word| line.contains(word))
+    });
+    Some(if paid { ReceiptKind::Receipt } else { ReceiptKind::Invoice })
+}
+
+/// How surely a line holds the total: `Amount due`, `Grand total` and the like rank 2, a
+/// plain `Total` 1; subtotals and tax totals aren't totals
+fn total_rank(line: &str) -> Option<u8> {
+    static FINAL: OnceLock<Regex> = OnceLock::new();
+    static TOTAL: OnceLock<Regex> = OnceLock::new();
+    static NOT_TOTAL: OnceLock<Regex> = OnceLock::new();
+    let final_total = FINAL.get_or_init(|| {
+        Regex::new(r"(?i)\b(grand total|total due|amount due|balance due|total amount|amount payable|invoice total|total to pay|gesamtbetrag|endbetrag|zu zahlen|montant total|net à payer|total ttc|importe total|totale|totaal)\b").unwrap()
+    });
+    let total = TOTAL.get_or_init(|| Regex::new(r"(?i)\b(total|summe|gesamt|betrag)\b").unwrap());
+    let not_total = NOT_TOTAL.get_or_init(|| {
+        Regex::new(r"(?i)\b(sub-?\s?total|zwischensumme|total (tax|vat)|(tax|vat) total|total items|total qty|total savings|sous-total)\b").unwrap()
+    });
+
+    if not_total.is_match(line) {
+        None
+    } else if final_total.is_match(line) {
+        Some(2)
+    } else if total.is_match(line) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Amounts with cents, as bills print them: `142.50`, `$1,234.50`, `1.234,50 €`, `1'234.50`
+/// Thousands split by a plain space aren't read, as `2 100.00` is as likely a quantity and a price
+/// Returns each amount as `1234.50` with where it ends in `line`
+fn find_amounts(line: &str) -> Vec<(String, usize)> {
+    static AMOUNT: OnceLock<Regex> = OnceLock::new();
+    let amount = AMOUNT.get_or_init(|| {
+        Regex::new(r"(\d{1,3}(?:[,.'\u{A0}\u{202F}]\d{3})+|\d+)[.,](\d{2})").unwrap()
+    });
+    // Not part of a longer number: `9.615`, `2023.10.15` and `v1.20.3` aren't amounts
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.' || c == ',';
+    amount
+        .captures_iter(line)
+        .filter(|caps| {
+            let found = caps.get(0).unwrap();
+            !line[..found.start()].ends_with(is_number_char) && !line[found.end()..].starts_with(is_number_char)
+        })
+        .map(|caps| {
+            let units: String = caps[1].chars().filter(char::is_ascii_digit).collect();
+            let units = units.trim_start_matches('0');
+            let amount = format!("{}.{}", if units.is_empty() { "0" } else { units }, &caps[2]);
+            (amount, caps.get(0).unwrap().end())
+        })
+        .collect()
+}
+
+/// The amounts in `line`, as `1234.50`
+fn amounts(line: &str) -> Vec<String> {
+    find_amounts(line).into_iter().map(|(amount, _)| amount).collect()
+}
+
+/// Whether `line` ends in an amount, maybe followed by its currency
+fn ends_in_amount(line: &str) -> bool {
+    let line = line.trim_end_matches(|c: char| c.is_whitespace() || "$€£¥".contains(c) || c.is_ascii_uppercase());
+    find_amounts(line).last().is_some_and(|(_, end)| *end == line.len())
+}
+
+fn amount_value(amount: &str) -> f64 {
+    amount.parse().unwrap_or(0.0)
+}
+
+/// The total: the amount on (or, when OCR split the columns, under) the surest total line,
+/// the largest if several are as sure; without a total line, the largest amount on a bill
+/// with a column of prices
+fn find_total(lines: &[&str]) -> Option<String> {
+    let mut best: Option<(u8, String)> = None;
+    for (index, line) in lines.iter().enumerate() {
+        let Some(rank) = total_rank(line) else {
+            continue;
+        };
+        let amount = amounts(line)
+            .pop()
+            .or_else(|| lines.get(index + 1).and_then(|next| amounts(next).pop()));
+        if let Some(amount) = amount {
+            let better = best.as_ref().is_none_or(|(best_rank, best_amount)| {
+                rank > *best_rank || (rank == *best_rank && amount_value(&amount) > amount_value(best_amount))
+            });
+            if better {
+                best = Some((rank, amount));
+            }
+        }
+    }
+    if let Some((_, total)) = best {
+        return Some(total);
+    }
+
+    if lines.iter().filter(|line| ends_in_amount(line)).count() < PRICE_COLUMN_LINES {
+        return None;
+    }
+    lines
+        .iter()
+        .flat_map(|line| amounts(line))
+        .max_by(|a, b| amount_value(a).total_cmp(&amount_value(b)))
+}
+
+/// Numeric dates: `2023-10-15`, `15.10.2023`, `15/10/23`, `10/15/2023`
+fn numeric_date_pattern() -> &'static Regex {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    DATE.get_or_init(|| {
+        Regex::new(r"\b(?:(?P<y1>\d{4})[-/.](?P<m1>\d{1,2})[-/.](?P<d1>\d{1,2})|(?P<a>\d{1,2})(?P<sep>[-/.])(?P<b>\d{1,2})[-/.](?P<y2>\d{4}|\d{2}))\b").unwrap()
+    })
+}
+
+/// The dates written in `line`, numeric or with a month name
+/// `03/04/2023` is read the American way (March 4) with slashes and the European way
+/// (3 April) with dots or dashes, unless one of the numbers can only be the day
+pub(super) fn dates(line: &str) -> Vec<NaiveDate> {
+    let numeric = numeric_date_pattern().captures_iter(line).filter_map(|caps| {
+        let number = |name: &str| caps.name(name)?.as_str().parse::<u32>().ok();
+        if let (Some(year), Some(month), Some(day)) = (number("y1"), number("m1"), number("d1")) {
+            return NaiveDate::from_ymd_opt(year as i32, month, day);
+        }
+        let (a, b, year) = (number("a")?, number("b")?, number("y2")?);
+        let year = if year < 100 { 2000 + year } else { year };
+        let (month, day) = match (a, b) {
+            (a, b) if a > 12 => (b, a),
+            (a, b) if b > 12 => (a, b),
+            (a, b) if &caps["sep"] == "/" => (a, b),
+            (a, b) => (b, a),
+        };
+        NaiveDate::from_ymd_opt(year as i32, month, day)
+    });
+    let written = crate::location_timestamp::find_written_dates(line).into_iter().map(|(_, date)| date);
+    numeric.chain(written).collect()
+}
+
+/// The date the bill was written: the first date on a line labeled as a date, unless it is
+/// the due date, else the first date anywhere (a due date last)
+fn find_date(lines: &[&str]) -> Option<NaiveDate> {
+    static DATE_LABEL: OnceLock<Regex> = OnceLock::new();
+    static DUE: OnceLock<Regex> = OnceLock::new();
+    let date_label = DATE_LABEL.get_or_init(|| Regex::new(r"(?i)\b(date|dated|datum|fecha|data)\b").unwrap());
+    let due = DUE.get_or_init(|| Regex::new(r"(?i)\b(due|fällig|payable by|échéance|vencimiento|scadenza)\b").unwrap());
+
+    let dated: Vec<(&str, NaiveDate)> = lines
+        .iter()
+        .flat_map(|line| dates(line).into_iter().map(move |date| (*line, date)))
+        .collect();
+    dated
+        .iter()
+        .find(|(line, _)| date_label.is_match(line) && !due.is_match(line))
+        .or_else(|| dated.iter().find(|(line, _)| !due.is_match(line)))
+        .or_else(|| dated.first())
+        .map(|(_, date)| *date)
+}
+
+/// The vendor: the first line at the top that names something rather than being the
+/// document's title, a label, an address, a date or a number
+fn find_vendor(lines: &[&str]) -> Option<String> {
+    static NOT_NAME: OnceLock<Regex> = OnceLock::new();
+    let not_name = NOT_NAME.get_or_init(|| {
+        Regex::new(r"(?i)(^\d|@|www\.|https?:|\.com\b|\b(tel|phone|fax|page|date|no\.?|number|store|street|st\.|ave|road|rd\.|suite)\b)").unwrap()
+    });
+
+    lines
+        .iter()
+        .take(VENDOR_LINES)
+        .filter(|line| line.chars().filter(|c| c.is_alphabetic()).count() >= 2)
+        .filter(|line| kind_keyword(line).is_none() && total_rank(line).is_none() && !is_bill_label(line))
+        .filter(|line| !not_name.is_match(line) && amounts(line).is_empty() && dates(line).is_empty())
+        .find_map(|line| clean_vendor(line))
+}
+
+/// `ACME CORP, Inc.` → `AcmeCorp`: legal forms dropped, at most four words, each
+/// capitalized and joined so the vendor stays one part of the name
+pub(super) fn clean_vendor(line: &str) -> Option<String> {
+    static LEGAL_FORM: OnceLock<Regex> = OnceLock::new();
+    let legal_form = LEGAL_FORM.get_or_init(|| {
+        Regex::new(r"(?i)[,.]?\s+(inc\.?|incorporated|llc|l\.l\.c\.|ltd\.?|limited|plc|gmbh|ag|s\.?a\.?s?|s\.?r\.?l\.?|b\.?v\.?|co\.?,?\s*ltd\.?)$").unwrap()
+    });
+    let mut vendor = line.trim().to_string();
+    while let Some(found) = legal_form.find(&vendor) {
+        vendor.truncate(found.start());
+    }
+
+    let words: Vec<String> = vendor
+        .split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .take(4)
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+            // Shouted names are capitalized like the rest; mixed case (McDonalds) is kept
+            let rest: String = chars.collect();
+            if word.chars().any(char::is_lowercase) {
+                first + &rest
+            } else {
+                first + &rest.to_lowercase()
+            }
+        })
+        .collect();
+    let vendor = words.concat();
+    (vendor.chars().filter(|c| c.is_alphabetic()).count() >= 2).then_some(vendor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style() -> TimestampStyle {
+        TimestampStyle::default()
+    }
+
+    #[test]
+    fn test_invoice() {
+        let text = "ACME CORP, Inc.\n\
+                    123 Industrial Way\n\
+                    Springfield, IL 62701\n\
+                    INVOICE\n\
+                    Invoice No: 2023-0042\n\
+                    Invoice Date: October 15, 2023\n\
+                    Due Date: November 14, 2023\n\
+                    Bill To: Jane Doe\n\
+                    Widget (2 x 50.00)      100.00\n\
+                    Service fee              30.00\n\
+                    Subtotal                130.00\n\
+                    Tax (9.615%)             12.50\n\
+                    Amount Due             $142.50\n";
+        let fields = parse_receipt(text).unwrap();
+        assert_eq!(fields.kind, ReceiptKind::Invoice);
+        assert_eq!(fields.vendor.as_deref(), Some("AcmeCorp"));
+        assert_eq!(fields.date, NaiveDate::from_ymd_opt(2023, 10, 15));
+        assert_eq!(fields.total, "142.50");
+        assert_eq!(format_receipt_filename(&fields, &style()), "2023-10-15 AcmeCorp invoice 142.50");
+
+        let compact = TimestampStyle {
+            format: Some("%Y%m%d".to_string()),
+            ..TimestampStyle::default()
+        };
+        assert_eq!(receipt_name(text, &compact).as_deref(), Some("20231015 AcmeCorp invoice 142.50"));
+    }
+
+    #[test]
+    fn test_shop_receipt_from_ocr() {
+        // No title, a column of prices, and the total on the line under its label
+        let text = "Joe's Corner Deli\n\
+                    412 Main St.\n\
+                    Tel 555-0199\n\
+                    10/03/2023 12:41\n\
+                    Turkey sandwich   8.95\n\
+                    Coffee            2.50\n\
+                    Cookie            1.75\n\
+                    SUBTOTAL         13.20\n\
+                    TAX               1.16\n\
+                    TOTAL\n\
+                    14.36\n\
+                    CASH             20.00\n\
+                    CHANGE            5.64\n\
+                    Thank you!";
+        let fields = parse_receipt(text).unwrap();
+        assert_eq!(fields.kind, ReceiptKind::Receipt);
+        assert_eq!(fields.vendor.as_deref(), Some("JoesCornerDeli"));
+        // Slashes are read month first
+        assert_eq!(fields.date, NaiveDate::from_ymd_opt(2023, 10, 3));
+        assert_eq!(fields.total, "14.36");
+    }
+
+    #[test]
+    fn test_european_invoice() {
+        let text = "Müller Bürobedarf GmbH\n\
+                    Rechnung Nr. 4711\n\
+                    Datum: 03.10.2023\n\
+                    Papier A4             1.000,00 €\n\
+                    Zwischensumme         1.000,00 €\n\
+                    MwSt 19%                190,00 €\n\
+                    Gesamtbetrag          1.190,00 €\n";
+        let fields = parse_receipt(text).unwrap();
+        assert_eq!(fields.kind, ReceiptKind::Invoice);
+        assert_eq!(fields.vendor.as_deref(), Some("MüllerBürobedarf"));
+        assert_eq!(fields.date, NaiveDate::from_ymd_opt(2023, 10, 3));
+        assert_eq!(fields.total, "1190.00");
+    }
+
+    #[test]
+    fn test_not_a_receipt() {
+        // Prose about invoices, and numbers without a bill around them
+        assert_eq!(parse_receipt("How to write an invoice\nA guide for freelancers"), None);
+        assert_eq!(parse_receipt("Quarterly Report\nRevenue grew 12.50 percent\nCosts fell 3.20 percent"), None);
+        // A bill without a total names nothing
+        assert_eq!(parse_receipt("INVOICE\nAcme Corp\nDate: 2023-10-15"), None);
+    }
+
+    #[test]
+    fn test_amounts_and_dates() {
+        assert_eq!(amounts("Total: $1,234.50"), vec!["1234.50"]);
+        assert_eq!(amounts("1.234,50 EUR and 0.99"), vec!["1234.50", "0.99"]);
+        assert_eq!(amounts("Widget 2 100.00"), vec!["100.00"]);
+        assert_eq!(amounts("100.00 30.00"), vec!["100.00", "30.00"]);
+        assert_eq!(amounts("Tax (9.615%) on 2023.10.15"), Vec::<String>::new());
+        assert_eq!(amounts("Invoice 2023-0042, 15 items"), Vec::<String>::new());
+        assert!(ends_in_amount("Coffee   2.50 EUR"));
+        assert!(!ends_in_amount("2.50 for coffee"));
+        assert_eq!(dates("Date: 2023-10-15"), vec![NaiveDate::from_ymd_opt(2023, 10, 15).unwrap()]);
+        assert_eq!(dates("15/10/2023"), vec![NaiveDate::from_ymd_opt(2023, 10, 15).unwrap()]);
+        assert_eq!(dates("03-04-23"), vec![NaiveDate::from_ymd_opt(2023, 4, 3).unwrap()]);
+        assert_eq!(dates("15 Octubre 2023"), vec![NaiveDate::from_ymd_opt(2023, 10, 15).unwrap()]);
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use super::receipt::{clean_vendor, dates};
+
+/// Lines from the top searched for the bank's name
+const BANK_LINES: usize = 10;
+
+/// Banks and card issuers named on statements without the word "Bank" nearby, matched as
+/// written or in capitals (so `Discover` in a sentence isn't taken for the card issuer)
+const KNOWN_BANKS: &[&str] = &[
+    "JPMorgan Chase", "Chase", "Bank of America", "Wells Fargo", "Citibank", "Capital One",
+    "American Express", "Amex", "U.S. Bank", "PNC", "TD Bank", "USAA",
+    "Navy Federal", "Charles Schwab", "Schwab", "Fidelity", "Barclays", "Barclaycard", "HSBC",
+    "Lloyds", "NatWest", "Santander", "Nationwide", "Monzo", "Revolut", "N26", "ING", "BBVA",
+    "Deutsche Bank", "Commerzbank", "Sparkasse", "Volksbank", "DKB", "BNP Paribas",
+    "Société Générale", "Crédit Agricole", "UBS", "Rabobank", "ABN AMRO", "RBC", "Scotiabank",
+    "BMO", "CIBC", "Commonwealth Bank", "Westpac", "ANZ", "NAB",
+];
+
+/// What kind of account a statement is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Bank,
+    Card,
+}
+
+/// What names a bank or credit card statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementInfo {
+    pub kind: StatementKind,
+    /// Bank or card issuer, e.g. `WellsFargo`
+    pub bank: Option<String>,
+    /// Last day of the period the statement covers (or the statement date); its month names
+    /// the statement
+    pub period_end: NaiveDate,
+}
+
+/// Recognizes the text of a bank or credit card statement: a statement title or balance
+/// lines (`Opening balance`, `New balance`, `Kontoauszug`, ...) next to an account number,
+/// IBAN or card number, and the period it covers
+/// Without a period there is nothing better than the file's dates to name it by, so None
+pub fn parse_statement(text: &str) -> Option<StatementInfo> {
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let (statement, account, balances, card) = markers();
+
+    let titled = lines.iter().any(|line| statement.is_match(line));
+    let has_account = lines.iter().any(|line| account.is_match(line));
+    let balance_lines = lines.iter().filter(|line| balances.is_match(line)).count();
+    let recognized = has_account && ((titled && balance_lines >= 1) || balance_lines >= 2);
+    if !recognized {
+        return None;
+    }
+
+    let kind = match lines.iter().any(|line| card.is_match(line)) {
+        true => StatementKind::Card,
+        false => StatementKind::Bank,
+    };
+    Some(StatementInfo {
+        kind,
+        bank: find_bank(&lines),
+        period_end: find_period_end(&lines)?,
+    })
+}
+
+/// Formats statement info as `WellsFargo statement 2023-09` or `Chase card statement 2023-09`
+/// (sanitized to `WellsFargo_statement_2023-09`): the month the period ends in, however the
+/// file's timestamps are formatted, as statements are monthly
+pub fn format_statement_filename(info: &StatementInfo) -> String {
+    let kind = match info.kind {
+        StatementKind::Bank => "statement",
+        StatementKind::Card => "card statement",
+    };
+    let month = info.period_end.format("%Y-%m").to_string();
+    info.bank
+        .iter()
+        .map(String::as_str)
+        .chain([kind, month.as_str()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The name of `text` if it is a bank or card statement (see `parse_statement`)
+pub(crate) fn statement_name(text: &str) -> Option<String> {
+    let info = parse_statement(text)?;
+    debug!("Text reads as a statement: {:?}", info);
+    Some(format_statement_filename(&info))
+}
+
+/// Statement titles, account numbers, balance lines and credit card terms, in English,
+/// German, French, Spanish, Italian, Portuguese and Dutch
+fn markers() -> (&'static Regex, &'static Regex, &'static Regex, &'static Regex) {
+    static STATEMENT: OnceLock<Regex> = OnceLock::new();
+    static ACCOUNT: OnceLock<Regex> = OnceLock::new();
+    static BALANCES: OnceLock<Regex> = OnceLock::new();
+    static CARD: OnceLock<Regex> = OnceLock::new();
+    let statement = STATEMENT.get_or_init(|| {
+        Regex::new(r"(?i)\b(statement|account summary|kontoauszug|relevé de compte|relevé bancaire|extracto|estratto conto|extrato|rekeningafschrift)\b").unwrap()
+    });
+    let account = ACCOUNT.get_or_init(|| {
+        Regex::new(r"(?i)\b(account (number|no\.?|#)|acct|iban|sort code|routing|card (number|ending|no\.?)|ending in|kontonummer|numéro de compte|número de cuenta|numero di conto|número da conta|rekeningnummer)\b").unwrap()
+    });
+    let balances = BALANCES.get_or_init(|| {
+        Regex::new(r"(?i)\b(opening balance|closing balance|beginning balance|ending balance|previous balance|new balance|balance forward|balance brought forward|minimum payment|payment due date|credit limit|anfangssaldo|endsaldo|alter kontostand|neuer kontostand|solde (initial|final|précédent|nouveau|au)|saldo (inicial|final|anterior|iniziale|finale)|beginsaldo|eindsaldo)\b").unwrap()
+    });
+    let card = CARD.get_or_init(|| {
+        Regex::new(r"(?i)\b(credit card|card statement|card ending|minimum payment|credit limit|kreditkarte|carte de crédit|tarjeta de crédito|carta di credito|cartão de crédito|creditcard)\b").unwrap()
+    });
+    (statement, account, balances, card)
+}
+
+/// The end of the statement period: the later date of a `Statement period: 09/01/2023 -
+/// 09/30/2023` line (or any line with a period or closing date label and two dates), else
+/// the date on a `Statement date` or `Closing date` line, else the date on a period line
+fn find_period_end(lines: &[&str]) -> Option<NaiveDate> {
+    static PERIOD: OnceLock<Regex> = OnceLock::new();
+    static STATEMENT_DATE: OnceLock<Regex> = OnceLock::new();
+    let period = PERIOD.get_or_init(|| {
+        Regex::new(r"(?i)\b(period|from|through|thru|between|zeitraum|période|periodo|período|periode|du|vom|del|de)\b").unwrap()
+    });
+    let statement_date = STATEMENT_DATE.get_or_init(|| {
+        Regex::new(r"(?i)\b(statement date|closing date|as of|as at|auszugsdatum|date du relevé|fecha de(l)? extracto|data estratto)\b").unwrap()
+    });
+
+    let first_date = |label: &Regex| {
+        lines.iter().filter(|line| label.is_match(line)).find_map(|line| dates(line).first().copied())
+    };
+    let range = lines
+        .iter()
+        .filter(|line| period.is_match(line) || statement_date.is_match(line))
+        .map(|line| dates(line))
+        .find(|found| found.len() >= 2)
+        .and_then(|found| found.into_iter().max());
+    range.or_else(|| first_date(statement_date)).or_else(|| first_date(period))
+}
+
+/// The bank: a known bank or card issuer at the top, else the first line there that calls
+/// itself a bank (`First Valley Credit Union`, `Sparkasse Köln`)
+fn find_bank(lines: &[&str]) -> Option<String> {
+    static BANK_WORD: OnceLock<Regex> = OnceLock::new();
+    let bank_word = BANK_WORD.get_or_init(|| {
+        Regex::new(r"(?i)\b(bank|banque|banco|banca|sparkasse|credit union|building society|savings)\b").unwrap()
+    });
+    static KNOWN: OnceLock<Regex> = OnceLock::new();
+    let known = KNOWN.get_or_init(|| {
+        let names: Vec<String> = KNOWN_BANKS
+            .iter()
+            .flat_map(|bank| [regex::escape(bank), regex::escape(&bank.to_uppercase())])
+            .collect();
+        // `\b` would not end a name after `U.S.`, so names are bounded by hand
+        let edge = r"[^\p{L}\p{N}]";
+        Regex::new(&format!("(?:^|{edge})({})(?:{edge}|$)", names.join("|"))).unwrap()
+    });
+    let top = &lines[..lines.len().min(BANK_LINES)];
+
+    let found = top.iter().find_map(|line| Some(known.captures(line)?.get(1)?.as_str()));
+    if let Some(name) = found {
+        let bank = KNOWN_BANKS.iter().find(|bank| bank.to_uppercase() == name.to_uppercase())?;
+        return clean_vendor(bank);
+    }
+    top.iter()
+        .filter(|line| bank_word.is_match(line) && line.split_whitespace().count() <= 5)
+        .filter(|line| dates(line).is_empty() && !line.contains(|c: char| c.is_ascii_digit()))
+        .find_map(|line| clean_vendor(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bank_statement() {
+        let text = "WELLS FARGO\n\
+                    Everyday Checking\n\
+                    Account number: 1234567890\n\
+                    Statement period: 09/01/2023 - 09/30/2023\n\
+                    Beginning balance on 9/1        $1,204.33\n\
+                    Deposits and other additions      3,100.00\n\
+                    Ending balance on 9/30           $2,011.87\n";
+        let info = parse_statement(text).unwrap();
+        assert_eq!(info.kind, StatementKind::Bank);
+        assert_eq!(info.bank.as_deref(), Some("WellsFargo"));
+        assert_eq!(info.period_end, NaiveDate::from_ymd_opt(2023, 9, 30).unwrap());
+        assert_eq!(format_statement_filename(&info), "WellsFargo statement 2023-09");
+    }
+
+    #[test]
+    fn test_card_statement_names_the_closing_month() {
+        // Billing cycles straddle months; the statement is named after the month it closes in
+        let text = "Chase Freedom Unlimited\n\
+                    Card ending in 4321\n\
+                    Opening/Closing Date 08/15/23 - 09/14/23\n\
+                    Previous Balance $812.40\n\
+                    New Balance $640.12\n\
+                    Minimum Payment Due $35.00\n\
+                    Credit Limit $10,000\n";
+        let info = parse_statement(text).unwrap();
+        assert_eq!(info.kind, StatementKind::Card);
+        assert_eq!(info.bank.as_deref(), Some("Chase"));
+        assert_eq!(statement_name(text).as_deref(), Some("Chase card statement 2023-09"));
+    }
+
+    #[test]
+    fn test_german_statement() {
+        let text = "Sparkasse Köln Bonn\n\
+                    Kontoauszug 9/2023\n\
+                    IBAN DE89 3705 0198 0000 1234 56\n\
+                    Zeitraum vom 01.09.2023 bis 30.09.2023\n\
+                    Alter Kontostand 1.204,33 EUR\n\
+                    Neuer Kontostand 2.011,87 EUR\n";
+        let info = parse_statement(text).unwrap();
+        assert_eq!(info.bank.as_deref(), Some("Sparkasse"));
+        assert_eq!(info.period_end, NaiveDate::from_ymd_opt(2023, 9, 30).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_bank_and_statement_date() {
+        let text = "First Valley Credit Union\n\
+                    Member Statement\n\
+                    Statement Date: October 1, 2023\n\
+                    Account No. 000-1234\n\
+                    Closing balance 512.00\n";
+        let info = parse_statement(text).unwrap();
+        assert_eq!(info.bank.as_deref(), Some("FirstValleyCreditUnion"));
+        assert_eq!(format_statement_filename(&info), "FirstValleyCreditUnion statement 2023-10");
+    }
+
+    #[test]
+    fn test_not_a_statement() {
+        // Statements mentioned, but no account or balances
+        assert_eq!(parse_statement("Mission statement\nOur values, 2023-09-01 to 2023-09-30"), None);
+        // An invoice has totals, not balances
+        assert_eq!(parse_statement("INVOICE\nAccount number: 42\nDate: 2023-10-15\nTotal 142.50"), None);
+        // No period to name it by
+        assert_eq!(parse_statement("Statement\nAccount number: 42\nOpening balance 10.00"), None);
+    }
+}
@@ -1,58 +1,70 @@
 use regex::Regex;
 use std::collections::HashSet;
 use std::ffi::OsStr;
+use std::path::Path;
 use crate::extractor::FileMetadata;
+use crate::{LocationPrecision, TimestampPosition, TimestampStyle};
 
 /// Generates a sanitized filename from a candidate name
-#[allow(dead_code)]
-pub fn generate_filename(
+#[cfg(test)]
+fn generate_filename(
     candidate: &str,
     original_extension: Option<&OsStr>,
     existing_names: &mut HashSet<String>,
 ) -> String {
-    generate_filename_with_metadata(candidate, original_extension, existing_names, None)
+    generate_filename_with_metadata(candidate, original_extension, existing_names, None).0
 }
 
 /// Generates a sanitized filename from a candidate name with optional metadata enhancements
+/// Also returns the counter appended to make the name unique, if one was needed
+/// With a template containing `/`, the name includes subfolders (always `/`-separated)
 pub fn generate_filename_with_metadata(
     candidate: &str,
     original_extension: Option<&OsStr>,
     existing_names: &mut HashSet<String>,
     metadata: Option<&FileMetadata>,
-) -> String {
-    // Sanitize the candidate name
-    let mut sanitized = sanitize_filename(candidate);
+) -> (String, Option<u32>) {
+    let extension_str = original_extension.and_then(|e| e.to_str());
+    let template = metadata.and_then(|meta| Some((meta, meta.template.as_deref()?)));
+
+    // Dates written out in the name ("15 Octubre 2023") take the configured timestamp format
+    let default_timestamp_style = TimestampStyle::default();
+    let timestamp_style = metadata.map_or(&default_timestamp_style, |meta| &meta.timestamp_style);
+    let candidate = &crate::location_timestamp::normalize_written_dates(candidate, timestamp_style);
+
+    // Sanitize the candidate name, expanding the template around it if one is set
+    let (folders, mut sanitized) = match template {
+        Some((meta, template)) => {
+            // The extension is always kept; a trailing ".{ext}" just spells that out
+            let template = template.strip_suffix(".{ext}").unwrap_or(template);
+            let rendered = crate::template::render(template, |variable| {
+                template_value(variable, candidate, meta, extension_str)
+            });
+            split_folders(&rendered)
+        }
+        None => (String::new(), sanitize_filename(candidate)),
+    };
 
-    // Add location and timestamp if enabled in config and available in metadata
+    // Add location and timestamp if enabled in config and available in metadata,
+    // unless the template already places them
     if let Some(meta) = metadata {
+        let template = meta.template.as_deref().unwrap_or_default();
         let mut additions: Vec<String> = Vec::new();
 
         // Add GPS location if enabled and available
-        if meta.include_location {
-            if let Some(location) = &meta.gps_location {
-                // Try geocoding first (enabled by default)
-                // This will convert GPS to city names like "Seattle_WA"
-                let location_str = if meta.geocode_enabled.unwrap_or(true) {
-                    // Try to reverse geocode to get city/state name
-                    crate::geocoding::reverse_geocode(location.latitude, location.longitude)
-                        .unwrap_or_else(|| {
-                            // Fall back to coordinates if geocoding fails
-                            crate::location_timestamp::format_location(location)
-                        })
-                } else {
-                    // User disabled geocoding, use coordinates format
-                    crate::location_timestamp::format_location(location)
-                };
+        if !crate::template::uses(template, &["location"]) {
+            if let Some(location_str) = location_name(meta) {
                 additions.push(location_str);
             }
         }
 
-        // Add timestamp if enabled and available (use date_time_original or creation_date)
-        if meta.include_timestamp {
-            if let Some(timestamp) = meta.date_time_original.as_ref().or(meta.creation_date.as_ref()) {
-                // Format timestamp to YYYY-MM-DD for filename
-                if let Some(formatted) = format_timestamp_for_filename(timestamp) {
-                    additions.push(formatted);
+        // Add timestamp if enabled and available (use date_time_original or creation_date),
+        // unless the name already carries that date
+        if meta.include_timestamp && !crate::template::uses(template, crate::template::DATE_VARIABLES) {
+            if let Some(formatted) = timestamp(meta).filter(|t| !sanitized.contains(&sanitize_filename(t))) {
+                match meta.timestamp_style.position {
+                    TimestampPosition::Suffix => additions.push(formatted),
+                    TimestampPosition::Prefix => sanitized = format!("{}_{}", formatted, sanitized),
                 }
             }
         }
@@ -64,45 +76,310 @@ pub fn generate_filename_with_metadata(
         }
     }
 
-    // Limit length to 200 characters to leave room for extension and counter
-    let mut base_name = sanitized.chars().take(200).collect::<String>();
+    // Case and join the words as configured, now that every part of the name is in place
+    let style = metadata.map(|meta| meta.name_style).unwrap_or_default();
+    let sanitized = style.apply(&sanitized);
+
+    // Add extension if present, lower-cased when configured
+    let extension = extension_str
+        .map(|e| format!(".{}", style.extension(e)))
+        .unwrap_or_default();
+
+    // Shorten to what the filesystem and the folder's path length allow, leaving room for
+    // the extension and a counter, then make sure Windows accepts the result
+    let folder = metadata.and_then(|meta| meta.folder.as_deref());
+    let limits = NameLimits::new(folder, &folders, &extension);
+    let mut base_name = windows_safe(&limits.truncate(&sanitized));
 
     // If empty after sanitization, use a default
     if base_name.is_empty() {
-        base_name = "renamed_file".to_string();
+        base_name = style.apply("renamed_file");
     }
 
-    // Add extension if present
-    let extension = original_extension
-        .and_then(|e| e.to_str())
-        .map(|e| format!(".{}", e))
-        .unwrap_or_default();
-
     // Generate unique filename
-    let mut filename = format!("{}{}", base_name, extension);
+    let mut filename = format!("{}{}{}", folders, base_name, extension);
+    let mut suffix = None;
     let mut counter = 1;
 
     while existing_names.contains(&filename) {
-        filename = format!("{}_{}{}", base_name, counter, extension);
+        filename = format!("{}{}{}{}{}", folders, base_name, style.separator(), counter, extension);
+        suffix = Some(counter);
         counter += 1;
     }
 
     existing_names.insert(filename.clone());
-    filename
-}
-
-/// Formats a timestamp string for use in filename (YYYY-MM-DD format)
-fn format_timestamp_for_filename(timestamp: &str) -> Option<String> {
-    // Try to extract date in YYYY:MM:DD format from EXIF timestamp
-    // Format is typically "YYYY:MM:DD HH:MM:SS"
-    if let Some(date_part) = timestamp.split_whitespace().next() {
-        // Convert colons to dashes for filename compatibility
-        let formatted = date_part.replace(':', "-");
-        if formatted.len() == 10 && formatted.chars().filter(|c| *c == '-').count() == 2 {
-            return Some(formatted);
+    (filename, suffix)
+}
+
+/// Expands a folder template such as `{yyyy}/{mm}/{location}` into sanitized,
+/// `/`-separated folders; empty folders (from variables without a value) are dropped
+pub(crate) fn render_folders(
+    template: &str,
+    candidate: &str,
+    metadata: &FileMetadata,
+    original_extension: Option<&OsStr>,
+) -> String {
+    let extension = original_extension.and_then(|e| e.to_str());
+    let rendered = crate::template::render(template, |variable| {
+        template_value(variable, candidate, metadata, extension)
+    });
+    // Every component is a folder, so split as if a file name followed
+    let (folders, _) = split_folders(&format!("{}/name", rendered));
+    folders.trim_end_matches('/').to_string()
+}
+
+/// Value of a template variable for this file (see `TEMPLATE_VARIABLES`)
+/// Path separators in the value are replaced, so only the template's own `/` make folders
+fn template_value(
+    variable: &str,
+    candidate: &str,
+    meta: &FileMetadata,
+    extension: Option<&str>,
+) -> Option<String> {
+    let date = || iso_date(meta);
+    let value = match variable {
+        "name" => Some(candidate.to_string()),
+        "title" => meta.title.clone().or_else(|| Some(candidate.to_string())),
+        "camera" => meta.camera.clone(),
+        "lens" => meta.lens.clone(),
+        "artist" => meta.artist.clone(),
+        "show" => meta.show.clone(),
+        "date" => timestamp(meta),
+        "yyyy" => date().and_then(|d| d.split('-').next().map(str::to_string)),
+        "mm" => date().and_then(|d| d.split('-').nth(1).map(str::to_string)),
+        "dd" => date().and_then(|d| d.split('-').nth(2).map(str::to_string)),
+        "location" => location_name(meta),
+        "category" => meta.category.as_ref().map(|c| c.name().to_string()),
+        "ext" => extension.map(str::to_string),
+        _ => None,
+    };
+    value.map(|value| value.replace(['/', '\\'], "_"))
+}
+
+/// Capture or creation date as YYYY-MM-DD
+fn iso_date(meta: &FileMetadata) -> Option<String> {
+    TimestampStyle::default().render(meta.date_time_original.as_ref().or(meta.creation_date.as_ref())?)
+}
+
+/// Capture or creation date in the configured timestamp style
+fn timestamp(meta: &FileMetadata) -> Option<String> {
+    meta.timestamp_style
+        .render(meta.date_time_original.as_ref().or(meta.creation_date.as_ref())?)
+}
+
+/// City name (or coordinates) for the file's GPS position, if location is enabled,
+/// named no more precisely than `location_precision` and never inside a blocked area
+fn location_name(meta: &FileMetadata) -> Option<String> {
+    if !meta.include_location {
+        return None;
+    }
+    let location = meta.gps_location.as_ref()?;
+    if meta.location_blocklist.iter().any(|area| area.contains(location)) {
+        return None;
+    }
+
+    match meta.location_precision {
+        LocationPrecision::Coordinates => return Some(crate::location_timestamp::format_location(location)),
+        // Never fall back to something more precise than asked for
+        precision @ (LocationPrecision::Country | LocationPrecision::Region) => {
+            let geocoder = meta.geocoder.as_ref()?;
+            return crate::profiling::time(crate::profiling::Stage::Geocode, || {
+                geocoder.reverse_geocode_at(location.latitude, location.longitude, precision)
+            });
         }
+        LocationPrecision::City => {}
+    }
+
+    // Try geocoding first (enabled by default)
+    // This will convert GPS to city names like "Seattle_WA"
+    if let Some(geocoder) = &meta.geocoder {
+        let geocoded = crate::profiling::time(crate::profiling::Stage::Geocode, || {
+            geocoder.reverse_geocode(location.latitude, location.longitude)
+        });
+        // Fall back to coordinates if geocoding fails
+        return Some(geocoded.unwrap_or_else(|| crate::location_timestamp::format_location(location)));
     }
-    None
+
+    // User disabled geocoding, use coordinates format
+    Some(crate::location_timestamp::format_location(location))
+}
+
+/// Extensions made of two parts that stay together, so `backup.tar.gz` keeps its `.tar`
+const COMPOUND_EXTENSIONS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz", "tar.lzma", "tar.z", "nii.gz", "warc.gz",
+];
+
+/// Splits a file name into its stem and extension, keeping both parts of a compound
+/// extension (`backup.tar.gz` → `backup` and `tar.gz`, matched ignoring case)
+/// Like `Path::extension`, a leading dot (`.bashrc`) doesn't start an extension
+pub(crate) fn split_extension(name: &str) -> (&str, Option<&str>) {
+    let lower = name.to_ascii_lowercase();
+    let compound = COMPOUND_EXTENSIONS
+        .iter()
+        .find(|extension| lower.len() > extension.len() + 1 && lower.ends_with(&format!(".{}", extension)));
+    let dot = match (compound, name.rfind('.')) {
+        (Some(extension), _) => name.len() - extension.len() - 1,
+        (None, Some(dot)) if dot > 0 => dot,
+        _ => return (name, None),
+    };
+    (&name[..dot], Some(&name[dot + 1..]))
+}
+
+/// A file's extension, with both parts of a compound one (see `split_extension`)
+pub(crate) fn full_extension(path: &Path) -> Option<&str> {
+    split_extension(path.file_name()?.to_str()?).1
+}
+
+/// Device names Windows reserves in every folder, with or without an extension
+/// (Windows 11 also reserves the superscript-digit ports)
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM0", "COM1", "COM2", "COM3", "COM4",
+    "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// Longest file name ext4, APFS and NTFS accept (bytes on the first two, UTF-16 units on NTFS)
+const MAX_NAME_LEN: usize = 255;
+
+/// Longest path Windows programs handle without the `\\?\` prefix (`MAX_PATH` less the NUL)
+/// Other systems only limit each name
+#[cfg(windows)]
+const MAX_PATH_LEN: Option<usize> = Some(259);
+#[cfg(not(windows))]
+const MAX_PATH_LEN: Option<usize> = None;
+
+/// Generated names are kept to this many characters even when the limits allow more
+const MAX_BASE_CHARS: usize = 200;
+
+/// Room kept for a collision counter such as `_12`
+const COUNTER_ROOM: usize = 4;
+
+/// Names aren't shortened below this to fit a deep folder; the rename then relies on the
+/// `\\?\` prefix Rust adds for long paths on Windows instead
+const MIN_BASE_CHARS: usize = 16;
+
+/// Whether Windows reserves `name` (the part before the first dot, ignoring trailing spaces)
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Makes a sanitized name acceptable to Windows: trailing dots and spaces (which Windows
+/// silently drops) are removed, and reserved device names get a `_` after their stem
+fn windows_safe(name: &str) -> String {
+    let mut safe = name.trim_end_matches(['.', ' ']).to_string();
+    if is_reserved(&safe) {
+        let stem_len = safe.split('.').next().unwrap_or_default().len();
+        safe.insert(stem_len, '_');
+    }
+    safe
+}
+
+/// How long a base name may be, in characters, UTF-8 bytes (Linux and macOS name limits)
+/// and UTF-16 units (Windows path limit)
+struct NameLimits {
+    chars: usize,
+    bytes: usize,
+    utf16: usize,
+}
+
+impl NameLimits {
+    /// Limits for a name placed in `folder` (if known) under template `subfolders`, with `extension`
+    /// A folder already given as a `\\?\` path has no path length limit
+    fn new(folder: Option<&Path>, subfolders: &str, extension: &str) -> Self {
+        let reserved = extension.len() + COUNTER_ROOM;
+        let utf16 = MAX_PATH_LEN
+            .zip(folder.and_then(|folder| folder.to_str()))
+            .filter(|(_, folder)| !folder.starts_with(r"\\?\"))
+            .map(|(max_path_len, folder)| {
+                let used = utf16_len(folder) + 1 + utf16_len(subfolders) + utf16_len(extension) + COUNTER_ROOM;
+                max_path_len.saturating_sub(used).max(MIN_BASE_CHARS)
+            })
+            .unwrap_or(usize::MAX);
+        Self {
+            chars: MAX_BASE_CHARS,
+            bytes: MAX_NAME_LEN.saturating_sub(reserved),
+            utf16: utf16.min(MAX_NAME_LEN.saturating_sub(reserved)),
+        }
+    }
+
+    /// The longest prefix of `name` within every limit that doesn't part a letter from its
+    /// accents or vowel points
+    fn truncate(&self, name: &str) -> String {
+        let (mut bytes, mut utf16) = (0, 0);
+        let cut: String = name
+            .chars()
+            .take(self.chars)
+            .take_while(|c| {
+                bytes += c.len_utf8();
+                utf16 += c.len_utf16();
+                bytes <= self.bytes && utf16 <= self.utf16
+            })
+            .collect();
+        crate::script::keep_marks_with_letters(&cut, &name[cut.len()..]).to_string()
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// A name typed by the user for the file at `original`, checked to be usable on every platform
+/// Keeps the original extension when the user leaves it off; the error says what is wrong
+pub fn check_filename(input: &str, original: &Path) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("A name can't be empty".to_string());
+    }
+    if input.contains(['/', '\\']) {
+        return Err("A name can't contain / or \\ (files stay in their folder)".to_string());
+    }
+    if input.chars().any(|c| c.is_control() || matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|')) {
+        return Err("A name can't contain : * ? \" < > | or control characters".to_string());
+    }
+    // Windows drops a trailing dot, and "." / ".." aren't names at all
+    if input.ends_with('.') {
+        return Err("A name can't end with a dot".to_string());
+    }
+    if is_reserved(input) {
+        let stem = input.split('.').next().unwrap_or_default().trim_end();
+        return Err(format!("{} is a reserved name on Windows", stem));
+    }
+
+    // A dot in the name ("Invoice 2023.10", "Report v1.2") only replaces the extension when
+    // what follows it is the same format, like `.jpeg` for a `.jpg`
+    let name = match full_extension(original) {
+        Some(ext) if !split_extension(input).1.is_some_and(|typed| crate::detector::same_format(typed, ext)) => {
+            format!("{}.{}", input, ext)
+        }
+        _ => input.to_string(),
+    };
+    if name.len() > MAX_NAME_LEN || utf16_len(&name) > MAX_NAME_LEN {
+        return Err(format!("A name can't be longer than {} bytes", MAX_NAME_LEN));
+    }
+    Ok(name)
+}
+
+/// Splits a rendered template into sanitized folders (each followed by `/`) and the file name
+/// Empty folders (from variables without a value) are dropped
+fn split_folders(rendered: &str) -> (String, String) {
+    let mut components: Vec<String> = rendered
+        .split(['/', '\\'])
+        .map(sanitize_component)
+        .filter(|component| !component.is_empty())
+        .collect();
+    let name = components.pop().unwrap_or_default();
+    let folders = components
+        .iter()
+        .map(|folder| format!("{}/", folder.chars().take(100).collect::<String>()))
+        .collect();
+    (folders, name)
+}
+
+/// One path component that is safe on every platform
+pub(crate) fn sanitize_component(component: &str) -> String {
+    // "." and ".." lose their dots, so they aren't names at all
+    windows_safe(&sanitize_filename(component))
 }
 
 /// Sanitizes a filename by removing or replacing invalid characters
@@ -111,11 +388,19 @@ fn sanitize_filename(name: &str) -> String {
     let re = Regex::new(r#"[/\\:*?"<>|()\[\]]"#).unwrap();
     let mut sanitized = re.replace_all(name, "_").to_string();
 
-    // Replace spaces with underscores
-    sanitized = sanitized.replace(' ', "_");
+    // Replace spaces with underscores, as well as Chinese and Japanese punctuation: without
+    // spaces it is all that separates their words
+    sanitized = sanitized
+        .chars()
+        .map(|c| if c == ' ' || crate::script::is_cjk_separator(c) { '_' } else { c })
+        .collect();
 
-    // Remove control characters
-    sanitized = sanitized.chars().filter(|c| !c.is_control()).collect();
+    // Remove control characters, and the invisible bidi and justification marks of
+    // right-to-left text
+    sanitized = sanitized
+        .chars()
+        .filter(|c| !c.is_control() && !crate::script::is_layout_mark(*c))
+        .collect();
 
     // Collapse multiple underscores into one
     let re_multiple = Regex::new(r"_{2,}").unwrap();
@@ -128,6 +413,8 @@ fn sanitize_filename(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::detector::FileCategory;
+    use std::path::PathBuf;
 
     #[test]
     fn test_sanitize_filename() {
@@ -137,6 +424,19 @@ mod tests {
         assert_eq!(sanitize_filename("a/b\\c:d"), "a_b_c_d");
     }
 
+    #[test]
+    fn test_sanitize_multilingual_filenames() {
+        // Chinese and Japanese punctuation separates words; letters like 々 and ー stay
+        assert_eq!(sanitize_filename("東京タワー「夜景」、人々"), "東京タワー_夜景_人々");
+        assert_eq!(sanitize_filename("会议纪要\u{3000}第三季度"), "会议纪要_第三季度");
+        // Arabic keeps its letters and harakat, but loses the tatweel and bidi marks
+        assert_eq!(sanitize_filename("\u{200F}التقريـــر السنوي"), "التقرير_السنوي");
+        assert_eq!(sanitize_filename("مُحَمَّد"), "مُحَمَّد");
+        // An override can't make the name display as something else
+        assert_eq!(sanitize_filename("invoice\u{202E}fdp.exe"), "invoicefdp.exe");
+        assert_eq!(sanitize_filename("שָׁלוֹם עולם"), "שָׁלוֹם_עולם");
+    }
+
     #[test]
     fn test_generate_filename_unique() {
         let mut existing = HashSet::new();
@@ -150,4 +450,406 @@ mod tests {
         let name3 = generate_filename("test", Some(OsStr::new("txt")), &mut existing);
         assert_eq!(name3, "test_2.txt");
     }
+
+    #[test]
+    fn test_generate_filename_reports_collision_suffix() {
+        let mut existing = HashSet::new();
+
+        let first = generate_filename_with_metadata("test", Some(OsStr::new("txt")), &mut existing, None);
+        assert_eq!(first, ("test.txt".to_string(), None));
+
+        let second = generate_filename_with_metadata("test", Some(OsStr::new("txt")), &mut existing, None);
+        assert_eq!(second, ("test_1.txt".to_string(), Some(1)));
+    }
+
+    #[test]
+    fn test_generate_filename_with_template() {
+        let metadata = FileMetadata {
+            title: None,
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: None,
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: false,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: false,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: Some("Nikon_Z6".to_string()),
+            lens: None,
+            template: Some("{camera}_{name}_{lens}".to_string()),
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: None,
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let mut existing = HashSet::new();
+
+        let (name, _) = generate_filename_with_metadata(
+            "Sunset Beach",
+            Some(OsStr::new("jpg")),
+            &mut existing,
+            Some(&metadata),
+        );
+        // Empty variables leave no stray separators
+        assert_eq!(name, "Nikon_Z6_Sunset_Beach.jpg");
+    }
+
+    #[test]
+    fn test_generate_filename_with_folder_template() {
+        let metadata = FileMetadata {
+            title: Some("Quarterly: Results".to_string()),
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: Some("2024:03:15 10:30:00".to_string()),
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: true,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: true,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: Some("{category}/{yyyy}/{show}/{title}_{date}.{ext}".to_string()),
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: Some(FileCategory::Document),
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let mut existing = HashSet::new();
+        let mut generate = |candidate| {
+            generate_filename_with_metadata(candidate, Some(OsStr::new("pdf")), &mut existing, Some(&metadata))
+        };
+
+        // The date isn't appended twice, the empty {show} folder is dropped,
+        // and the title is sanitized
+        assert_eq!(generate("Report").0, "document/2024/Quarterly_Results_2024-03-15.pdf");
+        assert_eq!(
+            generate("Report"),
+            ("document/2024/Quarterly_Results_2024-03-15_1.pdf".to_string(), Some(1))
+        );
+    }
+
+    #[test]
+    fn test_template_values_never_make_folders() {
+        let metadata = FileMetadata {
+            title: Some("1/2 Live".to_string()),
+            artist: Some("AC/DC".to_string()),
+            album: None,
+            show: Some("..\\..".to_string()),
+            date_time_original: None,
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: false,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: false,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: Some("{artist}/{title}".to_string()),
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: None,
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let mut existing = HashSet::new();
+        let (name, _) =
+            generate_filename_with_metadata("Song", Some(OsStr::new("mp3")), &mut existing, Some(&metadata));
+        assert_eq!(name, "AC_DC/1_2_Live.mp3");
+
+        // Organize folders too, including a value that tries to leave the destination
+        assert_eq!(render_folders("{artist}/{show}", "Song", &metadata, None), "AC_DC/.._");
+    }
+
+    #[test]
+    fn test_timestamp_styles_in_names() {
+        let metadata = |format: Option<&str>, precision, position| FileMetadata {
+            title: None,
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: Some("2024:03:15 10:30:00".to_string()),
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: false,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: true,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: None,
+            name_style: crate::NameStyle::default(),
+            timestamp_style: TimestampStyle {
+                format: format.map(str::to_string),
+                precision,
+                position,
+            },
+            category: None,
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let generate = |metadata: FileMetadata| {
+            generate_filename_with_metadata("Report", Some(OsStr::new("pdf")), &mut HashSet::new(), Some(&metadata)).0
+        };
+        use crate::TimestampPrecision::{Date, DateTime};
+
+        assert_eq!(generate(metadata(None, Date, TimestampPosition::Suffix)), "Report_2024-03-15.pdf");
+        assert_eq!(
+            generate(metadata(None, DateTime, TimestampPosition::Prefix)),
+            "2024-03-15_103000_Report.pdf"
+        );
+        assert_eq!(
+            generate(metadata(Some("%Y%m%d"), DateTime, TimestampPosition::Prefix)),
+            "20240315_Report.pdf"
+        );
+        // {yyyy} and friends stay numeric whatever the format
+        let templated = FileMetadata {
+            template: Some("{yyyy}/{date}_{name}".to_string()),
+            ..metadata(Some("%d.%m.%Y"), Date, TimestampPosition::Suffix)
+        };
+        assert_eq!(generate(templated), "2024/15.03.2024_Report.pdf");
+
+        // Dates written in the name take the same format, and aren't added twice
+        let dated = |candidate| {
+            let metadata = metadata(None, Date, TimestampPosition::Suffix);
+            generate_filename_with_metadata(candidate, Some(OsStr::new("pdf")), &mut HashSet::new(), Some(&metadata)).0
+        };
+        assert_eq!(dated("Factura 15 de marzo de 2024"), "Factura_2024-03-15.pdf");
+        assert_eq!(dated("Rechnung 3. Februar 2024"), "Rechnung_2024-02-03_2024-03-15.pdf");
+    }
+
+    #[test]
+    fn test_location_precision_and_blocklist() {
+        #[derive(Debug)]
+        struct Seattle;
+        impl crate::geocoding::GeocodeBackend for Seattle {
+            fn reverse_geocode(&self, _: f64, _: f64) -> Option<String> {
+                Some("Seattle_WA".to_string())
+            }
+        }
+
+        let metadata = |precision, blocklist: &[&str], geocoder: bool| FileMetadata {
+            title: None,
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: None,
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: Some(crate::LocationData { latitude: 47.6062, longitude: -122.3321 }),
+            geocoder: geocoder.then(|| std::sync::Arc::new(Seattle) as _),
+            include_location: true,
+            location_precision: precision,
+            location_blocklist: blocklist.iter().map(|area| area.parse().unwrap()).collect(),
+            include_timestamp: false,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: None,
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: None,
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let generate = |metadata: FileMetadata| {
+            generate_filename_with_metadata("Beach", Some(OsStr::new("jpg")), &mut HashSet::new(), Some(&metadata)).0
+        };
+
+        assert_eq!(generate(metadata(LocationPrecision::City, &[], true)), "Beach_Seattle_WA.jpg");
+        assert!(generate(metadata(LocationPrecision::Coordinates, &[], true)).starts_with("Beach_47.61N_122.33"));
+        // A backend that only knows cities names nothing coarser, and nothing falls back to coordinates
+        assert_eq!(generate(metadata(LocationPrecision::Region, &[], true)), "Beach.jpg");
+        assert_eq!(generate(metadata(LocationPrecision::Country, &[], false)), "Beach.jpg");
+
+        // Within 2 km of home: no location at all, whatever the precision
+        assert_eq!(generate(metadata(LocationPrecision::City, &["47.61,-122.33,2km"], true)), "Beach.jpg");
+        assert_eq!(generate(metadata(LocationPrecision::Coordinates, &["47.61,-122.33,2km"], false)), "Beach.jpg");
+        assert_eq!(generate(metadata(LocationPrecision::City, &["48.85,2.35,50km"], true)), "Beach_Seattle_WA.jpg");
+    }
+
+    #[test]
+    fn test_split_folders_is_filesystem_safe() {
+        assert_eq!(split_folders("a/../b"), ("a/".to_string(), "b".to_string()));
+        assert_eq!(split_folders("CON/aux.txt"), ("CON_/".to_string(), "aux_.txt".to_string()));
+        assert_eq!(split_folders("notes.//x*y"), ("notes/".to_string(), "x_y".to_string()));
+    }
+
+    #[test]
+    fn test_reserved_names_and_trailing_dots() {
+        let mut existing = HashSet::new();
+        let mut generate = |candidate, extension: Option<&str>| {
+            generate_filename(candidate, extension.map(OsStr::new), &mut existing)
+        };
+        assert_eq!(generate("CON", Some("txt")), "CON_.txt");
+        assert_eq!(generate("nul", None), "nul_");
+        assert_eq!(generate("com¹", Some("log")), "com¹_.log");
+        assert_eq!(generate("CONOUT$", Some("txt")), "CONOUT$_.txt");
+        assert_eq!(generate("Console", Some("txt")), "Console.txt");
+        // Windows would drop trailing dots, so they never reach the name
+        assert_eq!(generate("Notes..", None), "Notes");
+        assert_eq!(generate("Minutes...", Some("pdf")), "Minutes.pdf");
+        assert_eq!(generate("a\u{7}b:c", Some("txt")), "ab_c.txt");
+    }
+
+    #[test]
+    fn test_names_fit_filesystem_limits() {
+        let mut existing = HashSet::new();
+        let (name, _) = generate_filename_with_metadata(&"a".repeat(300), Some(OsStr::new("txt")), &mut existing, None);
+        assert_eq!(name.len(), MAX_BASE_CHARS + 4);
+
+        // 3 bytes per character: 200 would be 600 bytes, over the 255-byte name limit
+        let (name, _) = generate_filename_with_metadata(&"雪".repeat(200), Some(OsStr::new("jpg")), &mut existing, None);
+        assert!(name.len() + COUNTER_ROOM <= MAX_NAME_LEN, "{}", name.len());
+        assert!(name.ends_with("雪.jpg"));
+
+        // A letter is never cut off from its vowel points
+        let pointed = "בְּ";
+        let (name, _) = generate_filename_with_metadata(&pointed.repeat(100), Some(OsStr::new("txt")), &mut existing, None);
+        let stem = name.strip_suffix(".txt").unwrap();
+        assert_eq!(stem.len() % pointed.len(), 0, "{}", name);
+        assert!(stem.ends_with(pointed));
+    }
+
+    #[test]
+    fn test_names_fit_windows_max_path() {
+        let deep = if cfg!(windows) {
+            format!(r"C:\Users\me\{}", "Projects\\".repeat(20))
+        } else {
+            format!("/home/me/{}", "Projects/".repeat(20))
+        };
+        let metadata = |folder: &str| FileMetadata {
+            title: None,
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: None,
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: false,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: false,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: None,
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: None,
+            folder: Some(PathBuf::from(folder)),
+            page_count: None,
+            duration: None,
+            shot: None,
+        };
+        let candidate = "Quarterly_Results_".repeat(10);
+        let generate = |folder: &str| {
+            generate_filename_with_metadata(&candidate, Some(OsStr::new("pdf")), &mut HashSet::new(), Some(&metadata(folder))).0
+        };
+
+        let name = generate(&deep);
+        assert!(name.ends_with(".pdf"));
+        let Some(max_path_len) = MAX_PATH_LEN else {
+            // Only the name is limited
+            assert_eq!(name.len(), candidate.len() - 1 + 4);
+            return;
+        };
+        assert_eq!(utf16_len(&deep) + 1 + utf16_len(&name) + COUNTER_ROOM, max_path_len);
+
+        // Too deep for any sensible name: keep a minimum and rely on the long-path prefix
+        let deeper = deep.repeat(2);
+        assert_eq!(generate(&deeper).len(), MIN_BASE_CHARS + 4);
+        // Folders given with the prefix have no path limit at all
+        assert_eq!(generate(&format!(r"\\?\{}", deep)).len(), candidate.len() - 1 + 4);
+    }
+
+    #[test]
+    fn test_compound_extensions() {
+        assert_eq!(split_extension("backup.tar.gz"), ("backup", Some("tar.gz")));
+        assert_eq!(split_extension("Scan.NII.GZ"), ("Scan", Some("NII.GZ")));
+        assert_eq!(split_extension("notes.v2.txt"), ("notes.v2", Some("txt")));
+        assert_eq!(split_extension("report.gz"), ("report", Some("gz")));
+        assert_eq!(split_extension(".tar.gz"), (".tar", Some("gz")));
+        assert_eq!(split_extension(".bashrc"), (".bashrc", None));
+        assert_eq!(split_extension("README"), ("README", None));
+        assert_eq!(full_extension(Path::new("/backups/site.tar.bz2")), Some("tar.bz2"));
+
+        let mut existing = HashSet::new();
+        let extension = full_extension(Path::new("website.tar.gz")).map(OsStr::new);
+        assert_eq!(generate_filename("Website Backup", extension, &mut existing), "Website_Backup.tar.gz");
+        assert_eq!(generate_filename("Website Backup", extension, &mut existing), "Website_Backup_1.tar.gz");
+        assert_eq!(check_filename("Old Site", Path::new("website.tar.gz")).unwrap(), "Old Site.tar.gz");
+    }
+
+    #[test]
+    fn test_check_filename() {
+        let original = Path::new("/photos/IMG_1.jpg");
+        assert_eq!(check_filename(" Beach Day ", original).unwrap(), "Beach Day.jpg");
+        assert_eq!(check_filename("Beach.jpeg", original).unwrap(), "Beach.jpeg");
+        assert_eq!(check_filename("Beach.JPG", original).unwrap(), "Beach.JPG");
+        let scan = Path::new("/docs/scan.pdf");
+        assert_eq!(check_filename("Invoice 2023.10", scan).unwrap(), "Invoice 2023.10.pdf");
+        assert_eq!(check_filename("Report v1.2", scan).unwrap(), "Report v1.2.pdf");
+        assert_eq!(check_filename("Report.docx", scan).unwrap(), "Report.docx.pdf");
+        assert_eq!(check_filename("Backup 2024.tar.gz", Path::new("/b/old.tar.gz")).unwrap(), "Backup 2024.tar.gz");
+        assert_eq!(check_filename("Backup 2024", Path::new("/b/old.tar.gz")).unwrap(), "Backup 2024.tar.gz");
+        assert_eq!(check_filename("notes", Path::new("README")).unwrap(), "notes");
+        let long = "a".repeat(MAX_NAME_LEN);
+        for bad in ["", "a/b", "a\\b", "What?", "..", "Trip.", "con.jpg", "CON .jpg", "tab\there", long.as_str()] {
+            assert!(check_filename(bad, original).is_err(), "{:?}", bad);
+        }
+    }
 }
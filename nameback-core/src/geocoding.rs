@@ -1,9 +1,172 @@
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 
+use crate::geonames::OfflineGeocoder;
+use crate::LocationPrecision;
+
+/// Turns GPS coordinates into a place name for file names
+pub trait GeocodeBackend: Send + Sync + fmt::Debug {
+    /// Location like "Seattle_WA" or "Paris_France", or None if it isn't known
+    fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Option<String>;
+
+    /// Location named no more precisely than `precision`, e.g. "WA" for a region or
+    /// "France" for a country
+    /// By default only cities are named; a backend that can't tell regions and countries
+    /// apart names nothing rather than more than was asked for
+    fn reverse_geocode_at(&self, latitude: f64, longitude: f64, precision: LocationPrecision) -> Option<String> {
+        match precision {
+            LocationPrecision::City => self.reverse_geocode(latitude, longitude),
+            _ => None,
+        }
+    }
+}
+
+/// What a place is called at each `LocationPrecision`, cleaned for file names
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PlaceNames {
+    /// "Seattle_WA", "Paris_France"
+    pub city: Option<String>,
+    /// "WA", "Ontario", "Ile_de_France"
+    pub region: Option<String>,
+    /// "United_States", "France"
+    pub country: Option<String>,
+}
+
+impl PlaceNames {
+    /// The name at `precision`, or a coarser one if that isn't known (never a finer one)
+    pub(crate) fn name(&self, precision: LocationPrecision) -> Option<String> {
+        match precision {
+            LocationPrecision::City | LocationPrecision::Coordinates => {
+                self.city.clone().or_else(|| self.region.clone()).or_else(|| self.country.clone())
+            }
+            LocationPrecision::Region => self.region.clone().or_else(|| self.country.clone()),
+            LocationPrecision::Country => self.country.clone(),
+        }
+    }
+}
+
+/// Where place names for GPS coordinates come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeocodeSource {
+    /// The offline city database if it has been downloaded, otherwise the online service
+    #[default]
+    Auto,
+    /// OpenStreetMap's Nominatim service (one request per second, needs network access)
+    Online,
+    /// A GeoNames city database on disk, downloaded once if it isn't there yet
+    Offline,
+}
+
+impl fmt::Display for GeocodeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeocodeSource::Auto => write!(f, "auto"),
+            GeocodeSource::Online => write!(f, "online"),
+            GeocodeSource::Offline => write!(f, "offline"),
+        }
+    }
+}
+
+impl FromStr for GeocodeSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(GeocodeSource::Auto),
+            "online" => Ok(GeocodeSource::Online),
+            "offline" => Ok(GeocodeSource::Offline),
+            _ => anyhow::bail!("unknown geocode source '{}' (expected auto, online or offline)", s),
+        }
+    }
+}
+
+/// Offline databases by path and whether they may be downloaded
+type DatabaseMap = HashMap<(PathBuf, bool), Arc<LazyDatabase>>;
+
+/// Shared by every run, so a database is read at most once per process
+static OFFLINE_DATABASES: Mutex<Option<DatabaseMap>> = Mutex::new(None);
+
+/// The backend `config` asks for, or None if geocoding is off or there is no place for the
+/// offline database (file names then get coordinates)
+pub(crate) fn backend(config: &crate::RenameConfig) -> Option<Arc<dyn GeocodeBackend>> {
+    if !config.geocode {
+        return None;
+    }
+    let database = || {
+        config
+            .geocode_database
+            .clone()
+            .or_else(crate::geonames::default_database)
+    };
+    match config.geocode_source {
+        GeocodeSource::Online => Some(Arc::new(OnlineGeocoder)),
+        GeocodeSource::Auto => match database().filter(|path| path.exists()) {
+            Some(path) => Some(offline(path, false)),
+            None => Some(Arc::new(OnlineGeocoder)),
+        },
+        GeocodeSource::Offline => Some(offline(database()?, true)),
+    }
+}
+
+fn offline(path: PathBuf, download: bool) -> Arc<dyn GeocodeBackend> {
+    let mut databases = OFFLINE_DATABASES.lock().unwrap();
+    databases
+        .get_or_insert_with(HashMap::new)
+        .entry((path.clone(), download))
+        .or_insert_with(|| {
+            Arc::new(LazyDatabase {
+                path,
+                download,
+                loaded: OnceLock::new(),
+            })
+        })
+        .clone()
+}
+
+/// An offline database loaded when the first photo with GPS data needs it
+/// With `download`, a missing database is downloaded first; otherwise (the `Auto` source)
+/// a database that fails to load hands over to the online service
+#[derive(Debug)]
+struct LazyDatabase {
+    path: PathBuf,
+    download: bool,
+    loaded: OnceLock<Option<OfflineGeocoder>>,
+}
+
+impl GeocodeBackend for LazyDatabase {
+    fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Option<String> {
+        self.reverse_geocode_at(latitude, longitude, LocationPrecision::City)
+    }
+
+    fn reverse_geocode_at(&self, latitude: f64, longitude: f64, precision: LocationPrecision) -> Option<String> {
+        let database = self.loaded.get_or_init(|| {
+            let loaded = (|| {
+                if self.download && !self.path.exists() {
+                    crate::geonames::download(&self.path)?;
+                }
+                OfflineGeocoder::load(&self.path)
+            })();
+            loaded
+                .inspect_err(|e| {
+                    tracing::warn!("Offline geocoding unavailable ({}): {:#}", self.path.display(), e)
+                })
+                .ok()
+        });
+        match database {
+            Some(database) => database.reverse_geocode_at(latitude, longitude, precision),
+            None if !self.download => OnlineGeocoder.reverse_geocode_at(latitude, longitude, precision),
+            None => None,
+        }
+    }
+}
+
 // Cache for geocoding results to avoid repeated API calls and respect rate limits
 lazy_static::lazy_static! {
     static ref GEOCODE_CACHE: Mutex<GeocodeCache> = Mutex::new(GeocodeCache::new());
@@ -17,7 +180,7 @@ struct GeocodeCache {
 
 #[derive(Clone)]
 struct CachedLocation {
-    location: String,
+    location: PlaceNames,
     cached_at: Instant,
 }
 
@@ -30,7 +193,7 @@ impl GeocodeCache {
     }
 
     /// Get cached location or None if not cached/expired (1 hour expiry)
-    fn get(&self, lat: f64, lon: f64) -> Option<String> {
+    fn get(&self, lat: f64, lon: f64) -> Option<PlaceNames> {
         let key = format!("{:.4},{:.4}", lat, lon);
         self.cache.get(&key).and_then(|cached| {
             if cached.cached_at.elapsed() < Duration::from_secs(3600) {
@@ -42,7 +205,7 @@ impl GeocodeCache {
     }
 
     /// Store location in cache
-    fn set(&mut self, lat: f64, lon: f64, location: String) {
+    fn set(&mut self, lat: f64, lon: f64, location: PlaceNames) {
         let key = format!("{:.4},{:.4}", lat, lon);
         self.cache.insert(
             key,
@@ -84,14 +247,29 @@ struct Address {
     country_code: Option<String>,
 }
 
-/// Reverse geocode GPS coordinates to a location name using Nominatim
-/// Returns location in format like "Seattle_WA" or "Paris_France"
-pub fn reverse_geocode(lat: f64, lon: f64) -> Option<String> {
+/// Reverse geocoding through OpenStreetMap's Nominatim service
+/// Results are cached for an hour; requests closer than a second apart are skipped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OnlineGeocoder;
+
+impl GeocodeBackend for OnlineGeocoder {
+    fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Option<String> {
+        self.reverse_geocode_at(latitude, longitude, LocationPrecision::City)
+    }
+
+    fn reverse_geocode_at(&self, latitude: f64, longitude: f64, precision: LocationPrecision) -> Option<String> {
+        reverse_geocode(latitude, longitude)?.name(precision)
+    }
+}
+
+/// Reverse geocode GPS coordinates to place names using Nominatim
+/// The city is named like "Seattle_WA" or "Paris_France"
+fn reverse_geocode(lat: f64, lon: f64) -> Option<PlaceNames> {
     // Check cache first
     {
         let cache = GEOCODE_CACHE.lock().unwrap();
         if let Some(cached) = cache.get(lat, lon) {
-            log::debug!("Geocode cache hit for {:.4},{:.4}", lat, lon);
+            tracing::debug!("Geocode cache hit for {:.4},{:.4}", lat, lon);
             return Some(cached);
         }
     }
@@ -100,7 +278,7 @@ pub fn reverse_geocode(lat: f64, lon: f64) -> Option<String> {
     {
         let cache = GEOCODE_CACHE.lock().unwrap();
         if cache.should_rate_limit() {
-            log::debug!("Rate limiting geocoding request");
+            tracing::debug!("Rate limiting geocoding request");
             // Fall back to coordinates format
             return None;
         }
@@ -116,14 +294,14 @@ pub fn reverse_geocode(lat: f64, lon: f64) -> Option<String> {
             Some(location)
         }
         Err(e) => {
-            log::warn!("Geocoding failed: {}", e);
+            tracing::warn!("Geocoding failed: {}", e);
             None
         }
     }
 }
 
 /// Make the actual API request to Nominatim
-fn geocode_from_nominatim(lat: f64, lon: f64) -> Result<String> {
+fn geocode_from_nominatim(lat: f64, lon: f64) -> Result<PlaceNames> {
     // Build the User-Agent with version and contact email
     let user_agent = format!(
         "Nameback/{} (https://github.com/h4x0r/nameback; info@securityronin.com)",
@@ -136,7 +314,7 @@ fn geocode_from_nominatim(lat: f64, lon: f64) -> Result<String> {
         lat, lon
     );
 
-    log::debug!("Geocoding {},{} via Nominatim", lat, lon);
+    tracing::debug!("Geocoding {},{} via Nominatim", lat, lon);
 
     // Use blocking reqwest since we're in a sync context
     let client = reqwest::blocking::Client::builder()
@@ -159,8 +337,8 @@ fn geocode_from_nominatim(lat: f64, lon: f64) -> Result<String> {
 
     // Extract location from response
     if let Some(address) = data.address {
-        let location = format_location_from_address(&address);
-        if !location.is_empty() {
+        let location = place_names_from_address(&address);
+        if location != PlaceNames::default() {
             return Ok(location);
         }
     }
@@ -168,6 +346,24 @@ fn geocode_from_nominatim(lat: f64, lon: f64) -> Result<String> {
     anyhow::bail!("No location data in geocoding response")
 }
 
+/// The address's names at each precision, cleaned for file names
+fn place_names_from_address(address: &Address) -> PlaceNames {
+    let clean = |name: Option<&String>| name.map(|n| clean_for_filename(n)).filter(|n| !n.is_empty());
+    let region = match address.country_code.as_deref() {
+        Some("us") => address
+            .state
+            .as_deref()
+            .and_then(abbreviate_us_state)
+            .or_else(|| clean(address.state.as_ref())),
+        _ => clean(address.state.as_ref()),
+    };
+    PlaceNames {
+        city: Some(format_location_from_address(address)).filter(|city| !city.is_empty()),
+        region,
+        country: clean(address.country.as_ref()),
+    }
+}
+
 /// Format the address into a filename-friendly location string
 fn format_location_from_address(address: &Address) -> String {
     // Get city/town/village name
@@ -215,7 +411,7 @@ fn format_location_from_address(address: &Address) -> String {
 }
 
 /// Clean a string for use in filename (remove special chars, spaces to underscores)
-fn clean_for_filename(s: &str) -> String {
+pub(crate) fn clean_for_filename(s: &str) -> String {
     s.chars()
         .map(|c| {
             if c.is_alphanumeric() {
@@ -308,4 +504,35 @@ mod tests {
         assert_eq!(abbreviate_us_state("washington"), Some("WA".to_string()));
         assert_eq!(abbreviate_us_state("Ontario"), None);
     }
+
+    #[test]
+    fn test_place_names_from_address() {
+        let address = Address {
+            city: Some("Brooklyn".to_string()),
+            town: None,
+            village: None,
+            hamlet: None,
+            suburb: None,
+            state: Some("New York".to_string()),
+            country: Some("United States".to_string()),
+            country_code: Some("us".to_string()),
+        };
+        let names = place_names_from_address(&address);
+        assert_eq!(names.region.as_deref(), Some("NY"));
+        assert_eq!(names.name(LocationPrecision::Country).as_deref(), Some("United_States"));
+
+        // Only coarser names stand in for missing ones
+        let names = PlaceNames {
+            city: None,
+            region: None,
+            country: Some("France".to_string()),
+        };
+        assert_eq!(names.name(LocationPrecision::City).as_deref(), Some("France"));
+        let names = PlaceNames {
+            city: Some("Paris_France".to_string()),
+            region: None,
+            country: None,
+        };
+        assert_eq!(names.name(LocationPrecision::Region), None);
+    }
 }
\ No newline at end of file
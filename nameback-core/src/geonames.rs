@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::geocoding::{clean_for_filename, GeocodeBackend, PlaceNames};
+use crate::location_timestamp::distance_km;
+use crate::LocationPrecision;
+
+/// Every place with at least 1000 inhabitants, about 10 MB zipped
+const CITIES_URL: &str = "https://download.geonames.org/export/dump/cities1000.zip";
+/// Country names by ISO code
+const COUNTRIES_URL: &str = "https://download.geonames.org/export/dump/countryInfo.txt";
+/// State and province names by `<country>.<code>`
+const REGIONS_URL: &str = "https://download.geonames.org/export/dump/admin1CodesASCII.txt";
+
+const COUNTRIES_FILE: &str = "countryInfo.txt";
+const REGIONS_FILE: &str = "admin1CodesASCII.txt";
+
+/// Positions farther than this from every place in the database are left as coordinates
+const MAX_DISTANCE_KM: f64 = 30.0;
+
+/// Largest unzipped city file read (`cities1000.txt` is about 30 MB)
+const MAX_UNZIPPED_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Where the downloaded database is kept: `<data dir>/geonames/cities1000.txt`
+pub(crate) fn default_database() -> Option<PathBuf> {
+    crate::app_dirs::data_dir().map(|dir| dir.join("geonames").join("cities1000.txt"))
+}
+
+/// Download the GeoNames city database to `path` (unzipped), with the country and region
+/// names next to it. The cities are written last, so their presence means the download finished
+pub(crate) fn download(path: &Path) -> Result<()> {
+    let folder = path.parent().context("Database path has no folder")?;
+    fs::create_dir_all(folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+    tracing::info!("Downloading the offline city database to {}", folder.display());
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(format!("Nameback/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(300))
+        .build()?;
+    let get = |url: &str| -> Result<Vec<u8>> {
+        let response = client.get(url).send()?.error_for_status()?;
+        Ok(response.bytes()?.to_vec())
+    };
+
+    let archive = path.with_extension("zip");
+    write_whole(&archive, &get(CITIES_URL).context("Failed to download the city database")?)?;
+    let cities = unzip_cities(&archive);
+    let _ = fs::remove_file(&archive);
+    let cities = cities?;
+    // Without these, places are named with country codes instead of names
+    for (url, name) in [(COUNTRIES_URL, COUNTRIES_FILE), (REGIONS_URL, REGIONS_FILE)] {
+        match get(url) {
+            Ok(data) => write_whole(&folder.join(name), &data)?,
+            Err(e) => tracing::warn!("Failed to download {}: {:#}", name, e),
+        }
+    }
+    write_whole(path, &cities)
+}
+
+/// Write via a temporary file, so an interrupted write never leaves a truncated file
+fn write_whole(path: &Path, data: &[u8]) -> Result<()> {
+    let partial = path.with_extension("part");
+    fs::write(&partial, data).with_context(|| format!("Failed to write {}", partial.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// The cities file in a GeoNames archive, named after it (`cities1000.zip` holds `cities1000.txt`)
+fn unzip_cities(archive: &Path) -> Result<Vec<u8>> {
+    let stem = archive.file_stem().unwrap_or_default().to_string_lossy();
+    let name = format!("{}.txt", stem);
+    crate::format_handlers::archive::zip_file(archive, &name, MAX_UNZIPPED_BYTES)
+        .with_context(|| format!("Failed to read {}", archive.display()))?
+        .with_context(|| format!("{} has no {}", archive.display(), name))
+}
+
+struct Place {
+    latitude: f64,
+    longitude: f64,
+    name: String,
+    country: String,
+    region: String,
+}
+
+/// Reverse geocoding from a GeoNames city database on disk: the nearest place within
+/// `MAX_DISTANCE_KM`, named like the online service does ("Seattle_WA", "Paris_France")
+pub struct OfflineGeocoder {
+    places: Vec<Place>,
+    /// Indices into `places` by whole-degree latitude and longitude
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    /// Country names by ISO code
+    countries: HashMap<String, String>,
+    /// Region names by `<country>.<code>`, e.g. `CA.08` for Ontario
+    regions: HashMap<String, String>,
+}
+
+impl fmt::Debug for OfflineGeocoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OfflineGeocoder")
+            .field("places", &self.places.len())
+            .finish()
+    }
+}
+
+impl OfflineGeocoder {
+    /// Load a GeoNames cities file (`cities1000.txt` or another `cities*` dump, unzipped or
+    /// not), with names from `countryInfo.txt` and `admin1CodesASCII.txt` next to it if present
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let data = if data.starts_with(b"PK\x03\x04") {
+            unzip_cities(path)?
+        } else {
+            data
+        };
+        let folder = path.parent().unwrap_or_else(|| Path::new(""));
+        let names = |file: &str| fs::read_to_string(folder.join(file)).unwrap_or_default();
+
+        let geocoder = Self::parse(
+            &String::from_utf8_lossy(&data),
+            &names(COUNTRIES_FILE),
+            &names(REGIONS_FILE),
+        );
+        if geocoder.places.is_empty() {
+            anyhow::bail!("No places in {}", path.display());
+        }
+        tracing::debug!("Loaded {} places from {}", geocoder.places.len(), path.display());
+        Ok(geocoder)
+    }
+
+    fn parse(cities: &str, countries: &str, regions: &str) -> Self {
+        let places: Vec<Place> = cities
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                Some(Place {
+                    name: fields.get(1)?.to_string(),
+                    latitude: fields.get(4)?.parse().ok()?,
+                    longitude: fields.get(5)?.parse().ok()?,
+                    country: fields.get(8)?.to_string(),
+                    region: fields.get(10)?.to_string(),
+                })
+            })
+            .collect();
+
+        let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (index, place) in places.iter().enumerate() {
+            cells.entry(cell(place.latitude, place.longitude)).or_default().push(index);
+        }
+
+        // Both files are tab-separated with `#` comments: code, ..., name
+        let table = |text: &str, name_column: usize| -> HashMap<String, String> {
+            text.lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split('\t').collect();
+                    Some((fields.first()?.to_string(), fields.get(name_column)?.to_string()))
+                })
+                .collect()
+        };
+
+        Self {
+            places,
+            cells,
+            countries: table(countries, 4),
+            regions: table(regions, 1),
+        }
+    }
+
+    /// The place nearest to the position and its distance in km, looking in the
+    /// surrounding one-degree cells
+    fn nearest(&self, latitude: f64, longitude: f64) -> Option<(&Place, f64)> {
+        let (row, column) = cell(latitude, longitude);
+        (-1..=1)
+            .flat_map(|dr| (-1..=1).map(move |dc| (row + dr, (column + dc + 180).rem_euclid(360) - 180)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .map(|&index| {
+                let place = &self.places[index];
+                (place, distance_km(latitude, longitude, place.latitude, place.longitude))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// The city as "City_Region": US states (whose GeoNames codes are their abbreviations),
+    /// Canadian province names, otherwise the country. The region alone is the state or
+    /// province name, where known
+    fn names(&self, place: &Place) -> PlaceNames {
+        let country = self
+            .countries
+            .get(&place.country)
+            .cloned()
+            .unwrap_or_else(|| place.country.clone());
+        let province = self.regions.get(&format!("{}.{}", place.country, place.region)).cloned();
+        let (city_region, region) = match place.country.as_str() {
+            "US" => (place.region.clone(), Some(place.region.clone())),
+            "CA" => (province.clone().unwrap_or_else(|| place.country.clone()), province),
+            _ => (country.clone(), province),
+        };
+        let city = clean_for_filename(&place.name);
+        let city = match clean_for_filename(&city_region) {
+            region if region.is_empty() => city,
+            region => format!("{}_{}", city, region),
+        };
+        let clean = |name: String| Some(clean_for_filename(&name)).filter(|name| !name.is_empty());
+        PlaceNames {
+            city: Some(city).filter(|city| !city.is_empty()),
+            region: region.and_then(clean),
+            country: clean(country),
+        }
+    }
+
+    fn place_names(&self, latitude: f64, longitude: f64) -> Option<PlaceNames> {
+        let (place, distance) = self.nearest(latitude, longitude)?;
+        (distance <= MAX_DISTANCE_KM).then(|| self.names(place))
+    }
+}
+
+impl GeocodeBackend for OfflineGeocoder {
+    fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Option<String> {
+        self.reverse_geocode_at(latitude, longitude, LocationPrecision::City)
+    }
+
+    fn reverse_geocode_at(&self, latitude: f64, longitude: f64, precision: LocationPrecision) -> Option<String> {
+        self.place_names(latitude, longitude)?.name(precision)
+    }
+}
+
+fn cell(latitude: f64, longitude: f64) -> (i32, i32) {
+    (latitude.floor() as i32, longitude.floor() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CITIES: &str = "\
+5809844\tSeattle\tSeattle\t\t47.60621\t-122.33207\tP\tPPLA2\tUS\t\tWA\t033\t\t\t737015\t56\t\tAmerica/Los_Angeles\t2024-01-01
+5815135\tBellevue\tBellevue\t\t47.61038\t-122.20068\tP\tPPL\tUS\t\tWA\t033\t\t\t151854\t\t\tAmerica/Los_Angeles\t2024-01-01
+6167865\tToronto\tToronto\t\t43.70643\t-79.39864\tP\tPPLA\tCA\t\t08\t3520\t\t\t2600000\t\t\tAmerica/Toronto\t2024-01-01
+2988507\tParis\tParis\t\t48.85341\t2.3488\tP\tPPLC\tFR\t\t11\t75\t\t\t2138551\t\t\tEurope/Paris\t2024-01-01
+2193733\tAuckland\tAuckland\t\t-36.84853\t174.76349\tP\tPPLA\tNZ\t\tE7\t\t\t\t417910\t\t\tPacific/Auckland\t2024-01-01
+4036284\tAlofi\tAlofi\t\t-19.05451\t-169.91768\tP\tPPLC\tNU\t\t\t\t\t\t624\t\t\tPacific/Niue\t2024-01-01
+";
+    const COUNTRIES: &str = "#ISO\tISO3\tISO-Numeric\tfips\tCountry\n\
+FR\tFRA\t250\tFR\tFrance\n\
+NZ\tNZL\t554\tNZ\tNew Zealand\n";
+    const REGIONS: &str = "CA.08\tOntario\tOntario\t6093943\nUS.WA\tWashington\tWashington\t5815135\n";
+
+    #[test]
+    fn test_nearest_place_names() {
+        let geocoder = OfflineGeocoder::parse(CITIES, COUNTRIES, REGIONS);
+        // Closer to Bellevue than to Seattle
+        assert_eq!(geocoder.reverse_geocode(47.615, -122.19).as_deref(), Some("Bellevue_WA"));
+        assert_eq!(geocoder.reverse_geocode(47.6, -122.33).as_deref(), Some("Seattle_WA"));
+        assert_eq!(geocoder.reverse_geocode(43.65, -79.38).as_deref(), Some("Toronto_Ontario"));
+        assert_eq!(geocoder.reverse_geocode(48.86, 2.29).as_deref(), Some("Paris_France"));
+        assert_eq!(geocoder.reverse_geocode(-36.9, 174.8).as_deref(), Some("Auckland_New_Zealand"));
+        // Unknown country names fall back to the code; the open ocean to nothing
+        assert_eq!(geocoder.reverse_geocode(-19.06, -169.92).as_deref(), Some("Alofi_NU"));
+        assert_eq!(geocoder.reverse_geocode(30.0, -40.0), None);
+
+        let at = |precision| geocoder.reverse_geocode_at(43.65, -79.38, precision);
+        assert_eq!(at(LocationPrecision::Region).as_deref(), Some("Ontario"));
+        assert_eq!(at(LocationPrecision::Country).as_deref(), Some("CA"));
+        let region = geocoder.reverse_geocode_at(47.6, -122.33, LocationPrecision::Region);
+        assert_eq!(region.as_deref(), Some("WA"));
+        // Without a region name, the country stands in
+        let region = geocoder.reverse_geocode_at(48.86, 2.29, LocationPrecision::Region);
+        assert_eq!(region.as_deref(), Some("France"));
+        // Just past the cell boundary and the distance limit
+        assert_eq!(geocoder.reverse_geocode(48.0, -122.33).as_deref(), None);
+    }
+
+    #[test]
+    fn test_load_zipped_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = crate::format_handlers::archive::tests::build_zip(&[("cities1000.txt", CITIES, 2024)]);
+        let path = temp_dir.path().join("cities1000.zip");
+        fs::write(&path, &archive).unwrap();
+        assert_eq!(unzip_cities(&path).unwrap(), CITIES.as_bytes());
+        fs::write(temp_dir.path().join(COUNTRIES_FILE), COUNTRIES).unwrap();
+        let geocoder = OfflineGeocoder::load(&path).unwrap();
+        assert_eq!(geocoder.reverse_geocode(48.86, 2.29).as_deref(), Some("Paris_France"));
+
+        fs::write(&path, "not a database").unwrap();
+        assert!(OfflineGeocoder::load(&path).is_err());
+    }
+}
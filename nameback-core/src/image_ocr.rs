@@ -1,9 +1,29 @@
 use anyhow::{Context, Result};
-use log::debug;
-use std::path::Path;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use tracing::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::key_phrases::PhraseLimits;
+use crate::location_timestamp::TimestampStyle;
+use crate::ocr_cleanup::OcrText;
+use crate::pdf_content::PdfContent;
+use crate::tool_command::ToolCommand;
 
 /// Extracts text from an image using OCR (requires tesseract-ocr installed)
-pub fn extract_image_text(path: &Path) -> Result<Option<String>> {
+/// `languages` are the tesseract language arguments to try (see `ocr_languages::language_attempts`)
+/// Long text is named from its key phrases, as many and as long as `phrases` allows; text
+/// tesseract read with a mean confidence under `min_confidence` (0-100) isn't used
+/// With `receipts`, a photographed receipt or invoice is named from its vendor, date (in that
+/// style) and total, and the result is marked `dated`
+pub fn extract_image_text(
+    path: &Path,
+    languages: &[String],
+    phrases: PhraseLimits,
+    min_confidence: u8,
+    receipts: Option<&TimestampStyle>,
+) -> Result<Option<PdfContent>> {
     debug!("Attempting OCR on image: {}", path.display());
 
     // Check if tesseract is available
@@ -13,29 +33,30 @@ pub fn extract_image_text(path: &Path) -> Result<Option<String>> {
     }
 
     // Run tesseract OCR on the image
-    match run_tesseract_ocr(path) {
-        Ok(text) => {
-            let cleaned = clean_text(&text);
+    match run_tesseract_ocr(path, languages) {
+        Ok(ocr) if ocr.confidence < f32::from(min_confidence) => {
+            debug!("OCR confidence {:.0} below minimum {}, skipping", ocr.confidence, min_confidence);
+            Ok(None)
+        }
+        Ok(ocr) => {
+            if let Some(name) = receipts.and_then(|style| crate::format_handlers::receipt::receipt_name(&ocr.text, style)) {
+                return Ok(Some(PdfContent { name, dated: true }));
+            }
+            let cleaned = clean_text(&ocr.text);
             if cleaned.len() > 10 {
                 // Use key phrase extraction for longer OCR text
                 if cleaned.len() > 150 {
                     debug!("Extracting key phrases from image OCR text ({} chars)", cleaned.len());
-                    let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-                    if !phrases.is_empty() {
-                        let best_phrase = &phrases[0];
-                        debug!("Selected key phrase from image OCR: {}", best_phrase);
-                        return Ok(Some(best_phrase.clone()));
+                    if let Some(phrase) = crate::key_phrases::summarize(&cleaned, phrases) {
+                        debug!("Selected key phrase from image OCR: {}", phrase);
+                        return Ok(Some(phrase.into()));
                     }
                 }
 
                 // For shorter text or if key phrase extraction failed, truncate
-                let truncated = if cleaned.len() > 80 {
-                    &cleaned[..80]
-                } else {
-                    &cleaned
-                };
+                let truncated = crate::script::truncate(&cleaned, 80);
                 debug!("OCR extracted from image: {}", truncated);
-                Ok(Some(truncated.to_string()))
+                Ok(Some(truncated.into()))
             } else {
                 debug!("OCR text too short");
                 Ok(None)
@@ -53,9 +74,39 @@ fn is_tesseract_available() -> bool {
     crate::deps_check::Dependency::Tesseract.is_available()
 }
 
+/// Mean tesseract confidence (0-100) at which the upright image is trusted
+/// Below this, the image is also OCR'd rotated in case it was photographed sideways
+const CONFIDENT_OCR: i32 = 70;
+
+/// Extra rotations tried on low-confidence images, in degrees clockwise
+const ROTATIONS: [u32; 3] = [90, 180, 270];
+
+/// Distinguishes temp files written by concurrent OCR runs
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Text recognized from one OCR run
+#[derive(Debug)]
+struct OcrAttempt {
+    text: String,
+    language: String,
+    /// Mean confidence of the words kept after cleanup (0-100)
+    confidence: i32,
+    char_count: usize,
+}
+
+impl OcrAttempt {
+    /// Whether this attempt should replace `best` when comparing rotations
+    fn beats(&self, best: &Option<OcrAttempt>) -> bool {
+        self.char_count > 0
+            && best
+                .as_ref()
+                .is_none_or(|best| self.confidence > best.confidence)
+    }
+}
+
 /// Runs tesseract OCR on an image file
-/// Tries multiple languages in priority order: Traditional Chinese, Simplified Chinese, English
-fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
+/// Applies the EXIF orientation first, and tries other rotations when the result is poor
+fn run_tesseract_ocr(image_path: &Path, languages: &[String]) -> Result<OcrText> {
     // Convert to absolute path
     let absolute_path = if image_path.is_absolute() {
         image_path.to_path_buf()
@@ -65,48 +116,89 @@ fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
             .join(image_path)
     };
 
-    // Check if this is a HEIC file that needs conversion
-    let needs_conversion = image_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| matches!(ext.to_lowercase().as_str(), "heic" | "heif"))
-        .unwrap_or(false);
+    // HEIC and RAW files are OCR'd from a converted PNG
+    let image = readable_image(&absolute_path)?;
+    let result = ocr_with_rotations(image.path(), languages);
 
-    // Convert HEIC to PNG if needed
-    let (ocr_path, temp_file) = if needs_conversion {
-        let temp_png = convert_heic_to_png(&absolute_path)?;
-        (temp_png.clone(), Some(temp_png))
-    } else {
-        (absolute_path.clone(), None)
+    match result? {
+        Some(best) => Ok(OcrText {
+            text: best.text,
+            confidence: best.confidence as f32,
+        }),
+        None => anyhow::bail!("All OCR language attempts failed"),
+    }
+}
+
+/// OCRs the image upright (per its EXIF orientation), then rotated if that wasn't confident
+fn ocr_with_rotations(path: &Path, languages: &[String]) -> Result<Option<OcrAttempt>> {
+    // Formats the image crate can't decode are still worth handing to tesseract as-is
+    let (image, oriented) = match load_oriented(path) {
+        Ok(loaded) => (Some(loaded.0), loaded.1),
+        Err(e) => {
+            debug!("Could not decode image for rotation: {}", e);
+            (None, false)
+        }
+    };
+
+    let mut best = match (&image, oriented) {
+        (Some(image), true) => with_temp_image(image, |p| ocr_languages(p, languages))?,
+        _ => ocr_languages(path.to_str().context("Path not valid UTF-8")?, languages),
     };
 
-    let path_str = ocr_path.to_str().context("Path not valid UTF-8")?;
+    let Some(image) = image else {
+        return Ok(best);
+    };
+    if best.as_ref().is_some_and(|b| b.confidence >= CONFIDENT_OCR) {
+        return Ok(best);
+    }
+
+    // Rotated attempts reuse the language that worked upright to keep the number of runs down
+    let languages = match &best {
+        Some(upright) => vec![upright.language.clone()],
+        None => languages.to_vec(),
+    };
+    for degrees in ROTATIONS {
+        let rotated = rotate(&image, degrees);
+        let attempt = with_temp_image(&rotated, |p| ocr_languages(p, &languages))?;
+        if let Some(attempt) = attempt {
+            debug!(
+                "OCR rotated {}°: confidence {} ({} chars)",
+                degrees, attempt.confidence, attempt.char_count
+            );
+            if attempt.beats(&best) {
+                best = Some(attempt);
+            }
+        }
+    }
+
+    Ok(best)
+}
 
-    // Try languages in order: Traditional Chinese, Simplified Chinese, English
-    let languages = ["chi_tra", "chi_sim", "eng"];
-    let mut best_result = String::new();
-    let mut best_confidence = 0;
+/// OCRs `path` with each language, keeping the result with the most characters
+fn ocr_languages(path: &str, languages: &[String]) -> Option<OcrAttempt> {
+    let mut best: Option<OcrAttempt> = None;
 
-    for lang in &languages {
+    for lang in languages {
         debug!("Trying OCR with language: {}", lang);
 
-        let result = tesseract::Tesseract::new(None, Some(lang))
-            .context("Failed to initialize Tesseract")
-            .and_then(|t| t.set_image(path_str).context("Failed to set image"))
-            .and_then(|mut t| t.get_text().context("Failed to extract text"));
+        let result = crate::ocr_cleanup::recognize(path, lang);
 
         match result {
-            Ok(text) => {
-                let cleaned = clean_text(&text);
-                let char_count = cleaned.chars().count();
+            Ok(OcrText { text, confidence }) => {
+                let confidence = confidence.round() as i32;
+                let char_count = clean_text(&text).chars().count();
 
                 debug!("OCR with {}: {} characters extracted", lang, char_count);
 
                 // Use the result with the most characters as proxy for best match
-                if char_count > best_confidence {
-                    best_confidence = char_count;
-                    best_result = text;
+                if char_count > best.as_ref().map_or(0, |b| b.char_count) {
                     debug!("New best result with {}: {} chars", lang, char_count);
+                    best = Some(OcrAttempt {
+                        text,
+                        language: lang.clone(),
+                        confidence,
+                        char_count,
+                    });
                 }
             }
             Err(e) => {
@@ -115,67 +207,301 @@ fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
         }
     }
 
-    // Clean up temp file if we created one
-    if let Some(temp) = temp_file {
-        let _ = std::fs::remove_file(&temp);
+    best
+}
+
+/// Decodes an image and applies its EXIF orientation
+/// Also returns whether the orientation changed the image
+pub(crate) fn load_oriented(path: &Path) -> Result<(DynamicImage, bool)> {
+    let mut decoder = ImageReader::open(path)
+        .context("Failed to open image")?
+        .with_guessed_format()
+        .context("Failed to read image")?
+        .into_decoder()
+        .context("Unsupported image format")?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).context("Failed to decode image")?;
+
+    if orientation == Orientation::NoTransforms {
+        return Ok((image, false));
+    }
+    debug!("Applying EXIF orientation {:?}", orientation);
+    image.apply_orientation(orientation);
+    Ok((image, true))
+}
+
+/// Rotates an image clockwise by a multiple of 90 degrees
+fn rotate(image: &DynamicImage, degrees: u32) -> DynamicImage {
+    match degrees % 360 {
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        _ => image.clone(),
+    }
+}
+
+/// Saves `image` to a temporary PNG, runs `f` on its path and removes it again
+fn with_temp_image<T>(image: &DynamicImage, f: impl FnOnce(&str) -> T) -> Result<T> {
+    let temp_png = temp_png_path(&std::env::temp_dir(), "nameback_ocr");
+    image.save(&temp_png).context("Failed to save temp image")?;
+
+    let result = temp_png.to_str().context("Path not valid UTF-8").map(f);
+    let _ = std::fs::remove_file(&temp_png);
+    result
+}
+
+/// Longest side of images converted from HEIC/RAW; plenty for OCR and quick to decode
+const CONVERTED_MAX_SIDE: u32 = 2048;
+
+/// Size the cached conversions are kept under, the least recently used going first
+const MAX_THUMBNAIL_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// An image tesseract and the image crate can read, either the file itself or a converted PNG
+pub(crate) struct ReadableImage {
+    path: PathBuf,
+    /// Removed on drop (conversions that couldn't be cached)
+    temporary: bool,
+}
+
+impl ReadableImage {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ReadableImage {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Whether an image has to be converted before tesseract or the image crate can read it
+fn needs_conversion(path: &Path) -> bool {
+    let heic = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "heic" | "heif"));
+    heic || crate::detector::is_raw(path)
+}
+
+/// `path` itself, or for HEIC and camera RAW files a PNG converted from it
+/// Conversions are cached in the data directory, so a file is only converted again once it changes
+pub(crate) fn readable_image(path: &Path) -> Result<ReadableImage> {
+    if !needs_conversion(path) {
+        return Ok(ReadableImage {
+            path: path.to_path_buf(),
+            temporary: false,
+        });
     }
 
-    if best_confidence > 0 {
-        Ok(best_result)
+    let cached = thumbnail_cache_path(path).filter(|cached| {
+        cached
+            .parent()
+            .is_some_and(|dir| std::fs::create_dir_all(dir).is_ok())
+    });
+    let Some(cached) = cached else {
+        let temp_png = temp_png_path(&std::env::temp_dir(), "nameback_converted");
+        convert_to_png(path, &temp_png)?;
+        return Ok(ReadableImage {
+            path: temp_png,
+            temporary: true,
+        });
+    };
+
+    if cached.exists() {
+        debug!("Using cached conversion of {}: {}", path.display(), cached.display());
+        // Marks the entry as recently used for `evict_thumbnails`
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(&cached)
+            .and_then(|file| file.set_modified(std::time::SystemTime::now()));
     } else {
-        anyhow::bail!("All OCR language attempts failed")
+        // Converted next to the cache entry and renamed into place, so concurrent runs
+        // never see a half-written PNG
+        let dir = cached.parent().unwrap_or(Path::new("."));
+        let partial = temp_png_path(dir, "partial");
+        if let Err(e) = convert_to_png(path, &partial) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+        std::fs::rename(&partial, &cached).context("Failed to cache converted image")?;
+        if let Some(dir) = cached.parent() {
+            evict_thumbnails(dir, MAX_THUMBNAIL_CACHE_BYTES);
+        }
     }
+
+    Ok(ReadableImage {
+        path: cached,
+        temporary: false,
+    })
 }
 
-/// Converts HEIC image to PNG using sips (macOS) or magick (ImageMagick)
-fn convert_heic_to_png(heic_path: &Path) -> Result<std::path::PathBuf> {
-    let temp_dir = std::env::temp_dir();
-    let temp_png = temp_dir.join(format!("nameback_heic_{}.png", std::process::id()));
+/// Where the PNG converted from `path` is cached, keyed by its path, size and modification time
+fn thumbnail_cache_path(path: &Path) -> Option<PathBuf> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(thumbnail_dir()?.join(format!("{}.png", thumbnail_key(path, metadata.len(), modified))))
+}
+
+fn thumbnail_dir() -> Option<PathBuf> {
+    Some(crate::app_dirs::data_dir()?.join("thumbnails"))
+}
+
+/// Removes the least recently used conversions in `dir` until they take up at most `max_bytes`
+fn evict_thumbnails(dir: &Path, max_bytes: u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    // Only finished entries; partial ones belong to conversions still running
+    let mut thumbnails: Vec<(std::time::SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with("partial_"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = thumbnails.iter().map(|(_, size, _)| size).sum();
+    thumbnails.sort();
+    for (_, size, path) in thumbnails {
+        if total <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            debug!("Evicted cached conversion {}", path.display());
+            total -= size;
+        }
+    }
+}
+
+/// Removes every cached conversion
+pub(crate) fn clear_thumbnails() -> Result<()> {
+    let Some(dir) = thumbnail_dir() else {
+        return Ok(());
+    };
+    match std::fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn thumbnail_key(path: &Path, size: u64, modified: u64) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(&size.to_le_bytes());
+    hasher.update(&modified.to_le_bytes());
+    hasher.finalize().to_hex()[..32].to_string()
+}
+
+/// A fresh PNG path in `dir` that no concurrent conversion uses
+pub(crate) fn temp_png_path(dir: &Path, prefix: &str) -> PathBuf {
+    dir.join(format!(
+        "{}_{}_{}.png",
+        prefix,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Finds the `sips` tool that ships with macOS
+/// Apps launched from Finder get a minimal PATH, so also check its fixed location
+fn find_sips() -> Option<PathBuf> {
+    which::which("sips").ok().or_else(|| {
+        let builtin = Path::new("/usr/bin/sips");
+        (cfg!(target_os = "macos") && builtin.exists()).then(|| builtin.to_path_buf())
+    })
+}
 
-    debug!(
-        "Converting HEIC to PNG: {} -> {}",
-        heic_path.display(),
-        temp_png.display()
-    );
+/// Converts a HEIC or RAW image to a PNG at `dest`, scaled down to `CONVERTED_MAX_SIDE`
+/// RAW files use the preview JPEG dcraw extracts when it's installed, which is much faster
+/// than developing the RAW data; otherwise sips (always present on macOS) or ImageMagick convert
+fn convert_to_png(source: &Path, dest: &Path) -> Result<()> {
+    debug!("Converting {} to PNG: {}", source.display(), dest.display());
+
+    if crate::detector::is_raw(source) {
+        match extract_raw_preview(source, dest) {
+            Ok(()) => {
+                debug!("Converted RAW using its embedded preview");
+                return Ok(());
+            }
+            Err(e) => debug!("No usable RAW preview: {}", e),
+        }
+    }
 
     // Try sips first (available on macOS)
-    if let Some(mut cmd) = which::which("sips").ok().map(std::process::Command::new) {
+    if let Some(mut cmd) = find_sips().map(std::process::Command::new) {
         let sips_result = cmd
             .arg("-s")
             .arg("format")
             .arg("png")
-            .arg(heic_path)
+            .arg("-Z")
+            .arg(CONVERTED_MAX_SIDE.to_string())
+            .arg(source)
             .arg("--out")
-            .arg(&temp_png)
-            .output();
+            .arg(dest)
+            .tool_output();
 
         if let Ok(output) = sips_result {
             if output.status.success() {
-                debug!("Successfully converted HEIC using sips");
-                return Ok(temp_png);
+                debug!("Successfully converted using sips");
+                return Ok(());
             }
         }
     }
 
-    // Fallback to ImageMagick's magick command
+    // Fallback to ImageMagick's magick command (which develops RAW files through its
+    // dcraw/libraw delegate)
     debug!("sips not available or failed, trying ImageMagick");
     let mut cmd = crate::deps_check::Dependency::ImageMagick
         .create_command()
-        .context("ImageMagick not available for HEIC conversion")?;
+        .context("HEIC and RAW conversion needs sips (macOS) or ImageMagick")?;
 
     let output = cmd
         .arg("convert")
-        .arg(heic_path)
-        .arg(&temp_png)
-        .output()
+        .arg(source)
+        .arg("-auto-orient")
+        .arg("-resize")
+        .arg(format!("{0}x{0}>", CONVERTED_MAX_SIDE))
+        .arg(dest)
+        .tool_output()
         .context("Failed to run magick command")?;
 
     if !output.status.success() {
-        anyhow::bail!("HEIC conversion failed with both sips and magick");
+        anyhow::bail!("Image conversion failed with both sips and magick");
+    }
+
+    debug!("Successfully converted using ImageMagick");
+    Ok(())
+}
+
+/// Saves the JPEG preview embedded in a RAW file (`dcraw -e -c`) as a PNG
+fn extract_raw_preview(raw_path: &Path, dest: &Path) -> Result<()> {
+    let dcraw = which::which("dcraw").context("dcraw not installed")?;
+    let output = std::process::Command::new(dcraw)
+        .arg("-e")
+        .arg("-c")
+        .arg(raw_path)
+        .tool_output()
+        .context("Failed to run dcraw")?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!("dcraw found no embedded preview");
     }
 
-    debug!("Successfully converted HEIC using ImageMagick");
-    Ok(temp_png)
+    let preview = image::load_from_memory(&output.stdout).context("Failed to decode RAW preview")?;
+    preview
+        .thumbnail(CONVERTED_MAX_SIDE, CONVERTED_MAX_SIDE)
+        .save(dest)
+        .context("Failed to save RAW preview")
 }
 
 /// Cleans extracted text for use in filenames
@@ -223,4 +549,81 @@ mod tests {
         let input = "\n\n   \n  ";
         assert_eq!(clean_text(input), "");
     }
+
+    #[test]
+    fn test_rotate() {
+        let image = DynamicImage::new_rgb8(40, 10);
+        assert_eq!((rotate(&image, 90).width(), rotate(&image, 90).height()), (10, 40));
+        assert_eq!(rotate(&image, 180).width(), 40);
+        assert_eq!(rotate(&image, 270).height(), 40);
+    }
+
+    #[test]
+    fn test_load_oriented_without_exif() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("plain.png");
+        DynamicImage::new_rgb8(40, 10).save(&path)?;
+
+        let (image, oriented) = load_oriented(&path)?;
+        assert!(!oriented);
+        assert_eq!((image.width(), image.height()), (40, 10));
+        Ok(())
+    }
+
+    #[test]
+    fn test_readable_image_converts_only_heic_and_raw() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("plain.png");
+        DynamicImage::new_rgb8(40, 10).save(&path)?;
+        assert_eq!(readable_image(&path)?.path(), path);
+
+        assert!(needs_conversion(Path::new("IMG_0001.HEIC")));
+        assert!(needs_conversion(Path::new("DSC_0001.nef")));
+        assert!(!needs_conversion(Path::new("DSC_0001.jpg")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_thumbnails_keeps_recent_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = std::time::SystemTime::now();
+        for (age, name) in [(30, "old.png"), (20, "used.png"), (10, "new.png"), (40, "partial_1_0.png")] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, [0u8; 100]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age)).unwrap();
+        }
+
+        evict_thumbnails(dir.path(), 250);
+        let left: Vec<bool> = ["old.png", "used.png", "new.png", "partial_1_0.png"]
+            .iter()
+            .map(|name| dir.path().join(name).exists())
+            .collect();
+        assert_eq!(left, [false, true, true, true]);
+    }
+
+    #[test]
+    fn test_thumbnail_key_changes_with_file() {
+        let path = Path::new("/photos/IMG_1234.CR2");
+        let key = thumbnail_key(path, 25_000_000, 1_700_000_000);
+        assert_eq!(key, thumbnail_key(path, 25_000_000, 1_700_000_000));
+        assert_ne!(key, thumbnail_key(path, 25_000_000, 1_700_000_001));
+        assert_ne!(key, thumbnail_key(path, 24_000_000, 1_700_000_000));
+        assert_ne!(key, thumbnail_key(Path::new("/photos/IMG_1235.CR2"), 25_000_000, 1_700_000_000));
+    }
+
+    #[test]
+    fn test_rotation_kept_only_when_more_confident() {
+        let attempt = |confidence, char_count| OcrAttempt {
+            text: "text".to_string(),
+            language: "eng".to_string(),
+            confidence,
+            char_count,
+        };
+        let upright = Some(attempt(30, 50));
+        assert!(attempt(80, 20).beats(&upright));
+        assert!(!attempt(20, 200).beats(&upright));
+        assert!(!attempt(90, 0).beats(&None));
+        assert!(attempt(10, 5).beats(&None));
+    }
 }
@@ -0,0 +1,396 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::renamer::Replaced;
+
+/// Batches named in the "unknown batch" error
+const RECENT_BATCHES_SHOWN: usize = 5;
+
+/// One line of the journal: a rename, or the later undo of one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    batch_id: String,
+    original_path: PathBuf,
+    new_path: PathBuf,
+    /// Unix timestamp of the rename (or of its undo)
+    timestamp: u64,
+    /// Marks an earlier rename with the same batch and paths as undone
+    #[serde(default)]
+    undone: bool,
+    /// Folders created to move the file into (outermost first), removed on undo if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    created_dirs: Vec<PathBuf>,
+    /// What became of a file the rename replaced (`OnConflict::Trash` and `Overwrite`),
+    /// brought back on undo where it can be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replaced: Option<Replaced>,
+}
+
+impl JournalEntry {
+    fn same_rename(&self, other: &JournalEntry) -> bool {
+        self.batch_id == other.batch_id
+            && self.original_path == other.original_path
+            && self.new_path == other.new_path
+    }
+}
+
+/// A rename that could not be undone, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoConflict {
+    /// Where the renamed file should be
+    pub new_path: PathBuf,
+    /// The name it would be restored to
+    pub original_path: PathBuf,
+    /// Why it was left alone
+    pub reason: String,
+}
+
+/// Outcome of `RenameEngine::undo_batch`
+#[derive(Debug, Clone, Default)]
+pub struct UndoReport {
+    /// Batch that was undone
+    pub batch_id: String,
+    /// Files restored to their original names, as (renamed path, original path)
+    pub restored: Vec<(PathBuf, PathBuf)>,
+    /// Renames left in place because restoring them would lose or overwrite a file
+    pub conflicts: Vec<UndoConflict>,
+}
+
+/// Append-only log of every rename, grouped in batches (one per `rename_files` call)
+/// Survives the process, so a batch can be reverted after the CLI or GUI has exited
+#[derive(Debug, Clone)]
+pub(crate) struct Journal {
+    path: PathBuf,
+}
+
+/// An open batch renames are recorded under
+pub(crate) struct Batch<'a> {
+    journal: &'a Journal,
+    pub(crate) id: String,
+}
+
+impl Batch<'_> {
+    /// Record a completed rename or move; failures are logged, never fatal to the rename
+    /// `created_dirs` are the folders created for a move, outermost first
+    pub(crate) fn record(&self, original_path: &Path, new_path: &Path, created_dirs: &[PathBuf]) {
+        self.record_replacing(original_path, new_path, created_dirs, None);
+    }
+
+    /// Record a rename that replaced another file, and what became of that file
+    pub(crate) fn record_replacing(
+        &self,
+        original_path: &Path,
+        new_path: &Path,
+        created_dirs: &[PathBuf],
+        replaced: Option<Replaced>,
+    ) {
+        let entry = JournalEntry {
+            batch_id: self.id.clone(),
+            original_path: original_path.to_path_buf(),
+            new_path: new_path.to_path_buf(),
+            timestamp: now(),
+            undone: false,
+            created_dirs: created_dirs.to_vec(),
+            replaced,
+        };
+        if let Err(e) = self.journal.append(&entry) {
+            tracing::warn!("Failed to record rename in undo journal: {}", e);
+        }
+    }
+}
+
+impl Journal {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// journal.jsonl in the user data directory
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::data_dir().map(|dir| dir.join("journal.jsonl"))
+    }
+
+    /// Start a batch with a fresh id, e.g. `20240315-142210`
+    pub(crate) fn begin(&self) -> Result<Batch<'_>> {
+        let taken: HashSet<String> = self.entries()?.into_iter().map(|e| e.batch_id).collect();
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let id = std::iter::once(stamp.clone())
+            .chain((2..).map(|n| format!("{}-{}", stamp, n)))
+            .find(|id| !taken.contains(id))
+            .unwrap_or(stamp);
+        Ok(Batch { journal: self, id })
+    }
+
+    /// Record a rename that was undone another way (the GUI's rename history) as undone, so
+    /// `undo` doesn't try it again
+    pub(crate) fn mark_undone(&self, batch_id: &str, original_path: &Path, new_path: &Path) -> Result<()> {
+        self.append(&JournalEntry {
+            batch_id: batch_id.to_string(),
+            original_path: original_path.to_path_buf(),
+            new_path: new_path.to_path_buf(),
+            timestamp: now(),
+            undone: true,
+            created_dirs: Vec::new(),
+            replaced: None,
+        })
+    }
+
+    /// Restore the original names of a batch (the most recent one not yet undone if None)
+    /// Files that were moved, deleted, or whose original name is taken again are reported
+    /// as conflicts and left alone
+    pub(crate) fn undo(&self, batch_id: Option<&str>) -> Result<UndoReport> {
+        let entries = self.entries()?;
+        let pending: Vec<&JournalEntry> = entries.iter().filter(|e| !e.undone).collect();
+
+        let batch_id = match batch_id {
+            Some(id) => {
+                if !entries.iter().any(|e| e.batch_id == id) {
+                    let mut recent: Vec<&str> = Vec::new();
+                    for entry in entries.iter().rev() {
+                        if !recent.contains(&entry.batch_id.as_str()) {
+                            recent.push(&entry.batch_id);
+                        }
+                    }
+                    recent.truncate(RECENT_BATCHES_SHOWN);
+                    anyhow::bail!(
+                        "Unknown batch '{}' (recent batches: {})",
+                        id,
+                        if recent.is_empty() { "none".to_string() } else { recent.join(", ") }
+                    );
+                }
+                id.to_string()
+            }
+            None => pending
+                .last()
+                .map(|e| e.batch_id.clone())
+                .ok_or_else(|| anyhow::anyhow!("Nothing to undo"))?,
+        };
+
+        let mut report = UndoReport {
+            batch_id: batch_id.clone(),
+            ..UndoReport::default()
+        };
+
+        // Newest first, so chained renames within a batch unwind in order
+        for entry in pending.iter().rev().filter(|e| e.batch_id == batch_id) {
+            let conflict = |reason: String| UndoConflict {
+                new_path: entry.new_path.clone(),
+                original_path: entry.original_path.clone(),
+                reason,
+            };
+
+            if !entry.new_path.exists() {
+                report.conflicts.push(conflict("renamed file no longer exists".to_string()));
+                continue;
+            }
+            if entry.original_path.exists() && !same_file(&entry.original_path, &entry.new_path) {
+                report.conflicts.push(conflict("original name is taken by another file".to_string()));
+                continue;
+            }
+            // Goes through `move_file`, so files organized onto another disk can come back
+            if let Err(e) = crate::renamer::move_file(&entry.new_path, &entry.original_path, false) {
+                report.conflicts.push(conflict(format!("{:#}", e)));
+                continue;
+            }
+            crate::renamer::remove_empty_folders(&entry.created_dirs);
+            if let Some(replaced) = &entry.replaced {
+                if let Err(e) = replaced.bring_back(&entry.new_path, &crate::renamer::system_restore) {
+                    report.conflicts.push(UndoConflict {
+                        new_path: entry.new_path.clone(),
+                        original_path: entry.new_path.clone(),
+                        reason: format!("renamed back, but {:#}", e),
+                    });
+                }
+            }
+
+            tracing::info!(
+                "Undone rename: {} -> {}",
+                entry.new_path.display(),
+                entry.original_path.display()
+            );
+            self.append(&JournalEntry {
+                timestamp: now(),
+                undone: true,
+                created_dirs: Vec::new(),
+                replaced: None,
+                ..(*entry).clone()
+            })?;
+            report
+                .restored
+                .push((entry.new_path.clone(), entry.original_path.clone()));
+        }
+
+        Ok(report)
+    }
+
+    /// Every rename in the journal, oldest first, with undos folded in
+    fn entries(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read undo journal {}", self.path.display()))?;
+
+        let mut entries: Vec<JournalEntry> = Vec::new();
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            // A line cut short by a crash shouldn't make the whole journal unreadable
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(line) else {
+                tracing::warn!("Skipping unreadable undo journal line");
+                continue;
+            };
+            if entry.undone {
+                if let Some(rename) = entries.iter_mut().rev().find(|e| e.same_rename(&entry)) {
+                    rename.undone = true;
+                }
+            } else {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open undo journal {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+/// Whether two paths name the same file (e.g. a case-only rename on a case-insensitive disk)
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_undo_latest_batch() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let journal = Journal::new(temp_dir.path().join("journal.jsonl"));
+        let original = temp_dir.path().join("IMG_0001.jpg");
+        let renamed = temp_dir.path().join("Sunset.jpg");
+        fs::write(&original, "photo")?;
+
+        let batch = journal.begin()?;
+        fs::rename(&original, &renamed)?;
+        batch.record(&original, &renamed, &[]);
+
+        let report = journal.undo(None)?;
+        assert_eq!(report.batch_id, batch.id);
+        assert_eq!(report.restored, vec![(renamed.clone(), original.clone())]);
+        assert!(report.conflicts.is_empty());
+        assert!(original.exists());
+
+        // The undo is journaled too, so there is nothing left to undo
+        assert!(journal.undo(None).is_err());
+        assert!(journal.undo(Some("19990101-000000")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_across_filesystems() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let journal = Journal::new(temp_dir.path().join("journal.jsonl"));
+        let original = temp_dir.path().join("IMG_0001.jpg");
+        let organized = temp_dir.path().join("archive/2023/Sunset.jpg");
+        fs::write(&original, "photo")?;
+
+        let batch = journal.begin()?;
+        fs::create_dir_all(organized.parent().unwrap())?;
+        fs::rename(&original, &organized)?;
+        batch.record(&original, &organized, &[]);
+
+        // Renaming back fails with EXDEV, so the file is copied back instead
+        crate::renamer::CROSS_DEVICE_RENAMES.with(|cross| cross.set(true));
+        let report = journal.undo(None)?;
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!(fs::read_to_string(&original)?, "photo");
+        assert!(!organized.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_reports_conflicts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let journal = Journal::new(temp_dir.path().join("journal.jsonl"));
+        let a = temp_dir.path().join("a.txt");
+        let b = temp_dir.path().join("b.txt");
+        let a_new = temp_dir.path().join("Report.txt");
+        let b_new = temp_dir.path().join("Notes.txt");
+        fs::write(&a, "a")?;
+        fs::write(&b, "b")?;
+
+        let batch = journal.begin()?;
+        for (from, to) in [(&a, &a_new), (&b, &b_new)] {
+            fs::rename(from, to)?;
+            batch.record(from, to, &[]);
+        }
+        let id = batch.id.clone();
+
+        // Someone created a new a.txt, and Notes.txt was deleted
+        fs::write(&a, "new file")?;
+        fs::remove_file(&b_new)?;
+
+        let report = journal.undo(Some(&id))?;
+        assert!(report.restored.is_empty());
+        assert_eq!(report.conflicts.len(), 2);
+        assert!(a_new.exists());
+        assert_eq!(fs::read_to_string(&a)?, "new file");
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_brings_back_replaced_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let journal = Journal::new(temp_dir.path().join("journal.jsonl"));
+        let dir = temp_dir.path();
+        let (scan, photo) = (dir.join("scan.pdf"), dir.join("IMG_1.jpg"));
+        let (lease, harbor) = (dir.join("Lease.pdf"), dir.join("Harbor.jpg"));
+        fs::write(&scan, "scan")?;
+        fs::write(&photo, "photo")?;
+
+        // Lease.pdf's old file couldn't be trashed and was kept aside; Harbor.jpg's was deleted
+        let kept = dir.join(".nameback-replaced-1-0/Lease.pdf");
+        fs::create_dir(kept.parent().unwrap())?;
+        fs::write(&kept, "old lease")?;
+        let batch = journal.begin()?;
+        fs::rename(&scan, &lease)?;
+        batch.record_replacing(&scan, &lease, &[], Some(Replaced::Kept(kept.clone())));
+        fs::rename(&photo, &harbor)?;
+        batch.record_replacing(&photo, &harbor, &[], Some(Replaced::Deleted));
+
+        let report = journal.undo(None)?;
+        assert_eq!(report.restored.len(), 2);
+        assert_eq!(fs::read_to_string(&scan)?, "scan");
+        assert_eq!(fs::read_to_string(&lease)?, "old lease");
+        assert!(!kept.parent().unwrap().exists());
+        assert_eq!(fs::read_to_string(&photo)?, "photo");
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].new_path, harbor);
+        assert!(report.conflicts[0].reason.contains("deleted"), "{}", report.conflicts[0].reason);
+        Ok(())
+    }
+}
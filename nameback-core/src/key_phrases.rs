@@ -1,88 +1,348 @@
-use std::collections::HashMap;
-
-/// Extracts key phrases from text using lightweight NLP
-/// Returns top N most relevant phrases without heavy ML dependencies
-#[allow(dead_code)]
-pub fn extract_key_phrases(text: &str, max_phrases: usize) -> Vec<String> {
-    // Tokenize and remove stop words
-    let words: Vec<&str> = text
-        .split_whitespace()
-        .filter(|w| !is_stop_word(w))
+use std::collections::{HashMap, HashSet};
+
+use crate::RenameConfig;
+
+/// How many key phrases name a file, and how many words each may have
+/// (`RenameConfig::key_phrase_count` and `key_phrase_max_words`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PhraseLimits {
+    pub count: usize,
+    pub max_words: usize,
+}
+
+impl PhraseLimits {
+    pub(crate) fn new(config: &RenameConfig) -> Self {
+        Self {
+            count: config.key_phrase_count,
+            max_words: config.key_phrase_max_words,
+        }
+    }
+}
+
+impl Default for PhraseLimits {
+    fn default() -> Self {
+        Self::new(&RenameConfig::default())
+    }
+}
+
+/// A name for long text: its best key phrases, joined by spaces
+/// Phrases sharing a word with a better one are passed over, so no word is repeated
+pub(crate) fn summarize(text: &str, limits: PhraseLimits) -> Option<String> {
+    let mut used = HashSet::new();
+    let phrases: Vec<String> = extract_key_phrases(text, usize::MAX, limits.max_words)
+        .into_iter()
+        .filter(|phrase| {
+            let words: Vec<String> = phrase.split(' ').map(str::to_lowercase).collect();
+            let fresh = words.iter().all(|word| !used.contains(word));
+            if fresh {
+                used.extend(words);
+            }
+            fresh
+        })
+        .take(limits.count)
         .collect();
+    (!phrases.is_empty()).then(|| phrases.join(" "))
+}
 
-    if words.is_empty() {
+/// Extracts key phrases from text with RAKE (Rapid Automatic Keyword Extraction)
+/// Candidates are runs of words between stop words and punctuation (every `max_words` words
+/// in a row of longer runs); each word scores its degree, the number of words it appears with
+/// summed over its runs, itself included, and a phrase the sum of its words. Words that recur
+/// in several phrases make those phrases rank first; ties go to the earlier phrase.
+/// Stop words are those of the language the text is in (see `StopWords::detect`)
+/// Chinese and Japanese text has no spaces to split words at, so it gets none
+pub(crate) fn extract_key_phrases(text: &str, max_phrases: usize, max_words: usize) -> Vec<String> {
+    if !crate::script::detect(text).uses_spaces() || max_words == 0 {
         return Vec::new();
     }
 
-    // Generate n-grams (1-3 words)
-    let mut ngrams = Vec::new();
+    let runs = content_runs(text, StopWords::detect(text));
 
-    for i in 0..words.len() {
-        // Unigrams
-        ngrams.push(words[i].to_string());
+    // A long run counts as `max_words` words, so runs of OCR noise don't outweigh phrases
+    // that recur
+    let mut degree: HashMap<String, f32> = HashMap::new();
+    for run in &runs {
+        for word in run {
+            *degree.entry(word.to_lowercase()).or_default() += run.len().min(max_words) as f32;
+        }
+    }
 
-        // Bigrams
-        if i + 1 < words.len() {
-            ngrams.push(format!("{} {}", words[i], words[i + 1]));
+    // Phrase scores, keeping the first spelling of each phrase
+    let mut scored: Vec<(String, f32)> = Vec::new();
+    let mut seen = HashSet::new();
+    for phrase in runs.iter().flat_map(|run| run.windows(run.len().min(max_words))) {
+        if !seen.insert(phrase.join(" ").to_lowercase()) {
+            continue;
         }
+        let score = phrase.iter().map(|word| degree[&word.to_lowercase()]).sum();
+        scored.push((phrase.join(" "), score));
+    }
 
-        // Trigrams
-        if i + 2 < words.len() {
-            ngrams.push(format!("{} {} {}", words[i], words[i + 1], words[i + 2]));
+    // Stable, so equal scores keep text order
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(max_phrases);
+    scored.into_iter().map(|(phrase, _)| phrase).collect()
+}
+
+/// Runs of content words in `text`, split at stop words and punctuation
+/// Runs without a letter, such as page numbers, are dropped
+fn content_runs<'a>(text: &'a str, stop_words: StopWords) -> Vec<Vec<&'a str>> {
+    let mut runs = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut flush = |run: &mut Vec<&'a str>| {
+        if run.iter().any(|word| word.chars().any(char::is_alphabetic)) {
+            runs.push(std::mem::take(run));
+        }
+        run.clear();
+    };
+
+    for fragment in text.split(is_phrase_delimiter) {
+        for token in fragment.split_whitespace() {
+            let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+            let word = stop_words.without_elision(token);
+            // An elided article or pronoun is a stop word too ("l'air s'améliore")
+            if word.len() < token.len() || word.is_empty() || stop_words.contains(word) {
+                flush(&mut run);
+            }
+            if !word.is_empty() && !stop_words.contains(word) {
+                run.push(word);
+            }
         }
+        flush(&mut run);
     }
+    runs
+}
 
-    // Score by frequency and position (earlier = better)
-    let mut scored: HashMap<String, f32> = HashMap::new();
+/// Punctuation that ends a phrase
+fn is_phrase_delimiter(c: char) -> bool {
+    matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '"' | '|' | '/' | '\n'
+        | '\t' | '\u{201C}' | '\u{201D}' | '\u{00AB}' | '\u{00BB}' | '\u{2013}' | '\u{2014}' | '\u{2022}'
+        | '\u{00BF}' | '\u{00A1}' | '\u{060C}' | '\u{061B}' | '\u{061F}')
+}
 
-    for (idx, ngram) in ngrams.iter().enumerate() {
-        // Position score: earlier text weighted higher
-        let position_score = 1.0 / (1.0 + idx as f32 * 0.05);
+/// A stop word list for one language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StopWords(&'static [&'static str]);
 
-        // Length bonus: prefer multi-word phrases
-        let word_count = ngram.split_whitespace().count();
-        let length_bonus = word_count as f32 * 0.3;
+impl StopWords {
+    const ENGLISH: StopWords = StopWords(&[
+        "a", "about", "above", "after", "again", "all", "also", "am", "an", "and", "any", "are",
+        "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but",
+        "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few", "for",
+        "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers", "him",
+        "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "may", "me", "might",
+        "more", "most", "must", "my", "no", "nor", "not", "now", "of", "off", "on", "once",
+        "only", "or", "other", "our", "ours", "out", "over", "own", "per", "same", "she",
+        "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them", "then",
+        "there", "these", "they", "this", "those", "through", "to", "too", "under", "until", "up",
+        "upon", "us", "very", "via", "was", "we", "were", "what", "when", "where", "which",
+        "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours",
+    ]);
+    const SPANISH: StopWords = StopWords(&[
+        "a", "al", "algo", "algunas", "algunos", "ante", "antes", "como", "con", "contra", "cual",
+        "cuando", "de", "del", "desde", "donde", "durante", "e", "el", "ella", "ellas", "ellos",
+        "en", "entre", "era", "es", "esa", "ese", "eso", "esta", "estas", "este", "esto", "estos",
+        "fue", "ha", "han", "hasta", "hay", "la", "las", "le", "les", "lo", "los", "mas", "más",
+        "me", "mi", "muy", "ni", "no", "nos", "o", "otra", "otras", "otro", "otros", "para",
+        "pero", "poco", "por", "porque", "que", "qué", "quien", "se", "ser", "si", "sí", "sin",
+        "sobre", "son", "su", "sus", "también", "tanto", "te", "todo", "todos", "tu", "un", "una",
+        "uno", "unos", "y", "ya", "yo",
+    ]);
+    const FRENCH: StopWords = StopWords(&[
+        "a", "à", "ai", "au", "aux", "avec", "ce", "ces", "cet", "cette", "comme", "d", "dans",
+        "de", "des", "du", "elle", "elles", "en", "est", "et", "été", "être", "eu", "il", "ils",
+        "j", "je", "l", "la", "le", "les", "leur", "leurs", "lui", "m", "ma", "mais", "me", "mes",
+        "moi", "mon", "n", "ne", "nos", "notre", "nous", "on", "ont", "ou", "où", "par", "pas",
+        "plus", "pour", "qu", "que", "qui", "s", "sa", "sans", "se", "ses", "son", "sont", "sur",
+        "ta", "te", "tes", "ton", "tous", "tout", "très", "tu", "un", "une", "vos", "votre",
+        "vous", "y", "depuis", "entre", "sous", "chez", "vers", "peut", "fait",
+    ]);
+    const GERMAN: StopWords = StopWords(&[
+        "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "dass", "dem",
+        "den", "der", "des", "die", "dies", "diese", "diesem", "diesen", "dieser", "du", "durch",
+        "ein", "eine", "einem", "einen", "einer", "eines", "er", "es", "für", "hat", "haben", "ich",
+        "ihr", "ihre", "im", "in", "ist", "mit", "nach", "nicht", "noch", "nur", "oder", "sich",
+        "sie", "sind", "so", "über", "um", "und", "uns", "unter", "vom", "von", "vor", "war",
+        "waren", "was", "wie", "wir", "wird", "werden", "zu", "zum", "zur",
+    ]);
+    const ITALIAN: StopWords = StopWords(&[
+        "a", "ai", "al", "alla", "alle", "anche", "che", "ci", "come", "con", "da", "dal", "dalla",
+        "dei", "del", "della", "delle", "dello", "di", "e", "ed", "è", "gli", "ha", "hanno", "i",
+        "il", "in", "la", "le", "lo", "ma", "mi", "ne", "negli", "nei", "nel", "nella", "non",
+        "o", "per", "più", "questa", "questo", "se", "si", "sono", "su", "sua", "sue", "sul",
+        "sulla", "suo", "suoi", "tra", "fra", "un", "una", "uno", "all", "dall", "dell", "nell",
+        "sull",
+    ]);
+    const PORTUGUESE: StopWords = StopWords(&[
+        "a", "ao", "aos", "as", "à", "com", "como", "da", "das", "de", "do", "dos", "e", "é",
+        "ela", "ele", "em", "entre", "era", "essa", "esse", "esta", "este", "foi", "isso", "já",
+        "mais", "mas", "na", "nas", "no", "nos", "não", "o", "os", "ou", "para", "pela", "pelo",
+        "por", "que", "se", "sem", "ser", "seu", "seus", "sua", "suas", "são", "também", "um",
+        "uma", "umas", "uns",
+    ]);
+    const DUTCH: StopWords = StopWords(&[
+        "aan", "al", "als", "bij", "dan", "dat", "de", "deze", "die", "dit", "door", "een", "en",
+        "er", "het", "hij", "in", "is", "je", "maar", "met", "naar", "niet", "nog", "of", "om",
+        "ook", "op", "over", "te", "tot", "uit", "van", "voor", "was", "werd", "wij", "worden",
+        "wordt", "zal", "ze", "zich", "zijn", "zo",
+    ]);
 
-        // Combine scores
-        let score = position_score + length_bonus;
+    /// The languages of `code_docstring::find_written_dates`; English first, so it wins ties
+    const ALL: [StopWords; 7] = [
+        StopWords::ENGLISH,
+        StopWords::SPANISH,
+        StopWords::FRENCH,
+        StopWords::GERMAN,
+        StopWords::ITALIAN,
+        StopWords::PORTUGUESE,
+        StopWords::DUTCH,
+    ];
 
-        *scored.entry(ngram.clone()).or_insert(0.0) += score;
+    /// The list that most words of `text` are in: stop words are the commonest words of a
+    /// language, so this is the language of the text (English if none match)
+    fn detect(text: &str) -> StopWords {
+        let words: Vec<String> = text
+            .split_whitespace()
+            .take(500)
+            .map(|token| token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .collect();
+        StopWords::ALL
+            .into_iter()
+            .rev()
+            .max_by_key(|list| words.iter().filter(|word| list.0.contains(&word.as_str())).count())
+            .unwrap_or(StopWords::ENGLISH)
     }
 
-    // Get top N phrases
-    let mut phrases: Vec<_> = scored.into_iter().collect();
-    phrases.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-    phrases.truncate(max_phrases);
+    fn contains(&self, word: &str) -> bool {
+        self.0.contains(&word.to_lowercase().as_str())
+    }
 
-    phrases.into_iter().map(|(phrase, _)| phrase).collect()
+    /// "l'entreprise", "dell'arte": `word` without the article or preposition elided onto it
+    fn without_elision<'a>(&self, word: &'a str) -> &'a str {
+        match word.split_once(['\'', '\u{2019}']) {
+            Some((elided, rest)) if self.contains(elided) => rest,
+            _ => word,
+        }
+    }
 }
 
-/// Checks if a word is a common stop word
-#[allow(dead_code)]
-fn is_stop_word(word: &str) -> bool {
-    let lower = word.to_lowercase();
-
-    let stop_words = [
-        "the", "a", "an", "and", "or", "but", "in", "on", "at",
-        "to", "for", "of", "with", "by", "from", "as", "is", "was",
-        "are", "were", "been", "be", "have", "has", "had", "do", "does",
-        "did", "will", "would", "could", "should", "may", "might", "must",
-        "can", "this", "that", "these", "those", "i", "you", "he", "she",
-        "it", "we", "they", "what", "which", "who", "when", "where", "why",
-        "how",
-    ];
+/// Checks if a word is a common (English) stop word
+pub(crate) fn is_stop_word(word: &str) -> bool {
+    StopWords::ENGLISH.contains(word)
+}
 
-    stop_words.contains(&lower.as_str())
+/// Whether `word` is a stop word in any of the languages with a list
+pub(crate) fn is_any_stop_word(word: &str) -> bool {
+    StopWords::ALL.iter().any(|list| list.contains(word))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
+    use std::time::Instant;
+
+    /// The n-gram ranking used before RAKE (frequency, position and a length bonus), kept to
+    /// benchmark against
+    fn ngram_key_phrases(text: &str, max_phrases: usize) -> Vec<String> {
+        let words: Vec<&str> = text.split_whitespace().filter(|w| !is_stop_word(w)).collect();
+        let mut ngrams = Vec::new();
+        for i in 0..words.len() {
+            ngrams.push(words[i].to_string());
+            if i + 1 < words.len() {
+                ngrams.push(format!("{} {}", words[i], words[i + 1]));
+            }
+            if i + 2 < words.len() {
+                ngrams.push(format!("{} {} {}", words[i], words[i + 1], words[i + 2]));
+            }
+        }
+
+        let mut scored: HashMap<String, f32> = HashMap::new();
+        for (idx, ngram) in ngrams.iter().enumerate() {
+            let position_score = 1.0 / (1.0 + idx as f32 * 0.05);
+            let length_bonus = ngram.split_whitespace().count() as f32 * 0.3;
+            *scored.entry(ngram.clone()).or_insert(0.0) += position_score + length_bonus;
+        }
+
+        let mut phrases: Vec<_> = scored.into_iter().collect();
+        phrases.sort_by(|a, b| b.1.total_cmp(&a.1));
+        phrases.truncate(max_phrases);
+        phrases.into_iter().map(|(phrase, _)| phrase).collect()
+    }
+
+    #[derive(serde::Deserialize)]
+    struct CorpusEntry {
+        file: String,
+        /// The top phrase is a hit if it contains any of these (case-insensitive)
+        expected: Vec<String>,
+    }
+
+    /// The fixture corpus and the text of each entry
+    fn load_corpus() -> (Vec<CorpusEntry>, Vec<String>) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/key_phrases");
+        let corpus: Vec<CorpusEntry> =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("corpus.json")).unwrap()).unwrap();
+        let texts = corpus
+            .iter()
+            .map(|entry| {
+                // As the extractors see it: lines joined, whitespace collapsed
+                let text = std::fs::read_to_string(dir.join(&entry.file)).unwrap();
+                text.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .collect();
+        (corpus, texts)
+    }
+
+    /// Ranks the fixture corpus with both algorithms; `cargo test -p nameback-core
+    /// key_phrase_corpus -- --nocapture` prints the comparison
+    #[test]
+    fn test_key_phrase_corpus() {
+        let (corpus, texts) = load_corpus();
+        let is_hit = |entry: &CorpusEntry, phrase: Option<&String>| {
+            phrase.is_some_and(|phrase| {
+                let phrase = phrase.to_lowercase();
+                entry.expected.iter().any(|expected| phrase.contains(expected.as_str()))
+            })
+        };
+
+        let (mut ngram_hits, mut rake_hits) = (0, 0);
+        println!("{:<22} {:<36} RAKE", "fixture", "n-gram");
+        for (entry, text) in corpus.iter().zip(&texts) {
+            let ngram = ngram_key_phrases(text, 1);
+            let rake = extract_key_phrases(text, 1, 3);
+            ngram_hits += is_hit(entry, ngram.first()) as usize;
+            rake_hits += is_hit(entry, rake.first()) as usize;
+            println!("{:<22} {:<36} {}", entry.file, ngram.join(""), rake.join(""));
+        }
+        println!("hits: n-gram {}/{}, RAKE {}/{}", ngram_hits, corpus.len(), rake_hits, corpus.len());
+
+        assert!(rake_hits > ngram_hits, "RAKE {} vs n-gram {}", rake_hits, ngram_hits);
+        assert!(rake_hits + 1 >= corpus.len(), "RAKE found {} of {}", rake_hits, corpus.len());
+    }
+
+    /// Times both algorithms on the fixture corpus; run with `cargo test -p nameback-core
+    /// key_phrase_corpus_benchmark -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn test_key_phrase_corpus_benchmark() {
+        const ROUNDS: u32 = 50;
+        let (_, texts) = load_corpus();
+        let time = |rank: &dyn Fn(&str) -> Vec<String>| {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                texts.iter().for_each(|text| drop(rank(text)));
+            }
+            start.elapsed() / ROUNDS
+        };
+        let ngram_time = time(&|text| ngram_key_phrases(text, 3));
+        let rake_time = time(&|text| extract_key_phrases(text, 3, 3));
+        println!("time per corpus pass: n-gram {:?}, RAKE {:?}", ngram_time, rake_time);
+    }
 
     #[test]
     fn test_extract_key_phrases_basic() {
         let text = "Quarterly Sales Report for Q3 2023 showing revenue growth";
-        let phrases = extract_key_phrases(text, 3);
+        let phrases = extract_key_phrases(text, 3, 3);
 
         assert!(!phrases.is_empty());
         // Should prioritize multi-word phrases and earlier text
@@ -92,7 +352,7 @@ mod tests {
     #[test]
     fn test_extract_key_phrases_filters_stop_words() {
         let text = "The report is about the quarterly sales and the revenue";
-        let phrases = extract_key_phrases(text, 5);
+        let phrases = extract_key_phrases(text, 5, 3);
 
         // Should not include pure stop words
         assert!(!phrases.contains(&"the".to_string()));
@@ -100,10 +360,17 @@ mod tests {
         assert!(!phrases.contains(&"and".to_string()));
     }
 
+    #[test]
+    fn test_extract_key_phrases_skips_cjk() {
+        // Split at spaces, a whole sentence would be one "word"
+        assert!(extract_key_phrases("第三四半期の売上報告書。東京本社と大阪支社の業績をまとめた。", 3, 3).is_empty());
+        assert!(!extract_key_phrases("التقرير السنوي للشركة", 3, 3).is_empty());
+    }
+
     #[test]
     fn test_extract_key_phrases_prioritizes_bigrams() {
         let text = "Machine Learning Applications in Healthcare Systems";
-        let phrases = extract_key_phrases(text, 3);
+        let phrases = extract_key_phrases(text, 3, 3);
 
         // Should include bigrams/trigrams
         let has_multi_word = phrases.iter().any(|p| p.split_whitespace().count() > 1);
@@ -113,7 +380,7 @@ mod tests {
     #[test]
     fn test_extract_key_phrases_position_weighting() {
         let text = "Important Document about routine maintenance";
-        let phrases = extract_key_phrases(text, 2);
+        let phrases = extract_key_phrases(text, 2, 3);
 
         // "Important" and "Document" should rank higher (earlier position)
         assert!(
@@ -125,14 +392,14 @@ mod tests {
     #[test]
     fn test_extract_key_phrases_empty_text() {
         let text = "";
-        let phrases = extract_key_phrases(text, 3);
+        let phrases = extract_key_phrases(text, 3, 3);
         assert!(phrases.is_empty());
     }
 
     #[test]
     fn test_extract_key_phrases_only_stop_words() {
         let text = "the and or but with";
-        let phrases = extract_key_phrases(text, 3);
+        let phrases = extract_key_phrases(text, 3, 3);
         assert!(phrases.is_empty());
     }
 
@@ -148,10 +415,37 @@ mod tests {
         assert!(!is_stop_word("sales"));
     }
 
+    #[test]
+    fn test_stop_words_of_the_text_language() {
+        // "die" and "war" are German stop words, but English words in English text
+        assert_eq!(StopWords::detect("Die Feuerwehr war im Einsatz und die Straße ist frei"), StopWords::GERMAN);
+        assert_eq!(
+            extract_key_phrases("Die Hard is a film about the war on a skyscraper", 1, 3),
+            ["Die Hard"]
+        );
+        // Elided articles split phrases like the full ones
+        assert_eq!(
+            extract_key_phrases("Le rapport de l'entreprise et l'entreprise du groupe", 1, 3),
+            ["entreprise"]
+        );
+    }
+
+    #[test]
+    fn test_summarize_joins_best_phrases() {
+        let text = "Quarterly Sales Report. The quarterly sales grew, while marketing costs fell. \
+                    Marketing costs are reviewed in the quarterly sales meeting.";
+        let one = PhraseLimits { count: 1, max_words: 3 };
+        assert_eq!(summarize(text, one).as_deref(), Some("Quarterly Sales Report"));
+        // "Sales Report" scores next, but repeats "Sales"
+        let two = PhraseLimits { count: 2, max_words: 2 };
+        assert_eq!(summarize(text, two).as_deref(), Some("Quarterly Sales marketing costs"));
+        assert_eq!(summarize("the and of", one), None);
+    }
+
     #[test]
     fn test_extract_key_phrases_respects_limit() {
         let text = "One Two Three Four Five Six Seven Eight Nine Ten";
-        let phrases = extract_key_phrases(text, 3);
+        let phrases = extract_key_phrases(text, 3, 3);
 
         assert_eq!(phrases.len(), 3, "Should return exactly max_phrases");
     }
@@ -1,69 +1,494 @@
-use anyhow::Result;
-use std::collections::HashSet;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 // Internal modules (private)
+mod acoustid;
+mod app_dirs;
+mod camera;
 mod code_docstring;
+mod collisions;
+mod config_builder;
+mod config_file;
 mod deps;
 mod deps_check;
 mod detector;
 mod dir_context;
+mod dir_names;
+mod disambiguate;
+mod duplicates;
+mod error;
+mod explain;
+mod file_state;
 mod extractor;
 mod format_handlers;
 mod generator;
 mod geocoding;
+mod geonames;
 mod image_ocr;
+mod journal;
 mod key_phrases;
+mod limits;
+mod links;
 mod location_timestamp;
 mod metadata_cache;
+mod metadata_writer;
+mod name_provider;
+mod name_style;
+mod native_metadata;
+mod ocr_cleanup;
+mod ocr_languages;
+mod organizer;
+mod paper_lookup;
 mod pdf_content;
+mod plan;
+mod pipeline;
+mod preview;
+mod process_limit;
+mod profiling;
+mod progress;
 mod rename_history;
 mod renamer;
+mod report;
+mod scan_filter;
 mod scorer;
+mod script;
 mod series_detector;
 mod stem_analyzer;
+mod stream_tags;
+mod subject_hints;
+mod template;
+mod tool_command;
 mod text_content;
+mod video_metadata;
 mod video_ocr;
+mod watcher;
 
 // Re-export public types
-pub use deps_check::{detect_needed_dependencies, Dependency, DependencyNeeds};
-pub use detector::FileCategory;
-pub use rename_history::{RenameHistory, RenameOperation};
+pub use app_dirs::{data_dir as app_data_dir, log_dir as app_log_dir};
+pub use collisions::CollisionStrategy;
+pub use config_builder::{ConfigError, RenameConfigBuilder};
+pub use config_file::{CategoryOverrides, ConfigFile, Profile};
+pub use deps_check::{
+    detect_needed_dependencies, detect_needed_dependencies_for, Dependency, DependencyNeeds,
+    DependencyStatus, OutdatedDependency,
+};
+pub use detector::{ExtensionMismatch, FileCategory};
+pub use duplicates::DuplicateHandling;
+pub use error::NamebackError;
+pub use explain::{ExplainedCandidate, Explanation};
+pub use extractor::{FileMetadata, MetadataBackend};
+pub use name_style::{NameCase, NameStyle, WordSeparator};
+pub use file_state::DeferReason;
+pub use generator::check_filename;
+pub use geocoding::{GeocodeBackend, GeocodeSource, OnlineGeocoder};
+pub use geonames::OfflineGeocoder;
+pub use journal::{UndoConflict, UndoReport};
+pub use limits::LimitExceeded;
+pub use links::SymlinkPolicy;
+pub use location_timestamp::{
+    BlockedArea, LocationData, LocationPrecision, TimestampPosition, TimestampPrecision, TimestampStyle,
+};
+pub use metadata_cache::CacheStats;
+pub use metadata_writer::MetadataWriteMode;
+pub use name_provider::NameProvider;
+pub use ocr_languages::{installed_ocr_languages, missing_ocr_languages};
+pub use preview::{preview_image, preview_text};
+pub use plan::{OnPlanError, PlanConflict, PlanFailure, PlanOutcome, PlannedRename, RenamePlan};
+pub use profiling::{FileTimings, ProfileReport, Stage, StageStats};
+pub use progress::Progress;
+pub use rename_history::{RenameHistory, RenameOperation, RollbackToken};
+pub use renamer::{OnConflict, RenameError};
+pub use report::{RunEvent, RunReport};
+pub use scorer::{NameCandidate, NameSource, Penalty, RejectReason, RejectedCandidate, ScoreBreakdown};
+pub use series_detector::Shot;
+pub use template::TEMPLATE_VARIABLES;
+pub use video_ocr::FrameAggregation;
+pub use watcher::{StopSignal, WatchEvent, WatchOptions};
 
 /// Configuration options for the rename engine
 #[derive(Debug, Clone)]
 pub struct RenameConfig {
     /// Skip hidden files and directories (starting with .)
     pub skip_hidden: bool,
+    /// How many folder levels to descend (None = all; 1 = only files directly in the directory)
+    pub max_depth: Option<usize>,
+    /// Only analyze files matching one of these globs (empty = all files), e.g. `**/*.pdf`
+    /// Patterns without `/` match a name at any depth
+    pub include_globs: Vec<String>,
+    /// Skip files and folders matching these globs, e.g. `node_modules/` (trailing `/` = folders only)
+    pub exclude_globs: Vec<String>,
+    /// Only analyze files of these categories (None = all); other files are left out of the results
+    pub categories: Option<HashSet<FileCategory>>,
     /// Include GPS location in filenames (for photos/videos)
     pub include_location: bool,
+    /// How precisely that location is named: country, region, city (default) or raw
+    /// coordinates. Coarser than a city needs geocoding, and a place that can't be named that
+    /// coarsely is left out rather than named more precisely
+    pub location_precision: LocationPrecision,
+    /// Private places (home, say): files taken within these areas get no location at all
+    pub location_blocklist: Vec<BlockedArea>,
     /// Include formatted timestamp in filenames
     pub include_timestamp: bool,
+    /// strftime pattern for that timestamp, e.g. `%Y%m%d` (None = `2023-10-15`, or
+    /// `2023-10-15_143022` with `TimestampPrecision::DateTime`)
+    pub timestamp_format: Option<String>,
+    /// Whether the timestamp has the time of day too (ignored when `timestamp_format` is set)
+    pub timestamp_precision: TimestampPrecision,
+    /// Whether the timestamp starts or ends names
+    pub timestamp_position: TimestampPosition,
+    /// Append the number of attachments to email names, e.g. `_3attachments`
+    pub email_attachment_count: bool,
+    /// Guess a rough subject for photos (document scan, receipt, whiteboard, screenshot,
+    /// person, landscape) and offer it as a name, e.g. `Receipt_2023-10-15_Seattle.jpg`
+    pub subject_hints: bool,
+    /// Name archives after their contents (README/manifest title or top folder, dominant file
+    /// type, newest year), e.g. `ProjectX_source_2021.zip`
+    pub peek_archives: bool,
+    /// Name receipts and invoices (PDFs and photos, told apart by their keywords and layout)
+    /// from their date, vendor and total, e.g. `2023-10-15_AcmeCorp_invoice_142.50.pdf`
+    pub receipt_mode: bool,
+    /// Tesseract language packs OCR reads text in, e.g. `["eng", "deu", "jpn"]`, used together
+    /// (empty = try Chinese and English one at a time, or whatever packs are installed)
+    /// Packs that aren't installed are skipped with a warning
+    pub ocr_languages: Vec<String>,
     /// Use multi-frame video analysis (slower but better OCR)
     pub multiframe_video: bool,
+    /// Files analyzed at the same time (None = one per CPU core, or per thread of the
+    /// pool given to `RenameEngine::with_thread_pool`)
+    pub max_parallel_files: Option<usize>,
+    /// External OCR and media tools (tesseract, ffmpeg, pdftoppm, fpcalc) run at the same
+    /// time, across all files being analyzed (None = one per file analyzed at once)
+    pub max_ocr_processes: Option<usize>,
+    /// How multi-frame video OCR combines text across frames
+    pub frame_aggregation: FrameAggregation,
+    /// Most frames multi-frame video OCR reads per video, picked at scene changes
+    /// (title cards, slides) and topped up with evenly spaced frames
+    pub video_frames: usize,
+    /// How many key phrases name a file from long text (PDF and text content, OCR), best first
+    pub key_phrase_count: usize,
+    /// Most words in one key phrase
+    pub key_phrase_max_words: usize,
+    /// OCR text whose words tesseract read with a lower mean confidence (0-100) doesn't name
+    /// files; the least confident words are dropped before that either way
+    pub min_ocr_confidence: u8,
+    /// Where embedded metadata is read from (exiftool, or the built-in reader without it)
+    pub metadata_backend: MetadataBackend,
     /// Use geocoding to convert GPS coordinates to city names (defaults to true)
     /// When false, shows coordinates like "47.6N_122.3W" instead of "Seattle_WA"
     pub geocode: bool,
+    /// Where place names come from: the offline city database or the online service
+    pub geocode_source: GeocodeSource,
+    /// GeoNames city database for offline geocoding (None = `cities1000.txt` in the
+    /// `geonames` folder of the data directory, downloaded there on first use)
+    pub geocode_database: Option<PathBuf>,
+    /// AcoustID API key; when set, audio files without a title are identified from their
+    /// Chromaprint fingerprint (fpcalc) online and named `Artist - Title` (None = no lookups)
+    pub acoustid_key: Option<String>,
+    /// Name PDFs of academic papers `FirstAuthor Year Title` from the Crossref or arXiv record
+    /// of the DOI or arXiv identifier on their first page (online, results cached)
+    pub paper_lookup: bool,
     /// Enable metadata caching to speed up re-analysis
     pub enable_cache: bool,
-    /// Cache file path (None = use default location)
+    /// Analyze every file afresh, ignoring cached results but still caching the new ones
+    pub refresh_cache: bool,
+    /// Cache database path (None = metadata.sqlite in the user cache directory, shared by
+    /// every scanned directory)
     pub cache_path: Option<PathBuf>,
+    /// Undo journal recording every rename (None = journal.jsonl in the user data directory)
+    pub journal_path: Option<PathBuf>,
+    /// Filename template such as `{camera}_{name}` or `{category}/{yyyy}/{title}` (None = the
+    /// picked name alone). See `TEMPLATE_VARIABLES`; `/` sorts files into subfolders.
+    /// Location and timestamp are appended when enabled unless the template places them
+    pub template: Option<String>,
+    /// How the words of proposed names are cased (snake_case, kebab-case, Title_Case,
+    /// camelCase, or as found)
+    pub name_case: NameCase,
+    /// What joins the words of proposed names, and the counters and details that tell
+    /// colliding names apart (None = `-` for kebab-case, `_` otherwise)
+    pub word_separator: Option<WordSeparator>,
+    /// Fewest files that make a numbered series (`IMG_001`, ...) or a photo burst
+    pub series_min_files: usize,
+    /// Digits series numbers are padded to at least (None = 3 for numbered series, 2 for
+    /// bursts); larger numbers keep all their digits
+    pub series_padding: Option<usize>,
+    /// What goes between a series' name and its number (None = numbered series keep their
+    /// own `_001`, `(001)`, `-001` or ` 001`, bursts use `word_separator`)
+    pub series_separator: Option<WordSeparator>,
+    /// Number of the first file of a series (None = numbered series keep their numbers,
+    /// bursts count from 1)
+    pub series_start: Option<usize>,
+    /// Give files whose content is in another format than their extension says (a PNG
+    /// named `.jpg`, a PDF without an extension) the extension of that format in their new
+    /// name. Mismatches are reported in `FileAnalysis::extension_mismatch` either way
+    pub fix_extensions: bool,
+    /// Lower-case the extensions of new names (`IMG_0042.JPG` → `Sunset.jpg`) instead of
+    /// keeping them as they are
+    pub lowercase_extensions: bool,
+    /// Folder template such as `{yyyy}/{mm}/{location}` or `{category}` that turns on
+    /// organization mode: files are moved into these folders (under `organize_root`) as well
+    /// as renamed. Same variables as `template`; empty folders are dropped
+    pub organize: Option<String>,
+    /// Where `organize` folders are created (None = next to each file)
+    pub organize_root: Option<PathBuf>,
+    /// What to do with files whose content exactly matches another file
+    pub duplicates: DuplicateHandling,
+    /// What to do when a proposed name is already taken by another file of the batch
+    pub collision_strategy: CollisionStrategy,
+    /// What to do when a new name is already taken by a file on disk outside the batch:
+    /// skip the rename (default), add a counter, or replace that file, moving it to the
+    /// system trash or deleting it
+    pub on_conflict: OnConflict,
+    /// Write the derived title into the file's metadata (XMP/EXIF title, PDF Title, ID3 title)
+    /// as well as, or instead of, renaming. With `Also`, formats that can't take a title are
+    /// just renamed; with `Only` they are left alone
+    pub write_metadata: MetadataWriteMode,
+    /// Rename sidecars (`.xmp`, `.aae`, `.srt`, `.vtt`, `.thm`) and RAW+JPEG pairs together
+    /// with the file they belong to, as one unit under its new name
+    pub rename_sidecars: bool,
+    /// Also propose names for folders still called `New Folder (3)` or the like, from what
+    /// the files directly in them have in common (e.g. `Tax_Documents_2022`). Folders are
+    /// renamed after the files, deepest first; the folders given to scan keep their names
+    pub rename_directories: bool,
+    /// Options that differ for one file category (`[category.<name>]` in the config file)
+    pub category_overrides: HashMap<FileCategory, CategoryOverrides>,
+    /// Minimum confidence score (see `FileAnalysis::confidence`) a name needs to be proposed
+    /// Names scoring below 2.0 are never proposed, so only higher values have an effect
+    pub min_confidence: f32,
+    /// Never switch system DNS to public servers when installing dependencies runs into
+    /// network errors; downloads are retried with backoff instead (see `InstallOptions`)
+    pub never_touch_dns: bool,
+    /// Longest an external tool (exiftool, ffmpeg, pdftoppm, fpcalc, tesseract, ...) may run
+    /// over one file before it is stopped; the file gets a timeout issue and the batch goes on
+    pub tool_timeout: std::time::Duration,
+    /// Files modified within this long are left alone as still arriving, both before they
+    /// are analyzed and before they are renamed (None = only files seen changing during
+    /// analysis are)
+    pub stable_for: Option<std::time::Duration>,
+    /// Files larger than this many megabytes are left out of analysis (None = no limit)
+    pub max_file_size_mb: Option<u64>,
+    /// PDFs with more pages are named from their metadata alone, without reading or OCR'ing
+    /// their text (None = no limit)
+    pub max_pdf_pages: Option<u32>,
+    /// Videos longer than this many minutes are named from their metadata alone, without
+    /// OCR'ing frames (None = no limit)
+    pub max_video_duration_min: Option<u64>,
+    /// What scanning does with symbolic links: skip them (default), follow them to the
+    /// files and folders they point to, or rename the links themselves
+    pub symlinks: SymlinkPolicy,
 }
 
 impl Default for RenameConfig {
     fn default() -> Self {
         Self {
             skip_hidden: false,
+            max_depth: None, // Whole tree
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            categories: None, // Every category
             include_location: true, // Include GPS location by default
+            location_precision: LocationPrecision::City,
+            location_blocklist: Vec::new(),
             include_timestamp: true, // Include timestamps by default
+            timestamp_format: None,
+            timestamp_precision: TimestampPrecision::Date,
+            timestamp_position: TimestampPosition::Suffix,
+            email_attachment_count: false,
+            subject_hints: false, // Reads every photo's pixels, so opt-in
+            peek_archives: true, // Listings are capped in size and time
+            receipt_mode: false,
+            ocr_languages: Vec::new(), // Pick from the installed language packs
             multiframe_video: true, // Multi-frame video analysis is now the default
+            max_parallel_files: None, // One per core
+            max_ocr_processes: None,
+            frame_aggregation: FrameAggregation::default(),
+            video_frames: 6, // Each one is an ffmpeg run and an OCR pass
+            key_phrase_count: 1,
+            key_phrase_max_words: 3,
+            min_ocr_confidence: 40, // Below this, OCR text is mostly misread
+            metadata_backend: MetadataBackend::default(), // exiftool if installed
             geocode: true, // Geocoding is enabled by default
+            geocode_source: GeocodeSource::default(), // Offline database if downloaded
+            geocode_database: None,
+            acoustid_key: None, // Fingerprint lookups are opt-in
+            paper_lookup: false, // Sends identifiers to Crossref and arXiv, so opt-in
             enable_cache: true, // Metadata caching enabled by default
+            refresh_cache: false,
             cache_path: None, // Use default cache location
+            journal_path: None, // Use default journal location
+            template: None,
+            name_case: NameCase::Keep,
+            word_separator: None,
+            series_min_files: 3, // Two similar names are often a coincidence
+            series_padding: None,
+            series_separator: None,
+            series_start: None,
+            fix_extensions: false, // Report mismatched extensions, keep them
+            lowercase_extensions: false, // Extensions keep their case
+            organize: None, // Rename in place
+            organize_root: None,
+            duplicates: DuplicateHandling::default(), // Flag only
+            collision_strategy: CollisionStrategy::default(), // Tell apart by content
+            on_conflict: OnConflict::default(), // Never touch files outside the batch
+            write_metadata: MetadataWriteMode::Off,
+            rename_sidecars: true, // Keep sidecars with their files
+            rename_directories: false, // Files only
+            category_overrides: HashMap::new(),
+            min_confidence: 0.0, // Any name the scorer accepts
+            never_touch_dns: false, // DNS fallback allowed
+            tool_timeout: tool_command::DEFAULT_TOOL_TIMEOUT,
+            stable_for: None,
+            max_file_size_mb: None,
+            max_pdf_pages: None,
+            max_video_duration_min: None,
+            symlinks: SymlinkPolicy::Skip, // Links could lead outside the scanned folder
         }
     }
 }
 
+impl RenameConfig {
+    /// Start building a validated configuration
+    pub fn builder() -> RenameConfigBuilder {
+        RenameConfigBuilder::new()
+    }
+
+    /// Whether files of `category` are analyzed
+    pub fn includes_category(&self, category: &FileCategory) -> bool {
+        self.categories.as_ref().is_none_or(|categories| categories.contains(category))
+    }
+
+    /// `name_case` and `word_separator` as the generator applies them
+    pub(crate) fn name_style(&self) -> NameStyle {
+        NameStyle {
+            case: self.name_case,
+            separator: self.word_separator,
+            lowercase_extension: self.lowercase_extensions,
+        }
+    }
+
+    /// `series_min_files`, `series_padding`, `series_separator` and `series_start` as series
+    /// and bursts are numbered with them
+    pub(crate) fn series_numbering(&self) -> series_detector::SeriesNumbering {
+        series_detector::SeriesNumbering {
+            min_files: self.series_min_files,
+            padding: self.series_padding,
+            separator: self.series_separator,
+            start: self.series_start,
+        }
+    }
+
+    /// Where the metadata cache is kept: `cache_path`, else a database in the user cache directory
+    pub(crate) fn cache_location(&self) -> Option<PathBuf> {
+        self.cache_path.clone().or_else(metadata_cache::MetadataCache::default_path)
+    }
+
+    /// Where paper lookups are kept between runs, next to the metadata cache (None with
+    /// caching off, so lookups are kept for this run only)
+    pub(crate) fn paper_cache_path(&self) -> Option<PathBuf> {
+        self.enable_cache
+            .then(|| self.cache_location().map(|path| paper_lookup::cache_file(&path)))
+            .flatten()
+    }
+
+    /// `timestamp_format`, `timestamp_precision` and `timestamp_position` as the generator
+    /// applies them
+    pub(crate) fn timestamp_style(&self) -> TimestampStyle {
+        TimestampStyle {
+            format: self.timestamp_format.clone(),
+            precision: self.timestamp_precision,
+            position: self.timestamp_position,
+        }
+    }
+
+    /// Tells cache entries whose names were made in another name, timestamp or location
+    /// style, from other key phrase limits, OCR confidence or series numbering, in receipt,
+    /// paper lookup or AcoustID mode, with extensions fixed, from another template, with other
+    /// content sources (location, timestamps, geocoding and its source, the metadata reader,
+    /// OCR languages, subject hints, attachment counts, archive listings) or per-category
+    /// overrides, apart (empty for the defaults)
+    pub(crate) fn style_cache_key(&self) -> String {
+        let mut key = self.name_style().cache_key();
+        let timestamp = self.timestamp_style().cache_key();
+        if !timestamp.is_empty() {
+            key = format!("{};{}", key, timestamp);
+        }
+        if self.location_precision != LocationPrecision::City || !self.location_blocklist.is_empty() {
+            let blocked: Vec<String> = self.location_blocklist.iter().map(|area| area.to_string()).collect();
+            key = format!("{};{}/{}", key, self.location_precision, blocked.join("+"));
+        }
+        let defaults = Self::default();
+        if (self.key_phrase_count, self.key_phrase_max_words) != (defaults.key_phrase_count, defaults.key_phrase_max_words) {
+            key = format!("{};phrases/{}x{}", key, self.key_phrase_count, self.key_phrase_max_words);
+        }
+        if self.min_ocr_confidence != defaults.min_ocr_confidence {
+            key = format!("{};ocr/{}", key, self.min_ocr_confidence);
+        }
+        if self.receipt_mode {
+            key = format!("{};receipts", key);
+        }
+        if self.paper_lookup {
+            key = format!("{};papers", key);
+        }
+        // Whether music is fingerprinted, not the key itself, which is a secret
+        if self.acoustid_key.is_some() {
+            key = format!("{};acoustid", key);
+        }
+        let series = self.series_numbering().cache_key();
+        if !series.is_empty() {
+            key = format!("{};series/{}", key, series);
+        }
+        if self.fix_extensions {
+            key = format!("{};extensions", key);
+        }
+        if let Some(template) = &self.template {
+            key = format!("{};template/{}", key, template);
+        }
+        let sources = [
+            ("location", self.include_location, defaults.include_location),
+            ("timestamp", self.include_timestamp, defaults.include_timestamp),
+            ("geocode", self.geocode, defaults.geocode),
+            ("subject-hints", self.subject_hints, defaults.subject_hints),
+            ("attachment-count", self.email_attachment_count, defaults.email_attachment_count),
+            ("archives", self.peek_archives, defaults.peek_archives),
+        ];
+        for (name, value, default) in sources {
+            if value != default {
+                key = format!("{};{}/{}", key, name, if value { "on" } else { "off" });
+            }
+        }
+        if !self.ocr_languages.is_empty() {
+            key = format!("{};languages/{}", key, self.ocr_languages.join("+"));
+        }
+        if self.metadata_backend != defaults.metadata_backend {
+            key = format!("{};metadata/{}", key, self.metadata_backend);
+        }
+        if self.geocode_source != defaults.geocode_source {
+            key = format!("{};geocoder/{}", key, self.geocode_source);
+        }
+        // Sorted, as the map's order differs between runs
+        let mut overrides: Vec<String> = self
+            .category_overrides
+            .iter()
+            .filter(|(_, overrides)| **overrides != CategoryOverrides::default())
+            .map(|(category, overrides)| format!("{:?}/{:?}", category, overrides))
+            .collect();
+        overrides.sort();
+        if !overrides.is_empty() {
+            key = format!("{};overrides/{}", key, overrides.join("+"));
+        }
+        key
+    }
+
+    /// Minimum confidence for files of `category`, after its overrides
+    pub fn min_confidence_for(&self, category: &FileCategory) -> f32 {
+        self.category_overrides
+            .get(category)
+            .and_then(|overrides| overrides.min_confidence)
+            .unwrap_or(self.min_confidence)
+    }
+}
+
 /// Result of analyzing a single file
 #[derive(Debug, Clone)]
 pub struct FileAnalysis {
@@ -73,8 +498,148 @@ pub struct FileAnalysis {
     pub original_name: String,
     /// Proposed new filename (None if no suitable name found)
     pub proposed_name: Option<String>,
+    /// The picked name as readable text, before location, date and counters are added
+    /// Written into the file's metadata with `RenameConfig::write_metadata`
+    pub title: Option<String>,
+    /// Counter appended to the proposed name to avoid a collision (e.g. 2 for `name_2.jpg`)
+    /// Only used when nothing in the file's content (vendor, amount, hash) tells it apart
+    /// Not tracked for cached results or series names
+    pub collision_suffix: Option<u32>,
+    /// How a collision with another file's proposed name was settled (None = no collision)
+    pub collision_strategy: Option<CollisionStrategy>,
+    /// Folder the file is moved into in organization mode (None = renamed in its own folder)
+    pub destination: Option<PathBuf>,
+    /// The file this one is an exact copy of (of a set of copies, the one with the first path)
+    pub duplicate_of: Option<PathBuf>,
+    /// Sidecars (`.xmp`, `.aae`, …) and the other half of a RAW+JPEG pair, renamed along
+    /// with this file (see `RenameConfig::rename_sidecars`)
+    pub companions: Vec<PathBuf>,
     /// File category detected
     pub file_category: FileCategory,
+    /// Quality score of the picked name (higher is better, 5.0 or more is a good name)
+    /// 0.0 when no name was found
+    pub confidence: f32,
+    /// Where the picked name came from (`Fallback` when no name was found)
+    pub name_source: NameSource,
+    /// Problems encountered during analysis (empty for clean or cached results)
+    pub errors: Vec<AnalysisIssue>,
+    /// Candidate names the scorer turned down, and why (empty for cached results)
+    pub rejected: Vec<RejectedCandidate>,
+    /// Other names the file could get, from the next best candidates (best first), for
+    /// picking one in place of the proposal; empty without a proposed name or when organizing
+    pub alternatives: Vec<NameAlternative>,
+    /// Set when the file was left alone because it is in use or still arriving
+    /// Such files have no proposed name; analyze again later
+    pub deferred: Option<DeferReason>,
+    /// Set when the file was over one of the size limits (`RenameConfig::max_file_size_mb`
+    /// and friends). Files too large weren't analyzed and have no proposed name; PDFs with
+    /// too many pages and videos too long were named without reading their content
+    pub over_limit: Option<LimitExceeded>,
+    /// Set when the file is named as one of a series: numbered files (`IMG_001.jpg`, ...)
+    /// or a burst of photos taken seconds apart
+    pub series: Option<SeriesInfo>,
+    /// Set when the file's content is in another format than its extension says; the
+    /// proposed name has the detected extension with `RenameConfig::fix_extensions`
+    pub extension_mismatch: Option<ExtensionMismatch>,
+}
+
+impl FileAnalysis {
+    /// Extension of the proposed name: the detected one when the mismatch was fixed,
+    /// otherwise the file's own (both parts of a `tar.gz`), in the case the name has it in
+    pub(crate) fn new_extension(&self) -> Option<&str> {
+        let extension = match &self.extension_mismatch {
+            Some(mismatch) if mismatch.fixed => Some(mismatch.detected.as_str()),
+            _ => generator::full_extension(&self.original_path),
+        }?;
+        let in_name = self
+            .proposed_name
+            .as_deref()
+            .and_then(|name| name.get(name.len().checked_sub(extension.len())?..))
+            .filter(|in_name| in_name.eq_ignore_ascii_case(extension));
+        Some(in_name.unwrap_or(extension))
+    }
+}
+
+/// Where a file stands in a series of files named together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SeriesInfo {
+    /// Shared by the files of one series, unique among the series of one analysis
+    pub id: usize,
+    /// Position in the series, from 1
+    pub index: usize,
+    /// Files in the series
+    pub total: usize,
+    pub kind: SeriesKind,
+}
+
+/// How the files of a series were found to belong together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesKind {
+    /// Names with a running number (`IMG_001`, `Scan (2)`)
+    Numbered,
+    /// Photos from one camera, at one resolution, taken within seconds of each other
+    Burst,
+}
+
+/// A name a file could get instead of its proposed one, from a candidate the winner outscored
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NameAlternative {
+    /// The filename, made like the proposed one (location, date, extension, ...)
+    pub name: String,
+    /// The candidate name it was made from
+    pub title: String,
+    pub source: NameSource,
+    pub score: f32,
+}
+
+/// A problem encountered while analyzing a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisIssue {
+    /// Stage where the problem occurred
+    pub stage: Stage,
+    /// Human-readable description
+    pub message: String,
+    /// Whether analysis carried on after the problem (e.g. OCR failed but metadata was used)
+    pub recoverable: bool,
+    /// Whether an external tool was stopped for running longer than `RenameConfig::tool_timeout`
+    pub timed_out: bool,
+}
+
+impl AnalysisIssue {
+    /// An issue after which analysis continued
+    pub fn recoverable(stage: Stage, message: impl Into<String>) -> Self {
+        Self {
+            stage,
+            message: message.into(),
+            recoverable: true,
+            timed_out: false,
+        }
+    }
+
+    /// An issue that ended analysis of the file
+    pub fn fatal(stage: Stage, message: impl Into<String>) -> Self {
+        Self {
+            stage,
+            message: message.into(),
+            recoverable: false,
+            timed_out: false,
+        }
+    }
+
+    /// An external tool that ran too long was stopped; analysis went on without it
+    pub fn timeout(stage: Stage, message: impl Into<String>) -> Self {
+        Self {
+            timed_out: true,
+            ..Self::recoverable(stage, message)
+        }
+    }
+}
+
+impl std::fmt::Display for AnalysisIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.stage.name(), self.message)
+    }
 }
 
 /// Result of a rename operation
@@ -84,21 +649,155 @@ pub struct RenameResult {
     pub original_path: PathBuf,
     /// New filename applied
     pub new_name: String,
+    /// Full path the file was (or in dry-run mode, would be) renamed to
+    pub new_path: PathBuf,
+    /// Counter appended to the name to avoid a collision, if any
+    pub collision_suffix: Option<u32>,
+    /// How a collision with another file's proposed name was settled, if there was one
+    pub collision_strategy: Option<CollisionStrategy>,
+    /// Token for undoing this rename via `RenameHistory::undo_token`
+    /// Only set when the rename was recorded in a history
+    pub rollback_token: Option<RollbackToken>,
     /// Whether the rename was successful
     pub success: bool,
     /// Error message if failed
     pub error: Option<String>,
+    /// Why the rename failed, when it was one of the causes `RenameError` tells apart
+    pub error_kind: Option<RenameError>,
+    /// Set when the file was not renamed because it is in use (not counted as an error)
+    pub deferred: Option<DeferReason>,
+    /// Undo journal batch the rename was recorded in, for `RenameEngine::undo_batch`
+    pub batch_id: Option<String>,
+    /// Whether the title was written into the file's metadata (in dry-run mode, whether it
+    /// could be). Undo restores names only, not metadata
+    pub metadata_written: bool,
+    /// Sidecars and paired files moved along with the file, as (old path, new path)
+    pub companions: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Progress reported by `RenameEngine::analyze_directory_streaming`
+#[derive(Debug, Clone)]
+pub enum AnalysisEvent {
+    /// The directory was scanned and this many files will be reported
+    Started { total: usize },
+    /// A file entered a pipeline stage (`Detect`, `Exif`, `Ocr` or `Score`)
+    Stage { path: PathBuf, stage: Stage },
+    /// Files done out of the total, the current file and stage, and the estimated time left
+    /// Sent whenever a file enters a stage or finishes
+    Progress(Progress),
+    /// A file's analysis is complete
+    Analyzed(FileAnalysis),
+    /// A file reported earlier was changed by a final pass over all files: deferred because
+    /// it was still being written, or renamed to tell it apart from a duplicate
+    Revised(FileAnalysis),
+}
+
+/// Receiver of `AnalysisEvent`s, shared with the pipeline's worker threads
+type EventSink<'a> = Mutex<dyn FnMut(AnalysisEvent) + Send + 'a>;
+
+/// Result of incremental re-analysis (see `RenameEngine::reanalyze_directory`)
+#[derive(Debug, Clone)]
+pub struct IncrementalAnalysis {
+    /// Analyses for all current files (reused or fresh), in scan order
+    pub analyses: Vec<FileAnalysis>,
+    /// Files that were new or modified and therefore re-analyzed
+    pub changed: Vec<PathBuf>,
+    /// Previously analyzed files that no longer exist
+    pub removed: Vec<PathBuf>,
+}
+
+/// Outcome of a full analyze-and-rename run
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    /// Analysis result for every scanned file
+    pub analyses: Vec<FileAnalysis>,
+    /// Rename result for every file that had a proposed name
+    pub results: Vec<RenameResult>,
+    /// Number of files without a proposed name (nothing to rename)
+    pub skipped: usize,
+    /// Number of files left alone because they were in use or still being written
+    pub deferred: usize,
+    /// Number of renames that failed
+    pub errors: usize,
+    /// Wall time of the whole run
+    pub duration: std::time::Duration,
+    /// Metadata cache statistics (None if caching is disabled)
+    pub cache_stats: Option<CacheStats>,
+    /// Per-file, per-stage timings
+    pub profile: ProfileReport,
+}
+
+impl RunSummary {
+    /// Number of files renamed successfully (or that would be, in dry-run mode)
+    pub fn renamed(&self) -> usize {
+        self.results.iter().filter(|r| r.success).count()
+    }
 }
 
 /// Main rename engine that handles file analysis and renaming
 pub struct RenameEngine {
     config: RenameConfig,
+    /// Stage timings for the most recent analyze/rename run
+    profile: std::sync::Mutex<ProfileReport>,
+    /// Cache statistics for the most recent analysis (None if caching is disabled)
+    cache_stats: std::sync::Mutex<Option<CacheStats>>,
+    /// Host-provided pool for analysis work (None = nameback's own threads)
+    thread_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+    /// Extra candidate name sources, asked in registration order
+    name_providers: Vec<std::sync::Arc<dyn NameProvider>>,
+    /// Runtime the external tools run on, set only for the engine of an async analysis
+    tool_runtime: Option<tokio::runtime::Handle>,
 }
 
 impl RenameEngine {
     /// Create a new rename engine with the given configuration
     pub fn new(config: RenameConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            profile: std::sync::Mutex::new(ProfileReport::default()),
+            cache_stats: std::sync::Mutex::new(None),
+            thread_pool: None,
+            name_providers: Vec::new(),
+            tool_runtime: None,
+        }
+    }
+
+    /// Run analysis work on the given rayon pool instead of nameback's own threads
+    ///
+    /// Worker counts follow the pool size, so an embedding application can keep
+    /// nameback from competing with its global pool. Only lightweight coordinator
+    /// threads that wait on channels are created outside the pool.
+    pub fn with_thread_pool(mut self, pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    /// Also consider the names `provider` suggests for each analyzed file
+    ///
+    /// Its candidates are scored together with nameback's own (see `NameProvider`). Cached
+    /// results aren't re-analyzed, so disable the cache or clear it after adding a provider
+    pub fn with_name_provider(mut self, provider: impl NameProvider + 'static) -> Self {
+        self.name_providers.push(std::sync::Arc::new(provider));
+        self
+    }
+
+    /// Stop external tools (ffmpeg, pdftoppm, tesseract, ...) that run longer than `timeout`
+    /// on one file; the same as setting `RenameConfig::tool_timeout`
+    pub fn with_tool_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.config.tool_timeout = timeout;
+        self
+    }
+
+    /// Per-file, per-stage timings for the most recent run
+    /// Reset by `analyze_directory`; `rename_files` adds rename timings to it
+    pub fn profile_report(&self) -> ProfileReport {
+        self.profile.lock().unwrap().clone()
+    }
+
+    /// Cache hits, misses and invalidations of the most recent analysis, and the cache's size
+    /// None before the first analysis or with caching disabled
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache_stats.lock().unwrap().clone()
     }
 
     /// Create a rename engine with default configuration
@@ -108,358 +807,2140 @@ impl RenameEngine {
 
     /// Analyze all files in a directory and return proposed renames
     /// This does not perform any actual renaming - use for preview
-    pub fn analyze_directory(&self, directory: &Path) -> Result<Vec<FileAnalysis>> {
-        let analyses;
+    pub fn analyze_directory(&self, directory: &Path) -> Result<Vec<FileAnalysis>, NamebackError> {
+        self.analyze_paths(&[directory.to_path_buf()])
+    }
 
-        // Scan files
-        let files = self.scan_files(directory)?;
+    /// Analyze a directory from async code, returning the same analyses as `analyze_directory`
+    ///
+    /// The analysis runs on tokio's blocking threads, and the external tools it needs run as
+    /// tasks on the current runtime with async process spawning: at most
+    /// `max_ocr_processes` at once, each stopped after `tool_timeout`.
+    /// The runtime needs its IO and time drivers enabled. Must be awaited within a tokio
+    /// runtime; dropping the future doesn't stop an analysis already started
+    pub async fn analyze_directory_async(&self, directory: &Path) -> Result<Vec<FileAnalysis>, NamebackError> {
+        let engine = RenameEngine {
+            thread_pool: self.thread_pool.clone(),
+            name_providers: self.name_providers.clone(),
+            tool_runtime: Some(tokio::runtime::Handle::current()),
+            ..RenameEngine::new(self.config.clone())
+        };
+        let directory = directory.to_path_buf();
+        let (engine, analyses) = tokio::task::spawn_blocking(move || {
+            let analyses = engine.analyze_directory(&directory);
+            (engine, analyses)
+        })
+        .await
+        .map_err(|e| NamebackError::Other(e.into()))?;
 
-        // Load or create metadata cache
-        let cache_path = self.config.cache_path.clone().unwrap_or_else(|| {
-            directory.join(".nameback_cache.json")
-        });
+        *self.profile.lock().unwrap() = engine.profile_report();
+        *self.cache_stats.lock().unwrap() = engine.cache_stats();
+        analyses
+    }
 
-        let mut cache = if self.config.enable_cache {
-            metadata_cache::MetadataCache::load(cache_path.clone()).unwrap_or_else(|_| {
-                log::debug!("Failed to load cache, creating new one");
-                metadata_cache::MetadataCache::new(cache_path.clone())
-            })
-        } else {
-            metadata_cache::MetadataCache::new(cache_path.clone())
-        };
+    /// Analyze a directory, reporting each file as soon as it is done
+    /// `on_event` receives the file count up front, each file's progress through the
+    /// pipeline stages, and every finished analysis, so callers can show results while
+    /// the rest of the directory is still being analyzed. Events arrive one at a time,
+    /// from worker threads as well as the calling thread.
+    /// Returns the same analyses as `analyze_directory`
+    pub fn analyze_directory_streaming(
+        &self,
+        directory: &Path,
+        on_event: impl FnMut(AnalysisEvent) + Send,
+    ) -> Result<Vec<FileAnalysis>, NamebackError> {
+        self.analyze_paths_streaming(&[directory.to_path_buf()], on_event)
+    }
 
-        // Clean up stale cache entries
-        if self.config.enable_cache {
-            cache.cleanup_stale_entries(&files);
-        }
+    /// Analyze a directory, reporting how far along the run is as files move through it
+    /// `on_progress` is called whenever a file enters a stage or finishes (see `Progress`)
+    /// Returns the same analyses as `analyze_directory`
+    pub fn analyze_directory_with_progress(
+        &self,
+        directory: &Path,
+        on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<Vec<FileAnalysis>, NamebackError> {
+        self.analyze_paths_with_progress(&[directory.to_path_buf()], on_progress)
+    }
 
-        // Detect file series (e.g., IMG_001.jpg, IMG_002.jpg, etc.)
-        let series_list = series_detector::detect_series(&files);
-        log::info!("Detected {} file series", series_list.len());
+    /// Analyze several directories and files as one batch
+    /// Directories are scanned as by `analyze_directory`; files are analyzed as given, even
+    /// if hidden or left out by the include/exclude patterns. Names are kept unique and
+    /// series detected across all of them, and a file reached through more than one path
+    /// is analyzed once. Paths that are neither are skipped with a warning
+    pub fn analyze_paths(&self, paths: &[PathBuf]) -> Result<Vec<FileAnalysis>, NamebackError> {
+        let (analyses, _) = self.analyze_paths_with_cache_stats(paths)?;
+        Ok(analyses)
+    }
 
-        // Build a map of file paths to their series
-        let mut file_series_map = std::collections::HashMap::new();
-        for series in &series_list {
-            for (file_path, _) in &series.files {
-                file_series_map.insert(file_path.clone(), series.clone());
+    /// Analyze several directories and files, reporting each file as soon as it is done
+    /// (see `analyze_directory_streaming` and `analyze_paths`)
+    pub fn analyze_paths_streaming(
+        &self,
+        paths: &[PathBuf],
+        on_event: impl FnMut(AnalysisEvent) + Send,
+    ) -> Result<Vec<FileAnalysis>, NamebackError> {
+        let files = self.collect_files(paths)?;
+        let cache = self.load_cache(paths);
+        let existing_names = Self::existing_names(&files);
+        let events: &EventSink = &Mutex::new(on_event);
+        let (mut analyses, _) = self.analyze_files(&files, &files, existing_names, cache, Some(events))?;
+        for folder in self.folder_names(paths, &analyses) {
+            (events.lock().unwrap())(AnalysisEvent::Analyzed(folder.clone()));
+            analyses.push(folder);
+        }
+        Ok(analyses)
+    }
+
+    /// Analyze several directories and files, reporting how far along the run is
+    /// (see `analyze_directory_with_progress` and `analyze_paths`)
+    pub fn analyze_paths_with_progress(
+        &self,
+        paths: &[PathBuf],
+        mut on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<Vec<FileAnalysis>, NamebackError> {
+        self.analyze_paths_streaming(paths, |event| {
+            if let AnalysisEvent::Progress(progress) = event {
+                on_progress(progress);
             }
+        })
+    }
+
+    /// Analyze one file afresh and show how its name was picked: every candidate name, its
+    /// score broken down, and why the winner won or why no name was proposed
+    /// The cache is neither read nor written, so the candidates are always there
+    pub fn explain(&self, path: &Path) -> Result<Explanation, NamebackError> {
+        if !path.is_file() {
+            return Err(NamebackError::NotAFile(path.to_path_buf()));
         }
+        let files = [path.to_path_buf()];
+        let cache = metadata_cache::MetadataCache::in_memory();
+        let (analyses, _) = self.analyze_files(&files, &files, Self::existing_names(&files), cache, None)?;
+        let analysis = analyses
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} was not analyzed", path.display()))?;
+        Ok(Explanation::new(analysis))
+    }
+
+    /// Re-analyze a previously analyzed directory, only analyzing new or modified files
+    ///
+    /// Files from `previous` whose size and modification time still match the metadata
+    /// cache keep their earlier analysis; everything else goes through the full pipeline.
+    /// Requires caching - with `enable_cache` off (or `refresh_cache` on) every file counts as
+    /// changed.
+    pub fn reanalyze_directory(
+        &self,
+        directory: &Path,
+        previous: &[FileAnalysis],
+    ) -> Result<IncrementalAnalysis, NamebackError> {
+        let files = self.scan_files(directory)?;
+        let cache = self.load_cache(&[directory.to_path_buf()]);
+
+        let previous_by_path: HashMap<&Path, &FileAnalysis> = previous
+            .iter()
+            .map(|a| (a.original_path.as_path(), a))
+            .collect();
 
-        // Pre-populate existing names
-        let mut existing_names = HashSet::new();
+        let mut unchanged = HashMap::new();
+        let mut changed = Vec::new();
         for file_path in &files {
-            if let Some(filename) = file_path.file_name() {
-                if let Some(name) = filename.to_str() {
-                    existing_names.insert(name.to_string());
+            match previous_by_path.get(file_path.as_path()) {
+                Some(analysis)
+                    if self.config.enable_cache
+                        && !self.config.refresh_cache
+                        && cache.is_unchanged(file_path) =>
+                {
+                    unchanged.insert(file_path.clone(), (*analysis).clone());
                 }
+                _ => changed.push(file_path.clone()),
             }
         }
 
-        // Analyze each file in parallel using rayon
-        use rayon::prelude::*;
-        use std::sync::Mutex;
-
-        // Wrap existing_names and cache in Mutex for thread-safe access
-        let existing_names = Mutex::new(existing_names);
-        let cache = Mutex::new(cache);
+        let current: HashSet<&Path> = files.iter().map(|f| f.as_path()).collect();
+        let removed: Vec<PathBuf> = previous
+            .iter()
+            .filter(|a| !current.contains(a.original_path.as_path()))
+            .map(|a| a.original_path.clone())
+            .collect();
 
-        // Process files in parallel
-        analyses = files
-            .par_iter()
-            .filter_map(|file_path| {
-                // Check cache first if enabled
-                if self.config.enable_cache {
-                    let cache_guard = cache.lock().unwrap();
-                    if let Ok(true) = cache_guard.has_valid_entry(file_path) {
-                        if let Some(entry) = cache_guard.get(file_path) {
-                            log::debug!("Cache hit for {}", file_path.display());
-                            let category = match entry.category.as_str() {
-                                "Image" => FileCategory::Image,
-                                "Document" => FileCategory::Document,
-                                "Audio" => FileCategory::Audio,
-                                "Video" => FileCategory::Video,
-                                "Email" => FileCategory::Email,
-                                "Web" => FileCategory::Web,
-                                "Archive" => FileCategory::Archive,
-                                "SourceCode" => FileCategory::SourceCode,
-                                _ => FileCategory::Unknown,
-                            };
-
-                            let original_name = file_path
-                                .file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("unknown")
-                                .to_string();
-
-                            return Some(FileAnalysis {
-                                original_path: file_path.clone(),
-                                original_name,
-                                proposed_name: entry.proposed_name.clone(),
-                                file_category: category,
-                            });
-                        }
-                    }
-                    drop(cache_guard); // Release lock before analysis
-                }
+        tracing::info!(
+            "Incremental analysis: {} changed, {} unchanged, {} removed",
+            changed.len(),
+            unchanged.len(),
+            removed.len()
+        );
 
-                // Cache miss or caching disabled - analyze the file
-                match self.analyze_file_parallel(file_path, &existing_names) {
-                    Ok(mut analysis) => {
-                        // Check if this file is part of a series
-                        if let Some(series) = file_series_map.get(file_path) {
-                            // Apply series naming if we have a proposed name
-                            if let Some(proposed_name) = &analysis.proposed_name {
-                                // Extract just the base name without extension
-                                let base_name = if let Some(pos) = proposed_name.rfind('.') {
-                                    &proposed_name[..pos]
-                                } else {
-                                    proposed_name
-                                };
-
-                                // Apply series naming pattern
-                                if let Some(series_name) = series_detector::apply_series_naming(
-                                    series,
-                                    file_path,
-                                    base_name,
-                                ) {
-                                    analysis.proposed_name = Some(series_name);
-                                }
-                            }
-                        }
+        // Names already proposed for unchanged files are taken, too
+        let mut existing_names = Self::existing_names(&files);
+        existing_names.extend(unchanged.values().filter_map(|a| a.proposed_name.clone()));
 
-                        // Update cache if enabled
-                        if self.config.enable_cache {
-                            let mut cache_guard = cache.lock().unwrap();
-                            let category_str = match analysis.file_category {
-                                FileCategory::Image => "Image",
-                                FileCategory::Document => "Document",
-                                FileCategory::Audio => "Audio",
-                                FileCategory::Video => "Video",
-                                FileCategory::Email => "Email",
-                                FileCategory::Web => "Web",
-                                FileCategory::Archive => "Archive",
-                                FileCategory::SourceCode => "SourceCode",
-                                FileCategory::Unknown => "Unknown",
-                            };
-
-                            if let Err(e) = cache_guard.insert(
-                                file_path,
-                                analysis.proposed_name.clone(),
-                                category_str,
-                            ) {
-                                log::warn!("Failed to cache entry for {}: {}", file_path.display(), e);
-                            }
-                        }
+        let (fresh, _) = self.analyze_files(&files, &changed, existing_names, cache, None)?;
+        let mut fresh: HashMap<PathBuf, FileAnalysis> = fresh
+            .into_iter()
+            .map(|a| (a.original_path.clone(), a))
+            .collect();
 
-                        Some(analysis)
-                    },
-                    Err(e) => {
-                        log::warn!("Failed to analyze {}: {}", file_path.display(), e);
-                        // Still add to results but with no proposed name
-                        file_path.file_name().and_then(|n| n.to_str()).map(|name| FileAnalysis {
-                                original_path: file_path.clone(),
-                                original_name: name.to_string(),
-                                proposed_name: None,
-                                file_category: FileCategory::Unknown,
-                            })
-                    }
-                }
-            })
+        // Merge in scan order
+        let analyses = files
+            .iter()
+            .filter_map(|f| unchanged.remove(f).or_else(|| fresh.remove(f)))
             .collect();
 
-        // Save cache to disk if enabled
-        if self.config.enable_cache {
-            let cache_guard = cache.lock().unwrap();
-            if let Err(e) = cache_guard.save() {
-                log::warn!("Failed to save cache: {}", e);
-            } else {
-                let stats = cache_guard.stats();
-                log::info!(
-                    "Cached {} entries ({} bytes)",
-                    stats.total_entries,
-                    stats.cache_size_bytes
-                );
-            }
-        }
+        Ok(IncrementalAnalysis {
+            analyses,
+            changed,
+            removed,
+        })
+    }
 
-        Ok(analyses)
+    /// Analyze directories and files, also returning cache statistics (None if caching is disabled)
+    fn analyze_paths_with_cache_stats(
+        &self,
+        paths: &[PathBuf],
+    ) -> Result<(Vec<FileAnalysis>, Option<CacheStats>)> {
+        let files = self.collect_files(paths)?;
+        let cache = self.load_cache(paths);
+        let existing_names = Self::existing_names(&files);
+        let (mut analyses, cache_stats) = self.analyze_files(&files, &files, existing_names, cache, None)?;
+        analyses.extend(self.folder_names(paths, &analyses));
+        Ok((analyses, cache_stats))
     }
 
-    /// Rename files based on analysis results
-    /// Only renames files where analysis.proposed_name is Some()
-    pub fn rename_files(&self, analyses: &[FileAnalysis], dry_run: bool) -> Vec<RenameResult> {
-        self.rename_files_with_history(analyses, dry_run, None)
+    /// Proposed names for placeholder folders under the directories in `paths`, if
+    /// `rename_directories` is on (see `dir_names::propose`)
+    fn folder_names(&self, paths: &[PathBuf], analyses: &[FileAnalysis]) -> Vec<FileAnalysis> {
+        if !self.config.rename_directories {
+            return Vec::new();
+        }
+        dir_names::propose(paths, analyses, &self.config)
     }
 
-    /// Rename files with history tracking
-    /// If history is provided, successful renames will be added to the history
-    pub fn rename_files_with_history(
-        &self,
-        analyses: &[FileAnalysis],
-        dry_run: bool,
-        mut history: Option<&mut RenameHistory>,
-    ) -> Vec<RenameResult> {
-        let mut results = Vec::new();
-
-        for analysis in analyses {
-            if let Some(new_name) = &analysis.proposed_name {
-                match renamer::rename_file(&analysis.original_path, new_name, dry_run) {
-                    Ok(new_path) => {
-                        // Add to history if provided and not dry run
-                        if let Some(hist) = history.as_deref_mut() {
-                            if !dry_run {
-                                let operation = RenameOperation::new(
-                                    analysis.original_path.clone(),
-                                    new_path.clone(),
-                                );
-                                hist.add(operation);
-                            }
-                        }
+    /// Open the metadata cache, dropping entries for files under the `roots` directories that
+    /// are gone (an empty in-memory cache if caching is disabled or the database can't be opened)
+    fn load_cache(&self, roots: &[PathBuf]) -> metadata_cache::MetadataCache {
+        self.open_cache(roots).with_name_style(self.config.style_cache_key())
+    }
 
-                        results.push(RenameResult {
-                            original_path: analysis.original_path.clone(),
-                            new_name: new_name.clone(),
-                            success: true,
-                            error: None,
-                        });
-                    }
-                    Err(e) => {
-                        results.push(RenameResult {
-                            original_path: analysis.original_path.clone(),
-                            new_name: new_name.clone(),
-                            success: false,
-                            error: Some(e.to_string()),
-                        });
-                    }
-                }
+    fn open_cache(&self, roots: &[PathBuf]) -> metadata_cache::MetadataCache {
+        if !self.config.enable_cache {
+            return metadata_cache::MetadataCache::in_memory();
+        }
+        let Some(cache_path) = self.cache_path() else {
+            tracing::warn!("No cache directory, caching for this run only");
+            return metadata_cache::MetadataCache::in_memory();
+        };
+
+        let directories: Vec<&PathBuf> = roots.iter().filter(|root| root.is_dir()).collect();
+
+        // Caches used to be kept in each scanned directory
+        for directory in &directories {
+            let legacy = directory.join(".nameback_cache.json");
+            if self.config.cache_path.is_none() && legacy.is_file() {
+                tracing::debug!("Removing old cache file {}", legacy.display());
+                let _ = std::fs::remove_file(&legacy);
             }
         }
 
-        results
+        let mut cache = metadata_cache::MetadataCache::open(&cache_path).unwrap_or_else(|e| {
+            tracing::warn!("Failed to open cache, caching for this run only: {}", e);
+            metadata_cache::MetadataCache::in_memory()
+        });
+        for directory in directories {
+            if let Err(e) = cache.remove_missing(directory) {
+                tracing::warn!("Failed to clean up cache: {}", e);
+            }
+        }
+        cache
     }
 
-    /// Analyze and rename files in one step (like the original CLI behavior)
-    pub fn process_directory(&self, directory: &Path, dry_run: bool) -> Result<Vec<RenameResult>> {
-        let analyses = self.analyze_directory(directory)?;
-        Ok(self.rename_files(&analyses, dry_run))
+    fn cache_path(&self) -> Option<PathBuf> {
+        self.config.cache_location()
     }
 
-    // Private helper methods
+    /// A file's embedded metadata (title, author, dates, camera, GPS, ...), read with the
+    /// configured backend; no content extraction or OCR
+    /// Fails with `DependencyMissing` if the backend is exiftool and it isn't installed
+    pub fn read_metadata(&self, path: &Path) -> Result<FileMetadata, NamebackError> {
+        if self.config.metadata_backend == MetadataBackend::Exiftool && !extractor::exiftool_installed() {
+            return Err(NamebackError::DependencyMissing { tool: Dependency::ExifTool });
+        }
+        let runner = tool_command::ToolRunner::new(self.config.tool_timeout);
+        runner.enter(|| extractor::read_metadata(path, &self.config, None)).map_err(|source| {
+            NamebackError::MetadataExtraction {
+                path: path.to_path_buf(),
+                source,
+            }
+        })
+    }
 
-    fn scan_files(&self, directory: &Path) -> Result<Vec<PathBuf>> {
-        use walkdir::WalkDir;
+    /// Remove every entry from the metadata cache, returning what was there, and forget the
+    /// paper lookups kept next to it and the cached HEIC/RAW conversions
+    pub fn clear_cache(&self) -> Result<CacheStats, NamebackError> {
+        let cache_path = self.cache_path().ok_or(NamebackError::NoCacheDirectory)?;
+        paper_lookup::clear(&paper_lookup::cache_file(&cache_path))
+            .map_err(NamebackError::Cache)?;
+        image_ocr::clear_thumbnails().map_err(NamebackError::Cache)?;
+        if !cache_path.exists() {
+            return Ok(CacheStats::default());
+        }
+        let mut cache = metadata_cache::MetadataCache::open(&cache_path).map_err(NamebackError::Cache)?;
+        let stats = cache.stats();
+        cache.clear().map_err(NamebackError::Cache)?;
+        Ok(stats)
+    }
 
-        let mut files = Vec::new();
+    /// Current filenames, used to keep proposed names unique
+    fn existing_names(files: &[PathBuf]) -> HashSet<String> {
+        files
+            .iter()
+            .filter_map(|f| f.file_name().and_then(|n| n.to_str()))
+            .map(|name| name.to_string())
+            .collect()
+    }
 
-        for entry in WalkDir::new(directory)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| {
-                let filename = e.file_name().to_str().unwrap_or("");
+    /// Analyze `targets` out of the full set of scanned `files`
+    /// `files` drives series detection; only `targets` are analyzed
+    /// Progress goes to `events` if given
+    fn analyze_files(
+        &self,
+        files: &[PathBuf],
+        targets: &[PathBuf],
+        existing_names: HashSet<String>,
+        cache: metadata_cache::MetadataCache,
+        events: Option<&EventSink>,
+    ) -> Result<(Vec<FileAnalysis>, Option<CacheStats>)> {
+        *self.profile.lock().unwrap() = ProfileReport::default();
 
-                // Always skip cache file
-                if filename == ".nameback_cache.json" {
-                    return false;
-                }
+        let emit = |event: AnalysisEvent| {
+            if let Some(events) = events {
+                (events.lock().unwrap())(event);
+            }
+        };
+        emit(AnalysisEvent::Started { total: targets.len() });
+        let tracker = Mutex::new(progress::ProgressTracker::new(targets.len()));
 
-                // Skip hidden files if configured
-                if self.config.skip_hidden && filename.starts_with('.') {
-                    return false;
-                }
+        // Detect file series (e.g., IMG_001.jpg, IMG_002.jpg, etc.)
+        let numbering = self.config.series_numbering();
+        let series_list = series_detector::detect_series(files, numbering.min_files);
+        tracing::info!("Detected {} file series", series_list.len());
 
-                true
-            })
-        {
-            match entry {
-                Ok(entry) => {
-                    if entry.file_type().is_file() {
-                        files.push(entry.path().to_path_buf());
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Failed to access entry: {}", e);
-                }
+        // Build a map of file paths to their series and its id
+        let mut file_series_map = std::collections::HashMap::new();
+        for (id, series) in series_list.iter().enumerate() {
+            for (file_path, _) in &series.files {
+                file_series_map.insert(file_path.clone(), (id, series.clone()));
             }
         }
 
-        Ok(files)
-    }
+        // Wrap existing_names and cache in Mutex for thread-safe access
+        let existing_names = Mutex::new(existing_names);
+        let cache = Mutex::new(cache);
 
+        // Size and modification time before analysis, to catch files still being written
+        let snapshots: Vec<Option<file_state::Snapshot>> =
+            targets.iter().map(|f| file_state::Snapshot::take(f)).collect();
 
-    /// Parallel version of analyze_file that uses Mutex-protected existing_names
-    fn analyze_file_parallel(
-        &self,
-        file_path: &Path,
-        existing_names: &std::sync::Mutex<HashSet<String>>,
-    ) -> Result<FileAnalysis> {
-        // Detect file type
-        let file_category = detector::detect_file_type(file_path)?;
-
-        let original_name = file_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        // Skip unknown file types
-        if file_category == FileCategory::Unknown {
-            return Ok(FileAnalysis {
+        // The cached analysis of a file, if it is still valid and fits this run's settings
+        let cached = |file_path: &Path| {
+            if self.config.refresh_cache {
+                return None;
+            }
+            let mut cache_guard = cache.lock().unwrap();
+            if !matches!(cache_guard.has_valid_entry(file_path), Ok(true)) {
+                return None;
+            }
+            let entry = cache_guard.get(file_path)?;
+            // Entries written before titles were cached have nothing to write back
+            if entry.title.is_none()
+                && entry.proposed_name.is_some()
+                && self.config.write_metadata != MetadataWriteMode::Off
+            {
+                return None;
+            }
+            // Destinations aren't cached; they depend on what is in the target folders now
+            if self.config.organize.is_some() {
+                return None;
+            }
+            // Names made in another case, with other separators or another timestamp style
+            if entry.name_style != self.config.style_cache_key() {
+                return None;
+            }
+            tracing::debug!("Cache hit for {}", file_path.display());
+            let category = match entry.category.as_str() {
+                "Image" => FileCategory::Image,
+                "Document" => FileCategory::Document,
+                "Audio" => FileCategory::Audio,
+                "Video" => FileCategory::Video,
+                "Email" => FileCategory::Email,
+                "Web" => FileCategory::Web,
+                "Archive" => FileCategory::Archive,
+                "Installer" => FileCategory::Installer,
+                "Ebook" => FileCategory::Ebook,
+                "SourceCode" => FileCategory::SourceCode,
+                _ => FileCategory::Unknown,
+            };
+            if !self.config.includes_category(&category) {
+                return Some(pipeline::unanalyzed(file_path, category));
+            }
+            // Entries written before scores were cached can't be checked against a threshold
+            let min_confidence = self.config.min_confidence_for(&category);
+            if entry.confidence.is_none() && min_confidence > 0.0 {
+                return None;
+            }
+
+            let original_name = file_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            let confidence = entry.confidence.unwrap_or(0.0);
+            let mut analysis = FileAnalysis {
                 original_path: file_path.to_path_buf(),
                 original_name,
-                proposed_name: None,
-                file_category,
-            });
-        }
+                proposed_name: entry.proposed_name.clone(),
+                title: entry.title.clone(),
+                collision_suffix: None,
+                collision_strategy: None,
+                destination: None,
+                duplicate_of: None,
+                companions: Vec::new(),
+                file_category: category,
+                confidence,
+                name_source: entry.name_source.unwrap_or_default(),
+                errors: Vec::new(),
+                rejected: Vec::new(),
+                alternatives: entry.alternatives.clone(),
+                deferred: None,
+                over_limit: None,
+                series: None,
+                extension_mismatch: None,
+            };
+            if analysis.proposed_name.is_some() && confidence < min_confidence {
+                analysis.proposed_name = None;
+                analysis.title = None;
+                analysis.alternatives.clear();
+                analysis.errors.push(pipeline::low_confidence(confidence, min_confidence));
+            }
+            // Not cached: checking the extension only reads the file's first bytes
+            analysis.extension_mismatch = detector::check_extension(file_path)
+                .map(|mismatch| mismatch.settled(&self.config, analysis.proposed_name.is_some()));
+            // Claim the name as analysis would; if another file has it, analysis settles the
+            // collision with this run's strategy
+            if let Some(name) = &analysis.proposed_name {
+                if !existing_names.lock().unwrap().insert(name.clone()) && *name != analysis.original_name {
+                    return None;
+                }
+            }
+            Some(analysis)
+        };
 
-        // Extract metadata with configuration
-        let metadata = match extractor::extract_metadata(file_path, &self.config) {
-            Ok(m) => m,
-            Err(_) => {
-                return Ok(FileAnalysis {
-                    original_path: file_path.to_path_buf(),
-                    original_name,
-                    proposed_name: None,
-                    file_category,
+        let lookup = |file_path: &Path| {
+            if let Some(reason) = file_state::check(file_path, self.config.stable_for) {
+                tracing::info!("Deferring {}: {}", file_path.display(), reason);
+                return Some(FileAnalysis {
+                    deferred: Some(reason),
+                    ..pipeline::unanalyzed(file_path, FileCategory::Unknown)
                 });
             }
+            if !self.config.enable_cache {
+                return None;
+            }
+            let cached = cached(file_path);
+            cache.lock().unwrap().record_lookup(cached.is_some());
+            cached
         };
 
-        // Extract candidate name
-        let candidate_name = metadata.extract_name(&file_category, file_path);
+        // Run the staged pipeline; results arrive in completion order
+        let mut results: Vec<Option<FileAnalysis>> = vec![None; targets.len()];
+        // Capture times read while extracting, for grouping bursts without reading them again
+        let mut shots: HashMap<PathBuf, series_detector::Shot> = HashMap::new();
+        let on_stage = |path: &Path, stage: Stage| {
+            emit(AnalysisEvent::Stage {
+                path: path.to_path_buf(),
+                stage,
+            });
+            emit(AnalysisEvent::Progress(tracker.lock().unwrap().stage(path, stage)));
+        };
+        let file_done = || {
+            if events.is_some() {
+                emit(AnalysisEvent::Progress(tracker.lock().unwrap().finish_file()));
+            }
+        };
+        let mut pipeline =
+            pipeline::Pipeline::new(&self.config, &self.profile, self.thread_pool.as_deref())
+                .with_name_providers(&self.name_providers);
+        if let Some(runtime) = &self.tool_runtime {
+            pipeline = pipeline.with_tool_runtime(runtime.clone());
+        }
+        if events.is_some() {
+            pipeline = pipeline.with_stage_events(&on_stage);
+        }
+        pipeline.run(targets, &existing_names, lookup, |index, finished| {
+            let file_path = &targets[index];
+            let mut analysis = finished.analysis;
+            if let Some(shot) = finished.shot {
+                shots.insert(file_path.clone(), shot);
+            }
 
-        let proposed_name = candidate_name.map(|name| {
-            let extension = file_path.extension();
-            // Lock the mutex to access existing_names
-            let mut names = existing_names.lock().unwrap();
-            generator::generate_filename_with_metadata(&name, extension, &mut names, Some(&metadata))
-        });
+            // Files of a turned-off category come back untouched and are left out
+            if analysis.errors.is_empty()
+                && analysis.deferred.is_none()
+                && !self.config.includes_category(&analysis.file_category)
+            {
+                file_done();
+                return;
+            }
 
-        Ok(FileAnalysis {
-            original_path: file_path.to_path_buf(),
-            original_name,
-            proposed_name,
-            file_category,
-        })
-    }
-}
+            let series = file_series_map.get(file_path);
+            if let Some((id, series)) = series.filter(|_| analysis.proposed_name.is_some()) {
+                analysis.series = series.info(*id, file_path);
+            }
 
-/// Check if all required dependencies are installed
-pub fn check_dependencies() -> Result<()> {
-    deps::print_dependency_status();
-    Ok(())
-}
+            if finished.store_in_cache {
+                // Check if this file is part of a series
+                if let Some((_, series)) = series {
+                    // Apply series naming if we have a proposed name
+                    if let Some(proposed_name) = &analysis.proposed_name {
+                        // Extract just the base name without extension
+                        let base_name = if let Some(pos) = proposed_name.rfind('.') {
+                            &proposed_name[..pos]
+                        } else {
+                            proposed_name
+                        };
 
-/// Install missing dependencies (interactive)
-pub fn install_dependencies() -> Result<()> {
-    deps::run_installer().map_err(|e| anyhow::anyhow!(e))
-}
+                        // Apply series naming pattern
+                        if let Some(series_name) =
+                            series_detector::apply_series_naming(series, file_path, base_name, analysis.new_extension(), numbering)
+                        {
+                            analysis.proposed_name = Some(series_name);
+                            analysis.collision_suffix = None;
+                        }
+                    }
+                }
 
-/// Install dependencies with progress callback
-pub fn install_dependencies_with_progress(
-    progress: Option<deps::ProgressCallback>,
-) -> Result<()> {
-    deps::run_installer_with_progress(progress).map_err(|e| anyhow::anyhow!(e))
-}
+                // Update cache if enabled
+                if self.config.enable_cache {
+                    let mut cache_guard = cache.lock().unwrap();
+                    let category_str = match analysis.file_category {
+                        FileCategory::Image => "Image",
+                        FileCategory::Document => "Document",
+                        FileCategory::Audio => "Audio",
+                        FileCategory::Video => "Video",
+                        FileCategory::Email => "Email",
+                        FileCategory::Web => "Web",
+                        FileCategory::Archive => "Archive",
+                        FileCategory::Installer => "Installer",
+                        FileCategory::Ebook => "Ebook",
+                        FileCategory::SourceCode => "SourceCode",
+                        FileCategory::Unknown => "Unknown",
+                    };
 
-/// Re-export progress callback type
-pub use deps::ProgressCallback;
+                    // A name that collided is settled afresh every run, by the strategy then
+                    // configured (a cached counter would hide the collision from `Fail`)
+                    if analysis.collision_suffix.is_some() {
+                        cache_guard.remove(file_path);
+                    } else if let Err(e) = cache_guard.insert(file_path, &analysis, category_str) {
+                        tracing::warn!("Failed to cache entry for {}: {}", file_path.display(), e);
+                    }
+                }
+            }
+
+            for event in RunEvent::from_analysis(&analysis) {
+                event.log();
+            }
+            emit(AnalysisEvent::Analyzed(analysis.clone()));
+            file_done();
+            results[index] = Some(analysis);
+        });
+
+        // Anything that changed while it was analyzed may have been read half-written
+        let analyzed: Vec<(&Path, file_state::Snapshot)> = targets
+            .iter()
+            .zip(&snapshots)
+            .zip(&results)
+            .filter(|(_, result)| result.as_ref().is_some_and(|a| a.deferred.is_none()))
+            .filter_map(|((path, snapshot), _)| Some((path.as_path(), (*snapshot)?)))
+            .collect();
+        let still_writing = file_state::still_writing(&analyzed);
+
+        let mut analyses: Vec<FileAnalysis> = results.into_iter().flatten().collect();
+        for analysis in analyses.iter_mut() {
+            if still_writing.contains(&analysis.original_path) {
+                tracing::info!(
+                    "Deferring {}: {}",
+                    analysis.original_path.display(),
+                    DeferReason::StillWriting
+                );
+                cache.lock().unwrap().remove(&analysis.original_path);
+                analysis.proposed_name = None;
+                analysis.collision_suffix = None;
+                analysis.deferred = Some(DeferReason::StillWriting);
+                emit(AnalysisEvent::Revised(analysis.clone()));
+            }
+        }
+
+        // Photos shot in a burst are named together, numbered in the order they were taken
+        let mut existing_names = existing_names.into_inner().unwrap();
+        let bursts = series_detector::apply_bursts(
+            &mut analyses,
+            numbering,
+            self.config.name_style().separator(),
+            series_list.len(),
+            &mut existing_names,
+            &shots,
+            self.thread_pool.as_deref(),
+        );
+        for &index in &bursts {
+            emit(AnalysisEvent::Revised(analyses[index].clone()));
+        }
+
+        // Settle colliding names the configured way (by default, by content rather than counter)
+        let renamed = collisions::resolve(
+            &mut analyses,
+            self.config.collision_strategy,
+            self.config.name_style(),
+            &mut existing_names,
+        );
+        for &index in &renamed {
+            emit(AnalysisEvent::Revised(analyses[index].clone()));
+        }
+
+        // Exact copies, found by content hash among files of the same size
+        let copies = duplicates::apply(
+            &mut analyses,
+            self.config.duplicates,
+            self.config.name_style().separator(),
+            &mut existing_names,
+        );
+        for &index in &copies {
+            emit(AnalysisEvent::Revised(analyses[index].clone()));
+        }
+
+        // Sidecars and RAW+JPEG pairs are renamed with the file they belong to
+        if self.config.rename_sidecars {
+            for index in renamer::attach_companions(&mut analyses) {
+                emit(AnalysisEvent::Revised(analyses[index].clone()));
+            }
+        }
+
+        // Entries are written as files are analyzed; keep the cache within its size cap
+        let mut cache_stats = None;
+        if self.config.enable_cache {
+            let mut cache_guard = cache.lock().unwrap();
+            if let Err(e) = cache_guard.evict() {
+                tracing::warn!("Failed to trim cache: {}", e);
+            }
+            let stats = cache_guard.stats();
+            tracing::debug!(
+                "Cache: {} hits, {} misses ({} invalidated); {} entries ({} bytes)",
+                stats.hits,
+                stats.misses,
+                stats.invalidations,
+                stats.total_entries,
+                stats.cache_size_bytes
+            );
+            cache_stats = Some(stats);
+        }
+        *self.cache_stats.lock().unwrap() = cache_stats.clone();
+
+        Ok((analyses, cache_stats))
+    }
+
+    /// Watch a directory, analyzing and renaming files as they arrive, until `stop` is signalled
+    ///
+    /// A file is picked up once it has gone `options.settle` without changes; files that are
+    /// still in use are retried with a growing delay. Each file's outcome goes to `on_event`.
+    /// Files already in the directory are left alone, and the scan settings (hidden files,
+    /// depth, include/exclude patterns, categories) apply to new ones. Blocks until stopped
+    pub fn watch(
+        &self,
+        directory: &Path,
+        options: &WatchOptions,
+        stop: &StopSignal,
+        on_event: impl FnMut(WatchEvent),
+    ) -> Result<(), NamebackError> {
+        Ok(watcher::run(self, directory, options, stop, on_event)?)
+    }
+
+    /// Rename files based on analysis results
+    /// Only renames files where analysis.proposed_name is Some()
+    /// Folders (see `RenameConfig::rename_directories`) go after the files, deepest first
+    /// Every call is one batch in the undo journal (see `undo_batch`)
+    pub fn rename_files(&self, analyses: &[FileAnalysis], dry_run: bool) -> Vec<RenameResult> {
+        self.rename_files_with_history(analyses, dry_run, None)
+    }
+
+    /// Rename files with history tracking
+    /// If history is provided, successful renames will be added to the history
+    pub fn rename_files_with_history(
+        &self,
+        analyses: &[FileAnalysis],
+        dry_run: bool,
+        mut history: Option<&mut RenameHistory>,
+    ) -> Vec<RenameResult> {
+        // The whole batch is checked first, so a clash refuses one file instead of whichever comes second
+        // Files keep their names when only metadata is written, so there is nothing to check
+        let plan = match self.config.write_metadata {
+            MetadataWriteMode::Only => RenamePlan::default(),
+            _ => self.plan(analyses),
+        };
+        let conflicts = plan.blocking_conflicts();
+        // One error per folder that can't be written to; its files are only logged at debug level
+        for (conflict, files) in plan.unwritable_folders() {
+            tracing::error!("Can't rename {} file{}: {}", files, if files == 1 { "" } else { "s" }, conflict);
+        }
+        let journal = self.journal();
+        let batch = Self::begin_batch(journal.as_ref(), dry_run);
+        dir_names::rename_order(analyses)
+            .into_iter()
+            .filter_map(|analysis| {
+                let conflict = conflicts.get(analysis.original_path.as_path()).copied();
+                self.rename_one(analysis, dry_run, conflict, history.as_deref_mut(), batch.as_ref())
+            })
+            .collect()
+    }
+
+    /// Check a batch of proposed renames before running it
+    /// Finds files given the same new name, new names already taken on disk, names differing
+    /// only in case, files gone since analysis and folders that can't be written to.
+    /// `rename_files` refuses the renames with blocking conflicts
+    /// The plan can also be saved for review and run on its own with `RenamePlan::execute`,
+    /// recording its renames in this engine's undo journal
+    pub fn plan(&self, analyses: &[FileAnalysis]) -> RenamePlan {
+        RenamePlan {
+            journal_path: self.config.journal_path.clone().or_else(journal::Journal::default_path),
+            ..RenamePlan::new(analyses)
+        }
+    }
+
+    /// Rename a single file based on its analysis
+    /// Fails only if the analysis has no proposed name; rename errors are reported in the result
+    pub fn rename_single(&self, analysis: &FileAnalysis, dry_run: bool) -> Result<RenameResult, NamebackError> {
+        let journal = self.journal();
+        let batch = Self::begin_batch(journal.as_ref(), dry_run);
+        self.rename_one(analysis, dry_run, None, None, batch.as_ref())
+            .ok_or_else(|| NamebackError::NoProposedName(analysis.original_path.clone()))
+    }
+
+    /// Restore the original names of a batch of renames recorded in the undo journal
+    /// `None` undoes the most recent batch that hasn't been undone yet. Renames whose file
+    /// has gone or whose original name is taken again are reported as conflicts and skipped
+    pub fn undo_batch(&self, batch_id: Option<&str>) -> Result<UndoReport, NamebackError> {
+        Ok(self.journal().ok_or(NamebackError::NoJournal)?.undo(batch_id)?)
+    }
+
+    /// Undo one rename recorded in `history` (see `RenameHistory::undo_token`) and mark it
+    /// undone in the undo journal as well, so `undo_batch` (`nameback --undo`) doesn't
+    /// report it as a conflict later
+    pub fn undo_from_history(&self, history: &mut RenameHistory, token: &RollbackToken) -> Result<(), NamebackError> {
+        let operation = history.get(token).cloned();
+        history.undo_token(token)?;
+        let batch = operation.and_then(|op| Some((op.batch_id.clone()?, op)));
+        if let (Some(journal), Some((batch_id, op))) = (self.journal(), batch) {
+            if let Err(e) = journal.mark_undone(&batch_id, &op.original_path, &op.new_path) {
+                tracing::warn!("Failed to record the undo in the undo journal: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo journal at the configured or default location
+    fn journal(&self) -> Option<journal::Journal> {
+        self.config
+            .journal_path
+            .clone()
+            .or_else(journal::Journal::default_path)
+            .map(journal::Journal::new)
+    }
+
+    /// Start a journal batch for real (not dry-run) renames
+    fn begin_batch(journal: Option<&journal::Journal>, dry_run: bool) -> Option<journal::Batch<'_>> {
+        if dry_run {
+            return None;
+        }
+        match journal?.begin() {
+            Ok(batch) => Some(batch),
+            Err(e) => {
+                tracing::warn!("Renames won't be undoable with undo_batch: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Rename one file, recording it in `history` and the journal `batch` if given
+    /// (None if there is no proposed name). A `conflict` from the batch's plan refuses it
+    fn rename_one(
+        &self,
+        analysis: &FileAnalysis,
+        dry_run: bool,
+        conflict: Option<&PlanConflict>,
+        history: Option<&mut RenameHistory>,
+        batch: Option<&journal::Batch>,
+    ) -> Option<RenameResult> {
+        let new_name = analysis.proposed_name.as_ref()?;
+
+        let _file_span =
+            tracing::info_span!("file", path = %analysis.original_path.display()).entered();
+
+        let intended_path = match &analysis.destination {
+            Some(folder) => folder.join(new_name),
+            None => analysis.original_path.with_file_name(new_name),
+        };
+
+        // A file opened since analysis would fail to rename (or be renamed mid-write)
+        if let Some(reason) = file_state::check(&analysis.original_path, self.config.stable_for) {
+            tracing::info!("Deferring rename: {}", reason);
+            return Some(RenameResult {
+                original_path: analysis.original_path.clone(),
+                new_name: new_name.clone(),
+                new_path: intended_path,
+                collision_suffix: analysis.collision_suffix,
+                collision_strategy: analysis.collision_strategy,
+                rollback_token: None,
+                success: false,
+                error: Some(format!("Deferred: {}", reason)),
+                error_kind: None,
+                deferred: Some(reason),
+                batch_id: None,
+                metadata_written: false,
+                companions: Vec::new(),
+            });
+        }
+
+        profiling::begin_file();
+        let rename_result = profiling::time(profiling::Stage::Rename, || {
+            // An existing file in the way is handled when moving, as `on_conflict` says
+            let conflict = conflict.filter(|conflict| {
+                !matches!(conflict, PlanConflict::TargetExists) || self.config.on_conflict == OnConflict::Skip
+            });
+            if let Some(conflict) = conflict {
+                let message = format!("Not renamed: {}", conflict);
+                return Err(match conflict {
+                    PlanConflict::SameTarget { .. } | PlanConflict::TargetExists => {
+                        anyhow::Error::new(renamer::RenameError::TargetExists {
+                            path: intended_path.clone(),
+                        })
+                        .context(message)
+                    }
+                    PlanConflict::PermissionDenied { path } => {
+                        anyhow::Error::new(renamer::RenameError::PermissionDenied { path: path.clone() })
+                            .context(message)
+                    }
+                    PlanConflict::ReadOnlyVolume { path } => {
+                        anyhow::Error::new(renamer::RenameError::ReadOnlyVolume { path: path.clone() })
+                            .context(message)
+                    }
+                    _ => anyhow::anyhow!(message),
+                });
+            }
+            let metadata_written = self.write_title(analysis, dry_run)?;
+            if self.config.write_metadata == MetadataWriteMode::Only {
+                return Ok((analysis.original_path.clone(), Vec::new(), Vec::new(), metadata_written, None));
+            }
+            let new_path = renamer::target_path(
+                &analysis.original_path,
+                new_name,
+                analysis.destination.as_deref(),
+            )?;
+            // A file replaced by this one is set aside until the rename is done, and put
+            // back if it fails
+            let (new_path, displaced) =
+                renamer::make_room(&analysis.original_path, &new_path, self.config.on_conflict, dry_run)?;
+            let moved = renamer::move_file(&analysis.original_path, &new_path, dry_run).and_then(|created_dirs| {
+                // Sidecars follow the file; if one can't, the file is moved back too
+                let companions = renamer::move_companions(
+                    &analysis.companions,
+                    &analysis.original_path,
+                    &new_path,
+                    dry_run,
+                )
+                .inspect_err(|_| {
+                    if !dry_run {
+                        renamer::undo_moves(&[(analysis.original_path.clone(), new_path.clone())]);
+                        renamer::remove_empty_folders(&created_dirs);
+                    }
+                })?;
+                Ok((created_dirs, companions))
+            });
+            let (created_dirs, companions) = match moved {
+                Ok(moved) => moved,
+                Err(e) => {
+                    if let Some(displaced) = displaced {
+                        displaced.restore();
+                    }
+                    return Err(e);
+                }
+            };
+            let replaced = displaced.map(|displaced| displaced.finish(&renamer::system_trash));
+            anyhow::Ok((new_path, created_dirs, companions, metadata_written, replaced))
+        });
+        self.profile
+            .lock()
+            .unwrap()
+            .record_file(&analysis.original_path, profiling::end_file());
+
+        let result = match rename_result {
+            Ok((new_path, created_dirs, companions, metadata_written, replaced)) => {
+                // Add to history if provided and not dry run (and the file was renamed)
+                let renamed = !dry_run && new_path != analysis.original_path;
+                let mut rollback_token = None;
+                if let Some(hist) = history {
+                    if renamed {
+                        let batch_id = batch.map(|batch| batch.id.clone());
+                        let operation = RenameOperation {
+                            created_dirs: created_dirs.clone(),
+                            batch_id: batch_id.clone(),
+                            ..RenameOperation::new(analysis.original_path.clone(), new_path.clone())
+                        };
+                        rollback_token = Some(operation.rollback_token());
+                        hist.add(operation);
+                        for (old_path, moved_to) in &companions {
+                            hist.add(RenameOperation {
+                                batch_id: batch_id.clone(),
+                                ..RenameOperation::new(old_path.clone(), moved_to.clone())
+                            });
+                        }
+                    }
+                }
+
+                let batch_id = batch.filter(|_| renamed).map(|batch| {
+                    batch.record_replacing(&analysis.original_path, &new_path, &created_dirs, replaced);
+                    for (old_path, moved_to) in &companions {
+                        batch.record(old_path, moved_to, &[]);
+                    }
+                    batch.id.clone()
+                });
+
+                let new_name = match self.config.write_metadata {
+                    MetadataWriteMode::Only => analysis.original_name.clone(),
+                    // `OnConflict::Suffix` may have added a counter
+                    _ => match new_path.file_name() {
+                        Some(name) => Path::new(new_name).with_file_name(name).to_string_lossy().into_owned(),
+                        None => new_name.clone(),
+                    },
+                };
+                RenameResult {
+                    original_path: analysis.original_path.clone(),
+                    new_name,
+                    new_path,
+                    collision_suffix: analysis.collision_suffix,
+                    collision_strategy: analysis.collision_strategy,
+                    rollback_token,
+                    success: true,
+                    error: None,
+                    error_kind: None,
+                    deferred: None,
+                    batch_id,
+                    metadata_written,
+                    companions,
+                }
+            }
+            Err(e) => RenameResult {
+                original_path: analysis.original_path.clone(),
+                new_name: new_name.clone(),
+                new_path: intended_path,
+                collision_suffix: analysis.collision_suffix,
+                collision_strategy: analysis.collision_strategy,
+                rollback_token: None,
+                success: false,
+                error: Some(e.to_string()),
+                error_kind: renamer::RenameError::find(&e).cloned(),
+                deferred: None,
+                batch_id: None,
+                metadata_written: false,
+                companions: Vec::new(),
+            },
+        };
+        if conflict.is_some_and(|conflict| conflict.unwritable_folder().is_some()) {
+            tracing::debug!("Not renamed: {}", result.error.as_deref().unwrap_or_default());
+        } else {
+            RunEvent::from_result(&result, dry_run).log();
+        }
+
+        Some(result)
+    }
+
+    /// Write the file's title into its metadata if `write_metadata` asks for it
+    /// Returns whether it was (or in dry-run mode, could be) written. Only in `Only` mode is
+    /// a format that can't take it, or a failed write, an error; otherwise the file is just renamed
+    fn write_title(&self, analysis: &FileAnalysis, dry_run: bool) -> Result<bool> {
+        let mode = self.config.write_metadata;
+        let Some(title) = analysis.title.as_deref().filter(|_| mode != MetadataWriteMode::Off) else {
+            return Ok(false);
+        };
+        let backend = self.config.metadata_backend;
+        let written = if dry_run {
+            metadata_writer::check(&analysis.original_path, backend)
+        } else {
+            metadata_writer::write_title(&analysis.original_path, title, backend)
+        };
+        match written {
+            Ok(()) => Ok(true),
+            Err(e) if mode == MetadataWriteMode::Only => Err(e),
+            Err(e) => {
+                tracing::debug!("Not writing the title into the file's metadata: {:#}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Analyze and rename files in one step (like the original CLI behavior)
+    pub fn process_directory(&self, directory: &Path, dry_run: bool) -> Result<RunSummary, NamebackError> {
+        let started = std::time::Instant::now();
+
+        let (analyses, cache_stats) = self.analyze_paths_with_cache_stats(&[directory.to_path_buf()])?;
+        let results = self.rename_files(&analyses, dry_run);
+
+        let skipped = analyses
+            .iter()
+            .filter(|a| a.proposed_name.is_none() && a.deferred.is_none())
+            .count();
+        let deferred = analyses.iter().filter(|a| a.deferred.is_some()).count()
+            + results.iter().filter(|r| r.deferred.is_some()).count();
+        let errors = results
+            .iter()
+            .filter(|r| !r.success && r.deferred.is_none())
+            .count();
+
+        Ok(RunSummary {
+            analyses,
+            results,
+            skipped,
+            deferred,
+            errors,
+            duration: started.elapsed(),
+            cache_stats,
+            profile: self.profile_report(),
+        })
+    }
+
+    // Private helper methods
+
+    /// The files to analyze for `paths`: each directory's scan (see `scan_files`) and each
+    /// file as given, in order, without repeats
+    fn collect_files(&self, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                files.extend(self.scan_files(path)?);
+            } else if path.is_file() {
+                files.push(path.clone());
+            } else {
+                tracing::warn!("Skipping {}: not a file or directory", path.display());
+            }
+        }
+
+        // Overlapping inputs (`photos` and `photos/2023`, or a listed file inside a scanned
+        // directory) would otherwise analyze and rename a file twice
+        // Only the folder is resolved, so a link kept by `SymlinkPolicy::RenameLink` stays
+        // apart from the file it points to
+        if paths.len() > 1 {
+            let mut seen = HashSet::new();
+            files.retain(|file| {
+                let folder = file.parent().and_then(|parent| std::fs::canonicalize(parent).ok());
+                let key = match (folder, file.file_name()) {
+                    (Some(folder), Some(name)) => folder.join(name),
+                    _ => file.clone(),
+                };
+                seen.insert(key)
+            });
+            links::dedupe_hardlinks(&mut files);
+        }
+        Ok(files)
+    }
+
+    /// Files under `directory` that pass the scan settings and every `.namebackignore` on the way
+    ///
+    /// A path is skipped if either a `.namebackignore` or an exclude pattern matches it, so a
+    /// `!pattern` in an ignore file cannot bring back something excluded from the command line.
+    /// Include patterns then narrow down what is left. Symbolic links are handled as
+    /// `RenameConfig::symlinks` says, and a file with several hard links is scanned once.
+    fn scan_files(&self, directory: &Path) -> Result<Vec<PathBuf>> {
+        let filter = scan_filter::ScanFilter::new(&self.config.include_globs, &self.config.exclude_globs)
+            .context("Invalid include/exclude pattern")?;
+        let filter = std::sync::Arc::new(filter);
+        let root = directory.to_path_buf();
+        let skip_hidden = self.config.skip_hidden;
+        let symlinks = self.config.symlinks;
+
+        let mut files = Vec::new();
+
+        // Only `.namebackignore` files inside the directory count; .gitignore and friends don't
+        let mut walker = ignore::WalkBuilder::new(directory);
+        walker
+            .standard_filters(false)
+            .add_custom_ignore_filename(scan_filter::IGNORE_FILE_NAME)
+            .follow_links(symlinks == SymlinkPolicy::Follow)
+            .max_depth(self.config.max_depth);
+        let entry_filter = std::sync::Arc::clone(&filter);
+        walker.filter_entry(move |e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            let filename = e.file_name().to_str().unwrap_or("");
+
+            // Always skip cache file and ignore files
+            if filename == ".nameback_cache.json" || filename == scan_filter::IGNORE_FILE_NAME {
+                return false;
+            }
+
+            // Skip hidden files if configured
+            if skip_hidden && filename.starts_with('.') {
+                return false;
+            }
+
+            // Excluded folders are pruned here, so nothing below them is visited
+            let relative = e.path().strip_prefix(&root).unwrap_or(e.path());
+            !entry_filter.excludes(relative, e.file_type().is_some_and(|t| t.is_dir()))
+        });
+
+        for entry in walker.build() {
+            match entry {
+                Ok(entry) => {
+                    let relative = entry.path().strip_prefix(directory).unwrap_or(entry.path());
+                    if !filter.includes(relative) {
+                        continue;
+                    }
+                    // Followed links report the type of what they point to
+                    let is_file = entry.file_type().is_some_and(|t| t.is_file());
+                    match (entry.path_is_symlink(), symlinks) {
+                        (false, _) if is_file => files.push(entry.into_path()),
+                        (false, _) => {}
+                        (true, SymlinkPolicy::Follow) if is_file => match std::fs::canonicalize(entry.path()) {
+                            Ok(target) => files.push(target),
+                            Err(e) => tracing::warn!("Failed to follow {}: {}", entry.path().display(), e),
+                        },
+                        (true, SymlinkPolicy::RenameLink) if entry.path().is_file() => files.push(entry.into_path()),
+                        (true, _) => tracing::debug!("Skipping symlink {}", entry.path().display()),
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to access entry: {}", e);
+                }
+            }
+        }
+
+        links::dedupe_hardlinks(&mut files);
+        Ok(files)
+    }
+}
+
+/// Check if all required dependencies are installed
+pub fn check_dependencies() -> Result<(), NamebackError> {
+    deps::print_dependency_status();
+    Ok(())
+}
+
+/// Install missing dependencies (interactive)
+pub fn install_dependencies() -> std::result::Result<(), InstallError> {
+    install_dependencies_with_options(None, &InstallOptions::default())
+}
+
+/// Install dependencies with progress callback
+pub fn install_dependencies_with_progress(
+    progress: Option<deps::ProgressCallback>,
+) -> std::result::Result<(), InstallError> {
+    install_dependencies_with_options(progress, &InstallOptions::default())
+}
+
+/// Install dependencies with progress callback (None = interactive) and install options
+pub fn install_dependencies_with_options(
+    progress: Option<deps::ProgressCallback>,
+    options: &InstallOptions,
+) -> std::result::Result<(), InstallError> {
+    deps::run_installer_with_progress(progress, options)
+}
+
+/// Whether one dependency is installed, with its version, path and whether it is outdated
+pub fn check_dependency(dependency: Dependency) -> DependencyStatus {
+    dependency.status()
+}
+
+/// Install a single dependency with the platform's package manager, with progress callback,
+/// and check it can be found afterwards
+pub fn install_dependency(
+    dependency: Dependency,
+    progress: Option<deps::ProgressCallback>,
+) -> std::result::Result<(), InstallError> {
+    install_dependency_with_options(dependency, progress, &InstallOptions::default())
+}
+
+/// `install_dependency` with install options
+pub fn install_dependency_with_options(
+    dependency: Dependency,
+    progress: Option<deps::ProgressCallback>,
+    options: &InstallOptions,
+) -> std::result::Result<(), InstallError> {
+    deps::run_single_installer(dependency, progress, options)
+}
+
+/// Re-export progress callback type, install options and errors
+pub use deps::{InstallError, InstallOptions, ProgressCallback};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_process_directory_summary() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("unknown.xyz"), "no usable metadata")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        });
+        let summary = engine.process_directory(temp_dir.path(), true)?;
+
+        assert_eq!(summary.analyses.len(), 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.deferred, 0);
+        assert!(summary.results.is_empty());
+        assert_eq!(summary.errors, 0);
+        assert_eq!(summary.renamed(), 0);
+        assert!(summary.cache_stats.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_downloads_are_deferred() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("report.pdf.crdownload"), "half a pdf")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        });
+        let summary = engine.process_directory(temp_dir.path(), true)?;
+
+        assert_eq!(summary.analyses[0].deferred, Some(DeferReason::PartialDownload));
+        assert_eq!(summary.deferred, 1);
+        assert_eq!(summary.skipped, 0);
+        assert_eq!(summary.errors, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recently_changed_files_are_deferred() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("~$Budget.xlsx"), "lock")?;
+        std::fs::write(temp_dir.path().join("notes.txt"), "Quarterly planning meeting notes")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            stable_for: Some(std::time::Duration::from_secs(60)),
+            ..RenameConfig::default()
+        });
+        let summary = engine.process_directory(temp_dir.path(), false)?;
+
+        let reasons: Vec<_> = summary.analyses.iter().filter_map(|a| a.deferred).collect();
+        assert_eq!(reasons.len(), 2);
+        assert!(reasons.contains(&DeferReason::Temporary));
+        assert!(reasons.contains(&DeferReason::RecentlyChanged));
+        assert_eq!(summary.deferred, 2);
+        assert_eq!(summary.renamed(), 0);
+        assert!(temp_dir.path().join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_providers_are_scored_with_builtin_candidates() -> Result<()> {
+        struct Register;
+        impl NameProvider for Register {
+            fn candidates(&self, path: &Path, metadata: &FileMetadata) -> Vec<NameCandidate> {
+                assert_eq!(metadata.category, Some(FileCategory::Document));
+                match path.file_stem().and_then(|s| s.to_str()) {
+                    Some("DOC-1234") => vec![NameCandidate::new(
+                        "Supplier Agreement Final".to_string(),
+                        NameSource::Provider,
+                    )],
+                    _ => Vec::new(),
+                }
+            }
+        }
+
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("DOC-1234.txt"), "0000")?;
+        std::fs::write(temp_dir.path().join("scan.txt"), "0000")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            include_timestamp: false,
+            ..RenameConfig::default()
+        })
+        .with_name_provider(Register);
+        let mut analyses = engine.analyze_directory(temp_dir.path())?;
+        analyses.sort_by(|a, b| a.original_name.cmp(&b.original_name));
+
+        assert_eq!(analyses[0].proposed_name.as_deref(), Some("Supplier_Agreement_Final.txt"));
+        assert_eq!(analyses[0].name_source, NameSource::Provider);
+        assert_ne!(analyses[1].name_source, NameSource::Provider);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_outscored_candidates_are_offered_as_alternatives() -> Result<()> {
+        struct Drafts;
+        impl NameProvider for Drafts {
+            fn candidates(&self, _path: &Path, _metadata: &FileMetadata) -> Vec<NameCandidate> {
+                let named = |name: &str, score: f32| NameCandidate {
+                    score,
+                    ..NameCandidate::new(name.to_string(), NameSource::Provider)
+                };
+                vec![named("Contract Final", 9.0), named("Contract Draft", 8.0), named("contract final", 7.0)]
+            }
+        }
+
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("scan.txt"), "0000")?;
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            include_timestamp: false,
+            ..RenameConfig::default()
+        })
+        .with_name_provider(Drafts);
+        let analysis = engine.analyze_directory(temp_dir.path())?.remove(0);
+
+        assert_eq!(analysis.proposed_name.as_deref(), Some("Contract_Final.txt"));
+        let alternative = &analysis.alternatives[0];
+        assert_eq!((alternative.name.as_str(), alternative.score), ("Contract_Draft.txt", 8.0));
+        // Names differing from the proposal only in case are still offered
+        assert!(analysis.alternatives.iter().any(|a| a.name == "contract_final.txt"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_paths_merges_inputs() -> Result<()> {
+        struct Report;
+        impl NameProvider for Report {
+            fn candidates(&self, _path: &Path, _metadata: &FileMetadata) -> Vec<NameCandidate> {
+                vec![NameCandidate::new("Quarterly Report".to_string(), NameSource::Provider)]
+            }
+        }
+
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        for (file, content) in [("a/DOC-1.txt", "1111"), ("b/DOC-2.txt", "2222"), ("loose/.DOC-3.txt", "3333")] {
+            std::fs::create_dir_all(root.join(file).parent().unwrap())?;
+            std::fs::write(root.join(file), content)?;
+        }
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            include_timestamp: false,
+            skip_hidden: true,
+            collision_strategy: CollisionStrategy::Number,
+            ..RenameConfig::default()
+        })
+        .with_name_provider(Report);
+        // A listed file is analyzed even if hidden; one reached twice, or missing, is not
+        let analyses = engine.analyze_paths(&[
+            root.join("a"),
+            root.join("b"),
+            root.join("a/DOC-1.txt"),
+            root.join("loose/.DOC-3.txt"),
+            root.join("gone.txt"),
+        ])?;
+
+        let originals: Vec<&str> = analyses.iter().map(|a| a.original_name.as_str()).collect();
+        assert_eq!(originals, ["DOC-1.txt", "DOC-2.txt", ".DOC-3.txt"]);
+        // Names are kept unique across the inputs, not just within each folder
+        let names: HashSet<&str> = analyses.iter().filter_map(|a| a.proposed_name.as_deref()).collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains("Quarterly_Report.txt"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_directory_streaming() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for i in 0..3 {
+            std::fs::write(temp_dir.path().join(format!("file_{}.xyz", i)), "data")?;
+        }
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        });
+        let mut events = Vec::new();
+        let analyses = engine.analyze_directory_streaming(temp_dir.path(), |event| events.push(event))?;
+
+        assert!(matches!(events[0], AnalysisEvent::Started { total: 3 }));
+        let analyzed: Vec<&FileAnalysis> = events
+            .iter()
+            .filter_map(|event| match event {
+                AnalysisEvent::Analyzed(analysis) => Some(analysis),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(analyzed.len(), 3);
+        assert_eq!(analyses.len(), 3);
+        // Every file is seen entering detection before its result arrives
+        let detected = events
+            .iter()
+            .filter(|event| matches!(event, AnalysisEvent::Stage { stage: Stage::Detect, .. }))
+            .count();
+        assert_eq!(detected, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_directory_with_progress() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for i in 0..4 {
+            std::fs::write(temp_dir.path().join(format!("file_{}.xyz", i)), "data")?;
+        }
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        });
+        let mut updates = Vec::new();
+        engine.analyze_directory_with_progress(temp_dir.path(), |progress| updates.push(progress))?;
+
+        assert!(updates.iter().all(|p| p.total == 4));
+        assert!(updates.windows(2).all(|w| w[0].done <= w[1].done));
+        let last = updates.last().unwrap();
+        assert_eq!(last.done, 4);
+        assert_eq!(last.eta, Some(std::time::Duration::ZERO));
+        assert!(updates.iter().any(|p| p.stage == Some(Stage::Detect) && p.current_file.is_some()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_depth_and_globs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for file in ["a.pdf", "b.txt", "docs/c.pdf", "docs/deep/d.pdf", "node_modules/e.pdf"] {
+            let path = temp_dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "data")?;
+        }
+
+        let scan = |config: RenameConfig| -> Result<Vec<String>> {
+            let mut names: Vec<String> = RenameEngine::new(config)
+                .scan_files(temp_dir.path())?
+                .iter()
+                .map(|p| p.strip_prefix(temp_dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        let config = RenameConfig::builder()
+            .include_globs(["*.pdf"])
+            .exclude_globs(["node_modules/"])
+            .build()?;
+        assert_eq!(scan(config)?, ["a.pdf", "docs/c.pdf", "docs/deep/d.pdf"]);
+
+        let config = RenameConfig::builder().max_depth(2).exclude_globs(["*.txt"]).build()?;
+        assert_eq!(scan(config)?, ["a.pdf", "docs/c.pdf", "node_modules/e.pdf"]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_and_hardlinks() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let outside = TempDir::new()?;
+        let root = temp_dir.path();
+        std::fs::write(root.join("report.pdf"), "data")?;
+        std::fs::hard_link(root.join("report.pdf"), root.join("report_link.pdf"))?;
+        std::fs::create_dir(outside.path().join("shared"))?;
+        std::fs::write(outside.path().join("shared/notes.txt"), "data")?;
+        std::os::unix::fs::symlink(outside.path().join("shared"), root.join("shared"))?;
+        std::os::unix::fs::symlink(root.join("report.pdf"), root.join("latest.pdf"))?;
+
+        let scan = |symlinks: SymlinkPolicy| -> Result<Vec<PathBuf>> {
+            let config = RenameConfig::builder().symlinks(symlinks).build()?;
+            let mut files = RenameEngine::new(config).scan_files(root)?;
+            files.sort();
+            Ok(files)
+        };
+
+        // The two hard links are one file, scanned under its first path
+        let skipped = scan(SymlinkPolicy::Skip)?;
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with(root));
+
+        let followed = scan(SymlinkPolicy::Follow)?;
+        assert_eq!(followed.len(), 2);
+        assert!(followed.iter().any(|file| file.ends_with("shared/notes.txt")));
+
+        let links = scan(SymlinkPolicy::RenameLink)?;
+        assert_eq!(links.len(), 2);
+        assert!(links.contains(&root.join("latest.pdf")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_namebackignore_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for file in ["a.pdf", "b.tmp", "build/c.pdf", "docs/d.pdf", "docs/keep.tmp", "docs/e.txt"] {
+            let path = temp_dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "data")?;
+        }
+        std::fs::write(temp_dir.path().join(".namebackignore"), "*.tmp\nbuild/\n")?;
+        // Deeper files take precedence, and their patterns are relative to their own folder
+        std::fs::write(temp_dir.path().join("docs/.namebackignore"), "!keep.tmp\n/*.txt\n")?;
+
+        let scan = |config: RenameConfig| -> Result<Vec<String>> {
+            let mut names: Vec<String> = RenameEngine::new(config)
+                .scan_files(temp_dir.path())?
+                .iter()
+                .map(|p| p.strip_prefix(temp_dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        assert_eq!(scan(RenameConfig::default())?, ["a.pdf", "docs/d.pdf", "docs/keep.tmp"]);
+
+        // A `!pattern` can't bring back what an exclude pattern leaves out
+        let config = RenameConfig::builder().exclude_globs(["*.tmp"]).build()?;
+        assert_eq!(scan(config)?, ["a.pdf", "docs/d.pdf"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_turned_off_categories_are_left_out() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for file in ["notes.txt", "song.mp3", "blob.xyz"] {
+            std::fs::write(temp_dir.path().join(file), "data")?;
+        }
+
+        let analyze = |categories: &[FileCategory]| -> Result<Vec<String>> {
+            let config = RenameConfig::builder()
+                .enable_cache(false)
+                .categories(categories.iter().cloned())
+                .build()?;
+            let mut names: Vec<String> = RenameEngine::new(config)
+                .analyze_directory(temp_dir.path())?
+                .into_iter()
+                .map(|a| a.original_name)
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        assert_eq!(analyze(&[FileCategory::Document])?, ["notes.txt"]);
+        // Unsupported files are still reported when `other` is enabled
+        assert_eq!(
+            analyze(&[FileCategory::Audio, FileCategory::Unknown])?,
+            ["blob.xyz", "song.mp3"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatched_extensions_are_reported_and_fixed() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let photo = temp_dir.path().join("Beach_Sunset_Malibu.jpg");
+        image::RgbImage::new(4, 4).save_with_format(&photo, image::ImageFormat::Png)?;
+
+        let analyze = |fix_extensions: bool| -> Result<FileAnalysis> {
+            let config = RenameConfig::builder()
+                .enable_cache(false)
+                .fix_extensions(fix_extensions)
+                .build()?;
+            let mut analyses = RenameEngine::new(config).analyze_directory(temp_dir.path())?;
+            Ok(analyses.remove(0))
+        };
+
+        let kept = analyze(false)?;
+        let mismatch = kept.extension_mismatch.as_ref().expect("PNG content in a .jpg");
+        assert_eq!((mismatch.detected.as_str(), mismatch.fixed), ("png", false));
+        assert!(kept.proposed_name.as_deref().is_some_and(|name| name.ends_with(".jpg")));
+
+        let fixed = analyze(true)?;
+        assert!(fixed.extension_mismatch.is_some_and(|mismatch| mismatch.fixed));
+        assert!(fixed.proposed_name.as_deref().is_some_and(|name| name.ends_with(".png")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_and_lowercase_extensions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gz, b"not a tar")?;
+        std::fs::write(temp_dir.path().join("Website_Backup_Final.TAR.GZ"), gz.finish()?)?;
+        std::fs::write(temp_dir.path().join("meeting.TXT"), "Quarterly planning notes")?;
+
+        let analyze = |lowercase: bool| -> Result<Vec<String>> {
+            let config = RenameConfig::builder().enable_cache(false).lowercase_extensions(lowercase).build()?;
+            let mut analyses = RenameEngine::new(config).analyze_directory(temp_dir.path())?;
+            analyses.sort_by(|a, b| a.original_name.cmp(&b.original_name));
+            Ok(analyses.into_iter().filter_map(|a| a.proposed_name).collect())
+        };
+
+        let kept = analyze(false)?;
+        assert!(kept[0].ends_with(".TAR.GZ") && !kept[0].contains("TAR_GZ"), "{:?}", kept);
+        assert!(kept[1].ends_with(".TXT"), "{:?}", kept);
+        let lowercase = analyze(true)?;
+        assert!(lowercase[0].ends_with(".tar.gz") && lowercase[1].ends_with(".txt"), "{:?}", lowercase);
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_over_size_limit_are_skipped() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("huge_notes.txt"), "Quarterly planning notes\n".repeat(100_000))?;
+        std::fs::write(temp_dir.path().join("small_notes.txt"), "Quarterly planning notes")?;
+
+        let config = RenameConfig::builder().enable_cache(false).max_file_size_mb(1).build()?;
+        let mut analyses = RenameEngine::new(config).analyze_directory(temp_dir.path())?;
+        analyses.sort_by(|a, b| a.original_name.cmp(&b.original_name));
+
+        let huge = &analyses[0];
+        assert_eq!(huge.over_limit, Some(LimitExceeded::FileSize { megabytes: 2, limit: 1 }));
+        assert!(huge.proposed_name.is_none());
+        assert!(huge.errors.is_empty());
+        assert_eq!(analyses[1].over_limit, None);
+        assert!(analyses[1].proposed_name.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_confidence_gates_cached_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("scan.xyz");
+        std::fs::write(&file, "data")?;
+
+        // A weak name from an earlier run (cached outside the analyzed directory)
+        let cache_dir = TempDir::new()?;
+        let cache_path = cache_dir.path().join("metadata.sqlite");
+        let mut cache = metadata_cache::MetadataCache::open(&cache_path)?;
+        let weak = FileAnalysis {
+            proposed_name: Some("Receipt.xyz".to_string()),
+            confidence: 3.0,
+            name_source: NameSource::FilenameAnalysis,
+            ..pipeline::unanalyzed(&file, FileCategory::Document)
+        };
+        cache.insert(&file, &weak, "Document")?;
+        drop(cache);
+
+        let analyze = |min_confidence: f32| {
+            let engine = RenameEngine::new(RenameConfig {
+                cache_path: Some(cache_path.clone()),
+                min_confidence,
+                ..RenameConfig::default()
+            });
+            engine.analyze_directory(temp_dir.path())
+        };
+
+        let analysis = analyze(0.0)?.remove(0);
+        assert_eq!(analysis.proposed_name.as_deref(), Some("Receipt.xyz"));
+        assert_eq!(analysis.confidence, 3.0);
+        assert_eq!(analysis.name_source, NameSource::FilenameAnalysis);
+
+        let analysis = analyze(5.0)?.remove(0);
+        assert_eq!(analysis.proposed_name, None);
+        assert_eq!(analysis.confidence, 3.0);
+        assert!(analysis.errors[0].message.contains("minimum confidence"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_affecting_options_miss_the_cache() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("notes.txt"), "Quarterly planning meeting notes")?;
+        let cache_dir = TempDir::new()?;
+        let cache_path = cache_dir.path().join("metadata.sqlite");
+
+        let analyze = |template: Option<&str>| {
+            let engine = RenameEngine::new(RenameConfig {
+                cache_path: Some(cache_path.clone()),
+                template: template.map(str::to_string),
+                ..RenameConfig::default()
+            });
+            engine.analyze_directory(temp_dir.path())
+        };
+
+        let plain = analyze(None)?.remove(0).proposed_name.unwrap();
+        assert!(plain.starts_with("Quarterly"), "{}", plain);
+        let templated = analyze(Some("{category}_{name}"))?.remove(0).proposed_name.unwrap();
+        assert!(templated.starts_with("document_Quarterly"), "{}", templated);
+
+        let defaults = RenameConfig::default();
+        assert_eq!(defaults.style_cache_key(), "");
+        for config in [
+            RenameConfig { include_location: false, ..RenameConfig::default() },
+            RenameConfig { geocode: false, ..RenameConfig::default() },
+            RenameConfig { geocode_source: GeocodeSource::Offline, ..RenameConfig::default() },
+            RenameConfig { metadata_backend: MetadataBackend::Native, ..RenameConfig::default() },
+            RenameConfig { ocr_languages: vec!["deu".to_string()], ..RenameConfig::default() },
+            RenameConfig { peek_archives: false, ..RenameConfig::default() },
+            RenameConfig { acoustid_key: Some("key".to_string()), ..RenameConfig::default() },
+            RenameConfig {
+                category_overrides: HashMap::from([(
+                    FileCategory::Image,
+                    CategoryOverrides { include_timestamp: Some(false), ..CategoryOverrides::default() },
+                )]),
+                ..RenameConfig::default()
+            },
+        ] {
+            assert_ne!(config.style_cache_key(), "");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collisions_are_settled_on_cached_runs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("a.txt"), "Quarterly planning meeting notes")?;
+        std::fs::write(temp_dir.path().join("b.txt"), "Quarterly planning meeting notes")?;
+        let cache_dir = TempDir::new()?;
+        let cache_path = cache_dir.path().join("metadata.sqlite");
+
+        let analyze = |collision_strategy| {
+            let engine = RenameEngine::new(RenameConfig {
+                cache_path: Some(cache_path.clone()),
+                collision_strategy,
+                ..RenameConfig::default()
+            });
+            engine.analyze_directory(temp_dir.path())
+        };
+
+        let strategies = |analyses: Vec<FileAnalysis>| -> Vec<Option<CollisionStrategy>> {
+            analyses.into_iter().map(|a| a.collision_strategy).filter(Option::is_some).collect()
+        };
+        assert_eq!(strategies(analyze(CollisionStrategy::Number)?), [Some(CollisionStrategy::Number)]);
+        // The second run serves one name from the cache, and still sees the other collide
+        assert_eq!(strategies(analyze(CollisionStrategy::Fail)?), [Some(CollisionStrategy::Fail)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_stats_and_refresh() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = TempDir::new()?;
+        let file = temp_dir.path().join("notes.xyz");
+        std::fs::write(&file, "data")?;
+        std::fs::write(temp_dir.path().join("other.xyz"), "other")?;
+
+        let engine = |refresh_cache: bool| {
+            RenameEngine::new(RenameConfig {
+                cache_path: Some(cache_dir.path().join("metadata.sqlite")),
+                refresh_cache,
+                ..RenameConfig::default()
+            })
+        };
+        let stats = |engine: &RenameEngine| -> Result<(usize, usize, usize)> {
+            engine.analyze_directory(temp_dir.path())?;
+            let stats = engine.cache_stats().unwrap();
+            Ok((stats.hits, stats.misses, stats.invalidations))
+        };
+
+        let cached = engine(false);
+        assert!(cached.cache_stats().is_none());
+        assert_eq!(stats(&cached)?, (0, 2, 0));
+        assert_eq!(stats(&cached)?, (2, 0, 0));
+
+        std::fs::write(&file, "changed data")?;
+        assert_eq!(stats(&cached)?, (1, 1, 1));
+
+        // Refreshing reads nothing from the cache but still fills it
+        assert_eq!(stats(&engine(true))?, (0, 2, 0));
+        assert_eq!(stats(&cached)?, (2, 0, 0));
+        assert_eq!(cached.cache_stats().unwrap().total_entries, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_directory_on_injected_pool() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for i in 0..5 {
+            std::fs::write(temp_dir.path().join(format!("file_{}.xyz", i)), "data")?;
+        }
+
+        let pool = std::sync::Arc::new(rayon::ThreadPoolBuilder::new().num_threads(1).build()?);
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        })
+        .with_thread_pool(pool.clone());
+
+        assert_eq!(engine.analyze_directory(temp_dir.path())?.len(), 5);
+        // Also from inside the (single-threaded) pool itself
+        assert_eq!(pool.install(|| engine.analyze_directory(temp_dir.path()))?.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_directory_async() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("notes.txt"), "Quarterly Planning Meeting\nAgenda")?;
+        std::fs::write(temp_dir.path().join("unknown.xyz"), "data")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            ..RenameConfig::default()
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let analyses = runtime.block_on(engine.analyze_directory_async(temp_dir.path()))?;
+
+        let names = |analyses: &[FileAnalysis]| {
+            let mut names: Vec<Option<String>> = analyses.iter().map(|a| a.proposed_name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&analyses), names(&engine.analyze_directory(temp_dir.path())?));
+        assert!(analyses.iter().any(|a| a.proposed_name.is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_result_paths_and_rollback() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let original = temp_dir.path().join("original.txt");
+        std::fs::write(&original, "content")?;
+
+        let analysis = FileAnalysis {
+            original_path: original.clone(),
+            original_name: "original.txt".to_string(),
+            proposed_name: Some("report_1.txt".to_string()),
+            title: None,
+            collision_suffix: Some(1),
+            collision_strategy: Some(CollisionStrategy::Number),
+            destination: None,
+            duplicate_of: None,
+            companions: Vec::new(),
+            file_category: FileCategory::Document,
+            confidence: 0.0,
+            name_source: NameSource::Fallback,
+            errors: Vec::new(),
+            rejected: Vec::new(),
+            alternatives: Vec::new(),
+            deferred: None,
+            over_limit: None,
+            series: None,
+            extension_mismatch: None,
+        };
+
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+        let mut history = RenameHistory::new(temp_dir.path().join("history.json"), 10);
+        let results = engine.rename_files_with_history(&[analysis], false, Some(&mut history));
+
+        let result = &results[0];
+        assert!(result.success);
+        assert_eq!(result.new_path, temp_dir.path().join("report_1.txt"));
+        assert_eq!(result.collision_suffix, Some(1));
+        assert_eq!(result.collision_strategy, Some(CollisionStrategy::Number));
+        assert!(result.new_path.exists());
+        assert_eq!(history.operations()[0].batch_id, result.batch_id);
+
+        history.undo_token(result.rollback_token.as_ref().unwrap())?;
+        assert!(original.exists());
+        assert!(!result.new_path.exists());
+
+        // Undoing the journal batch now finds the file already restored
+        let report = engine.undo_batch(result.batch_id.as_deref())?;
+        assert!(report.restored.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_organized_move_and_undo() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let original = temp_dir.path().join("IMG_0001.jpg");
+        std::fs::write(&original, "photo")?;
+        let sorted = temp_dir.path().join("Sorted");
+
+        let analysis = FileAnalysis {
+            proposed_name: Some("Sunset.jpg".to_string()),
+            destination: Some(sorted.join("2023/10")),
+            ..pipeline::unanalyzed(&original, FileCategory::Image)
+        };
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+
+        let result = engine.rename_single(&analysis, false)?;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.new_path, sorted.join("2023/10/Sunset.jpg"));
+        assert!(result.new_path.exists());
+
+        // Undo moves the file back and removes the folders the move created
+        let report = engine.undo_batch(result.batch_id.as_deref())?;
+        assert_eq!(report.restored.len(), 1);
+        assert!(original.exists());
+        assert!(!sorted.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_folders_renamed_after_their_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let folder = temp_dir.path().join("New Folder (2)");
+        std::fs::create_dir(&folder)?;
+        let photo = |name: &str, title: &str| -> Result<FileAnalysis> {
+            let path = folder.join(name);
+            std::fs::write(&path, name)?;
+            Ok(FileAnalysis {
+                proposed_name: Some(format!("{}.jpg", title.replace(' ', "_"))),
+                title: Some(title.to_string()),
+                ..pipeline::unanalyzed(&path, FileCategory::Image)
+            })
+        };
+        let files = vec![photo("IMG_1.jpg", "Beach Sunset")?, photo("IMG_2.jpg", "Beach Picnic")?];
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            rename_directories: true,
+            ..RenameConfig::default()
+        });
+
+        // Listed first, the folder is still renamed last
+        let mut analyses = engine.folder_names(&[temp_dir.path().to_path_buf()], &files);
+        analyses.extend(files);
+        let results = engine.rename_files(&analyses, false);
+        assert!(results.iter().all(|r| r.success), "{:?}", results);
+        let renamed = temp_dir.path().join("Beach_Photos");
+        assert_eq!(results[2].new_path, renamed);
+        assert!(renamed.join("Beach_Sunset.jpg").exists() && renamed.join("Beach_Picnic.jpg").exists());
+
+        let report = engine.undo_batch(results[0].batch_id.as_deref())?;
+        assert_eq!(report.restored.len(), 3);
+        assert!(folder.join("IMG_1.jpg").exists() && folder.join("IMG_2.jpg").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_single() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let original = temp_dir.path().join("original.txt");
+        std::fs::write(&original, "content")?;
+
+        let mut analysis = FileAnalysis {
+            original_path: original.clone(),
+            original_name: "original.txt".to_string(),
+            proposed_name: None,
+            title: None,
+            collision_suffix: None,
+            collision_strategy: None,
+            destination: None,
+            duplicate_of: None,
+            companions: Vec::new(),
+            file_category: FileCategory::Document,
+            confidence: 0.0,
+            name_source: NameSource::Fallback,
+            errors: Vec::new(),
+            rejected: Vec::new(),
+            alternatives: Vec::new(),
+            deferred: None,
+            over_limit: None,
+            series: None,
+            extension_mismatch: None,
+        };
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+        assert!(engine.rename_single(&analysis, false).is_err());
+
+        analysis.proposed_name = Some("renamed.txt".to_string());
+        let result = engine.rename_single(&analysis, false)?;
+        assert!(result.success);
+        assert!(result.rollback_token.is_none());
+        assert!(temp_dir.path().join("renamed.txt").exists());
+
+        // Source is gone now, so a second attempt fails inside the result
+        let result = engine.rename_single(&analysis, false)?;
+        assert!(!result.success);
+        assert!(result.error.is_some());
+
+        // The successful rename is still undoable from the journal
+        let report = engine.undo_batch(None)?;
+        assert_eq!(report.restored.len(), 1);
+        assert!(original.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_files_refuses_planned_conflicts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let analyses: Vec<FileAnalysis> = ["scan1.pdf", "scan2.pdf"]
+            .iter()
+            .map(|name| {
+                let path = temp_dir.path().join(name);
+                std::fs::write(&path, name).unwrap();
+                FileAnalysis {
+                    proposed_name: Some("Lease.pdf".to_string()),
+                    ..pipeline::unanalyzed(&path, FileCategory::Document)
+                }
+            })
+            .collect();
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+
+        let plan = engine.plan(&analyses);
+        assert_eq!(plan.blocked().count(), 1);
+
+        for dry_run in [true, false] {
+            let results = engine.rename_files(&analyses, dry_run);
+            assert!(results[0].success);
+            assert!(!results[1].success);
+            assert!(results[1].error.as_deref().unwrap().starts_with("Not renamed"));
+            assert!(matches!(results[1].error_kind, Some(RenameError::TargetExists { .. })));
+        }
+        assert!(temp_dir.path().join("scan2.pdf").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_metadata_only_keeps_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("track01.mp3");
+        std::fs::write(&track, [0xFF, 0xFB, 0x90, 0x00])?;
+        let notes = temp_dir.path().join("notes.txt");
+        std::fs::write(&notes, "Budget")?;
+        let analyses: Vec<FileAnalysis> = [(&track, "Morning_Walk.mp3"), (&notes, "Budget.txt")]
+            .iter()
+            .map(|(path, name)| FileAnalysis {
+                proposed_name: Some(name.to_string()),
+                title: Some(name.split('.').next().unwrap().replace('_', " ")),
+                ..pipeline::unanalyzed(path, FileCategory::Audio)
+            })
+            .collect();
+        let engine = RenameEngine::new(RenameConfig {
+            write_metadata: MetadataWriteMode::Only,
+            metadata_backend: MetadataBackend::Native,
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+
+        let results = engine.rename_files(&analyses, false);
+        assert!(results[0].success && results[0].metadata_written);
+        assert_eq!(results[0].new_path, track);
+        assert!(results[0].batch_id.is_none());
+        assert_eq!(native_metadata::read(&track)?.title.as_deref(), Some("Morning Walk"));
+        // Plain text has no metadata to write into, so it is left alone
+        assert!(!results[1].success);
+        assert!(notes.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_moves_sidecars_as_a_unit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        for name in ["IMG_1.CR2", "IMG_1.xmp", "IMG_2.CR2", "IMG_2.JPG"] {
+            std::fs::write(dir.join(name), name)?;
+        }
+        // The pair's JPEG can't follow, so its RAW file stays put as well
+        std::fs::write(dir.join("Harbor.JPG"), "taken")?;
+        let mut analyses: Vec<FileAnalysis> = [("IMG_1.CR2", "Sunset.cr2"), ("IMG_2.CR2", "Harbor.cr2")]
+            .iter()
+            .map(|(name, proposed)| FileAnalysis {
+                proposed_name: Some(proposed.to_string()),
+                ..pipeline::unanalyzed(&dir.join(name), FileCategory::Image)
+            })
+            .collect();
+        renamer::attach_companions(&mut analyses);
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(dir.join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+
+        let results = engine.rename_files(&analyses, false);
+        assert!(results[0].success);
+        assert_eq!(results[0].companions, [(dir.join("IMG_1.xmp"), dir.join("Sunset.xmp"))]);
+        assert!(dir.join("Sunset.cr2").exists() && dir.join("Sunset.xmp").exists());
+        assert!(!results[1].success);
+        assert!(dir.join("IMG_2.CR2").exists() && dir.join("IMG_2.JPG").exists());
+        assert!(!dir.join("Harbor.cr2").exists());
+
+        // Undoing the batch brings the sidecar back too
+        engine.undo_batch(results[0].batch_id.as_deref())?;
+        assert!(dir.join("IMG_1.CR2").exists() && dir.join("IMG_1.xmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_from_history_updates_the_journal() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        let analyses: Vec<FileAnalysis> = [("scan1.pdf", "Invoice.pdf"), ("scan2.pdf", "Receipt.pdf")]
+            .iter()
+            .map(|(name, proposed)| {
+                std::fs::write(dir.join(name), name).unwrap();
+                FileAnalysis {
+                    proposed_name: Some(proposed.to_string()),
+                    ..pipeline::unanalyzed(&dir.join(name), FileCategory::Document)
+                }
+            })
+            .collect();
+        let engine = RenameEngine::new(RenameConfig {
+            journal_path: Some(dir.join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+        let mut history = RenameHistory::new(dir.join("history.json"), 10);
+        let results = engine.rename_files_with_history(&analyses, false, Some(&mut history));
+
+        // Reverting one file from the history leaves only the other for the journal's undo
+        engine.undo_from_history(&mut history, results[0].rollback_token.as_ref().unwrap())?;
+        assert!(dir.join("scan1.pdf").exists());
+        let report = engine.undo_batch(None)?;
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!(report.restored, [(dir.join("Receipt.pdf"), dir.join("scan2.pdf"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reanalyze_directory_only_changed_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let files_dir = temp_dir.path().join("files");
+        std::fs::create_dir(&files_dir)?;
+        let kept = files_dir.join("kept.xyz");
+        let deleted = files_dir.join("deleted.xyz");
+        std::fs::write(&kept, "kept")?;
+        std::fs::write(&deleted, "deleted")?;
+
+        let engine = RenameEngine::new(RenameConfig {
+            cache_path: Some(temp_dir.path().join("metadata.sqlite")),
+            ..RenameConfig::default()
+        });
+        let previous = engine.analyze_directory(&files_dir)?;
+        assert_eq!(previous.len(), 2);
+
+        std::fs::remove_file(&deleted)?;
+        let added = files_dir.join("added.xyz");
+        std::fs::write(&added, "added")?;
+
+        let incremental = engine.reanalyze_directory(&files_dir, &previous)?;
+        assert_eq!(incremental.changed, vec![added.clone()]);
+        assert_eq!(incremental.removed, vec![deleted]);
+        assert_eq!(incremental.analyses.len(), 2);
+        assert!(incremental.analyses.iter().any(|a| a.original_path == kept));
+        assert!(incremental.analyses.iter().any(|a| a.original_path == added));
+
+        Ok(())
+    }
+}
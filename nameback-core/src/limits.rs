@@ -0,0 +1,122 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::extractor::{self, FileMetadata};
+use crate::RenameConfig;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Which size limit a file was over (see `FileAnalysis::over_limit`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// Larger than `RenameConfig::max_file_size_mb`; the file wasn't analyzed
+    FileSize { megabytes: u64, limit: u64 },
+    /// More pages than `RenameConfig::max_pdf_pages`; the text wasn't read or OCR'd
+    PdfPages { pages: u32, limit: u32 },
+    /// Longer than `RenameConfig::max_video_duration_min`; no frames were OCR'd
+    VideoDuration { minutes: u64, limit: u64 },
+}
+
+impl LimitExceeded {
+    /// Whether the file was left out of analysis entirely, rather than named from its
+    /// metadata alone
+    pub fn skipped(&self) -> bool {
+        matches!(self, LimitExceeded::FileSize { .. })
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::FileSize { megabytes, limit } => {
+                write!(f, "{} MB, over the {} MB size limit", megabytes, limit)
+            }
+            LimitExceeded::PdfPages { pages, limit } => {
+                write!(f, "{} pages, over the {} page limit", pages, limit)
+            }
+            LimitExceeded::VideoDuration { minutes, limit } => {
+                write!(f, "{} minutes long, over the {} minute limit", minutes, limit)
+            }
+        }
+    }
+}
+
+/// Whether `path` is larger than `RenameConfig::max_file_size_mb` (only a `stat`)
+pub(crate) fn file_size(path: &Path, config: &RenameConfig) -> Option<LimitExceeded> {
+    let limit = config.max_file_size_mb?;
+    let len = std::fs::metadata(path).ok()?.len();
+    (len > limit.saturating_mul(BYTES_PER_MB)).then_some(LimitExceeded::FileSize {
+        megabytes: len / BYTES_PER_MB,
+        limit,
+    })
+}
+
+/// Whether the content of a file is too long to read: a PDF with more pages than
+/// `RenameConfig::max_pdf_pages` or a video longer than `RenameConfig::max_video_duration_min`
+/// `extension` is the one its content calls for (see `detector::content_extension`)
+/// Files whose page count or duration isn't in their metadata are never over
+pub(crate) fn content(extension: Option<&str>, config: &RenameConfig, metadata: &FileMetadata) -> Option<LimitExceeded> {
+    if extractor::is_pdf(extension) {
+        let (pages, limit) = (metadata.page_count?, config.max_pdf_pages?);
+        return (pages > limit).then_some(LimitExceeded::PdfPages { pages, limit });
+    }
+    if extractor::is_video(extension) {
+        let (seconds, limit) = (metadata.duration?, config.max_video_duration_min?);
+        return (seconds > limit as f64 * 60.0).then_some(LimitExceeded::VideoDuration {
+            minutes: (seconds / 60.0) as u64,
+            limit,
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn metadata(page_count: Option<u32>, duration: Option<f64>) -> FileMetadata {
+        let mut metadata = extractor::build_metadata(Default::default(), &RenameConfig::default());
+        metadata.page_count = page_count;
+        metadata.duration = duration;
+        metadata
+    }
+
+    #[test]
+    fn test_file_size_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.bin");
+        std::fs::write(&path, vec![0; 3 * BYTES_PER_MB as usize]).unwrap();
+
+        let mut config = RenameConfig::default();
+        assert_eq!(file_size(&path, &config), None);
+        config.max_file_size_mb = Some(2);
+        let over = file_size(&path, &config).unwrap();
+        assert_eq!(over, LimitExceeded::FileSize { megabytes: 3, limit: 2 });
+        assert!(over.skipped());
+        config.max_file_size_mb = Some(3);
+        assert_eq!(file_size(&path, &config), None);
+    }
+
+    #[test]
+    fn test_content_limits() {
+        let config = RenameConfig {
+            max_pdf_pages: Some(100),
+            max_video_duration_min: Some(60),
+            ..RenameConfig::default()
+        };
+        let pdf = Some("pdf");
+        assert_eq!(
+            content(pdf, &config, &metadata(Some(400), None)),
+            Some(LimitExceeded::PdfPages { pages: 400, limit: 100 })
+        );
+        assert_eq!(content(pdf, &config, &metadata(Some(12), None)), None);
+        assert_eq!(content(pdf, &config, &metadata(None, None)), None);
+
+        let video = Some("mkv");
+        let over = content(video, &config, &metadata(None, Some(3.0 * 3600.0))).unwrap();
+        assert_eq!(over, LimitExceeded::VideoDuration { minutes: 180, limit: 60 });
+        assert!(!over.skipped());
+        assert_eq!(content(video, &config, &metadata(None, Some(600.0))), None);
+    }
+}
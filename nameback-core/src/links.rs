@@ -0,0 +1,96 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// What scanning does with symbolic links to files and folders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// Leave links out of the scan
+    #[default]
+    Skip,
+    /// Descend into linked folders and analyze linked files, renaming the files they point to
+    Follow,
+    /// Analyze what links to files point to, but rename the links themselves; linked folders
+    /// are not entered
+    RenameLink,
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymlinkPolicy::Skip => write!(f, "skip"),
+            SymlinkPolicy::Follow => write!(f, "follow"),
+            SymlinkPolicy::RenameLink => write!(f, "rename-link"),
+        }
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            "rename-link" => Ok(SymlinkPolicy::RenameLink),
+            _ => anyhow::bail!("unknown symlink policy '{}' (expected skip, follow or rename-link)", s),
+        }
+    }
+}
+
+/// Drop every path but the first to each file with several hard links (or reached twice
+/// through followed links), so its content is analyzed and renamed once
+/// Files are told apart by device and inode; elsewhere than on Unix nothing is dropped
+pub(crate) fn dedupe_hardlinks(files: &mut Vec<PathBuf>) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut seen = std::collections::HashSet::new();
+        // Every file, not only those with several links: a followed folder link reaches
+        // single-link files twice
+        files.retain(|file| match std::fs::symlink_metadata(file) {
+            Ok(metadata) => {
+                let first = seen.insert((metadata.dev(), metadata.ino()));
+                if !first {
+                    tracing::debug!("Skipping {}: another path to a file already scanned", file.display());
+                }
+                first
+            }
+            Err(_) => true,
+        });
+    }
+    #[cfg(not(unix))]
+    let _ = files;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_symlink_policy() {
+        assert_eq!("rename_link".parse::<SymlinkPolicy>().unwrap(), SymlinkPolicy::RenameLink);
+        assert_eq!(" Follow".parse::<SymlinkPolicy>().unwrap(), SymlinkPolicy::Follow);
+        assert_eq!(SymlinkPolicy::RenameLink.to_string(), "rename-link");
+        assert!("hop".parse::<SymlinkPolicy>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dedupe_hardlinks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let (a, b, c) = (temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt"), temp_dir.path().join("c.txt"));
+        std::fs::write(&a, "same file").unwrap();
+        std::fs::hard_link(&a, &b).unwrap();
+        std::fs::write(&c, "same file").unwrap();
+
+        let mut files = vec![a.clone(), b, c.clone()];
+        dedupe_hardlinks(&mut files);
+        // Copies with the same content are separate files (see `duplicates`)
+        assert_eq!(files, [a, c]);
+    }
+}
@@ -1,4 +1,11 @@
-use chrono::{NaiveDateTime, Timelike};
+use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::LazyLock;
 
 /// Represents GPS location data
 #[derive(Debug, Clone)]
@@ -91,8 +98,8 @@ pub fn format_location(loc: &LocationData) -> String {
 
 /// Formats a timestamp string into a human-readable format
 /// Supports various EXIF timestamp formats
-#[allow(dead_code)]
-pub fn format_timestamp(datetime_str: &str) -> Option<String> {
+#[cfg(test)]
+fn format_timestamp(datetime_str: &str) -> Option<String> {
     // Try common EXIF timestamp formats
     let formats = vec![
         "%Y:%m:%d %H:%M:%S",     // "2023:10:15 14:30:22"
@@ -118,7 +125,7 @@ pub fn format_timestamp(datetime_str: &str) -> Option<String> {
 }
 
 /// Formats date-only strings
-#[allow(dead_code)]
+#[cfg(test)]
 fn format_date_only(date_str: &str) -> Option<String> {
     use chrono::NaiveDate;
 
@@ -139,8 +146,10 @@ fn format_date_only(date_str: &str) -> Option<String> {
 
 /// Determines time of day from timestamp for optional enrichment
 /// Returns: "morning", "afternoon", "evening", or "night"
-#[allow(dead_code)]
-pub fn get_time_of_day(datetime_str: &str) -> Option<&'static str> {
+#[cfg(test)]
+fn get_time_of_day(datetime_str: &str) -> Option<&'static str> {
+    use chrono::Timelike;
+
     let formats = vec![
         "%Y:%m:%d %H:%M:%S",
         "%Y-%m-%d %H:%M:%S",
@@ -162,6 +171,402 @@ pub fn get_time_of_day(datetime_str: &str) -> Option<&'static str> {
     None
 }
 
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance in km (haversine)
+pub(crate) fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// How precisely a GPS position is named in file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocationPrecision {
+    /// `France`
+    Country,
+    /// `WA`, `Ontario` or `Ile_de_France` (the country where the region isn't known)
+    Region,
+    /// `Seattle_WA` or `Paris_France`
+    #[default]
+    City,
+    /// `37.77N_122.42W`, never looked up
+    Coordinates,
+}
+
+impl fmt::Display for LocationPrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationPrecision::Country => write!(f, "country"),
+            LocationPrecision::Region => write!(f, "region"),
+            LocationPrecision::City => write!(f, "city"),
+            LocationPrecision::Coordinates => write!(f, "coordinates"),
+        }
+    }
+}
+
+impl FromStr for LocationPrecision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "country" => Ok(LocationPrecision::Country),
+            "region" | "state" => Ok(LocationPrecision::Region),
+            "city" => Ok(LocationPrecision::City),
+            "coordinates" | "coords" => Ok(LocationPrecision::Coordinates),
+            _ => anyhow::bail!(
+                "unknown location precision '{}' (expected country, region, city or coordinates)",
+                s
+            ),
+        }
+    }
+}
+
+/// A circle around a private place (home, say) whose photos never get a location in their
+/// names, written `LAT,LON[,RADIUS]` with the radius in km (default 1) or with `m`/`km`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BlockedArea {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+}
+
+impl BlockedArea {
+    /// Whether `location` lies within the area
+    pub fn contains(&self, location: &LocationData) -> bool {
+        distance_km(self.latitude, self.longitude, location.latitude, location.longitude) <= self.radius_km
+    }
+
+    /// Why the area can't be used, if it can't
+    pub(crate) fn problem(&self) -> Option<&'static str> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            Some("latitude must be between -90 and 90")
+        } else if !(-180.0..=180.0).contains(&self.longitude) {
+            Some("longitude must be between -180 and 180")
+        } else if !(self.radius_km.is_finite() && self.radius_km > 0.0) {
+            Some("radius must be more than 0")
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for BlockedArea {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}km", self.latitude, self.longitude, self.radius_km)
+    }
+}
+
+impl FromStr for BlockedArea {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let (latitude, longitude, radius) = match parts.as_slice() {
+            [latitude, longitude] => (latitude, longitude, "1"),
+            [latitude, longitude, radius] => (latitude, longitude, *radius),
+            _ => anyhow::bail!("invalid blocked area '{}' (expected LAT,LON or LAT,LON,RADIUS)", s),
+        };
+        let number = |text: &str| {
+            text.trim()
+                .parse::<f64>()
+                .map_err(|_| anyhow::anyhow!("invalid blocked area '{}': '{}' isn't a number", s, text))
+        };
+        let radius_km = match radius.to_lowercase() {
+            r if r.ends_with("km") => number(&r[..r.len() - 2])?,
+            r if r.ends_with('m') => number(&r[..r.len() - 1])? / 1000.0,
+            r => number(&r)?,
+        };
+        let area = BlockedArea {
+            latitude: number(latitude)?,
+            longitude: number(longitude)?,
+            radius_km,
+        };
+        match area.problem() {
+            Some(problem) => anyhow::bail!("invalid blocked area '{}': {}", s, problem),
+            None => Ok(area),
+        }
+    }
+}
+
+impl TryFrom<String> for BlockedArea {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<BlockedArea> for String {
+    fn from(area: BlockedArea) -> Self {
+        area.to_string()
+    }
+}
+
+/// Date added to names unless `RenameConfig::timestamp_format` says otherwise
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Date and time added to names with `TimestampPrecision::DateTime`
+pub const DATETIME_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+/// How much of a file's timestamp goes into its name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampPrecision {
+    /// `2023-10-15`
+    #[default]
+    Date,
+    /// `2023-10-15_143022` (the date alone for files that only record one)
+    DateTime,
+}
+
+impl fmt::Display for TimestampPrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampPrecision::Date => write!(f, "date"),
+            TimestampPrecision::DateTime => write!(f, "datetime"),
+        }
+    }
+}
+
+impl FromStr for TimestampPrecision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "date" => Ok(TimestampPrecision::Date),
+            "datetime" | "date-time" => Ok(TimestampPrecision::DateTime),
+            _ => anyhow::bail!("unknown timestamp precision '{}' (expected date or datetime)", s),
+        }
+    }
+}
+
+/// Which end of a name the timestamp goes on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampPosition {
+    /// `Sunset_Paris_2023-10-15`
+    #[default]
+    Suffix,
+    /// `2023-10-15_Sunset_Paris`, so names sort by date
+    Prefix,
+}
+
+impl fmt::Display for TimestampPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampPosition::Suffix => write!(f, "suffix"),
+            TimestampPosition::Prefix => write!(f, "prefix"),
+        }
+    }
+}
+
+impl FromStr for TimestampPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "suffix" | "end" => Ok(TimestampPosition::Suffix),
+            "prefix" | "start" => Ok(TimestampPosition::Prefix),
+            _ => anyhow::bail!("unknown timestamp position '{}' (expected suffix or prefix)", s),
+        }
+    }
+}
+
+/// `RenameConfig::timestamp_format`, `timestamp_precision` and `timestamp_position`
+/// together, as the generator applies them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimestampStyle {
+    /// strftime pattern (None = the precision's own, `DATE_FORMAT` or `DATETIME_FORMAT`)
+    pub format: Option<String>,
+    pub precision: TimestampPrecision,
+    pub position: TimestampPosition,
+}
+
+impl TimestampStyle {
+    /// A metadata timestamp such as `2023:10:15 14:30:22`, formatted for a name
+    /// Files that only record a date get `DATE_FORMAT` if the pattern needs a time of day
+    pub(crate) fn render(&self, timestamp: &str) -> Option<String> {
+        let (date, time) = parse_timestamp(timestamp)?;
+        Some(self.format(date, time))
+    }
+
+    fn format(&self, date: NaiveDate, time: Option<NaiveTime>) -> String {
+        let pattern = self.format.as_deref().unwrap_or(match self.precision {
+            TimestampPrecision::Date => DATE_FORMAT,
+            TimestampPrecision::DateTime => DATETIME_FORMAT,
+        });
+
+        let mut rendered = String::new();
+        let written = match time {
+            Some(time) => write!(rendered, "{}", date.and_time(time).format(pattern)),
+            None => write!(rendered, "{}", date.format(pattern)),
+        };
+        match written {
+            Ok(()) => rendered,
+            Err(_) => date.format(DATE_FORMAT).to_string(),
+        }
+    }
+
+    /// Tells cache entries made with another timestamp style apart; empty for the default
+    pub(crate) fn cache_key(&self) -> String {
+        if *self == TimestampStyle::default() {
+            return String::new();
+        }
+        format!(
+            "{}/{}/{}",
+            self.precision,
+            self.position,
+            self.format.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// Why `pattern` can't be used as a `timestamp_format`, if it can't
+pub(crate) fn check_format(pattern: &str) -> Option<&'static str> {
+    if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+        return Some("isn't a valid strftime pattern");
+    }
+    let sample = NaiveDate::from_ymd_opt(2023, 10, 15)
+        .and_then(|date| date.and_hms_opt(14, 30, 22))
+        .expect("valid sample date");
+    let mut rendered = String::new();
+    if write!(rendered, "{}", sample.format(pattern)).is_err() {
+        return Some("can't be formatted");
+    }
+    if rendered.trim().is_empty() {
+        return Some("produces an empty timestamp");
+    }
+    let unsafe_char = |c: char| matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control();
+    if rendered.contains(unsafe_char) {
+        return Some("produces characters that aren't allowed in file names");
+    }
+    None
+}
+
+/// Date and (if recorded) time of day of a metadata timestamp: EXIF-style `2023:10:15
+/// 14:30:22`, ISO `2023-10-15T14:30:22`, `20231015_143022` or a bare date; anything after
+/// the seconds (a time zone, say) is ignored
+fn parse_timestamp(timestamp: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let timestamp = timestamp.trim();
+    let datetime_formats = [
+        (19, "%Y:%m:%d %H:%M:%S"),
+        (19, "%Y-%m-%d %H:%M:%S"),
+        (19, "%Y-%m-%dT%H:%M:%S"),
+        (15, "%Y%m%d_%H%M%S"),
+    ];
+    for (len, format) in datetime_formats {
+        if let Some(dt) = timestamp.get(..len).and_then(|t| NaiveDateTime::parse_from_str(t, format).ok()) {
+            return Some((dt.date(), Some(dt.time())));
+        }
+    }
+
+    let date_part = timestamp.split_whitespace().next()?.replace(':', "-");
+    let date = NaiveDate::parse_from_str(&date_part, DATE_FORMAT)
+        .ok()
+        .or_else(|| parse_written_date(timestamp))?;
+    Some((date, None))
+}
+
+/// Month names and abbreviations in English, Spanish, French, German, Italian, Portuguese
+/// and Dutch (lower-case; a trailing `.` is dropped before lookup)
+const MONTH_NAMES: &[(&str, u32)] = &[
+    ("january", 1), ("jan", 1), ("enero", 1), ("ene", 1), ("janvier", 1), ("janv", 1),
+    ("januar", 1), ("jänner", 1), ("gennaio", 1), ("gen", 1), ("janeiro", 1), ("januari", 1),
+    ("february", 2), ("feb", 2), ("febrero", 2), ("février", 2), ("fevrier", 2), ("févr", 2),
+    ("februar", 2), ("febbraio", 2), ("fevereiro", 2), ("fev", 2), ("februari", 2),
+    ("march", 3), ("mar", 3), ("marzo", 3), ("mars", 3), ("märz", 3), ("maerz", 3), ("mär", 3),
+    ("março", 3), ("marco", 3), ("maart", 3), ("mrt", 3),
+    ("april", 4), ("apr", 4), ("abril", 4), ("abr", 4), ("avril", 4), ("avr", 4), ("aprile", 4),
+    ("may", 5), ("mayo", 5), ("mai", 5), ("maggio", 5), ("mag", 5), ("maio", 5), ("mei", 5),
+    ("june", 6), ("jun", 6), ("junio", 6), ("juin", 6), ("juni", 6), ("giugno", 6), ("giu", 6),
+    ("junho", 6),
+    ("july", 7), ("jul", 7), ("julio", 7), ("juillet", 7), ("juil", 7), ("juli", 7),
+    ("luglio", 7), ("lug", 7), ("julho", 7),
+    ("august", 8), ("aug", 8), ("agosto", 8), ("ago", 8), ("août", 8), ("aout", 8),
+    ("augustus", 8),
+    ("september", 9), ("sep", 9), ("sept", 9), ("septiembre", 9), ("setiembre", 9),
+    ("septembre", 9), ("settembre", 9), ("set", 9), ("setembro", 9),
+    ("october", 10), ("oct", 10), ("octubre", 10), ("octobre", 10), ("oktober", 10), ("okt", 10),
+    ("ottobre", 10), ("ott", 10), ("outubro", 10), ("out", 10),
+    ("november", 11), ("nov", 11), ("noviembre", 11), ("novembre", 11), ("novembro", 11),
+    ("december", 12), ("dec", 12), ("diciembre", 12), ("dic", 12), ("décembre", 12),
+    ("decembre", 12), ("déc", 12), ("dezember", 12), ("dez", 12), ("dicembre", 12),
+    ("dezembro", 12),
+];
+
+/// Dates written with a month name or in CJK form: `15 Octubre 2023`, `15. Oktober 2023`,
+/// `15 de octubre de 2023`, `October 15th, 2023`, `2023年10月15日`, `2023년 10월 15일`
+/// Words may also be joined by `_` or `-`, as in file names
+static WRITTEN_DATE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    let mut names: Vec<&str> = MONTH_NAMES.iter().map(|(name, _)| *name).collect();
+    // Longest first, so "septiembre" isn't read as "sep"
+    names.sort_by_key(|name| std::cmp::Reverse(name.chars().count()));
+    let month = names.join("|");
+    let gap = r"[\s_-]+";
+    let day_month_year = format!(
+        r"(?P<d1>\d{{1,2}})(?:st|nd|rd|th|er|º)?\.?{gap}(?:de{gap})?(?P<m1>{month})\.?,?{gap}(?:del?{gap})?(?P<y1>\d{{4}})"
+    );
+    let month_day_year = format!(r"(?P<m2>{month})\.?{gap}(?P<d2>\d{{1,2}})(?:st|nd|rd|th)?,?{gap}(?P<y2>\d{{4}})");
+    let cjk = r"(?P<y3>\d{4})\s*[年년]\s*(?P<m3>\d{1,2})\s*[月월]\s*(?P<d3>\d{1,2})\s*[日일]";
+    // `\b` would take `_` for part of a word, so words are bounded by hand
+    let edge = r"[^\p{L}\p{N}]";
+    regex::Regex::new(&format!(
+        "(?i)(?:^|{edge})(?P<date>{day_month_year}|{month_day_year})(?:{edge}|$)|(?P<cjk>{cjk})"
+    ))
+    .expect("WRITTEN_DATE pattern should be valid")
+});
+
+/// Every written date in `text` (see `WRITTEN_DATE`), in order, with where it is
+pub(crate) fn find_written_dates(text: &str) -> Vec<(Range<usize>, NaiveDate)> {
+    WRITTEN_DATE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let number = |name: &str| caps.name(name)?.as_str().parse::<u32>().ok();
+            let month = caps
+                .name("m1")
+                .or_else(|| caps.name("m2"))
+                .and_then(|name| month_number(name.as_str()))
+                .or_else(|| number("m3"))?;
+            let year = number("y1").or_else(|| number("y2")).or_else(|| number("y3"))?;
+            let day = number("d1").or_else(|| number("d2")).or_else(|| number("d3"))?;
+            let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+            Some((caps.name("date").or_else(|| caps.name("cjk"))?.range(), date))
+        })
+        .collect()
+}
+
+/// `text` if it is nothing but a written date (see `WRITTEN_DATE`)
+pub(crate) fn parse_written_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    match find_written_dates(text).as_slice() {
+        [(range, date)] if range.len() == text.len() => Some(*date),
+        _ => None,
+    }
+}
+
+/// `text` with its written dates in `style`, e.g. `Factura 15 Octubre 2023` becomes
+/// `Factura 2023-10-15`
+pub(crate) fn normalize_written_dates(text: &str, style: &TimestampStyle) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = 0;
+    for (range, date) in find_written_dates(text) {
+        normalized.push_str(&text[rest..range.start]);
+        normalized.push_str(&style.format(date, None));
+        rest = range.end;
+    }
+    normalized.push_str(&text[rest..]);
+    normalized
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.trim_end_matches('.').to_lowercase();
+    MONTH_NAMES.iter().find(|(month, _)| *month == name).map(|(_, number)| *number)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,4 +675,90 @@ mod tests {
         assert!(formatted.contains("S"));
         assert!(formatted.contains("E"));
     }
+
+    #[test]
+    fn test_timestamp_styles() {
+        let style = |format: Option<&str>, precision| TimestampStyle {
+            format: format.map(str::to_string),
+            precision,
+            position: TimestampPosition::Suffix,
+        };
+        let exif = "2023:10:15 14:30:22+02:00";
+        assert_eq!(TimestampStyle::default().render(exif).as_deref(), Some("2023-10-15"));
+        assert_eq!(
+            style(None, TimestampPrecision::DateTime).render(exif).as_deref(),
+            Some("2023-10-15_143022")
+        );
+        assert_eq!(
+            style(Some("%d.%m.%Y"), TimestampPrecision::Date).render("2023-10-15T14:30:22Z").as_deref(),
+            Some("15.10.2023")
+        );
+        // Without a recorded time of day, only the date is used
+        assert_eq!(
+            style(None, TimestampPrecision::DateTime).render("2023:10:15").as_deref(),
+            Some("2023-10-15")
+        );
+        assert_eq!(
+            style(Some("%Y%m%d"), TimestampPrecision::Date).render("2023:10:15").as_deref(),
+            Some("20231015")
+        );
+        assert_eq!(TimestampStyle::default().render("0000:00:00 00:00:00"), None);
+    }
+
+    #[test]
+    fn test_check_timestamp_format() {
+        assert_eq!(check_format("%Y-%m-%d_%Hh%M"), None);
+        assert_eq!(check_format("%B %Y"), None);
+        assert!(check_format("%Q").is_some());
+        assert!(check_format("%Y/%m").is_some());
+        assert!(check_format("%H:%M").is_some());
+        assert!(check_format("").is_some());
+    }
+
+    #[test]
+    fn test_written_dates() {
+        for (text, expected) in [
+            ("15 Octubre 2023", (2023, 10, 15)),
+            ("15 de octubre de 2023", (2023, 10, 15)),
+            ("15. Oktober 2023", (2023, 10, 15)),
+            ("1er décembre 2023", (2023, 12, 1)),
+            ("October 15th, 2023", (2023, 10, 15)),
+            ("Sept. 3, 2023", (2023, 9, 3)),
+            ("3 maart 2023", (2023, 3, 3)),
+            ("2023年10月15日", (2023, 10, 15)),
+            ("2023년 10월 15일", (2023, 10, 15)),
+        ] {
+            let (year, month, day) = expected;
+            assert_eq!(parse_written_date(text), NaiveDate::from_ymd_opt(year, month, day), "{}", text);
+        }
+        assert_eq!(parse_written_date("31 February 2023"), None);
+        assert_eq!(parse_written_date("Octubre 2023"), None);
+        // Month words inside other words aren't dates
+        assert!(find_written_dates("Version 12 Mayonnaise 2023").is_empty());
+
+        let style = TimestampStyle {
+            format: Some("%d.%m.%Y".to_string()),
+            ..TimestampStyle::default()
+        };
+        assert_eq!(
+            normalize_written_dates("Factura_15_Octubre_2023 y 2023年10月16日", &style),
+            "Factura_15.10.2023 y 16.10.2023"
+        );
+        assert_eq!(TimestampStyle::default().render("15 Okt 2023").as_deref(), Some("2023-10-15"));
+    }
+
+    #[test]
+    fn test_blocked_areas() {
+        let home: BlockedArea = "47.6062, -122.3321, 500m".parse().unwrap();
+        assert_eq!(home.radius_km, 0.5);
+        assert!(home.contains(&LocationData { latitude: 47.607, longitude: -122.333 }));
+        assert!(!home.contains(&LocationData { latitude: 47.62, longitude: -122.33 }));
+
+        assert_eq!("10,20".parse::<BlockedArea>().unwrap().radius_km, 1.0);
+        assert_eq!("10,20,2.5km".parse::<BlockedArea>().unwrap().radius_km, 2.5);
+        for bad in ["10", "95,0", "0,200", "10,20,0", "10,20,-1km", "north,20"] {
+            assert!(bad.parse::<BlockedArea>().is_err(), "{}", bad);
+        }
+        assert_eq!(home.to_string().parse::<BlockedArea>().unwrap(), home);
+    }
 }
@@ -1,13 +1,24 @@
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{FileAnalysis, NameAlternative, NameSource};
+
+/// Entries kept before the least recently used are evicted (roughly 25 MB on disk)
+const MAX_ENTRIES: usize = 100_000;
+
+/// How long a write waits for another nameback process holding the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bumped when the table layout changes; older databases are rebuilt
+const SCHEMA_VERSION: i32 = 3;
 
 /// Cache entry storing metadata and file hash
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CacheEntry {
-    /// SHA-256 hash of file contents
+    /// Hash of file contents (see `compute_file_hash`)
     pub file_hash: String,
     /// File size in bytes
     pub file_size: u64,
@@ -15,129 +26,331 @@ pub struct CacheEntry {
     pub modified_time: u64,
     /// Cached proposed filename
     pub proposed_name: Option<String>,
+    /// Title the proposed name was built from
+    pub title: Option<String>,
+    /// Score of the proposed name
+    pub confidence: Option<f32>,
+    /// Where the proposed name came from
+    pub name_source: Option<NameSource>,
+    /// Other names offered for the file
+    pub alternatives: Vec<NameAlternative>,
     /// File category
     pub category: String,
+    /// `RenameConfig::style_cache_key` of the styles the proposed name was made in
+    pub name_style: String,
     /// Timestamp when this cache entry was created
     pub cache_time: u64,
 }
 
-/// Metadata cache that persists to disk
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Metadata cache kept in an SQLite database shared by every scanned directory
+///
+/// Each write commits on its own in WAL mode, so several nameback processes can use the
+/// same database; one waits for another's write for up to `BUSY_TIMEOUT`.
 pub struct MetadataCache {
-    /// Map from file path to cache entry
-    entries: HashMap<String, CacheEntry>,
-    /// Cache file path
-    #[serde(skip)]
-    cache_path: PathBuf,
+    connection: Connection,
+    /// Lookups this run, for `CacheStats`
+    hits: usize,
+    misses: usize,
+    invalidations: usize,
+    /// Style recorded with the entries this cache writes
+    name_style: String,
 }
 
 impl MetadataCache {
-    /// Create a new cache that will be saved to the given path
-    pub fn new(cache_path: PathBuf) -> Self {
-        Self {
-            entries: HashMap::new(),
-            cache_path,
-        }
+    /// Where the cache is kept unless `cache_path` says otherwise
+    pub fn default_path() -> Option<PathBuf> {
+        crate::app_dirs::cache_dir().map(|dir| dir.join("metadata.sqlite"))
     }
 
-    /// Load cache from disk, or create new if doesn't exist
-    pub fn load(cache_path: PathBuf) -> Result<Self> {
-        if cache_path.exists() {
-            let data = fs::read_to_string(&cache_path)?;
-            let mut cache: MetadataCache = serde_json::from_str(&data)?;
-            cache.cache_path = cache_path;
-            Ok(cache)
-        } else {
-            Ok(Self::new(cache_path))
+    /// Open (or create) the cache database at the given path
+    /// A file there that isn't a cache database (e.g. an old JSON cache) is replaced
+    pub fn open(cache_path: &Path) -> Result<Self> {
+        if let Some(parent) = cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match Self::connect(cache_path) {
+            Err(e) if e.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) => {
+                tracing::info!("Replacing {}, which isn't a cache database", cache_path.display());
+                fs::remove_file(cache_path)?;
+                Ok(Self::connect(cache_path)?)
+            }
+            result => result.with_context(|| format!("Failed to open cache {}", cache_path.display())),
         }
     }
 
-    /// Save cache to disk
-    pub fn save(&self) -> Result<()> {
-        // Create parent directory if needed
-        if let Some(parent) = self.cache_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// A cache that lives only as long as this value
+    pub fn in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("SQLite can always open in memory");
+        let cache = Self::with_connection(connection);
+        cache.create_schema().expect("Schema of a fresh database");
+        cache
+    }
+
+    fn connect(cache_path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(cache_path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        // WAL lets readers carry on during writes; it isn't available on every file system
+        let _ = connection.pragma_update(None, "journal_mode", "WAL");
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let cache = Self::with_connection(connection);
+        cache.create_schema()?;
+        Ok(cache)
+    }
+
+    fn with_connection(connection: Connection) -> Self {
+        Self {
+            connection,
+            hits: 0,
+            misses: 0,
+            invalidations: 0,
+            name_style: String::new(),
         }
+    }
 
-        let data = serde_json::to_string_pretty(&self)?;
-        fs::write(&self.cache_path, data)?;
-        Ok(())
+    /// Record `name_style` (a `RenameConfig::style_cache_key`) with every entry written from now on
+    pub fn with_name_style(mut self, name_style: String) -> Self {
+        self.name_style = name_style;
+        self
+    }
+
+    fn create_schema(&self) -> rusqlite::Result<()> {
+        let version = || self.connection.pragma_query_value(None, "user_version", |row| row.get::<_, i32>(0));
+        if version()? == SCHEMA_VERSION {
+            return Ok(());
+        }
+        // Another process may be creating the schema right now: check again holding the write
+        // lock so its fresh table isn't dropped
+        self.connection.execute_batch("BEGIN IMMEDIATE")?;
+        let result = version().and_then(|version| {
+            if version == SCHEMA_VERSION {
+                return Ok(());
+            }
+            self.connection.execute_batch(
+                "DROP TABLE IF EXISTS entries;
+                CREATE TABLE entries (
+                    path TEXT PRIMARY KEY,
+                    file_hash TEXT NOT NULL,
+                    file_size INTEGER NOT NULL,
+                    modified_time INTEGER NOT NULL,
+                    proposed_name TEXT,
+                    title TEXT,
+                    confidence REAL,
+                    name_source TEXT,
+                    alternatives TEXT,
+                    category TEXT NOT NULL,
+                    name_style TEXT NOT NULL,
+                    cache_time INTEGER NOT NULL,
+                    last_used INTEGER NOT NULL
+                );
+                CREATE INDEX entries_last_used ON entries (last_used);",
+            )?;
+            self.connection.pragma_update(None, "user_version", SCHEMA_VERSION)
+        });
+        match result {
+            Ok(()) => self.connection.execute_batch("COMMIT"),
+            Err(e) => {
+                let _ = self.connection.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
     }
 
     /// Check if file has valid cache entry (hash matches)
-    pub fn has_valid_entry(&self, file_path: &Path) -> Result<bool> {
-        let path_str = file_path.to_string_lossy().to_string();
-
-        if let Some(entry) = self.entries.get(&path_str) {
-            // Check if file still exists and hasn't changed
-            if let Ok(metadata) = fs::metadata(file_path) {
-                let current_size = metadata.len();
-                let current_modified = metadata
-                    .modified()?
-                    .duration_since(std::time::UNIX_EPOCH)?
-                    .as_secs();
-
-                // Quick check: size and modification time
-                if entry.file_size == current_size && entry.modified_time == current_modified {
-                    return Ok(true);
-                }
-
-                // If quick check fails, verify with hash
-                let current_hash = Self::compute_file_hash(file_path)?;
-                return Ok(entry.file_hash == current_hash);
+    /// An entry for a file that has since changed counts as an invalidation
+    pub fn has_valid_entry(&mut self, file_path: &Path) -> Result<bool> {
+        let Some(entry) = self.lookup(file_path)? else {
+            return Ok(false);
+        };
+
+        // Check if file still exists and hasn't changed
+        if let Ok(metadata) = fs::metadata(file_path) {
+            // Quick check: size and modification time
+            if entry.file_size == metadata.len() && Some(entry.modified_time) == modified_secs(&metadata) {
+                return Ok(true);
+            }
+
+            // If quick check fails, verify with hash
+            let current_hash = Self::compute_file_hash(file_path)?;
+            if entry.file_hash == current_hash {
+                return Ok(true);
             }
         }
 
+        self.invalidations += 1;
         Ok(false)
     }
 
-    /// Get cached entry for file
-    pub fn get(&self, file_path: &Path) -> Option<&CacheEntry> {
-        let path_str = file_path.to_string_lossy().to_string();
-        self.entries.get(&path_str)
+    /// Quick check that a cached file's size and mtime are unchanged (no hashing)
+    pub fn is_unchanged(&self, file_path: &Path) -> bool {
+        let Ok(Some(entry)) = self.lookup(file_path) else {
+            return false;
+        };
+        let Ok(metadata) = fs::metadata(file_path) else {
+            return false;
+        };
+
+        entry.file_size == metadata.len() && modified_secs(&metadata) == Some(entry.modified_time)
     }
 
-    /// Store cache entry for file
-    pub fn insert(
-        &mut self,
-        file_path: &Path,
-        proposed_name: Option<String>,
-        category: &str,
-    ) -> Result<()> {
-        let path_str = file_path.to_string_lossy().to_string();
-        let metadata = fs::metadata(file_path)?;
+    /// Count a lookup: served from the cache, or analyzed afresh
+    pub fn record_lookup(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
 
-        let entry = CacheEntry {
-            file_hash: Self::compute_file_hash(file_path)?,
-            file_size: metadata.len(),
-            modified_time: metadata
-                .modified()?
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs(),
-            proposed_name,
-            category: category.to_string(),
-            cache_time: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs(),
-        };
+    /// Get cached entry for file, marking it as recently used
+    pub fn get(&self, file_path: &Path) -> Option<CacheEntry> {
+        let entry = self
+            .lookup(file_path)
+            .inspect_err(|e| tracing::debug!("Cache lookup failed for {}: {}", file_path.display(), e))
+            .ok()??;
+        let touched = self.connection.execute(
+            "UPDATE entries SET last_used = ?1 WHERE path = ?2",
+            params![now_secs() as i64, path_key(file_path)],
+        );
+        if let Err(e) = touched {
+            tracing::debug!("Failed to mark cache entry as used: {}", e);
+        }
+        Some(entry)
+    }
 
-        self.entries.insert(path_str, entry);
+    fn lookup(&self, file_path: &Path) -> Result<Option<CacheEntry>> {
+        let entry = self
+            .connection
+            .query_row(
+                "SELECT file_hash, file_size, modified_time, proposed_name, title, confidence,
+                        name_source, category, name_style, cache_time, alternatives
+                 FROM entries WHERE path = ?1",
+                [path_key(file_path)],
+                |row| {
+                    let name_source: Option<String> = row.get(6)?;
+                    let alternatives: Option<String> = row.get(10)?;
+                    Ok(CacheEntry {
+                        file_hash: row.get(0)?,
+                        file_size: row.get::<_, i64>(1)? as u64,
+                        modified_time: row.get::<_, i64>(2)? as u64,
+                        proposed_name: row.get(3)?,
+                        title: row.get(4)?,
+                        confidence: row.get::<_, Option<f64>>(5)?.map(|c| c as f32),
+                        name_source: name_source.and_then(|s| serde_json::from_str(&s).ok()),
+                        alternatives: alternatives
+                            .and_then(|s| serde_json::from_str(&s).ok())
+                            .unwrap_or_default(),
+                        category: row.get(7)?,
+                        name_style: row.get(8)?,
+                        cache_time: row.get::<_, i64>(9)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(entry)
+    }
+
+    /// Store the proposed name and score of an analyzed file
+    pub fn insert(&mut self, file_path: &Path, analysis: &FileAnalysis, category: &str) -> Result<()> {
+        let metadata = fs::metadata(file_path)?;
+        let modified_time = modified_secs(&metadata).context("File has no modification time")?;
+        let now = now_secs() as i64;
+
+        self.connection.execute(
+            "INSERT OR REPLACE INTO entries
+                (path, file_hash, file_size, modified_time, proposed_name, title, confidence,
+                 name_source, alternatives, category, name_style, cache_time, last_used)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?12)",
+            params![
+                path_key(file_path),
+                Self::compute_file_hash(file_path)?,
+                metadata.len() as i64,
+                modified_time as i64,
+                analysis.proposed_name,
+                analysis.title,
+                analysis.confidence as f64,
+                serde_json::to_string(&analysis.name_source)?,
+                serde_json::to_string(&analysis.alternatives)?,
+                category,
+                self.name_style,
+                now,
+            ],
+        )?;
         Ok(())
     }
 
-    /// Remove stale entries for files that no longer exist
-    pub fn cleanup_stale_entries(&mut self, valid_paths: &[PathBuf]) {
-        let valid_set: std::collections::HashSet<String> = valid_paths
-            .iter()
-            .map(|p| p.to_string_lossy().to_string())
-            .collect();
+    /// Drop the entry for a file, if any
+    pub fn remove(&mut self, file_path: &Path) {
+        let removed = self
+            .connection
+            .execute("DELETE FROM entries WHERE path = ?1", [path_key(file_path)]);
+        if let Err(e) = removed {
+            tracing::debug!("Failed to remove cache entry for {}: {}", file_path.display(), e);
+        }
+    }
+
+    /// Remove entries for files under `directory` that no longer exist
+    /// Entries elsewhere are left for the scans of their own directories (or eviction)
+    pub fn remove_missing(&mut self, directory: &Path) -> Result<usize> {
+        let prefix = path_key(&directory.join(""));
+        // Every path starting with `prefix` sorts between it and the prefix with its last
+        // character (the separator) incremented
+        let mut upper = prefix.clone();
+        let separator = upper.pop().unwrap_or('/');
+        upper.push(char::from_u32(separator as u32 + 1).unwrap_or(char::MAX));
+
+        let paths: Vec<String> = self
+            .connection
+            .prepare("SELECT path FROM entries WHERE path >= ?1 AND path < ?2")?
+            .query_map([&prefix, &upper], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let transaction = self.connection.transaction()?;
+        let mut removed = 0;
+        for path in paths.iter().filter(|p| !Path::new(p).exists()) {
+            removed += transaction.execute("DELETE FROM entries WHERE path = ?1", [path])?;
+        }
+        transaction.commit()?;
+        Ok(removed)
+    }
+
+    /// Evict the least recently used entries beyond the size cap
+    pub fn evict(&mut self) -> Result<usize> {
+        self.evict_to(MAX_ENTRIES)
+    }
+
+    fn evict_to(&mut self, max_entries: usize) -> Result<usize> {
+        let excess = self.len()?.saturating_sub(max_entries);
+        if excess == 0 {
+            return Ok(0);
+        }
+        let evicted = self.connection.execute(
+            "DELETE FROM entries WHERE path IN
+                (SELECT path FROM entries ORDER BY last_used, cache_time LIMIT ?1)",
+            [excess as i64],
+        )?;
+        tracing::debug!("Evicted {} least recently used cache entries", evicted);
+        Ok(evicted)
+    }
 
-        self.entries.retain(|path, _| valid_set.contains(path));
+    /// Remove every entry, returning how many there were
+    pub fn clear(&mut self) -> Result<usize> {
+        let removed = self.connection.execute("DELETE FROM entries", [])?;
+        self.connection.execute_batch("VACUUM")?;
+        Ok(removed)
     }
 
-    /// Compute SHA-256 hash of file contents (fast for small files)
+    fn len(&self) -> Result<usize> {
+        let count: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Compute a hash of file contents (fast for small files)
     /// For large files, only hash first and last 64KB + file size
-    fn compute_file_hash(file_path: &Path) -> Result<String> {
+    pub(crate) fn compute_file_hash(file_path: &Path) -> Result<String> {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
         use std::io::Read;
@@ -179,20 +392,53 @@ impl MetadataCache {
 
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
+        let pages = |pragma: &str| -> i64 {
+            self.connection
+                .pragma_query_value(None, pragma, |row| row.get(0))
+                .unwrap_or(0)
+        };
         CacheStats {
-            total_entries: self.entries.len(),
-            cache_size_bytes: serde_json::to_string(&self.entries)
-                .map(|s| s.len())
-                .unwrap_or(0),
+            total_entries: self.len().unwrap_or(0),
+            cache_size_bytes: (pages("page_count") * pages("page_size")) as usize,
+            hits: self.hits,
+            misses: self.misses,
+            invalidations: self.invalidations,
         }
     }
 }
 
 /// Cache statistics
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct CacheStats {
+    /// Number of files with a cache entry
     pub total_entries: usize,
+    /// Size of the cache database
     pub cache_size_bytes: usize,
+    /// Files this run served from the cache
+    pub hits: usize,
+    /// Files this run analyzed afresh (including invalidated and refreshed ones)
+    pub misses: usize,
+    /// Cache entries found stale because their file changed
+    pub invalidations: usize,
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
@@ -201,42 +447,103 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    /// Analysis proposing `name` for `path` with a good score
+    fn named(path: &Path, name: Option<&str>) -> FileAnalysis {
+        FileAnalysis {
+            proposed_name: name.map(str::to_string),
+            confidence: 6.5,
+            name_source: NameSource::Metadata,
+            ..crate::pipeline::unanalyzed(path, crate::FileCategory::Document)
+        }
+    }
+
     #[test]
     fn test_cache_roundtrip() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        let cache_path = temp_dir.path().join("cache.json");
+        let cache_path = temp_dir.path().join("cache").join("metadata.sqlite");
 
         // Create cache and insert entry
-        let mut cache = MetadataCache::new(cache_path.clone());
+        let mut cache = MetadataCache::open(&cache_path)?;
 
         let test_file = temp_dir.path().join("test.txt");
         fs::write(&test_file, "test content")?;
 
-        cache.insert(&test_file, Some("new_name.txt".to_string()), "Document")?;
-        cache.save()?;
+        let alternative = NameAlternative {
+            name: "Other_Name.txt".to_string(),
+            title: "Other Name".to_string(),
+            source: NameSource::FilenameAnalysis,
+            score: 3.5,
+        };
+        let analysis = FileAnalysis {
+            alternatives: vec![alternative.clone()],
+            ..named(&test_file, Some("new_name.txt"))
+        };
+        cache.insert(&test_file, &analysis, "Document")?;
+        drop(cache);
 
-        // Load cache and verify entry exists
-        let loaded_cache = MetadataCache::load(cache_path)?;
+        // Reopen cache and verify entry exists
+        let mut loaded_cache = MetadataCache::open(&cache_path)?;
         assert!(loaded_cache.has_valid_entry(&test_file)?);
 
         let entry = loaded_cache.get(&test_file).unwrap();
         assert_eq!(entry.proposed_name, Some("new_name.txt".to_string()));
         assert_eq!(entry.category, "Document");
+        assert_eq!(entry.confidence, Some(6.5));
+        assert_eq!(entry.name_source, Some(NameSource::Metadata));
+        assert_eq!(entry.alternatives, [alternative]);
 
         Ok(())
     }
 
     #[test]
-    fn test_cache_invalidation_on_modify() -> Result<()> {
+    fn test_open_replaces_old_json_cache() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let cache_path = temp_dir.path().join("cache.json");
+        fs::write(&cache_path, "{\"entries\": {}}".repeat(100))?;
 
-        let mut cache = MetadataCache::new(cache_path);
+        let cache = MetadataCache::open(&cache_path)?;
+        assert_eq!(cache.stats().total_entries, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_writers() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_path = temp_dir.path().join("metadata.sqlite");
+        let files: Vec<PathBuf> = (0..20)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("file{}.txt", i));
+                fs::write(&path, format!("content {}", i)).unwrap();
+                path
+            })
+            .collect();
+
+        // Two connections, as two nameback processes would have
+        std::thread::scope(|scope| {
+            for half in files.chunks(10) {
+                let cache_path = &cache_path;
+                scope.spawn(move || {
+                    let mut cache = MetadataCache::open(cache_path).unwrap();
+                    for file in half {
+                        cache.insert(file, &named(file, Some("name.txt")), "Document").unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(MetadataCache::open(&cache_path)?.stats().total_entries, 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_invalidation_on_modify() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut cache = MetadataCache::in_memory();
         let test_file = temp_dir.path().join("test.txt");
 
         // Write initial content and cache it
         fs::write(&test_file, "initial content")?;
-        cache.insert(&test_file, Some("cached_name.txt".to_string()), "Document")?;
+        cache.insert(&test_file, &named(&test_file, Some("cached_name.txt")), "Document")?;
 
         assert!(cache.has_valid_entry(&test_file)?);
 
@@ -248,34 +555,86 @@ mod tests {
 
         // Cache should be invalid now
         assert!(!cache.has_valid_entry(&test_file)?);
+        assert_eq!(cache.stats().invalidations, 1);
+
+        // A file that was never cached is a miss, not an invalidation
+        assert!(!cache.has_valid_entry(&temp_dir.path().join("other.txt"))?);
+        cache.record_lookup(false);
+        cache.record_lookup(true);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.invalidations), (1, 1, 1));
 
         Ok(())
     }
 
     #[test]
-    fn test_cleanup_stale_entries() -> Result<()> {
+    fn test_is_unchanged() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        let cache_path = temp_dir.path().join("cache.json");
+        let mut cache = MetadataCache::in_memory();
 
-        let mut cache = MetadataCache::new(cache_path);
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "content")?;
+        assert!(!cache.is_unchanged(&test_file));
 
-        // Create two files
-        let file1 = temp_dir.path().join("file1.txt");
-        let file2 = temp_dir.path().join("file2.txt");
-        fs::write(&file1, "content1")?;
-        fs::write(&file2, "content2")?;
+        cache.insert(&test_file, &named(&test_file, None), "Document")?;
+        assert!(cache.is_unchanged(&test_file));
+
+        fs::write(&test_file, "longer content")?;
+        assert!(!cache.is_unchanged(&test_file));
+
+        Ok(())
+    }
 
-        cache.insert(&file1, Some("name1.txt".to_string()), "Document")?;
-        cache.insert(&file2, Some("name2.txt".to_string()), "Document")?;
+    #[test]
+    fn test_remove_missing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let other_dir = TempDir::new()?;
+        let mut cache = MetadataCache::in_memory();
 
-        assert_eq!(cache.entries.len(), 2);
+        // Create two files here and one elsewhere
+        let file1 = temp_dir.path().join("file1.txt");
+        let file2 = temp_dir.path().join("file2.txt");
+        let elsewhere = other_dir.path().join("file3.txt");
+        for file in [&file1, &file2, &elsewhere] {
+            fs::write(file, "content")?;
+            cache.insert(file, &named(file, Some("name.txt")), "Document")?;
+        }
+        assert_eq!(cache.stats().total_entries, 3);
 
-        // Cleanup with only file1 valid
-        cache.cleanup_stale_entries(&[file1.clone()]);
-        assert_eq!(cache.entries.len(), 1);
+        // Only entries under the scanned directory whose files are gone are dropped
+        fs::remove_file(&file2)?;
+        fs::remove_file(&elsewhere)?;
+        assert_eq!(cache.remove_missing(temp_dir.path())?, 1);
         assert!(cache.get(&file1).is_some());
         assert!(cache.get(&file2).is_none());
+        assert!(cache.get(&elsewhere).is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut cache = MetadataCache::in_memory();
+        let files: Vec<PathBuf> = (0..3).map(|i| temp_dir.path().join(format!("file{}.txt", i))).collect();
+        for (age, file) in files.iter().enumerate() {
+            fs::write(file, "content")?;
+            cache.insert(file, &named(file, Some("name.txt")), "Document")?;
+            cache.connection.execute(
+                "UPDATE entries SET last_used = ?1 WHERE path = ?2",
+                params![100 - age as i64, path_key(file)],
+            )?;
+        }
+
+        // file0 was used last; reading file2 makes it the most recent
+        assert!(cache.get(&files[2]).is_some());
+        assert_eq!(cache.evict_to(2)?, 1);
+        assert!(cache.lookup(&files[0])?.is_some());
+        assert!(cache.lookup(&files[1])?.is_none());
+        assert!(cache.lookup(&files[2])?.is_some());
 
+        assert_eq!(cache.clear()?, 2);
+        assert_eq!(cache.stats().total_entries, 0);
         Ok(())
     }
 }
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::MetadataBackend;
+
+/// Whether the title nameback derived is written into the file's own metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataWriteMode {
+    /// Leave metadata alone
+    #[default]
+    Off,
+    /// Write the title and rename the file
+    Also,
+    /// Write the title and keep the current filename
+    Only,
+}
+
+impl fmt::Display for MetadataWriteMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataWriteMode::Off => write!(f, "off"),
+            MetadataWriteMode::Also => write!(f, "also"),
+            MetadataWriteMode::Only => write!(f, "only"),
+        }
+    }
+}
+
+impl FromStr for MetadataWriteMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(MetadataWriteMode::Off),
+            "also" => Ok(MetadataWriteMode::Also),
+            "only" => Ok(MetadataWriteMode::Only),
+            _ => anyhow::bail!("unknown metadata write mode '{}' (expected off, also or only)", s),
+        }
+    }
+}
+
+/// Formats exiftool writes a title into, and the tags it is written to
+const EXIFTOOL_TAGS: &[(&[&str], &[&str])] = &[
+    (
+        &["jpg", "jpeg", "tif", "tiff", "heic", "heif", "webp", "dng"],
+        &["XMP-dc:Title", "EXIF:ImageDescription"],
+    ),
+    (&["png", "gif"], &["XMP-dc:Title"]),
+    (&["pdf"], &["PDF:Title", "XMP-dc:Title"]),
+    (&["mp4", "mov", "m4v", "m4a"], &["QuickTime:Title"]),
+];
+
+/// How a title gets into a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Writer {
+    /// `exiftool -TAG=title` for these tags
+    Exiftool(&'static [&'static str]),
+    /// ID3 title frame, written with the id3 crate
+    Id3,
+}
+
+/// The writer for `path`'s format (None if its title can't be written)
+/// MP3 tags are written natively; everything else needs exiftool unless the backend is native
+fn writer(path: &Path, backend: MetadataBackend) -> Option<Writer> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if extension == "mp3" {
+        return Some(Writer::Id3);
+    }
+    let exiftool = match backend {
+        MetadataBackend::Auto => crate::extractor::exiftool_installed(),
+        MetadataBackend::Exiftool => true,
+        MetadataBackend::Native => false,
+    };
+    EXIFTOOL_TAGS
+        .iter()
+        .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+        .filter(|_| exiftool)
+        .map(|(_, tags)| Writer::Exiftool(tags))
+}
+
+/// Check that a title can be written into `path` with `backend`, e.g. for a dry run
+pub(crate) fn check(path: &Path, backend: MetadataBackend) -> Result<()> {
+    writer_for(path, backend).map(|_| ())
+}
+
+/// Write `title` into the metadata of the file at `path`, replacing the file in place
+pub(crate) fn write_title(path: &Path, title: &str, backend: MetadataBackend) -> Result<()> {
+    match writer_for(path, backend)? {
+        Writer::Exiftool(tags) => write_with_exiftool(path, title, tags),
+        Writer::Id3 => write_id3(path, title),
+    }
+}
+
+fn writer_for(path: &Path, backend: MetadataBackend) -> Result<Writer> {
+    writer(path, backend).with_context(|| {
+        format!(
+            "Can't write metadata to {} files{}",
+            path.extension().map_or("these".into(), |e| format!(".{}", e.to_string_lossy())),
+            if backend == MetadataBackend::Native { " without exiftool" } else { "" }
+        )
+    })
+}
+
+fn write_with_exiftool(path: &Path, title: &str, tags: &[&str]) -> Result<()> {
+    // A leading `-` would be read as an option
+    let path = if path.to_string_lossy().starts_with('-') {
+        Path::new(".").join(path)
+    } else {
+        PathBuf::from(path)
+    };
+    let output = crate::deps_check::create_command("exiftool")
+        .arg("-overwrite_original")
+        .args(tags.iter().map(|tag| format!("-{}={}", tag, title)))
+        .arg(&path)
+        .output()
+        .context("Failed to execute `exiftool` command. Is exiftool installed?")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "exiftool could not write metadata: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn write_id3(path: &Path, title: &str) -> Result<()> {
+    use id3::{ErrorKind, TagLike};
+
+    let mut tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, ErrorKind::NoTag) => id3::Tag::new(),
+        Err(e) => return Err(e).context("Failed to read ID3 tag"),
+    };
+    tag.set_title(title);
+    let version = tag.version();
+    tag.write_to_path(path, version).context("Failed to write ID3 tag")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_writer_per_format() {
+        let native = MetadataBackend::Native;
+        let exiftool = MetadataBackend::Exiftool;
+        assert_eq!(writer(Path::new("song.MP3"), native), Some(Writer::Id3));
+        assert_eq!(writer(Path::new("a.pdf"), native), None);
+        assert_eq!(
+            writer(Path::new("a.pdf"), exiftool),
+            Some(Writer::Exiftool(&["PDF:Title", "XMP-dc:Title"]))
+        );
+        assert!(check(Path::new("IMG_1.JPG"), exiftool).is_ok());
+        assert!(check(Path::new("notes.txt"), exiftool).is_err());
+        assert!(check(Path::new("README"), exiftool).is_err());
+
+        let err = write_title(Path::new("notes.txt"), "Notes", exiftool).unwrap_err();
+        assert!(err.to_string().contains(".txt"));
+    }
+
+    #[test]
+    fn test_write_id3_title() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("track01.mp3");
+        std::fs::write(&path, [0xFF, 0xFB, 0x90, 0x00]).unwrap();
+
+        write_title(&path, "Morning Walk", MetadataBackend::Native).unwrap();
+        let raw = crate::native_metadata::read(&path).unwrap();
+        assert_eq!(raw.title.as_deref(), Some("Morning Walk"));
+
+        // An existing tag keeps its other frames
+        let mut tag = id3::Tag::read_from_path(&path).unwrap();
+        id3::TagLike::set_artist(&mut tag, "Field Recordings");
+        tag.write_to_path(&path, tag.version()).unwrap();
+        write_title(&path, "Evening Walk", MetadataBackend::Native).unwrap();
+        let tag = id3::Tag::read_from_path(&path).unwrap();
+        assert_eq!(id3::TagLike::title(&tag), Some("Evening Walk"));
+        assert_eq!(id3::TagLike::artist(&tag), Some("Field Recordings"));
+    }
+
+    #[test]
+    fn test_mode_parsing() {
+        assert_eq!("Only".parse::<MetadataWriteMode>().unwrap(), MetadataWriteMode::Only);
+        assert_eq!(MetadataWriteMode::Also.to_string(), "also");
+        assert!("sometimes".parse::<MetadataWriteMode>().is_err());
+    }
+}
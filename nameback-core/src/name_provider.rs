@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use crate::extractor::FileMetadata;
+use crate::scorer::NameCandidate;
+
+/// Extra source of candidate names, registered with `RenameEngine::with_name_provider`
+///
+/// Candidates from every provider are scored together with those from metadata, content,
+/// the filename and the directory, and the best one wins. Build them with
+/// `NameCandidate::new(name, NameSource::Provider)` to have them scored like any other name,
+/// or set `score` directly to rank them yourself (5.0 or more is a good name, below 2.0 is
+/// never used; NaN and infinite scores drop the candidate).
+///
+/// Providers are called from several analysis threads at once, and only for files that
+/// aren't answered from the cache.
+///
+/// ```no_run
+/// use nameback_core::{FileMetadata, NameCandidate, NameProvider, NameSource, RenameEngine};
+/// use std::path::Path;
+///
+/// /// Names files like `DOC-1234.pdf` after the document register's title
+/// struct DocumentRegister;
+///
+/// impl NameProvider for DocumentRegister {
+///     fn candidates(&self, path: &Path, _metadata: &FileMetadata) -> Vec<NameCandidate> {
+///         let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+///         match stem {
+///             "DOC-1234" => vec![NameCandidate::new("Supplier Agreement 2024".to_string(), NameSource::Provider)],
+///             _ => Vec::new(),
+///         }
+///     }
+/// }
+///
+/// let engine = RenameEngine::with_defaults().with_name_provider(DocumentRegister);
+/// ```
+pub trait NameProvider: Send + Sync {
+    /// Candidate names for the file at `path`, given what nameback read from it
+    /// (`metadata.category` tells the detected file type)
+    fn candidates(&self, path: &Path, metadata: &FileMetadata) -> Vec<NameCandidate>;
+}
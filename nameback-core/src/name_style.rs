@@ -0,0 +1,255 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How the words of a proposed name are cased and joined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameCase {
+    /// Words as found in the metadata, e.g. `Sunset_Beach_iPhone`
+    #[default]
+    Keep,
+    /// `sunset_beach_iphone`
+    #[serde(alias = "snake_case")]
+    Snake,
+    /// `sunset-beach-iphone`
+    #[serde(alias = "kebab-case")]
+    Kebab,
+    /// `Sunset_Beach_Iphone`
+    #[serde(alias = "Title_Case")]
+    Title,
+    /// `sunsetBeachIphone`
+    #[serde(alias = "camelCase")]
+    Camel,
+}
+
+impl fmt::Display for NameCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameCase::Keep => write!(f, "keep"),
+            NameCase::Snake => write!(f, "snake"),
+            NameCase::Kebab => write!(f, "kebab"),
+            NameCase::Title => write!(f, "title"),
+            NameCase::Camel => write!(f, "camel"),
+        }
+    }
+}
+
+impl FromStr for NameCase {
+    type Err = anyhow::Error;
+
+    /// Also takes the styles spelled in their own case, e.g. `kebab-case` or `camelCase`
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase().replace(['-', '_'], "");
+        match name.strip_suffix("case").unwrap_or(&name) {
+            "keep" => Ok(NameCase::Keep),
+            "snake" => Ok(NameCase::Snake),
+            "kebab" => Ok(NameCase::Kebab),
+            "title" => Ok(NameCase::Title),
+            "camel" => Ok(NameCase::Camel),
+            _ => anyhow::bail!(
+                "unknown name case '{}' (expected keep, snake, kebab, title or camel)",
+                s
+            ),
+        }
+    }
+}
+
+/// What goes between the words of a proposed name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordSeparator {
+    /// `_`
+    #[serde(alias = "_")]
+    Underscore,
+    /// `-`
+    #[serde(alias = "-")]
+    Hyphen,
+    /// ` `
+    #[serde(alias = " ")]
+    Space,
+}
+
+impl WordSeparator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WordSeparator::Underscore => "_",
+            WordSeparator::Hyphen => "-",
+            WordSeparator::Space => " ",
+        }
+    }
+}
+
+impl fmt::Display for WordSeparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WordSeparator::Underscore => write!(f, "underscore"),
+            WordSeparator::Hyphen => write!(f, "hyphen"),
+            WordSeparator::Space => write!(f, "space"),
+        }
+    }
+}
+
+impl FromStr for WordSeparator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // A lone space is the separator itself, so it isn't trimmed away
+        match s.to_lowercase().as_str() {
+            "_" | "underscore" => Ok(WordSeparator::Underscore),
+            "-" | "hyphen" | "dash" => Ok(WordSeparator::Hyphen),
+            " " | "space" => Ok(WordSeparator::Space),
+            _ => anyhow::bail!(
+                "unknown word separator '{}' (expected underscore, hyphen or space)",
+                s
+            ),
+        }
+    }
+}
+
+/// `RenameConfig::name_case`, `word_separator` and `lowercase_extensions` together, as the
+/// generator applies them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameStyle {
+    pub case: NameCase,
+    /// None = the case's own (`-` for kebab, `_` otherwise)
+    pub separator: Option<WordSeparator>,
+    /// Lower-case extensions (`.JPG` → `.jpg`) instead of keeping them as they are
+    pub lowercase_extension: bool,
+}
+
+impl NameStyle {
+    /// What joins words, and the counters and details added to tell names apart
+    /// camelCase joins its words without one, but still uses it before counters
+    pub(crate) fn separator(&self) -> &'static str {
+        match (self.separator, self.case) {
+            (Some(separator), _) => separator.as_str(),
+            (None, NameCase::Kebab) => "-",
+            (None, _) => "_",
+        }
+    }
+
+    /// Restyles a generated base name, whose words are joined by `_`
+    /// Other characters are left alone, so dates like `2023-10-15` survive every style
+    pub(crate) fn apply(&self, name: &str) -> String {
+        let words = name.split('_').filter(|word| !word.is_empty());
+        match self.case {
+            NameCase::Keep => words.collect::<Vec<_>>().join(self.separator()),
+            NameCase::Snake | NameCase::Kebab => words
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+                .join(self.separator()),
+            NameCase::Title => words.map(capitalize).collect::<Vec<_>>().join(self.separator()),
+            NameCase::Camel => words
+                .enumerate()
+                .map(|(index, word)| match index {
+                    0 => word.to_lowercase(),
+                    // Only capitals show where words start, so Arabic or Hebrew words,
+                    // which have none, keep the separator
+                    _ if is_caseless(word) => format!("{}{}", self.separator(), word),
+                    _ => capitalize(word),
+                })
+                .collect(),
+        }
+    }
+
+    /// The extension as it goes into a new name
+    pub(crate) fn extension(&self, extension: &str) -> String {
+        match self.lowercase_extension {
+            true => extension.to_lowercase(),
+            false => extension.to_string(),
+        }
+    }
+
+    /// Tells cache entries made with another style apart; empty for the default style, so
+    /// entries from before styles existed still match it
+    pub(crate) fn cache_key(&self) -> String {
+        let key = match (self.case, self.separator) {
+            (NameCase::Keep, None) => String::new(),
+            (case, None) => case.to_string(),
+            (case, Some(separator)) => format!("{}/{}", case, separator),
+        };
+        match self.lowercase_extension {
+            true => format!("{};lowercase-extensions", key),
+            false => key,
+        }
+    }
+}
+
+/// Whether `word` starts with a letter that has no capital, in a script that separates words
+/// with spaces (Chinese and Japanese words run together anyway)
+fn is_caseless(word: &str) -> bool {
+    word.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() && !c.is_uppercase() && !c.is_lowercase())
+        && crate::script::detect(word).uses_spaces()
+}
+
+/// `word` with its first letter upper-case and the rest lower-case
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(case: NameCase, separator: Option<WordSeparator>) -> NameStyle {
+        NameStyle { case, separator, ..NameStyle::default() }
+    }
+
+    #[test]
+    fn test_name_styles() {
+        let name = "Sunset_Beach_iPhone_Paris_2023-10-15";
+        assert_eq!(NameStyle::default().apply(name), name);
+        assert_eq!(style(NameCase::Snake, None).apply(name), "sunset_beach_iphone_paris_2023-10-15");
+        assert_eq!(style(NameCase::Kebab, None).apply(name), "sunset-beach-iphone-paris-2023-10-15");
+        assert_eq!(style(NameCase::Title, None).apply(name), "Sunset_Beach_Iphone_Paris_2023-10-15");
+        assert_eq!(style(NameCase::Camel, None).apply(name), "sunsetBeachIphoneParis2023-10-15");
+        assert_eq!(
+            style(NameCase::Keep, Some(WordSeparator::Space)).apply(name),
+            "Sunset Beach iPhone Paris 2023-10-15"
+        );
+        assert_eq!(style(NameCase::Snake, Some(WordSeparator::Hyphen)).apply("A__B_"), "a-b");
+
+        // Words without capitals keep their separator in camelCase, except in Chinese and Japanese
+        assert_eq!(style(NameCase::Camel, None).apply("Annual_التقرير_السنوي_2023"), "annual_التقرير_السنوي2023");
+        assert_eq!(style(NameCase::Camel, None).apply("דוח_רבעוני"), "דוח_רבעוני");
+        assert_eq!(style(NameCase::Camel, None).apply("Tokyo_東京_夜景"), "tokyo東京夜景");
+        assert_eq!(style(NameCase::Title, None).apply("التقرير_السنوي"), "التقرير_السنوي");
+
+        assert_eq!(style(NameCase::Camel, None).separator(), "_");
+        assert_eq!(style(NameCase::Kebab, None).separator(), "-");
+        assert_eq!(style(NameCase::Kebab, Some(WordSeparator::Space)).separator(), " ");
+    }
+
+    #[test]
+    fn test_parse_name_styles() {
+        for (text, case) in [
+            ("snake_case", NameCase::Snake),
+            ("kebab-case", NameCase::Kebab),
+            ("Title_Case", NameCase::Title),
+            ("camelCase", NameCase::Camel),
+            ("keep", NameCase::Keep),
+        ] {
+            assert_eq!(text.parse::<NameCase>().unwrap(), case);
+        }
+        assert!("shout".parse::<NameCase>().is_err());
+
+        assert_eq!("-".parse::<WordSeparator>().unwrap(), WordSeparator::Hyphen);
+        assert_eq!(" ".parse::<WordSeparator>().unwrap(), WordSeparator::Space);
+        assert_eq!("Underscore".parse::<WordSeparator>().unwrap(), WordSeparator::Underscore);
+        assert!(".".parse::<WordSeparator>().is_err());
+
+        assert_eq!(NameStyle::default().cache_key(), "");
+        assert_eq!(style(NameCase::Snake, Some(WordSeparator::Hyphen)).cache_key(), "snake/hyphen");
+        let lowercase = NameStyle { lowercase_extension: true, ..NameStyle::default() };
+        assert_eq!(lowercase.cache_key(), ";lowercase-extensions");
+        assert_eq!((lowercase.extension("JPG"), NameStyle::default().extension("JPG")), ("jpg".into(), "JPG".into()));
+    }
+}
@@ -0,0 +1,406 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::debug;
+
+use crate::extractor::RawMetadata;
+
+/// MP4 `moov` boxes are read whole; anything bigger than this is not a real header
+const MAX_MOOV_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Seconds between the MP4 epoch (1904-01-01) and the Unix epoch
+const MP4_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+lazy_static::lazy_static! {
+    /// Latitude and longitude at the start of an ISO 6709 string
+    static ref ISO6709: Regex =
+        Regex::new(r"^\s*([+-]\d+(?:\.\d+)?)([+-]\d+(?:\.\d+)?)").expect("valid ISO 6709 regex");
+}
+
+/// Reads embedded metadata without exiftool
+/// Covers EXIF in JPEG/HEIC/PNG/TIFF/WebP, MP4/MOV atoms and ID3 tags; other formats
+/// (and files without metadata) get empty fields so content extraction can still name them
+pub(crate) fn read(path: &Path) -> Result<RawMetadata> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "jpg" | "jpeg" | "heic" | "heif" | "avif" | "png" | "tif" | "tiff" | "webp" | "dng" => {
+            read_exif(path)
+        }
+        "mp4" | "m4v" | "mov" | "m4a" | "3gp" => read_mp4(path),
+        "mp3" => read_id3(path),
+        _ => {
+            debug!("No built-in metadata reader for {}", path.display());
+            Ok(RawMetadata::default())
+        }
+    }
+}
+
+/// EXIF attributes of an image, formatted as exiftool prints them
+fn read_exif(path: &Path) -> Result<RawMetadata> {
+    use exif::{In, Tag, Value};
+
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let exif = match exif::Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(exif::Error::Io(e)) => return Err(e).context("Failed to read EXIF data"),
+        Err(e) => {
+            debug!("No EXIF data in {}: {}", path.display(), e);
+            return Ok(RawMetadata::default());
+        }
+    };
+
+    let ascii = |tag: Tag| -> Option<&[u8]> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => values.first().map(Vec::as_slice),
+            _ => None,
+        }
+    };
+    let text = |tag: Tag| {
+        let value = String::from_utf8_lossy(ascii(tag)?).trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    let date = |tag: Tag| {
+        let d = exif::DateTime::from_ascii(ascii(tag)?).ok()?;
+        Some(format!(
+            "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+            d.year, d.month, d.day, d.hour, d.minute, d.second
+        ))
+    };
+    let number = |tag: Tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0);
+    let size = number(Tag::PixelXDimension)
+        .zip(number(Tag::PixelYDimension))
+        .or_else(|| number(Tag::ImageWidth).zip(number(Tag::ImageLength)));
+    // Degrees, minutes and seconds as decimal degrees
+    let coordinate = |tag: Tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(dms) if dms.len() >= 3 => Some(format!(
+            "{:.6}",
+            dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0
+        )),
+        _ => None,
+    };
+
+    Ok(RawMetadata {
+        artist: text(Tag::Artist),
+        date_time_original: date(Tag::DateTimeOriginal),
+        sub_sec_time_original: text(Tag::SubSecTimeOriginal),
+        image_width: size.map(|(width, _)| width.to_string()),
+        image_height: size.map(|(_, height)| height.to_string()),
+        create_date: date(Tag::DateTimeDigitized),
+        description: text(Tag::ImageDescription),
+        gps_latitude: coordinate(Tag::GPSLatitude),
+        gps_latitude_ref: text(Tag::GPSLatitudeRef),
+        gps_longitude: coordinate(Tag::GPSLongitude),
+        gps_longitude_ref: text(Tag::GPSLongitudeRef),
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        lens_model: text(Tag::LensModel),
+        ..RawMetadata::default()
+    })
+}
+
+/// Title, artist and album from an MP3's ID3 tag
+fn read_id3(path: &Path) -> Result<RawMetadata> {
+    use id3::{ErrorKind, TagLike};
+
+    let tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, ErrorKind::Io(_)) => {
+            return Err(e).context("Failed to read ID3 tag");
+        }
+        Err(e) => {
+            debug!("No ID3 tag in {}: {}", path.display(), e);
+            return Ok(RawMetadata::default());
+        }
+    };
+
+    Ok(RawMetadata {
+        title: tag.title().map(str::to_string),
+        artist: tag.artist().map(str::to_string),
+        album: tag.album().map(str::to_string),
+        ..RawMetadata::default()
+    })
+}
+
+/// Metadata from the `moov` box of an MP4/MOV file
+/// Top-level boxes are skipped by seeking, so the media data is never read
+fn read_mp4(path: &Path) -> Result<RawMetadata> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let length = file.metadata()?.len();
+
+    let mut offset = 0;
+    while offset + 8 <= length {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let (size, header_len) = match u32::from_be_bytes(header[0..4].try_into()?) {
+            0 => (length - offset, 8),
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                (u64::from_be_bytes(large), 16)
+            }
+            size => (u64::from(size), 8),
+        };
+        if size < header_len {
+            break;
+        }
+
+        if &header[4..8] == b"moov" {
+            let payload_len = size - header_len;
+            if payload_len > MAX_MOOV_SIZE {
+                anyhow::bail!("MP4 header is implausibly large ({} bytes)", payload_len);
+            }
+            let mut moov = vec![0u8; payload_len as usize];
+            file.read_exact(&mut moov).context("MP4 header is truncated")?;
+            return Ok(parse_moov(&moov));
+        }
+        offset += size;
+    }
+
+    debug!("No moov box in {}", path.display());
+    Ok(RawMetadata::default())
+}
+
+/// Creation time from `mvhd`, iTunes-style tags from `udta`, and QuickTime `keys` metadata
+fn parse_moov(moov: &[u8]) -> RawMetadata {
+    let mut raw = RawMetadata::default();
+
+    if let Some(mvhd) = child(moov, b"mvhd") {
+        let seconds = match mvhd.first() {
+            Some(1) => mvhd.get(4..12).map(|b| u64::from_be_bytes(b.try_into().unwrap())),
+            _ => mvhd.get(4..8).map(|b| u64::from(u32::from_be_bytes(b.try_into().unwrap()))),
+        };
+        // Zero means the muxer didn't set it
+        raw.create_date = seconds
+            .filter(|&s| s > 0)
+            .and_then(|s| chrono::DateTime::from_timestamp(s as i64 - MP4_EPOCH_OFFSET, 0))
+            .map(|t| t.format("%Y:%m:%d %H:%M:%S").to_string());
+        // Timescale (units per second) and duration follow the modification time
+        let (timescale, duration) = match mvhd.first() {
+            Some(1) => (mvhd.get(20..24), mvhd.get(24..32).map(|b| u64::from_be_bytes(b.try_into().unwrap()))),
+            _ => (mvhd.get(12..16), mvhd.get(16..20).map(|b| u64::from(u32::from_be_bytes(b.try_into().unwrap())))),
+        };
+        let timescale = timescale.map(|b| u64::from(u32::from_be_bytes(b.try_into().unwrap())));
+        if let (Some(timescale @ 1..), Some(duration)) = (timescale, duration) {
+            let seconds = duration / timescale;
+            raw.duration = Some(format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60));
+        }
+    }
+
+    // iTunes-style tags: moov/udta/meta/ilst/©nam etc.
+    if let Some(udta) = child(moov, b"udta") {
+        if let Some(ilst) = child(udta, b"meta").and_then(meta_children).and_then(|m| child(m, b"ilst")) {
+            for (kind, item) in boxes(ilst) {
+                let value = child(item, b"data").and_then(data_text);
+                match &kind {
+                    b"\xa9nam" => raw.title = raw.title.or(value),
+                    b"\xa9ART" => raw.artist = raw.artist.or(value),
+                    b"\xa9alb" => raw.album = raw.album.or(value),
+                    _ => {}
+                }
+            }
+        }
+        // Location as an ISO 6709 string after a 2-byte length and 2-byte language
+        if let Some(xyz) = child(udta, b"\xa9xyz").and_then(|b| b.get(4..)) {
+            set_location(&mut raw, &String::from_utf8_lossy(xyz));
+        }
+    }
+
+    // QuickTime metadata (iPhone and most cameras): moov/meta with keys naming each ilst item
+    if let Some(meta) = child(moov, b"meta").and_then(meta_children) {
+        let keys = child(meta, b"keys").map(key_names).unwrap_or_default();
+        for (kind, item) in child(meta, b"ilst").map(boxes).unwrap_or_default() {
+            let index = u32::from_be_bytes(kind) as usize;
+            let Some(key) = index.checked_sub(1).and_then(|i| keys.get(i)) else {
+                continue;
+            };
+            let Some(value) = child(item, b"data").and_then(data_text) else {
+                continue;
+            };
+            match key.as_str() {
+                "com.apple.quicktime.title" => raw.title = Some(value),
+                "com.apple.quicktime.artist" => raw.artist = Some(value),
+                "com.apple.quicktime.description" => raw.description = Some(value),
+                "com.apple.quicktime.make" => raw.make = Some(value),
+                "com.apple.quicktime.model" => raw.model = Some(value),
+                "com.apple.quicktime.creationdate" => {
+                    raw.creation_date = iso8601_to_exif(&value).or(raw.creation_date)
+                }
+                "com.apple.quicktime.location.ISO6709" => set_location(&mut raw, &value),
+                _ => {}
+            }
+        }
+    }
+
+    raw
+}
+
+/// Child boxes of a box payload, as (type, payload); stops at the first malformed box
+fn boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (size, header_len) = match u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) {
+            0 => (data.len() - pos, 8),
+            1 => match data.get(pos + 8..pos + 16) {
+                Some(large) => (u64::from_be_bytes(large.try_into().unwrap()) as usize, 16),
+                None => break,
+            },
+            size => (size as usize, 8),
+        };
+        if size < header_len || pos + size > data.len() {
+            break;
+        }
+        found.push((kind, &data[pos + header_len..pos + size]));
+        pos += size;
+    }
+    found
+}
+
+/// Payload of the first child box of the given type
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data)
+        .into_iter()
+        .find(|(k, _)| k == kind)
+        .map(|(_, payload)| payload)
+}
+
+/// Children of a `meta` box, which is a full box (version and flags first) in MP4
+/// but a plain container in QuickTime files
+fn meta_children(meta: &[u8]) -> Option<&[u8]> {
+    if meta.get(4..8) == Some(b"hdlr".as_slice()) {
+        Some(meta)
+    } else {
+        meta.get(4..)
+    }
+}
+
+/// UTF-8 text of a `data` box (after its 4-byte type and 4-byte locale)
+fn data_text(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data.get(8..)?).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Key names in a QuickTime `keys` box, in order (ilst items refer to them from 1)
+fn key_names(keys: &[u8]) -> Vec<String> {
+    // Version and flags, then the entry count; entries look like boxes (size, namespace, name)
+    keys.get(8..)
+        .map(boxes)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, name)| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+/// Sets GPS fields from an ISO 6709 string such as `+47.6062-122.3321+012.000/`
+fn set_location(raw: &mut RawMetadata, iso6709: &str) {
+    let Some(caps) = ISO6709.captures(iso6709) else {
+        return;
+    };
+    let (Ok(latitude), Ok(longitude)) = (caps[1].parse::<f64>(), caps[2].parse::<f64>()) else {
+        return;
+    };
+    raw.gps_latitude = Some(format!("{:.6}", latitude.abs()));
+    raw.gps_latitude_ref = Some(if latitude < 0.0 { "S" } else { "N" }.to_string());
+    raw.gps_longitude = Some(format!("{:.6}", longitude.abs()));
+    raw.gps_longitude_ref = Some(if longitude < 0.0 { "W" } else { "E" }.to_string());
+}
+
+/// `2024-03-15T10:30:00+0100` as exiftool's `2024:03:15 10:30:00` (local time kept)
+fn iso8601_to_exif(value: &str) -> Option<String> {
+    let local = chrono::NaiveDateTime::parse_from_str(value.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()?;
+    Some(local.format("%Y:%m:%d %H:%M:%S").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A box with the given type and payload
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// An ilst item holding UTF-8 text
+    fn text_item(kind: &[u8; 4], text: &str) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(text.as_bytes());
+        mp4_box(kind, &mp4_box(b"data", &data))
+    }
+
+    #[test]
+    fn test_read_mp4_atoms() -> Result<()> {
+        // mvhd version 0 with 2024-03-15 10:30:00 UTC as its creation time, 1:02:03 long
+        let mut mvhd = vec![0, 0, 0, 0];
+        mvhd.extend_from_slice(&((1_710_498_600 + MP4_EPOCH_OFFSET) as u32).to_be_bytes());
+        mvhd.extend_from_slice(&[0; 4]);
+        mvhd.extend_from_slice(&600u32.to_be_bytes());
+        mvhd.extend_from_slice(&(600u32 * 3723).to_be_bytes());
+
+        let ilst = [text_item(b"\xa9nam", "Beach Day"), text_item(b"\xa9ART", "Alice")].concat();
+        let meta = [vec![0, 0, 0, 0], mp4_box(b"ilst", &ilst)].concat();
+        let xyz = [vec![0, 26, 0x15, 0xc7], b"+47.6062-122.3321+012.000/".to_vec()].concat();
+        let udta = [mp4_box(b"meta", &meta), mp4_box(b"\xa9xyz", &xyz)].concat();
+        let moov = [mp4_box(b"mvhd", &mvhd), mp4_box(b"udta", &udta)].concat();
+        let file = [
+            mp4_box(b"ftyp", b"isom\0\0\0\0"),
+            mp4_box(b"mdat", &[0; 64]),
+            mp4_box(b"moov", &moov),
+        ]
+        .concat();
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("clip.mp4");
+        fs::write(&path, file)?;
+
+        let raw = read(&path)?;
+        assert_eq!(raw.title.as_deref(), Some("Beach Day"));
+        assert_eq!(raw.artist.as_deref(), Some("Alice"));
+        assert_eq!(raw.create_date.as_deref(), Some("2024:03:15 10:30:00"));
+        assert_eq!(raw.duration.as_deref(), Some("1:02:03"));
+        assert_eq!(raw.gps_latitude.as_deref(), Some("47.606200"));
+        assert_eq!(raw.gps_longitude_ref.as_deref(), Some("W"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tiff_exif_and_unsupported() -> Result<()> {
+        // Little-endian TIFF with one IFD holding Make = "Canon"
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x010fu16.to_le_bytes()); // Make
+        tiff.extend_from_slice(&2u16.to_le_bytes()); // ASCII
+        tiff.extend_from_slice(&6u32.to_le_bytes());
+        tiff.extend_from_slice(&26u32.to_le_bytes()); // value offset
+        tiff.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+        tiff.extend_from_slice(b"Canon\0");
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("scan.tif");
+        fs::write(&path, tiff)?;
+        assert_eq!(read(&path)?.make.as_deref(), Some("Canon"));
+
+        // No EXIF, or a format without a built-in reader: empty, not an error
+        let jpeg = temp_dir.path().join("blank.jpg");
+        fs::write(&jpeg, [0xff, 0xd8, 0xff, 0xd9])?;
+        assert!(read(&jpeg)?.make.is_none());
+        let pdf = temp_dir.path().join("report.pdf");
+        fs::write(&pdf, "%PDF-1.4")?;
+        assert!(read(&pdf)?.title.is_none());
+        Ok(())
+    }
+}
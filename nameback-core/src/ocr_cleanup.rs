@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Words tesseract is less sure of than this (0-100) are dropped as noise
+const MIN_WORD_CONFIDENCE: f32 = 30.0;
+
+/// Characters tesseract mixes up with digits, and the letters they stand for
+/// (upper-case, lower-case) inside words
+const CONFUSABLE_DIGITS: [(char, char, char); 3] = [('0', 'O', 'o'), ('1', 'I', 'l'), ('5', 'S', 's')];
+
+/// Text read by one tesseract run with the noise taken out
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OcrText {
+    /// The words kept, a line of the image per line
+    pub text: String,
+    /// Mean confidence of the words kept (0-100; 0 when none were)
+    pub confidence: f32,
+}
+
+/// Reads the text of the image at `path` in `lang`, cleaned (see `from_tsv`)
+/// Tesseract runs under the analysis's tool timeout (see `tool_command::run_in_process`)
+pub(crate) fn recognize(path: &str, lang: &str) -> Result<OcrText> {
+    let (path, lang) = (path.to_string(), lang.to_string());
+    crate::tool_command::run_in_process("tesseract", move || {
+        let mut tesseract = tesseract::Tesseract::new(None, Some(lang.as_str()))
+            .context("Failed to initialize Tesseract")?
+            .set_image(&path)
+            .context("Failed to set image")?;
+        let tsv = tesseract.get_tsv_text(0).context("Failed to extract text")?;
+        Ok(from_tsv(&tsv))
+    })
+}
+
+/// Cleans tesseract TSV output: words under `MIN_WORD_CONFIDENCE` are dropped, digits read
+/// in place of letters (and letters in place of digits) are put right, and tokens that
+/// aren't words (stray marks, runs of consonants, repeated letters) are dropped unless the
+/// dictionary knows them
+pub(crate) fn from_tsv(tsv: &str) -> OcrText {
+    let mut lines: Vec<Vec<String>> = Vec::new();
+    let mut last_line = None;
+    let mut confidences = Vec::new();
+
+    // level, page, block, paragraph, line, word, left, top, width, height, conf, text
+    for row in tsv.lines() {
+        let columns: Vec<&str> = row.split('\t').collect();
+        let [level, _, block, paragraph, line, _, _, _, _, _, conf, text] = columns[..] else {
+            continue;
+        };
+        let Ok(confidence) = conf.trim().parse::<f32>() else {
+            continue;
+        };
+        if level != "5" || confidence < MIN_WORD_CONFIDENCE {
+            continue;
+        }
+        let word = fix_confusables(text.trim());
+        if word.is_empty() || is_noise(&word) {
+            continue;
+        }
+
+        let key = Some((block, paragraph, line));
+        match lines.last_mut() {
+            Some(words) if last_line == key => words.push(word),
+            _ => lines.push(vec![word]),
+        }
+        last_line = key;
+        confidences.push(confidence);
+    }
+
+    let text = lines.iter().map(|words| words.join(" ")).collect::<Vec<_>>().join("\n");
+    let confidence = match confidences.len() {
+        0 => 0.0,
+        count => confidences.iter().sum::<f32>() / count as f32,
+    };
+    OcrText { text, confidence }
+}
+
+/// Common words and the stop words of every language with a list
+fn is_known_word(word: &str) -> bool {
+    static WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    let words = WORDS.get_or_init(|| {
+        include_str!("ocr_cleanup/words.txt")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    });
+    let lower = word.to_lowercase();
+    words.contains(lower.as_str()) || crate::key_phrases::is_any_stop_word(&lower)
+}
+
+/// Puts right characters tesseract confuses: `0`, `1` and `5` between letters of a word,
+/// `O`, `l` and `I` among the digits of a number, and `|` for `I` or `l`
+/// The dictionary decides between `l` and `i` for a `1`; words it knows are left alone
+pub(crate) fn fix_confusables(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if is_known_word(token) || !chars.iter().all(|c| c.is_ascii()) {
+        return token.to_string();
+    }
+    let digits = chars.iter().filter(|c| c.is_ascii_digit()).count();
+    let letters = chars.iter().filter(|c| c.is_ascii_alphabetic()).count();
+
+    // "2O23", "l0.5O": a number with letters that look like digits
+    if digits >= 2 && letters > 0 && letters <= digits && chars.iter().all(|c| !c.is_ascii_alphabetic() || "OolI".contains(*c)) {
+        return chars
+            .iter()
+            .map(|c| match c {
+                'O' | 'o' => '0',
+                'l' | 'I' => '1',
+                c => *c,
+            })
+            .collect();
+    }
+
+    // "C0NFERENCE", "he1lo", "|nvoice": a word with digits that look like letters
+    if letters < 3 || digits >= letters {
+        return token.to_string();
+    }
+    let is_letter = |index: usize| chars.get(index).is_some_and(|c| c.is_ascii_alphabetic());
+    let mut fixed = chars.clone();
+    let mut alternative = None;
+    for (index, c) in chars.iter().enumerate() {
+        let before = index.checked_sub(1).is_some_and(is_letter);
+        let after = is_letter(index + 1);
+        let upper = chars
+            .get(index + 1)
+            .filter(|c| c.is_ascii_alphabetic())
+            .or(index.checked_sub(1).and_then(|i| chars.get(i)))
+            .is_some_and(|c| c.is_ascii_uppercase());
+        if *c == '|' && after {
+            fixed[index] = if before { 'l' } else { 'I' };
+        } else if before && after {
+            if let Some((_, capital, small)) = CONFUSABLE_DIGITS.iter().find(|(digit, _, _)| digit == c) {
+                fixed[index] = if upper { *capital } else { *small };
+                if *c == '1' && !upper {
+                    alternative = Some(index);
+                }
+            }
+        }
+    }
+
+    let fixed: String = fixed.into_iter().collect();
+    // A lower-case `1` is as often an `i` as an `l`: take the one that makes a known word
+    if let Some(index) = alternative.filter(|_| !is_known_word(&fixed)) {
+        let with_i: String = fixed.chars().enumerate().map(|(i, c)| if i == index { 'i' } else { c }).collect();
+        if is_known_word(&with_i) {
+            return with_i;
+        }
+    }
+    fixed
+}
+
+/// Whether an OCR token is noise rather than a word: no letters or digits, short tokens the
+/// dictionary doesn't know, runs of consonants or of one letter, or letters, digits and
+/// capitals mixed at random
+/// Only Latin text is judged; numbers, dates and other scripts are kept
+pub(crate) fn is_noise(token: &str) -> bool {
+    let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+    if word.is_empty() {
+        return true;
+    }
+    if is_known_word(word) || !word.chars().all(|c| c.is_ascii() || is_latin_letter(c)) {
+        return false;
+    }
+    let chars: Vec<char> = word.chars().collect();
+    let letters = chars.iter().filter(|c| c.is_alphabetic()).count();
+    let digits = chars.iter().filter(|c| c.is_ascii_digit()).count();
+    if letters == 0 {
+        return false;
+    }
+    // Roman numerals ("XIV") look like runs of consonants
+    if chars.iter().all(|c| "IVXLCDM".contains(*c)) {
+        return false;
+    }
+
+    let short = digits == 0 && letters <= 2;
+    let symbols = chars.iter().filter(|c| !c.is_alphanumeric() && !"'-.&_".contains(**c)).count();
+    let repeated = chars.windows(3).any(|w| w[0].is_alphabetic() && w[0] == w[1] && w[1] == w[2]);
+    let all_caps = chars.iter().all(|c| !c.is_lowercase());
+    let no_vowels = letters >= 3 && !all_caps && !chars.iter().any(|c| is_vowel(*c));
+    let switches = |change: fn(char, char) -> bool| chars.windows(2).filter(|w| change(w[0], w[1])).count();
+    let mixed_digits = switches(|a, b| a.is_ascii_digit() != b.is_ascii_digit()) >= 4;
+    let mixed_case = switches(|a, b| a.is_lowercase() && b.is_uppercase()) >= 3;
+
+    short || symbols * 3 > chars.len() || repeated || no_vowels || mixed_digits || mixed_case
+}
+
+fn is_latin_letter(c: char) -> bool {
+    matches!(c as u32, 0x00C0..=0x024F)
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyAEIOUYàáâãäåæèéêëìíîïòóôõöøùúûüýÿÀÁÂÃÄÅÆÈÉÊËÌÍÎÏÒÓÔÕÖØÙÚÛÜÝ".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A TSV word row on `line`, as tesseract writes it
+    fn word(line: u32, conf: f32, text: &str) -> String {
+        format!("5\t1\t1\t1\t{}\t1\t0\t0\t10\t10\t{}\t{}", line, conf, text)
+    }
+
+    #[test]
+    fn test_from_tsv() {
+        let tsv = [
+            "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext".to_string(),
+            "1\t1\t0\t0\t0\t0\t0\t0\t640\t480\t-1\t".to_string(),
+            word(1, 91.0, "ANNUAL"),
+            word(1, 88.5, "C0NFERENCE"),
+            word(1, 12.0, "Tl;"),
+            word(2, 95.0, "Berlin,"),
+            word(2, 90.0, "2O23"),
+            word(2, 70.0, "~~"),
+            word(2, 64.0, "rnvwq"),
+            word(3, 20.0, "~"),
+        ]
+        .join("\n");
+
+        let ocr = from_tsv(&tsv);
+        assert_eq!(ocr.text, "ANNUAL CONFERENCE\nBerlin, 2023");
+        assert_eq!(ocr.confidence, (91.0 + 88.5 + 95.0 + 90.0) / 4.0);
+
+        // Nothing worth keeping
+        let ocr = from_tsv(&[word(1, 15.0, "Invoice"), word(1, 80.0, "|")].join("\n"));
+        assert_eq!(ocr, OcrText { text: String::new(), confidence: 0.0 });
+    }
+
+    #[test]
+    fn test_fix_confusables() {
+        assert_eq!(fix_confusables("C0NFERENCE"), "CONFERENCE");
+        assert_eq!(fix_confusables("he1lo"), "hello");
+        assert_eq!(fix_confusables("Meet1ng"), "Meeting");
+        assert_eq!(fix_confusables("|nvoice"), "Invoice");
+        assert_eq!(fix_confusables("2O23"), "2023");
+        assert_eq!(fix_confusables("l0.5O"), "10.50");
+        // Names with digits in them stay as they are
+        for token in ["iPhone15", "MP3", "Q3", "B2B", "H1N1", "Win10", "x86", "2023", "Zoë"] {
+            assert_eq!(fix_confusables(token), token);
+        }
+    }
+
+    #[test]
+    fn test_is_noise() {
+        for noise in ["~~", "|", "rn", "q", "rnvwq", "Brrr", "a1b2c3", "aBcDeF", "ab#$%c"] {
+            assert!(is_noise(noise), "{}", noise);
+        }
+        for word in ["a", "I", "of", "TV", "Dr.", "HTML", "XIV", "2023-10-15", "COVID-19", "iPhone", "McDonald's", "Straße", "東京", "über"] {
+            assert!(!is_noise(word), "{}", word);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, warn};
+
+/// Languages tried in order when none are configured:
+/// Traditional Chinese, Simplified Chinese, English
+const DEFAULT_LANGUAGES: [&str; 3] = ["chi_tra", "chi_sim", "eng"];
+
+/// Tesseract data files that aren't languages
+const NON_LANGUAGES: [&str; 2] = ["osd", "equ"];
+
+/// First tesseract release with `--list-langs`
+const LIST_LANGS_SINCE: &str = "3.02";
+
+// Language packs found by `tesseract --list-langs`, None when it couldn't be run
+static INSTALLED: OnceLock<Option<Vec<String>>> = OnceLock::new();
+
+// Missing languages already warned about, so each is reported once per run
+static WARNED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Language packs installed for tesseract, as listed by `tesseract --list-langs`
+/// Returns None when the tesseract command isn't available to ask
+pub fn installed_ocr_languages() -> Option<&'static [String]> {
+    INSTALLED.get_or_init(list_installed).as_deref()
+}
+
+/// The requested languages that have no installed tesseract language pack
+/// Empty when nothing is missing or the installed packs can't be listed
+pub fn missing_ocr_languages(requested: &[String]) -> Vec<String> {
+    match installed_ocr_languages() {
+        Some(installed) => missing_from(requested, installed),
+        None => Vec::new(),
+    }
+}
+
+/// Tesseract language arguments to try, one run each, for the configured languages
+/// Configured languages are combined into a single run (e.g. "eng+deu+jpn"), leaving out
+/// missing packs with a warning; with none configured or none left, the default languages
+/// that are installed are tried one by one
+pub(crate) fn language_attempts(configured: &[String]) -> Vec<String> {
+    let installed = installed_ocr_languages();
+    if let Some(installed) = installed {
+        warn_missing(&missing_from(configured, installed));
+    }
+    attempts_for(configured, installed)
+}
+
+fn attempts_for(configured: &[String], installed: Option<&[String]>) -> Vec<String> {
+    let is_installed = |lang: &str| installed.is_none_or(|installed| installed.iter().any(|l| l == lang));
+
+    let usable: Vec<&str> = configured
+        .iter()
+        .map(String::as_str)
+        .filter(|lang| is_installed(lang))
+        .collect();
+    if !usable.is_empty() {
+        return vec![usable.join("+")];
+    }
+
+    let defaults: Vec<String> = DEFAULT_LANGUAGES
+        .iter()
+        .filter(|lang| is_installed(lang))
+        .map(|lang| lang.to_string())
+        .collect();
+    if !defaults.is_empty() {
+        return defaults;
+    }
+
+    // None of the defaults are installed, so use whatever languages are
+    match installed {
+        Some(installed) => installed
+            .iter()
+            .filter(|lang| !NON_LANGUAGES.contains(&lang.as_str()))
+            .take(DEFAULT_LANGUAGES.len())
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn missing_from(requested: &[String], installed: &[String]) -> Vec<String> {
+    requested
+        .iter()
+        .filter(|lang| !installed.contains(lang))
+        .cloned()
+        .collect()
+}
+
+/// Warns about missing language packs not reported yet
+fn warn_missing(missing: &[String]) {
+    let mut warned = WARNED.lock().unwrap();
+    let warned = warned.get_or_insert_with(HashSet::new);
+    for lang in missing {
+        if warned.insert(lang.clone()) {
+            warn!(
+                "OCR language '{}' is not installed for tesseract; install its language pack to use it",
+                lang
+            );
+        }
+    }
+}
+
+fn list_installed() -> Option<Vec<String>> {
+    let tesseract = crate::deps_check::Dependency::Tesseract;
+    // Older releases can't list their language packs, so requested languages are tried as is
+    if let Some(version) = tesseract.version() {
+        if crate::deps_check::version_at_least(&version, LIST_LANGS_SINCE) == Some(false) {
+            debug!("tesseract {} can't list installed languages (needs {})", version, LIST_LANGS_SINCE);
+            return None;
+        }
+    }
+
+    let mut cmd = tesseract.create_command()?;
+    let output = match cmd.arg("--list-langs").output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!("tesseract --list-langs failed with status: {}", output.status);
+            return None;
+        }
+        Err(e) => {
+            debug!("Failed to run tesseract --list-langs: {}", e);
+            return None;
+        }
+    };
+
+    // Older tesseract versions print the list to stderr
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let languages = parse_list_langs(&text);
+    debug!("Installed OCR languages: {}", languages.join(", "));
+    Some(languages)
+}
+
+/// Parses the output of `tesseract --list-langs`
+fn parse_list_langs(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("List of available languages"))
+        .filter(|line| !line.contains(' '))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn langs(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_list_langs() {
+        let output = "List of available languages in \"/usr/share/tesseract-ocr/5/tessdata/\" (4):\n\
+                      chi_sim\neng\nosd\nscript/Latin\n";
+        assert_eq!(parse_list_langs(output), langs(&["chi_sim", "eng", "osd", "script/Latin"]));
+        assert!(parse_list_langs("").is_empty());
+    }
+
+    #[test]
+    fn test_configured_languages_run_together() {
+        let installed = langs(&["deu", "eng", "jpn", "osd"]);
+        let configured = langs(&["eng", "deu", "jpn"]);
+        assert_eq!(attempts_for(&configured, Some(&installed)), langs(&["eng+deu+jpn"]));
+
+        // Missing packs are left out
+        let configured = langs(&["eng", "fra"]);
+        assert_eq!(attempts_for(&configured, Some(&installed)), langs(&["eng"]));
+        assert_eq!(missing_from(&configured, &installed), langs(&["fra"]));
+
+        // Unknown installed packs: pass the configuration through
+        assert_eq!(attempts_for(&configured, None), langs(&["eng+fra"]));
+    }
+
+    #[test]
+    fn test_auto_languages() {
+        // Defaults that are installed, one at a time
+        let installed = langs(&["chi_sim", "eng", "osd"]);
+        assert_eq!(attempts_for(&[], Some(&installed)), langs(&["chi_sim", "eng"]));
+        assert_eq!(attempts_for(&[], None), langs(&DEFAULT_LANGUAGES));
+
+        // Nothing configured is installed: fall back to automatic selection
+        assert_eq!(attempts_for(&langs(&["fra"]), Some(&installed)), langs(&["chi_sim", "eng"]));
+
+        // No defaults installed: any installed language
+        let installed = langs(&["osd", "deu"]);
+        assert_eq!(attempts_for(&[], Some(&installed)), langs(&["deu"]));
+    }
+}
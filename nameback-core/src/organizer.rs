@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::extractor::FileMetadata;
+use crate::generator;
+use crate::RenameConfig;
+
+/// Works out where files go in organization mode (`RenameConfig::organize`)
+///
+/// Each file's folder comes from the folder template, under `organize_root` or the
+/// file's own folder. Names are kept unique per destination folder, against both the
+/// files already there and the files moved there earlier in the same run.
+pub(crate) struct Organizer<'a> {
+    template: &'a str,
+    root: Option<&'a Path>,
+    /// Names taken in each destination folder, read from disk when a folder is first used
+    taken: Mutex<HashMap<PathBuf, HashSet<String>>>,
+}
+
+impl<'a> Organizer<'a> {
+    /// None unless organization mode is on
+    pub(crate) fn new(config: &'a RenameConfig) -> Option<Self> {
+        Some(Self {
+            template: config.organize.as_deref()?,
+            root: config.organize_root.as_deref(),
+            taken: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Folder `path` is moved into, e.g. `<root>/2023/10/Seattle`
+    pub(crate) fn destination(&self, path: &Path, metadata: &FileMetadata, candidate: &str) -> PathBuf {
+        let base = self
+            .root
+            .or_else(|| path.parent())
+            .unwrap_or_else(|| Path::new(""));
+        let folders = generator::render_folders(self.template, candidate, metadata, generator::full_extension(path).map(OsStr::new));
+        if folders.is_empty() {
+            base.to_path_buf()
+        } else {
+            base.join(folders)
+        }
+    }
+
+    /// Generate a name that is free in `destination`, claiming it for this run
+    /// Returns the name and the counter appended to it, if one was needed
+    pub(crate) fn claim_name(
+        &self,
+        destination: &Path,
+        candidate: &str,
+        extension: Option<&OsStr>,
+        metadata: &FileMetadata,
+    ) -> (String, Option<u32>) {
+        let mut taken = self.taken.lock().unwrap();
+        let names = taken
+            .entry(destination.to_path_buf())
+            .or_insert_with(|| names_in(destination));
+        let metadata = FileMetadata {
+            folder: Some(destination.to_path_buf()),
+            ..metadata.clone()
+        };
+        generator::generate_filename_with_metadata(candidate, extension, names, Some(&metadata))
+    }
+}
+
+/// Names of the entries in `folder` (none if it doesn't exist yet)
+fn names_in(folder: &Path) -> HashSet<String> {
+    fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn photo_metadata() -> FileMetadata {
+        FileMetadata {
+            title: None,
+            artist: None,
+            album: None,
+            show: None,
+            date_time_original: Some("2023:10:07 18:02:11".to_string()),
+            description: None,
+            subject: None,
+            author: None,
+            creation_date: None,
+            gps_location: None,
+            geocoder: None,
+            include_location: true,
+            location_precision: crate::LocationPrecision::City,
+            location_blocklist: Vec::new(),
+            include_timestamp: false,
+            include_attachment_count: false,
+            peek_archives: false,
+            subject_hint: None,
+            camera: None,
+            lens: None,
+            template: None,
+            name_style: crate::NameStyle::default(),
+            timestamp_style: crate::TimestampStyle::default(),
+            category: Some(crate::FileCategory::Image),
+            folder: None,
+            page_count: None,
+            duration: None,
+            shot: None,
+        }
+    }
+
+    #[test]
+    fn test_destination_and_unique_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("Sorted");
+        std::fs::create_dir_all(root.join("image/2023/10")).unwrap();
+        std::fs::write(root.join("image/2023/10/Sunset.jpg"), "already there").unwrap();
+
+        let config = RenameConfig {
+            organize: Some("{category}/{yyyy}/{mm}/{location}".to_string()),
+            organize_root: Some(root.clone()),
+            ..RenameConfig::default()
+        };
+        let organizer = Organizer::new(&config).unwrap();
+        let metadata = photo_metadata();
+        let photo = temp_dir.path().join("inbox/IMG_0001.jpg");
+
+        // The empty {location} folder is dropped
+        let destination = organizer.destination(&photo, &metadata, "Sunset");
+        assert_eq!(destination, root.join("image/2023/10"));
+
+        let extension = Some(OsStr::new("jpg"));
+        let claim = || organizer.claim_name(&destination, "Sunset", extension, &metadata);
+        assert_eq!(claim(), ("Sunset_1.jpg".to_string(), Some(1)));
+        assert_eq!(claim(), ("Sunset_2.jpg".to_string(), Some(2)));
+
+        // Without a root, folders are created next to the file
+        let config = RenameConfig {
+            organize: Some("{yyyy}".to_string()),
+            ..RenameConfig::default()
+        };
+        let organizer = Organizer::new(&config).unwrap();
+        assert_eq!(
+            organizer.destination(&photo, &metadata, "Sunset"),
+            temp_dir.path().join("inbox/2023")
+        );
+        assert!(Organizer::new(&RenameConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,589 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Crossref works endpoint; the DOI is appended as a path segment
+const CROSSREF_URL: &str = "https://api.crossref.org/works/";
+
+/// arXiv query endpoint, answering in Atom
+const ARXIV_URL: &str = "https://export.arxiv.org/api/query";
+
+/// arXiv asks for no more than one request every three seconds
+const ARXIV_REQUEST_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Extension of the lookup results kept between runs, next to the metadata cache
+const CACHE_EXTENSION: &str = "papers.jsonl";
+
+/// How long an identifier the service didn't know is taken as unknown; it may be
+/// registered since
+const UNKNOWN_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Titles longer than this (in characters, see `script::truncate`) are cut between words
+const MAX_TITLE_WIDTH: usize = 100;
+
+// Time of the latest arXiv request, made or waiting for its turn, shared by the analysis threads
+static LAST_ARXIV_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+// Set when a lookup couldn't reach the service, so the rest of the run doesn't wait on
+// timeouts file after file
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+// Lookups from this and earlier runs, loaded on first use (and again for another cache file)
+static CACHE: Mutex<Option<PaperCache>> = Mutex::new(None);
+
+/// A paper identifier printed on a PDF's first page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaperId {
+    /// A DOI such as `10.1038/nature14539`, looked up on Crossref
+    Doi(String),
+    /// A new (`1706.03762`) or old style (`hep-th/9901001`) arXiv identifier, without version
+    Arxiv(String),
+}
+
+impl PaperId {
+    /// Key of the identifier in the cache; DOIs are case-insensitive
+    fn cache_key(&self) -> String {
+        match self {
+            PaperId::Doi(doi) => format!("doi:{}", doi.to_lowercase()),
+            PaperId::Arxiv(id) => format!("arxiv:{}", id),
+        }
+    }
+}
+
+/// What names a paper
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Paper {
+    /// Family name of the first author (or the name of an organization author)
+    pub first_author: Option<String>,
+    pub year: Option<i32>,
+    pub title: String,
+}
+
+/// Names a PDF of an academic paper from the Crossref or arXiv record of the DOI or arXiv
+/// identifier on its first page, as `FirstAuthor Year Title`
+/// Answers are kept in `cache` (see `RenameConfig::paper_cache_path`; None = this run only)
+/// Returns None when the first page has no identifier, the service doesn't know it, or the
+/// service can't be reached (after which this run makes no more lookups)
+pub fn identify_paper(path: &Path, cache: Option<PathBuf>) -> Result<Option<String>> {
+    let Some(text) = first_page_text(path) else {
+        return Ok(None);
+    };
+    let Some(id) = find_identifier(&text) else {
+        return Ok(None);
+    };
+    debug!("First page cites {:?}", id);
+    Ok(lookup(&id, cache)?.map(|paper| format_paper_name(&paper)))
+}
+
+/// Text of the first page, or None when the PDF can't be read (it's named from its
+/// content as usual then)
+fn first_page_text(path: &Path) -> Option<String> {
+    let mut document = pdf_extract::Document::load(path).ok()?;
+    if document.is_encrypted() && document.decrypt("").is_err() {
+        return None;
+    }
+    let mut text = String::new();
+    let mut output = pdf_extract::PlainTextOutput::new(&mut text);
+    pdf_extract::output_doc_page(&document, &mut output, 1).ok()?;
+    Some(text)
+}
+
+/// The DOI or arXiv identifier on a first page: a DOI if there is one (the published
+/// version), else an arXiv identifier
+/// DataCite's arXiv DOIs (`10.48550/arXiv.1706.03762`) aren't on Crossref, so they are
+/// looked up on arXiv
+pub fn find_identifier(text: &str) -> Option<PaperId> {
+    static DOI: OnceLock<Regex> = OnceLock::new();
+    static ARXIV: OnceLock<Regex> = OnceLock::new();
+    let doi = DOI.get_or_init(|| Regex::new(r"\b10\.\d{4,9}/[^\s\x22<>]+").unwrap());
+    let arxiv = ARXIV.get_or_init(|| {
+        Regex::new(r"(?i)\barxiv[:\s.]\s*(\d{4}\.\d{4,5}|[a-z\-]+(?:\.[a-z]{2})?/\d{7})(?:v\d+)?\b").unwrap()
+    });
+
+    let found_doi = doi
+        .find(text)
+        .map(|found| found.as_str().trim_end_matches(['.', ',', ';', ':', ')', ']', '}']).to_string());
+    if let Some(found) = found_doi {
+        return Some(match found.to_lowercase().strip_prefix("10.48550/arxiv.") {
+            Some(id) => PaperId::Arxiv(id.to_string()),
+            None => PaperId::Doi(found),
+        });
+    }
+    let id = arxiv.captures(text)?.get(1)?.as_str();
+    Some(PaperId::Arxiv(id.to_string()))
+}
+
+/// Formats a paper as `Vaswani 2017 Attention Is All You Need` (sanitized to
+/// `Vaswani_2017_Attention_Is_All_You_Need`), leaving out the author or year when unknown
+pub fn format_paper_name(paper: &Paper) -> String {
+    let year = paper.year.map(|year| year.to_string());
+    let title = crate::script::truncate(&paper.title, MAX_TITLE_WIDTH);
+    paper
+        .first_author
+        .iter()
+        .chain(year.iter())
+        .map(String::as_str)
+        .chain([title.trim_end_matches('.')])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The paper `id` stands for, from the cache or else its service
+/// Papers the service doesn't know are cached too (for `UNKNOWN_TTL`); failed lookups aren't
+fn lookup(id: &PaperId, cache_path: Option<PathBuf>) -> Result<Option<Paper>> {
+    let key = id.cache_key();
+    if let Some(cached) = with_cache(&cache_path, |cache| cache.get(&key).cloned()) {
+        debug!("Paper lookup for {} answered from the cache", key);
+        return Ok(cached);
+    }
+    if OFFLINE.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    if matches!(id, PaperId::Arxiv(_)) {
+        wait_for_arxiv();
+    }
+    let paper = match request(id) {
+        Ok(paper) => paper,
+        Err(e) if is_unreachable(&e) => {
+            debug!("Paper lookup service unreachable ({}), not looking up papers this run", e);
+            OFFLINE.store(true, Ordering::Relaxed);
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    with_cache(&cache_path, |cache| cache.insert(key, paper.clone()));
+    Ok(paper)
+}
+
+/// Whether `error` means there's no network (or the service is down) rather than a bad answer
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// Runs `f` on the cache at `path`, loading it first if this is the first lookup there
+fn with_cache<T>(path: &Option<PathBuf>, f: impl FnOnce(&mut PaperCache) -> T) -> T {
+    let mut cache = CACHE.lock().unwrap();
+    if cache.as_ref().is_some_and(|cache| &cache.path != path) {
+        *cache = None;
+    }
+    let cache = cache.get_or_insert_with(|| PaperCache::load(path.clone()));
+    f(cache)
+}
+
+/// The paper cache kept next to the metadata cache at `metadata_cache`
+pub(crate) fn cache_file(metadata_cache: &Path) -> PathBuf {
+    metadata_cache.with_extension(CACHE_EXTENSION)
+}
+
+/// Forgets every lookup kept at `path`
+pub(crate) fn clear(path: &Path) -> Result<()> {
+    let mut cache = CACHE.lock().unwrap();
+    if cache.as_ref().is_some_and(|cache| cache.path.as_deref() == Some(path)) {
+        *cache = None;
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Waits for this thread's turn to ask arXiv: each caller takes the next free slot and
+/// sleeps without holding the lock, so other threads can take theirs (Crossref has no
+/// such limit)
+fn wait_for_arxiv() {
+    let wait = {
+        let mut last_request = LAST_ARXIV_REQUEST.lock().unwrap();
+        let now = Instant::now();
+        let turn = last_request.map_or(now, |last| (last + ARXIV_REQUEST_INTERVAL).max(now));
+        *last_request = Some(turn);
+        turn - now
+    };
+    if !wait.is_zero() {
+        std::thread::sleep(wait);
+    }
+}
+
+/// Asks Crossref or arXiv for the paper; None when it doesn't know the identifier
+fn request(id: &PaperId) -> Result<Option<Paper>> {
+    let user_agent = format!(
+        "Nameback/{} (https://github.com/h4x0r/nameback)",
+        env!("CARGO_PKG_VERSION")
+    );
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    match id {
+        PaperId::Doi(doi) => {
+            debug!("Looking up DOI {} on Crossref", doi);
+            // DOIs may hold any character, `/` included, so the DOI is one encoded segment
+            let mut url = reqwest::Url::parse(CROSSREF_URL)?;
+            url.path_segments_mut()
+                .map_err(|_| anyhow::anyhow!("Invalid Crossref URL"))?
+                .pop_if_empty()
+                .push(doi);
+            let response = client.get(url).send()?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let response = response.error_for_status().context("Crossref lookup failed")?;
+            let work: CrossrefResponse = response.json().context("Failed to parse Crossref response")?;
+            Ok(crossref_paper(work))
+        }
+        PaperId::Arxiv(arxiv_id) => {
+            debug!("Looking up arXiv:{} on arXiv", arxiv_id);
+            let response = client
+                .get(ARXIV_URL)
+                .query(&[("id_list", arxiv_id.as_str())])
+                .send()?
+                .error_for_status()
+                .context("arXiv lookup failed")?;
+            let feed = response.text().context("Failed to read arXiv response")?;
+            Ok(arxiv_paper(&feed))
+        }
+    }
+}
+
+/// Crossref `works/{doi}` response, the fields that name a paper
+#[derive(Debug, Deserialize)]
+struct CrossrefResponse {
+    message: CrossrefWork,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossrefWork {
+    #[serde(default)]
+    title: Vec<String>,
+    #[serde(default)]
+    author: Vec<CrossrefAuthor>,
+    issued: Option<CrossrefDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossrefAuthor {
+    family: Option<String>,
+    /// Organizations have a name instead of a family name
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossrefDate {
+    /// `[[2015, 5, 28]]`; parts are null when the date is unknown
+    #[serde(rename = "date-parts", default)]
+    date_parts: Vec<Vec<Option<i32>>>,
+}
+
+/// The paper of a Crossref work, or None without a title
+fn crossref_paper(response: CrossrefResponse) -> Option<Paper> {
+    let work = response.message;
+    let title = clean_markup(work.title.first()?);
+    if title.is_empty() {
+        return None;
+    }
+    let first_author = work
+        .author
+        .into_iter()
+        .find_map(|author| author.family.or(author.name))
+        .map(|name| clean_markup(&name))
+        .filter(|name| !name.is_empty());
+    let year = work.issued.and_then(|date| *date.date_parts.first()?.first()?);
+    Some(Paper { first_author, year, title })
+}
+
+/// The paper of the first entry of an arXiv Atom feed, or None when the feed has none (arXiv
+/// answers unknown identifiers with an empty feed, or an entry titled `Error`)
+fn arxiv_paper(feed: &str) -> Option<Paper> {
+    let entry = element(feed, "entry")?;
+    if element(entry, "id").is_some_and(|id| id.contains("/api/errors")) {
+        return None;
+    }
+    let title = clean_markup(element(entry, "title")?);
+    if title.is_empty() {
+        return None;
+    }
+    let first_author = element(entry, "author")
+        .and_then(|author| element(author, "name"))
+        .and_then(|name| family_name(&clean_markup(name)));
+    let year = element(entry, "published").and_then(|date| date.get(..4)?.parse().ok());
+    Some(Paper { first_author, year, title })
+}
+
+/// The content of the first `<tag>` element in `xml` (its attributes skipped)
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}", tag);
+    let start = xml.match_indices(&open).find_map(|(index, _)| {
+        let rest = &xml[index + open.len()..];
+        if !rest.starts_with(['>', ' ', '\n', '\t', '\r']) {
+            return None;
+        }
+        Some(index + open.len() + rest.find('>')? + 1)
+    })?;
+    let end = xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..start + end])
+}
+
+/// The family name of a full name as arXiv gives it (`Ashish Vaswani`, `Guido van Rossum`),
+/// taken as the last word but for suffixes
+fn family_name(name: &str) -> Option<String> {
+    name.split_whitespace()
+        .rev()
+        .find(|word| !matches!(word.trim_end_matches('.'), "Jr" | "Sr" | "II" | "III" | "IV"))
+        .map(|word| word.trim_end_matches(',').to_string())
+}
+
+/// Text without the tags (`<i>`, MathML, `<jats:italic>`) and entities of XML and JATS, and
+/// with its whitespace collapsed
+fn clean_markup(text: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    let text = tag
+        .replace_all(text, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Papers looked up before, by `PaperId::cache_key`; None records an identifier the service
+/// doesn't know
+/// Kept in a JSON Lines file so offline runs and reruns don't ask again: each lookup appends
+/// a line, and loading drops lines a later one replaced or whose unknown answer expired
+#[derive(Debug, Default)]
+struct PaperCache {
+    path: Option<PathBuf>,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// One line of the cache file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    paper: Option<Paper>,
+    /// When the service answered, in seconds since the Unix epoch
+    looked_up: u64,
+}
+
+impl CacheEntry {
+    /// Unknown identifiers are asked again after `UNKNOWN_TTL`
+    fn expired(&self, now: u64) -> bool {
+        self.paper.is_none() && now.saturating_sub(self.looked_up) > UNKNOWN_TTL.as_secs()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+impl PaperCache {
+    /// The cache at `path`; empty when there is none yet or it can't be read
+    fn load(path: Option<PathBuf>) -> Self {
+        let text = path.as_deref().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+        let now = unix_now();
+        let mut lines = 0;
+        let mut entries = HashMap::new();
+        for line in text.lines() {
+            lines += 1;
+            if let Ok(entry) = serde_json::from_str::<CacheEntry>(line) {
+                entries.insert(entry.key.clone(), entry);
+            }
+        }
+        entries.retain(|_, entry| !entry.expired(now));
+
+        let cache = PaperCache { path, entries };
+        if lines > cache.entries.len() {
+            if let Err(e) = cache.compact() {
+                debug!("Failed to compact the paper cache: {}", e);
+            }
+        }
+        cache
+    }
+
+    fn get(&self, key: &str) -> Option<&Option<Paper>> {
+        self.entries.get(key).map(|entry| &entry.paper)
+    }
+
+    /// Records a lookup and appends it to the file; a cache that can't be written only costs
+    /// the lookup being made again next run
+    fn insert(&mut self, key: String, paper: Option<Paper>) {
+        let entry = CacheEntry {
+            key: key.clone(),
+            paper,
+            looked_up: unix_now(),
+        };
+        if let Err(e) = self.append(&entry) {
+            debug!("Failed to save the paper cache: {}", e);
+        }
+        self.entries.insert(key, entry);
+    }
+
+    fn append(&self, entry: &CacheEntry) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        // One write of a whole line, so lines of other nameback processes don't interleave
+        fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Rewrites the file with the live entries only
+    fn compact(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = Vec::new();
+        for entry in self.entries.values() {
+            text.extend(serde_json::to_vec(entry)?);
+            text.push(b'\n');
+        }
+        // Written aside and moved into place, so another nameback process never reads half a file
+        let temp = path.with_extension(format!("{}.{}", CACHE_EXTENSION, std::process::id()));
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_identifier() {
+        let page = "Deep learning\nYann LeCun, Yoshua Bengio & Geoffrey Hinton\nNature 521, 436–444 (2015)\ndoi:10.1038/nature14539.\n";
+        assert_eq!(find_identifier(page), Some(PaperId::Doi("10.1038/nature14539".to_string())));
+        assert_eq!(
+            find_identifier("https://doi.org/10.1145/3292500.3330701)"),
+            Some(PaperId::Doi("10.1145/3292500.3330701".to_string()))
+        );
+        // The stamp arXiv prints down the side of a preprint
+        assert_eq!(
+            find_identifier("Attention Is All You Need\narXiv:1706.03762v7 [cs.CL] 2 Aug 2023"),
+            Some(PaperId::Arxiv("1706.03762".to_string()))
+        );
+        assert_eq!(find_identifier("arXiv:hep-th/9901001v2"), Some(PaperId::Arxiv("hep-th/9901001".to_string())));
+        assert_eq!(
+            find_identifier("https://doi.org/10.48550/arXiv.2303.08774"),
+            Some(PaperId::Arxiv("2303.08774".to_string()))
+        );
+        assert_eq!(find_identifier("Minutes of the meeting, version 10.2"), None);
+    }
+
+    #[test]
+    fn test_crossref_paper() {
+        let response: CrossrefResponse = serde_json::from_str(
+            r#"{"status": "ok", "message-type": "work", "message": {
+                "DOI": "10.1038/nature14539",
+                "title": ["Deep <i>learning</i>"],
+                "author": [
+                    {"given": "Yann", "family": "LeCun", "sequence": "first"},
+                    {"given": "Yoshua", "family": "Bengio", "sequence": "additional"}
+                ],
+                "issued": {"date-parts": [[2015, 5, 27]]}
+            }}"#,
+        )
+        .unwrap();
+        let paper = crossref_paper(response).unwrap();
+        assert_eq!(format_paper_name(&paper), "LeCun 2015 Deep learning");
+
+        // An organization author and an unknown date
+        let response: CrossrefResponse = serde_json::from_str(
+            r#"{"message": {"title": ["Annual report &amp; accounts."], "author": [{"name": "WHO"}], "issued": {"date-parts": [[null]]}}}"#,
+        )
+        .unwrap();
+        let paper = crossref_paper(response).unwrap();
+        assert_eq!(paper.year, None);
+        assert_eq!(format_paper_name(&paper), "WHO Annual report & accounts");
+
+        let response: CrossrefResponse = serde_json::from_str(r#"{"message": {"title": []}}"#).unwrap();
+        assert_eq!(crossref_paper(response), None);
+    }
+
+    #[test]
+    fn test_arxiv_paper() {
+        let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title type="html">ArXiv Query: id_list=1706.03762</title>
+  <entry>
+    <id>http://arxiv.org/abs/1706.03762v7</id>
+    <published>2017-06-12T17:57:34Z</published>
+    <title>Attention Is All
+  You Need</title>
+    <author>
+      <name>Ashish Vaswani</name>
+    </author>
+    <author>
+      <name>Noam Shazeer</name>
+    </author>
+  </entry>
+</feed>"#;
+        let paper = arxiv_paper(feed).unwrap();
+        assert_eq!(format_paper_name(&paper), "Vaswani 2017 Attention Is All You Need");
+
+        let error = r#"<feed><entry><id>http://arxiv.org/api/errors#incorrect_id_format_for_1706</id><title>Error</title></entry></feed>"#;
+        assert_eq!(arxiv_paper(error), None);
+        assert_eq!(arxiv_paper("<feed><title>ArXiv Query</title></feed>"), None);
+    }
+
+    #[test]
+    fn test_cache_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = cache_file(&dir.path().join("cache").join("metadata.sqlite"));
+        let paper = Paper { first_author: Some("LeCun".to_string()), year: Some(2015), title: "Deep learning".to_string() };
+
+        let mut cache = PaperCache::load(Some(path.clone()));
+        assert_eq!(cache.get("doi:10.1038/nature14539"), None);
+        cache.insert("doi:10.1038/nature14539".to_string(), Some(paper.clone()));
+        cache.insert("doi:10.9999/unknown".to_string(), None);
+
+        let cache = PaperCache::load(Some(path.clone()));
+        assert_eq!(cache.get("doi:10.1038/nature14539"), Some(&Some(paper)));
+        assert_eq!(cache.get("doi:10.9999/unknown"), Some(&None));
+
+        clear(&path).unwrap();
+        assert!(!path.exists());
+        assert!(clear(&path).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_papers_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_EXTENSION);
+        let old = unix_now() - UNKNOWN_TTL.as_secs() - 60;
+        let lines = [
+            CacheEntry { key: "doi:10.9999/old".to_string(), paper: None, looked_up: old },
+            CacheEntry { key: "arxiv:1706.03762".to_string(), paper: None, looked_up: old },
+            CacheEntry { key: "arxiv:1706.03762".to_string(), paper: None, looked_up: unix_now() },
+        ]
+        .iter()
+        .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+        .collect::<String>();
+        fs::write(&path, lines).unwrap();
+
+        let cache = PaperCache::load(Some(path.clone()));
+        assert_eq!(cache.get("doi:10.9999/old"), None);
+        assert_eq!(cache.get("arxiv:1706.03762"), Some(&None));
+        // The replaced and expired lines are dropped from the file
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+}
@@ -1,13 +1,49 @@
 use anyhow::{Context, Result};
 use image::DynamicImage;
-use log::debug;
+use tracing::debug;
 use std::path::Path;
 
+use crate::key_phrases::PhraseLimits;
+use crate::location_timestamp::TimestampStyle;
+use crate::ocr_cleanup::OcrText;
+use crate::tool_command::ToolCommand;
+
+/// A name read from a PDF's content
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfContent {
+    pub name: String,
+    /// The name carries the date the document is about (a statement's month, a receipt's
+    /// date); that dates the file better than its own dates, which are usually when it was
+    /// downloaded or saved
+    pub dated: bool,
+}
+
+impl From<String> for PdfContent {
+    fn from(name: String) -> Self {
+        PdfContent { name, dated: false }
+    }
+}
+
 /// Extracts text content from a PDF file and returns the first useful portion
-pub fn extract_pdf_content(path: &Path) -> Result<Option<String>> {
+/// `languages` are the tesseract language arguments tried if the PDF needs OCR; long text is
+/// named from its key phrases, as many and as long as `phrases` allows; OCR text read with a
+/// mean confidence under `min_confidence` (0-100) isn't used
+/// Bank and card statements are named from their bank and period; with `receipts`, a receipt
+/// or invoice is named from its vendor, date (in that style) and total
+pub fn extract_pdf_content(
+    path: &Path,
+    languages: &[String],
+    phrases: PhraseLimits,
+    min_confidence: u8,
+    receipts: Option<&TimestampStyle>,
+) -> Result<Option<PdfContent>> {
     // Try extracting text from PDF first
     match pdf_extract::extract_text(path) {
         Ok(text) => {
+            if let Some(content) = name_bill(&text, receipts) {
+                return Ok(Some(content));
+            }
+
             // For PDFs, prioritize the beginning where titles typically appear
             // Extract from RAW text BEFORE cleaning (which collapses lines)
             let raw_lines: Vec<&str> = text
@@ -44,7 +80,7 @@ pub fn extract_pdf_content(path: &Path) -> Result<Option<String>> {
                 // Accept the combined title if it's at least 10 chars
                 if combined.len() >= 10 {
                     debug!("Using combined title from document start: {}", combined);
-                    return Ok(Some(combined));
+                    return Ok(Some(combined.into()));
                 }
             }
 
@@ -52,22 +88,16 @@ pub fn extract_pdf_content(path: &Path) -> Result<Option<String>> {
             let cleaned = clean_text(&text);
             if cleaned.len() > 150 {
                 debug!("Extracting key phrases from PDF text ({} chars)", cleaned.len());
-                let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-                if !phrases.is_empty() {
-                    let best_phrase = &phrases[0];
-                    debug!("Selected key phrase: {}", best_phrase);
-                    return Ok(Some(best_phrase.clone()));
+                if let Some(phrase) = crate::key_phrases::summarize(&cleaned, phrases) {
+                    debug!("Selected key phrase: {}", phrase);
+                    return Ok(Some(phrase.into()));
                 }
             }
 
             // Final fallback: truncate from beginning of cleaned text
             if cleaned.len() > 10 {
-                let truncated = if cleaned.len() > 80 {
-                    &cleaned[..80]
-                } else {
-                    &cleaned
-                };
-                return Ok(Some(truncated.to_string()));
+                let truncated = crate::script::truncate(&cleaned, 80);
+                return Ok(Some(truncated.into()));
             }
 
             // Text too short, fall through to OCR
@@ -79,11 +109,19 @@ pub fn extract_pdf_content(path: &Path) -> Result<Option<String>> {
     }
 
     // Fallback to OCR if text extraction failed or returned insufficient text
-    extract_pdf_with_ocr(path)
+    crate::profiling::time(crate::profiling::Stage::Ocr, || {
+        extract_pdf_with_ocr(path, languages, phrases, min_confidence, receipts)
+    })
 }
 
 /// Extracts text from PDF using OCR (requires tesseract-ocr installed)
-fn extract_pdf_with_ocr(path: &Path) -> Result<Option<String>> {
+fn extract_pdf_with_ocr(
+    path: &Path,
+    languages: &[String],
+    phrases: PhraseLimits,
+    min_confidence: u8,
+    receipts: Option<&TimestampStyle>,
+) -> Result<Option<PdfContent>> {
     debug!("Attempting OCR on PDF: {}", path.display());
 
     // Check if tesseract is available
@@ -102,29 +140,30 @@ fn extract_pdf_with_ocr(path: &Path) -> Result<Option<String>> {
     };
 
     // Run OCR on the image
-    match run_tesseract_ocr(&image) {
-        Ok(text) => {
-            let cleaned = clean_text(&text);
+    match run_tesseract_ocr(&image, languages) {
+        Ok(ocr) if ocr.confidence < f32::from(min_confidence) => {
+            debug!("OCR confidence {:.0} below minimum {}, skipping", ocr.confidence, min_confidence);
+            Ok(None)
+        }
+        Ok(ocr) => {
+            if let Some(content) = name_bill(&ocr.text, receipts) {
+                return Ok(Some(content));
+            }
+            let cleaned = clean_text(&ocr.text);
             if cleaned.len() > 10 {
                 // Use key phrase extraction for longer OCR text
                 if cleaned.len() > 150 {
                     debug!("Extracting key phrases from OCR text ({} chars)", cleaned.len());
-                    let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-                    if !phrases.is_empty() {
-                        let best_phrase = &phrases[0];
-                        debug!("Selected key phrase from OCR: {}", best_phrase);
-                        return Ok(Some(best_phrase.clone()));
+                    if let Some(phrase) = crate::key_phrases::summarize(&cleaned, phrases) {
+                        debug!("Selected key phrase from OCR: {}", phrase);
+                        return Ok(Some(phrase.into()));
                     }
                 }
 
                 // For shorter text or if key phrase extraction failed, truncate
-                let truncated = if cleaned.len() > 80 {
-                    &cleaned[..80]
-                } else {
-                    &cleaned
-                };
+                let truncated = crate::script::truncate(&cleaned, 80);
                 debug!("OCR extracted: {}", truncated);
-                Ok(Some(truncated.to_string()))
+                Ok(Some(truncated.into()))
             } else {
                 debug!("OCR text too short");
                 Ok(None)
@@ -137,16 +176,22 @@ fn extract_pdf_with_ocr(path: &Path) -> Result<Option<String>> {
     }
 }
 
+/// Names bank and card statements, and with `receipts` receipts and invoices, from their fields
+pub(crate) fn name_bill(text: &str, receipts: Option<&TimestampStyle>) -> Option<PdfContent> {
+    let name = crate::format_handlers::statement::statement_name(text)
+        .or_else(|| receipts.and_then(|style| crate::format_handlers::receipt::receipt_name(text, style)))?;
+    Some(PdfContent { name, dated: true })
+}
+
 /// Checks if tesseract-ocr is installed and available
 fn is_tesseract_available() -> bool {
     crate::deps_check::Dependency::Tesseract.is_available()
 }
 
 /// Converts first page of PDF to image using pdftoppm
-fn pdf_page_to_image(path: &Path) -> Result<DynamicImage> {
-    // Create temporary directory for image output
-    let temp_dir = std::env::temp_dir();
-    let temp_prefix = temp_dir.join(format!("nameback_pdf_{}", std::process::id()));
+pub(crate) fn pdf_page_to_image(path: &Path) -> Result<DynamicImage> {
+    // Unique per call, as previews and analysis can render pages at the same time
+    let temp_prefix = crate::image_ocr::temp_png_path(&std::env::temp_dir(), "nameback_pdf").with_extension("");
 
     // Run pdftoppm to convert first page to PNG
     let pdftoppm_path = which::which("pdftoppm")
@@ -161,7 +206,7 @@ fn pdf_page_to_image(path: &Path) -> Result<DynamicImage> {
         .arg("-singlefile")
         .arg(path)
         .arg(&temp_prefix)
-        .output()
+        .tool_output()
         .context("Failed to run pdftoppm")?;
 
     if !output.status.success() {
@@ -179,8 +224,8 @@ fn pdf_page_to_image(path: &Path) -> Result<DynamicImage> {
 }
 
 /// Runs tesseract OCR on an image
-/// Tries multiple languages in priority order: Traditional Chinese, Simplified Chinese, English
-fn run_tesseract_ocr(image: &DynamicImage) -> Result<String> {
+/// Tries each language argument in turn, keeping the result with the most characters
+fn run_tesseract_ocr(image: &DynamicImage, languages: &[String]) -> Result<OcrText> {
     // Save image to temp file for tesseract
     let temp_dir = std::env::temp_dir();
     let temp_img = temp_dir.join(format!("nameback_ocr_{}.png", std::process::id()));
@@ -189,22 +234,17 @@ fn run_tesseract_ocr(image: &DynamicImage) -> Result<String> {
 
     let temp_img_str = temp_img.to_str().context("Path not valid UTF-8")?;
 
-    // Try languages in order: Traditional Chinese, Simplified Chinese, English
-    let languages = ["chi_tra", "chi_sim", "eng"];
-    let mut best_result = String::new();
+    let mut best_result = None;
     let mut best_confidence = 0;
 
-    for lang in &languages {
+    for lang in languages {
         debug!("Trying OCR with language: {}", lang);
 
-        let result = tesseract::Tesseract::new(None, Some(lang))
-            .context("Failed to initialize Tesseract")
-            .and_then(|t| t.set_image(temp_img_str).context("Failed to set image"))
-            .and_then(|mut t| t.get_text().context("Failed to extract text"));
+        let result = crate::ocr_cleanup::recognize(temp_img_str, lang);
 
         match result {
-            Ok(text) => {
-                let cleaned = clean_text(&text);
+            Ok(ocr) => {
+                let cleaned = clean_text(&ocr.text);
                 let char_count = cleaned.chars().count();
 
                 debug!("OCR with {}: {} characters extracted", lang, char_count);
@@ -212,7 +252,7 @@ fn run_tesseract_ocr(image: &DynamicImage) -> Result<String> {
                 // Use the result with the most characters as proxy for best match
                 if char_count > best_confidence {
                     best_confidence = char_count;
-                    best_result = text;
+                    best_result = Some(ocr);
                     debug!("New best result with {}: {} chars", lang, char_count);
                 }
             }
@@ -225,11 +265,7 @@ fn run_tesseract_ocr(image: &DynamicImage) -> Result<String> {
     // Clean up temp file
     let _ = std::fs::remove_file(&temp_img);
 
-    if best_confidence > 0 {
-        Ok(best_result)
-    } else {
-        anyhow::bail!("All OCR language attempts failed")
-    }
+    best_result.context("All OCR language attempts failed")
 }
 
 /// Cleans extracted text for use in filenames
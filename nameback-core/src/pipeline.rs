@@ -0,0 +1,635 @@
+use crossbeam_channel::{bounded, Receiver, Sender};
+use rayon::ThreadPool;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::detector::{self, Detection, ExtensionMismatch, FileCategory};
+use crate::extractor::{self, ExiftoolPool, FileMetadata};
+use crate::generator;
+use crate::limits::{self, LimitExceeded};
+use crate::name_provider::NameProvider;
+use crate::organizer::Organizer;
+use crate::process_limit::ProcessLimit;
+use crate::profiling::{self, ProfileReport, Stage};
+use crate::scorer::{self, NameSource, RejectReason};
+use crate::series_detector::Shot;
+use crate::tool_command::{self, ToolRunner};
+use crate::{AnalysisIssue, FileAnalysis, NameAlternative, RenameConfig};
+
+/// Most candidates offered as alternative names for a file (see `FileAnalysis::alternatives`)
+const MAX_ALTERNATIVES: usize = 4;
+
+/// Items buffered between two stages, per worker of the receiving stage
+const QUEUE_DEPTH_PER_WORKER: usize = 2;
+
+/// Callback told when a file enters a stage
+pub(crate) type StageEvents<'a> = dyn Fn(&Path, Stage) + Sync + 'a;
+
+/// A file moving through the pipeline, tagged with its position in the input
+struct Item<T> {
+    index: usize,
+    path: PathBuf,
+    data: T,
+}
+
+/// A file that has left the pipeline
+pub(crate) struct Finished {
+    pub analysis: FileAnalysis,
+    /// Whether the result came from a completed analysis and should be cached
+    pub store_in_cache: bool,
+    /// The photo's capture time, resolution and camera, when its metadata was extracted
+    pub shot: Option<Shot>,
+}
+
+/// Metadata gathered for a file so far, with any recoverable issues
+struct Extracted {
+    category: FileCategory,
+    metadata: FileMetadata,
+    issues: Vec<AnalysisIssue>,
+    /// Set when the content was too long to read (see `limits::content`)
+    over_limit: Option<LimitExceeded>,
+    /// Set when the content is in another format than the extension says
+    mismatch: Option<ExtensionMismatch>,
+}
+
+/// Result of running one stage on a file
+enum Step<T> {
+    /// Hand the file to the next stage
+    Next(T),
+    /// Nothing more to do for this file
+    Done(Box<Finished>),
+}
+
+impl<T> Step<T> {
+    fn done(finished: Finished) -> Self {
+        Step::Done(Box::new(finished))
+    }
+}
+
+/// Analysis split into stages (scan → detect → extract → OCR → score), each with
+/// its own worker threads and connected by bounded channels. Cheap stages keep
+/// running ahead while OCR is busy, and the bounds keep memory flat on large trees.
+pub(crate) struct Pipeline<'a> {
+    config: &'a RenameConfig,
+    profile: &'a Mutex<ProfileReport>,
+    /// Pool that runs the stage work; stage threads only coordinate when set
+    pool: Option<&'a ThreadPool>,
+    workers: usize,
+    /// Called from worker threads as each file enters a stage
+    on_stage: Option<&'a StageEvents<'a>>,
+    /// exiftool processes kept running for the extract workers
+    exiftool: ExiftoolPool,
+    /// Caps the OCR and media tools run at once by the OCR workers
+    processes: ProcessLimit,
+    /// Destination folders and their taken names, in organization mode
+    organizer: Option<Organizer<'a>>,
+    /// Extra candidate name sources, scored with the built-in ones
+    name_providers: &'a [Arc<dyn NameProvider>],
+    /// Runs the external tools of the stage work, stopping those that take too long
+    tools: ToolRunner,
+}
+
+impl<'a> Pipeline<'a> {
+    /// The extract, OCR and score stages each get one worker per thread of `pool`
+    /// (or of the current rayon pool if None), up to `RenameConfig::max_parallel_files`
+    pub(crate) fn new(
+        config: &'a RenameConfig,
+        profile: &'a Mutex<ProfileReport>,
+        pool: Option<&'a ThreadPool>,
+    ) -> Self {
+        // Called from inside the pool, the collector would occupy a pool thread
+        // while waiting on work queued behind it, so use our own threads instead
+        let pool = pool.filter(|pool| pool.current_thread_index().is_none());
+        let threads = pool.map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads);
+        let workers = config.max_parallel_files.map_or(threads, |max| threads.min(max)).max(1);
+        Self {
+            config,
+            profile,
+            pool,
+            workers,
+            on_stage: None,
+            exiftool: ExiftoolPool::new(config.tool_timeout),
+            processes: ProcessLimit::new(config.max_ocr_processes.unwrap_or(workers)),
+            organizer: Organizer::new(config),
+            name_providers: &[],
+            tools: ToolRunner::new(config.tool_timeout),
+        }
+    }
+
+    /// Report each file entering the detect, exif, OCR and score stages
+    pub(crate) fn with_stage_events(mut self, on_stage: &'a StageEvents<'a>) -> Self {
+        self.on_stage = Some(on_stage);
+        self
+    }
+
+    /// Score the candidates of `providers` along with the built-in ones
+    pub(crate) fn with_name_providers(mut self, providers: &'a [Arc<dyn NameProvider>]) -> Self {
+        self.name_providers = providers;
+        self
+    }
+
+    /// Run external tools as tasks on `runtime`, as many at once as OCR and media tools
+    /// otherwise
+    pub(crate) fn with_tool_runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        let max = self.config.max_ocr_processes.unwrap_or(self.workers);
+        self.tools = self.tools.on_runtime(runtime, max);
+        self
+    }
+
+    /// Analyze `files`, calling `finish` on the current thread as each file completes
+    ///
+    /// `lookup` runs in the scan stage; returning Some skips analysis for that file
+    /// (used for cache hits). `finish` receives the file's index in `files`.
+    pub(crate) fn run(
+        &self,
+        files: &[PathBuf],
+        existing_names: &Mutex<HashSet<String>>,
+        lookup: impl Fn(&Path) -> Option<FileAnalysis> + Sync,
+        mut finish: impl FnMut(usize, Finished),
+    ) {
+        let queue = self.workers * QUEUE_DEPTH_PER_WORKER;
+        let (detect_tx, detect_rx) = bounded::<Item<()>>(queue);
+        let (extract_tx, extract_rx) = bounded::<Item<Detection>>(queue);
+        let (ocr_tx, ocr_rx) = bounded::<Item<Extracted>>(queue);
+        let (score_tx, score_rx) = bounded::<Item<Extracted>>(queue);
+        let (done_tx, done_rx) = bounded::<Item<Finished>>(queue);
+
+        let detect = |path: &Path, _: ()| self.detect(path);
+        let extract = |path: &Path, detection| self.extract(path, detection);
+        let ocr = |path: &Path, mut extracted: Extracted| {
+            // Detected once: the extension the content calls for decides what is read
+            let extension = detector::content_extension(path, extracted.mismatch.as_ref());
+            extracted.over_limit = limits::content(extension.as_deref(), self.config, &extracted.metadata);
+            match extracted.over_limit {
+                Some(limit) => tracing::info!("Not reading the content of {}: {}", path.display(), limit),
+                None => {
+                    let issues = extractor::extract_content(
+                        path,
+                        extension.as_deref(),
+                        self.config,
+                        &mut extracted.metadata,
+                        Some(&self.processes),
+                    );
+                    extracted.issues.extend(issues);
+                }
+            }
+            Step::Next(extracted)
+        };
+        let score = |path: &Path, extracted| self.score(path, extracted, existing_names);
+
+        thread::scope(|scope| {
+            // Scan: feed files into the pipeline, short-circuiting lookups
+            let scan_done = done_tx.clone();
+            let lookup = &lookup;
+            scope.spawn(move || {
+                for (index, path) in files.iter().enumerate() {
+                    let sent = match lookup(path) {
+                        Some(analysis) => scan_done.send(Item {
+                            index,
+                            path: path.clone(),
+                            data: Finished {
+                                analysis,
+                                store_in_cache: false,
+                                shot: None,
+                            },
+                        }).is_ok(),
+                        None => detect_tx.send(Item {
+                            index,
+                            path: path.clone(),
+                            data: (),
+                        }).is_ok(),
+                    };
+                    if !sent {
+                        break;
+                    }
+                }
+            });
+
+            // Detection only reads a few bytes, so one worker keeps up
+            self.spawn_stage(scope, 1, Stage::Detect, detect_rx, extract_tx, &done_tx, &detect);
+            self.spawn_stage(scope, self.workers, Stage::Exif, extract_rx, ocr_tx, &done_tx, &extract);
+            self.spawn_stage(scope, self.workers, Stage::Ocr, ocr_rx, score_tx, &done_tx, &ocr);
+            self.spawn_stage(
+                scope,
+                self.workers,
+                Stage::Score,
+                score_rx,
+                done_tx.clone(),
+                &done_tx,
+                &score,
+            );
+
+            // Only worker threads hold senders now, so the loop ends once all have exited
+            drop(done_tx);
+            for item in done_rx {
+                finish(item.index, item.data);
+            }
+        });
+    }
+
+    /// Start `workers` threads running `stage` on items from `input`
+    /// `name` is the stage reported to `on_stage`
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_stage<'scope, I, O, F>(
+        &'scope self,
+        scope: &'scope thread::Scope<'scope, '_>,
+        workers: usize,
+        name: Stage,
+        input: Receiver<Item<I>>,
+        next: Sender<Item<O>>,
+        done: &Sender<Item<Finished>>,
+        stage: &'scope F,
+    ) where
+        I: Send + 'scope,
+        O: Send + 'scope,
+        F: Fn(&Path, I) -> Step<O> + Sync,
+    {
+        for _ in 0..workers {
+            let (input, next, done) = (input.clone(), next.clone(), done.clone());
+            scope.spawn(move || {
+                for Item { index, path, data } in input {
+                    if let Some(on_stage) = self.on_stage {
+                        on_stage(&path, name);
+                    }
+                    let (step, timings) = self.execute(|| {
+                        let _file_span =
+                            tracing::info_span!("file", path = %path.display()).entered();
+                        profiling::begin_file();
+                        let step = self.tools.enter(|| stage(&path, data));
+                        (step, profiling::end_file())
+                    });
+                    self.profile.lock().unwrap().record_file(&path, timings);
+
+                    let sent = match step {
+                        Step::Next(data) => next.send(Item { index, path, data }).is_ok(),
+                        Step::Done(data) => done.send(Item { index, path, data: *data }).is_ok(),
+                    };
+                    if !sent {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Run `work` on the pool if there is one, otherwise on the current thread
+    /// Stage threads are never pool threads, so blocking on channels can't starve the pool
+    fn execute<T: Send>(&self, work: impl FnOnce() -> T + Send) -> T {
+        match self.pool {
+            Some(pool) => pool.install(work),
+            None => work(),
+        }
+    }
+
+    fn detect(&self, path: &Path) -> Step<Detection> {
+        match profiling::time(Stage::Detect, || detector::detect(path)) {
+            // Turned-off categories stop here, before any metadata or OCR work
+            Ok(Detection { category, .. }) if !self.config.includes_category(&category) => Step::done(Finished {
+                analysis: unanalyzed(path, category),
+                store_in_cache: false,
+                shot: None,
+            }),
+            Ok(Detection { category: FileCategory::Unknown, .. }) => Step::done(Finished {
+                analysis: failed(
+                    path,
+                    FileCategory::Unknown,
+                    AnalysisIssue::fatal(Stage::Detect, "Unsupported file type"),
+                ),
+                store_in_cache: true,
+                shot: None,
+            }),
+            // Too large to read at all: not even hashed or cached, only a `stat` was spent
+            Ok(detection) => match limits::file_size(path, self.config) {
+                Some(limit) => {
+                    tracing::info!("Skipping {}: {}", path.display(), limit);
+                    Step::done(Finished {
+                        analysis: FileAnalysis {
+                            over_limit: Some(limit),
+                            extension_mismatch: detection.mismatch,
+                            ..unanalyzed(path, detection.category)
+                        },
+                        store_in_cache: false,
+                        shot: None,
+                    })
+                }
+                None => Step::Next(detection),
+            },
+            Err(e) => {
+                tracing::warn!("Failed to analyze {}: {}", path.display(), e);
+                Step::done(Finished {
+                    analysis: failed(
+                        path,
+                        FileCategory::Unknown,
+                        AnalysisIssue::fatal(Stage::Detect, format!("Could not read file: {}", e)),
+                    ),
+                    store_in_cache: false,
+                    shot: None,
+                })
+            }
+        }
+    }
+
+    fn extract(&self, path: &Path, detection: Detection) -> Step<Extracted> {
+        let Detection { category, mismatch } = detection;
+        match extractor::read_metadata(path, self.config, Some(&self.exiftool)) {
+            Ok(metadata) => Step::Next(Extracted {
+                category,
+                metadata,
+                issues: Vec::new(),
+                over_limit: None,
+                mismatch,
+            }),
+            Err(e) => {
+                tracing::debug!("Metadata extraction failed for {}: {}", path.display(), e);
+                // Not cached: the usual cause (exiftool missing) is fixable without touching the file
+                Step::done(Finished {
+                    analysis: failed(
+                        path,
+                        category,
+                        AnalysisIssue {
+                            timed_out: tool_command::is_timeout(&e),
+                            ..AnalysisIssue::fatal(Stage::Exif, format!("{:#}", e))
+                        },
+                    ),
+                    store_in_cache: false,
+                    shot: None,
+                })
+            }
+        }
+    }
+
+    fn score(
+        &self,
+        path: &Path,
+        extracted: Extracted,
+        existing_names: &Mutex<HashSet<String>>,
+    ) -> Step<Finished> {
+        let Extracted {
+            category,
+            mut metadata,
+            mut issues,
+            over_limit,
+            mismatch,
+        } = extracted;
+        metadata.category = Some(category.clone());
+        metadata.folder = path.parent().map(Path::to_path_buf);
+        if let Some(overrides) = self.config.category_overrides.get(&category) {
+            metadata.include_location = overrides.include_location.unwrap_or(metadata.include_location);
+            metadata.include_timestamp = overrides.include_timestamp.unwrap_or(metadata.include_timestamp);
+            if overrides.template.is_some() {
+                metadata.template = overrides.template.clone();
+            }
+        }
+        let (candidate, mut rejected) = profiling::time(Stage::Score, || {
+            let mut candidates = metadata.name_candidates(&category, path);
+            for provider in self.name_providers {
+                candidates.extend(provider.candidates(path, &metadata));
+            }
+            scorer::rank_candidates(candidates)
+        });
+        let (confidence, name_source) = candidate
+            .as_ref()
+            .map_or((0.0, NameSource::default()), |c| (c.score, c.source));
+
+        // Not cached, so the file is analyzed again on the next run, whatever its threshold
+        let min_confidence = self.config.min_confidence_for(&category);
+        if let Some(candidate) = candidate.clone().filter(|_| confidence < min_confidence) {
+            issues.push(low_confidence(confidence, min_confidence));
+            rejected.insert(0, candidate.rejected(RejectReason::BelowMinConfidence { min_confidence }));
+            return Step::done(Finished {
+                analysis: FileAnalysis {
+                    confidence,
+                    name_source,
+                    errors: issues,
+                    rejected,
+                    over_limit,
+                    extension_mismatch: mismatch,
+                    ..unanalyzed(path, category)
+                },
+                store_in_cache: false,
+                shot: None,
+            });
+        }
+
+        // Named files take the extension of their content's format when asked to
+        let mismatch = mismatch.map(|mismatch| mismatch.settled(self.config, candidate.is_some()));
+        let extension = match &mismatch {
+            Some(mismatch) if mismatch.fixed => Some(OsStr::new(mismatch.detected.as_str())),
+            _ => generator::full_extension(path).map(OsStr::new),
+        };
+        let mut destination = None;
+        let title = candidate.as_ref().map(|c| c.name.clone());
+        let generated = candidate.map(|candidate| {
+            // Organized files only need names that are free in their destination folder
+            if let Some(organizer) = &self.organizer {
+                let folder = organizer.destination(path, &metadata, &candidate.name);
+                let generated = organizer.claim_name(&folder, &candidate.name, extension, &metadata);
+                destination = Some(folder);
+                return generated;
+            }
+            let mut names = existing_names.lock().unwrap();
+            generator::generate_filename_with_metadata(
+                &candidate.name,
+                extension,
+                &mut names,
+                Some(&metadata),
+            )
+        });
+        let (proposed_name, collision_suffix) = match generated {
+            Some((name, suffix)) => (Some(name), suffix),
+            None => (None, None),
+        };
+
+        if proposed_name.is_none() {
+            issues.push(AnalysisIssue::fatal(
+                Stage::Score,
+                "No usable name in metadata, content, filename or directory",
+            ));
+        }
+        let alternatives = match &proposed_name {
+            // Organized names depend on what is free in the destination folder
+            Some(proposed) if self.organizer.is_none() => alternatives(extension, &rejected, &metadata, proposed),
+            _ => Vec::new(),
+        };
+
+        Step::Next(Finished {
+            analysis: FileAnalysis {
+                title: title.filter(|_| proposed_name.is_some()),
+                proposed_name,
+                collision_suffix,
+                destination,
+                confidence,
+                name_source,
+                errors: issues,
+                rejected,
+                alternatives,
+                over_limit,
+                extension_mismatch: mismatch,
+                ..unanalyzed(path, category)
+            },
+            // Named without its content, so raising the limit later reads it
+            store_in_cache: over_limit.is_none(),
+            shot: metadata.shot,
+        })
+    }
+}
+
+/// Names made from the best candidates the winner outscored, skipping any that come out the
+/// same as `proposed` or an earlier alternative
+/// They aren't reserved against other files' names; picking one is checked then
+fn alternatives(
+    extension: Option<&OsStr>,
+    rejected: &[scorer::RejectedCandidate],
+    metadata: &FileMetadata,
+    proposed: &str,
+) -> Vec<NameAlternative> {
+    let mut alternatives: Vec<NameAlternative> = Vec::new();
+    let outscored = rejected
+        .iter()
+        .filter(|candidate| matches!(candidate.reason, RejectReason::Outscored { .. }));
+    for candidate in outscored {
+        if alternatives.len() == MAX_ALTERNATIVES {
+            break;
+        }
+        let (name, _) = generator::generate_filename_with_metadata(
+            &candidate.name,
+            extension,
+            &mut HashSet::new(),
+            Some(metadata),
+        );
+        if name == proposed || alternatives.iter().any(|alternative| alternative.name == name) {
+            continue;
+        }
+        alternatives.push(NameAlternative {
+            name,
+            title: candidate.name.clone(),
+            source: candidate.source,
+            score: candidate.score,
+        });
+    }
+    alternatives
+}
+
+/// Analysis for a file with no proposed name
+pub(crate) fn unanalyzed(path: &Path, file_category: FileCategory) -> FileAnalysis {
+    FileAnalysis {
+        original_path: path.to_path_buf(),
+        original_name: path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        proposed_name: None,
+        title: None,
+        collision_suffix: None,
+        collision_strategy: None,
+        destination: None,
+        duplicate_of: None,
+        companions: Vec::new(),
+        file_category,
+        confidence: 0.0,
+        name_source: NameSource::Fallback,
+        errors: Vec::new(),
+        rejected: Vec::new(),
+        alternatives: Vec::new(),
+        deferred: None,
+        over_limit: None,
+        series: None,
+        extension_mismatch: None,
+    }
+}
+
+/// Issue for a file whose best name scored below `RenameConfig::min_confidence`
+pub(crate) fn low_confidence(confidence: f32, min_confidence: f32) -> AnalysisIssue {
+    AnalysisIssue::fatal(
+        Stage::Score,
+        format!(
+            "Best name scored {:.1}, below the minimum confidence of {:.1}",
+            confidence, min_confidence
+        ),
+    )
+}
+
+/// Analysis for a file that stopped at `issue`
+fn failed(path: &Path, file_category: FileCategory, issue: AnalysisIssue) -> FileAnalysis {
+    FileAnalysis {
+        errors: vec![issue],
+        ..unanalyzed(path, file_category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_finishes_every_file_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<PathBuf> = (0..20)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("file_{}.xyz", i));
+                std::fs::write(&path, "data").unwrap();
+                path
+            })
+            .collect();
+
+        let config = RenameConfig::default();
+        let profile = Mutex::new(ProfileReport::default());
+        let existing_names = Mutex::new(HashSet::new());
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let pipeline = Pipeline::new(&config, &profile, Some(&pool));
+
+        // Every other file is "cached" and must skip the later stages
+        let lookup = |path: &Path| {
+            let cached = path.to_str().unwrap().ends_with("0.xyz")
+                || path.to_str().unwrap().ends_with("2.xyz");
+            cached.then(|| FileAnalysis {
+                proposed_name: Some("cached.xyz".to_string()),
+                ..unanalyzed(path, FileCategory::Unknown)
+            })
+        };
+
+        let mut finished = vec![None; files.len()];
+        pipeline.run(&files, &existing_names, lookup, |index, result| {
+            assert!(finished[index].is_none(), "file {} finished twice", index);
+            finished[index] = Some((result.analysis, result.store_in_cache));
+        });
+
+        for (index, result) in finished.into_iter().enumerate() {
+            let (analysis, store_in_cache) = result.expect("file not finished");
+            assert_eq!(analysis.original_path, files[index]);
+            let cached = index % 10 == 0 || index % 10 == 2;
+            assert_eq!(analysis.proposed_name.is_some(), cached);
+            assert_eq!(store_in_cache, !cached);
+            if !cached {
+                assert_eq!(analysis.errors.len(), 1);
+                assert_eq!(analysis.errors[0].stage, Stage::Detect);
+            }
+        }
+    }
+    #[test]
+    fn test_max_parallel_files_caps_workers() {
+        let profile = Mutex::new(ProfileReport::default());
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+
+        let config = RenameConfig::default();
+        assert_eq!(Pipeline::new(&config, &profile, Some(&pool)).workers, 3);
+
+        let config = RenameConfig {
+            max_parallel_files: Some(2),
+            ..RenameConfig::default()
+        };
+        assert_eq!(Pipeline::new(&config, &profile, Some(&pool)).workers, 2);
+
+        // Fewer threads than allowed: the pool decides
+        let config = RenameConfig {
+            max_parallel_files: Some(8),
+            ..RenameConfig::default()
+        };
+        assert_eq!(Pipeline::new(&config, &profile, Some(&pool)).workers, 3);
+    }
+}
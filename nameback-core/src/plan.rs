@@ -0,0 +1,557 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{journal, renamer, CollisionStrategy, FileAnalysis, RenameError};
+
+/// Something standing in the way of one rename in a batch, found before anything is renamed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "conflict", rename_all = "snake_case")]
+pub enum PlanConflict {
+    /// An earlier file in the batch is renamed to the same path
+    SameTarget { other: PathBuf },
+    /// A file that isn't renamed away first already has the new name
+    TargetExists,
+    /// The new name differs only in case from `other` (an existing file or another file's
+    /// new path); the two clash on case-insensitive filesystems like the macOS and Windows defaults
+    CaseOnly { other: PathBuf },
+    /// `files` proposed names collided with others in the batch, and
+    /// `CollisionStrategy::Fail` refuses the whole batch then
+    NameCollisions { files: usize },
+    /// The file is gone since it was analyzed
+    SourceMissing,
+    /// `path`, the folder the file leaves or the one it moves into, can't be written to
+    PermissionDenied { path: PathBuf },
+    /// `path`, the folder the file leaves or the one it moves into, is on a read-only volume
+    ReadOnlyVolume { path: PathBuf },
+}
+
+impl PlanConflict {
+    /// Whether the rename is refused; a case-only clash is only a warning
+    pub fn is_blocking(&self) -> bool {
+        !matches!(self, PlanConflict::CaseOnly { .. })
+    }
+
+    /// The folder behind a permission or read-only volume conflict, which refuses every
+    /// rename into or out of it
+    pub fn unwritable_folder(&self) -> Option<&Path> {
+        match self {
+            PlanConflict::PermissionDenied { path } | PlanConflict::ReadOnlyVolume { path } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PlanConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |path: &Path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+        match self {
+            PlanConflict::SameTarget { other } => write!(f, "same new name as {}", name(other)),
+            PlanConflict::TargetExists => write!(f, "a file with the new name already exists"),
+            PlanConflict::CaseOnly { other } => write!(f, "differs only in case from {}", name(other)),
+            PlanConflict::NameCollisions { files } => {
+                write!(f, "{} proposed name(s) in the batch collided (collision strategy: fail)", files)
+            }
+            PlanConflict::SourceMissing => write!(f, "the file no longer exists"),
+            PlanConflict::PermissionDenied { path } => write!(f, "no permission to write to {}", path.display()),
+            PlanConflict::ReadOnlyVolume { path } => write!(f, "{} is on a read-only volume", path.display()),
+        }
+    }
+}
+
+/// One proposed rename and its conflicts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedRename {
+    pub original_path: PathBuf,
+    pub new_path: PathBuf,
+    /// Sidecars and RAW+JPEG partners renamed along with the file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companions: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<PlanConflict>,
+}
+
+impl PlannedRename {
+    /// The conflict that keeps this file from being renamed, if any
+    pub fn blocking_conflict(&self) -> Option<&PlanConflict> {
+        self.conflicts.iter().find(|c| c.is_blocking())
+    }
+}
+
+/// What `RenamePlan::execute` does when a rename fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnPlanError {
+    /// Keep the renames done so far and stop
+    #[default]
+    Stop,
+    /// Move the files renamed so far back, leaving everything as it was
+    Rollback,
+}
+
+/// The rename `RenamePlan::execute` stopped at
+#[derive(Debug, Clone)]
+pub struct PlanFailure {
+    pub original_path: PathBuf,
+    pub new_path: PathBuf,
+    pub error: String,
+    /// The cause, if it is one a caller may want to handle
+    pub error_kind: Option<RenameError>,
+}
+
+/// Outcome of `RenamePlan::execute`
+#[derive(Debug, Clone, Default)]
+pub struct PlanOutcome {
+    /// Files renamed and kept renamed, as (original path, new path), companions included
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Undo journal batch the renames were recorded in
+    pub batch_id: Option<String>,
+    /// The rename that failed, if the plan didn't run to the end
+    pub failure: Option<PlanFailure>,
+    /// Whether the renames done before the failure were moved back (`OnPlanError::Rollback`)
+    pub rolled_back: bool,
+}
+
+/// A batch of proposed renames checked as a whole, in the order they would run
+/// (see `RenameEngine::plan`). Can be saved as JSON for review, loaded again and run with `execute`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenamePlan {
+    pub renames: Vec<PlannedRename>,
+    /// What to do when a rename fails while executing
+    #[serde(default)]
+    pub on_error: OnPlanError,
+    /// Undo journal executed renames are recorded in (none = not undoable with `undo_batch`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_path: Option<PathBuf>,
+}
+
+/// A rename done while executing a plan, for rolling it back
+struct Done {
+    original_path: PathBuf,
+    new_path: PathBuf,
+    created_dirs: Vec<PathBuf>,
+    companions: Vec<(PathBuf, PathBuf)>,
+}
+
+impl RenamePlan {
+    /// Check the renames proposed in `analyses` against each other and the files on disk
+    /// Deferred files and files without a proposed name are left out
+    pub(crate) fn new(analyses: &[FileAnalysis]) -> Self {
+        let renames: Vec<(PathBuf, PathBuf, &[PathBuf])> = crate::dir_names::rename_order(analyses)
+            .into_iter()
+            .filter(|a| a.deferred.is_none())
+            .filter_map(|a| {
+                let name = a.proposed_name.as_deref()?;
+                // Unusable names are reported when the rename is attempted
+                let target = renamer::target_path(&a.original_path, name, a.destination.as_deref()).ok()?;
+                Some((a.original_path.clone(), target, a.companions.as_slice()))
+            })
+            .collect();
+
+        // Position of each file in the batch, to tell whether it has moved away by a given point
+        let order: HashMap<&Path, usize> = renames
+            .iter()
+            .enumerate()
+            .map(|(index, (original, _, _))| (original.as_path(), index))
+            .collect();
+        let moved_before = |path: &Path, index: usize| order.get(path).is_some_and(|&i| i < index);
+
+        let mut folders: HashMap<PathBuf, HashMap<String, Vec<String>>> = HashMap::new();
+        let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+        let mut claimed_folded: HashMap<(PathBuf, String), PathBuf> = HashMap::new();
+        let mut unwritable: HashMap<PathBuf, Option<PlanConflict>> = HashMap::new();
+
+        let mut planned = Vec::with_capacity(renames.len());
+        for (index, (original, target, companions)) in renames.iter().enumerate() {
+            let mut conflicts = Vec::new();
+            let folder = target.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+            let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let folded = name.to_lowercase();
+
+            if target != original {
+                if !original.exists() {
+                    conflicts.push(PlanConflict::SourceMissing);
+                }
+                // A folder that doesn't exist yet is created in its nearest existing ancestor
+                let source = original.parent().unwrap_or_else(|| Path::new(""));
+                let destination = folder.ancestors().find(|ancestor| ancestor.exists()).filter(|d| *d != source);
+                for checked in std::iter::once(source).chain(destination) {
+                    let conflict = unwritable.entry(checked.to_path_buf()).or_insert_with(|| {
+                        let error = renamer::check_writable(checked).err()?;
+                        let path = checked.to_path_buf();
+                        Some(match RenameError::find(&error) {
+                            Some(RenameError::ReadOnlyVolume { .. }) => PlanConflict::ReadOnlyVolume { path },
+                            _ => PlanConflict::PermissionDenied { path },
+                        })
+                    });
+                    conflicts.extend(conflict.clone());
+                }
+
+                if let Some(other) = claimed.get(target) {
+                    conflicts.push(PlanConflict::SameTarget { other: other.clone() });
+                } else if let Some(other) = claimed_folded.get(&(folder.clone(), folded.clone())) {
+                    conflicts.push(PlanConflict::CaseOnly { other: other.clone() });
+                }
+
+                let existing = folders.entry(folder.clone()).or_insert_with(|| names_by_case(&folder));
+                for existing_name in existing.get(&folded).into_iter().flatten() {
+                    let path = folder.join(existing_name);
+                    if path == *original || moved_before(&path, index) || claimed.contains_key(&path) {
+                        continue;
+                    }
+                    conflicts.push(if *existing_name == name {
+                        PlanConflict::TargetExists
+                    } else {
+                        PlanConflict::CaseOnly { other: path }
+                    });
+                }
+            }
+
+            if !conflicts.iter().any(PlanConflict::is_blocking) {
+                claimed.insert(target.clone(), original.clone());
+                claimed_folded.insert((folder, folded), target.clone());
+            }
+            planned.push(PlannedRename {
+                original_path: original.clone(),
+                new_path: target.clone(),
+                companions: companions.to_vec(),
+                conflicts,
+            });
+        }
+
+        let collided = analyses
+            .iter()
+            .filter(|a| a.deferred.is_none() && a.proposed_name.is_some())
+            .filter(|a| a.collision_strategy == Some(CollisionStrategy::Fail))
+            .count();
+        if collided > 0 {
+            for rename in &mut planned {
+                rename.conflicts.push(PlanConflict::NameCollisions { files: collided });
+            }
+        }
+
+        Self {
+            renames: planned,
+            ..Self::default()
+        }
+    }
+
+    /// Set what `execute` does when a rename fails
+    pub fn on_error(mut self, policy: OnPlanError) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Read a plan saved with `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).with_context(|| format!("Failed to read plan {}", path.display()))?;
+        serde_json::from_str(&data).with_context(|| format!("Invalid plan {}", path.display()))
+    }
+
+    /// Write the plan as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        fs::write(path, data).with_context(|| format!("Failed to write plan {}", path.display()))
+    }
+
+    /// Rename the files, in order, with their companions
+    /// Refuses to start if any rename has a blocking conflict. When a rename fails, the ones
+    /// before it are kept or moved back as `on_error` says; kept renames are recorded in the
+    /// undo journal as one batch. Titles aren't written into metadata, and a name taken on
+    /// disk is never replaced, whatever `on_conflict` says
+    pub fn execute(&self) -> Result<PlanOutcome> {
+        let blocked: Vec<&PlannedRename> = self.blocked().collect();
+        if let Some(first) = blocked.first() {
+            anyhow::bail!(
+                "{} planned rename(s) are blocked, nothing was renamed ({}: {})",
+                blocked.len(),
+                first.original_path.display(),
+                first.blocking_conflict().map(ToString::to_string).unwrap_or_default()
+            );
+        }
+
+        let mut done: Vec<Done> = Vec::new();
+        let mut failure = None;
+        for rename in self.renames.iter().filter(|r| r.new_path != r.original_path) {
+            match execute_one(rename) {
+                Ok(renamed) => done.push(renamed),
+                Err(e) => {
+                    failure = Some(PlanFailure {
+                        original_path: rename.original_path.clone(),
+                        new_path: rename.new_path.clone(),
+                        error: format!("{:#}", e),
+                        error_kind: RenameError::find(&e).cloned(),
+                    });
+                    break;
+                }
+            }
+        }
+
+        if failure.is_some() && self.on_error == OnPlanError::Rollback {
+            for renamed in done.iter().rev() {
+                renamer::undo_moves(&renamed.companions);
+                renamer::undo_moves(&[(renamed.original_path.clone(), renamed.new_path.clone())]);
+                renamer::remove_empty_folders(&renamed.created_dirs);
+            }
+            return Ok(PlanOutcome {
+                failure,
+                rolled_back: true,
+                ..PlanOutcome::default()
+            });
+        }
+
+        let journal = self.journal_path.clone().map(journal::Journal::new);
+        let batch = journal.as_ref().filter(|_| !done.is_empty()).and_then(|journal| {
+            journal
+                .begin()
+                .inspect_err(|e| tracing::warn!("Renames won't be undoable with undo_batch: {}", e))
+                .ok()
+        });
+        let mut renamed = Vec::new();
+        for rename in done {
+            if let Some(batch) = &batch {
+                batch.record(&rename.original_path, &rename.new_path, &rename.created_dirs);
+                for (old_path, moved_to) in &rename.companions {
+                    batch.record(old_path, moved_to, &[]);
+                }
+            }
+            renamed.push((rename.original_path, rename.new_path));
+            renamed.extend(rename.companions);
+        }
+        Ok(PlanOutcome {
+            renamed,
+            batch_id: batch.map(|batch| batch.id),
+            failure,
+            rolled_back: false,
+        })
+    }
+
+    /// Whether any rename has a conflict, blocking or not
+    pub fn has_conflicts(&self) -> bool {
+        self.renames.iter().any(|r| !r.conflicts.is_empty())
+    }
+
+    /// Renames that won't go ahead
+    pub fn blocked(&self) -> impl Iterator<Item = &PlannedRename> {
+        self.renames.iter().filter(|r| r.blocking_conflict().is_some())
+    }
+
+    /// Folders that can't be written to, each with the conflict reporting it and how many
+    /// renames it refuses, in the order the batch first runs into them. One line per
+    /// folder reads better than the same refusal for every file on a read-only card
+    pub fn unwritable_folders(&self) -> Vec<(&PlanConflict, usize)> {
+        let mut folders: Vec<(&PlanConflict, usize)> = Vec::new();
+        let refusals = self.renames.iter().filter_map(|r| r.blocking_conflict());
+        for conflict in refusals.filter(|c| c.unwritable_folder().is_some()) {
+            match folders.iter_mut().find(|(seen, _)| *seen == conflict) {
+                Some((_, renames)) => *renames += 1,
+                None => folders.push((conflict, 1)),
+            }
+        }
+        folders
+    }
+
+    /// The planned rename of the file at `original_path`
+    pub fn get(&self, original_path: &Path) -> Option<&PlannedRename> {
+        self.renames.iter().find(|r| r.original_path == original_path)
+    }
+
+    /// Blocking conflicts by original path, for checking a batch as it is renamed
+    pub(crate) fn blocking_conflicts(&self) -> HashMap<&Path, &PlanConflict> {
+        self.renames
+            .iter()
+            .filter_map(|r| Some((r.original_path.as_path(), r.blocking_conflict()?)))
+            .collect()
+    }
+}
+
+/// Move one planned file and its companions; if a companion can't follow, the file is moved back
+fn execute_one(rename: &PlannedRename) -> Result<Done> {
+    let created_dirs = renamer::move_file(&rename.original_path, &rename.new_path, false)?;
+    let companions = renamer::move_companions(&rename.companions, &rename.original_path, &rename.new_path, false)
+        .inspect_err(|_| {
+            renamer::undo_moves(&[(rename.original_path.clone(), rename.new_path.clone())]);
+            renamer::remove_empty_folders(&created_dirs);
+        })?;
+    Ok(Done {
+        original_path: rename.original_path.clone(),
+        new_path: rename.new_path.clone(),
+        created_dirs,
+        companions,
+    })
+}
+
+/// Names in `folder` keyed by their lowercase form (nothing if it doesn't exist yet)
+fn names_by_case(folder: &Path) -> HashMap<String, Vec<String>> {
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let entries = fs::read_dir(folder).into_iter().flatten().filter_map(|e| e.ok());
+    for name in entries.filter_map(|e| e.file_name().into_string().ok()) {
+        names.entry(name.to_lowercase()).or_default().push(name);
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use tempfile::TempDir;
+
+    fn proposal(dir: &Path, from: &str, to: &str) -> FileAnalysis {
+        let path = dir.join(from);
+        fs::write(&path, from).unwrap();
+        FileAnalysis {
+            proposed_name: Some(to.to_string()),
+            ..crate::pipeline::unanalyzed(&path, FileCategory::Document)
+        }
+    }
+
+    #[test]
+    fn test_plan_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("Invoice.pdf"), "kept").unwrap();
+        fs::write(dir.join("notes.txt"), "kept").unwrap();
+
+        let analyses = vec![
+            proposal(dir, "a.pdf", "Report.pdf"),
+            proposal(dir, "b.pdf", "Report.pdf"),
+            proposal(dir, "c.pdf", "report.pdf"),
+            proposal(dir, "d.pdf", "Invoice.pdf"),
+            proposal(dir, "e.txt", "Notes.txt"),
+            // Chains work when the name is freed first, and case-only renames of a file itself
+            proposal(dir, "f.pdf", "Summary.pdf"),
+            proposal(dir, "g.pdf", "f.pdf"),
+            proposal(dir, "memo.pdf", "Memo.pdf"),
+        ];
+        let plan = RenamePlan::new(&analyses);
+        let conflicts: Vec<&[PlanConflict]> = plan.renames.iter().map(|r| r.conflicts.as_slice()).collect();
+
+        assert!(conflicts[0].is_empty());
+        assert_eq!(conflicts[1], [PlanConflict::SameTarget { other: dir.join("a.pdf") }]);
+        assert_eq!(conflicts[2], [PlanConflict::CaseOnly { other: dir.join("Report.pdf") }]);
+        assert_eq!(conflicts[3], [PlanConflict::TargetExists]);
+        assert_eq!(conflicts[4], [PlanConflict::CaseOnly { other: dir.join("notes.txt") }]);
+        assert!(conflicts[5..].iter().all(|c| c.is_empty()), "{:?}", conflicts);
+
+        assert!(plan.has_conflicts());
+        let blocked: Vec<&Path> = plan.blocked().map(|r| r.original_path.as_path()).collect();
+        assert_eq!(blocked, [dir.join("b.pdf"), dir.join("d.pdf")]);
+    }
+
+    #[test]
+    fn test_failed_collisions_block_the_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let analyses = vec![
+            proposal(dir, "a.pdf", "Report.pdf"),
+            FileAnalysis {
+                collision_suffix: Some(1),
+                collision_strategy: Some(CollisionStrategy::Fail),
+                ..proposal(dir, "b.pdf", "Report_1.pdf")
+            },
+            proposal(dir, "c.pdf", "Summary.pdf"),
+        ];
+        let plan = RenamePlan::new(&analyses);
+        assert_eq!(plan.blocked().count(), 3);
+        assert_eq!(plan.renames[2].conflicts, [PlanConflict::NameCollisions { files: 1 }]);
+    }
+
+    #[test]
+    fn test_missing_files_are_blocked() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let analyses = vec![proposal(dir, "a.pdf", "Report.pdf")];
+        fs::remove_file(dir.join("a.pdf")).unwrap();
+        let plan = RenamePlan::new(&analyses);
+        assert_eq!(plan.renames[0].conflicts, [PlanConflict::SourceMissing]);
+        assert!(plan.execute().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unwritable_folders_are_counted_once() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let locked = dir.join("locked");
+        fs::create_dir(&locked).unwrap();
+        let analyses = vec![
+            proposal(&locked, "a.pdf", "Report.pdf"),
+            proposal(dir, "b.pdf", "Summary.pdf"),
+            proposal(&locked, "c.pdf", "Invoice.pdf"),
+        ];
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+        let plan = RenamePlan::new(&analyses);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let denied = PlanConflict::PermissionDenied { path: locked.clone() };
+        assert_eq!(plan.renames[0].blocking_conflict(), Some(&denied));
+        assert!(plan.renames[1].conflicts.is_empty());
+        assert_eq!(plan.unwritable_folders(), [(&denied, 2)]);
+        assert_eq!(denied.unwritable_folder(), Some(locked.as_path()));
+        assert_eq!(PlanConflict::TargetExists.unwritable_folder(), None);
+    }
+
+    #[test]
+    fn test_plan_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        let analyses = vec![proposal(dir, "a.pdf", "Report.pdf"), proposal(dir, "b.pdf", "Report.pdf")];
+        let path = dir.join("plan.json");
+        RenamePlan::new(&analyses).on_error(OnPlanError::Rollback).save(&path)?;
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(json["on_error"], "rollback");
+        assert_eq!(json["renames"][1]["conflicts"][0]["conflict"], "same_target");
+
+        let loaded = RenamePlan::load(&path)?;
+        assert_eq!(loaded.on_error, OnPlanError::Rollback);
+        assert_eq!(loaded.renames[1].new_path, dir.join("Report.pdf"));
+        assert_eq!(loaded.blocked().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_stops_or_rolls_back() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        let analyses = vec![
+            FileAnalysis {
+                destination: Some(dir.join("2024")),
+                ..proposal(dir, "a.pdf", "Report.pdf")
+            },
+            proposal(dir, "b.pdf", "Summary.pdf"),
+            proposal(dir, "c.pdf", "Memo.pdf"),
+        ];
+        let plan = RenamePlan {
+            journal_path: Some(dir.join("journal.jsonl")),
+            ..RenamePlan::new(&analyses)
+        };
+        // Taken after planning: the second rename fails
+        fs::write(dir.join("Summary.pdf"), "in the way")?;
+
+        let outcome = plan.clone().on_error(OnPlanError::Rollback).execute()?;
+        assert!(outcome.rolled_back);
+        assert!(outcome.renamed.is_empty() && outcome.batch_id.is_none());
+        assert_eq!(outcome.failure.unwrap().error_kind, Some(RenameError::TargetExists { path: dir.join("Summary.pdf") }));
+        assert!(dir.join("a.pdf").exists() && dir.join("b.pdf").exists());
+        assert!(!dir.join("2024").exists());
+
+        let outcome = plan.execute()?;
+        assert!(!outcome.rolled_back);
+        assert_eq!(outcome.renamed, [(dir.join("a.pdf"), dir.join("2024/Report.pdf"))]);
+        assert_eq!(outcome.failure.unwrap().original_path, dir.join("b.pdf"));
+        assert!(dir.join("b.pdf").exists() && dir.join("c.pdf").exists());
+
+        // The kept renames are one undoable batch
+        let report = journal::Journal::new(dir.join("journal.jsonl")).undo(outcome.batch_id.as_deref())?;
+        assert_eq!(report.restored.len(), 1);
+        assert!(dir.join("a.pdf").exists() && !dir.join("2024").exists());
+        Ok(())
+    }
+}
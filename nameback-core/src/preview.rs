@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{detector, extractor, image_ocr, pdf_content, NamebackError};
+
+/// Bytes read to tell text from binary files and to take a text preview from
+const TEXT_PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// An upright picture of a file for showing next to its proposed name, at most `max_side`
+/// pixels on its longest side: the image itself (HEIC and RAW converted first, as for OCR)
+/// or a PDF's first page (needs pdftoppm). None for other files
+pub fn preview_image(path: &Path, max_side: u32) -> Result<Option<DynamicImage>, NamebackError> {
+    let extension = detector::effective_extension(path);
+    let image = if extractor::is_pdf(extension.as_deref()) {
+        pdf_content::pdf_page_to_image(path)?
+    } else if extractor::is_image(extension.as_deref()) {
+        let readable = image_ocr::readable_image(path)?;
+        image_ocr::load_oriented(readable.path())?.0
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(image.thumbnail(max_side, max_side)))
+}
+
+/// The first `max_lines` lines of a file that reads as text (any extension), or None for
+/// binary files
+pub fn preview_text(path: &Path, max_lines: usize) -> Result<Option<String>, NamebackError> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .context("Failed to open file")?
+        .take(TEXT_PREVIEW_BYTES)
+        .read_to_end(&mut bytes)
+        .context("Failed to read file")?;
+    if bytes.is_empty() || bytes.contains(&0) {
+        return Ok(None);
+    }
+    // The cut at TEXT_PREVIEW_BYTES may split a character; anything else invalid is binary
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).map_err(anyhow::Error::from)?,
+        Err(_) => return Ok(None),
+    };
+    let lines: Vec<&str> = text.lines().take(max_lines).collect();
+    Ok(Some(lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_preview_text() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let notes = temp_dir.path().join("notes.log");
+        std::fs::write(&notes, "Meeting notes\nBudget 2024\nActions\nMore")?;
+        assert_eq!(preview_text(&notes, 2)?.as_deref(), Some("Meeting notes\nBudget 2024"));
+
+        let binary = temp_dir.path().join("data.bin");
+        std::fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 1, 2])?;
+        assert_eq!(preview_text(&binary, 2)?, None);
+
+        // A character cut off at the end of the read doesn't make a file binary
+        let long = temp_dir.path().join("long.txt");
+        let mut text = "a".repeat(TEXT_PREVIEW_BYTES as usize - 1);
+        text.push('é');
+        std::fs::write(&long, &text)?;
+        assert_eq!(preview_text(&long, 1)?.map(|t| t.len()), Some(TEXT_PREVIEW_BYTES as usize - 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_image() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let photo = temp_dir.path().join("photo.png");
+        DynamicImage::new_rgb8(400, 200).save(&photo)?;
+
+        let preview = preview_image(&photo, 100)?.expect("png is previewed");
+        assert_eq!((preview.width(), preview.height()), (100, 50));
+        assert!(preview_image(&temp_dir.path().join("notes.txt"), 100)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::{Condvar, Mutex};
+
+/// Counting semaphore capping how many external tools (tesseract, ffmpeg, pdftoppm,
+/// fpcalc, ...) the analysis threads run at once
+pub(crate) struct ProcessLimit {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl ProcessLimit {
+    /// Allow at most `max` concurrent tool runs (at least one)
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            available: Mutex::new(max.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Run `work` once a slot is free, blocking the calling thread until then
+    pub(crate) fn run<T>(&self, work: impl FnOnce() -> T) -> T {
+        let _permit = self.acquire();
+        work()
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= 1;
+        Permit { limit: self }
+    }
+}
+
+/// A taken slot, given back when dropped (also when the tool run panics)
+struct Permit<'a> {
+    limit: &'a ProcessLimit,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.limit.available.lock().unwrap() += 1;
+        self.limit.released.notify_one();
+    }
+}
+
+/// Runs `work` under `limit` if there is one
+pub(crate) fn limited<T>(limit: Option<&ProcessLimit>, work: impl FnOnce() -> T) -> T {
+    match limit {
+        Some(limit) => limit.run(work),
+        None => work(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_limit_caps_concurrent_runs() {
+        let limit = ProcessLimit::new(2);
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+
+        std::thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    limit.run(|| {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                });
+            }
+        });
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(*limit.available.lock().unwrap(), 2);
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A timed stage of the processing pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// File type detection (magic bytes / extension)
+    Detect,
+    /// exiftool metadata extraction
+    Exif,
+    /// Content extraction: OCR of images, video frames and scanned PDFs, PDF/text parsing
+    Ocr,
+    /// Candidate name scoring
+    Score,
+    /// Reverse geocoding of GPS coordinates
+    Geocode,
+    /// Filesystem rename
+    Rename,
+}
+
+impl Stage {
+    /// All stages in pipeline order
+    pub const ALL: [Stage; 6] = [
+        Stage::Detect,
+        Stage::Exif,
+        Stage::Ocr,
+        Stage::Score,
+        Stage::Geocode,
+        Stage::Rename,
+    ];
+
+    /// Short stage name (matches the tracing span name)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Detect => "detect",
+            Stage::Exif => "exif",
+            Stage::Ocr => "ocr",
+            Stage::Score => "score",
+            Stage::Geocode => "geocode",
+            Stage::Rename => "rename",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Stage::Detect => 0,
+            Stage::Exif => 1,
+            Stage::Ocr => 2,
+            Stage::Score => 3,
+            Stage::Geocode => 4,
+            Stage::Rename => 5,
+        }
+    }
+
+    fn span(&self) -> tracing::Span {
+        // Span names must be static, so each stage gets its own macro call
+        match self {
+            Stage::Detect => tracing::info_span!("detect"),
+            Stage::Exif => tracing::info_span!("exif"),
+            Stage::Ocr => tracing::info_span!("ocr"),
+            Stage::Score => tracing::info_span!("score"),
+            Stage::Geocode => tracing::info_span!("geocode"),
+            Stage::Rename => tracing::info_span!("rename"),
+        }
+    }
+}
+
+/// Wall time spent in each stage for a single file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileTimings {
+    durations: [Option<Duration>; Stage::ALL.len()],
+}
+
+impl FileTimings {
+    /// Time spent in a stage (None if the stage did not run for this file)
+    pub fn get(&self, stage: Stage) -> Option<Duration> {
+        self.durations[stage.index()]
+    }
+
+    /// Total time across all stages
+    pub fn total(&self) -> Duration {
+        self.durations.iter().flatten().sum()
+    }
+
+    fn add(&mut self, stage: Stage, elapsed: Duration) {
+        let slot = &mut self.durations[stage.index()];
+        *slot = Some(slot.unwrap_or_default() + elapsed);
+    }
+
+    fn merge(&mut self, other: &FileTimings) {
+        for stage in Stage::ALL {
+            if let Some(elapsed) = other.get(stage) {
+                self.add(stage, elapsed);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.durations.iter().all(Option::is_none)
+    }
+}
+
+/// Aggregate statistics for one stage across all files
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageStats {
+    pub stage: Stage,
+    /// Number of files for which the stage ran
+    pub files: usize,
+    /// Total wall time summed over files
+    pub total: Duration,
+    /// Slowest single file
+    pub max: Duration,
+}
+
+impl StageStats {
+    /// Average time per file that ran this stage
+    pub fn mean(&self) -> Duration {
+        if self.files == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.files as u32
+        }
+    }
+}
+
+/// Per-file, per-stage timing report for a run
+#[derive(Debug, Clone, Default)]
+pub struct ProfileReport {
+    files: HashMap<PathBuf, FileTimings>,
+}
+
+impl ProfileReport {
+    /// Timings for a specific file
+    pub fn file(&self, path: &Path) -> Option<&FileTimings> {
+        self.files.get(path)
+    }
+
+    /// All files with recorded timings
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &FileTimings)> {
+        self.files.iter().map(|(path, timings)| (path.as_path(), timings))
+    }
+
+    /// Whether any timings were recorded
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Aggregate statistics per stage, in pipeline order (stages that never ran are omitted)
+    pub fn stage_stats(&self) -> Vec<StageStats> {
+        Stage::ALL
+            .iter()
+            .filter_map(|&stage| {
+                let durations: Vec<Duration> =
+                    self.files.values().filter_map(|t| t.get(stage)).collect();
+                if durations.is_empty() {
+                    return None;
+                }
+                Some(StageStats {
+                    stage,
+                    files: durations.len(),
+                    total: durations.iter().sum(),
+                    max: durations.iter().copied().max().unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// The `limit` files with the highest total time, slowest first
+    pub fn slowest_files(&self, limit: usize) -> Vec<(&Path, Duration)> {
+        let mut files: Vec<(&Path, Duration)> = self
+            .files
+            .iter()
+            .map(|(path, timings)| (path.as_path(), timings.total()))
+            .collect();
+        files.sort_by_key(|&(_, elapsed)| std::cmp::Reverse(elapsed));
+        files.truncate(limit);
+        files
+    }
+
+    /// Total time across all stages and files
+    pub fn total(&self) -> Duration {
+        self.files.values().map(FileTimings::total).sum()
+    }
+
+    pub(crate) fn record_file(&mut self, path: &Path, timings: FileTimings) {
+        if timings.is_empty() {
+            return;
+        }
+        self.files
+            .entry(path.to_path_buf())
+            .or_default()
+            .merge(&timings);
+    }
+}
+
+thread_local! {
+    // Timings for the file currently being processed on this thread
+    static CURRENT_FILE: RefCell<Option<FileTimings>> = const { RefCell::new(None) };
+}
+
+/// Start collecting stage timings for a file on the current thread
+pub(crate) fn begin_file() {
+    CURRENT_FILE.with(|current| *current.borrow_mut() = Some(FileTimings::default()));
+}
+
+/// Stop collecting and return the timings gathered since `begin_file`
+pub(crate) fn end_file() -> FileTimings {
+    CURRENT_FILE
+        .with(|current| current.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+/// Times a stage and enters its tracing span until dropped
+/// Timings are only recorded between `begin_file` and `end_file`
+pub(crate) struct StageTimer {
+    stage: Stage,
+    start: Instant,
+    _span: tracing::span::EnteredSpan,
+}
+
+impl StageTimer {
+    pub(crate) fn start(stage: Stage) -> Self {
+        Self {
+            stage,
+            start: Instant::now(),
+            _span: stage.span().entered(),
+        }
+    }
+}
+
+impl Drop for StageTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        CURRENT_FILE.with(|current| {
+            if let Some(timings) = current.borrow_mut().as_mut() {
+                timings.add(self.stage, elapsed);
+            }
+        });
+    }
+}
+
+/// Runs `f` as the given stage
+pub(crate) fn time<T>(stage: Stage, f: impl FnOnce() -> T) -> T {
+    let _timer = StageTimer::start(stage);
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_records_only_inside_file() {
+        // Outside begin/end nothing is recorded (and nothing panics)
+        time(Stage::Detect, || {});
+
+        begin_file();
+        time(Stage::Detect, || std::thread::sleep(Duration::from_millis(2)));
+        time(Stage::Exif, || {});
+        let timings = end_file();
+
+        assert!(timings.get(Stage::Detect).unwrap() >= Duration::from_millis(2));
+        assert!(timings.get(Stage::Exif).is_some());
+        assert!(timings.get(Stage::Ocr).is_none());
+        assert_eq!(end_file(), FileTimings::default());
+    }
+
+    #[test]
+    fn test_report_aggregates_stages() {
+        let mut report = ProfileReport::default();
+
+        let mut a = FileTimings::default();
+        a.add(Stage::Exif, Duration::from_millis(10));
+        a.add(Stage::Ocr, Duration::from_millis(100));
+        let mut b = FileTimings::default();
+        b.add(Stage::Exif, Duration::from_millis(30));
+
+        report.record_file(Path::new("a.jpg"), a);
+        report.record_file(Path::new("b.pdf"), b);
+        report.record_file(Path::new("c.txt"), FileTimings::default());
+
+        let stats = report.stage_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].stage, Stage::Exif);
+        assert_eq!(stats[0].files, 2);
+        assert_eq!(stats[0].total, Duration::from_millis(40));
+        assert_eq!(stats[0].max, Duration::from_millis(30));
+        assert_eq!(stats[0].mean(), Duration::from_millis(20));
+        assert_eq!(stats[1].stage, Stage::Ocr);
+
+        let slowest = report.slowest_files(1);
+        assert_eq!(slowest, vec![(Path::new("a.jpg"), Duration::from_millis(110))]);
+        assert!(report.file(Path::new("c.txt")).is_none());
+    }
+
+    #[test]
+    fn test_record_file_merges_phases() {
+        let mut report = ProfileReport::default();
+
+        let mut analysis = FileTimings::default();
+        analysis.add(Stage::Detect, Duration::from_millis(1));
+        let mut rename = FileTimings::default();
+        rename.add(Stage::Rename, Duration::from_millis(2));
+
+        report.record_file(Path::new("a.jpg"), analysis);
+        report.record_file(Path::new("a.jpg"), rename);
+
+        let timings = report.file(Path::new("a.jpg")).unwrap();
+        assert_eq!(timings.total(), Duration::from_millis(3));
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::Stage;
+
+/// Where an analysis run stands (see `AnalysisEvent::Progress`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Files finished so far
+    pub done: usize,
+    /// Files the run will report
+    pub total: usize,
+    /// The file that most recently entered a pipeline stage
+    pub current_file: Option<PathBuf>,
+    /// The stage `current_file` entered, e.g. `Stage::Ocr`
+    pub stage: Option<Stage>,
+    /// Time since analysis started
+    pub elapsed: Duration,
+    /// Estimated time until every file is done (None until the first file is)
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// Share of files done, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+/// Counts finished files and estimates the time left from the rate so far
+pub(crate) struct ProgressTracker {
+    started: Instant,
+    progress: Progress,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(total: usize) -> Self {
+        Self {
+            started: Instant::now(),
+            progress: Progress {
+                done: 0,
+                total,
+                current_file: None,
+                stage: None,
+                elapsed: Duration::ZERO,
+                eta: None,
+            },
+        }
+    }
+
+    /// A file entered `stage`
+    pub(crate) fn stage(&mut self, path: &Path, stage: Stage) -> Progress {
+        self.progress.current_file = Some(path.to_path_buf());
+        self.progress.stage = Some(stage);
+        self.snapshot(self.started.elapsed())
+    }
+
+    /// A file left the pipeline
+    pub(crate) fn finish_file(&mut self) -> Progress {
+        self.progress.done = (self.progress.done + 1).min(self.progress.total);
+        self.snapshot(self.started.elapsed())
+    }
+
+    fn snapshot(&mut self, elapsed: Duration) -> Progress {
+        let Progress { done, total, .. } = self.progress;
+        self.progress.elapsed = elapsed;
+        self.progress.eta = (done > 0).then(|| elapsed.mul_f64((total - done) as f64 / done as f64));
+        self.progress.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_from_rate_so_far() {
+        let mut tracker = ProgressTracker::new(4);
+        let progress = tracker.stage(Path::new("a.jpg"), Stage::Ocr);
+        assert_eq!(progress.current_file.as_deref(), Some(Path::new("a.jpg")));
+        assert_eq!(progress.stage, Some(Stage::Ocr));
+        assert_eq!(progress.eta, None);
+
+        tracker.progress.done = 1;
+        let progress = tracker.snapshot(Duration::from_secs(10));
+        assert_eq!(progress.eta, Some(Duration::from_secs(30)));
+        assert_eq!(progress.fraction(), 0.25);
+
+        tracker.progress.done = 4;
+        assert_eq!(tracker.snapshot(Duration::from_secs(40)).eta, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_done_never_exceeds_total() {
+        let mut tracker = ProgressTracker::new(1);
+        tracker.finish_file();
+        assert_eq!(tracker.finish_file().done, 1);
+        assert_eq!(ProgressTracker::new(0).finish_file().fraction(), 1.0);
+    }
+}
@@ -15,6 +15,12 @@ pub struct RenameOperation {
     pub timestamp: u64,
     /// Whether this operation has been undone
     pub undone: bool,
+    /// Folders created to move the file into (outermost first), removed again on undo if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created_dirs: Vec<PathBuf>,
+    /// Undo journal batch the rename was made in (None for renames recorded without one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
 }
 
 impl RenameOperation {
@@ -28,6 +34,17 @@ impl RenameOperation {
                 .unwrap()
                 .as_secs(),
             undone: false,
+            created_dirs: Vec::new(),
+            batch_id: None,
+        }
+    }
+
+    /// Token identifying this operation, for `RenameHistory::undo_token`
+    pub fn rollback_token(&self) -> RollbackToken {
+        RollbackToken {
+            original_path: self.original_path.clone(),
+            new_path: self.new_path.clone(),
+            timestamp: self.timestamp,
         }
     }
 
@@ -53,11 +70,12 @@ impl RenameOperation {
             );
         }
 
-        // Perform the undo (rename back to original)
-        fs::rename(&self.new_path, &self.original_path)?;
+        // Perform the undo (move back to original, copying if it was moved to another disk)
+        crate::renamer::move_file(&self.new_path, &self.original_path, false)?;
+        crate::renamer::remove_empty_folders(&self.created_dirs);
         self.undone = true;
 
-        log::info!(
+        tracing::info!(
             "Undone rename: {} -> {}",
             self.new_path.display(),
             self.original_path.display()
@@ -67,6 +85,15 @@ impl RenameOperation {
     }
 }
 
+/// Opaque reference to a recorded rename, used to undo that single rename
+/// Serializable so callers can persist it alongside their own state
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RollbackToken {
+    original_path: PathBuf,
+    new_path: PathBuf,
+    timestamp: u64,
+}
+
 /// Rename history tracker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenameHistory {
@@ -130,6 +157,27 @@ impl RenameHistory {
         &self.operations
     }
 
+    /// Operations made together, newest batch first, as indexes into `operations()`
+    /// Renames recorded without a batch id are grouped by the second they were made in
+    pub fn batches(&self) -> Vec<Vec<usize>> {
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        for (index, operation) in self.operations.iter().enumerate() {
+            let same_batch = batches.last().and_then(|batch| batch.last()).is_some_and(|&last| {
+                let previous = &self.operations[last];
+                match (&previous.batch_id, &operation.batch_id) {
+                    (Some(a), Some(b)) => a == b,
+                    (None, None) => previous.timestamp == operation.timestamp,
+                    _ => false,
+                }
+            });
+            match batches.last_mut() {
+                Some(batch) if same_batch => batch.push(index),
+                _ => batches.push(vec![index]),
+            }
+        }
+        batches
+    }
+
     /// Get the most recent operation that can be undone
     pub fn last_undoable(&self) -> Option<&RenameOperation> {
         self.operations.iter().find(|op| !op.undone)
@@ -157,6 +205,22 @@ impl RenameHistory {
         Ok(())
     }
 
+    /// The operation a rollback token refers to
+    pub fn get(&self, token: &RollbackToken) -> Option<&RenameOperation> {
+        self.operations.iter().find(|op| op.rollback_token() == *token)
+    }
+
+    /// Undo the operation a rollback token refers to
+    pub fn undo_token(&mut self, token: &RollbackToken) -> Result<()> {
+        let operation = self
+            .operations
+            .iter_mut()
+            .find(|op| op.rollback_token() == *token)
+            .ok_or_else(|| anyhow::anyhow!("Operation not found in history"))?;
+
+        operation.undo()
+    }
+
     /// Get count of undoable operations
     pub fn undoable_count(&self) -> usize {
         self.operations.iter().filter(|op| !op.undone).count()
@@ -216,6 +280,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_undo_across_filesystems() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let original = temp_dir.path().join("scan.pdf");
+        let new = temp_dir.path().join("Invoices").join("Invoice March.pdf");
+        fs::create_dir(new.parent().unwrap())?;
+        fs::write(&new, "invoice")?;
+
+        crate::renamer::CROSS_DEVICE_RENAMES.with(|cross| cross.set(true));
+        let mut op = RenameOperation::new(original.clone(), new.clone());
+        op.undo()?;
+
+        assert_eq!(fs::read_to_string(&original)?, "invoice");
+        assert!(!new.exists());
+        Ok(())
+    }
+
     #[test]
     fn test_history_add_and_undo() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -305,4 +386,51 @@ mod tests {
             .unwrap()
             .contains("file4"));
     }
+
+    #[test]
+    fn test_undo_token_targets_single_operation() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut history = RenameHistory::new(temp_dir.path().join("history.json"), 10);
+
+        let mut tokens = Vec::new();
+        for i in 0..2 {
+            let orig = temp_dir.path().join(format!("file{}.txt", i));
+            let new = temp_dir.path().join(format!("renamed{}.txt", i));
+            fs::write(&new, "content")?;
+            let op = RenameOperation::new(orig, new);
+            tokens.push(op.rollback_token());
+            history.add(op);
+        }
+
+        // Undo the older rename, leaving the newer one in place
+        history.undo_token(&tokens[0])?;
+        assert!(temp_dir.path().join("file0.txt").exists());
+        assert!(temp_dir.path().join("renamed1.txt").exists());
+        assert_eq!(history.undoable_count(), 1);
+
+        assert!(history.undo_token(&tokens[0]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batches() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut history = RenameHistory::new(temp_dir.path().join("history.json"), 10);
+        let operation = |name: &str, batch_id: Option<&str>, timestamp: u64| RenameOperation {
+            timestamp,
+            batch_id: batch_id.map(str::to_string),
+            ..RenameOperation::new(PathBuf::from(name), PathBuf::from(format!("new_{}", name)))
+        };
+
+        // Oldest first, as renames are recorded
+        history.add(operation("a.txt", None, 100));
+        history.add(operation("b.txt", None, 100));
+        history.add(operation("c.txt", None, 101));
+        history.add(operation("d.txt", Some("20240315-142210"), 200));
+        history.add(operation("d.xmp", Some("20240315-142210"), 201));
+        history.add(operation("e.txt", Some("20240315-142210-2"), 201));
+
+        assert_eq!(history.batches(), vec![vec![0], vec![1, 2], vec![3], vec![4, 5]]);
+    }
 }
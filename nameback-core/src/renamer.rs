@@ -1,114 +1,1096 @@
 use anyhow::{Context, Result};
-use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// Renames a file, either in dry-run mode (preview only) or actual mode
-/// Returns the new path of the file
-pub fn rename_file(old_path: &Path, new_filename: &str, dry_run: bool) -> Result<std::path::PathBuf> {
+use crate::detector::RAW_EXTENSIONS;
+use crate::profiling::Stage;
+use crate::{AnalysisIssue, FileAnalysis};
+
+/// Longest file name (in bytes) common filesystems accept (ext4, APFS, NTFS)
+const MAX_NAME_BYTES: usize = 255;
+
+/// Why a rename failed, for the causes a caller may want to handle (see `RenameResult::error_kind`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// Another file already has the new name
+    TargetExists { path: PathBuf },
+    /// The folder the file is in, or the one it moves to, can't be written to
+    PermissionDenied { path: PathBuf },
+    /// The folder is on a volume mounted read-only (a card, a disc, a snapshot)
+    ReadOnlyVolume { path: PathBuf },
+    /// Moving to another filesystem failed while copying or checking the copy; the original
+    /// file is left as it was
+    CrossDevice { detail: String },
+    /// The new name or path is longer than the filesystem allows
+    PathTooLong { path: PathBuf },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::TargetExists { path } => {
+                write!(f, "{} already exists (not overwritten)", path.display())
+            }
+            RenameError::PermissionDenied { path } => {
+                write!(f, "no permission to write to {}", path.display())
+            }
+            RenameError::ReadOnlyVolume { path } => {
+                write!(f, "{} is on a read-only volume", path.display())
+            }
+            RenameError::CrossDevice { detail } => {
+                write!(f, "could not move to another filesystem: {}", detail)
+            }
+            RenameError::PathTooLong { path } => {
+                write!(f, "path too long for the filesystem: {}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+impl RenameError {
+    /// The cause behind an error from `move_file` and friends, if it is one of these
+    pub(crate) fn find(error: &anyhow::Error) -> Option<&RenameError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<RenameError>())
+    }
+
+    /// Tells apart the io errors worth reporting as one of these; `path` is the file or
+    /// folder the operation was about
+    fn from_io(error: &io::Error, path: &Path) -> Option<Self> {
+        let path = path.to_path_buf();
+        match error.kind() {
+            ErrorKind::AlreadyExists => Some(RenameError::TargetExists { path }),
+            ErrorKind::PermissionDenied => Some(RenameError::PermissionDenied { path }),
+            ErrorKind::ReadOnlyFilesystem => Some(RenameError::ReadOnlyVolume { path }),
+            ErrorKind::InvalidFilename => Some(RenameError::PathTooLong { path }),
+            _ => None,
+        }
+    }
+}
+
+/// What to do when a file's new name is already taken by a file on disk that isn't part of
+/// the batch (names taken within the batch are settled by `CollisionStrategy`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnConflict {
+    /// Leave the file as it is and report the conflict
+    #[default]
+    Skip,
+    /// Add a `_1`, `_2`, … counter until the name is free
+    Suffix,
+    /// Move the existing file to the system trash (recycle bin) once the rename is done; an
+    /// undo takes it back out where the platform allows
+    Trash,
+    /// Delete the existing file once the rename is done; it can't be brought back by an undo
+    Overwrite,
+}
+
+impl fmt::Display for OnConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnConflict::Skip => write!(f, "skip"),
+            OnConflict::Suffix => write!(f, "suffix"),
+            OnConflict::Trash => write!(f, "trash"),
+            OnConflict::Overwrite => write!(f, "overwrite"),
+        }
+    }
+}
+
+impl FromStr for OnConflict {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "skip" => Ok(OnConflict::Skip),
+            "suffix" => Ok(OnConflict::Suffix),
+            "trash" => Ok(OnConflict::Trash),
+            "overwrite" => Ok(OnConflict::Overwrite),
+            _ => anyhow::bail!(
+                "unknown conflict action '{}' (expected skip, suffix, trash or overwrite)",
+                s
+            ),
+        }
+    }
+}
+
+/// Clears the way for moving `old_path` to `new_path` when another file already has that name,
+/// as `action` says (in dry-run mode, only checks). Returns the path to move to, which only
+/// differs from `new_path` for `OnConflict::Suffix`, and for `Trash` and `Overwrite` the
+/// existing file, set aside until the move is done (see `Displaced`)
+/// Folders, and a target that is `old_path` itself (a case-only rename on a case-insensitive
+/// filesystem), are never removed
+pub(crate) fn make_room(
+    old_path: &Path,
+    new_path: &Path,
+    action: OnConflict,
+    dry_run: bool,
+) -> Result<(PathBuf, Option<Displaced>)> {
+    let taken = || RenameError::TargetExists { path: new_path.to_path_buf() };
+    let Ok(existing) = fs::symlink_metadata(new_path) else {
+        return Ok((new_path.to_path_buf(), None));
+    };
+    if new_path == old_path || same_file(old_path, new_path) {
+        return Ok((new_path.to_path_buf(), None));
+    }
+    match action {
+        OnConflict::Skip => Err(taken().into()),
+        OnConflict::Suffix => {
+            let name = new_path.file_name().unwrap_or_default().to_string_lossy();
+            let (stem, extension) = crate::generator::split_extension(&name);
+            let extension = extension.map(|ext| format!(".{}", ext)).unwrap_or_default();
+            let free = (1..)
+                .map(|counter| new_path.with_file_name(format!("{}_{}{}", stem, counter, extension)))
+                .find(|candidate| fs::symlink_metadata(candidate).is_err())
+                .context("No free name left")?;
+            Ok((free, None))
+        }
+        _ if existing.is_dir() => Err(taken().into()),
+        OnConflict::Trash | OnConflict::Overwrite if dry_run => Ok((new_path.to_path_buf(), None)),
+        OnConflict::Trash | OnConflict::Overwrite => {
+            let displaced = Displaced::set_aside(new_path, action)
+                .map_err(|e| anyhow::Error::new(taken()).context(format!("{:#}", e)))?;
+            Ok((new_path.to_path_buf(), Some(displaced)))
+        }
+    }
+}
+
+/// For the hidden folders files are set aside in, unique within the process
+static ASIDE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A file `make_room` moved out of the way of a rename (`OnConflict::Trash` and `Overwrite`)
+/// It waits under its own name in a hidden folder next to where it was, so a failed rename
+/// can put it back, and is only trashed or deleted by `finish` once the rename is done
+#[derive(Debug)]
+pub(crate) struct Displaced {
+    /// Where the file was
+    path: PathBuf,
+    /// Where it waits
+    aside: PathBuf,
+    action: OnConflict,
+}
+
+impl Displaced {
+    fn set_aside(path: &Path, action: OnConflict) -> Result<Self> {
+        let folder = path.with_file_name(format!(
+            ".nameback-replaced-{}-{}",
+            std::process::id(),
+            ASIDE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+        let aside = folder.join(path.file_name().unwrap_or_default());
+        if let Err(e) = fs::rename(path, &aside) {
+            let _ = fs::remove_dir(&folder);
+            return Err(e).with_context(|| format!("Failed to move {} out of the way", path.display()));
+        }
+        Ok(Self { path: path.to_path_buf(), aside, action })
+    }
+
+    /// Put the file back, after the rename it made way for failed
+    pub(crate) fn restore(self) {
+        match fs::rename(&self.aside, &self.path) {
+            Ok(()) => self.remove_folder(),
+            Err(e) => warn!("Could not put {} back (it is at {}): {}", self.path.display(), self.aside.display(), e),
+        }
+    }
+
+    /// Trash or delete the file now that the renamed file has taken its place; `trash` moves
+    /// a file to the trash (`system_trash` outside tests). Returns what became of it, for
+    /// the undo journal; a file that couldn't be trashed or deleted is kept where it waits
+    pub(crate) fn finish(self, trash: &dyn Fn(&Path) -> Result<()>) -> Replaced {
+        let done = match self.action {
+            OnConflict::Trash => trash(&self.aside).map(|()| Replaced::Trashed(self.aside.clone())),
+            _ => fs::remove_file(&self.aside).map(|()| Replaced::Deleted).map_err(Into::into),
+        };
+        match done {
+            Ok(replaced) => {
+                info!("Replaced {} ({})", self.path.display(), replaced);
+                self.remove_folder();
+                replaced
+            }
+            Err(e) => {
+                warn!("Replaced {}, but kept the old file at {}: {:#}", self.path.display(), self.aside.display(), e);
+                Replaced::Kept(self.aside)
+            }
+        }
+    }
+
+    fn remove_folder(&self) {
+        if let Some(folder) = self.aside.parent() {
+            let _ = fs::remove_dir(folder);
+        }
+    }
+}
+
+/// What became of a file replaced by a rename, as the undo journal records it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Replaced {
+    /// Moved to the trash from this path
+    Trashed(PathBuf),
+    /// Deleted (`OnConflict::Overwrite`)
+    Deleted,
+    /// Still at this path, as trashing or deleting it failed
+    Kept(PathBuf),
+}
+
+impl fmt::Display for Replaced {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Replaced::Trashed(_) => write!(f, "moved to the trash"),
+            Replaced::Deleted => write!(f, "deleted"),
+            Replaced::Kept(path) => write!(f, "kept at {}", path.display()),
+        }
+    }
+}
+
+impl Replaced {
+    /// Bring the replaced file back to `path`, once the file that replaced it has been moved
+    /// away; `restore_trashed` takes a file back out of the trash (`system_restore` outside
+    /// tests). Errs with why it can't
+    pub(crate) fn bring_back(&self, path: &Path, restore_trashed: &dyn Fn(&Path) -> Result<()>) -> Result<()> {
+        let aside = match self {
+            Replaced::Deleted => anyhow::bail!("the file it replaced was deleted (on_conflict = overwrite)"),
+            Replaced::Trashed(aside) => {
+                if let Some(folder) = aside.parent() {
+                    fs::create_dir_all(folder)?;
+                }
+                restore_trashed(aside).context("the file it replaced is in the trash")?;
+                aside
+            }
+            Replaced::Kept(aside) => aside,
+        };
+        move_file(aside, path, false)?;
+        if let Some(folder) = aside.parent() {
+            let _ = fs::remove_dir(folder);
+        }
+        Ok(())
+    }
+}
+
+/// Moves `path` to the system trash (recycle bin)
+pub(crate) fn system_trash(path: &Path) -> Result<()> {
+    Ok(trash::delete(path)?)
+}
+
+/// Takes the file most recently trashed from `path` back out of the system trash
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+pub(crate) fn system_restore(path: &Path) -> Result<()> {
+    let item = trash::os_limited::list()?
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .max_by_key(|item| item.time_deleted)
+        .context("not found in the trash")?;
+    Ok(trash::os_limited::restore_all([item])?)
+}
+
+/// Takes the file most recently trashed from `path` back out of the system trash
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+pub(crate) fn system_restore(_path: &Path) -> Result<()> {
+    anyhow::bail!("put it back from the Trash by hand")
+}
+
+/// Whether two paths name the same file, e.g. differently cased names on a case-insensitive
+/// filesystem. Errs on the side of "same" when names only differ in case
+fn same_file(a: &Path, b: &Path) -> bool {
+    let same_name = a.parent() == b.parent()
+        && a.file_name().map(|name| name.to_string_lossy().to_lowercase())
+            == b.file_name().map(|name| name.to_string_lossy().to_lowercase());
+    same_name || same_inode(a, b)
+}
+
+#[cfg(unix)]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Where `old_path` ends up as `new_filename`, in `destination` or else its own folder
+pub(crate) fn target_path(
+    old_path: &Path,
+    new_filename: &str,
+    destination: Option<&Path>,
+) -> Result<PathBuf> {
     let parent = old_path.parent().context("File has no parent directory")?;
 
-    let new_path = parent.join(new_filename);
+    // Names from folder templates may include subfolders, but never leave the parent
+    let relative = Path::new(new_filename);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        anyhow::bail!("Refusing to rename outside the file's folder: {}", new_filename);
+    }
+    Ok(destination.unwrap_or(parent).join(relative))
+}
+
+/// Moves a file to `new_path`, creating missing folders (or in dry-run mode, only checks)
+/// Returns the folders it created, outermost first, so an undo can remove them again
+/// Common failures carry a `RenameError` (see `RenameError::find`)
+pub(crate) fn move_file(old_path: &Path, new_path: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let parent = old_path.parent().context("File has no parent directory")?;
 
     // Check if source file exists
     if !old_path.exists() {
         anyhow::bail!("Source file does not exist: {}", old_path.display());
     }
 
-    // Check if destination file already exists (prevent overwrite)
-    if new_path.exists() && new_path != old_path {
-        anyhow::bail!(
-            "Destination file already exists: {}. Skipping to prevent data loss.",
-            new_path.display()
-        );
+    // Check if destination file already exists (prevent overwrite); a case-only rename on a
+    // case-insensitive filesystem finds the file itself there
+    let case_only = new_path.to_string_lossy().to_lowercase() == old_path.to_string_lossy().to_lowercase();
+    if new_path.exists() && new_path != old_path && !(case_only && same_inode(old_path, new_path)) {
+        return Err(RenameError::TargetExists { path: new_path.to_path_buf() }.into());
     }
 
-    // Check write permissions on parent directory
-    if !dry_run {
-        let metadata =
-            fs::metadata(parent).context("Failed to check parent directory permissions")?;
+    if new_path
+        .file_name()
+        .is_some_and(|name| name.as_encoded_bytes().len() > MAX_NAME_BYTES)
+    {
+        return Err(RenameError::PathTooLong { path: new_path.to_path_buf() }.into());
+    }
 
-        if metadata.permissions().readonly() {
-            anyhow::bail!("No write permission for directory: {}", parent.display());
+    // Both the folder the file leaves and the one it lands in must be writable
+    if !dry_run {
+        check_writable(parent)?;
+        if let Some(folder) = new_path.parent().filter(|folder| *folder != parent) {
+            // A folder that doesn't exist yet is created in its nearest existing ancestor
+            if let Some(existing) = folder.ancestors().find(|ancestor| ancestor.exists()) {
+                check_writable(existing)?;
+            }
         }
     }
 
-    // Perform rename or log dry-run
+    // Perform rename or log dry-run (`RunEvent::RenamePerformed` reports the file itself)
     if dry_run {
-        info!("[DRY RUN] {} -> {}", old_path.display(), new_filename);
-    } else {
-        fs::rename(old_path, &new_path).context(format!(
+        debug!("[DRY RUN] {} -> {}", old_path.display(), new_path.display());
+        return Ok(Vec::new());
+    }
+
+    let mut created = Vec::new();
+    if let Some(folder) = new_path.parent().filter(|folder| *folder != parent) {
+        created = folder
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect();
+        created.reverse();
+        fs::create_dir_all(folder)
+            .with_context(|| format!("Failed to create folder {}", folder.display()))?;
+    }
+
+    let moved = match rename(old_path, new_path) {
+        // Moving to another disk: copy, check the copy, then remove the original
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => move_across(old_path, new_path),
+        Err(e) => Err(match RenameError::from_io(&e, new_path) {
+            Some(cause) => anyhow::Error::new(cause).context(e),
+            None => e.into(),
+        }),
+        Ok(()) => Ok(()),
+    };
+    if let Err(e) = moved {
+        remove_empty_folders(&created);
+        return Err(e).context(format!(
             "Failed to rename {} to {}",
             old_path.display(),
             new_path.display()
-        ))?;
+        ));
+    }
+
+    debug!("Renamed: {} -> {}", old_path.display(), new_path.display());
+    Ok(created)
+}
 
-        info!("Renamed: {} -> {}", old_path.display(), new_filename);
+/// `fs::rename`; tests can make it fail as if the two paths were on different filesystems
+/// (see `CROSS_DEVICE_RENAMES`)
+fn rename(old_path: &Path, new_path: &Path) -> io::Result<()> {
+    #[cfg(test)]
+    if CROSS_DEVICE_RENAMES.with(std::cell::Cell::get) {
+        return Err(io::Error::from(ErrorKind::CrossesDevices));
     }
+    fs::rename(old_path, new_path)
+}
 
-    Ok(new_path)
+#[cfg(test)]
+thread_local! {
+    /// Set in a test to have every `move_file` on its thread take the cross-filesystem path
+    pub(crate) static CROSS_DEVICE_RENAMES: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
 }
 
-/// Processes a single file: detects type, extracts metadata, generates name, and renames
-#[allow(dead_code)]
-pub fn process_file(
-    file_path: &Path,
-    dry_run: bool,
-    existing_names: &mut std::collections::HashSet<String>,
-) -> Result<()> {
-    use crate::detector;
-    use crate::extractor;
-    use crate::generator;
-
-    // Detect file type
-    let file_category =
-        detector::detect_file_type(file_path).context("Failed to detect file type")?;
-
-    // Skip unknown file types
-    if file_category == detector::FileCategory::Unknown {
-        warn!("Skipping unknown file type: {}", file_path.display());
-        return Ok(());
-    }
-
-    // Extract metadata (using default config for this legacy function)
-    let config = crate::RenameConfig::default();
-    let metadata = match extractor::extract_metadata(file_path, &config) {
-        Ok(m) => m,
+/// Fails with `RenameError::PermissionDenied` if entries can't be added to or removed from `folder`,
+/// or `RenameError::ReadOnlyVolume` if nothing on its volume can be
+pub(crate) fn check_writable(folder: &Path) -> Result<()> {
+    let metadata = fs::metadata(folder)
+        .with_context(|| format!("Failed to check permissions of {}", folder.display()))?;
+    if read_only_volume(folder) {
+        return Err(RenameError::ReadOnlyVolume { path: folder.to_path_buf() }.into());
+    }
+    if metadata.permissions().readonly() || !writable(folder) {
+        return Err(RenameError::PermissionDenied { path: folder.to_path_buf() }.into());
+    }
+    Ok(())
+}
+
+/// Whether this process may create and remove entries in `folder` (checks the mode bits,
+/// the owner and read-only mounts, not just whether any write bit is set)
+#[cfg(unix)]
+fn writable(folder: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(folder.as_os_str().as_bytes()) else {
+        return true;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the duration of the call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn writable(_folder: &Path) -> bool {
+    true
+}
+
+/// Whether `folder` is on a filesystem mounted read-only
+#[cfg(unix)]
+fn read_only_volume(folder: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(folder.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stats` is only read after statvfs filled it in
+    unsafe {
+        libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) == 0
+            && stats.assume_init().f_flag & libc::ST_RDONLY != 0
+    }
+}
+
+#[cfg(not(unix))]
+fn read_only_volume(_folder: &Path) -> bool {
+    false
+}
+
+/// Moves a file to another filesystem: copies it with its permissions, modification time and
+/// (on Unix, where the filesystems allow) owner, extended attributes and ACLs, checks the
+/// copy's content against the original, then removes the original
+/// If any step fails the copy is removed again and the original stays where it was
+fn move_across(old_path: &Path, new_path: &Path) -> Result<()> {
+    let cross_device = |detail: String| anyhow::Error::new(RenameError::CrossDevice { detail });
+
+    let metadata = fs::metadata(old_path)?;
+    let mut source = fs::File::open(old_path)?;
+    // `create_new` so a file that appeared since the checks is never overwritten
+    let mut target = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(new_path)
+        .map_err(|e| match RenameError::from_io(&e, new_path) {
+            Some(cause) => anyhow::Error::new(cause).context(e),
+            None => cross_device(e.to_string()),
+        })?;
+
+    let copied = (|| {
+        io::copy(&mut source, &mut target).map_err(|e| cross_device(format!("copy failed: {}", e)))?;
+        copy_ownership(&metadata, &target);
+        copy_xattrs(old_path, &target);
+        target.set_permissions(metadata.permissions())?;
+        if let Ok(modified) = metadata.modified() {
+            target.set_modified(modified)?;
+        }
+        target.sync_all()?;
+        if crate::duplicates::hash(old_path)? != crate::duplicates::hash(new_path)? {
+            return Err(cross_device("the copy doesn't match the original".to_string()));
+        }
+        fs::remove_file(old_path).map_err(|e| match RenameError::from_io(&e, old_path) {
+            Some(cause) => anyhow::Error::new(cause).context(e),
+            None => cross_device(format!("could not remove the original: {}", e)),
+        })
+    })();
+    if copied.is_err() {
+        drop(target);
+        let _ = fs::remove_file(new_path);
+    }
+    copied
+}
+
+/// Gives the copy the original's owner and group where this process may (usually only as
+/// root; otherwise the copy belongs to whoever runs nameback)
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, target: &fs::File) {
+    use std::os::unix::fs::MetadataExt;
+
+    if let Err(e) = std::os::unix::fs::fchown(target, Some(metadata.uid()), Some(metadata.gid())) {
+        debug!("Keeping the copy's owner: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _target: &fs::File) {}
+
+/// Copies the original's extended attributes to the copy. POSIX ACLs are stored as
+/// `system.posix_acl_*` attributes on Linux, so they come along. Attributes the target
+/// filesystem doesn't support are left behind with a warning
+#[cfg(unix)]
+fn copy_xattrs(old_path: &Path, target: &fs::File) {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+
+    let Ok(path) = std::ffi::CString::new(old_path.as_os_str().as_bytes()) else {
+        return;
+    };
+    let names = match xattr::list(&path) {
+        Ok(names) => names,
         Err(e) => {
+            debug!("Not copying extended attributes: {}", e);
+            return;
+        }
+    };
+    for name in names.split(|&byte| byte == 0).filter(|name| !name.is_empty()) {
+        let Ok(name) = std::ffi::CString::new(name) else {
+            continue;
+        };
+        let copied = xattr::get(&path, &name).and_then(|value| xattr::set(target.as_raw_fd(), &name, &value));
+        if let Err(e) = copied {
             warn!(
-                "Failed to extract metadata from {}: {}. Skipping.",
-                file_path.display(),
+                "Extended attribute {} of {} was not kept: {}",
+                name.to_string_lossy(),
+                old_path.display(),
                 e
             );
-            return Ok(());
         }
-    };
+    }
+}
 
-    // Extract candidate name from metadata (now with intelligent scoring)
-    let candidate_name = match metadata.extract_name(&file_category, file_path) {
-        Some(name) => name,
-        None => {
-            warn!(
-                "No suitable metadata found for renaming: {}. Skipping.",
-                file_path.display()
-            );
-            return Ok(());
+#[cfg(not(unix))]
+fn copy_xattrs(_old_path: &Path, _target: &fs::File) {}
+
+/// The xattr calls, whose signatures differ between Linux and macOS
+#[cfg(unix)]
+mod xattr {
+    use std::ffi::CStr;
+    use std::io;
+
+    /// Calls `call` with a buffer of the size it asks for when given none
+    fn read(call: impl Fn(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        let size = call(buffer.as_mut_ptr().cast(), buffer.len());
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buffer.truncate(size as usize);
+        Ok(buffer)
+    }
+
+    /// NUL-separated names of the attributes of `path`
+    pub(super) fn list(path: &CStr) -> io::Result<Vec<u8>> {
+        // SAFETY: `path` is NUL-terminated and the buffer is `size` bytes long
+        #[cfg(target_os = "macos")]
+        return read(|buffer, size| unsafe { libc::listxattr(path.as_ptr(), buffer.cast(), size, 0) });
+        #[cfg(not(target_os = "macos"))]
+        return read(|buffer, size| unsafe { libc::listxattr(path.as_ptr(), buffer.cast(), size) });
+    }
+
+    pub(super) fn get(path: &CStr, name: &CStr) -> io::Result<Vec<u8>> {
+        // SAFETY: as in `list`
+        #[cfg(target_os = "macos")]
+        return read(|buffer, size| unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buffer, size, 0, 0) });
+        #[cfg(not(target_os = "macos"))]
+        return read(|buffer, size| unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buffer, size) });
+    }
+
+    pub(super) fn set(fd: libc::c_int, name: &CStr, value: &[u8]) -> io::Result<()> {
+        // SAFETY: `name` is NUL-terminated and `value` is valid for its length
+        #[cfg(target_os = "macos")]
+        let result = unsafe { libc::fsetxattr(fd, name.as_ptr(), value.as_ptr().cast(), value.len(), 0, 0) };
+        #[cfg(not(target_os = "macos"))]
+        let result = unsafe { libc::fsetxattr(fd, name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
         }
+        Ok(())
+    }
+}
+
+/// Removes `folders` (outermost first) that are empty, innermost first
+/// Used to tidy up after an undone or failed move
+pub(crate) fn remove_empty_folders(folders: &[PathBuf]) {
+    for folder in folders.iter().rev() {
+        // Fails harmlessly if something else was put there since
+        let _ = fs::remove_dir(folder);
+    }
+}
+
+/// Files that only make sense next to the file they are named after: edit settings,
+/// iOS adjustments, subtitles and video thumbnails
+const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "aae", "srt", "vtt", "thm"];
+
+/// A JPEG or HEIC with the same name as a camera RAW file is the same shot
+const RAW_PAIR_EXTENSIONS: &[&str] = &["jpg", "jpeg", "heic", "heif"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_lowercase().as_str()))
+}
+
+/// Name without its last extension
+fn stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Files among `names_in_folder` that belong with `path`: sidecars named after it
+/// (`IMG_1234.xmp` or `IMG_1234.CR2.xmp`) and the other half of a RAW+JPEG pair.
+/// Names are compared ignoring case, as cameras and phones mix `.JPG` and `.xmp`
+pub(crate) fn companions(path: &Path, names_in_folder: &[String]) -> Vec<PathBuf> {
+    let (Some(folder), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy().to_lowercase();
+    let stem = stem(path).to_lowercase();
+    let pair_extensions = if has_extension(path, RAW_EXTENSIONS) {
+        RAW_PAIR_EXTENSIONS
+    } else if has_extension(path, RAW_PAIR_EXTENSIONS) {
+        RAW_EXTENSIONS
+    } else {
+        &[]
     };
 
-    // Get original extension
-    let extension = file_path.extension();
+    let mut found: Vec<PathBuf> = names_in_folder
+        .iter()
+        .map(|other| folder.join(other))
+        .filter(|other| other.as_path() != path)
+        .filter(|other| {
+            let other_stem = self::stem(other).to_lowercase();
+            let named_after = other_stem == stem || other_stem == name;
+            (named_after && has_extension(other, SIDECAR_EXTENSIONS))
+                || (other_stem == stem && has_extension(other, pair_extensions))
+        })
+        .collect();
+    found.sort();
+    found
+}
 
-    // Generate sanitized, unique filename with metadata enhancements
-    let new_filename = generator::generate_filename_with_metadata(
-        &candidate_name,
-        extension,
-        existing_names,
-        Some(&metadata),
-    );
+/// Name for `companion` once `primary` is called `new_name`: `IMG_1234.xmp` → `Sunset.xmp`,
+/// `IMG_1234.CR2.xmp` → `Sunset.CR2.xmp`
+pub(crate) fn companion_name(primary: &Path, new_name: &str, companion: &Path) -> String {
+    let companion_name = companion.file_name().unwrap_or_default().to_string_lossy();
+    let primary_name = primary.file_name().unwrap_or_default().to_string_lossy();
+    let new_path = Path::new(new_name);
+    // Everything after the part named after the primary (`.xmp`, or `.CR2.xmp` → `.xmp`)
+    let (base, rest) = if stem(companion).eq_ignore_ascii_case(&primary_name) {
+        (new_name.to_string(), companion_name.get(primary_name.len()..))
+    } else {
+        (stem(new_path), companion_name.get(stem(primary).len()..))
+    };
+    format!("{}{}", base, rest.unwrap_or_default())
+}
 
-    // Rename the file
-    let _new_path = rename_file(file_path, &new_filename, dry_run)?;
+/// Move `companions` of a file just moved from `original` to `new_path` after it
+/// Returns the (old, new) paths. If one fails, the ones already moved are moved back
+pub(crate) fn move_companions(
+    companions: &[PathBuf],
+    original: &Path,
+    new_path: &Path,
+    dry_run: bool,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let new_name = new_path.file_name().unwrap_or_default().to_string_lossy();
+    let folder = new_path.parent().context("File has no parent directory")?;
 
-    Ok(())
+    let mut moved = Vec::new();
+    for companion in companions {
+        let target = folder.join(companion_name(original, &new_name, companion));
+        if let Err(e) = move_file(companion, &target, dry_run) {
+            if !dry_run {
+                undo_moves(&moved);
+            }
+            return Err(e).context(format!("Failed to rename {} with it", companion.display()));
+        }
+        moved.push((companion.clone(), target));
+    }
+    Ok(moved)
+}
+
+/// Move files back after part of a group failed to move, last first
+pub(crate) fn undo_moves(moved: &[(PathBuf, PathBuf)]) {
+    for (old_path, new_path) in moved.iter().rev() {
+        if let Err(e) = move_file(new_path, old_path, false) {
+            warn!("Could not move {} back: {:#}", new_path.display(), e);
+        }
+    }
+}
+
+/// Rank in which files get to claim companions: RAW files keep their JPEGs, and a
+/// sidecar is only renamed on its own if nothing else claims it
+fn claim_rank(path: &Path) -> u8 {
+    if has_extension(path, RAW_EXTENSIONS) {
+        0
+    } else if has_extension(path, SIDECAR_EXTENSIONS) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Find the companions of each file with a proposed name and set `FileAnalysis::companions`
+/// Companions that were analyzed themselves get no name of their own, so a group is renamed
+/// as one unit under its main file's new name. Returns the indices of analyses that changed
+pub(crate) fn attach_companions(analyses: &mut [FileAnalysis]) -> Vec<usize> {
+    let index_of: HashMap<PathBuf, usize> = analyses
+        .iter()
+        .enumerate()
+        .map(|(index, analysis)| (analysis.original_path.clone(), index))
+        .collect();
+    let mut order: Vec<usize> = (0..analyses.len())
+        .filter(|&index| analyses[index].deferred.is_none() && analyses[index].proposed_name.is_some())
+        .collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&analyses[a].original_path, &analyses[b].original_path);
+        claim_rank(a).cmp(&claim_rank(b)).then_with(|| a.cmp(b))
+    });
+
+    let mut listings: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+    let mut changed = Vec::new();
+    for index in order {
+        let path = analyses[index].original_path.clone();
+        if !claimed.insert(path.clone()) {
+            continue;
+        }
+        let folder = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let names = listings.entry(folder.clone()).or_insert_with(|| file_names(&folder));
+        let found: Vec<PathBuf> = companions(&path, names)
+            .into_iter()
+            .filter(|companion| claimed.insert(companion.clone()))
+            .collect();
+        if found.is_empty() {
+            continue;
+        }
+
+        let primary_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        for companion in &found {
+            if let Some(&other) = index_of.get(companion) {
+                let analysis = &mut analyses[other];
+                analysis.proposed_name = None;
+                analysis.title = None;
+                analysis.collision_suffix = None;
+                analysis.destination = None;
+                analysis.errors.push(AnalysisIssue::fatal(
+                    Stage::Score,
+                    format!("Renamed along with {}", primary_name),
+                ));
+                changed.push(other);
+            }
+        }
+        info!("{} is renamed along with {} companion files", primary_name, found.len());
+        analyses[index].companions = found;
+        changed.push(index);
+    }
+    changed.sort_unstable();
+    changed
+}
+
+/// Names of the files (not folders) in `folder`
+fn file_names(folder: &Path) -> Vec<String> {
+    fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use tempfile::TempDir;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_companions_and_their_names() {
+        let folder = Path::new("/photos");
+        let listing = names(&[
+            "IMG_1234.CR2",
+            "IMG_1234.JPG",
+            "IMG_1234.xmp",
+            "IMG_1234.CR2.xmp",
+            "IMG_1234.mov",
+            "IMG_12345.xmp",
+            "IMG_5678.HEIC",
+            "IMG_5678.aae",
+        ]);
+
+        let raw = folder.join("IMG_1234.CR2");
+        assert_eq!(
+            companions(&raw, &listing),
+            [
+                folder.join("IMG_1234.CR2.xmp"),
+                folder.join("IMG_1234.JPG"),
+                folder.join("IMG_1234.xmp"),
+            ]
+        );
+        // The JPEG pairs with the RAW; an unrelated video with the same name doesn't
+        let jpeg = folder.join("IMG_1234.JPG");
+        assert!(companions(&jpeg, &listing).contains(&raw));
+        assert!(!companions(&jpeg, &listing).contains(&folder.join("IMG_1234.mov")));
+        assert_eq!(companions(&folder.join("IMG_5678.HEIC"), &listing), [folder.join("IMG_5678.aae")]);
+
+        assert_eq!(companion_name(&raw, "Sunset.cr2", &folder.join("IMG_1234.xmp")), "Sunset.xmp");
+        assert_eq!(companion_name(&raw, "Sunset.cr2", &folder.join("IMG_1234.CR2.xmp")), "Sunset.cr2.xmp");
+        assert_eq!(companion_name(&raw, "Sunset.cr2", &jpeg), "Sunset.JPG");
+    }
+
+    #[test]
+    fn test_attach_companions_claims_analyzed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let analyses: Vec<FileAnalysis> = [
+            ("IMG_1.JPG", Some("Beach.jpg")),
+            ("IMG_1.NEF", Some("Harbor.nef")),
+            ("IMG_1.xmp", None),
+            ("clip.mp4", Some("Party.mp4")),
+            ("clip.srt", Some("Subtitles.srt")),
+        ]
+        .iter()
+        .map(|(name, proposed)| {
+            let path = dir.join(name);
+            fs::write(&path, name).unwrap();
+            FileAnalysis {
+                proposed_name: proposed.map(str::to_string),
+                ..crate::pipeline::unanalyzed(&path, FileCategory::Image)
+            }
+        })
+        .collect();
+
+        let mut attached = analyses.clone();
+        assert_eq!(attach_companions(&mut attached), [0, 1, 2, 3, 4]);
+        // The RAW file claims its JPEG even though the JPEG comes first
+        assert_eq!(attached[1].companions, [dir.join("IMG_1.JPG"), dir.join("IMG_1.xmp")]);
+        assert!(attached[0].proposed_name.is_none());
+        assert!(attached[0].errors[0].message.contains("IMG_1.NEF"));
+        assert_eq!(attached[3].companions, [dir.join("clip.srt")]);
+        assert!(attached[4].proposed_name.is_none());
+    }
+
+    #[test]
+    fn test_move_companions_rolls_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let sidecars = [dir.join("IMG_1.aae"), dir.join("IMG_1.xmp")];
+        for path in &sidecars {
+            fs::write(path, "sidecar").unwrap();
+        }
+        let original = dir.join("IMG_1.heic");
+
+        // The second sidecar's new name is taken, so the first is moved back
+        fs::write(dir.join("Sunset.xmp"), "taken").unwrap();
+        assert!(move_companions(&sidecars, &original, &dir.join("Sunset.heic"), false).is_err());
+        assert!(sidecars.iter().all(|path| path.exists()));
+        assert!(!dir.join("Sunset.aae").exists());
+
+        let moved = move_companions(&sidecars, &original, &dir.join("Harbor.heic"), false).unwrap();
+        assert_eq!(moved[1], (sidecars[1].clone(), dir.join("Harbor.xmp")));
+        assert!(dir.join("Harbor.aae").exists() && dir.join("Harbor.xmp").exists());
+    }
+
+    #[test]
+    fn test_move_file_error_kinds() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let original = dir.join("scan.pdf");
+        fs::write(&original, "scan").unwrap();
+        fs::write(dir.join("Lease.pdf"), "taken").unwrap();
+
+        let err = move_file(&original, &dir.join("Lease.pdf"), false).unwrap_err();
+        assert_eq!(
+            RenameError::find(&err),
+            Some(&RenameError::TargetExists { path: dir.join("Lease.pdf") })
+        );
+
+        let long = dir.join(format!("{}.pdf", "a".repeat(MAX_NAME_BYTES)));
+        let err = move_file(&original, &long, true).unwrap_err();
+        assert_eq!(RenameError::find(&err), Some(&RenameError::PathTooLong { path: long }));
+
+        let denied = io::Error::from(ErrorKind::PermissionDenied);
+        assert_eq!(
+            RenameError::from_io(&denied, dir),
+            Some(RenameError::PermissionDenied { path: dir.to_path_buf() })
+        );
+        assert_eq!(RenameError::from_io(&io::Error::from(ErrorKind::Interrupted), dir), None);
+        assert!(original.exists());
+    }
+
+    #[test]
+    fn test_make_room_for_existing_target() {
+        let no_trash = |path: &Path| -> Result<()> { panic!("{} moved to the trash", path.display()) };
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let original = dir.join("scan.pdf");
+        let target = dir.join("Lease.pdf");
+        fs::write(&original, "scan").unwrap();
+        fs::write(&target, "taken").unwrap();
+        fs::write(dir.join("Lease_1.pdf"), "taken too").unwrap();
+        let entries = || fs::read_dir(dir).unwrap().count();
+
+        let err = make_room(&original, &target, OnConflict::Skip, false).unwrap_err();
+        assert!(matches!(RenameError::find(&err), Some(RenameError::TargetExists { .. })));
+        assert_eq!(make_room(&original, &target, OnConflict::Suffix, false).unwrap().0, dir.join("Lease_2.pdf"));
+
+        // Dry runs leave the existing file alone
+        for action in [OnConflict::Overwrite, OnConflict::Trash] {
+            let (path, displaced) = make_room(&original, &target, action, true).unwrap();
+            assert_eq!(path, target);
+            assert!(displaced.is_none());
+        }
+        assert!(target.exists());
+
+        // The existing file is only set aside, and put back if the move fails
+        let (_, displaced) = make_room(&original, &target, OnConflict::Overwrite, false).unwrap();
+        assert!(!target.exists());
+        displaced.unwrap().restore();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "taken");
+        assert_eq!(entries(), 3);
+
+        // Once the move is done, it is deleted
+        let (_, displaced) = make_room(&original, &target, OnConflict::Overwrite, false).unwrap();
+        move_file(&original, &target, false).unwrap();
+        assert_eq!(displaced.unwrap().finish(&no_trash), Replaced::Deleted);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "scan");
+        assert_eq!(entries(), 2);
+
+        // A free name, the file itself under another case, and folders are never touched
+        let other = dir.join("Deed.pdf");
+        assert_eq!(make_room(&target, &other, OnConflict::Overwrite, false).unwrap().0, other);
+        fs::write(dir.join("lease.PDF"), "case").unwrap();
+        let (path, displaced) = make_room(&target, &dir.join("lease.PDF"), OnConflict::Overwrite, false).unwrap();
+        assert_eq!(path, dir.join("lease.PDF"));
+        assert!(displaced.is_none() && dir.join("lease.PDF").exists());
+        fs::create_dir(dir.join("Scans")).unwrap();
+        assert!(make_room(&target, &dir.join("Scans"), OnConflict::Overwrite, false).is_err());
+        assert!(target.exists() && dir.join("Scans").is_dir());
+
+        assert_eq!("Trash".parse::<OnConflict>().unwrap(), OnConflict::Trash);
+        assert_eq!(OnConflict::Suffix.to_string(), "suffix");
+        assert!("replace".parse::<OnConflict>().is_err());
+    }
+
+    #[test]
+    fn test_make_room_moves_existing_target_to_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let original = dir.join("IMG_1.jpg");
+        let target = dir.join("Harbor.jpg");
+        fs::write(&original, "new").unwrap();
+        fs::write(&target, "old").unwrap();
+
+        // A trash folder of the test's own instead of the machine's
+        let trash_dir = dir.join("Trash");
+        fs::create_dir(&trash_dir).unwrap();
+        let trashed = std::cell::RefCell::new(Vec::new());
+        let fake_trash = |path: &Path| -> Result<()> {
+            trashed.borrow_mut().push(path.to_path_buf());
+            Ok(fs::rename(path, trash_dir.join(path.file_name().unwrap()))?)
+        };
+        let fake_restore = |path: &Path| -> Result<()> {
+            Ok(fs::rename(trash_dir.join(path.file_name().unwrap()), path)?)
+        };
+
+        // Nothing is trashed until the file has taken its place, and it goes under its own name
+        let (path, displaced) = make_room(&original, &target, OnConflict::Trash, false).unwrap();
+        assert_eq!(path, target);
+        assert!(trashed.borrow().is_empty());
+        move_file(&original, &target, false).unwrap();
+        let replaced = displaced.unwrap().finish(&fake_trash);
+        assert!(matches!(&replaced, Replaced::Trashed(from) if from.file_name() == target.file_name()));
+        assert_eq!(fs::read_to_string(trash_dir.join("Harbor.jpg")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+
+        // An undo takes it back out of the trash, once the new file has moved away
+        move_file(&target, &original, false).unwrap();
+        replaced.bring_back(&target, &fake_restore).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert!(fs::read_dir(dir).unwrap().all(|entry| !entry.unwrap().file_name().to_string_lossy().starts_with('.')));
+
+        // Without a working trash the existing file is kept aside rather than lost
+        let (_, displaced) = make_room(&original, &target, OnConflict::Trash, false).unwrap();
+        move_file(&original, &target, false).unwrap();
+        let broken_trash = |_: &Path| -> Result<()> { anyhow::bail!("no trash on this volume") };
+        let Replaced::Kept(kept) = displaced.unwrap().finish(&broken_trash) else {
+            panic!("not kept");
+        };
+        assert_eq!(fs::read_to_string(&kept).unwrap(), "old");
+        assert!(Replaced::Deleted.bring_back(&original, &fake_restore).is_err());
+
+        // Folders are never trashed
+        fs::create_dir(dir.join("Scans")).unwrap();
+        assert!(make_room(&target, &dir.join("Scans"), OnConflict::Trash, false).is_err());
+        assert_eq!(trashed.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_move_across_keeps_content_and_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let original = dir.join("clip.mov");
+        fs::write(&original, vec![7u8; 100_000]).unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        fs::File::options().write(true).open(&original).unwrap().set_modified(modified).unwrap();
+
+        let moved = dir.join("Harbor.mov");
+        move_across(&original, &moved).unwrap();
+        assert!(!original.exists());
+        assert_eq!(fs::read(&moved).unwrap(), vec![7u8; 100_000]);
+        assert_eq!(fs::metadata(&moved).unwrap().modified().unwrap(), modified);
+
+        // An existing target is never overwritten, and the original stays
+        fs::write(&original, "again").unwrap();
+        let err = move_across(&original, &moved).unwrap_err();
+        assert!(matches!(RenameError::find(&err), Some(RenameError::TargetExists { .. })));
+        assert!(original.exists());
+        assert_eq!(fs::read(&moved).unwrap().len(), 100_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_across_keeps_extended_attributes() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::io::AsRawFd;
+
+        let temp_dir = TempDir::new().unwrap();
+        let original = temp_dir.path().join("scan.pdf");
+        fs::write(&original, "scanned").unwrap();
+        let name = CString::new("user.xdg.origin.url").unwrap();
+        let file = fs::File::open(&original).unwrap();
+        if xattr::set(file.as_raw_fd(), &name, b"https://example.com/scan.pdf").is_err() {
+            return; // The temp directory's filesystem has no user attributes
+        }
+
+        let moved = temp_dir.path().join("Tax Return.pdf");
+        move_across(&original, &moved).unwrap();
+        let moved = CString::new(moved.as_os_str().as_bytes()).unwrap();
+        assert_eq!(xattr::get(&moved, &name).unwrap(), b"https://example.com/scan.pdf");
+    }
 }
@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scorer::RejectReason;
+use crate::{CollisionStrategy, ExtensionMismatch, FileAnalysis, FileCategory, NameSource, RenameResult, SeriesInfo, Stage};
+
+/// A decision made for one file during a run
+///
+/// Each event is logged with its fields as it happens (see `RunEvent::log`), and a run's
+/// events can be collected into a `RunReport` for troubleshooting afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    /// A file was analyzed (with or without a proposed name)
+    FileAnalyzed {
+        path: PathBuf,
+        category: FileCategory,
+        proposed_name: Option<String>,
+        confidence: f32,
+        source: NameSource,
+        collision_strategy: Option<CollisionStrategy>,
+        deferred: Option<String>,
+        /// The size limit the file was over, if any
+        over_limit: Option<String>,
+        /// The numbered series or photo burst the file is named as part of, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        series: Option<SeriesInfo>,
+        /// Set when the content is in another format than the extension says
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        extension_mismatch: Option<ExtensionMismatch>,
+    },
+    /// The scorer turned down a candidate name for a file
+    NameRejected {
+        path: PathBuf,
+        candidate: String,
+        source: NameSource,
+        score: f32,
+        reason: RejectReason,
+    },
+    /// A file was renamed (or in a dry run, would have been)
+    RenamePerformed {
+        from: PathBuf,
+        to: PathBuf,
+        dry_run: bool,
+    },
+    /// Something went wrong with a file; `recoverable` problems didn't stop its analysis
+    Error {
+        path: PathBuf,
+        stage: Stage,
+        message: String,
+        recoverable: bool,
+    },
+}
+
+impl RunEvent {
+    /// The analysis as a `FileAnalyzed` event, followed by its rejected names and issues
+    pub fn from_analysis(analysis: &FileAnalysis) -> Vec<RunEvent> {
+        let path = &analysis.original_path;
+        let analyzed = RunEvent::FileAnalyzed {
+            path: path.clone(),
+            category: analysis.file_category.clone(),
+            proposed_name: analysis.proposed_name.clone(),
+            confidence: analysis.confidence,
+            source: analysis.name_source,
+            collision_strategy: analysis.collision_strategy,
+            deferred: analysis.deferred.map(|reason| reason.to_string()),
+            over_limit: analysis.over_limit.map(|limit| limit.to_string()),
+            series: analysis.series,
+            extension_mismatch: analysis.extension_mismatch.clone(),
+        };
+        let rejected = analysis.rejected.iter().map(|rejected| RunEvent::NameRejected {
+            path: path.clone(),
+            candidate: rejected.name.clone(),
+            source: rejected.source,
+            score: rejected.score,
+            reason: rejected.reason.clone(),
+        });
+        let errors = analysis.errors.iter().map(|issue| RunEvent::Error {
+            path: path.clone(),
+            stage: issue.stage,
+            message: issue.message.clone(),
+            recoverable: issue.recoverable,
+        });
+        std::iter::once(analyzed).chain(rejected).chain(errors).collect()
+    }
+
+    /// The result as a `RenamePerformed` event, or an `Error` if the file wasn't renamed
+    pub fn from_result(result: &RenameResult, dry_run: bool) -> RunEvent {
+        match &result.error {
+            Some(message) if !result.success => RunEvent::Error {
+                path: result.original_path.clone(),
+                stage: Stage::Rename,
+                message: message.clone(),
+                recoverable: result.deferred.is_some(),
+            },
+            _ => RunEvent::RenamePerformed {
+                from: result.original_path.clone(),
+                to: result.new_path.clone(),
+                dry_run,
+            },
+        }
+    }
+
+    /// Log the event, with its fields, through `tracing`
+    /// Renames are logged at info level and failed renames as warnings; the rest is debug output
+    pub fn log(&self) {
+        match self {
+            RunEvent::FileAnalyzed {
+                path,
+                proposed_name,
+                confidence,
+                source,
+                ..
+            } => tracing::debug!(
+                event = "file_analyzed",
+                path = %path.display(),
+                proposed_name = proposed_name.as_deref().unwrap_or(""),
+                confidence,
+                source = source.label(),
+                "Analyzed {}",
+                path.display()
+            ),
+            RunEvent::NameRejected {
+                path,
+                candidate,
+                score,
+                reason,
+                ..
+            } => tracing::debug!(
+                event = "name_rejected",
+                path = %path.display(),
+                candidate = candidate.as_str(),
+                score,
+                reason = %reason,
+                "Rejected \"{}\" ({:.1}): {}",
+                candidate,
+                score,
+                reason
+            ),
+            RunEvent::RenamePerformed { from, to, dry_run: true } => tracing::info!(
+                event = "rename_performed",
+                from = %from.display(),
+                to = %to.display(),
+                dry_run = true,
+                "[DRY RUN] {} -> {}",
+                from.display(),
+                to.display()
+            ),
+            RunEvent::RenamePerformed { from, to, dry_run: false } => tracing::info!(
+                event = "rename_performed",
+                from = %from.display(),
+                to = %to.display(),
+                dry_run = false,
+                "Renamed: {} -> {}",
+                from.display(),
+                to.display()
+            ),
+            RunEvent::Error {
+                path,
+                stage: Stage::Rename,
+                message,
+                recoverable: false,
+            } => tracing::warn!(
+                event = "error",
+                path = %path.display(),
+                stage = "rename",
+                recoverable = false,
+                "{}",
+                message
+            ),
+            RunEvent::Error {
+                path,
+                stage,
+                message,
+                recoverable,
+            } => tracing::debug!(
+                event = "error",
+                path = %path.display(),
+                stage = stage.name(),
+                recoverable,
+                "{}",
+                message
+            ),
+        }
+    }
+}
+
+/// Every decision of one run, written with `--report` for troubleshooting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// nameback version that made the run
+    pub version: String,
+    /// Directory that was processed
+    pub directory: PathBuf,
+    /// When the run started (RFC 3339, local time)
+    pub started: String,
+    pub dry_run: bool,
+    /// Analysis events in scan order, then rename events
+    pub events: Vec<RunEvent>,
+}
+
+impl RunReport {
+    /// An empty report for a run over `directory`
+    pub fn new(directory: &Path, dry_run: bool) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            directory: directory.to_path_buf(),
+            started: chrono::Local::now().to_rfc3339(),
+            dry_run,
+            events: Vec::new(),
+        }
+    }
+
+    /// Add the events of each analysis
+    pub fn record_analyses(&mut self, analyses: &[FileAnalysis]) {
+        self.events.extend(analyses.iter().flat_map(RunEvent::from_analysis));
+    }
+
+    /// Add the events of each rename
+    pub fn record_results(&mut self, results: &[RenameResult]) {
+        let dry_run = self.dry_run;
+        self.events
+            .extend(results.iter().map(|result| RunEvent::from_result(result, dry_run)));
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        fs::write(path, data).with_context(|| format!("Failed to write report {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scorer::RejectedCandidate;
+    use crate::{AnalysisIssue, SeriesKind};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("IMG_0001.jpg");
+        let analysis = FileAnalysis {
+            proposed_name: Some("Sunset.jpg".to_string()),
+            confidence: 6.5,
+            name_source: NameSource::Metadata,
+            rejected: vec![RejectedCandidate {
+                name: "2023:10:15 14:30:22".to_string(),
+                source: NameSource::Metadata,
+                score: 1.1,
+                reason: RejectReason::LowQuality,
+            }],
+            errors: vec![AnalysisIssue::recoverable(Stage::Ocr, "tesseract not installed")],
+            series: Some(SeriesInfo { id: 0, index: 2, total: 5, kind: SeriesKind::Burst }),
+            extension_mismatch: Some(ExtensionMismatch {
+                extension: Some("jpg".to_string()),
+                detected: "png".to_string(),
+                fixed: true,
+            }),
+            ..crate::pipeline::unanalyzed(&path, FileCategory::Image)
+        };
+
+        let mut report = RunReport::new(temp_dir.path(), true);
+        report.record_analyses(&[analysis]);
+        let kinds: Vec<&str> = report
+            .events
+            .iter()
+            .map(|event| match event {
+                RunEvent::FileAnalyzed { .. } => "analyzed",
+                RunEvent::NameRejected { .. } => "rejected",
+                RunEvent::RenamePerformed { .. } => "renamed",
+                RunEvent::Error { .. } => "error",
+            })
+            .collect();
+        assert_eq!(kinds, ["analyzed", "rejected", "error"]);
+
+        let report_path = temp_dir.path().join("run.json");
+        report.save(&report_path)?;
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path)?)?;
+        assert_eq!(json["events"][0]["series"]["kind"], "burst");
+        assert_eq!(json["events"][0]["extension_mismatch"]["detected"], "png");
+        assert_eq!(json["events"][1]["event"], "name_rejected");
+        assert_eq!(json["events"][1]["reason"]["kind"], "low_quality");
+        assert_eq!(json["events"][2]["stage"], "ocr");
+
+        let loaded: RunReport = serde_json::from_value(json)?;
+        assert_eq!(loaded.events, report.events);
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Gitignore-style pattern files honoured in every folder of a scan (see `RenameEngine::scan_files`)
+pub(crate) const IGNORE_FILE_NAME: &str = ".namebackignore";
+
+/// Include/exclude globs from `RenameConfig`, compiled once per scan
+///
+/// Paths are matched relative to the scanned directory with `/` separators.
+/// A pattern without `/` matches a file or folder name at any depth (`*.pdf`, `node_modules`);
+/// one with `/` matches the whole relative path (`invoices/**/*.pdf`). A trailing `/`
+/// restricts a pattern to folders.
+#[derive(Debug)]
+pub(crate) struct ScanFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    exclude_dirs: GlobSet,
+}
+
+impl ScanFilter {
+    pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Self, globset::Error> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build(include.iter().map(String::as_str))?)
+        };
+        let (dir_only, any): (Vec<&String>, Vec<&String>) =
+            exclude.iter().partition(|pattern| pattern.ends_with('/'));
+
+        Ok(Self {
+            include,
+            exclude: build(any.into_iter().map(String::as_str))?,
+            exclude_dirs: build(dir_only.into_iter().map(|p| p.trim_end_matches('/')))?,
+        })
+    }
+
+    /// Whether a file or folder (relative to the scanned directory) is excluded
+    /// Excluded folders are not descended into
+    pub(crate) fn excludes(&self, relative: &Path, is_dir: bool) -> bool {
+        self.exclude.is_match(relative) || (is_dir && self.exclude_dirs.is_match(relative))
+    }
+
+    /// Whether a file passes the include patterns (all files do when there are none)
+    pub(crate) fn includes(&self, relative: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| include.is_match(relative))
+    }
+}
+
+/// Check that a pattern compiles, for config validation
+pub(crate) fn check(pattern: &str) -> Result<(), globset::Error> {
+    glob(pattern.trim_end_matches('/')).map(|_| ())
+}
+
+fn build<'a>(patterns: impl Iterator<Item = &'a str>) -> Result<GlobSet, globset::Error> {
+    let mut set = GlobSetBuilder::new();
+    for pattern in patterns {
+        set.add(glob(pattern)?);
+    }
+    set.build()
+}
+
+/// `*` stays within one folder; bare names match at any depth
+fn glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let pattern = if pattern.contains('/') {
+        pattern.trim_start_matches("./").to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    GlobBuilder::new(&pattern).literal_separator(true).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> ScanFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        ScanFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let filter = filter(&["**/*.pdf", "scans/*.jpg"], &["node_modules/", "*.tmp.pdf"]);
+
+        assert!(filter.includes(Path::new("report.pdf")));
+        assert!(filter.includes(Path::new("2024/q1/report.pdf")));
+        assert!(filter.includes(Path::new("scans/page1.jpg")));
+        assert!(!filter.includes(Path::new("scans/old/page1.jpg")));
+        assert!(!filter.includes(Path::new("notes.txt")));
+
+        assert!(filter.excludes(Path::new("web/node_modules"), true));
+        // A file that happens to be called node_modules is kept
+        assert!(!filter.excludes(Path::new("node_modules"), false));
+        assert!(filter.excludes(Path::new("drafts/report.tmp.pdf"), false));
+        assert!(!filter.excludes(Path::new("drafts"), true));
+    }
+
+    #[test]
+    fn test_no_patterns_and_invalid_patterns() {
+        let filter = filter(&[], &[]);
+        assert!(filter.includes(Path::new("any/file.txt")));
+        assert!(!filter.excludes(Path::new("any"), true));
+
+        assert!(check("**/*.{pdf,docx}").is_ok());
+        assert!(check("[unclosed").is_err());
+    }
+}
@@ -1,8 +1,12 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::fmt;
+
+/// Lowest score a name can be used with
+pub(crate) const ACCEPTABLE_SCORE: f32 = 2.0;
 
 /// Represents a candidate name with its quality score
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct NameCandidate {
     pub name: String,
     pub score: f32,
@@ -10,17 +14,39 @@ pub struct NameCandidate {
 }
 
 /// Source of a candidate name
-#[derive(Debug, Clone, Copy, PartialEq)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NameSource {
     Metadata,       // From EXIF/metadata fields
     TextExtract,    // Extracted from text content
     PdfText,        // Extracted from PDF text
     OcrImage,       // OCR from image
     OcrVideo,       // OCR from video frame
+    ImageAnalysis,  // Subject guessed from a photo's pixels
     DirectoryContext, // From directory structure
     FilenameAnalysis, // From analyzing original filename
-    Fallback,       // Last resort (timestamp, etc.)
+    Provider,       // From a NameProvider registered with RenameEngine
+    FolderContents, // A folder's name, from what the files in it have in common
+    #[default]
+    Fallback,       // Last resort (timestamp, etc.), or no name found
+}
+
+impl NameSource {
+    /// Short lowercase label for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            NameSource::Metadata => "metadata",
+            NameSource::TextExtract => "text",
+            NameSource::PdfText => "pdf text",
+            NameSource::OcrImage => "image ocr",
+            NameSource::OcrVideo => "video ocr",
+            NameSource::ImageAnalysis => "subject",
+            NameSource::DirectoryContext => "directory",
+            NameSource::FilenameAnalysis => "filename",
+            NameSource::Provider => "provider",
+            NameSource::FolderContents => "folder contents",
+            NameSource::Fallback => "fallback",
+        }
+    }
 }
 
 impl NameCandidate {
@@ -31,73 +57,203 @@ impl NameCandidate {
     }
 
     /// Returns true if this candidate is high quality (score >= 5.0)
-    #[allow(dead_code)]
     pub fn is_high_quality(&self) -> bool {
         self.score >= 5.0
     }
 
     /// Returns true if this candidate is acceptable (score >= 2.0)
     pub fn is_acceptable(&self) -> bool {
-        self.score >= 2.0
+        self.score >= ACCEPTABLE_SCORE
+    }
+
+    /// This candidate, turned down for `reason`
+    pub(crate) fn rejected(self, reason: RejectReason) -> RejectedCandidate {
+        RejectedCandidate {
+            name: self.name,
+            source: self.source,
+            score: self.score,
+            reason,
+        }
+    }
+}
+
+/// A candidate name the scorer turned down, kept for troubleshooting (see `RunEvent::NameRejected`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectedCandidate {
+    pub name: String,
+    pub source: NameSource,
+    pub score: f32,
+    pub reason: RejectReason,
+}
+
+/// Why a candidate name wasn't used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RejectReason {
+    /// Scored below 2.0, too poor to name a file with (dates, IDs, error messages, ...)
+    LowQuality,
+    /// Another candidate scored higher
+    Outscored { by: String },
+    /// The best candidate, but below `RenameConfig::min_confidence`
+    BelowMinConfidence { min_confidence: f32 },
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::LowQuality => write!(f, "scored below {:.1}", ACCEPTABLE_SCORE),
+            RejectReason::Outscored { by } => write!(f, "outscored by \"{}\"", by),
+            RejectReason::BelowMinConfidence { min_confidence } => {
+                write!(f, "below the minimum confidence of {:.1}", min_confidence)
+            }
+        }
     }
 }
 
 /// Calculates quality score for a candidate name
 fn calculate_score(name: &str, source: NameSource) -> f32 {
-    // Guard against empty strings
-    if name.is_empty() {
-        return 0.0;
+    ScoreBreakdown::of(name, source).total()
+}
+
+/// How a candidate's score is made up: points for length, source, word count and character
+/// variety, multiplied by penalties for names that look like dates, errors or IDs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Up to 2.0, for names of 20-60 characters
+    pub length: f32,
+    /// How reliable the source is, from 0.5 (fallback) to 3.0 (metadata)
+    pub source: f32,
+    /// 0.5 per word, up to 5 words
+    pub words: f32,
+    /// Up to 1.5, for names that don't repeat the same few characters
+    pub diversity: f32,
+    /// Penalties applied, in order
+    pub penalties: Vec<Penalty>,
+}
+
+impl ScoreBreakdown {
+    /// Scores `name` from `source`, the way `NameCandidate::new` does
+    pub fn of(name: &str, source: NameSource) -> Self {
+        // Guard against empty strings
+        if name.is_empty() {
+            return Self {
+                length: 0.0,
+                source: 0.0,
+                words: 0.0,
+                diversity: 0.0,
+                penalties: Vec::new(),
+            };
+        }
+
+        // 1. Length score (optimal 20-60 chars)
+        let length_score = match name.len() {
+            0..=10 => 0.2,
+            11..=19 => 0.6,
+            20..=60 => 1.0,
+            61..=100 => 0.7,
+            _ => 0.4,
+        };
+
+        // 2. Source reliability
+        let source_score = match source {
+            NameSource::Metadata => 3.0,
+            NameSource::TextExtract => 2.5,
+            NameSource::Provider => 2.5,
+            NameSource::PdfText => 2.0,
+            NameSource::FolderContents => 2.0,
+            NameSource::DirectoryContext => 1.8,
+            NameSource::FilenameAnalysis => 1.5,
+            NameSource::OcrImage => 1.5,
+            NameSource::OcrVideo => 1.2,
+            NameSource::ImageAnalysis => 1.0,
+            NameSource::Fallback => 0.5,
+        };
+
+        // 3. Word count bonus (encourages descriptive multi-word names)
+        let word_count = name.split_whitespace().count();
+
+        // 4. Character diversity (avoid "AAAA" or "1111")
+        let unique_chars: HashSet<char> = name.chars().collect();
+        let diversity = unique_chars.len() as f32 / name.len() as f32;
+
+        Self {
+            length: length_score * 2.0,
+            source: source_score,
+            words: (word_count.min(5) as f32) * 0.5,
+            diversity: diversity * 1.5,
+            // 5. Apply penalties
+            penalties: penalties(name),
+        }
     }
 
-    let mut score = 0.0;
+    /// The breakdown of a candidate's score, or None when the score wasn't worked out by the
+    /// scorer (set directly by a `NameProvider`)
+    pub fn of_candidate(name: &str, source: NameSource, score: f32) -> Option<Self> {
+        Some(Self::of(name, source)).filter(|breakdown| breakdown.total() == score)
+    }
 
-    // 1. Length score (optimal 20-60 chars)
-    let length_score = match name.len() {
-        0..=10 => 0.2,
-        11..=19 => 0.6,
-        20..=60 => 1.0,
-        61..=100 => 0.7,
-        _ => 0.4,
-    };
-    score += length_score * 2.0;
-
-    // 2. Source reliability
-    let source_score = match source {
-        NameSource::Metadata => 3.0,
-        NameSource::TextExtract => 2.5,
-        NameSource::PdfText => 2.0,
-        NameSource::DirectoryContext => 1.8,
-        NameSource::FilenameAnalysis => 1.5,
-        NameSource::OcrImage => 1.5,
-        NameSource::OcrVideo => 1.2,
-        NameSource::Fallback => 0.5,
-    };
-    score += source_score;
+    /// Points before penalties
+    pub fn points(&self) -> f32 {
+        self.length + self.source + self.words + self.diversity
+    }
 
-    // 3. Word count bonus (encourages descriptive multi-word names)
-    let word_count = name.split_whitespace().count();
-    let word_bonus = (word_count.min(5) as f32) * 0.5;
-    score += word_bonus;
+    /// The score: points multiplied by every penalty
+    pub fn total(&self) -> f32 {
+        self.penalties
+            .iter()
+            .fold(self.points(), |score, penalty| score * penalty.factor())
+    }
+}
 
-    // 4. Character diversity (avoid "AAAA" or "1111")
-    let unique_chars: HashSet<char> = name.chars().collect();
-    let diversity = unique_chars.len() as f32 / name.len() as f32;
-    score += diversity * 1.5;
+/// Something that makes a name look like it isn't one, lowering its score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Penalty {
+    /// Nothing but a date, e.g. "2023-01-15"
+    DateOnly,
+    /// Reads like an error message ("error", "failed", "traceback", ...)
+    ErrorMessage,
+    /// A UUID or hash
+    TechnicalId,
+    /// A software download name: platform, version, vendor, year or "setup"
+    Installer,
+    /// Fewer than 3 letters, or mostly digits
+    MostlyNumeric,
+}
 
-    // 5. Apply penalties
-    score = apply_penalties(name, score);
+impl Penalty {
+    /// What the score is multiplied by
+    pub fn factor(self) -> f32 {
+        match self {
+            Penalty::DateOnly => 0.3,
+            Penalty::ErrorMessage => 0.2,
+            Penalty::TechnicalId => 0.3,
+            Penalty::Installer => 0.2,
+            Penalty::MostlyNumeric => 0.5,
+        }
+    }
+}
 
-    score
+impl fmt::Display for Penalty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Penalty::DateOnly => write!(f, "only a date"),
+            Penalty::ErrorMessage => write!(f, "looks like an error message"),
+            Penalty::TechnicalId => write!(f, "looks like a UUID or hash"),
+            Penalty::Installer => write!(f, "looks like an installer name"),
+            Penalty::MostlyNumeric => write!(f, "under 3 letters or mostly digits"),
+        }
+    }
 }
 
-/// Applies penalty multipliers for low-quality content
-fn apply_penalties(name: &str, base_score: f32) -> f32 {
-    let mut score = base_score;
+/// Penalties for low-quality content
+fn penalties(name: &str) -> Vec<Penalty> {
+    let mut penalties = Vec::new();
     let lower = name.to_lowercase();
 
     // Date-only penalty
     if is_date_only_pattern(&lower) {
-        score *= 0.3;
+        penalties.push(Penalty::DateOnly);
     }
 
     // Error indicator penalty
@@ -106,27 +262,27 @@ fn apply_penalties(name: &str, base_score: f32) -> f32 {
         "invalid", "traceback",
     ];
     if error_indicators.iter().any(|e| lower.contains(e)) {
-        score *= 0.2;
+        penalties.push(Penalty::ErrorMessage);
     }
 
     // Technical ID penalty (UUIDs, hashes)
     if looks_like_technical_id(name) {
-        score *= 0.3;
+        penalties.push(Penalty::TechnicalId);
     }
 
     // Software installer pattern penalty
     if looks_like_installer(name) {
-        score *= 0.2;
+        penalties.push(Penalty::Installer);
     }
 
     // Mostly numeric penalty
     let alpha_count = name.chars().filter(|c| c.is_alphabetic()).count();
     let numeric_ratio = name.chars().filter(|c| c.is_numeric()).count() as f32 / name.len() as f32;
     if alpha_count < 3 || numeric_ratio > 0.7 {
-        score *= 0.5;
+        penalties.push(Penalty::MostlyNumeric);
     }
 
-    score
+    penalties
 }
 
 /// Checks if name is just a date pattern (public for use in extractor)
@@ -230,21 +386,40 @@ fn is_decimal_version_pattern(s: &str) -> bool {
 
 /// Selects the best candidate from a list based on scores
 pub fn select_best_candidate(candidates: Vec<NameCandidate>) -> Option<NameCandidate> {
-    if candidates.is_empty() {
-        return None;
-    }
+    rank_candidates(candidates).0
+}
 
-    // Find candidate with highest score
-    let best = candidates
+/// The best acceptable candidate, and every other one with the reason it lost
+pub(crate) fn rank_candidates(
+    mut candidates: Vec<NameCandidate>,
+) -> (Option<NameCandidate>, Vec<RejectedCandidate>) {
+    // A NaN or infinite score (set by a `NameProvider`) can't be ranked
+    candidates.retain(|candidate| {
+        let finite = candidate.score.is_finite();
+        if !finite {
+            tracing::debug!("Dropping candidate {:?} with score {}", candidate.name, candidate.score);
+        }
+        finite
+    });
+    // Highest score first; of equal scores the later candidate wins
+    candidates.reverse();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let best = match candidates.first() {
+        Some(first) if first.is_acceptable() => Some(candidates.remove(0)),
+        _ => None,
+    };
+    let rejected = candidates
         .into_iter()
-        .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())?;
-
-    // Only return if acceptable quality
-    if best.is_acceptable() {
-        Some(best)
-    } else {
-        None
-    }
+        .map(|candidate| {
+            let reason = match &best {
+                Some(best) if candidate.is_acceptable() => RejectReason::Outscored { by: best.name.clone() },
+                _ => RejectReason::LowQuality,
+            };
+            candidate.rejected(reason)
+        })
+        .collect();
+    (best, rejected)
 }
 
 #[cfg(test)]
@@ -326,6 +501,42 @@ mod tests {
         assert!(best.is_none(), "Should reject all low quality candidates");
     }
 
+    #[test]
+    fn test_rank_gives_rejection_reasons() {
+        let candidates = vec![
+            NameCandidate::new("20231015".to_string(), NameSource::FilenameAnalysis),
+            NameCandidate::new("Project Proposal Draft".to_string(), NameSource::Metadata),
+            NameCandidate::new("Meeting Notes".to_string(), NameSource::DirectoryContext),
+        ];
+
+        let (best, rejected) = rank_candidates(candidates);
+        assert_eq!(best.unwrap().name, "Project Proposal Draft");
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].name, "Meeting Notes");
+        assert_eq!(
+            rejected[0].reason,
+            RejectReason::Outscored { by: "Project Proposal Draft".to_string() }
+        );
+        assert_eq!(rejected[1].reason, RejectReason::LowQuality);
+
+        let (best, rejected) = rank_candidates(vec![NameCandidate::new("123".to_string(), NameSource::Fallback)]);
+        assert!(best.is_none());
+        assert_eq!(rejected[0].reason, RejectReason::LowQuality);
+
+        let broken = [f32::NAN, f32::INFINITY].map(|score| NameCandidate {
+            score,
+            ..NameCandidate::new("Broken Provider".to_string(), NameSource::Provider)
+        });
+        let (best, rejected) = rank_candidates(
+            broken
+                .into_iter()
+                .chain([NameCandidate::new("Project Proposal Draft".to_string(), NameSource::Metadata)])
+                .collect(),
+        );
+        assert_eq!(best.unwrap().name, "Project Proposal Draft");
+        assert!(rejected.is_empty());
+    }
+
     #[test]
     fn test_length_scoring() {
         // Too short
@@ -390,6 +601,23 @@ mod tests {
         assert!(candidate.is_high_quality());
     }
 
+    #[test]
+    fn test_score_breakdown() {
+        let breakdown = ScoreBreakdown::of("20231015", NameSource::TextExtract);
+        assert_eq!(breakdown.source, 2.5);
+        assert_eq!(breakdown.penalties, [Penalty::DateOnly, Penalty::MostlyNumeric]);
+        assert!((breakdown.total() - breakdown.points() * 0.3 * 0.5).abs() < 1e-6);
+
+        let candidate = NameCandidate::new("Project Proposal Draft".to_string(), NameSource::Metadata);
+        let breakdown = ScoreBreakdown::of_candidate(&candidate.name, candidate.source, candidate.score)
+            .expect("scored by the scorer");
+        assert_eq!(breakdown.total(), candidate.score);
+        assert!(breakdown.penalties.is_empty());
+
+        // Scores set by hand have no breakdown
+        assert_eq!(ScoreBreakdown::of_candidate(&candidate.name, candidate.source, 9.0), None);
+    }
+
     #[test]
     fn test_is_decimal_version_pattern() {
         assert!(is_decimal_version_pattern("17.4"));
@@ -0,0 +1,191 @@
+/// The writing system of extracted text, as whatlang detects it
+/// Enough to pick how names are cut and split; languages of one script are cut alike
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Script {
+    Latin,
+    /// Chinese and Japanese (Han ideographs and kana), written without spaces between words
+    /// Korean Hangul separates words with spaces, so it counts as `Other`
+    Cjk,
+    Arabic,
+    Hebrew,
+    /// Cyrillic, Greek, Hangul, ...: words separated by spaces, like Latin
+    Other,
+}
+
+impl Script {
+    /// The script of `c`, or None for digits, punctuation and other characters that aren't letters
+    /// Only for measuring (see `width`); whole texts are detected with whatlang
+    fn of(c: char) -> Option<Script> {
+        if !c.is_alphabetic() {
+            return None;
+        }
+        Some(match c as u32 {
+            0x0000..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => {
+                Script::Arabic
+            }
+            0x3040..=0x30FF
+            | 0x31F0..=0x31FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xF900..=0xFAFF
+            | 0xFF66..=0xFF9F
+            | 0x20000..=0x2FFFF => Script::Cjk,
+            _ => Script::Other,
+        })
+    }
+
+    /// Whether words are separated by spaces, so text can be cut between them
+    pub(crate) fn uses_spaces(&self) -> bool {
+        *self != Script::Cjk
+    }
+}
+
+/// The script most letters of `text` are in (Latin when it has none)
+pub(crate) fn detect(text: &str) -> Script {
+    match whatlang::detect_script(text) {
+        None | Some(whatlang::Script::Latin) => Script::Latin,
+        // Japanese mixes kana with the Han ideographs whatlang calls Mandarin
+        Some(whatlang::Script::Mandarin | whatlang::Script::Hiragana | whatlang::Script::Katakana) => Script::Cjk,
+        Some(whatlang::Script::Arabic) => Script::Arabic,
+        Some(whatlang::Script::Hebrew) => Script::Hebrew,
+        Some(_) => Script::Other,
+    }
+}
+
+/// Accents, Hebrew points and Arabic harakat: marks drawn on the letter before them
+pub(crate) fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+        // Hebrew cantillation marks and vowel points
+        | 0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7
+        // Arabic harakat and Quranic marks
+        | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4
+        | 0x06E7..=0x06E8 | 0x06EA..=0x06ED | 0x08D3..=0x08FF
+        // Japanese (han)dakuten
+        | 0x3099..=0x309A)
+}
+
+/// Invisible characters that only steer layout: bidi marks, embeddings, overrides and isolates
+/// (which can also make a name display differently from how it sorts), the byte order mark, and
+/// the Arabic tatweel that stretches letters to justify lines
+/// The zero-width (non-)joiners are kept, as Persian and Indic spelling depends on them
+pub(crate) fn is_layout_mark(c: char) -> bool {
+    matches!(c as u32, 0x061C | 0x0640 | 0x200E | 0x200F | 0x202A..=0x202E | 0x2066..=0x2069 | 0xFEFF)
+}
+
+/// Chinese and Japanese punctuation and the ideographic space
+/// Without spaces between words these are the only word boundaries in CJK text
+pub(crate) fn is_cjk_separator(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x3004 | 0x3008..=0x3011 | 0x3014..=0x301F | 0x30FB
+        | 0xFF01 | 0xFF08 | 0xFF09 | 0xFF0C | 0xFF0E | 0xFF1A | 0xFF1B | 0xFF1F | 0xFF3B | 0xFF3D)
+}
+
+/// How wide `c` is next to Latin letters: ideographs and kana take two columns and carry
+/// about as much meaning as a short word, so they count double
+fn width(c: char) -> usize {
+    match Script::of(c) {
+        Some(Script::Cjk) => 2,
+        _ => 1,
+    }
+}
+
+/// `cut`, a prefix of some text followed by `rest`, without a letter parted from the marks
+/// that go with it
+pub(crate) fn keep_marks_with_letters<'a>(cut: &'a str, rest: &str) -> &'a str {
+    if !rest.starts_with(is_combining_mark) {
+        return cut;
+    }
+    let base = cut.trim_end_matches(is_combining_mark);
+    base.char_indices().last().map_or(base, |(index, _)| &base[..index])
+}
+
+/// Shortens `text` to at most `max_width` (see `width`), preferring to cut between words: at a
+/// space, or in Chinese and Japanese at punctuation, when that keeps at least half of it
+/// Never cuts inside a character or between a letter and its marks
+pub(crate) fn truncate(text: &str, max_width: usize) -> String {
+    let mut total = 0;
+    let end = text.char_indices().find_map(|(index, c)| {
+        total += width(c);
+        (total > max_width).then_some(index)
+    });
+    let Some(end) = end else {
+        return text.to_string();
+    };
+
+    let mut cut = keep_marks_with_letters(&text[..end], &text[end..]);
+    let is_break: fn(char) -> bool = match detect(text).uses_spaces() {
+        true => char::is_whitespace,
+        false => |c| c.is_whitespace() || is_cjk_separator(c),
+    };
+    if let Some(index) = cut.rfind(is_break) {
+        if cut[..index].chars().map(width).sum::<usize>() > max_width / 2 {
+            cut = &cut[..index];
+        }
+    }
+    cut.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_script() {
+        assert_eq!(detect("Quarterly report 2023"), Script::Latin);
+        assert_eq!(detect("2023-10-15"), Script::Latin);
+        assert_eq!(detect("第三四半期の売上報告 Q3"), Script::Cjk);
+        assert_eq!(detect("東京タワーの夜景"), Script::Cjk);
+        assert_eq!(detect("التقرير السنوي 2023"), Script::Arabic);
+        assert_eq!(detect("דוח רבעוני"), Script::Hebrew);
+        assert_eq!(detect("Годовой отчёт"), Script::Other);
+        assert!(detect("서울 여행 사진").uses_spaces());
+        assert!(!detect("会议纪要").uses_spaces());
+        assert!(!detect("ひらがなのタイトル").uses_spaces());
+        assert_eq!(detect("Αρχαία ελληνικά"), Script::Other);
+    }
+
+    #[test]
+    fn test_truncate_latin_at_word_boundary() {
+        assert_eq!(truncate("Short text", 80), "Short text");
+        assert_eq!(truncate("Minutes of the annual general meeting", 20), "Minutes of the");
+        assert_eq!(truncate("Résumé détaillé", 9), "Résumé");
+    }
+
+    #[test]
+    fn test_truncate_cjk() {
+        // Every ideograph counts double, and the cut is at the punctuation if there is some
+        assert_eq!(truncate("年度報告書。東京本社の売上と利益", 16), "年度報告書");
+        // Without punctuation, between any two characters
+        assert_eq!(truncate("第三四半期売上報告書", 10), "第三四半期");
+        // Never inside a character, where slicing bytes would panic
+        assert_eq!(truncate("会议纪要会议纪要", 7), "会议纪");
+    }
+
+    #[test]
+    fn test_truncate_rtl_keeps_marks_with_letters() {
+        assert_eq!(truncate("التقرير السنوي للشركة", 16), "التقرير السنوي");
+        assert_eq!(truncate("דוח רבעוני שלישי", 10), "דוח רבעוני");
+
+        // A cut right after בְּ would leave the letter without its points: drop it as well
+        let pointed = "שָׁלוֹם בְּרֵאשִׁית";
+        let cut = truncate(pointed, 10);
+        assert!(pointed.starts_with(&cut));
+        assert!(!pointed[cut.len()..].starts_with(is_combining_mark), "{}", cut);
+        assert_eq!(cut, "שָׁלוֹם");
+        assert_eq!(keep_marks_with_letters("שָׁלוֹם בְ", "ּרֵאשִׁית"), "שָׁלוֹם ");
+    }
+
+    #[test]
+    fn test_layout_marks_and_separators() {
+        assert!(is_layout_mark('\u{202E}'));
+        assert!(is_layout_mark('\u{0640}'));
+        assert!(!is_layout_mark('\u{200C}'));
+        assert!(is_cjk_separator('、'));
+        assert!(is_cjk_separator('\u{3000}'));
+        assert!(!is_cjk_separator('々'));
+        assert!(!is_cjk_separator('ー'));
+    }
+}
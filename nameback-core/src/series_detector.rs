@@ -1,12 +1,85 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use rayon::prelude::*;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+use crate::extractor::RawMetadata;
+use crate::{FileAnalysis, FileCategory, SeriesInfo, SeriesKind, WordSeparator};
+
+/// Most time between two shots of one burst; cameras without sub-second times round each
+/// shot down to its second
+const BURST_GAP: TimeDelta = TimeDelta::seconds(2);
+
+/// Digits numbers are padded to unless `RenameConfig::series_padding` says otherwise
+const NUMBERED_PADDING: usize = 3;
+const BURST_PADDING: usize = 2;
+
+/// What burst names start with, ahead of the first shot's name
+const BURST_PREFIX: &str = "Event";
+
+lazy_static::lazy_static! {
+    static ref UNDERSCORE: Regex = Regex::new(r"^(.+?)_(\d+)$").expect("valid underscore series regex");
+    static ref PARENTHESES: Regex = Regex::new(r"^(.+?)\((\d+)\)$").expect("valid parentheses series regex");
+    static ref HYPHEN: Regex = Regex::new(r"^(.+?)-(\d+)$").expect("valid hyphen series regex");
+    static ref SPACE: Regex = Regex::new(r"^(.+?)\s+(\d+)$").expect("valid space series regex");
+}
+
+/// `RenameConfig::series_min_files`, `series_padding`, `series_separator` and `series_start`
+/// together, as series and bursts are numbered with them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeriesNumbering {
+    /// Fewest files that make a series or a burst
+    pub min_files: usize,
+    /// Digits numbers are padded to at least (None = 3 for numbered series, 2 for bursts)
+    pub padding: Option<usize>,
+    /// What goes between the name and the number (None = a numbered series' own pattern,
+    /// the name's word separator for bursts)
+    pub separator: Option<WordSeparator>,
+    /// Number of the first file (None = numbered series keep their numbers, bursts count from 1)
+    pub start: Option<usize>,
+}
+
+impl Default for SeriesNumbering {
+    fn default() -> Self {
+        SeriesNumbering {
+            min_files: 3,
+            padding: None,
+            separator: None,
+            start: None,
+        }
+    }
+}
+
+impl SeriesNumbering {
+    /// Identifies the numbering in cache entries; empty for the default
+    pub(crate) fn cache_key(&self) -> String {
+        if *self == SeriesNumbering::default() {
+            return String::new();
+        }
+        let or_default = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        format!(
+            "{}/{}/{}/{}",
+            self.min_files,
+            or_default(self.padding.map(|padding| padding.to_string())),
+            or_default(self.separator.map(|separator| separator.to_string())),
+            or_default(self.start.map(|start| start.to_string())),
+        )
+    }
+
+    /// Width `numbers` are padded to: the padding (or `default`), or the digits of the
+    /// largest when it has more
+    fn width(&self, numbers: impl Iterator<Item = usize>, default: usize) -> usize {
+        let digits = numbers.max().unwrap_or(0).to_string().len();
+        digits.max(self.padding.unwrap_or(default))
+    }
+}
+
 /// Represents a detected file series
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct FileSeries {
-    pub base_name: String,
+    #[cfg(test)]
+    base_name: String,
     pub files: Vec<(PathBuf, usize)>, // (path, sequence_number)
     pub pattern: SeriesPattern,
 }
@@ -21,16 +94,24 @@ pub enum SeriesPattern {
 }
 
 impl SeriesPattern {
-    /// Returns the regex pattern for this series type
-    fn regex_pattern(&self) -> &str {
+    /// Returns the regex for this series type
+    fn regex(&self) -> &'static Regex {
         match self {
-            SeriesPattern::Underscore => r"^(.+?)_(\d+)$",
-            SeriesPattern::Parentheses => r"^(.+?)\((\d+)\)$",
-            SeriesPattern::Hyphen => r"^(.+?)-(\d+)$",
-            SeriesPattern::Space => r"^(.+?)\s+(\d+)$",
+            SeriesPattern::Underscore => &UNDERSCORE,
+            SeriesPattern::Parentheses => &PARENTHESES,
+            SeriesPattern::Hyphen => &HYPHEN,
+            SeriesPattern::Space => &SPACE,
         }
     }
 
+    /// The base of a stem numbered with any pattern (`IMG` of `IMG_001`), or None when it
+    /// isn't numbered
+    fn strip_number(stem: &str) -> Option<&str> {
+        [SeriesPattern::Underscore, SeriesPattern::Parentheses, SeriesPattern::Hyphen, SeriesPattern::Space]
+            .iter()
+            .find_map(|pattern| Some(pattern.regex().captures(stem)?.get(1)?.as_str()))
+    }
+
     /// Formats a name with this pattern
     pub fn format(&self, base: &str, number: usize, width: usize) -> String {
         let num_str = format!("{:0width$}", number, width = width);
@@ -44,8 +125,8 @@ impl SeriesPattern {
 }
 
 /// Detects file series from a list of file paths
-/// Returns series with 3+ members
-pub fn detect_series(files: &[PathBuf]) -> Vec<FileSeries> {
+/// Returns series with at least `min_files` members
+pub fn detect_series(files: &[PathBuf], min_files: usize) -> Vec<FileSeries> {
     let mut series_map: HashMap<(String, SeriesPattern), Vec<(PathBuf, usize)>> = HashMap::new();
 
     // Try each pattern type
@@ -55,10 +136,11 @@ pub fn detect_series(files: &[PathBuf]) -> Vec<FileSeries> {
         SeriesPattern::Hyphen,
         SeriesPattern::Space,
     ] {
-        let re = Regex::new(pattern_type.regex_pattern()).unwrap();
+        let re = pattern_type.regex();
 
         for file_path in files {
-            if let Some(stem) = file_path.file_stem().and_then(|s| s.to_str()) {
+            let stem = file_path.file_name().and_then(|s| s.to_str()).map(|name| crate::generator::split_extension(name).0);
+            if let Some(stem) = stem {
                 if let Some(captures) = re.captures(stem) {
                     let base = captures.get(1).unwrap().as_str().to_string();
                     let number: usize = captures.get(2).unwrap().as_str().parse().unwrap_or(0);
@@ -72,15 +154,16 @@ pub fn detect_series(files: &[PathBuf]) -> Vec<FileSeries> {
         }
     }
 
-    // Filter to series with 3+ members and convert to FileSeries
+    // Filter to series with enough members and convert to FileSeries
     series_map
         .into_iter()
-        .filter(|(_, files)| files.len() >= 3)
-        .map(|((base_name, pattern), mut files)| {
+        .filter(|(_, files)| files.len() >= min_files)
+        .map(|((_base_name, pattern), mut files)| {
             // Sort by sequence number
             files.sort_by_key(|(_, num)| *num);
             FileSeries {
-                base_name,
+                #[cfg(test)]
+                base_name: _base_name,
                 files,
                 pattern,
             }
@@ -88,32 +171,236 @@ pub fn detect_series(files: &[PathBuf]) -> Vec<FileSeries> {
         .collect()
 }
 
+impl FileSeries {
+    /// Where `file_path` stands in the series, as series `id`
+    pub fn info(&self, id: usize, file_path: &Path) -> Option<SeriesInfo> {
+        let position = self.files.iter().position(|(path, _)| path == file_path)?;
+        Some(SeriesInfo {
+            id,
+            index: position + 1,
+            total: self.files.len(),
+            kind: SeriesKind::Numbered,
+        })
+    }
+}
+
+/// What tells the shots of a burst apart from other photos
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shot {
+    /// When the shot was taken, to the fraction of a second if the camera records it
+    pub time: NaiveDateTime,
+    /// Width and height in pixels
+    pub resolution: Option<(u32, u32)>,
+    /// Camera make and model as recorded
+    pub camera: Option<String>,
+}
+
+/// The capture time, resolution and camera in metadata read from an image
+/// None when it has no capture time
+pub(crate) fn shot_of(raw: &RawMetadata) -> Option<Shot> {
+    // exiftool may add a time zone after the seconds
+    let original = raw.date_time_original.as_deref()?.trim();
+    let mut time = NaiveDateTime::parse_from_str(original.get(..19)?, "%Y:%m:%d %H:%M:%S").ok()?;
+    // `SubSecTimeOriginal` holds the digits after the decimal point: "05" is 50 ms
+    let sub_second = raw.sub_sec_time_original.as_deref().map(str::trim);
+    if let Some(digits) = sub_second.filter(|d| !d.is_empty() && d.chars().all(|c| c.is_ascii_digit())) {
+        let millis: String = digits.chars().chain("000".chars()).take(3).collect();
+        time += TimeDelta::milliseconds(millis.parse().unwrap_or(0));
+    }
+
+    let pixels = |value: &Option<String>| value.as_deref()?.trim().parse::<u32>().ok();
+    let resolution = pixels(&raw.image_width).zip(pixels(&raw.image_height));
+    let camera = [&raw.make, &raw.model]
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .reduce(|make, model| format!("{} {}", make, model));
+    Some(Shot { time, resolution, camera })
+}
+
+/// Reads the capture time, resolution and camera from an image's EXIF data, for photos
+/// whose metadata wasn't extracted this run
+/// None when it has no capture time (or no EXIF data at all)
+fn read_shot(path: &Path) -> Option<Shot> {
+    let mut shot = shot_of(&crate::native_metadata::read(path).ok()?)?;
+    if shot.resolution.is_none() {
+        shot.resolution = image::image_dimensions(path).ok();
+    }
+    Some(shot)
+}
+
+/// Groups photos into bursts: runs of at least `min_shots` shots from one folder and camera,
+/// at one resolution, each taken within `BURST_GAP` of the one before
+/// Returns the indices into `shots` of each burst's shots, in the order they were taken
+pub fn detect_bursts(shots: &[(PathBuf, Shot)], min_shots: usize) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..shots.len()).collect();
+    let key = |index: usize| {
+        let (path, shot) = &shots[index];
+        (path.parent(), &shot.camera, shot.resolution)
+    };
+    order.sort_by(|&a, &b| key(a).cmp(&key(b)).then(shots[a].1.time.cmp(&shots[b].1.time)));
+
+    let mut bursts = Vec::new();
+    let mut run: Vec<usize> = Vec::new();
+    for index in order {
+        let continues = run.last().is_some_and(|&last| {
+            key(last) == key(index) && shots[index].1.time - shots[last].1.time <= BURST_GAP
+        });
+        if !continues {
+            let finished = std::mem::take(&mut run);
+            if finished.len() >= min_shots {
+                bursts.push(finished);
+            }
+        }
+        run.push(index);
+    }
+    if run.len() >= min_shots {
+        bursts.push(run);
+    }
+    bursts
+}
+
+/// Names the photos of each burst among `analyses` (see `detect_bursts`) as an event after
+/// its first named shot, numbered in the order they were taken: `Event_Paris_2023-10-15_01`,
+/// `_02`, ...
+/// `separator` joins the parts of the name and the number unless `numbering` has its own
+/// Bursts get ids from `first_id` on and win over numbered series, which camera rolls
+/// (`IMG_1234`) always are; a burst whose names are in `existing_names` gets a counter
+/// after its base (`Event_Paris_2_01`)
+/// `extracted` holds the shots read while extracting metadata; the other photos (cache hits)
+/// are read here, on `pool` when given
+/// Returns the indices of the renamed analyses
+pub(crate) fn apply_bursts(
+    analyses: &mut [FileAnalysis],
+    numbering: SeriesNumbering,
+    separator: &str,
+    first_id: usize,
+    existing_names: &mut HashSet<String>,
+    extracted: &HashMap<PathBuf, Shot>,
+    pool: Option<&rayon::ThreadPool>,
+) -> Vec<usize> {
+    let candidates: Vec<usize> = (0..analyses.len())
+        .filter(|&index| {
+            let analysis = &analyses[index];
+            analysis.file_category == FileCategory::Image
+                && analysis.proposed_name.is_some()
+                && analysis.deferred.is_none()
+        })
+        .collect();
+    let read = || -> Vec<(PathBuf, Shot)> {
+        candidates
+            .par_iter()
+            .filter_map(|&index| {
+                let path = &analyses[index].original_path;
+                let shot = extracted.get(path).cloned().or_else(|| read_shot(path))?;
+                Some((path.clone(), shot))
+            })
+            .collect()
+    };
+    let shots = match pool {
+        Some(pool) => pool.install(read),
+        None => read(),
+    };
+    let position: HashMap<&Path, usize> = candidates
+        .iter()
+        .map(|&index| (analyses[index].original_path.as_path(), index))
+        .collect();
+    let bursts: Vec<Vec<usize>> = detect_bursts(&shots, numbering.min_files)
+        .into_iter()
+        .map(|burst| burst.into_iter().map(|shot| position[shots[shot].0.as_path()]).collect())
+        .collect();
+    // The names the shots had before are free to take
+    for &index in bursts.iter().flatten() {
+        if let Some(old) = &analyses[index].proposed_name {
+            existing_names.remove(old);
+        }
+    }
+
+    let mut renamed = Vec::new();
+    for (id, burst) in (first_id..).zip(bursts) {
+        let first = &analyses[burst[0]];
+        let Some(name) = &first.proposed_name else {
+            continue;
+        };
+        let stem = crate::generator::split_extension(name).0;
+        // The first shot's name may already carry its number in a camera roll
+        let stem = match first.series {
+            Some(SeriesInfo { kind: SeriesKind::Numbered, .. }) => SeriesPattern::strip_number(stem).unwrap_or(stem),
+            _ => stem,
+        };
+
+        let separator = numbering.separator.map_or(separator, |separator| separator.as_str());
+        let base = format!("{}{}{}", BURST_PREFIX, separator, stem);
+        let start = numbering.start.unwrap_or(1);
+        let width = numbering.width(start..start + burst.len(), BURST_PADDING);
+        let names_with = |base: &str| -> Vec<String> {
+            (start..)
+                .zip(&burst)
+                .map(|(number, &index)| {
+                    let numbered = format!("{}{}{:0width$}", base, separator, number, width = width);
+                    match analyses[index].new_extension() {
+                        Some(extension) => format!("{}.{}", numbered, extension),
+                        None => numbered,
+                    }
+                })
+                .collect()
+        };
+        let free = |names: &[String]| !names.iter().any(|name| existing_names.contains(name));
+        let names = std::iter::once(base.clone())
+            .chain((2..).map(|counter| format!("{}{}{}", base, separator, counter)))
+            .map(|base| names_with(&base))
+            .find(|names| free(names))
+            .expect("some counter leaves the names free");
+        tracing::info!("Burst of {} photos named {}", burst.len(), names[0]);
+
+        for ((position, &index), name) in (1..).zip(&burst).zip(names) {
+            let analysis = &mut analyses[index];
+            existing_names.insert(name.clone());
+            analysis.proposed_name = Some(name);
+            analysis.collision_suffix = None;
+            analysis.series = Some(SeriesInfo {
+                id,
+                index: position,
+                total: burst.len(),
+                kind: SeriesKind::Burst,
+            });
+            renamed.push(index);
+        }
+    }
+    renamed
+}
+
 /// Applies series naming to a specific file within a series
+/// Files keep their sequence numbers, or with `numbering.start` are numbered in order from it
+/// `extension` is the one the file's proposed name ends in
 pub fn apply_series_naming(
     series: &FileSeries,
     file_path: &Path,
     new_base_name: &str,
+    extension: Option<&str>,
+    numbering: SeriesNumbering,
 ) -> Option<String> {
+    let number = |position: usize, seq_num: usize| numbering.start.map_or(seq_num, |start| start + position);
+
     // Find this file's sequence number in the series
-    for (path, seq_num) in &series.files {
+    for (position, (path, seq_num)) in series.files.iter().enumerate() {
         if path == file_path {
-            // Determine padding width (minimum 3, or number of digits in max sequence)
-            let max_num = series.files.iter().map(|(_, n)| *n).max().unwrap_or(0);
-            let width = format!("{}", max_num).len().max(3);
-
-            // Get file extension
-            let extension = file_path
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("");
-
-            let new_name = series.pattern.format(new_base_name, *seq_num, width);
-
-            if extension.is_empty() {
-                return Some(new_name);
-            } else {
-                return Some(format!("{}.{}", new_name, extension));
-            }
+            // Padded to the configured width, or the digits of the largest number
+            let numbers = series.files.iter().enumerate().map(|(position, (_, n))| number(position, *n));
+            let width = numbering.width(numbers, NUMBERED_PADDING);
+
+            let number = number(position, *seq_num);
+            let new_name = match numbering.separator {
+                Some(separator) => format!("{}{}{:0width$}", new_base_name, separator.as_str(), number, width = width),
+                None => series.pattern.format(new_base_name, number, width),
+            };
+
+            return match extension.filter(|extension| !extension.is_empty()) {
+                Some(extension) => Some(format!("{}.{}", new_name, extension)),
+                None => Some(new_name),
+            };
         }
     }
 
@@ -132,7 +419,7 @@ mod tests {
             PathBuf::from("/test/IMG_003.jpg"),
         ];
 
-        let series = detect_series(&files);
+        let series = detect_series(&files, 3);
         assert_eq!(series.len(), 1);
         assert_eq!(series[0].base_name, "IMG");
         assert_eq!(series[0].pattern, SeriesPattern::Underscore);
@@ -147,7 +434,7 @@ mod tests {
             PathBuf::from("/test/Screenshot(3).png"),
         ];
 
-        let series = detect_series(&files);
+        let series = detect_series(&files, 3);
         assert_eq!(series.len(), 1);
         assert_eq!(series[0].base_name, "Screenshot");
         assert_eq!(series[0].pattern, SeriesPattern::Parentheses);
@@ -161,7 +448,7 @@ mod tests {
             PathBuf::from("/test/report-3.pdf"),
         ];
 
-        let series = detect_series(&files);
+        let series = detect_series(&files, 3);
         assert_eq!(series.len(), 1);
         assert_eq!(series[0].base_name, "report");
         assert_eq!(series[0].pattern, SeriesPattern::Hyphen);
@@ -174,7 +461,7 @@ mod tests {
             PathBuf::from("/test/IMG_002.jpg"),
         ];
 
-        let series = detect_series(&files);
+        let series = detect_series(&files, 3);
         assert_eq!(series.len(), 0); // Need 3+ members
     }
 
@@ -194,6 +481,8 @@ mod tests {
             &series,
             &PathBuf::from("/test/IMG_002.jpg"),
             "vacation_photos",
+            Some("jpg"),
+            SeriesNumbering::default(),
         );
 
         assert_eq!(result, Some("vacation_photos_002.jpg".to_string()));
@@ -211,12 +500,44 @@ mod tests {
         };
 
         let result =
-            apply_series_naming(&series, &PathBuf::from("/test/IMG_1.jpg"), "vacation");
+            apply_series_naming(&series, &PathBuf::from("/test/IMG_1.jpg"), "vacation", Some("jpg"), SeriesNumbering::default());
 
         // Should pad to 3 digits (minimum width)
         assert_eq!(result, Some("vacation_001.jpg".to_string()));
     }
 
+    #[test]
+    fn test_apply_series_naming_numbering() {
+        let series = FileSeries {
+            base_name: "Scan".to_string(),
+            files: vec![
+                (PathBuf::from("/test/Scan(7).png"), 7),
+                (PathBuf::from("/test/Scan(8).png"), 8),
+                (PathBuf::from("/test/Scan(12).png"), 12),
+            ],
+            pattern: SeriesPattern::Parentheses,
+        };
+        let numbering = SeriesNumbering {
+            padding: Some(2),
+            separator: Some(WordSeparator::Hyphen),
+            start: Some(1),
+            ..SeriesNumbering::default()
+        };
+        let name = |path: &str, numbering| apply_series_naming(&series, Path::new(path), "Contract", Some("png"), numbering);
+
+        // Renumbered from 1 in order, not after the gap in the old numbers
+        assert_eq!(name("/test/Scan(12).png", numbering).as_deref(), Some("Contract-03.png"));
+        // Padding only sets the least width
+        let narrow = SeriesNumbering { padding: Some(1), start: Some(98), ..numbering };
+        assert_eq!(name("/test/Scan(7).png", narrow).as_deref(), Some("Contract-098.png"));
+        // Without a separator the series keeps its pattern
+        let own_pattern = SeriesNumbering { separator: None, ..numbering };
+        assert_eq!(name("/test/Scan(8).png", own_pattern).as_deref(), Some("Contract(02).png"));
+
+        assert_eq!(SeriesNumbering::default().cache_key(), "");
+        assert_eq!(numbering.cache_key(), "3/2/hyphen/1");
+    }
+
     #[test]
     fn test_pattern_format() {
         assert_eq!(
@@ -242,11 +563,99 @@ mod tests {
             PathBuf::from("/test/VID_003.mp4"),
         ];
 
-        let series = detect_series(&files);
+        let series = detect_series(&files, 3);
         assert_eq!(series.len(), 2);
 
         let bases: Vec<&str> = series.iter().map(|s| s.base_name.as_str()).collect();
         assert!(bases.contains(&"IMG"));
         assert!(bases.contains(&"VID"));
     }
+
+    fn shot(path: &str, time: &str, camera: &str) -> (PathBuf, Shot) {
+        let shot = Shot {
+            time: NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f").unwrap(),
+            resolution: Some((4032, 3024)),
+            camera: Some(camera.to_string()),
+        };
+        (PathBuf::from(path), shot)
+    }
+
+    #[test]
+    fn test_detect_bursts() {
+        let shots = vec![
+            shot("/trip/IMG_0103.jpg", "2023-10-15 14:30:21.9", "Apple iPhone 14"),
+            shot("/trip/IMG_0101.jpg", "2023-10-15 14:30:20.1", "Apple iPhone 14"),
+            shot("/trip/IMG_0102.jpg", "2023-10-15 14:30:20.6", "Apple iPhone 14"),
+            // Too long after the last shot
+            shot("/trip/IMG_0104.jpg", "2023-10-15 14:30:30.0", "Apple iPhone 14"),
+            // Same moment, another camera
+            shot("/trip/DSC_0001.jpg", "2023-10-15 14:30:20.5", "NIKON D750"),
+            shot("/trip/DSC_0002.jpg", "2023-10-15 14:30:21.0", "NIKON D750"),
+        ];
+        assert_eq!(detect_bursts(&shots, 3), vec![vec![1, 2, 0]]);
+
+        // Another folder, or another resolution, starts another run
+        let mut elsewhere = shots.clone();
+        elsewhere[2].0 = PathBuf::from("/other/IMG_0102.jpg");
+        assert!(detect_bursts(&elsewhere, 3).is_empty());
+        let mut resized = shots.clone();
+        resized[0].1.resolution = Some((1920, 1080));
+        assert!(detect_bursts(&resized, 3).is_empty());
+        // Two shots are enough when the threshold allows it
+        assert_eq!(detect_bursts(&shots, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_shot_of() {
+        let raw = RawMetadata {
+            date_time_original: Some("2023:10:15 14:30:20+02:00".to_string()),
+            sub_sec_time_original: Some("05".to_string()),
+            image_width: Some("4032".to_string()),
+            image_height: Some("3024".to_string()),
+            make: Some("Apple".to_string()),
+            model: Some("iPhone 14".to_string()),
+            ..RawMetadata::default()
+        };
+        let shot = shot_of(&raw).unwrap();
+        assert_eq!(shot.time.to_string(), "2023-10-15 14:30:20.050");
+        assert_eq!(shot.resolution, Some((4032, 3024)));
+        assert_eq!(shot.camera.as_deref(), Some("Apple iPhone 14"));
+
+        assert!(shot_of(&RawMetadata::default()).is_none());
+    }
+
+    #[test]
+    fn test_apply_bursts() {
+        let shots: HashMap<PathBuf, Shot> = [
+            shot("/trip/IMG_0101.jpg", "2023-10-15 14:30:20.1", "Apple iPhone 14"),
+            shot("/trip/IMG_0102.jpg", "2023-10-15 14:30:20.6", "Apple iPhone 14"),
+            shot("/trip/IMG_0103.jpg", "2023-10-15 14:30:21.9", "Apple iPhone 14"),
+        ]
+        .into_iter()
+        .collect();
+        let mut analyses: Vec<FileAnalysis> = ["IMG_0101.jpg", "IMG_0102.jpg", "IMG_0103.jpg"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| FileAnalysis {
+                proposed_name: Some(format!("Paris_2023-10-15_{}.jpg", index)),
+                ..crate::pipeline::unanalyzed(&Path::new("/trip").join(name), FileCategory::Image)
+            })
+            .collect();
+        let mut existing_names: HashSet<String> = ["Event_Paris_2023-10-15_0_01.jpg".to_string()].into();
+
+        let renamed = apply_bursts(&mut analyses, SeriesNumbering::default(), "_", 0, &mut existing_names, &shots, None);
+        assert_eq!(renamed, vec![0, 1, 2]);
+        let names: Vec<_> = analyses.iter().filter_map(|analysis| analysis.proposed_name.as_deref()).collect();
+        assert_eq!(
+            names,
+            ["Event_Paris_2023-10-15_0_2_01.jpg", "Event_Paris_2023-10-15_0_2_02.jpg", "Event_Paris_2023-10-15_0_2_03.jpg"]
+        );
+    }
+
+    #[test]
+    fn test_strip_number() {
+        assert_eq!(SeriesPattern::strip_number("Paris_2023-10-15_0101"), Some("Paris_2023-10-15"));
+        assert_eq!(SeriesPattern::strip_number("Scan(3)"), Some("Scan"));
+        assert_eq!(SeriesPattern::strip_number("Paris"), None);
+    }
 }
@@ -9,25 +9,39 @@ lazy_static::lazy_static! {
 /// Analyzes original filename stem to extract meaningful parts
 /// Removes common prefixes and filters out date/numeric patterns
 pub fn extract_meaningful_stem(path: &Path) -> Option<String> {
-    let stem = path.file_stem()?.to_str()?;
+    let stem = crate::generator::split_extension(path.file_name()?.to_str()?).0;
 
     // Remove common prefixes
     let cleaned = remove_common_prefixes(stem);
 
-    // Split on common separators, but preserve date patterns like "2021-08-23"
+    // Split on common separators, but preserve date patterns like "2021-08-23" or
+    // "15_Octubre_2023"
     // First, replace date-like patterns with a placeholder, split, then restore
     let date_placeholder = "\x00DATE\x00";
-    let mut temp = cleaned.clone();
     let mut preserved_dates = Vec::new();
 
-    // Match patterns like YYYY-MM-DD, YYYY/MM/DD, or YYYYMMDD
-    let date_regex = &*DATE_REGEX;
-    for cap in date_regex.captures_iter(&cleaned) {
-        if let Some(matched) = cap.get(0) {
-            preserved_dates.push(matched.as_str().to_string());
-            temp = temp.replace(matched.as_str(), date_placeholder);
+    // Dates written with month names (in several languages), then patterns like
+    // YYYY-MM-DD, YYYY/MM/DD, or YYYYMMDD elsewhere
+    let mut date_spans: Vec<_> = crate::location_timestamp::find_written_dates(&cleaned)
+        .into_iter()
+        .map(|(range, _)| range)
+        .collect();
+    for matched in DATE_REGEX.find_iter(&cleaned) {
+        if !date_spans.iter().any(|span| span.start < matched.end() && matched.start() < span.end) {
+            date_spans.push(matched.range());
         }
     }
+    date_spans.sort_by_key(|span| span.start);
+
+    let mut temp = String::with_capacity(cleaned.len());
+    let mut rest = 0;
+    for span in date_spans {
+        temp.push_str(&cleaned[rest..span.start]);
+        temp.push_str(date_placeholder);
+        preserved_dates.push(cleaned[span.clone()].to_string());
+        rest = span.end;
+    }
+    temp.push_str(&cleaned[rest..]);
 
     // Split on common separators
     let mut parts: Vec<String> = temp
@@ -50,14 +64,14 @@ pub fn extract_meaningful_stem(path: &Path) -> Option<String> {
     // Separate date parts from other meaningful parts
     let date_parts: Vec<&str> = parts
         .iter()
-        .filter(|p| is_date_pattern(p))
+        .filter(|p| is_date_part(p))
         .copied()
         .collect();
 
     // Filter meaningful non-date parts
     let meaningful: Vec<&str> = parts
         .iter()
-        .filter(|p| !is_date_pattern(p) && is_meaningful_part(p))
+        .filter(|p| !is_date_part(p) && is_meaningful_part(p))
         .copied()
         .collect();
 
@@ -175,6 +189,11 @@ fn is_meaningful_part(part: &str) -> bool {
     part.chars().any(|c| c.is_alphabetic())
 }
 
+/// Detects numeric dates and dates written with a month name
+fn is_date_part(s: &str) -> bool {
+    is_date_pattern(s) || crate::location_timestamp::parse_written_date(s).is_some()
+}
+
 /// Detects date-like patterns
 fn is_date_pattern(s: &str) -> bool {
     // Remove separators
@@ -403,6 +422,17 @@ mod tests {
         assert_eq!(extract_meaningful_stem(&path), Some("20231015-143022".to_string()));
     }
 
+    #[test]
+    fn test_extract_meaningful_stem_written_dates() {
+        // Month names aren't meaningful words; the date is kept whole for the generator
+        let path = PathBuf::from("/test/Factura_Telefonica_15_Octubre_2023.pdf");
+        assert_eq!(extract_meaningful_stem(&path), Some("Factura_Telefonica".to_string()));
+        let path = PathBuf::from("/test/Foto 15. Oktober 2023.jpg");
+        assert_eq!(extract_meaningful_stem(&path), Some("15. Oktober 2023".to_string()));
+        let path = PathBuf::from("/test/2023年10月15日_001.jpg");
+        assert_eq!(extract_meaningful_stem(&path), Some("2023年10月15日".to_string()));
+    }
+
     #[test]
     fn test_extract_meaningful_stem_mixed() {
         let path = PathBuf::from("/test/Screenshot_2023_10_15_MyApp_Demo.png");
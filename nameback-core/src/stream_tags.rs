@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::debug;
+
+use crate::tool_command::ToolCommand;
+
+/// Tags carried by a video's audio stream or container
+/// (music videos, concert downloads, TV captures)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StreamTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub show: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeEntry>,
+    format: Option<FfprobeEntry>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeEntry {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Reads title/artist/show tags with ffprobe (ships with ffmpeg)
+/// Returns empty tags when ffmpeg isn't installed
+pub fn read_stream_tags(path: &Path) -> Result<StreamTags> {
+    if !crate::deps_check::Dependency::FFmpeg.is_available() {
+        debug!("ffmpeg not available, skipping stream tags");
+        return Ok(StreamTags::default());
+    }
+
+    let output = crate::deps_check::create_command("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("a")
+        .arg("-show_entries")
+        .arg("stream_tags:format_tags")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .tool_output()
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed with status: {}", output.status);
+    }
+
+    parse_ffprobe_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Picks the tags out of ffprobe's JSON, preferring audio streams over the container
+fn parse_ffprobe_output(json: &str) -> Result<StreamTags> {
+    let parsed: FfprobeOutput =
+        serde_json::from_str(json).context("Failed to parse ffprobe JSON output")?;
+
+    let sources: Vec<&HashMap<String, String>> = parsed
+        .streams
+        .iter()
+        .chain(parsed.format.iter())
+        .map(|entry| &entry.tags)
+        .collect();
+
+    // Tag names vary in case between containers ("TITLE" in Matroska, "title" in MP4)
+    let tag = |names: &[&str]| {
+        sources.iter().find_map(|tags| {
+            names.iter().find_map(|name| {
+                tags.iter()
+                    .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                    .map(|(_, value)| value.trim().to_string())
+            })
+        })
+    };
+
+    Ok(StreamTags {
+        title: tag(&["title"]),
+        artist: tag(&["artist", "album_artist"]),
+        show: tag(&["show"]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ffprobe_output() {
+        let json = r#"{
+            "programs": [],
+            "streams": [{"tags": {"language": "eng", "TITLE": "Live at Wembley", "ARTIST": " "}}],
+            "format": {"tags": {"title": "track01", "album_artist": "Queen", "show": "Concert Hour"}}
+        }"#;
+        let tags = parse_ffprobe_output(json).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Live at Wembley"));
+        assert_eq!(tags.artist.as_deref(), Some("Queen"));
+        assert_eq!(tags.show.as_deref(), Some("Concert Hour"));
+
+        let tags = parse_ffprobe_output(r#"{"streams": []}"#).unwrap();
+        assert_eq!(tags, StreamTags::default());
+    }
+}
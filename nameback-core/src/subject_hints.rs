@@ -0,0 +1,214 @@
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::{ImageFormat, RgbImage};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::debug;
+
+/// Rough subject of a photo, guessed from its pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    DocumentScan,
+    Receipt,
+    Whiteboard,
+    Screenshot,
+    Person,
+    Landscape,
+}
+
+impl Subject {
+    /// Name used as a filename candidate
+    pub fn label(&self) -> &'static str {
+        match self {
+            Subject::DocumentScan => "Document Scan",
+            Subject::Receipt => "Receipt",
+            Subject::Whiteboard => "Whiteboard",
+            Subject::Screenshot => "Screenshot",
+            Subject::Person => "Portrait",
+            Subject::Landscape => "Landscape",
+        }
+    }
+}
+
+/// Side of the square thumbnail the image is reduced to before classifying
+const THUMBNAIL_SIZE: u32 = 96;
+
+/// Guesses what a photo shows: paper (document, receipt, whiteboard), a screenshot,
+/// a person or a landscape
+/// `has_camera` is whether the file names the camera that took it (screenshots never do)
+/// Returns None for formats the image crate can't decode (e.g. HEIC) or unclear images
+pub fn detect_subject(path: &Path, has_camera: bool) -> Result<Option<Subject>> {
+    let format = match ImageFormat::from_path(path) {
+        Ok(format) if format.reading_enabled() => format,
+        _ => return Ok(None),
+    };
+
+    let (image, _) = crate::image_ocr::load_oriented(path)?;
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        return Ok(None);
+    }
+
+    // Nearest-neighbour sampling keeps the flat colors of screenshots exact
+    let thumbnail = image
+        .resize_exact(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Nearest)
+        .to_rgb8();
+    let aspect = height as f32 / width as f32;
+    let subject = classify(&thumbnail, aspect, has_camera, format == ImageFormat::Png);
+    debug!("Subject of {}: {:?}", path.display(), subject);
+    Ok(subject)
+}
+
+/// Classifies a square thumbnail of an image with the given height / width ratio
+fn classify(thumbnail: &RgbImage, aspect: f32, has_camera: bool, is_png: bool) -> Option<Subject> {
+    let stats = PixelStats::of(thumbnail);
+
+    // Screen captures are drawn in flat colors; photos and scans always carry noise
+    if is_png && !has_camera && stats.most_common_color > 0.3 {
+        return Some(Subject::Screenshot);
+    }
+
+    // Light, colorless background with some dark ink on it
+    if stats.paper > 0.55 && (0.01..0.4).contains(&stats.dark) {
+        return Some(if aspect > 2.0 {
+            Subject::Receipt
+        } else if aspect < 1.0 {
+            Subject::Whiteboard
+        } else {
+            Subject::DocumentScan
+        });
+    }
+
+    if stats.center_skin > 0.2 {
+        return Some(Subject::Person);
+    }
+    if aspect < 1.0 && stats.top_sky > 0.35 {
+        return Some(Subject::Landscape);
+    }
+    None
+}
+
+/// Fractions of thumbnail pixels with telling colors
+#[derive(Debug, Default)]
+struct PixelStats {
+    /// Light and nearly gray, like paper or a whiteboard
+    paper: f32,
+    /// Dark, like ink or marker
+    dark: f32,
+    /// Skin tones in the middle third of the image
+    center_skin: f32,
+    /// Sky blue in the top third of the image
+    top_sky: f32,
+    /// Share of the single most common exact color
+    most_common_color: f32,
+}
+
+impl PixelStats {
+    fn of(image: &RgbImage) -> Self {
+        let (width, height) = image.dimensions();
+        let total = (width * height) as f32;
+        let mut stats = PixelStats::default();
+        let mut colors: HashMap<[u8; 3], u32> = HashMap::new();
+        let (mut center, mut top) = (0u32, 0u32);
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b] = pixel.0;
+            *colors.entry(pixel.0).or_default() += 1;
+
+            let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+            let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            let saturation = if max == 0 { 0.0 } else { (max - min) as f32 / max as f32 };
+
+            if luma > 170.0 && saturation < 0.15 {
+                stats.paper += 1.0;
+            }
+            if luma < 80.0 {
+                stats.dark += 1.0;
+            }
+
+            let in_center = (width / 3..2 * width / 3).contains(&x) && (height / 3..2 * height / 3).contains(&y);
+            if in_center {
+                center += 1;
+                if is_skin(r, g, b) {
+                    stats.center_skin += 1.0;
+                }
+            }
+            if y < height / 3 {
+                top += 1;
+                if b > r && b >= g && luma > 100.0 && saturation > 0.15 {
+                    stats.top_sky += 1.0;
+                }
+            }
+        }
+
+        stats.paper /= total;
+        stats.dark /= total;
+        stats.center_skin /= center.max(1) as f32;
+        stats.top_sky /= top.max(1) as f32;
+        stats.most_common_color = colors.values().copied().max().unwrap_or(0) as f32 / total;
+        stats
+    }
+}
+
+/// RGB skin-tone rule (Kovac et al.), good enough to notice a face filling the frame
+fn is_skin(r: u8, g: u8, b: u8) -> bool {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    r > 95 && g > 40 && b > 20 && max - min > 15 && r.abs_diff(g) > 15 && r > g && r > b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Thumbnail filled by `color(x, y)`
+    fn thumbnail(color: impl Fn(u32, u32) -> [u8; 3]) -> RgbImage {
+        RgbImage::from_fn(THUMBNAIL_SIZE, THUMBNAIL_SIZE, |x, y| Rgb(color(x, y)))
+    }
+
+    /// Off-white paper with slightly varying shades and lines of dark text
+    fn paper(x: u32, y: u32) -> [u8; 3] {
+        if y.is_multiple_of(8) && !x.is_multiple_of(3) {
+            [30, 30, 35]
+        } else {
+            let shade = 225 + ((x * 7 + y * 13) % 20) as u8;
+            [shade, shade, shade - 3]
+        }
+    }
+
+    #[test]
+    fn test_classify_paper_by_shape() {
+        let scan = thumbnail(paper);
+        assert_eq!(classify(&scan, 1.41, true, false), Some(Subject::DocumentScan));
+        assert_eq!(classify(&scan, 3.0, true, false), Some(Subject::Receipt));
+        assert_eq!(classify(&scan, 0.75, true, false), Some(Subject::Whiteboard));
+    }
+
+    #[test]
+    fn test_classify_screenshot() {
+        // A flat window background with a darker sidebar
+        let screen = thumbnail(|x, _| if x < 20 { [40, 44, 52] } else { [250, 250, 250] });
+        assert_eq!(classify(&screen, 0.56, false, true), Some(Subject::Screenshot));
+        // Camera photos and JPEGs aren't screenshots
+        assert_ne!(classify(&screen, 0.56, true, true), Some(Subject::Screenshot));
+        assert_ne!(classify(&screen, 0.56, false, false), Some(Subject::Screenshot));
+    }
+
+    #[test]
+    fn test_classify_person_and_landscape() {
+        let portrait = thumbnail(|x, y| [200 - ((x + y) % 30) as u8, 150, 120]);
+        assert_eq!(classify(&portrait, 1.5, true, false), Some(Subject::Person));
+
+        let landscape = thumbnail(|x, y| {
+            let noise = ((x * 5 + y * 3) % 16) as u8;
+            if y < THUMBNAIL_SIZE / 2 {
+                [90 + noise, 150 + noise, 230]
+            } else {
+                [60 + noise, 120 + noise, 50]
+            }
+        });
+        assert_eq!(classify(&landscape, 0.66, true, false), Some(Subject::Landscape));
+        // Sky in a portrait-format photo says too little
+        assert_eq!(classify(&landscape, 1.5, true, false), None);
+    }
+}
@@ -0,0 +1,99 @@
+use regex::Regex;
+
+/// Variables available to filename templates
+/// - `name`: the name nameback picked from metadata, content or context
+/// - `title`: the metadata title, or the picked name when there is none
+/// - `camera`: camera make and model, e.g. `Nikon_Z6`
+/// - `lens`: lens model, e.g. `NIKKOR_Z_24-70mm_f4_S`
+/// - `artist`: artist from audio or video tags
+/// - `show`: TV show from video stream tags
+/// - `date`, `yyyy`, `mm`, `dd`: capture or creation date, e.g. `2024-03-15`
+/// - `location`: city or coordinates from GPS (empty when location is disabled)
+/// - `category`: detected file type, e.g. `image`, `document`
+/// - `ext`: the original extension, without the dot
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "name", "title", "camera", "lens", "artist", "show", "date", "yyyy", "mm", "dd", "location",
+    "category", "ext",
+];
+
+/// Variables that put the date in the name, so it isn't appended again
+pub(crate) const DATE_VARIABLES: &[&str] = &["date", "yyyy", "mm", "dd"];
+
+fn placeholder() -> Regex {
+    Regex::new(r"\{([^{}]*)\}").unwrap()
+}
+
+/// Expand the `{variable}` placeholders in `template`
+/// Variables without a value expand to nothing
+pub fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    placeholder()
+        .replace_all(template, |caps: &regex::Captures| {
+            value(&caps[1]).unwrap_or_default()
+        })
+        .into_owned()
+}
+
+/// First placeholder in `template` that isn't one of `TEMPLATE_VARIABLES`
+pub fn unknown_variable(template: &str) -> Option<String> {
+    placeholder()
+        .captures_iter(template)
+        .map(|caps| caps[1].to_string())
+        .find(|name| !TEMPLATE_VARIABLES.contains(&name.as_str()))
+}
+
+/// Whether `template` uses any of `variables`
+pub(crate) fn uses(template: &str, variables: &[&str]) -> bool {
+    placeholder()
+        .captures_iter(template)
+        .any(|caps| variables.contains(&&caps[1]))
+}
+
+/// Why `template` could produce a path outside the file's folder, if it could
+/// `/` in a template creates subfolders next to the file; absolute paths and `..` are refused
+pub fn unsafe_path(template: &str) -> Option<&'static str> {
+    let starts_at_root = template.starts_with(['/', '\\'])
+        || (template.chars().nth(1) == Some(':')
+            && template.starts_with(|c: char| c.is_ascii_alphabetic()));
+    if starts_at_root {
+        return Some("must be relative to the file's folder");
+    }
+    if template.split(['/', '\\']).any(|component| component.trim() == "..") {
+        return Some("can't leave the file's folder with ..");
+    }
+    if template.ends_with(['/', '\\']) {
+        return Some("must end with a file name, not a folder");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_validate() {
+        let value = |name: &str| match name {
+            "name" => Some("Sunset".to_string()),
+            "camera" => Some("Nikon_Z6".to_string()),
+            _ => None,
+        };
+        assert_eq!(render("{camera}_{name}", value), "Nikon_Z6_Sunset");
+        assert_eq!(render("{name}-{lens}", value), "Sunset-");
+        assert_eq!(render("plain", value), "plain");
+
+        assert_eq!(unknown_variable("{camera}_{name}_{lens}"), None);
+        assert_eq!(unknown_variable("{name}_{camra}"), Some("camra".to_string()));
+        assert_eq!(unknown_variable("{category}/{yyyy}/{title}.{ext}"), None);
+        assert!(uses("{yyyy}/{name}", DATE_VARIABLES));
+        assert!(!uses("{name}", DATE_VARIABLES));
+    }
+
+    #[test]
+    fn test_unsafe_path() {
+        assert_eq!(unsafe_path("{category}/{yyyy}/{title}"), None);
+        assert!(unsafe_path("/tmp/{name}").is_some());
+        assert!(unsafe_path("C:{name}").is_some());
+        assert!(unsafe_path("../{name}").is_some());
+        assert!(unsafe_path("{yyyy}/").is_some());
+    }
+}
@@ -1,29 +1,32 @@
 use anyhow::Result;
-use log::debug;
+use tracing::debug;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+use crate::key_phrases::PhraseLimits;
+
 /// Extracts meaningful content from text-based files (txt, csv, md, etc.)
-/// Returns the first useful portion suitable for a filename
-pub fn extract_text_content(path: &Path) -> Result<Option<String>> {
+/// Returns the first useful portion suitable for a filename; long text is named from its key
+/// phrases, as many and as long as `phrases` allows
+pub fn extract_text_content(path: &Path, phrases: PhraseLimits) -> Result<Option<String>> {
     let extension = path
         .extension()
         .and_then(|ext| ext.to_str())
         .map(|s| s.to_lowercase());
 
     match extension.as_deref() {
-        Some("md") | Some("markdown") => extract_from_markdown(path),
+        Some("md") | Some("markdown") => extract_from_markdown(path, phrases),
         Some("csv") => extract_from_csv(path),
-        Some("txt") | Some("text") => extract_from_plain_text(path),
-        Some("json") => extract_from_json(path),
-        Some("yaml") | Some("yml") => extract_from_yaml(path),
-        _ => extract_from_plain_text(path), // Default fallback
+        Some("txt") | Some("text") => extract_from_plain_text(path, phrases),
+        Some("json") => extract_from_json(path, phrases),
+        Some("yaml") | Some("yml") => extract_from_yaml(path, phrases),
+        _ => extract_from_plain_text(path, phrases), // Default fallback
     }
 }
 
 /// Extracts first heading from markdown file
-fn extract_from_markdown(path: &Path) -> Result<Option<String>> {
+fn extract_from_markdown(path: &Path, phrases: PhraseLimits) -> Result<Option<String>> {
     debug!("Attempting to extract markdown heading from: {}", path.display());
 
     let file = File::open(path)?;
@@ -86,7 +89,7 @@ fn extract_from_markdown(path: &Path) -> Result<Option<String>> {
     }
 
     // Fallback to first non-empty line if no heading found
-    extract_from_plain_text(path)
+    extract_from_plain_text(path, phrases)
 }
 
 /// Checks if a heading is too generic to be useful
@@ -197,7 +200,7 @@ fn extract_from_csv(path: &Path) -> Result<Option<String>> {
 }
 
 /// Extracts first meaningful line from plain text file
-fn extract_from_plain_text(path: &Path) -> Result<Option<String>> {
+fn extract_from_plain_text(path: &Path, phrases: PhraseLimits) -> Result<Option<String>> {
     debug!("Attempting to extract text from: {}", path.display());
 
     let file = File::open(path)?;
@@ -229,11 +232,9 @@ fn extract_from_plain_text(path: &Path) -> Result<Option<String>> {
         // Use key phrase extraction for longer text
         if cleaned.len() > 150 && line_count > 3 {
             debug!("Extracting key phrases from text file ({} chars, {} lines)", cleaned.len(), line_count);
-            let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-            if !phrases.is_empty() {
-                let best_phrase = &phrases[0];
-                debug!("Selected key phrase from text: {}", best_phrase);
-                return Ok(Some(best_phrase.clone()));
+            if let Some(phrase) = crate::key_phrases::summarize(&cleaned, phrases) {
+                debug!("Selected key phrase from text: {}", phrase);
+                return Ok(Some(phrase));
             }
         }
 
@@ -247,7 +248,7 @@ fn extract_from_plain_text(path: &Path) -> Result<Option<String>> {
 }
 
 /// Attempts to extract title/name field from JSON file
-fn extract_from_json(path: &Path) -> Result<Option<String>> {
+fn extract_from_json(path: &Path, phrases: PhraseLimits) -> Result<Option<String>> {
     debug!("Attempting to extract from JSON: {}", path.display());
 
     let file = File::open(path)?;
@@ -298,7 +299,7 @@ fn extract_from_json(path: &Path) -> Result<Option<String>> {
     }
 
     // Fallback to first meaningful text
-    extract_from_plain_text(path)
+    extract_from_plain_text(path, phrases)
 }
 
 /// Recursively searches JSON for a nested field path
@@ -321,7 +322,7 @@ fn search_json_path(json: &serde_json::Value, path: &[&str]) -> Option<String> {
 }
 
 /// Attempts to extract title/name field from YAML file
-fn extract_from_yaml(path: &Path) -> Result<Option<String>> {
+fn extract_from_yaml(path: &Path, phrases: PhraseLimits) -> Result<Option<String>> {
     debug!("Attempting to extract from YAML: {}", path.display());
 
     let file = File::open(path)?;
@@ -347,7 +348,7 @@ fn extract_from_yaml(path: &Path) -> Result<Option<String>> {
     }
 
     // Fallback to first meaningful line
-    extract_from_plain_text(path)
+    extract_from_plain_text(path, phrases)
 }
 
 /// Cleans text for use in filenames (similar to pdf_content clean_text)
@@ -380,21 +381,9 @@ fn clean_text(text: &str) -> String {
 }
 
 /// Truncates text to specified length, trying to break at word boundaries
+/// (at punctuation for Chinese and Japanese, which have no spaces; see `script::truncate`)
 fn truncate_text(text: &str, max_len: usize) -> String {
-    if text.len() <= max_len {
-        return text.to_string();
-    }
-
-    // Try to break at last space before max_len
-    let truncated = &text[..max_len];
-    if let Some(last_space) = truncated.rfind(' ') {
-        if last_space > max_len / 2 {
-            return truncated[..last_space].to_string();
-        }
-    }
-
-    // No good break point, just truncate
-    truncated.to_string()
+    crate::script::truncate(text, max_len)
 }
 
 #[cfg(test)]
@@ -424,4 +413,29 @@ mod tests {
         assert!(result.len() < 50);
         assert!(result.len() > 25); // At least half the max length
     }
+
+    #[test]
+    fn test_multilingual_text_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let extract = |name: &str, text: &str| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, text).unwrap();
+            extract_text_content(&path, PhraseLimits::default()).unwrap().unwrap()
+        };
+
+        // Chinese: cut at the full stop rather than inside a character (80 bytes isn't a boundary)
+        let chinese = "二〇二三年第三季度华东华南华北地区销售业绩报告。本报告总结了各地区的销售业绩以及明年的目标。\n".repeat(5);
+        assert_eq!(extract("report.txt", &chinese), "二〇二三年第三季度华东华南华北地区销售业绩报告");
+
+        // Japanese lines are too long for key phrases, which need spaces
+        let japanese = "東京本社の年次報告書と大阪支社の業績についてのまとめ\n".repeat(6);
+        let title = extract("notes.txt", &japanese);
+        assert!(japanese.starts_with(&title) && title.chars().count() <= 40, "{}", title);
+
+        // Arabic and Hebrew break between words
+        let arabic = extract("ar.yaml", "title: التقرير السنوي للشركة العربية للاستثمار والتنمية الاقتصادية في منطقة الخليج\n");
+        assert!(arabic.starts_with("التقرير السنوي") && !arabic.ends_with(' '));
+        assert!("التقرير السنوي للشركة العربية للاستثمار والتنمية الاقتصادية في منطقة الخليج".starts_with(&arabic));
+        assert_eq!(extract("he.json", r#"{"title": "דוח רבעוני"}"#), "דוח רבעוני");
+    }
 }
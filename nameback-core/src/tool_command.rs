@@ -0,0 +1,356 @@
+use std::cell::RefCell;
+use std::io::{self, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::profiling::Stage;
+
+/// Longest an external tool may run before it is stopped (`RenameConfig::tool_timeout`)
+pub(crate) const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Longest wait between checks whether a tool has exited
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// The runner of the analysis work on this thread, and the tools it stopped
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+struct Current {
+    runner: ToolRunner,
+    timed_out: Vec<(Stage, String)>,
+}
+
+/// How the external tools of an analysis run: each stopped once it runs longer than
+/// `timeout`, and for `RenameEngine::analyze_directory_async` as tasks on its tokio runtime
+#[derive(Clone)]
+pub(crate) struct ToolRunner {
+    timeout: Duration,
+    runtime: Option<AsyncRuntime>,
+}
+
+/// The runtime of an async analysis, running at most `permits` tools at once
+#[derive(Clone)]
+struct AsyncRuntime {
+    handle: Handle,
+    permits: Arc<Semaphore>,
+}
+
+impl ToolRunner {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self { timeout, runtime: None }
+    }
+
+    /// Run tools as tasks on `handle`, at most `max` at once (at least one)
+    pub(crate) fn on_runtime(mut self, handle: Handle, max: usize) -> Self {
+        self.runtime = Some(AsyncRuntime {
+            handle,
+            permits: Arc::new(Semaphore::new(max.max(1))),
+        });
+        self
+    }
+
+    /// Analyze one file with `work`, its tools (started through `ToolCommand`) run by this
+    /// runner. Once one of them is stopped, the file's other tools aren't started
+    pub(crate) fn enter<T>(&self, work: impl FnOnce() -> T) -> T {
+        let current = Current {
+            runner: self.clone(),
+            timed_out: Vec::new(),
+        };
+        let previous = CURRENT.with(|c| c.replace(Some(current)));
+        let result = work();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+        result
+    }
+
+    fn output(&self, command: &mut Command) -> io::Result<Output> {
+        match &self.runtime {
+            Some(runtime) => runtime.output(command, self.timeout),
+            None => output_with_timeout(command, self.timeout),
+        }
+    }
+}
+
+impl AsyncRuntime {
+    /// Spawn `command` on the runtime and block this (analysis) thread until it is done
+    fn output(&self, command: &mut Command, timeout: Duration) -> io::Result<Output> {
+        let program = program_name(command);
+        let mut command = tokio::process::Command::from(std::mem::replace(command, Command::new(&program)));
+        // Dropped when the timeout fires, which kills the tool
+        command.kill_on_drop(true);
+
+        let permits = Arc::clone(&self.permits);
+        let (sender, receiver) = oneshot::channel();
+        self.handle.spawn(async move {
+            let result = match permits.acquire_owned().await {
+                Ok(_permit) => match tokio::time::timeout(timeout, command.output()).await {
+                    Ok(output) => output,
+                    Err(_) => Err(timeout_error(&program, timeout)),
+                },
+                Err(e) => Err(io::Error::other(e)),
+            };
+            let _ = sender.send(result);
+        });
+        receiver
+            .blocking_recv()
+            .unwrap_or_else(|_| Err(io::Error::other("async runtime shut down while a tool was running")))
+    }
+}
+
+/// Runs external tools for the analysis, like `Command::output` but stopped after the
+/// timeout of the analysis the calling thread works for (`DEFAULT_TOOL_TIMEOUT` outside one)
+pub(crate) trait ToolCommand {
+    fn tool_output(&mut self) -> io::Result<Output>;
+}
+
+impl ToolCommand for Command {
+    fn tool_output(&mut self) -> io::Result<Output> {
+        let program = program_name(self);
+        let Some(runner) = current_runner(&program)? else {
+            return output_with_timeout(self, DEFAULT_TOOL_TIMEOUT);
+        };
+
+        let result = runner.output(self);
+        if let Err(e) = &result {
+            if e.kind() == io::ErrorKind::TimedOut {
+                record_timeout(&program, e);
+            }
+        }
+        result
+    }
+}
+
+/// Runs `work` for a tool used in-process (tesseract) under the same timeout as
+/// `ToolCommand::tool_output`. The work can't be stopped: once it runs too long it is left to
+/// finish on its own thread and its result is dropped
+pub(crate) fn run_in_process<T: Send + 'static>(
+    program: &str,
+    work: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    let timeout = current_runner(program)?.map_or(DEFAULT_TOOL_TIMEOUT, |runner| runner.timeout);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            let error = timeout_error(program, timeout);
+            record_timeout(program, &error);
+            Err(error.into())
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(anyhow::anyhow!("{} crashed", program)),
+    }
+}
+
+/// The runner of the analysis on this thread (None outside one), or an error when an
+/// earlier tool of the file timed out
+fn current_runner(program: &str) -> io::Result<Option<ToolRunner>> {
+    let current = CURRENT.with(|c| {
+        c.borrow()
+            .as_ref()
+            .map(|current| (current.runner.clone(), !current.timed_out.is_empty()))
+    });
+    match current {
+        Some((_, true)) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} not run: an earlier tool timed out on this file", program),
+        )),
+        Some((runner, false)) => Ok(Some(runner)),
+        None => Ok(None),
+    }
+}
+
+fn record_timeout(program: &str, error: &io::Error) {
+    CURRENT.with(|c| {
+        if let Some(current) = c.borrow_mut().as_mut() {
+            current.timed_out.push((stage_of(program), error.to_string()));
+        }
+    });
+}
+
+/// The stage a tool works for: the metadata readers for `Stage::Exif`, the rest extract content
+fn stage_of(program: &str) -> Stage {
+    match program {
+        "exiftool" | "ffprobe" => Stage::Exif,
+        _ => Stage::Ocr,
+    }
+}
+
+/// What tools the analysis on this thread stopped since the last call (see `ToolRunner::enter`),
+/// with the stage each worked for
+pub(crate) fn take_timeouts() -> Vec<(Stage, String)> {
+    CURRENT.with(|c| {
+        c.borrow_mut()
+            .as_mut()
+            .map(|current| std::mem::take(&mut current.timed_out))
+            .unwrap_or_default()
+    })
+}
+
+/// Whether `error` comes from a tool that was stopped for running too long
+pub(crate) fn is_timeout(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::TimedOut)
+}
+
+pub(crate) fn timeout_error(program: &str, timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("{} ran longer than {:?} and was stopped", program, timeout),
+    )
+}
+
+/// The program's file name, e.g. `ffmpeg`
+fn program_name(command: &Command) -> String {
+    let program = std::path::Path::new(command.get_program());
+    program
+        .file_stem()
+        .unwrap_or(program.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `Command::output`, killing the tool (and reaping it, so no zombie is left) once it runs
+/// longer than `timeout`
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Read on their own threads, so a tool filling a pipe can't block while we wait
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = match wait_timeout(&mut child, timeout)? {
+        Some(status) => status,
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            // The readers finish once the pipes close; there is nothing left to wait for
+            return Err(timeout_error(&program_name(command), timeout));
+        }
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// The child's exit status, or None if it is still running after `timeout`
+/// Checks often at first, so quick tools aren't slowed down
+fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<std::process::ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+    }
+
+    #[test]
+    fn test_tool_runs_on_runtime() {
+        let runtime = runtime();
+        let runner = ToolRunner::new(Duration::from_secs(10)).on_runtime(runtime.handle().clone(), 1);
+        let output = runtime.block_on(async {
+            tokio::task::spawn_blocking(move || runner.enter(|| Command::new("echo").arg("hello").tool_output()))
+                .await
+                .unwrap()
+        });
+        assert_eq!(String::from_utf8_lossy(&output.unwrap().stdout), "hello\n");
+    }
+
+    #[test]
+    fn test_async_tool_timeout() {
+        let runtime = runtime();
+        let runner = ToolRunner::new(Duration::from_millis(100)).on_runtime(runtime.handle().clone(), 1);
+        let started = Instant::now();
+        let result = runtime.block_on(async {
+            tokio::task::spawn_blocking(move || runner.enter(|| Command::new("sleep").arg("5").tool_output()))
+                .await
+                .unwrap()
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_tool_timeout() {
+        // Output is collected as Command::output does
+        let output = Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]).tool_output().unwrap();
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        assert_eq!(output.status.code(), Some(3));
+
+        let runner = ToolRunner::new(Duration::from_millis(100));
+        let started = Instant::now();
+        let (first, second, timeouts) = runner.enter(|| {
+            let first = Command::new("sleep").arg("5").tool_output();
+            // The file's other tools aren't started after a timeout
+            let second = Command::new("echo").tool_output();
+            (first, second, take_timeouts())
+        });
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(first.unwrap_err().to_string(), "sleep ran longer than 100ms and was stopped");
+        assert_eq!(second.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(timeouts, [(Stage::Ocr, "sleep ran longer than 100ms and was stopped".to_string())]);
+
+        // The next file starts afresh
+        assert!(runner.enter(|| Command::new("echo").tool_output()).is_ok());
+    }
+
+    #[test]
+    fn test_in_process_timeout() {
+        let runner = ToolRunner::new(Duration::from_millis(100));
+        let (quick, slow, skipped, timeouts) = runner.enter(|| {
+            let quick = run_in_process("tesseract", || Ok(1));
+            let slow = run_in_process("tesseract", || {
+                thread::sleep(Duration::from_secs(5));
+                Ok(2)
+            });
+            let skipped = run_in_process("tesseract", || Ok(3));
+            (quick, slow, skipped, take_timeouts())
+        });
+        assert_eq!(quick.unwrap(), 1);
+        assert!(is_timeout(&slow.unwrap_err()));
+        assert!(is_timeout(&skipped.unwrap_err()));
+        assert_eq!(timeouts, [(Stage::Ocr, "tesseract ran longer than 100ms and was stopped".to_string())]);
+        assert_eq!(stage_of("exiftool"), Stage::Exif);
+    }
+}
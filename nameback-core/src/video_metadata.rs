@@ -0,0 +1,249 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::debug;
+
+use crate::tool_command::ToolCommand;
+
+/// Names a video carries besides its tags: chapter titles and the subtitle script's title
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoMetadata {
+    /// First chapter title that isn't a placeholder like "Chapter 1"
+    pub chapter_title: Option<String>,
+    /// `Title:` from the [Script Info] header of the video's ASS/SSA subtitles
+    pub subtitle_header: Option<String>,
+}
+
+impl VideoMetadata {
+    /// The name to use for the video, preferring the chapter title
+    pub fn candidate_name(&self) -> Option<&str> {
+        self.chapter_title
+            .as_deref()
+            .or(self.subtitle_header.as_deref())
+    }
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    #[serde(default)]
+    chapters: Vec<FfprobeChapter>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeStream {
+    index: usize,
+    codec_name: Option<String>,
+    #[serde(default)]
+    disposition: HashMap<String, u8>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeChapter {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// What ffprobe reports about a video's chapters and subtitle tracks
+#[derive(Debug, Default, PartialEq)]
+struct ProbedVideo {
+    chapter_title: Option<String>,
+    /// Stream index of the subtitle track whose script header names the video
+    script_stream: Option<usize>,
+}
+
+/// Reads chapter titles and the subtitle header with ffprobe and ffmpeg
+/// Returns nothing when ffmpeg isn't installed
+pub fn read_video_metadata(path: &Path) -> Result<VideoMetadata> {
+    if !crate::deps_check::Dependency::FFmpeg.is_available() {
+        debug!("ffmpeg not available, skipping chapters and subtitles");
+        return Ok(VideoMetadata::default());
+    }
+
+    let output = crate::deps_check::create_command("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("s")
+        .arg("-show_entries")
+        .arg("stream=index,codec_name:stream_disposition=default:chapter_tags=title")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .tool_output()
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed with status: {}", output.status);
+    }
+
+    let probed = parse_ffprobe_output(&String::from_utf8_lossy(&output.stdout))?;
+    let subtitle_header = match (&probed.chapter_title, probed.script_stream) {
+        // The chapter title wins, so the subtitles needn't be read
+        (None, Some(stream)) => read_script_title(path, stream)?,
+        _ => None,
+    };
+
+    Ok(VideoMetadata {
+        chapter_title: probed.chapter_title,
+        subtitle_header,
+    })
+}
+
+/// Dumps the header of one ASS/SSA subtitle track and reads its title
+fn read_script_title(path: &Path, stream: usize) -> Result<Option<String>> {
+    let output = crate::deps_check::create_command("ffmpeg")
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-map")
+        .arg(format!("0:{}", stream))
+        .arg("-frames:s")
+        .arg("1")
+        .arg("-c:s")
+        .arg("copy")
+        .arg("-f")
+        .arg("ass")
+        .arg("-")
+        .tool_output()
+        .context("Failed to run ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg failed with status: {}", output.status);
+    }
+
+    Ok(parse_script_title(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Picks the first real chapter title and the subtitle track to read a header from
+/// (the default track if there is one, else the first ASS/SSA track)
+fn parse_ffprobe_output(json: &str) -> Result<ProbedVideo> {
+    let parsed: FfprobeOutput =
+        serde_json::from_str(json).context("Failed to parse ffprobe JSON output")?;
+
+    let chapter_title = parsed.chapters.iter().find_map(|chapter| {
+        chapter
+            .tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("title"))
+            .map(|(_, value)| value.trim())
+            .filter(|title| !is_placeholder_chapter(title))
+            .map(str::to_string)
+    });
+
+    let scripts: Vec<&FfprobeStream> = parsed
+        .streams
+        .iter()
+        .filter(|stream| matches!(stream.codec_name.as_deref(), Some("ass" | "ssa")))
+        .collect();
+    let script_stream = scripts
+        .iter()
+        .find(|stream| stream.disposition.get("default") == Some(&1))
+        .or(scripts.first())
+        .map(|stream| stream.index);
+
+    Ok(ProbedVideo {
+        chapter_title,
+        script_stream,
+    })
+}
+
+/// Whether a chapter title is one muxers make up ("Chapter 1", "Chapitre 02", "00:05:00.000")
+fn is_placeholder_chapter(title: &str) -> bool {
+    let re = Regex::new(
+        r"(?i)^((chapter|chapitre|kapitel|cap[ií]tulo|capitolo|hoofdstuk|chap\.?|ch\.?)?\s*\d+|\d{1,2}:\d{2}(:\d{2})?([.,]\d+)?)?$",
+    )
+    .unwrap();
+    re.is_match(title)
+}
+
+/// Reads `Title:` from an ASS/SSA script's [Script Info] section, ignoring editor defaults
+fn parse_script_title(script: &str) -> Option<String> {
+    let mut in_script_info = false;
+    for line in script.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_script_info = line.eq_ignore_ascii_case("[Script Info]");
+            continue;
+        }
+        if !in_script_info {
+            continue;
+        }
+        if let Some(title) = line.strip_prefix("Title:") {
+            let title = title.trim();
+            let untitled = ["", "Default Aegisub file", "<untitled>", "untitled"];
+            return (!untitled.iter().any(|u| title.eq_ignore_ascii_case(u)))
+                .then(|| title.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ffprobe_output_fixtures() {
+        let probed = parse_ffprobe_output(include_str!(
+            "../tests/fixtures/video_metadata/lecture_chapters.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            probed,
+            ProbedVideo {
+                chapter_title: Some("Introduction to Linear Algebra".to_string()),
+                script_stream: None,
+            }
+        );
+
+        let probed = parse_ffprobe_output(include_str!(
+            "../tests/fixtures/video_metadata/anime_subtitles.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            probed,
+            ProbedVideo {
+                chapter_title: None,
+                script_stream: Some(3),
+            }
+        );
+
+        let probed = parse_ffprobe_output(include_str!(
+            "../tests/fixtures/video_metadata/no_metadata.json"
+        ))
+        .unwrap();
+        assert_eq!(probed, ProbedVideo::default());
+    }
+
+    #[test]
+    fn test_parse_script_title_fixtures() {
+        assert_eq!(
+            parse_script_title(include_str!(
+                "../tests/fixtures/video_metadata/script_info.ass"
+            ))
+            .as_deref(),
+            Some("The Garden of Words")
+        );
+        assert_eq!(
+            parse_script_title(include_str!(
+                "../tests/fixtures/video_metadata/untitled_script.ass"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_placeholder_chapter() {
+        for title in ["Chapter 1", "chapter 07", "Chapitre 2", "Capítulo 3", "12", "00:05:00.000", "5:00", ""] {
+            assert!(is_placeholder_chapter(title), "{:?}", title);
+        }
+        for title in ["Introduction", "Chapter One: The Beginning", "Q3 Review"] {
+            assert!(!is_placeholder_chapter(title), "{:?}", title);
+        }
+    }
+}
@@ -1,10 +1,24 @@
 use anyhow::{Context, Result};
-use log::debug;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
+
+use crate::key_phrases::PhraseLimits;
+use crate::ocr_cleanup::OcrText;
+use crate::tool_command::ToolCommand;
 
 /// Extracts text from a video by extracting a frame and running OCR
 /// (requires ffmpeg and tesseract-ocr installed)
-pub fn extract_video_text(path: &Path) -> Result<Option<String>> {
+/// Text read with a mean confidence under `min_confidence` (0-100) isn't used
+pub fn extract_video_text(
+    path: &Path,
+    languages: &[String],
+    phrases: PhraseLimits,
+    min_confidence: u8,
+) -> Result<Option<String>> {
     debug!("Attempting video frame OCR on: {}", path.display());
 
     // Check if ffmpeg is available
@@ -29,34 +43,32 @@ pub fn extract_video_text(path: &Path) -> Result<Option<String>> {
     };
 
     // Run OCR on the frame
-    let result = run_tesseract_ocr(&frame_path);
+    let result = run_tesseract_ocr(&frame_path, languages);
 
     // Clean up temp frame file
     let _ = std::fs::remove_file(&frame_path);
 
     match result {
-        Ok(text) => {
-            let cleaned = clean_text(&text);
+        Ok(ocr) if ocr.confidence < f32::from(min_confidence) => {
+            debug!("Video OCR confidence {:.0} below minimum {}, skipping", ocr.confidence, min_confidence);
+            Ok(None)
+        }
+        Ok(ocr) => {
+            let cleaned = clean_text(&ocr.text);
             if cleaned.len() > 10 {
                 // Use key phrase extraction for longer video OCR text
                 if cleaned.len() > 150 {
                     debug!("Extracting key phrases from video OCR text ({} chars)", cleaned.len());
-                    let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-                    if !phrases.is_empty() {
-                        let best_phrase = &phrases[0];
-                        debug!("Selected key phrase from video OCR: {}", best_phrase);
-                        return Ok(Some(best_phrase.clone()));
+                    if let Some(phrase) = crate::key_phrases::summarize(&cleaned, phrases) {
+                        debug!("Selected key phrase from video OCR: {}", phrase);
+                        return Ok(Some(phrase));
                     }
                 }
 
                 // For shorter text or if key phrase extraction failed, truncate
-                let truncated = if cleaned.len() > 80 {
-                    &cleaned[..80]
-                } else {
-                    &cleaned
-                };
+                let truncated = crate::script::truncate(&cleaned, 80);
                 debug!("Video OCR extracted: {}", truncated);
-                Ok(Some(truncated.to_string()))
+                Ok(Some(truncated))
             } else {
                 debug!("Video OCR text too short");
                 Ok(None)
@@ -69,9 +81,66 @@ pub fn extract_video_text(path: &Path) -> Result<Option<String>> {
     }
 }
 
-/// Extracts text from multiple video frames and selects the best result
-/// Tries frames at 1s, 5s, and 10s, then scores each result for quality
-pub fn extract_video_text_multiframe(path: &Path) -> Result<Option<String>> {
+/// How multi-frame video OCR combines the text read from each frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameAggregation {
+    /// Prefer lines that recur across frames (titles, slide headers) over
+    /// text seen only once (tickers, captions); falls back to `Best`
+    #[default]
+    Vote,
+    /// Score each frame's text on its own and keep the single best one
+    Best,
+}
+
+impl fmt::Display for FrameAggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameAggregation::Vote => write!(f, "vote"),
+            FrameAggregation::Best => write!(f, "best"),
+        }
+    }
+}
+
+impl FromStr for FrameAggregation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "vote" => Ok(FrameAggregation::Vote),
+            "best" => Ok(FrameAggregation::Best),
+            _ => anyhow::bail!("unknown frame aggregation '{}' (expected vote or best)", s),
+        }
+    }
+}
+
+/// Lines shorter than this (in letters and digits) are OCR noise, not votes
+const MIN_VOTE_CHARS: usize = 4;
+
+/// How different a frame must be from the one before to count as a new scene (0-1)
+const SCENE_THRESHOLD: f64 = 0.4;
+
+/// Seconds after a scene change its frame is read, past fades and transitions
+const SCENE_SETTLE_SECS: f64 = 0.5;
+
+/// Frames read when the video's scenes and length are unknown
+const FALLBACK_FRAME_SECS: [f64; 3] = [1.0, 5.0, 10.0];
+
+/// Frames closer together than this show the same thing
+const MIN_FRAME_GAP_SECS: f64 = 1.0;
+
+/// Extracts text from up to `max_frames` video frames and combines it per `aggregation`
+/// Frames are taken at scene changes (title cards, slides), topped up with evenly spaced
+/// ones; without a scene list, at 1s, 5s, and 10s
+/// Frames whose text was read with a mean confidence under `min_confidence` (0-100) are left out
+pub fn extract_video_text_multiframe(
+    path: &Path,
+    aggregation: FrameAggregation,
+    max_frames: usize,
+    languages: &[String],
+    phrases: PhraseLimits,
+    min_confidence: u8,
+) -> Result<Option<String>> {
     use crate::scorer::{NameCandidate, NameSource};
 
     debug!("Attempting multi-frame video OCR on: {}", path.display());
@@ -87,48 +156,30 @@ pub fn extract_video_text_multiframe(path: &Path) -> Result<Option<String>> {
         return Ok(None);
     }
 
-    // Try multiple frame positions
-    let frame_times = ["00:00:01", "00:00:05", "00:00:10"];
-    let mut candidates = Vec::new();
+    let frame_times = match detect_scenes(path) {
+        Ok((scenes, duration)) => {
+            debug!("Found {} scene changes in {}", scenes.len(), path.display());
+            pick_frame_times(&scenes, duration, max_frames)
+        }
+        Err(e) => {
+            debug!("Scene detection failed: {}", e);
+            pick_frame_times(&[], None, max_frames)
+        }
+    };
+    let mut frames = Vec::new();
 
-    for time in &frame_times {
-        debug!("Extracting frame at {}", time);
+    for time in frame_times.iter().map(|secs| format!("{:.3}", secs)) {
+        debug!("Extracting frame at {}s", time);
 
-        match extract_video_frame_at_time(path, time) {
+        match extract_video_frame_at_time(path, &time) {
             Ok(frame_path) => {
-                match run_tesseract_ocr(&frame_path) {
-                    Ok(text) => {
-                        let cleaned = clean_text(&text);
-                        if cleaned.len() > 10 {
-                            // Use key phrase extraction for longer frame OCR text
-                            let extracted_text = if cleaned.len() > 150 {
-                                debug!("Extracting key phrases from frame OCR text ({} chars)", cleaned.len());
-                                let phrases = crate::key_phrases::extract_key_phrases(&cleaned, 3);
-                                if !phrases.is_empty() {
-                                    phrases[0].clone()
-                                } else {
-                                    // Fallback to truncation
-                                    if cleaned.len() > 80 {
-                                        cleaned[..80].to_string()
-                                    } else {
-                                        cleaned.clone()
-                                    }
-                                }
-                            } else {
-                                // For shorter text, just truncate
-                                if cleaned.len() > 80 {
-                                    cleaned[..80].to_string()
-                                } else {
-                                    cleaned.clone()
-                                }
-                            };
-
-                            debug!("Frame at {} extracted: {}", time, extracted_text);
-                            candidates.push(NameCandidate::new(
-                                extracted_text,
-                                NameSource::OcrVideo,
-                            ));
-                        }
+                match run_tesseract_ocr(&frame_path, languages) {
+                    Ok(ocr) if ocr.confidence < f32::from(min_confidence) => {
+                        debug!("Frame at {} OCR confidence {:.0} below minimum, skipping", time, ocr.confidence);
+                    }
+                    Ok(ocr) => {
+                        debug!("Frame at {} extracted {} chars", time, ocr.text.len());
+                        frames.push(ocr.text);
                     }
                     Err(e) => {
                         debug!("OCR failed for frame at {}: {}", time, e);
@@ -144,6 +195,25 @@ pub fn extract_video_text_multiframe(path: &Path) -> Result<Option<String>> {
         }
     }
 
+    if aggregation == FrameAggregation::Vote {
+        if let Some(text) = vote_persistent_text(&frames).and_then(|t| summarize(&t, phrases)) {
+            debug!("Selected persistent video OCR text: {}", text);
+            return Ok(Some(text));
+        }
+        debug!("No text recurs across frames, falling back to best single frame");
+    }
+
+    // Slides and title cards each show something else; their text read together may name the
+    // video better than any single frame
+    let merged = (frames.len() > 1).then(|| merge_distinct_lines(&frames));
+    let candidates = frames
+        .iter()
+        .map(|text| clean_text(text))
+        .chain(merged)
+        .filter_map(|text| summarize(&text, phrases))
+        .map(|text| NameCandidate::new(text, NameSource::OcrVideo))
+        .collect();
+
     // Select best candidate using scorer
     if let Some(best) = crate::scorer::select_best_candidate(candidates) {
         debug!("Selected best video OCR result with score: {}", best.score);
@@ -154,6 +224,158 @@ pub fn extract_video_text_multiframe(path: &Path) -> Result<Option<String>> {
     }
 }
 
+/// The lines seen in the most frames (at least two, and most of them), in reading order
+/// Lines are matched ignoring case, punctuation and spacing, which absorbs small OCR differences
+fn vote_persistent_text(frames: &[String]) -> Option<String> {
+    // key -> (frames containing it, first frame, position in that frame, text as first read)
+    let mut votes: HashMap<String, (usize, usize, usize, String)> = HashMap::new();
+
+    for (frame_index, text) in frames.iter().enumerate() {
+        let mut seen = HashSet::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = clean_text(line);
+            let key = line_key(&line);
+            if key.chars().count() < MIN_VOTE_CHARS || !seen.insert(key.clone()) {
+                continue;
+            }
+            votes
+                .entry(key)
+                .and_modify(|vote| vote.0 += 1)
+                .or_insert((1, frame_index, line_index, line));
+        }
+    }
+
+    let quorum = (frames.len() / 2 + 1).max(2);
+    let top = votes.values().map(|vote| vote.0).max().filter(|&count| count >= quorum)?;
+    let mut persistent: Vec<_> = votes.into_values().filter(|vote| vote.0 == top).collect();
+    persistent.sort_by_key(|vote| (vote.1, vote.2));
+
+    Some(
+        persistent
+            .into_iter()
+            .map(|(_, _, _, line)| line)
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Every distinct line of the frames, in the order first read, as one cleaned text
+/// Lines are told apart the way votes are, so a line read slightly differently twice is kept once
+fn merge_distinct_lines(frames: &[String]) -> String {
+    let mut seen = HashSet::new();
+    let lines: Vec<String> = frames
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(clean_text)
+        .filter(|line| {
+            let key = line_key(line);
+            key.chars().count() >= MIN_VOTE_CHARS && seen.insert(key)
+        })
+        .collect();
+    lines.join(" ")
+}
+
+/// A line reduced to lowercase letters and digits, absorbing small OCR differences
+fn line_key(line: &str) -> String {
+    line.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Scene change times (seconds) and the length of the video, from one ffmpeg pass
+/// Frames are scaled down first; scene scores don't need full resolution
+fn detect_scenes(video_path: &Path) -> Result<(Vec<f64>, Option<f64>)> {
+    let mut cmd = crate::deps_check::Dependency::FFmpeg
+        .create_command()
+        .context("FFmpeg not available for scene detection")?;
+
+    let output = cmd
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(video_path)
+        .arg("-an")
+        .arg("-sn")
+        .arg("-vf")
+        .arg(format!("scale=320:-2,select='gt(scene,{})',showinfo", SCENE_THRESHOLD))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .tool_output()
+        .context("Failed to run ffmpeg command")?;
+
+    // showinfo and the input summary both go to stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+    Ok((parse_scene_times(&stderr), parse_duration(&stderr)))
+}
+
+/// `pts_time` of each frame showinfo printed
+fn parse_scene_times(stderr: &str) -> Vec<f64> {
+    stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| line.split("pts_time:").nth(1))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+        .collect()
+}
+
+/// `Duration: 00:01:23.45` from ffmpeg's input summary, in seconds
+fn parse_duration(stderr: &str) -> Option<f64> {
+    let duration = stderr.split("Duration: ").nth(1)?.split(',').next()?;
+    let mut parts = duration.trim().split(':');
+    let (hours, minutes, seconds) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(hours.parse::<f64>().ok()? * 3600.0 + minutes.parse::<f64>().ok()? * 60.0 + seconds.parse::<f64>().ok()?)
+}
+
+/// Up to `budget` frame times: scene changes first (evenly thinned out if there are more),
+/// then evenly spaced times across the video, skipping any close to one already picked
+fn pick_frame_times(scenes: &[f64], duration: Option<f64>, budget: usize) -> Vec<f64> {
+    let end = duration.unwrap_or(f64::INFINITY);
+    let picked = scenes.len().min(budget);
+    let mut times: Vec<f64> = (0..picked)
+        .map(|i| scenes[i * scenes.len() / picked])
+        .map(|time| (time + SCENE_SETTLE_SECS).min(end))
+        .collect();
+
+    let fill: Vec<f64> = match duration {
+        Some(duration) => (0..budget)
+            .map(|i| (i as f64 + 0.5) * duration / budget as f64)
+            .collect(),
+        None => FALLBACK_FRAME_SECS.to_vec(),
+    };
+    for time in fill {
+        if times.len() >= budget {
+            break;
+        }
+        if times.iter().all(|picked| (picked - time).abs() >= MIN_FRAME_GAP_SECS) {
+            times.push(time);
+        }
+    }
+
+    times.sort_by(f64::total_cmp);
+    times
+}
+
+/// Turns cleaned OCR text into a name: key phrases for long text, otherwise truncated
+fn summarize(cleaned: &str, phrases: PhraseLimits) -> Option<String> {
+    if cleaned.len() <= 10 {
+        return None;
+    }
+
+    // Use key phrase extraction for longer frame OCR text
+    if cleaned.len() > 150 {
+        debug!("Extracting key phrases from frame OCR text ({} chars)", cleaned.len());
+        if let Some(phrase) = crate::key_phrases::summarize(cleaned, phrases) {
+            return Some(phrase);
+        }
+    }
+
+    Some(cleaned.chars().take(80).collect())
+}
+
 /// Extracts a frame at a specific time from the video
 fn extract_video_frame_at_time(video_path: &Path, time: &str) -> Result<std::path::PathBuf> {
     let temp_dir = std::env::temp_dir();
@@ -185,7 +407,7 @@ fn extract_video_frame_at_time(video_path: &Path, time: &str) -> Result<std::pat
         .arg("image2")
         .arg(&temp_frame)
         .arg("-y")
-        .output()
+        .tool_output()
         .context("Failed to run ffmpeg command")?;
 
     if !output.status.success() {
@@ -239,7 +461,7 @@ fn extract_video_frame(video_path: &Path) -> Result<std::path::PathBuf> {
         .arg("image2")
         .arg(&temp_frame)
         .arg("-y") // Overwrite if exists
-        .output()
+        .tool_output()
         .context("Failed to run ffmpeg command")?;
 
     if !output.status.success() {
@@ -257,26 +479,21 @@ fn extract_video_frame(video_path: &Path) -> Result<std::path::PathBuf> {
 }
 
 /// Runs tesseract OCR on an image file
-/// Tries multiple languages in priority order: Traditional Chinese, Simplified Chinese, English
-fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
+/// Tries each language argument in turn, keeping the result with the most characters
+fn run_tesseract_ocr(image_path: &Path, languages: &[String]) -> Result<OcrText> {
     let path_str = image_path.to_str().context("Path not valid UTF-8")?;
 
-    // Try languages in order: Traditional Chinese, Simplified Chinese, English
-    let languages = ["chi_tra", "chi_sim", "eng"];
-    let mut best_result = String::new();
+    let mut best_result = None;
     let mut best_confidence = 0;
 
-    for lang in &languages {
+    for lang in languages {
         debug!("Trying video OCR with language: {}", lang);
 
-        let result = tesseract::Tesseract::new(None, Some(lang))
-            .context("Failed to initialize Tesseract")
-            .and_then(|t| t.set_image(path_str).context("Failed to set image"))
-            .and_then(|mut t| t.get_text().context("Failed to extract text"));
+        let result = crate::ocr_cleanup::recognize(path_str, lang);
 
         match result {
-            Ok(text) => {
-                let cleaned = clean_text(&text);
+            Ok(ocr) => {
+                let cleaned = clean_text(&ocr.text);
                 let char_count = cleaned.chars().count();
 
                 debug!(
@@ -287,7 +504,7 @@ fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
                 // Use the result with the most characters as proxy for best match
                 if char_count > best_confidence {
                     best_confidence = char_count;
-                    best_result = text;
+                    best_result = Some(ocr);
                     debug!("New best result with {}: {} chars", lang, char_count);
                 }
             }
@@ -297,11 +514,7 @@ fn run_tesseract_ocr(image_path: &Path) -> Result<String> {
         }
     }
 
-    if best_confidence > 0 {
-        Ok(best_result)
-    } else {
-        anyhow::bail!("All video OCR language attempts failed")
-    }
+    best_result.context("All video OCR language attempts failed")
 }
 
 /// Cleans extracted text for use in filenames
@@ -349,4 +562,68 @@ mod tests {
         let input = "\n\n   \n  ";
         assert_eq!(clean_text(input), "");
     }
+
+    #[test]
+    fn test_vote_prefers_persistent_text() {
+        let frames = vec![
+            "Breaking: markets open higher\nQuarterly Results 2024\nAcme Corp".to_string(),
+            "QUARTERLY RESULTS 2024.\nWeather: sunny all week\nAcme  Corp".to_string(),
+            "Quarterly  Results 2024\nRevenue grew 12%\nBreaking: markets open higher\nAcme Corp"
+                .to_string(),
+        ];
+        // The ticker recurs in two frames, but the title and logo are in all three
+        assert_eq!(
+            vote_persistent_text(&frames),
+            Some("Quarterly Results 2024 Acme Corp".to_string())
+        );
+
+        // Nothing recurs, or only one frame: no vote
+        let transient = vec!["Intro title".to_string(), "Closing credits".to_string()];
+        assert_eq!(vote_persistent_text(&transient), None);
+        assert_eq!(vote_persistent_text(&frames[..1]), None);
+    }
+
+    #[test]
+    fn test_merge_distinct_lines() {
+        let frames = vec![
+            "Lecture 3: Graph Algorithms\nProf. Ada Lovelace".to_string(),
+            "LECTURE 3 - GRAPH ALGORITHMS\nDijkstra's shortest paths\nok".to_string(),
+        ];
+        assert_eq!(
+            merge_distinct_lines(&frames),
+            "Lecture 3: Graph Algorithms Prof. Ada Lovelace Dijkstra's shortest paths"
+        );
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_scene_output() {
+        let stderr = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'talk.mp4':\n  \
+            Duration: 00:01:30.50, start: 0.000000, bitrate: 512 kb/s\n\
+            [Parsed_showinfo_2 @ 0x6000] n:   0 pts:  12800 pts_time:12.5    duration:512\n\
+            [Parsed_showinfo_2 @ 0x6000] n:   1 pts:  40960 pts_time:40      duration:512\n";
+        assert_eq!(parse_scene_times(stderr), vec![12.5, 40.0]);
+        assert_eq!(parse_duration(stderr), Some(90.5));
+        assert_eq!(parse_duration("Duration: N/A, bitrate: N/A"), None);
+    }
+
+    #[test]
+    fn test_pick_frame_times() {
+        // Scene changes first, topped up with evenly spaced frames away from them
+        assert_eq!(pick_frame_times(&[12.5, 40.0], Some(60.0), 4), vec![7.5, 13.0, 22.5, 40.5]);
+
+        // More scenes than the budget: spread over the whole list
+        let scenes: Vec<f64> = (0..10).map(|i| i as f64 * 10.0).collect();
+        assert_eq!(pick_frame_times(&scenes, Some(100.0), 3), vec![0.5, 30.5, 60.5]);
+
+        // Nothing known about the video
+        assert_eq!(pick_frame_times(&[], None, 6), vec![1.0, 5.0, 10.0]);
+        assert_eq!(pick_frame_times(&[], None, 2), vec![1.0, 5.0]);
+    }
+
+    #[test]
+    fn test_frame_aggregation_from_str() {
+        assert_eq!("Vote".parse::<FrameAggregation>().unwrap(), FrameAggregation::Vote);
+        assert_eq!("best".parse::<FrameAggregation>().unwrap(), FrameAggregation::Best);
+        assert!("majority".parse::<FrameAggregation>().is_err());
+    }
 }
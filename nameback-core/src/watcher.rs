@@ -0,0 +1,393 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{DeferReason, FileAnalysis, RenameEngine, RenameResult};
+
+/// How often the stop signal is checked while nothing is happening
+const TICK: Duration = Duration::from_millis(200);
+
+/// How long events for a file nameback itself just renamed are ignored (on top of the settle delay)
+const OWN_RENAME_GRACE: Duration = Duration::from_secs(2);
+
+/// Settings for `RenameEngine::watch`
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// How long a file has to go without changes before it is analyzed
+    pub settle: Duration,
+    /// How many more times a file that is in use or still being written is tried
+    /// Each retry waits one settle delay longer than the last
+    pub retries: u32,
+    /// Only report the renames that would be made
+    pub dry_run: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            settle: Duration::from_secs(3),
+            retries: 5,
+            dry_run: false,
+        }
+    }
+}
+
+/// What happened to a file that arrived in a watched directory
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// The file was renamed (or failed to be - see `RenameResult::success`)
+    Renamed(RenameResult),
+    /// Analysis found no usable name for the file
+    Unnamed(FileAnalysis),
+    /// The file is in use or still being written and will be tried again
+    Retrying {
+        path: PathBuf,
+        attempt: u32,
+        reason: DeferReason,
+    },
+    /// The file was still in use after the last retry and was left alone
+    GaveUp { path: PathBuf, reason: DeferReason },
+}
+
+/// Tells a running `RenameEngine::watch` to stop
+/// Clones share the signal, so one can be handed to a Ctrl-C handler
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal(Arc<AtomicBool>);
+
+impl StopSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop watching once the file being worked on (if any) is done
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A file waiting for its settle delay to pass
+#[derive(Debug)]
+struct Pending {
+    due: Instant,
+    /// Tries so far that found the file in use
+    attempts: u32,
+}
+
+/// Files waiting to be analyzed, debounced so a burst of events for a file that is
+/// being written only leads to one analysis once it has gone quiet
+#[derive(Debug)]
+struct Queue {
+    settle: Duration,
+    pending: HashMap<PathBuf, Pending>,
+    /// Files nameback renamed itself, with when to stop ignoring their events
+    ignored: HashMap<PathBuf, Instant>,
+}
+
+impl Queue {
+    fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            pending: HashMap::new(),
+            ignored: HashMap::new(),
+        }
+    }
+
+    /// Record a change to `path`, (re)starting its settle delay
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        if self.ignored.get(&path).is_some_and(|&until| until > now) {
+            return;
+        }
+        let due = now + self.settle;
+        self.pending
+            .entry(path)
+            .and_modify(|pending| pending.due = pending.due.max(due))
+            .or_insert(Pending { due, attempts: 0 });
+    }
+
+    /// Try `path` again later, after its `attempts`-th try found it in use
+    fn retry(&mut self, path: PathBuf, attempts: u32, now: Instant) {
+        let due = now + self.settle * (attempts + 1);
+        self.pending.insert(path, Pending { due, attempts });
+    }
+
+    /// Ignore events for a name nameback just gave a file
+    fn ignore(&mut self, path: PathBuf, now: Instant) {
+        self.pending.remove(&path);
+        self.ignored.insert(path, now + self.settle + OWN_RENAME_GRACE);
+    }
+
+    /// Files whose settle delay has passed, with their earlier attempts
+    fn take_due(&mut self, now: Instant) -> Vec<(PathBuf, u32)> {
+        self.ignored.retain(|_, until| *until > now);
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.due <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        due.into_iter()
+            .filter_map(|path| {
+                let pending = self.pending.remove(&path)?;
+                Some((path, pending.attempts))
+            })
+            .collect()
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| pending.due).min()
+    }
+}
+
+/// Whether an event can mean a new or finished file
+fn is_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Modify(_)
+            | EventKind::Access(notify::event::AccessKind::Close(notify::event::AccessMode::Write))
+    )
+}
+
+/// Watch `directory` until `stop` is signalled (see `RenameEngine::watch`)
+pub(crate) fn run(
+    engine: &RenameEngine,
+    directory: &Path,
+    options: &WatchOptions,
+    stop: &StopSignal,
+    mut on_event: impl FnMut(WatchEvent),
+) -> Result<()> {
+    // Events carry resolved paths on some platforms; scanning the resolved directory matches them
+    let directory = &directory
+        .canonicalize()
+        .with_context(|| format!("Failed to watch {}", directory.display()))?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).context("Failed to start watching for new files")?;
+    let mode = if engine.config.max_depth == Some(1) {
+        RecursiveMode::NonRecursive
+    } else {
+        RecursiveMode::Recursive
+    };
+    watcher
+        .watch(directory, mode)
+        .with_context(|| format!("Failed to watch {}", directory.display()))?;
+    tracing::info!("Watching {} for new files", directory.display());
+
+    // The cache database and its -wal/-shm files change as files are analyzed
+    let cache_path = engine.cache_path().map(|p| p.to_string_lossy().to_string());
+    let is_cache = |path: &Path| {
+        cache_path.as_deref().is_some_and(|cache| path.to_string_lossy().starts_with(cache))
+    };
+    // A settle delay shorter than the stability period would only burn retries
+    let mut queue = Queue::new(options.settle.max(engine.config.stable_for.unwrap_or_default()));
+    while !stop.is_stopped() {
+        let wait = queue
+            .next_due()
+            .map_or(TICK, |due| due.saturating_duration_since(Instant::now()).min(TICK));
+        match receiver.recv_timeout(wait) {
+            Ok(Ok(event)) if is_change(&event.kind) => {
+                let now = Instant::now();
+                for path in event.paths.into_iter().filter(|p| !is_cache(p)) {
+                    // A folder moved in arrives as one event; its files are new too
+                    if path.is_dir() {
+                        walkdir::WalkDir::new(&path)
+                            .into_iter()
+                            .filter_map(|entry| entry.ok())
+                            .filter(|entry| entry.file_type().is_file())
+                            .for_each(|entry| queue.touch(entry.into_path(), now));
+                    } else {
+                        queue.touch(path, now);
+                    }
+                }
+            }
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(e)) => tracing::warn!("File watcher error: {}", e),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("File watcher stopped unexpectedly"),
+        }
+
+        let due = queue.take_due(Instant::now());
+        if !due.is_empty() {
+            // One failed batch (a file gone mid-scan, a busy cache) mustn't end the watch;
+            // its files are tried again like files in use
+            let tried = due.clone();
+            if let Err(e) = process(engine, directory, due, &mut queue, options, &mut on_event) {
+                tracing::error!("Failed to process new files: {:#}", e);
+                let now = Instant::now();
+                for (path, attempts) in tried.into_iter().filter(|(_, attempts)| *attempts < options.retries) {
+                    queue.retry(path, attempts + 1, now);
+                }
+            }
+        }
+    }
+
+    tracing::info!("Stopped watching {}", directory.display());
+    Ok(())
+}
+
+/// Analyze and rename files whose settle delay has passed
+/// Files that are gone or that the scan settings leave out are dropped
+fn process(
+    engine: &RenameEngine,
+    directory: &Path,
+    due: Vec<(PathBuf, u32)>,
+    queue: &mut Queue,
+    options: &WatchOptions,
+    on_event: &mut impl FnMut(WatchEvent),
+) -> Result<()> {
+    // The whole directory is scanned so names stay unique and series are still detected
+    let files = engine.scan_files(directory)?;
+    let scanned: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+    let attempts: HashMap<PathBuf, u32> = due
+        .into_iter()
+        .filter(|(path, _)| scanned.contains(path.as_path()))
+        .collect();
+    if attempts.is_empty() {
+        return Ok(());
+    }
+
+    let targets: Vec<PathBuf> = files
+        .iter()
+        .filter(|f| attempts.contains_key(*f))
+        .cloned()
+        .collect();
+    tracing::info!("Analyzing {} new file(s)", targets.len());
+    let cache = engine.load_cache(&[directory.to_path_buf()]);
+    let existing_names = RenameEngine::existing_names(&files);
+    let (analyses, _) = engine.analyze_files(&files, &targets, existing_names, cache, None)?;
+
+    // Sidecars that arrived with their file are renamed along with it
+    let companions: HashSet<PathBuf> = analyses.iter().flat_map(|a| a.companions.clone()).collect();
+    let mut deferred = Vec::new();
+    let mut ready = Vec::new();
+    for analysis in analyses {
+        match analysis.deferred {
+            Some(reason) => deferred.push((analysis.original_path, reason)),
+            None if analysis.proposed_name.is_some() => ready.push(analysis),
+            None if companions.contains(&analysis.original_path) => {}
+            None => on_event(WatchEvent::Unnamed(analysis)),
+        }
+    }
+
+    for result in engine.rename_files(&ready, options.dry_run) {
+        match result.deferred {
+            Some(reason) => deferred.push((result.original_path, reason)),
+            None => {
+                let now = Instant::now();
+                queue.ignore(result.new_path.clone(), now);
+                for (_, companion) in &result.companions {
+                    queue.ignore(companion.clone(), now);
+                }
+                on_event(WatchEvent::Renamed(result));
+            }
+        }
+    }
+
+    // A finished download or saved document shows up under its final name, so partial
+    // downloads and temporary files are just dropped
+    for (path, reason) in deferred {
+        if matches!(reason, DeferReason::PartialDownload | DeferReason::Temporary) {
+            continue;
+        }
+        let attempt = attempts.get(&path).copied().unwrap_or(0) + 1;
+        if attempt > options.retries {
+            on_event(WatchEvent::GaveUp { path, reason });
+        } else {
+            queue.retry(path.clone(), attempt, Instant::now());
+            on_event(WatchEvent::Retrying {
+                path,
+                attempt,
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenameConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_queue_debounces_and_backs_off() {
+        let settle = Duration::from_secs(3);
+        let mut queue = Queue::new(settle);
+        let start = Instant::now();
+        let file = PathBuf::from("/downloads/report.pdf");
+
+        queue.touch(file.clone(), start);
+        queue.touch(file.clone(), start + Duration::from_secs(2));
+        assert!(queue.take_due(start + Duration::from_secs(4)).is_empty());
+        assert_eq!(queue.take_due(start + Duration::from_secs(5)), [(file.clone(), 0)]);
+        assert_eq!(queue.next_due(), None);
+
+        // The second retry waits three settle delays
+        queue.retry(file.clone(), 2, start);
+        assert!(queue.take_due(start + Duration::from_secs(8)).is_empty());
+        assert_eq!(queue.take_due(start + Duration::from_secs(9)), [(file.clone(), 2)]);
+
+        // Events for a name nameback gave a file are ignored for a while
+        let renamed = PathBuf::from("/downloads/Quarterly_Report.pdf");
+        queue.ignore(renamed.clone(), start);
+        queue.touch(renamed.clone(), start + Duration::from_secs(1));
+        assert_eq!(queue.next_due(), None);
+        let later = start + settle + OWN_RENAME_GRACE + Duration::from_secs(1);
+        queue.take_due(later);
+        queue.touch(renamed, later);
+        assert!(queue.next_due().is_some());
+    }
+
+    #[test]
+    fn test_watch_renames_new_file_once() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let inbox = temp_dir.path().join("inbox");
+        std::fs::create_dir(&inbox)?;
+        let engine = RenameEngine::new(RenameConfig {
+            enable_cache: false,
+            journal_path: Some(temp_dir.path().join("journal.jsonl")),
+            ..RenameConfig::default()
+        });
+        let options = WatchOptions {
+            settle: Duration::from_millis(200),
+            ..WatchOptions::default()
+        };
+        let stop = StopSignal::new();
+
+        let events = std::thread::scope(|scope| {
+            let watching = scope.spawn(|| {
+                let mut events = Vec::new();
+                run(&engine, &inbox, &options, &stop, |event| events.push(event)).map(|_| events)
+            });
+
+            std::thread::sleep(Duration::from_millis(300));
+            std::fs::write(
+                inbox.join("notes.txt"),
+                "Quarterly Budget Review\n\nSpending was below forecast this quarter.",
+            )
+            .unwrap();
+            // Long enough to see the rename and any events it causes itself
+            std::thread::sleep(Duration::from_secs(3));
+            stop.stop();
+            watching.join().unwrap()
+        })?;
+
+        assert_eq!(events.len(), 1, "{:?}", events);
+        let WatchEvent::Renamed(result) = &events[0] else {
+            panic!("expected a rename, got {:?}", events[0]);
+        };
+        assert!(result.success);
+        assert!(result.new_path.exists());
+        assert!(!inbox.join("notes.txt").exists());
+        Ok(())
+    }
+}
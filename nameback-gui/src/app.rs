@@ -1,27 +1,100 @@
 use eframe::egui;
 use egui_phosphor::regular;
-use nameback_core::{DependencyNeeds, FileAnalysis, RenameConfig, RenameEngine, RenameHistory, RenameResult};
-use std::path::PathBuf;
+use nameback_core::{
+    AnalysisEvent, BlockedArea, CollisionStrategy, ConfigFile, DependencyNeeds, DuplicateHandling, FileAnalysis, FrameAggregation, GeocodeSource, InstallOptions,
+    LocationPrecision, MetadataBackend, MetadataWriteMode, NameCase, OnConflict, Profile, Progress, RenameConfig, RenameEngine, RenameHistory,
+    RenameOperation, RenameResult, RollbackToken, SeriesKind, SymlinkPolicy, TimestampPosition, TimestampPrecision, WordSeparator,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+use crate::deps_panel::DependencyPanel;
+use crate::preview::{Preview, PreviewLoader};
+
+/// Renames kept in the GUI's history file
+const HISTORY_SIZE: usize = 1000;
+
 #[derive(Debug, Clone, PartialEq)]
 enum FileStatus {
     Pending,
     Processing(String), // Contains operation message like "Extracting metadata..."
     Renamed,
+    Deferred(String), // In use or still being written; Refresh picks it up later
+    Skipped(String),  // Over the file size limit, so not analyzed
     Error(String),
 }
 
+impl FileStatus {
+    /// Status after analysis: ready to rename, or the reason no name was found
+    fn from_analysis(analysis: &FileAnalysis) -> Self {
+        if analysis.proposed_name.is_some() {
+            return FileStatus::Pending;
+        }
+        if let Some(reason) = analysis.deferred {
+            return FileStatus::Deferred(reason.to_string());
+        }
+        if let Some(limit) = analysis.over_limit.filter(|limit| limit.skipped()) {
+            return FileStatus::Skipped(limit.to_string());
+        }
+        let reason = analysis
+            .errors
+            .iter()
+            .find(|issue| !issue.recoverable)
+            .map(|issue| issue.to_string())
+            .unwrap_or_else(|| "No suitable metadata found".to_string());
+        FileStatus::Error(reason)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct FileEntry {
     analysis: FileAnalysis,
     selected: bool,
     status: FileStatus,
+    edited_name: Option<String>, // Typed by the user in place of the proposed name
+}
+
+impl FileEntry {
+    fn new(analysis: FileAnalysis) -> Self {
+        Self {
+            status: FileStatus::from_analysis(&analysis),
+            analysis,
+            selected: true,
+            edited_name: None,
+        }
+    }
+
+    /// Name the file gets when renamed: the user's edit, or else the proposal
+    fn new_name(&self) -> Option<&str> {
+        self.edited_name.as_deref().or(self.analysis.proposed_name.as_deref())
+    }
+
+    /// Analysis to rename with, carrying an edited name in place of the proposal
+    fn rename_analysis(&self) -> FileAnalysis {
+        match &self.edited_name {
+            Some(name) => FileAnalysis {
+                proposed_name: Some(name.clone()),
+                collision_suffix: None,
+                collision_strategy: None,
+                ..self.analysis.clone()
+            },
+            None => self.analysis.clone(),
+        }
+    }
+
+    /// Full path the file gets when renamed
+    fn target_path(&self, name: &str) -> PathBuf {
+        match &self.analysis.destination {
+            Some(folder) => folder.join(name),
+            None => self.analysis.original_path.with_file_name(name),
+        }
+    }
 }
 
 pub struct NamebackApp {
     // Directory state
-    current_directory: Option<PathBuf>,
+    current_roots: Vec<PathBuf>, // Folders and files picked or dropped onto the window
 
     // File entries
     file_entries: Vec<FileEntry>,
@@ -34,6 +107,10 @@ pub struct NamebackApp {
     dark_mode: bool,
     security_ronin_logo: Option<egui::TextureHandle>,
 
+    // Preview pane
+    previewed: Option<PathBuf>, // Original path of the row shown in the preview pane
+    previews: PreviewLoader,
+
     // Search state
     show_search: bool,
     search_query: String,
@@ -48,24 +125,46 @@ pub struct NamebackApp {
 
     // Dependency check dialog
     show_deps_dialog: bool,
-    pending_directory: Option<PathBuf>,
+    pending_roots: Vec<PathBuf>, // Waiting for the dependency dialog
     missing_deps: Option<DependencyNeeds>,
     installing_deps: bool,
     install_progress: Arc<Mutex<String>>,
     install_complete: Arc<Mutex<bool>>,
     install_error: Arc<Mutex<Option<String>>>,
+    show_deps_panel: bool, // Dependencies window, opened from the toolbar
+    deps_panel: DependencyPanel,
 
     // Configuration
     config: RenameConfig,
-
-    // History tracking
-    rename_history: Option<RenameHistory>,
+    config_file: ConfigFile,
+    config_path: Option<PathBuf>, // config.toml, shared with the CLI
+    active_profile: Option<String>, // None = defaults
+
+    // Settings dialog (edits the config file's top-level options)
+    show_settings_dialog: bool,
+    settings_draft: Profile,
+    blocklist_text: String, // location_blocklist as typed, kept while it doesn't parse
+    ocr_languages_text: String, // ocr_languages as typed, so separators being typed stay
+
+    // History tracking (history.json in the user data directory)
+    rename_history: Arc<Mutex<RenameHistory>>,
     show_history_dialog: bool,
+    history_selection: HashSet<RollbackToken>, // Renames ticked in the history dialog
+    last_batch: Vec<RenameResult>, // Successful renames of the last batch, reverted by Undo
+    show_undo_dialog: bool,
+    undo_results: Option<Vec<(String, Result<(), String>)>>, // Per-file outcome of the last undo
 
     // Processing
     processing_thread: Option<std::thread::JoinHandle<Result<(), String>>>,
     rename_results: Arc<Mutex<Option<Vec<RenameResult>>>>,
+    rename_warning: Arc<Mutex<Option<String>>>, // Folders the running batch can't write to
+    single_rename_request: Option<usize>, // Row picked via "Rename just this file"
+    editing: Option<(usize, String)>, // Row whose new name is being typed, and the text so far
+    edit_error: Option<String>,
+    edit_needs_focus: bool,
     shared_file_entries: Arc<Mutex<Vec<FileEntry>>>,
+    shared_progress: Arc<Mutex<Option<Progress>>>, // Latest progress of the running analysis
+    progress: Option<Progress>,
 }
 
 impl NamebackApp {
@@ -98,6 +197,17 @@ impl NamebackApp {
         visuals
     }
 
+    /// Rename history from the user data directory, or an empty one if it can't be read
+    fn load_history() -> RenameHistory {
+        let path = nameback_core::app_data_dir()
+            .map(|dir| dir.join("history.json"))
+            .unwrap_or_default();
+        RenameHistory::load(path.clone(), HISTORY_SIZE).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load rename history: {:#}", e);
+            RenameHistory::new(path, HISTORY_SIZE)
+        })
+    }
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Use system theme preference by default
         // egui automatically detects system dark/light mode on supported platforms
@@ -108,8 +218,14 @@ impl NamebackApp {
             cc.egui_ctx.set_visuals(Self::create_light_theme());
         }
 
-        Self {
-            current_directory: None,
+        let config_path = std::env::var_os("NAMEBACK_CONFIG")
+            .map(PathBuf::from)
+            .or_else(ConfigFile::default_path);
+
+        let mut app = Self {
+            current_roots: Vec::new(),
+            previewed: None,
+            previews: PreviewLoader::new(),
             file_entries: Vec::new(),
             is_processing: false,
             error_message: None,
@@ -126,88 +242,139 @@ impl NamebackApp {
             pattern_query: String::new(),
             pattern_error: None,
             show_deps_dialog: false,
-            pending_directory: None,
+            pending_roots: Vec::new(),
             missing_deps: None,
             installing_deps: false,
             install_progress: Arc::new(Mutex::new(String::new())),
             install_complete: Arc::new(Mutex::new(false)),
             install_error: Arc::new(Mutex::new(None)),
+            show_deps_panel: false,
+            deps_panel: DependencyPanel::new(),
             config: RenameConfig::default(),
-            rename_history: None,
+            config_file: config_path
+                .as_deref()
+                .map_or_else(|| Ok(ConfigFile::default()), ConfigFile::load)
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load config file: {:#}", e);
+                    ConfigFile::default()
+                }),
+            config_path,
+            active_profile: None,
+            show_settings_dialog: false,
+            settings_draft: Profile::default(),
+            blocklist_text: String::new(),
+            ocr_languages_text: String::new(),
+            rename_history: Arc::new(Mutex::new(Self::load_history())),
             show_history_dialog: false,
+            history_selection: HashSet::new(),
+            last_batch: Vec::new(),
+            show_undo_dialog: false,
+            undo_results: None,
             processing_thread: None,
             rename_results: Arc::new(Mutex::new(None)),
+            rename_warning: Arc::new(Mutex::new(None)),
+            single_rename_request: None,
+            editing: None,
+            edit_error: None,
+            edit_needs_focus: false,
             shared_file_entries: Arc::new(Mutex::new(Vec::new())),
-        }
+            shared_progress: Arc::new(Mutex::new(None)),
+            progress: None,
+        };
+
+        // Pick up NAMEBACK_* environment settings (and NAMEBACK_PROFILE) like the CLI does
+        let profile = std::env::var("NAMEBACK_PROFILE").ok();
+        app.apply_profile(profile);
+        app.status_message = None;
+        app
     }
 
     fn select_directory(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
-            self.current_directory = Some(path.clone());
-
-            // Check dependencies for this directory
-            match nameback_core::detect_needed_dependencies(&path) {
-                Ok(needs) => {
-                    log::debug!("Dependency check results - required missing: {}, optional missing: {}",
-                        needs.has_required_missing(),
-                        !needs.missing_optional.is_empty());
-
-                    if needs.has_required_missing() || !needs.missing_optional.is_empty() {
-                        log::info!("Showing dependency dialog");
-                        self.show_deps_dialog = true;
-                        self.pending_directory = Some(path);
-                        self.missing_deps = Some(needs);
-                    } else {
-                        log::info!("All required dependencies available, starting analysis");
-                        self.start_analysis(path);
+            self.open(vec![path]);
+        }
+    }
+
+    /// Start working on `roots`, folders and files picked or dropped onto the window, once
+    /// missing dependencies have been offered for install
+    fn open(&mut self, roots: Vec<PathBuf>) {
+        self.current_roots = roots.clone();
+
+        // Check dependencies for these folders and files
+        match nameback_core::detect_needed_dependencies_for(&roots) {
+            Ok(needs) => {
+                tracing::debug!("Dependency check results - required missing: {}, optional missing: {}",
+                    needs.has_required_missing(),
+                    !needs.missing_optional.is_empty());
+
+                if !needs.is_empty() {
+                    tracing::info!("Showing dependency dialog");
+                    self.show_deps_dialog = true;
+                    self.pending_roots = roots;
+                    self.missing_deps = Some(needs);
+                } else {
+                    // Old tools are still used, so they are only worth a log line
+                    for outdated in &needs.outdated {
+                        tracing::warn!("{}", outdated);
                     }
-                }
-                Err(e) => {
-                    log::warn!("Dependency check failed: {}", e);
-                    self.start_analysis(path); // Proceed anyway
+                    tracing::info!("All required dependencies available, starting analysis");
+                    self.start_analysis(roots);
                 }
             }
+            Err(e) => {
+                tracing::warn!("Dependency check failed: {}", e);
+                self.start_analysis(roots); // Proceed anyway
+            }
         }
     }
 
-    fn start_analysis(&mut self, path: PathBuf) {
+    fn start_analysis(&mut self, roots: Vec<PathBuf>) {
+        self.cancel_edit();
+        self.previewed = None;
+        self.previews.clear();
         self.is_processing = true;
         self.error_message = None;
-        self.status_message = Some("Scanning directory...".to_string());
+        self.status_message = Some("Scanning...".to_string());
         self.file_entries.clear();
 
         let config = self.config.clone();
         let file_entries = Arc::new(Mutex::new(Vec::new()));
         let file_entries_clone = Arc::clone(&file_entries);
+        self.shared_progress = Arc::new(Mutex::new(None));
+        let progress = Arc::clone(&self.shared_progress);
 
         // Spawn thread to scan directory and analyze files progressively
         self.processing_thread = Some(std::thread::spawn(move || {
             use walkdir::WalkDir;
 
-            // First, scan directory to get list of files
+            // First, scan the folders to get list of files (a dropped file lists itself)
             let mut files = Vec::new();
-            for entry in WalkDir::new(&path)
-                .follow_links(false)
-                .into_iter()
-                .filter_entry(|e| {
-                    if config.skip_hidden {
-                        !e.file_name()
-                            .to_str()
-                            .map(|s| s.starts_with('.'))
-                            .unwrap_or(false)
-                    } else {
-                        true
-                    }
-                })
-            {
+            let mut seen = std::collections::HashSet::new();
+            let entries = roots.iter().flat_map(|root| {
+                WalkDir::new(root)
+                    .follow_links(false)
+                    .into_iter()
+                    .filter_entry(|e| {
+                        if config.skip_hidden {
+                            !e.file_name()
+                                .to_str()
+                                .map(|s| s.starts_with('.'))
+                                .unwrap_or(false)
+                        } else {
+                            true
+                        }
+                    })
+            });
+            for entry in entries {
                 match entry {
                     Ok(entry) => {
-                        if entry.file_type().is_file() {
+                        // A file inside two dropped folders (one in the other) is listed once
+                        if entry.file_type().is_file() && seen.insert(entry.path().to_path_buf()) {
                             files.push(entry.path().to_path_buf());
                         }
                     }
                     Err(e) => {
-                        log::warn!("Failed to access entry: {}", e);
+                        tracing::warn!("Failed to access entry: {}", e);
                     }
                 }
             }
@@ -226,10 +393,26 @@ impl NamebackApp {
                         original_path: file_path.clone(),
                         original_name: original_name.clone(),
                         proposed_name: None, // Will be filled in progressively
+                        title: None,
+                        collision_suffix: None,
+                        collision_strategy: None,
+                        destination: None,
+                        duplicate_of: None,
+                        companions: Vec::new(),
                         file_category: nameback_core::FileCategory::Unknown,
+                        confidence: 0.0,
+                        name_source: nameback_core::NameSource::Fallback,
+                        errors: Vec::new(),
+                        rejected: Vec::new(),
+                        alternatives: Vec::new(),
+                        deferred: None,
+                        over_limit: None,
+                        series: None,
+                        extension_mismatch: None,
                     },
                     selected: true,
                     status: FileStatus::Pending,
+                    edited_name: None,
                 });
             }
             drop(entries_lock);
@@ -242,26 +425,33 @@ impl NamebackApp {
                 }
             }
 
-            // Now analyze each file and update progressively
+            // Now analyze each file and update entries as results stream in
             let engine = RenameEngine::new(config);
-            for analysis in engine.analyze_directory(&path).map_err(|e| e.to_string())? {
-                // Find and update the matching entry
-                let mut entries_lock = file_entries_clone.lock().unwrap();
-                if let Some(entry) = entries_lock
-                    .iter_mut()
-                    .find(|e| e.analysis.original_path == analysis.original_path)
-                {
-                    // Update analysis result
-                    entry.analysis = analysis.clone();
-
-                    // Update status based on whether we got a proposed name
-                    if analysis.proposed_name.is_some() {
-                        entry.status = FileStatus::Pending; // Ready for rename
-                    } else {
-                        entry.status = FileStatus::Error("No suitable metadata found".to_string());
+            engine
+                .analyze_paths_streaming(&roots, |event| {
+                    let mut entries_lock = file_entries_clone.lock().unwrap();
+                    match event {
+                        AnalysisEvent::Progress(update) => *progress.lock().unwrap() = Some(update),
+                        AnalysisEvent::Stage { path, stage } => {
+                            if let Some(entry) =
+                                entries_lock.iter_mut().find(|e| e.analysis.original_path == path)
+                            {
+                                entry.status = FileStatus::Processing(format!("{}...", stage.name()));
+                            }
+                        }
+                        AnalysisEvent::Analyzed(analysis) | AnalysisEvent::Revised(analysis) => {
+                            if let Some(entry) = entries_lock
+                                .iter_mut()
+                                .find(|e| e.analysis.original_path == analysis.original_path)
+                            {
+                                entry.status = FileStatus::from_analysis(&analysis);
+                                entry.analysis = analysis;
+                            }
+                        }
+                        AnalysisEvent::Started { .. } => {}
                     }
-                }
-            }
+                })
+                .map_err(|e| e.to_string())?;
 
             Ok(())
         }));
@@ -270,6 +460,71 @@ impl NamebackApp {
         self.shared_file_entries = file_entries;
     }
 
+    /// Re-analyze only files that changed since the last analysis, keeping
+    /// selection and status for everything else
+    /// Only a single folder is checked for changes; several dropped folders or files are
+    /// analyzed again
+    fn refresh_analysis(&mut self) {
+        let roots = self.current_roots.clone();
+        let path = match roots.as_slice() {
+            [path] if path.is_dir() && !self.file_entries.is_empty() => path.clone(),
+            _ => {
+                self.start_analysis(roots);
+                return;
+            }
+        };
+
+        self.cancel_edit();
+        self.is_processing = true;
+        self.error_message = None;
+        self.status_message = Some("Checking for changes...".to_string());
+        self.shared_progress = Arc::new(Mutex::new(None));
+
+        let config = self.config.clone();
+        let previous_entries = std::mem::take(&mut self.file_entries);
+        let file_entries = Arc::new(Mutex::new(previous_entries.clone()));
+        let file_entries_clone = Arc::clone(&file_entries);
+
+        self.processing_thread = Some(std::thread::spawn(move || {
+            let previous: Vec<FileAnalysis> = previous_entries
+                .iter()
+                .map(|e| e.analysis.clone())
+                .collect();
+
+            let engine = RenameEngine::new(config);
+            let incremental = engine
+                .reanalyze_directory(&path, &previous)
+                .map_err(|e| e.to_string())?;
+
+            tracing::info!(
+                "Refresh: {} changed, {} removed",
+                incremental.changed.len(),
+                incremental.removed.len()
+            );
+
+            let entries: Vec<FileEntry> = incremental
+                .analyses
+                .into_iter()
+                .map(|analysis| {
+                    let unchanged = !incremental.changed.contains(&analysis.original_path);
+                    if let Some(existing) = previous_entries
+                        .iter()
+                        .find(|e| unchanged && e.analysis.original_path == analysis.original_path)
+                    {
+                        return existing.clone();
+                    }
+
+                    FileEntry::new(analysis)
+                })
+                .collect();
+
+            *file_entries_clone.lock().unwrap() = entries;
+            Ok(())
+        }));
+
+        self.shared_file_entries = file_entries;
+    }
+
     fn check_analysis_complete(&mut self) {
         // Update file_entries from shared state (progressive updates)
         {
@@ -277,19 +532,11 @@ impl NamebackApp {
             self.file_entries = shared.clone();
         }
 
-        // Update status message with progress
+        // Update status message with the engine's progress
         let total = self.file_entries.len();
-        let analyzed = self
-            .file_entries
-            .iter()
-            .filter(|e| e.analysis.proposed_name.is_some() || e.analysis.file_category != nameback_core::FileCategory::Unknown)
-            .count();
-
-        if self.is_processing && total > 0 {
-            self.status_message = Some(format!(
-                "Analyzing... {}/{} files processed",
-                analyzed, total
-            ));
+        self.progress = self.shared_progress.lock().unwrap().clone();
+        if let Some(progress) = self.progress.as_ref().filter(|_| self.is_processing) {
+            self.status_message = Some(progress_message(progress));
         }
 
         // Check if background thread is complete
@@ -329,8 +576,8 @@ impl NamebackApp {
         let selected_analyses: Vec<FileAnalysis> = self
             .file_entries
             .iter()
-            .filter(|e| e.selected && e.analysis.proposed_name.is_some())
-            .map(|e| e.analysis.clone())
+            .filter(|e| e.selected && e.new_name().is_some())
+            .map(FileEntry::rename_analysis)
             .collect();
 
         if selected_analyses.is_empty() {
@@ -343,17 +590,230 @@ impl NamebackApp {
 
         let config = self.config.clone();
         let rename_results = Arc::clone(&self.rename_results);
+        let rename_warning = Arc::clone(&self.rename_warning);
+        let rename_history = Arc::clone(&self.rename_history);
 
         std::thread::spawn(move || {
             let engine = RenameEngine::new(config);
-            let results = engine.rename_files(&selected_analyses, false);
+
+            // Say up front which folders can't be written to, rather than only failing each file
+            // Planning stats every target, so it stays off the UI thread
+            let unwritable: Vec<String> = engine
+                .plan(&selected_analyses)
+                .unwritable_folders()
+                .into_iter()
+                .map(|(conflict, files)| {
+                    format!("{} file{} can't be renamed: {}", files, if files == 1 { "" } else { "s" }, conflict)
+                })
+                .collect();
+            if !unwritable.is_empty() {
+                *rename_warning.lock().unwrap() = Some(unwritable.join("\n"));
+            }
+
+            let mut history = rename_history.lock().unwrap();
+            let results = engine.rename_files_with_history(&selected_analyses, false, Some(&mut *history));
+            save_history(&history);
+            drop(history);
 
             let mut results_lock = rename_results.lock().unwrap();
             *results_lock = Some(results);
         });
     }
 
+    /// Rename one row immediately (a single rename is quick enough for the UI thread)
+    /// It is a batch of its own, so Undo reverts just this file
+    fn rename_single(&mut self, index: usize) {
+        let Some(entry) = self.file_entries.get_mut(index) else {
+            return;
+        };
+
+        let engine = RenameEngine::new(self.config.clone());
+        let mut history = self.rename_history.lock().unwrap();
+        let results = engine.rename_files_with_history(&[entry.rename_analysis()], false, Some(&mut *history));
+        save_history(&history);
+        drop(history);
+
+        match results.into_iter().next() {
+            Some(result) if result.success => {
+                entry.status = FileStatus::Renamed;
+                self.status_message = Some(format!("Renamed to {}", result.new_name));
+                self.last_batch = vec![result];
+            }
+            Some(RenameResult { deferred: Some(reason), .. }) => {
+                entry.status = FileStatus::Deferred(reason.to_string());
+            }
+            Some(result) => {
+                entry.status = FileStatus::Error(
+                    result.error.unwrap_or_else(|| "Unknown error".to_string()),
+                );
+            }
+            None => {
+                self.error_message = Some(format!(
+                    "No proposed name for {}",
+                    entry.analysis.original_name
+                ))
+            }
+        }
+    }
+
+    /// Restore the original names of the last batch from the rename history, sidecars
+    /// included, and record how each file fared
+    fn undo_last_batch(&mut self) {
+        let batch = std::mem::take(&mut self.last_batch);
+        // Undone through the engine, so the undo journal knows and `nameback --undo` skips them
+        let engine = RenameEngine::new(self.config.clone());
+        let mut history = self.rename_history.lock().unwrap();
+        let mut outcomes = Vec::new();
+
+        for result in &batch {
+            let Some(token) = &result.rollback_token else {
+                continue;
+            };
+            let outcome = engine.undo_from_history(&mut history, token).map_err(|e| e.to_string());
+            if outcome.is_ok() {
+                // Sidecars were recorded as renames of their own, right after the file
+                for (old_path, moved_to) in &result.companions {
+                    let companion = history
+                        .operations()
+                        .iter()
+                        .find(|op| !op.undone && op.original_path == *old_path && op.new_path == *moved_to)
+                        .map(RenameOperation::rollback_token);
+                    if let Some(Err(e)) = companion.map(|token| engine.undo_from_history(&mut history, &token)) {
+                        tracing::warn!("Failed to restore {}: {:#}", old_path.display(), e);
+                    }
+                }
+                if let Some(entry) = self
+                    .file_entries
+                    .iter_mut()
+                    .find(|e| e.analysis.original_path == result.original_path)
+                {
+                    entry.status = FileStatus::Pending;
+                }
+            }
+            outcomes.push((format!("{} → {}", result.new_name, display_name(&result.original_path)), outcome));
+        }
+        save_history(&history);
+        drop(history);
+
+        let failed = outcomes.iter().filter(|(_, outcome)| outcome.is_err()).count();
+        self.status_message = Some(format!(
+            "Undo complete! {} restored, {} failed",
+            outcomes.len() - failed,
+            failed
+        ));
+        self.undo_results = Some(outcomes);
+    }
+
+    /// Restore the original names of the renames ticked in the history dialog, newest first
+    /// so a file renamed twice unwinds in order, and record how each fared
+    fn revert_history_selection(&mut self) {
+        let selection = std::mem::take(&mut self.history_selection);
+        let engine = RenameEngine::new(self.config.clone());
+        let mut history = self.rename_history.lock().unwrap();
+        let selected: Vec<(RollbackToken, PathBuf, PathBuf)> = history
+            .operations()
+            .iter()
+            .map(|op| (op.rollback_token(), op.original_path.clone(), op.new_path.clone()))
+            .filter(|(token, _, _)| selection.contains(token))
+            .collect();
+
+        let mut outcomes = Vec::new();
+        for (token, original_path, new_path) in selected {
+            let outcome = engine.undo_from_history(&mut history, &token).map_err(|e| e.to_string());
+            if outcome.is_ok() {
+                if let Some(entry) = self
+                    .file_entries
+                    .iter_mut()
+                    .find(|e| e.analysis.original_path == original_path && e.status == FileStatus::Renamed)
+                {
+                    entry.status = FileStatus::Pending;
+                }
+                self.last_batch.retain(|result| result.rollback_token.as_ref() != Some(&token));
+            }
+            outcomes.push((format!("{} → {}", display_name(&new_path), display_name(&original_path)), outcome));
+        }
+        save_history(&history);
+        drop(history);
+
+        let failed = outcomes.iter().filter(|(_, outcome)| outcome.is_err()).count();
+        self.status_message = Some(format!(
+            "Revert complete! {} restored, {} failed",
+            outcomes.len() - failed,
+            failed
+        ));
+        self.undo_results = Some(outcomes);
+    }
+
+    fn start_edit(&mut self, index: usize) {
+        if let Some(name) = self.file_entries.get(index).and_then(FileEntry::new_name) {
+            self.editing = Some((index, name.to_string()));
+            self.edit_error = None;
+            self.edit_needs_focus = true;
+        }
+    }
+
+    fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.edit_error = None;
+    }
+
+    /// Use the typed name for the row being edited, or keep editing and show why it can't be used
+    fn commit_edit(&mut self) {
+        let Some((index, text)) = self.editing.clone() else {
+            return;
+        };
+        let Some(entry) = self.file_entries.get(index) else {
+            self.cancel_edit();
+            return;
+        };
+        let checked = nameback_core::check_filename(&text, &entry.analysis.original_path)
+            .and_then(|name| self.check_unique(index, &name).map(|()| name));
+        match checked {
+            Ok(name) => {
+                let entry = &mut self.file_entries[index];
+                entry.edited_name = Some(name).filter(|name| entry.analysis.proposed_name.as_ref() != Some(name));
+                entry.selected = true;
+                self.cancel_edit();
+            }
+            Err(problem) => {
+                self.edit_error = Some(problem);
+                self.edit_needs_focus = true;
+            }
+        }
+    }
+
+    /// Fails if another file in the list is getting `name` in the same folder, or a file
+    /// already has it there (unless that file is being renamed too)
+    fn check_unique(&self, index: usize, name: &str) -> Result<(), String> {
+        let entry = &self.file_entries[index];
+        let target = entry.target_path(name);
+        // Names that differ only in case clash on macOS and Windows
+        let same = |a: &Path, b: &Path| a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase();
+        let renaming = |other: &FileEntry| other.selected && other.status == FileStatus::Pending;
+
+        for (other_index, other) in self.file_entries.iter().enumerate() {
+            if other_index == index || !renaming(other) {
+                continue;
+            }
+            if other.new_name().is_some_and(|other_name| same(&other.target_path(other_name), &target)) {
+                return Err(format!("{} is getting this name too", other.analysis.original_name));
+            }
+        }
+
+        let renamed_away = self
+            .file_entries
+            .iter()
+            .any(|other| renaming(other) && other.new_name().is_some() && same(&other.analysis.original_path, &target));
+        if target.exists() && !same(&target, &entry.analysis.original_path) && !renamed_away {
+            return Err("A file with this name already exists".to_string());
+        }
+        Ok(())
+    }
+
     fn check_rename_complete(&mut self) {
+        if let Some(warning) = self.rename_warning.lock().unwrap().take() {
+            self.error_message = Some(warning);
+        }
         let mut results_lock = self.rename_results.lock().unwrap();
         if let Some(results) = results_lock.take() {
             // Update file entry statuses
@@ -365,6 +825,8 @@ impl NamebackApp {
                 {
                     entry.status = if result.success {
                         FileStatus::Renamed
+                    } else if let Some(reason) = result.deferred {
+                        FileStatus::Deferred(reason.to_string())
                     } else {
                         FileStatus::Error(
                             result.error.clone().unwrap_or_else(|| "Unknown error".to_string()),
@@ -374,16 +836,72 @@ impl NamebackApp {
             }
 
             let successful = results.iter().filter(|r| r.success).count();
-            let failed = results.iter().filter(|r| !r.success).count();
-
-            self.status_message = Some(format!(
-                "Rename complete! {} successful, {} failed",
-                successful, failed
-            ));
+            self.last_batch = results.iter().filter(|r| r.success && r.rollback_token.is_some()).cloned().collect();
+            let deferred = results.iter().filter(|r| r.deferred.is_some()).count();
+            let failed = results.len() - successful - deferred;
+
+            self.status_message = Some(if deferred > 0 {
+                format!(
+                    "Rename complete! {} successful, {} failed, {} deferred (in use)",
+                    successful, failed, deferred
+                )
+            } else {
+                format!("Rename complete! {} successful, {} failed", successful, failed)
+            });
             self.is_processing = false;
         }
     }
 
+    /// Switch to a named profile (None = defaults); takes effect on the next analysis
+    /// Returns whether the configuration was applied
+    fn apply_profile(&mut self, name: Option<String>) -> bool {
+        let mut builder = self.config_file.apply(RenameConfig::builder());
+        if let Some(name) = &name {
+            match self.config_file.profile(name) {
+                Some(profile) => builder = profile.apply(builder),
+                None => {
+                    self.error_message = Some(format!("Unknown profile '{}'", name));
+                    return false;
+                }
+            }
+        }
+
+        let env = match Profile::from_env() {
+            Ok(env) => env,
+            Err(e) => {
+                self.error_message = Some(format!("Invalid environment setting: {:#}", e));
+                return false;
+            }
+        };
+
+        match env.apply(builder).build() {
+            Ok(config) => {
+                self.config = config;
+                self.active_profile = name;
+                self.status_message = Some(match &self.active_profile {
+                    Some(name) => format!("Using profile '{}'", name),
+                    None => "Using default settings".to_string(),
+                });
+                true
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Invalid profile: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Analyze the current directory again from scratch, after the configuration changed
+    /// Names typed by the user are dropped along with the old proposals
+    fn reanalyze(&mut self) {
+        if self.is_processing {
+            return;
+        }
+        if !self.current_roots.is_empty() {
+            self.start_analysis(self.current_roots.clone());
+        }
+    }
+
     fn select_all(&mut self) {
         for entry in &mut self.file_entries {
             if entry.analysis.proposed_name.is_some() {
@@ -445,14 +963,15 @@ impl NamebackApp {
         let progress = Arc::clone(&self.install_progress);
         let complete = Arc::clone(&self.install_complete);
         let error = Arc::clone(&self.install_error);
+        let options = InstallOptions::from_config(&self.config);
 
         std::thread::spawn(move || {
-            let result = nameback_core::install_dependencies_with_progress(Some(Box::new(
+            let result = nameback_core::install_dependencies_with_options(Some(Box::new(
                 move |msg: &str, pct: u8| {
                     let mut prog = progress.lock().unwrap();
                     *prog = format!("{} ({}%)", msg, pct);
                 },
-            )));
+            )), &options);
 
             match result {
                 Ok(()) => {
@@ -478,9 +997,10 @@ impl NamebackApp {
             // Reset completion flag
             *self.install_complete.lock().unwrap() = false;
 
-            // Start analysis with the pending directory
-            if let Some(path) = self.pending_directory.take() {
-                self.start_analysis(path);
+            // Start analysis with the pending folders and files
+            let roots = std::mem::take(&mut self.pending_roots);
+            if !roots.is_empty() {
+                self.start_analysis(roots);
             }
 
             self.missing_deps = None;
@@ -504,8 +1024,8 @@ impl NamebackApp {
                 continue;
             }
 
-            // Search in proposed filename
-            if let Some(proposed) = &entry.analysis.proposed_name {
+            // Search in the new filename (edited or proposed)
+            if let Some(proposed) = entry.new_name() {
                 if proposed.to_lowercase().contains(&query_lower) {
                     self.search_results.push(index);
                 }
@@ -552,12 +1072,12 @@ impl NamebackApp {
             }
 
             // Up arrow - previous result
-            if ui.button(format!("{}", regular::CARET_UP)).clicked() {
+            if ui.button(regular::CARET_UP).clicked() {
                 self.find_previous();
             }
 
             // Down arrow - next result
-            if ui.button(format!("{}", regular::CARET_DOWN)).clicked() {
+            if ui.button(regular::CARET_DOWN).clicked() {
                 self.find_next();
             }
 
@@ -573,7 +1093,7 @@ impl NamebackApp {
             }
 
             // Close search button
-            if ui.button(format!("{}", regular::X)).clicked() {
+            if ui.button(regular::X).clicked() {
                 self.show_search = false;
                 self.search_query.clear();
                 self.search_results.clear();
@@ -591,10 +1111,29 @@ impl NamebackApp {
             ui.separator();
 
             // Refresh button
-            if let Some(dir) = &self.current_directory {
-                if ui.button(format!("{} Refresh", regular::ARROW_CLOCKWISE)).clicked() && !self.is_processing {
-                    self.start_analysis(dir.clone());
-                }
+            if !self.current_roots.is_empty()
+                && ui.button(format!("{} Refresh", regular::ARROW_CLOCKWISE)).clicked()
+                && !self.is_processing
+            {
+                self.refresh_analysis();
+            }
+
+            ui.separator();
+
+            // Configuration profile
+            let mut selected_profile = self.active_profile.clone();
+            egui::ComboBox::from_id_salt("profile")
+                .selected_text(selected_profile.as_deref().unwrap_or("Default"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected_profile, None, "Default");
+                    for name in self.config_file.profile_names() {
+                        ui.selectable_value(&mut selected_profile, Some(name.to_string()), name);
+                    }
+                })
+                .response
+                .on_hover_text("Configuration profile");
+            if selected_profile != self.active_profile && self.apply_profile(selected_profile) {
+                self.reanalyze();
             }
 
             ui.separator();
@@ -633,8 +1172,53 @@ impl NamebackApp {
                 self.execute_renames();
             }
 
+            // Undo button (reverts the last batch after confirmation)
+            let undo_button = egui::Button::new(format!("{} Undo", regular::ARROW_COUNTER_CLOCKWISE));
+            if ui
+                .add_enabled(!self.last_batch.is_empty() && !self.is_processing, undo_button)
+                .on_hover_text("Restore the original names of the last renamed files")
+                .clicked()
+            {
+                self.show_undo_dialog = true;
+            }
+
+            // History button (past batches, any of them revertible)
+            if ui
+                .add_enabled(!self.is_processing, egui::Button::new(format!("{} History", regular::CLOCK_COUNTER_CLOCKWISE)))
+                .on_hover_text("Browse past renames and restore original names")
+                .clicked()
+            {
+                self.history_selection.clear();
+                self.show_history_dialog = true;
+            }
+
             ui.separator();
 
+            // Settings button
+            if ui.button(format!("{} Settings", regular::GEAR)).clicked() {
+                self.settings_draft = self.config_file.settings.clone();
+                self.blocklist_text = self
+                    .settings_draft
+                    .location_blocklist
+                    .iter()
+                    .flatten()
+                    .map(BlockedArea::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                self.ocr_languages_text = self.settings_draft.ocr_languages.clone().unwrap_or_default().join("+");
+                self.show_settings_dialog = true;
+            }
+
+            // Dependencies button
+            if ui
+                .button(format!("{} Dependencies", regular::PUZZLE_PIECE))
+                .on_hover_text("See which tools are installed and install missing ones")
+                .clicked()
+            {
+                self.deps_panel.recheck_all(ui.ctx());
+                self.show_deps_panel = true;
+            }
+
             // About button
             if ui.button(format!("{} About", regular::INFO)).clicked() {
                 self.show_about_dialog = true;
@@ -649,7 +1233,7 @@ impl NamebackApp {
                     regular::MOON // Show moon icon when in light mode (click to go dark)
                 };
 
-                if ui.button(format!("{}", theme_icon))
+                if ui.button(theme_icon)
                     .on_hover_text(if self.dark_mode { "Switch to light theme" } else { "Switch to dark theme" })
                     .clicked()
                 {
@@ -666,17 +1250,22 @@ impl NamebackApp {
 
     fn render_dual_panes(&mut self, ui: &mut egui::Ui) {
         let scroll_to_index = self.scroll_to_index.take(); // Take the scroll request
+        let mut edit_request = None; // Row whose new name was clicked
+        let mut edit_finished = None; // Some(keep) once the name being typed loses focus
+        let mut pick_request = None; // Row and name picked from its other candidates
+        let mut preview_request = None; // Row whose original name was clicked
 
         // Calculate responsive column widths
         let available_width = ui.available_width();
         let checkbox_width = 30.0; // Fixed width for checkbox
         let arrow_width = 30.0;     // Fixed width for arrow
+        let score_width = 50.0;     // Fixed width for confidence score
         let spacing = 10.0;
 
         // Calculate original filename column width based on longest original filename
         let original_width = self.file_entries.iter()
             .map(|e| {
-                let text = &e.analysis.original_name;
+                let text = shown_original_name(&self.current_roots, &e.analysis);
                 // Estimate width: ~7 pixels per character (approximate)
                 (text.len() as f32 * 7.0).min(400.0) // Cap at 400px max
             })
@@ -684,13 +1273,13 @@ impl NamebackApp {
             .max(200.0); // Ensure at least 200px
 
         // New filename column takes all remaining space
-        let new_filename_width = available_width - checkbox_width - arrow_width - original_width - (spacing * 3.0);
+        let new_filename_width = available_width - checkbox_width - arrow_width - original_width - score_width - (spacing * 4.0);
         let new_filename_width = new_filename_width.max(200.0); // Ensure minimum width
 
         let scroll_area = egui::ScrollArea::vertical();
         scroll_area.show(ui, |ui| {
             egui::Grid::new("file_grid")
-                .num_columns(4)
+                .num_columns(5)
                 .spacing([spacing, 4.0])
                 .striped(true)
                 .min_col_width(0.0) // Allow columns to size dynamically
@@ -700,6 +1289,8 @@ impl NamebackApp {
                     ui.add_sized([original_width, 0.0], egui::Label::new(egui::RichText::new("Original Filename").strong()));
                     ui.allocate_space(egui::vec2(arrow_width, 0.0));
                     ui.add_sized([new_filename_width, 0.0], egui::Label::new(egui::RichText::new("New Filename").strong()));
+                    ui.add_sized([score_width, 0.0], egui::Label::new(egui::RichText::new("Score").strong()))
+                        .on_hover_text("How good the proposed name is (5 or more is a good name)");
                     ui.end_row();
 
                     // File rows
@@ -729,16 +1320,31 @@ impl NamebackApp {
                         }
 
                         // Original filename column (fixed width based on content)
-                        let original_label = if is_current_match || is_match {
-                            egui::Label::new(egui::RichText::new(&entry.analysis.original_name).strong())
+                        let original_name = shown_original_name(&self.current_roots, &entry.analysis);
+                        let previewed = self.previewed.as_ref() == Some(&entry.analysis.original_path);
+                        let original_label = if is_current_match || is_match || previewed {
+                            egui::Label::new(egui::RichText::new(original_name).strong())
                                 .wrap()
                         } else {
-                            egui::Label::new(&entry.analysis.original_name).wrap()
+                            egui::Label::new(original_name).wrap()
                         };
-                        ui.add_sized([original_width, 0.0], original_label);
+                        let original_response = ui
+                            .add_sized([original_width, 0.0], original_label.sense(egui::Sense::click()))
+                            .on_hover_text(format!("{}\nClick to preview", entry.analysis.original_path.display()));
+                        if original_response.clicked() {
+                            preview_request = Some(entry.analysis.original_path.clone());
+                        }
+                        if has_proposed_name && entry.status == FileStatus::Pending {
+                            original_response.context_menu(|ui| {
+                                if ui.button(format!("{} Rename just this file", regular::PENCIL_SIMPLE)).clicked() {
+                                    self.single_rename_request = Some(index);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
 
                         // Arrow column (fixed width)
-                        ui.add_sized([arrow_width, 0.0], egui::Label::new(format!("{}", regular::ARROW_RIGHT)));
+                        ui.add_sized([arrow_width, 0.0], egui::Label::new(regular::ARROW_RIGHT));
 
                         // New filename column (responsive width)
                         // Use theme-aware colors - darker colors for light mode, lighter for dark mode
@@ -766,8 +1372,75 @@ impl NamebackApp {
                             |ui| {
                                 match &entry.status {
                                     FileStatus::Pending => {
-                                        if let Some(new_name) = &entry.analysis.proposed_name {
-                                            ui.add(egui::Label::new(egui::RichText::new(new_name.as_str()).color(blue_color)).wrap());
+                                        if let Some((_, text)) = self.editing.as_mut().filter(|(row, _)| *row == index) {
+                                            let response = ui.add(
+                                                egui::TextEdit::singleline(text).desired_width(new_filename_width - 10.0),
+                                            );
+                                            if std::mem::take(&mut self.edit_needs_focus) {
+                                                response.request_focus();
+                                            }
+                                            // Enter or clicking away keeps the name, Escape drops it
+                                            if response.lost_focus() {
+                                                edit_finished = Some(!ui.input(|i| i.key_pressed(egui::Key::Escape)));
+                                            }
+                                            if let Some(problem) = &self.edit_error {
+                                                response.on_hover_text(problem.as_str());
+                                            }
+                                        } else if let Some(new_name) = entry.new_name() {
+                                            let text = egui::RichText::new(new_name).color(blue_color);
+                                            let text = if entry.edited_name.is_some() { text.italics() } else { text };
+                                            let response = ui
+                                                .add(egui::Label::new(text).wrap().sense(egui::Sense::click()))
+                                                .on_hover_text(if entry.edited_name.is_some() {
+                                                    "Edited by you; click to change, right-click to restore the proposal"
+                                                } else {
+                                                    "Click to edit"
+                                                });
+                                            if response.clicked() {
+                                                edit_request = Some(index);
+                                            }
+                                            if entry.edited_name.is_some() {
+                                                response.context_menu(|ui| {
+                                                    if ui.button(format!("{} Use the proposed name", regular::ARROW_COUNTER_CLOCKWISE)).clicked() {
+                                                        entry.edited_name = None;
+                                                        ui.close_menu();
+                                                    }
+                                                });
+                                            }
+                                            if !entry.analysis.alternatives.is_empty() {
+                                                ui.menu_button(regular::CARET_DOWN, |ui| {
+                                                    let analysis = &entry.analysis;
+                                                    let proposed = analysis.proposed_name.iter().map(|name| {
+                                                        (name, analysis.name_source, analysis.confidence)
+                                                    });
+                                                    let others = analysis.alternatives.iter().map(|a| (&a.name, a.source, a.score));
+                                                    for (name, source, score) in proposed.chain(others) {
+                                                        let current = entry.new_name() == Some(name.as_str());
+                                                        let label = format!("{}  ({}, {:.1})", name, source.label(), score);
+                                                        if ui.selectable_label(current, label).clicked() {
+                                                            pick_request = Some((index, name.clone()));
+                                                            ui.close_menu();
+                                                        }
+                                                    }
+                                                })
+                                                .response
+                                                .on_hover_text("Other names found for this file");
+                                            }
+                                            if let Some(series) = entry.analysis.series {
+                                                let badge = format!("{} {}/{}", regular::STACK, series.index, series.total);
+                                                ui.label(egui::RichText::new(badge).weak()).on_hover_text(match series.kind {
+                                                    SeriesKind::Burst => format!("Photo {} of a burst of {}, named together", series.index, series.total),
+                                                    SeriesKind::Numbered => format!("File {} of a numbered series of {}", series.index, series.total),
+                                                });
+                                            }
+                                            if let Some(mismatch) = &entry.analysis.extension_mismatch {
+                                                let badge = format!("{} .{}", regular::WARNING, mismatch.detected);
+                                                ui.label(egui::RichText::new(badge).weak()).on_hover_text(if mismatch.fixed {
+                                                    format!("This is {}; the new name has the right extension", mismatch)
+                                                } else {
+                                                    format!("This is {}; turn on fixing extensions in Settings to correct it", mismatch)
+                                                });
+                                            }
                                         } else {
                                             ui.colored_label(egui::Color32::GRAY, "(analyzing...)");
                                         }
@@ -779,25 +1452,254 @@ impl NamebackApp {
                                     FileStatus::Renamed => {
                                         ui.colored_label(green_color, "✓ Renamed");
                                     }
+                                    FileStatus::Deferred(reason) => {
+                                        ui.colored_label(egui::Color32::GRAY, format!("⏸ Deferred: {}", reason))
+                                            .on_hover_text("Refresh to pick this file up once it is closed or finished downloading");
+                                    }
+                                    FileStatus::Skipped(reason) => {
+                                        ui.colored_label(egui::Color32::GRAY, format!("⏭ Skipped: {}", reason))
+                                            .on_hover_text("Raise or turn off the file size limit in Settings to analyze it");
+                                    }
                                     FileStatus::Error(e) => {
-                                        ui.add(egui::Label::new(egui::RichText::new(e.as_str()).color(red_color)).wrap());
+                                        let label = ui.add(egui::Label::new(egui::RichText::new(e.as_str()).color(red_color)).wrap());
+                                        // Show every issue (including recovered ones) on hover
+                                        if !entry.analysis.errors.is_empty() {
+                                            let details: Vec<String> =
+                                                entry.analysis.errors.iter().map(|issue| issue.to_string()).collect();
+                                            label.on_hover_text(details.join("\n"));
+                                        }
                                     }
                                 }
                             }
                         );
 
+                        // Score column (fixed width); weak below the "good name" mark
+                        if entry.analysis.confidence > 0.0 {
+                            let score = egui::RichText::new(format!("{:.1}", entry.analysis.confidence));
+                            let score = if entry.analysis.confidence < 5.0 { score.weak() } else { score };
+                            ui.add_sized([score_width, 0.0], egui::Label::new(score))
+                                .on_hover_text(format!("Name from {}", entry.analysis.name_source.label()));
+                        } else {
+                            ui.add_sized([score_width, 0.0], egui::Label::new(egui::RichText::new("–").weak()));
+                        }
+
                         ui.end_row();
                     }
                 });
+
+            // Show why a typed name was refused under the grid
+            if let Some(problem) = &self.edit_error {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{} {}", regular::WARNING, problem));
+            }
+        });
+
+        match edit_finished {
+            Some(true) => self.commit_edit(),
+            Some(false) => self.cancel_edit(),
+            None => {}
+        }
+        if let Some(index) = edit_request {
+            self.commit_edit();
+            if self.editing.is_none() {
+                self.start_edit(index);
+            }
+        }
+        if let Some(path) = preview_request {
+            self.previewed = Some(path);
+        }
+        // Checked like a typed name; one that can't be used stays open for editing
+        if let Some((index, name)) = pick_request {
+            self.cancel_edit();
+            self.editing = Some((index, name));
+            self.commit_edit();
+        }
+    }
+
+    /// Right-hand pane with a picture or the first lines of the previewed file, its proposed
+    /// name and its metadata, to check the name against
+    fn render_preview(&mut self, ui: &mut egui::Ui) {
+        let Some(entry) = self
+            .previewed
+            .as_ref()
+            .and_then(|path| self.file_entries.iter().find(|e| &e.analysis.original_path == path))
+        else {
+            self.previewed = None;
+            return;
+        };
+        // Renamed files are previewed under their new name
+        let path = match (&entry.status, entry.new_name()) {
+            (FileStatus::Renamed, Some(name)) => entry.target_path(name),
+            _ => entry.analysis.original_path.clone(),
+        };
+        let proposed = entry.new_name().map(str::to_string);
+
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.heading(display_name(&path));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = ui.button(regular::X).on_hover_text("Close the preview").clicked();
+            });
+        });
+        if let Some(name) = proposed {
+            ui.label(format!("{} {}", regular::ARROW_RIGHT, name));
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| match self.previews.get(ui.ctx(), &path, &self.config) {
+            Preview::Loading => {
+                ui.spinner();
+            }
+            Preview::Ready { picture, text, metadata, problem } => {
+                if let Some(picture) = picture {
+                    ui.add(egui::Image::new(picture).max_width(ui.available_width()).maintain_aspect_ratio(true));
+                } else if let Some(text) = text {
+                    ui.add(egui::Label::new(egui::RichText::new(text.as_str()).monospace()).wrap());
+                } else {
+                    ui.weak(problem.as_deref().unwrap_or("No preview for this kind of file"));
+                }
+
+                if !metadata.is_empty() {
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new("Metadata").strong());
+                    egui::Grid::new("preview_metadata").num_columns(2).striped(true).show(ui, |ui| {
+                        for (label, value) in metadata {
+                            ui.weak(*label);
+                            ui.add(egui::Label::new(value.as_str()).wrap());
+                            ui.end_row();
+                        }
+                    });
+                }
+            }
+        });
+
+        if close {
+            self.previewed = None;
+        }
+    }
+
+    /// Past rename batches, newest first, each listing its renames with a checkbox to pick
+    /// the ones to revert
+    fn render_history_content(&mut self, ui: &mut egui::Ui) {
+        let history = Arc::clone(&self.rename_history);
+        // A batch being renamed holds the history; try again next frame
+        let Ok(history) = history.try_lock() else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Renaming...");
+            });
+            return;
+        };
+        let operations = history.operations();
+        let batches = history.batches();
+
+        if batches.is_empty() {
+            ui.label("No renames yet");
+        }
+        egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+            for (number, batch) in batches.iter().enumerate() {
+                let first = &operations[batch[0]];
+                let undoable: Vec<RollbackToken> = batch
+                    .iter()
+                    .map(|&index| &operations[index])
+                    .filter(|op| !op.undone)
+                    .map(|op| op.rollback_token())
+                    .collect();
+                let ticked = undoable.iter().filter(|token| self.history_selection.contains(*token)).count();
+
+                let id = ui.make_persistent_id(("history_batch", first.timestamp, number));
+                egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, number == 0)
+                    .show_header(ui, |ui| {
+                        let mut all = !undoable.is_empty() && ticked == undoable.len();
+                        let restored = batch.len() - undoable.len();
+                        let mut label = format!(
+                            "{} · {} rename{}",
+                            format_time(first.timestamp),
+                            batch.len(),
+                            if batch.len() == 1 { "" } else { "s" }
+                        );
+                        if restored > 0 {
+                            label.push_str(&format!(" · {} restored", restored));
+                        }
+                        let checkbox = egui::Checkbox::new(&mut all, label).indeterminate(ticked > 0 && ticked < undoable.len());
+                        let response = ui.add_enabled(!undoable.is_empty(), checkbox);
+                        if let Some(folder) = first.original_path.parent() {
+                            response.clone().on_hover_text(folder.display().to_string());
+                        }
+                        if response.changed() {
+                            for token in &undoable {
+                                if all {
+                                    self.history_selection.insert(token.clone());
+                                } else {
+                                    self.history_selection.remove(token);
+                                }
+                            }
+                        }
+                    })
+                    .body(|ui| {
+                        egui::Grid::new(("history_renames", id)).num_columns(4).striped(true).show(ui, |ui| {
+                            for op in batch.iter().map(|&index| &operations[index]) {
+                                let token = op.rollback_token();
+                                let mut ticked = self.history_selection.contains(&token);
+                                if ui.add_enabled(!op.undone, egui::Checkbox::without_text(&mut ticked)).changed() {
+                                    if ticked {
+                                        self.history_selection.insert(token);
+                                    } else {
+                                        self.history_selection.remove(&token);
+                                    }
+                                }
+                                ui.label(display_name(&op.original_path))
+                                    .on_hover_text(op.original_path.display().to_string());
+                                let new_name = format!("→ {}", display_name(&op.new_path));
+                                if op.undone {
+                                    ui.weak(new_name).on_hover_text("Restored to its original name");
+                                } else {
+                                    ui.label(new_name).on_hover_text(op.new_path.display().to_string());
+                                }
+                                ui.weak(format_time(op.timestamp));
+                                ui.end_row();
+                            }
+                        });
+                    });
+            }
+        });
+        drop(history);
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let count = self.history_selection.len();
+            if ui
+                .add_enabled(
+                    count > 0 && !self.is_processing,
+                    egui::Button::new(format!("{} Revert {} Selected", regular::ARROW_COUNTER_CLOCKWISE, count)),
+                )
+                .on_hover_text("Give the selected files their original names back")
+                .clicked()
+            {
+                self.show_history_dialog = false;
+                self.revert_history_selection();
+            }
+            if ui.button("Close").clicked() {
+                self.show_history_dialog = false;
+                self.history_selection.clear();
+            }
         });
     }
 
     fn render_status_bar(&self, ui: &mut egui::Ui) {
         ui.separator();
         ui.horizontal(|ui| {
-            if let Some(dir) = &self.current_directory {
-                ui.label(format!("{} {}", regular::FOLDER_OPEN, dir.display()));
-                ui.separator();
+            match self.current_roots.as_slice() {
+                [] => {}
+                [root] => {
+                    ui.label(format!("{} {}", regular::FOLDER_OPEN, root.display()));
+                    ui.separator();
+                }
+                roots => {
+                    let list: Vec<String> = roots.iter().map(|root| root.display().to_string()).collect();
+                    ui.label(format!("{} {} folders and files", regular::FOLDER_OPEN, roots.len()))
+                        .on_hover_text(list.join("\n"));
+                    ui.separator();
+                }
             }
 
             let total = self.file_entries.len();
@@ -827,7 +1729,14 @@ impl NamebackApp {
 
             if self.is_processing {
                 ui.separator();
-                ui.spinner();
+                match &self.progress {
+                    Some(progress) => {
+                        ui.add(egui::ProgressBar::new(progress.fraction()).desired_width(120.0));
+                    }
+                    None => {
+                        ui.spinner();
+                    }
+                }
                 if let Some(msg) = &self.status_message {
                     ui.label(msg);
                 }
@@ -835,6 +1744,339 @@ impl NamebackApp {
         });
     }
 
+    fn render_settings_content(&mut self, ui: &mut egui::Ui) {
+        let defaults = RenameConfig::default();
+        let draft = &mut self.settings_draft;
+
+        ui.label("Used for every run, here and by the nameback command:");
+        ui.add_space(10.0);
+        setting_checkbox(ui, &mut draft.include_location, defaults.include_location, "Add GPS location to names");
+        setting_checkbox(ui, &mut draft.geocode, defaults.geocode, "Look up place names for GPS coordinates");
+        setting_checkbox(ui, &mut draft.include_timestamp, defaults.include_timestamp, "Add dates to names");
+        setting_checkbox(ui, &mut draft.email_attachment_count, defaults.email_attachment_count, "Add attachment counts to email names");
+        setting_checkbox(ui, &mut draft.subject_hints, defaults.subject_hints, "Name photos by their subject (receipt, whiteboard, ...)");
+        setting_checkbox(ui, &mut draft.peek_archives, defaults.peek_archives, "Name archives after their contents");
+        setting_checkbox(ui, &mut draft.receipt_mode, defaults.receipt_mode, "Name receipts and invoices by date, vendor and total");
+        setting_checkbox(ui, &mut draft.paper_lookup, defaults.paper_lookup, "Name papers by author, year and title (looks up DOIs online)");
+        setting_checkbox(ui, &mut draft.multiframe_video, defaults.multiframe_video, "Read several frames of videos (slower)");
+        setting_checkbox(ui, &mut draft.fix_extensions, defaults.fix_extensions, "Fix extensions that don't match the content (a PNG named .jpg)");
+        setting_checkbox(ui, &mut draft.lowercase_extensions, defaults.lowercase_extensions, "Lower-case extensions (.JPG becomes .jpg)");
+        setting_checkbox(ui, &mut draft.rename_sidecars, defaults.rename_sidecars, "Rename sidecars and RAW+JPEG pairs together");
+        setting_checkbox(ui, &mut draft.rename_directories, defaults.rename_directories, "Name \"New Folder\" folders after their contents");
+        setting_checkbox(ui, &mut draft.skip_hidden, defaults.skip_hidden, "Skip hidden files");
+        setting_checkbox(ui, &mut draft.enable_cache, defaults.enable_cache, "Cache analysis results");
+        setting_checkbox(ui, &mut draft.never_touch_dns, defaults.never_touch_dns, "Never change DNS when installing dependencies");
+
+        ui.add_space(10.0);
+        egui::Grid::new("settings_choices").num_columns(2).spacing([10.0, 6.0]).show(ui, |ui| {
+            ui.label("Folder depth:");
+            let mut limited = draft.max_depth.is_some();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut limited, "Limit to");
+                let mut depth = draft.max_depth.unwrap_or(1);
+                ui.add_enabled(limited, egui::DragValue::new(&mut depth).range(1..=64))
+                    .on_hover_text("1 = only files directly in the chosen folder");
+                ui.label("levels");
+                draft.max_depth = limited.then_some(depth);
+            });
+            ui.end_row();
+
+            ui.label("Minimum score:");
+            let mut min_confidence = draft.min_confidence.unwrap_or(defaults.min_confidence);
+            if ui
+                .add(egui::Slider::new(&mut min_confidence, 0.0..=10.0).step_by(0.5))
+                .on_hover_text("Only propose names scoring at least this much (5 or more is a good name)")
+                .changed()
+            {
+                draft.min_confidence = Some(min_confidence);
+            }
+            ui.end_row();
+
+            ui.label("Duplicate files:");
+            setting_choice(ui, "duplicates", &mut draft.duplicates, defaults.duplicates,
+                &[DuplicateHandling::Flag, DuplicateHandling::Skip, DuplicateHandling::Suffix]);
+            ui.end_row();
+
+            ui.label("Symbolic links:");
+            setting_choice(ui, "symlinks", &mut draft.symlinks, defaults.symlinks,
+                &[SymlinkPolicy::Skip, SymlinkPolicy::Follow, SymlinkPolicy::RenameLink]);
+            ui.end_row();
+
+            ui.label("Name collisions:");
+            setting_choice(ui, "collision_strategy", &mut draft.collision_strategy, defaults.collision_strategy,
+                &[CollisionStrategy::Content, CollisionStrategy::Number, CollisionStrategy::Timestamp,
+                    CollisionStrategy::Hash, CollisionStrategy::KeepOriginal, CollisionStrategy::Fail]);
+            ui.end_row();
+
+            ui.label("Name taken on disk:");
+            setting_choice(ui, "on_conflict", &mut draft.on_conflict, defaults.on_conflict,
+                &[OnConflict::Skip, OnConflict::Suffix, OnConflict::Trash, OnConflict::Overwrite]);
+            ui.end_row();
+
+            ui.label("Dates in names:");
+            setting_choice(ui, "timestamp_precision", &mut draft.timestamp_precision, defaults.timestamp_precision,
+                &[TimestampPrecision::Date, TimestampPrecision::DateTime]);
+            ui.end_row();
+
+            ui.label("Date position:");
+            setting_choice(ui, "timestamp_position", &mut draft.timestamp_position, defaults.timestamp_position,
+                &[TimestampPosition::Suffix, TimestampPosition::Prefix]);
+            ui.end_row();
+
+            ui.label("Name case:");
+            setting_choice(ui, "name_case", &mut draft.name_case, defaults.name_case,
+                &[NameCase::Keep, NameCase::Snake, NameCase::Kebab, NameCase::Title, NameCase::Camel]);
+            ui.end_row();
+
+            // Unset, the separator follows the case (kebab-case uses hyphens)
+            ui.label("Word separator:");
+            let case_separator = match draft.name_case.unwrap_or(defaults.name_case) {
+                NameCase::Kebab => WordSeparator::Hyphen,
+                _ => WordSeparator::Underscore,
+            };
+            setting_choice(ui, "word_separator", &mut draft.word_separator, case_separator,
+                &[WordSeparator::Underscore, WordSeparator::Hyphen, WordSeparator::Space]);
+            ui.end_row();
+
+            ui.label("Write title into metadata:");
+            setting_choice(ui, "write_metadata", &mut draft.write_metadata, defaults.write_metadata,
+                &[MetadataWriteMode::Off, MetadataWriteMode::Also, MetadataWriteMode::Only]);
+            ui.end_row();
+
+            ui.label("Video frame text:");
+            setting_choice(ui, "frame_aggregation", &mut draft.frame_aggregation, defaults.frame_aggregation,
+                &[FrameAggregation::Vote, FrameAggregation::Best]);
+            ui.end_row();
+
+            ui.label("Video frames:");
+            let mut frames = draft.video_frames.unwrap_or(defaults.video_frames);
+            if ui
+                .add(egui::DragValue::new(&mut frames).range(1..=30))
+                .on_hover_text("Most frames read per video, picked at scene changes such as title cards and slides")
+                .changed()
+            {
+                draft.video_frames = Some(frames);
+            }
+            ui.end_row();
+
+            ui.label("Series:");
+            ui.horizontal(|ui| {
+                let mut files = draft.series_min_files.unwrap_or(defaults.series_min_files);
+                if ui
+                    .add(egui::DragValue::new(&mut files).range(2..=20))
+                    .on_hover_text("Fewest numbered files (IMG_001, ...) or burst photos that are named as a series")
+                    .changed()
+                {
+                    draft.series_min_files = Some(files);
+                }
+                ui.label("files or more, numbers padded to");
+                let mut digits = draft.series_padding.or(defaults.series_padding).unwrap_or(3);
+                if ui
+                    .add(egui::DragValue::new(&mut digits).range(1..=9))
+                    .on_hover_text("Least digits in series numbers (by default 3, or 2 for bursts)")
+                    .changed()
+                {
+                    draft.series_padding = Some(digits);
+                }
+                ui.label("digits");
+            });
+            ui.end_row();
+
+            ui.label("Key phrases:");
+            ui.horizontal(|ui| {
+                let mut count = draft.key_phrase_count.unwrap_or(defaults.key_phrase_count);
+                if ui
+                    .add(egui::DragValue::new(&mut count).range(1..=5))
+                    .on_hover_text("How many key phrases of long text (PDFs, text files, OCR) go into a name")
+                    .changed()
+                {
+                    draft.key_phrase_count = Some(count);
+                }
+                ui.label("of up to");
+                let mut words = draft.key_phrase_max_words.unwrap_or(defaults.key_phrase_max_words);
+                if ui
+                    .add(egui::DragValue::new(&mut words).range(1..=8))
+                    .on_hover_text("Most words in one key phrase")
+                    .changed()
+                {
+                    draft.key_phrase_max_words = Some(words);
+                }
+                ui.label("words");
+            });
+            ui.end_row();
+
+            ui.label("OCR confidence:");
+            let mut confidence = draft.min_ocr_confidence.unwrap_or(defaults.min_ocr_confidence);
+            if ui
+                .add(egui::DragValue::new(&mut confidence).range(0..=100))
+                .on_hover_text("Text read from images and videos with a lower mean confidence than this doesn't name files")
+                .changed()
+            {
+                draft.min_ocr_confidence = Some(confidence);
+            }
+            ui.end_row();
+
+            ui.label("Tool timeout:");
+            let mut secs = draft.tool_timeout.unwrap_or(defaults.tool_timeout.as_secs());
+            if ui
+                .add(egui::DragValue::new(&mut secs).range(1..=3600).suffix(" s"))
+                .on_hover_text("Stop an external tool (exiftool, ffmpeg, ...) that takes longer than this on one file")
+                .changed()
+            {
+                draft.tool_timeout = Some(secs);
+            }
+            ui.end_row();
+
+            ui.label("Unchanged for:");
+            setting_limit(ui, &mut draft.stable_for, 10, 1..=3600, "s",
+                "Files modified more recently than this are left alone as still being written");
+            ui.end_row();
+
+            ui.label("Largest file:");
+            setting_limit(ui, &mut draft.max_file_size_mb, 4096, 1..=1_000_000, "MB",
+                "Larger files are skipped without being analyzed");
+            ui.end_row();
+
+            ui.label("Longest PDF:");
+            setting_limit(ui, &mut draft.max_pdf_pages, 500, 1..=100_000, "pages",
+                "Longer PDFs are named from their metadata, without reading or OCR'ing their text");
+            ui.end_row();
+
+            ui.label("Longest video:");
+            setting_limit(ui, &mut draft.max_video_duration_min, 180, 1..=10_000, "minutes",
+                "Longer videos are named from their metadata, without OCR'ing frames");
+            ui.end_row();
+
+            ui.label("Metadata reader:");
+            setting_choice(ui, "metadata_backend", &mut draft.metadata_backend, defaults.metadata_backend,
+                &[MetadataBackend::Auto, MetadataBackend::Exiftool, MetadataBackend::Native]);
+            ui.end_row();
+
+            ui.label("Place names:");
+            setting_choice(ui, "geocode_source", &mut draft.geocode_source, defaults.geocode_source,
+                &[GeocodeSource::Auto, GeocodeSource::Online, GeocodeSource::Offline]);
+            ui.end_row();
+
+            ui.label("Place precision:");
+            setting_choice(ui, "location_precision", &mut draft.location_precision, defaults.location_precision,
+                &[LocationPrecision::Country, LocationPrecision::Region, LocationPrecision::City, LocationPrecision::Coordinates]);
+            ui.end_row();
+        });
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Name template:");
+            let mut template = draft.template.clone().unwrap_or_default();
+            if ui
+                .add(egui::TextEdit::singleline(&mut template).hint_text("{name}"))
+                .on_hover_text("e.g. {camera}_{name} or {category}/{yyyy}/{title}")
+                .changed()
+            {
+                draft.template = Some(template).filter(|t| !t.trim().is_empty());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Date format:");
+            let mut format = draft.timestamp_format.clone().unwrap_or_default();
+            if ui
+                .add(egui::TextEdit::singleline(&mut format).hint_text("%Y-%m-%d"))
+                .on_hover_text("strftime pattern, e.g. %Y%m%d or %d.%m.%Y (replaces the date/time choice)")
+                .changed()
+            {
+                draft.timestamp_format = Some(format).filter(|f| !f.trim().is_empty());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Never name places near:");
+            if ui
+                .add(egui::TextEdit::singleline(&mut self.blocklist_text).hint_text("47.61,-122.33,2km"))
+                .on_hover_text("Latitude,longitude and radius (default 1km) of places like home, separated by ;")
+                .changed()
+            {
+                let areas: Result<Vec<BlockedArea>, _> = self
+                    .blocklist_text
+                    .split(';')
+                    .filter(|area| !area.trim().is_empty())
+                    .map(str::parse)
+                    .collect();
+                // Half-typed areas keep the last valid list
+                if let Ok(areas) = areas {
+                    draft.location_blocklist = Some(areas).filter(|a| !a.is_empty());
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("OCR languages:");
+            if ui
+                .add(egui::TextEdit::singleline(&mut self.ocr_languages_text).hint_text("chi_tra+chi_sim+eng"))
+                .on_hover_text("Tesseract language packs used together, e.g. eng+deu+jpn")
+                .changed()
+            {
+                let languages: Vec<String> = self
+                    .ocr_languages_text
+                    .split(['+', ','])
+                    .map(str::trim)
+                    .filter(|lang| !lang.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                draft.ocr_languages = Some(languages).filter(|l| !l.is_empty());
+            }
+        });
+        if let Some(languages) = &draft.ocr_languages {
+            let missing = nameback_core::missing_ocr_languages(languages);
+            if !missing.is_empty() {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{} Not installed for tesseract: {}", regular::WARNING, missing.join(", ")),
+                );
+            }
+        }
+
+        if let Some(path) = &self.config_path {
+            ui.add_space(10.0);
+            ui.weak(format!("Saved in {}", path.display()));
+        }
+        if let Some(error) = &self.error_message {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.save_settings();
+            }
+            if ui.button("Cancel").clicked() {
+                self.show_settings_dialog = false;
+                self.error_message = None;
+            }
+        });
+    }
+
+    fn save_settings(&mut self) {
+        let Some(path) = self.config_path.clone() else {
+            self.error_message = Some("No user config directory to save settings in".to_string());
+            return;
+        };
+        if let Err(e) = self.settings_draft.apply(RenameConfig::builder()).build() {
+            self.error_message = Some(format!("Invalid settings: {}", e));
+            return;
+        }
+        let changed = self.settings_draft != self.config_file.settings;
+        match self.config_file.save_settings(&path, self.settings_draft.clone()) {
+            Ok(()) => {
+                self.error_message = None;
+                self.show_settings_dialog = false;
+                if self.apply_profile(self.active_profile.clone()) && changed {
+                    self.reanalyze();
+                }
+            }
+            Err(e) => self.error_message = Some(format!("Failed to save settings: {:#}", e)),
+        }
+    }
+
     fn render_about_content(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         ui.vertical_centered(|ui| {
             ui.add_space(10.0);
@@ -886,6 +2128,12 @@ impl NamebackApp {
             );
             ui.add_space(20.0);
 
+            // Diagnostic logs location (for bug reports)
+            if let Some(log_dir) = nameback_core::app_log_dir() {
+                ui.label(egui::RichText::new(format!("Logs: {}", log_dir.display())).small());
+                ui.add_space(10.0);
+            }
+
             if ui.button("Close").clicked() {
                 self.show_about_dialog = false;
             }
@@ -903,12 +2151,28 @@ impl eframe::App for NamebackApp {
             ctx.request_repaint(); // Keep refreshing while processing
         }
 
+        // Row-level rename requested from a context menu last frame
+        if let Some(index) = self.single_rename_request.take() {
+            self.rename_single(index);
+        }
+
         // Check for dependency installation completion
         if self.installing_deps {
             self.check_install_complete();
             ctx.request_repaint(); // Keep refreshing during installation
         }
 
+        // Folders and files dropped onto the window are analyzed in place of the current ones
+        let dropped: Vec<PathBuf> =
+            ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        if !dropped.is_empty() {
+            if self.is_processing {
+                self.error_message = Some("Wait for the current run to finish before dropping more".to_string());
+            } else {
+                self.open(dropped);
+            }
+        }
+
         // Handle Ctrl+F hotkey
         if ctx.input(|i| i.key_pressed(egui::Key::F) && i.modifiers.command) {
             self.show_search = !self.show_search;
@@ -925,21 +2189,28 @@ impl eframe::App for NamebackApp {
 
             // Enter or F3 for next match
             if ctx.input(|i| {
-                (i.key_pressed(egui::Key::Enter) && !i.modifiers.shift)
-                    || (i.key_pressed(egui::Key::F3) && !i.modifiers.shift)
+                !i.modifiers.shift
+                    && (i.key_pressed(egui::Key::Enter) || i.key_pressed(egui::Key::F3))
             }) {
                 self.find_next();
             }
 
             // Shift+Enter or Shift+F3 for previous match
             if ctx.input(|i| {
-                (i.key_pressed(egui::Key::Enter) && i.modifiers.shift)
-                    || (i.key_pressed(egui::Key::F3) && i.modifiers.shift)
+                i.modifiers.shift
+                    && (i.key_pressed(egui::Key::Enter) || i.key_pressed(egui::Key::F3))
             }) {
                 self.find_previous();
             }
         }
 
+        if self.previewed.is_some() {
+            egui::SidePanel::right("preview_pane")
+                .resizable(true)
+                .default_width(340.0)
+                .show(ctx, |ui| self.render_preview(ui));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Control buttons
             self.render_controls(ui);
@@ -983,7 +2254,7 @@ impl eframe::App for NamebackApp {
                 self.render_dual_panes(ui);
             } else if !self.is_processing {
                 ui.centered_and_justified(|ui| {
-                    ui.heading("Select a directory to begin");
+                    ui.heading("Select a directory, or drop folders and files here, to begin");
                 });
             }
         });
@@ -1009,11 +2280,14 @@ impl eframe::App for NamebackApp {
 
                             for dep in &needs.missing_required {
                                 ui.horizontal(|ui| {
-                                    ui.label(format!("{}", regular::X_CIRCLE));
+                                    ui.label(regular::X_CIRCLE);
                                     ui.strong(dep.name());
                                     ui.label("-");
                                     ui.label(dep.description());
                                 });
+                                if let Some(reason) = needs.reason(*dep) {
+                                    ui.indent(dep.name(), |ui| ui.weak(reason));
+                                }
                             }
                             ui.add_space(10.0);
                         }
@@ -1030,11 +2304,31 @@ impl eframe::App for NamebackApp {
 
                             for dep in &needs.missing_optional {
                                 ui.horizontal(|ui| {
-                                    ui.label(format!("{}", regular::WARNING));
+                                    ui.label(regular::WARNING);
                                     ui.strong(dep.name());
                                     ui.label("-");
                                     ui.label(dep.description());
                                 });
+                                if let Some(reason) = needs.reason(*dep) {
+                                    ui.indent(dep.name(), |ui| ui.weak(reason));
+                                }
+                            }
+                            ui.add_space(10.0);
+                        }
+
+                        if !needs.outdated.is_empty() {
+                            ui.heading("Outdated Dependencies");
+                            ui.add_space(10.0);
+                            ui.label("These are older than supported and will still be used; update them with your package manager:");
+                            ui.add_space(5.0);
+
+                            for outdated in &needs.outdated {
+                                ui.horizontal(|ui| {
+                                    ui.label(regular::WARNING);
+                                    ui.strong(format!("{} {}", outdated.dependency.name(), outdated.version));
+                                    ui.label("-");
+                                    ui.label(format!("{} or newer is supported; {}", outdated.minimum, outdated.impact));
+                                });
                             }
                             ui.add_space(10.0);
                         }
@@ -1045,7 +2339,7 @@ impl eframe::App for NamebackApp {
                             // Check for errors
                             let error = self.install_error.lock().unwrap().clone();
                             if let Some(err_msg) = error {
-                                ui.colored_label(egui::Color32::from_rgb(200, 50, 50), format!("❌ Installation Failed"));
+                                ui.colored_label(egui::Color32::from_rgb(200, 50, 50), "❌ Installation Failed");
                                 ui.add_space(5.0);
                                 ui.label(egui::RichText::new(&err_msg).color(egui::Color32::from_rgb(180, 50, 50)));
                                 ui.add_space(10.0);
@@ -1079,15 +2373,16 @@ impl eframe::App for NamebackApp {
 
                                 if ui.button(format!("{} Skip", regular::SKIP_FORWARD)).clicked() {
                                     self.show_deps_dialog = false;
-                                    if let Some(path) = self.pending_directory.take() {
-                                        self.start_analysis(path);
+                                    let roots = std::mem::take(&mut self.pending_roots);
+                                    if !roots.is_empty() {
+                                        self.start_analysis(roots);
                                     }
                                     self.missing_deps = None;
                                 }
 
                                 if ui.button(format!("{} Cancel", regular::X)).clicked() {
                                     self.show_deps_dialog = false;
-                                    self.pending_directory = None;
+                                    self.pending_roots.clear();
                                     self.missing_deps = None;
                                 }
                             });
@@ -1096,6 +2391,21 @@ impl eframe::App for NamebackApp {
                 });
         }
 
+        // Dependencies window
+        if self.show_deps_panel {
+            let options = InstallOptions::from_config(&self.config);
+            egui::Window::new("Dependencies")
+                .open(&mut self.show_deps_panel)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(640.0)
+                .show(ctx, |ui| {
+                    ui.label("External tools nameback uses when they are installed. All are optional.");
+                    ui.add_space(10.0);
+                    self.deps_panel.render(ui, &options);
+                });
+        }
+
         // Pattern selection dialog
         if self.show_pattern_dialog {
             egui::Window::new("Select by Pattern")
@@ -1137,6 +2447,84 @@ impl eframe::App for NamebackApp {
                 });
         }
 
+        // Undo confirmation dialog
+        if self.show_undo_dialog {
+            egui::Window::new("Undo Last Rename")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("These {} files will get their original names back:", self.last_batch.len()));
+                    ui.add_space(10.0);
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for result in &self.last_batch {
+                            ui.label(format!("{} → {}", result.new_name, display_name(&result.original_path)));
+                        }
+                    });
+
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button(format!("{} Undo {} Renames", regular::ARROW_COUNTER_CLOCKWISE, self.last_batch.len())).clicked() {
+                            self.show_undo_dialog = false;
+                            self.undo_last_batch();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.show_undo_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        // Rename history dialog
+        if self.show_history_dialog {
+            egui::Window::new("Rename History")
+                .collapsible(false)
+                .resizable(true)
+                .default_size([560.0, 420.0])
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    self.render_history_content(ui);
+                });
+        }
+
+        // Undo results dialog
+        if let Some(outcomes) = &self.undo_results {
+            let mut close = false;
+            egui::Window::new("Undo Results")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for (label, outcome) in outcomes {
+                            match outcome {
+                                Ok(()) => ui.label(format!("{} {}", regular::CHECK, label)),
+                                Err(e) => ui.colored_label(
+                                    ui.visuals().error_fg_color,
+                                    format!("{} {}: {}", regular::X, label, e),
+                                ),
+                            };
+                        }
+                    });
+                    ui.add_space(10.0);
+                    close = ui.button("Close").clicked();
+                });
+            if close {
+                self.undo_results = None;
+            }
+        }
+
+        // Settings dialog
+        if self.show_settings_dialog {
+            egui::Window::new("Settings")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    self.render_settings_content(ui);
+                });
+        }
+
         // About dialog
         if self.show_about_dialog {
             egui::Window::new("About nameback")
@@ -1157,5 +2545,123 @@ impl eframe::App for NamebackApp {
                     }
                 });
         }
+
+        // Dim the window while folders or files are dragged over it
+        let hovered = ctx.input(|i| i.raw.hovered_files.len());
+        if hovered > 0 {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_target")));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("{} Drop to analyze {} item(s)", regular::DOWNLOAD_SIMPLE, hovered),
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+}
+
+/// Write the rename history back to disk; a failure only costs undo after a restart
+fn save_history(history: &RenameHistory) {
+    if let Err(e) = history.save() {
+        tracing::warn!("Failed to save rename history: {:#}", e);
+    }
+}
+
+/// Local date and time of a Unix timestamp, e.g. "2024-03-15 14:22:10"
+fn format_time(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// File name part of a path, for listing files in dialogs
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// A row's original name, after the picked or dropped folder it was found in when several
+/// were dropped, e.g. "Scans › IMG_0042.jpg" (the nearest folder if one is inside another)
+fn shown_original_name(roots: &[PathBuf], analysis: &FileAnalysis) -> String {
+    let root = roots
+        .iter()
+        .filter(|root| analysis.original_path.starts_with(root))
+        .max_by_key(|root| root.components().count());
+    match root {
+        Some(root) if roots.len() > 1 && *root != analysis.original_path => {
+            format!("{} › {}", display_name(root), analysis.original_name)
+        }
+        _ => analysis.original_name.clone(),
+    }
+}
+
+/// Status bar text for a running analysis, e.g. "Analyzing 12/40 (ocr IMG_0042.jpg), about 28s left"
+fn progress_message(progress: &Progress) -> String {
+    let mut message = format!("Analyzing {}/{}", progress.done, progress.total);
+    if let (Some(path), Some(stage)) = (&progress.current_file, progress.stage) {
+        message.push_str(&format!(" ({} {})", stage.name(), display_name(path)));
+    }
+    if let Some(eta) = progress.eta {
+        let seconds = eta.as_secs();
+        if seconds >= 60 {
+            message.push_str(&format!(", about {} min left", seconds.div_ceil(60)));
+        } else {
+            message.push_str(&format!(", about {}s left", seconds));
+        }
+    }
+    message
+}
+
+/// Checkbox for an optional setting, showing `default` until it is changed
+fn setting_checkbox(ui: &mut egui::Ui, value: &mut Option<bool>, default: bool, label: &str) {
+    let mut checked = value.unwrap_or(default);
+    if ui.checkbox(&mut checked, label).changed() {
+        *value = Some(checked);
+    }
+}
+
+/// An optional limit: "Limit to" with a value in `range` (starting at `initial`) and its
+/// `unit`, or no limit while unchecked
+fn setting_limit<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut Option<T>,
+    initial: T,
+    range: std::ops::RangeInclusive<T>,
+    unit: &str,
+    hover: &str,
+) {
+    let mut limited = value.is_some();
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut limited, "Limit to").on_hover_text(hover);
+        let mut limit = value.unwrap_or(initial);
+        ui.add_enabled(limited, egui::DragValue::new(&mut limit).range(range));
+        ui.label(unit);
+        *value = limited.then_some(limit);
+    });
+}
+
+/// Dropdown for an optional setting with a fixed set of `choices`, showing `default` until
+/// it is changed (choices are shown as they are written in the config file)
+fn setting_choice<T: Copy + PartialEq + std::fmt::Display>(
+    ui: &mut egui::Ui,
+    id: &str,
+    value: &mut Option<T>,
+    default: T,
+    choices: &[T],
+) {
+    let mut selected = value.unwrap_or(default);
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected.to_string())
+        .show_ui(ui, |ui| {
+            for &choice in choices {
+                ui.selectable_value(&mut selected, choice, choice.to_string());
+            }
+        });
+    if selected != value.unwrap_or(default) {
+        *value = Some(selected);
     }
 }
@@ -0,0 +1,174 @@
+use eframe::egui;
+use egui_phosphor::regular;
+use nameback_core::{Dependency, DependencyStatus, InstallOptions, OutdatedDependency};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// What is known about one external tool
+#[derive(Debug, Clone)]
+enum ToolState {
+    Checking,
+    Found {
+        version: Option<String>,
+        path: Option<PathBuf>,
+        outdated: Option<OutdatedDependency>, // Older than supported
+    },
+    Missing,
+    Installing(String), // Latest progress message
+    Failed(String),     // Why the last install didn't work
+}
+
+/// The Dependencies window: every tool nameback can use with its version and path, each
+/// re-checked or installed on its own on background threads
+pub struct DependencyPanel {
+    states: Arc<Mutex<HashMap<Dependency, ToolState>>>,
+}
+
+impl DependencyPanel {
+    pub fn new() -> Self {
+        Self {
+            states: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Look up every tool again (e.g. when the window is opened)
+    pub fn recheck_all(&self, ctx: &egui::Context) {
+        for dependency in Dependency::ALL {
+            self.recheck(ctx, dependency);
+        }
+    }
+
+    /// Look up one tool's version and path in the background
+    fn recheck(&self, ctx: &egui::Context, dependency: Dependency) {
+        if matches!(self.state(dependency), Some(ToolState::Installing(_))) {
+            return;
+        }
+        self.set(dependency, ToolState::Checking);
+        let (states, ctx) = (Arc::clone(&self.states), ctx.clone());
+        std::thread::spawn(move || {
+            states.lock().unwrap().insert(dependency, check(dependency));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Install one tool with the platform's package manager in the background, then check it
+    fn install(&self, ctx: &egui::Context, dependency: Dependency, options: InstallOptions) {
+        self.set(dependency, ToolState::Installing(format!("Installing {}...", dependency.name())));
+        let (states, ctx) = (Arc::clone(&self.states), ctx.clone());
+        std::thread::spawn(move || {
+            let progress_states = Arc::clone(&states);
+            let progress_ctx = ctx.clone();
+            let result = nameback_core::install_dependency_with_options(
+                dependency,
+                Some(Box::new(move |msg: &str, pct: u8| {
+                    progress_states
+                        .lock()
+                        .unwrap()
+                        .insert(dependency, ToolState::Installing(format!("{} ({}%)", msg, pct)));
+                    progress_ctx.request_repaint();
+                })),
+                &options,
+            );
+
+            let state = match result {
+                Ok(()) => check(dependency),
+                Err(e) => {
+                    tracing::warn!("Failed to install {}: {}", dependency.name(), e);
+                    ToolState::Failed(e.to_string())
+                }
+            };
+            states.lock().unwrap().insert(dependency, state);
+            ctx.request_repaint();
+        });
+    }
+
+    fn state(&self, dependency: Dependency) -> Option<ToolState> {
+        self.states.lock().unwrap().get(&dependency).cloned()
+    }
+
+    fn set(&self, dependency: Dependency, state: ToolState) {
+        self.states.lock().unwrap().insert(dependency, state);
+    }
+
+    /// One row per tool: status, name, version, path, and its Re-check and Install buttons
+    /// (installing as `options` say)
+    pub fn render(&self, ui: &mut egui::Ui, options: &InstallOptions) {
+        let states = self.states.lock().unwrap().clone();
+        // Package managers don't like running twice at once
+        let installing = states.values().any(|state| matches!(state, ToolState::Installing(_)));
+
+        egui::Grid::new("dependencies").num_columns(5).striped(true).spacing([12.0, 8.0]).show(ui, |ui| {
+            for dependency in Dependency::ALL {
+                let state = states.get(&dependency).cloned().unwrap_or(ToolState::Checking);
+
+                match &state {
+                    ToolState::Found { outdated: Some(_), .. } => ui.label(regular::WARNING),
+                    ToolState::Found { .. } => ui.label(regular::CHECK_CIRCLE),
+                    ToolState::Checking | ToolState::Installing(_) => ui.spinner(),
+                    ToolState::Missing | ToolState::Failed(_) => ui.label(regular::X_CIRCLE),
+                };
+                ui.strong(dependency.name()).on_hover_text(dependency.description());
+
+                match &state {
+                    ToolState::Found { version, path, outdated } => {
+                        let version = ui.label(version.as_deref().unwrap_or("unknown version"));
+                        if let Some(outdated) = outdated {
+                            version.on_hover_text(format!(
+                                "Older than supported ({} or newer): {}",
+                                outdated.minimum, outdated.impact
+                            ));
+                        }
+                        let path = path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+                        ui.add(egui::Label::new(egui::RichText::new(&path).weak()).truncate())
+                            .on_hover_text(&path);
+                    }
+                    ToolState::Checking => {
+                        ui.weak("Checking...");
+                        ui.label("");
+                    }
+                    ToolState::Missing => {
+                        ui.weak("Not installed");
+                        ui.weak(dependency.description());
+                    }
+                    ToolState::Installing(progress) => {
+                        ui.weak("Installing...");
+                        ui.label(progress);
+                    }
+                    ToolState::Failed(error) => {
+                        ui.colored_label(ui.visuals().error_fg_color, "Install failed");
+                        ui.add(egui::Label::new(error).truncate()).on_hover_text(error);
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    let busy = matches!(state, ToolState::Checking | ToolState::Installing(_));
+                    if ui
+                        .add_enabled(!busy, egui::Button::new(format!("{} Re-check", regular::ARROW_CLOCKWISE)))
+                        .on_hover_text("Look for it again, e.g. after installing it yourself")
+                        .clicked()
+                    {
+                        self.recheck(ui.ctx(), dependency);
+                    }
+                    if matches!(state, ToolState::Missing | ToolState::Failed(_))
+                        && ui
+                            .add_enabled(!installing, egui::Button::new(format!("{} Install", regular::DOWNLOAD_SIMPLE)))
+                            .on_hover_text("Install with the system package manager")
+                            .clicked()
+                    {
+                        self.install(ui.ctx(), dependency, *options);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+    }
+}
+
+/// Version and path of a tool, or Missing (runs the tool, so off the UI thread)
+fn check(dependency: Dependency) -> ToolState {
+    match nameback_core::check_dependency(dependency) {
+        DependencyStatus::Installed { version, path, outdated } => ToolState::Found { version, path, outdated },
+        DependencyStatus::Missing => ToolState::Missing,
+    }
+}
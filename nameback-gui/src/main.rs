@@ -2,6 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
+mod deps_panel;
+mod preview;
 
 use app::NamebackApp;
 use eframe::egui;
@@ -20,6 +22,40 @@ fn load_icon() -> egui::IconData {
     }
 }
 
+/// Number of daily log files kept in the app log directory
+const MAX_LOG_FILES: usize = 7;
+
+/// Initialize logging to the console (RUST_LOG, errors only by default) and to a
+/// daily-rotated file in the app log directory so users can send us diagnostics
+fn init_logging() {
+    use tracing_appender::rolling::{RollingFileAppender, Rotation};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+    let file_layer = nameback_core::app_log_dir().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("nameback-gui")
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(&dir)
+            .map_err(|e| eprintln!("Failed to create log file in {}: {}", dir.display(), e))
+            .ok()
+    }).map(|appender| {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        fmt::layer()
+            .with_writer(appender)
+            .with_ansi(false)
+            .with_filter(filter)
+    });
+
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(file_layer)
+        .init();
+}
+
 /// Ensure common tool paths are in PATH for dependency detection
 /// This fixes the issue where GUI launched from Finder doesn't inherit shell PATH
 /// On Windows, fixes issue where GUI launched from MSI doesn't inherit updated system PATH
@@ -81,19 +117,19 @@ fn setup_path() {
         } else {
             format!("{};{}", additional_paths.join(";"), current_path)
         };
-        log::debug!("Enhanced PATH for dependency detection: {}", new_path);
+        tracing::debug!("Enhanced PATH for dependency detection: {}", new_path);
         env::set_var("PATH", new_path);
     } else {
         // Unix uses colons
         additional_paths.push(current_path);
         let new_path = additional_paths.join(":");
-        log::debug!("Enhanced PATH for dependency detection: {}", new_path);
+        tracing::debug!("Enhanced PATH for dependency detection: {}", new_path);
         env::set_var("PATH", new_path);
     }
 }
 
 fn main() -> eframe::Result<()> {
-    env_logger::init(); // Initialize logging
+    init_logging();
     setup_path(); // Ensure dependencies can be found
 
     let native_options = eframe::NativeOptions {
@@ -0,0 +1,145 @@
+use eframe::egui;
+use nameback_core::{RenameConfig, RenameEngine};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Longest side of preview pictures, in pixels
+const PICTURE_SIZE: u32 = 512;
+
+/// Lines shown of a text file
+const TEXT_LINES: usize = 40;
+
+/// Previews kept once loaded; the least recently shown is dropped first
+const CACHE_SIZE: usize = 32;
+
+/// What a loader thread read from a file
+struct Loaded {
+    picture: Option<egui::ColorImage>,
+    text: Option<String>,
+    metadata: Vec<(&'static str, String)>,
+    problem: Option<String>,
+}
+
+/// A file's preview for the preview pane
+pub enum Preview {
+    Loading,
+    Ready {
+        /// Photo, or a PDF's first page
+        picture: Option<egui::TextureHandle>,
+        /// First lines of a text file
+        text: Option<String>,
+        /// Embedded metadata fields, labeled
+        metadata: Vec<(&'static str, String)>,
+        /// Why there is no picture or text, if reading the file failed
+        problem: Option<String>,
+    },
+}
+
+/// Loads previews on background threads, so decoding photos and rendering PDF pages never
+/// holds up the UI, and keeps the most recently shown ones
+pub struct PreviewLoader {
+    previews: HashMap<PathBuf, Preview>,
+    recent: VecDeque<PathBuf>, // Least recently shown first
+    sender: Sender<(PathBuf, Loaded)>,
+    receiver: Receiver<(PathBuf, Loaded)>,
+}
+
+impl PreviewLoader {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            previews: HashMap::new(),
+            recent: VecDeque::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// The preview of `path`, loading it in the background (with metadata read as `config`
+    /// says) if it isn't cached; the UI is repainted once it is ready
+    pub fn get(&mut self, ctx: &egui::Context, path: &Path, config: &RenameConfig) -> &Preview {
+        self.receive(ctx);
+
+        if !self.previews.contains_key(path) {
+            self.previews.insert(path.to_path_buf(), Preview::Loading);
+            let (path, config) = (path.to_path_buf(), config.clone());
+            let (sender, ctx) = (self.sender.clone(), ctx.clone());
+            std::thread::spawn(move || {
+                let loaded = load(&path, config);
+                // The loader is gone when the app is closing
+                if sender.send((path, loaded)).is_ok() {
+                    ctx.request_repaint();
+                }
+            });
+        }
+
+        self.recent.retain(|recent| recent != path);
+        self.recent.push_back(path.to_path_buf());
+        while self.recent.len() > CACHE_SIZE {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.previews.remove(&oldest);
+            }
+        }
+        &self.previews[path]
+    }
+
+    /// Forget every preview, e.g. once files were renamed or analyzed again
+    pub fn clear(&mut self) {
+        self.previews.clear();
+        self.recent.clear();
+    }
+
+    /// Turn finished loads into textures (only the UI thread can create them)
+    fn receive(&mut self, ctx: &egui::Context) {
+        while let Ok((path, loaded)) = self.receiver.try_recv() {
+            // Dropped from the cache (or cleared) while loading
+            let Some(preview) = self.previews.get_mut(&path) else {
+                continue;
+            };
+            let picture = loaded.picture.map(|picture| {
+                ctx.load_texture(format!("preview:{}", path.display()), picture, egui::TextureOptions::LINEAR)
+            });
+            *preview = Preview::Ready {
+                picture,
+                text: loaded.text,
+                metadata: loaded.metadata,
+                problem: loaded.problem,
+            };
+        }
+    }
+}
+
+/// Read a picture or text and the metadata of `path` (runs on a loader thread)
+fn load(path: &Path, config: RenameConfig) -> Loaded {
+    let mut problem = None;
+    let picture = nameback_core::preview_image(path, PICTURE_SIZE).unwrap_or_else(|e| {
+        problem = Some(format!("No preview: {:#}", anyhow::Error::from(e)));
+        None
+    });
+    let text = match picture {
+        Some(_) => None,
+        None => nameback_core::preview_text(path, TEXT_LINES).unwrap_or_else(|e| {
+            problem = Some(format!("No preview: {:#}", anyhow::Error::from(e)));
+            None
+        }),
+    };
+    let metadata = RenameEngine::new(config)
+        .read_metadata(path)
+        .map(|metadata| metadata.fields())
+        .unwrap_or_else(|e| {
+            tracing::debug!("No metadata for preview of {}: {:#}", path.display(), anyhow::Error::from(e));
+            Vec::new()
+        });
+
+    Loaded {
+        picture: picture.map(|picture| {
+            let rgba = picture.to_rgba8();
+            let size = [rgba.width() as usize, rgba.height() as usize];
+            egui::ColorImage::from_rgba_unmultiplied(size, rgba.as_flat_samples().as_slice())
+        }),
+        text,
+        metadata,
+        problem,
+    }
+}
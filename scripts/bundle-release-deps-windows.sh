@@ -0,0 +1,98 @@
+#!/bin/bash
+#
+# Build the deps-*-windows assets the bundled installer fallback downloads from a release,
+# byte for byte the same on every run, and write or check their checksums
+#
+#   scripts/bundle-release-deps-windows.sh --write-manifest   # before tagging: update checksums.txt
+#   scripts/bundle-release-deps-windows.sh --check            # in CI: refuse assets the manifest doesn't list
+#
+# The manifest (nameback-core/src/deps/bundled/checksums.txt) is compiled into the release,
+# so it has to list the release's own assets before the tag is pushed. Everything here is
+# pinned (tool versions, tessdata revision, zip timestamps and order) so the assets the
+# release workflow rebuilds match what was committed.
+#
+# Output: target/release-deps/deps-*.zip (and the FFmpeg source tarball)
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+ROOT_DIR="$(cd "$SCRIPT_DIR/.." && pwd)"
+MANIFEST="$ROOT_DIR/nameback-core/src/deps/bundled/checksums.txt"
+OUTPUT_DIR="$ROOT_DIR/target/release-deps"
+
+EXIFTOOL_VERSION="12.70"
+TESSERACT_VERSION="5.3.3.20231005"
+# tessdata release tag; a moved tag would change the checksum and fail --check
+TESSDATA_REVISION="4.1.0"
+TESSDATA_LANGUAGES=(eng chi_sim chi_tra)
+FFMPEG_VERSION="6.1"
+IMAGEMAGICK_VERSION="7.1.1-28"
+# Timestamp stored for every file in the built zip (2023-10-05, the Tesseract build)
+SOURCE_DATE_EPOCH=1696464000
+
+MODE="${1:-}"
+if [ "$MODE" != "--write-manifest" ] && [ "$MODE" != "--check" ]; then
+    echo "Usage: $0 --write-manifest | --check" >&2
+    exit 2
+fi
+
+# A manifest of comments only means --write-manifest was never run for this release
+if [ "$MODE" = "--check" ] && ! grep -qv '^\(#\|[[:space:]]*$\)' "$MANIFEST"; then
+    echo "ERROR: $MANIFEST lists no assets (run $0 --write-manifest and commit it before tagging)" >&2
+    exit 1
+fi
+
+rm -rf "$OUTPUT_DIR"
+mkdir -p "$OUTPUT_DIR"
+WORK_DIR="$(mktemp -d)"
+trap 'rm -rf "$WORK_DIR"' EXIT
+
+fetch() {
+    curl -L -f --retry 3 --connect-timeout 30 "$1" -o "$2"
+}
+
+echo "=== Building bundled Windows dependency assets ==="
+
+fetch "https://exiftool.org/exiftool-${EXIFTOOL_VERSION}.zip" "$OUTPUT_DIR/deps-exiftool-windows.zip"
+
+# Tesseract's installer and trained data, zipped with fixed timestamps, permissions and order
+mkdir -p "$WORK_DIR/tesseract/tessdata"
+fetch "https://digi.bib.uni-mannheim.de/tesseract/tesseract-ocr-w64-setup-${TESSERACT_VERSION}.exe" \
+    "$WORK_DIR/tesseract/tesseract-windows-setup.exe"
+for lang in "${TESSDATA_LANGUAGES[@]}"; do
+    fetch "https://raw.githubusercontent.com/tesseract-ocr/tessdata/${TESSDATA_REVISION}/${lang}.traineddata" \
+        "$WORK_DIR/tesseract/tessdata/${lang}.traineddata"
+done
+(
+    cd "$WORK_DIR/tesseract"
+    find . -type d -exec chmod 755 {} +
+    find . -type f -exec chmod 644 {} +
+    find . -exec touch -h -d "@${SOURCE_DATE_EPOCH}" {} +
+    find . -type f | LC_ALL=C sort | sed 's|^\./||' \
+        | TZ=UTC zip -q -X -D "$OUTPUT_DIR/deps-tesseract-windows.zip" -@
+)
+
+fetch "https://github.com/GyanD/codexffmpeg/releases/download/${FFMPEG_VERSION}/ffmpeg-${FFMPEG_VERSION}-essentials_build.zip" \
+    "$OUTPUT_DIR/deps-ffmpeg-windows-lgpl.zip"
+fetch "https://ffmpeg.org/releases/ffmpeg-${FFMPEG_VERSION}.tar.gz" "$OUTPUT_DIR/deps-ffmpeg-source.tar.gz"
+fetch "https://imagemagick.org/archive/binaries/ImageMagick-${IMAGEMAGICK_VERSION}-portable-Q16-x64.zip" \
+    "$OUTPUT_DIR/deps-imagemagick-windows.zip"
+
+cd "$OUTPUT_DIR"
+if [ "$MODE" = "--write-manifest" ]; then
+    {
+        grep '^#' "$MANIFEST"
+        sha256sum deps-* | LC_ALL=C sort -k2
+    } > "$WORK_DIR/checksums.txt"
+    mv "$WORK_DIR/checksums.txt" "$MANIFEST"
+    echo "Updated $MANIFEST; commit it before tagging the release"
+else
+    # Every asset must be listed, and match
+    for asset in deps-*; do
+        if ! grep -q "  \*\?${asset}\$" "$MANIFEST"; then
+            echo "ERROR: $asset is not in $MANIFEST (run $0 --write-manifest before tagging)" >&2
+            exit 1
+        fi
+    done
+    grep -v '^#' "$MANIFEST" | grep . | sha256sum -c -
+fi